@@ -10,16 +10,20 @@ export interface CompilationResult {
 }
 
 export interface CompiledProgram {
+  format_version: number;                         // version of the graph format written, see FORMAT_VERSION in the backend
   inputs: any[];
   outputs: any[];
-  defaults: Record<string, any>;
+  defaults: Record<string, any>;                  // graph input values by port number, used when a run leaves one out or gives None
+  input_names?: string[];                         // socket labels by port, "" for an unnamed one
+  output_names?: string[];
   instances: Record<string, CompiledInstance>;
   end_node: string;
 }
 
 export interface CompiledInstance {
+  name?: string;                                  // shown in backend errors and traces
   node_type: NodeType;
-  default_overrides: Record<string, any>;
+  default_overrides: Record<string, any>;         // input values by port number, used when the port is unconnected or gives None
   outputs: string[];                              // UUID strings, one per downstream data consumer
   control_flow_in: Array<Array<[string, number]>>;
   control_flow_out: Array<Array<[string, number]>>;
@@ -35,7 +39,7 @@ export type NodeType =
   | { Atomic: { Control: { Loop: string } } }
   | { Atomic: { Control: { Loop: { Continue: string } } } }
   | { Atomic: { Variable: [string, string] } }
-  | { Atomic: { Io: string | { Open: string } } }
+  | { Atomic: { Io: string | { Open: string | { File: string } } } }
   | { Atomic: { Cast: string } }
   | { Atomic: { LogicalOp: string } }
   | { Atomic: { AgentOp: string | { Create: string } } }
@@ -560,6 +564,7 @@ export class CompilationService {
       const endNode = this.findEndNode(canvasData.nodes, canvasData.edges, nodeIdMap);
 
       const compiledProgram: CompiledProgram = {
+        format_version: 2,
         inputs,
         outputs,
         defaults: {},
@@ -625,7 +630,7 @@ export class CompilationService {
 
     // IO
     if (nodeId === 'tcp-socket') return { Atomic: { Io: { Open: 'TcpSocket' } } };
-    if (nodeId === 'file-open') return { Atomic: { Io: { Open: 'File' } } };
+    if (nodeId === 'file-open') return { Atomic: { Io: { Open: { File: 'Read' } } } };
     if (nodeId === 'get-line') return { Atomic: { Io: 'GetLine' } };
     if (nodeId === 'write') return { Atomic: { Io: 'Write' } };
     if (nodeId === 'read') return { Atomic: { Io: 'Read' } };
@@ -0,0 +1,17 @@
+use std::process::Command;
+
+fn main()
+{
+  // embedded in `--version`, "unknown" when not built from a git checkout
+  let hash = Command::new("git")
+    .args(["rev-parse", "--short", "HEAD"])
+    .output()
+    .ok()
+    .filter(|x| x.status.success())
+    .and_then(|x| String::from_utf8(x.stdout).ok())
+    .map(|x| x.trim().to_string())
+    .unwrap_or_else(|| "unknown".to_string());
+  println!("cargo:rustc-env=GIT_HASH={hash}");
+  println!("cargo:rerun-if-changed=../.git/HEAD");
+  println!("cargo:rerun-if-changed=../.git/refs/heads");
+}
@@ -0,0 +1,30 @@
+/* C interface to the AgentNodes engine, built as the agent_nodes_core shared library.
+ * See src/ffi.rs for the details of each function. */
+#ifndef AGENTNODES_H
+#define AGENTNODES_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct AgentNodesGraph AgentNodesGraph;
+
+/* Loads a graph file, returning NULL if it cannot be read, uses Complex graphs in a
+ * cycle or has nodes its config's sandbox denies */
+AgentNodesGraph *agentnodes_load(const char *path);
+
+/* Runs the graph with a JSON array of inputs (or NULL for none), returning 0 on
+ * success and -1 on failure */
+int agentnodes_run(AgentNodesGraph *graph, const char *inputs_json);
+
+/* The outputs of the last run as a JSON array, or {"error": ...} if it failed. Owned
+ * by the graph, valid until the next run or agentnodes_free */
+const char *agentnodes_get_output_json(const AgentNodesGraph *graph);
+
+void agentnodes_free(AgentNodesGraph *graph);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -1,23 +1,39 @@
-use crate::{ai::openai::OpenAiAgent, language::typing::DataValue};
-use openai::chat::ChatCompletionFunctionDefinition;
+#[cfg(feature = "ai")]
+use crate::ai::openai::OpenAiAgent;
+use crate::{
+  ai::{
+    mock::{MockAgent, MockMessage},
+    TokenUsage,
+  },
+  capabilities::Capability,
+  config::ProviderEnv,
+  language::typing::DataValue,
+};
+#[cfg(feature = "ai")]
+use openai::{chat::ChatCompletionFunctionDefinition, Credentials};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::pin::Pin;
+use std::{collections::HashMap, pin::Pin};
 
 #[derive(Serialize, Deserialize, Clone, Debug, JsonSchema, PartialEq, Eq, Hash)]
 pub enum AgentType
 {
   OpenAi,
   OpenRouter,
+  Mock,
 }
 
 #[derive(Debug, Clone)]
 pub enum ChatBody
 {
+  #[cfg(feature = "ai")]
   OpenAi(openai::chat::ChatCompletionMessage),
+  #[allow(dead_code)]
   OpenRouter(usize),
+  Mock(MockMessage),
 }
 
+#[derive(Debug, Clone)]
 pub struct FunctionCall
 {
   pub name: String,
@@ -31,54 +47,203 @@ pub struct FunctionDefinition
   pub arguments: Option<serde_json::Value>,
 }
 
+#[cfg(feature = "ai")]
+impl From<FunctionDefinition> for ChatCompletionFunctionDefinition
+{
+  fn from(x: FunctionDefinition) -> Self
+  {
+    Self {
+      name: x.name,
+      description: x.description,
+      parameters: x.arguments,
+    }
+  }
+}
+
 #[allow(dead_code)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, thiserror::Error)]
 pub enum AgentErr
 {
+  #[cfg(feature = "ai")]
+  #[error("openai error: {0}")]
   OpenAi(openai::OpenAiError),
-  IncorrectBodyType(AgentType, ChatBody),
+  #[error("message is not for a {0:?} agent")]
+  IncorrectBodyType(AgentType, Box<ChatBody>),
+  #[error("unknown role {0:?}")]
+  UnknownRole(String),
+}
+
+/// Provider independent form of a chat message, used to export a conversation and
+/// seed another agent with it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HistoryMessage
+{
+  pub role: String,
+  pub content: Option<String>,
+}
+
+impl HistoryMessage
+{
+  /// Wraps a history as `{"messages": [{"role": ..., "content": ...}, ...]}`.
+  pub fn to_value(history: Vec<Self>) -> DataValue
+  {
+    let messages = serde_json::to_value(history)
+      .and_then(serde_json::from_value)
+      .unwrap_or(DataValue::None);
+    DataValue::Object(indexmap::IndexMap::from([(
+      "messages".to_string(),
+      messages,
+    )]))
+  }
+
+  pub fn from_value(value: &DataValue) -> Option<Vec<Self>>
+  {
+    match value
+    {
+      DataValue::Object(map) =>
+      {
+        serde_json::to_value(map.get("messages")?)
+          .and_then(serde_json::from_value)
+          .ok()
+      }
+      _ => None,
+    }
+  }
+}
+
+#[derive(Clone, Default)]
+pub struct SamplingArgs
+{
+  pub(crate) tempurature: Option<f64>,
+  pub(crate) top_p: Option<f64>,
+  pub(crate) max_tokens: Option<u64>,
+  pub(crate) stop: Vec<String>,
 }
 
 pub struct AgentArgs
 {
   pub(crate) model: String,
+  #[cfg_attr(not(feature = "ai"), allow(dead_code))]
   pub(crate) functions: Option<Vec<FunctionDefinition>>,
-  pub(crate) tempurature: Option<f64>,
+  pub(crate) system_prompt: Option<String>,
+  pub(crate) sampling: SamplingArgs,
 }
 
 impl AgentArgs
 {
-  pub fn from_values(vals: &Vec<DataValue>) -> Option<Self>
+  /// Reads the agent settings from a Create node's inputs, in port order: model,
+  /// functions, temperature, system prompt, top_p, max tokens and stop sequences.
+  /// Everything after the model may be None or left off entirely. Agents that cannot
+  /// use a setting, like Mock ones, ignore it.
+  pub fn from_values(vals: &[DataValue]) -> Option<Self>
   {
-    match (
-      vals.get(0).cloned(),
-      vals.get(1).cloned(),
-      vals.get(2).cloned(),
-    )
+    let arg = |i: usize| vals.get(i).cloned().unwrap_or(DataValue::None);
+    let DataValue::String(model) = arg(0)
+    else
     {
-      (Some(DataValue::String(model)), Some(v_functions), Some(v_temp)) =>
+      return None;
+    };
+    let mut ret = Self {
+      model,
+      functions: None,
+      system_prompt: None,
+      sampling: SamplingArgs::default(),
+    };
+    match arg(1)
+    {
+      DataValue::Array(functions) =>
       {
-        let mut ret = Self {
-          model,
-          functions: None,
-          tempurature: None,
-        };
-        match v_functions
-        {
-          DataValue::Array(_functions) => todo!(),
-          DataValue::None => (),
-          _ => return None,
-        };
-
-        match v_temp
+        ret.functions = Some(
+          functions
+            .iter()
+            .map(Self::function)
+            .collect::<Option<_>>()?,
+        )
+      }
+      DataValue::None => (),
+      _ => return None,
+    };
+    ret.sampling.tempurature = Self::optional_float(arg(2))?;
+    match arg(3)
+    {
+      DataValue::String(prompt) => ret.system_prompt = Some(prompt),
+      DataValue::None => (),
+      _ => return None,
+    };
+    ret.sampling.top_p = Self::optional_float(arg(4))?;
+    match arg(5)
+    {
+      DataValue::Integer(max) if max > 0 => ret.sampling.max_tokens = Some(max as u64),
+      DataValue::None => (),
+      _ => return None,
+    };
+    match arg(6)
+    {
+      DataValue::String(stop) => ret.sampling.stop = vec![stop],
+      DataValue::Array(stops) =>
+      {
+        for stop in stops
         {
-          DataValue::Float(tempurature) => ret.tempurature = Some(tempurature),
-          DataValue::None => (),
-          _ => return None,
-        };
-        Some(ret)
+          match stop
+          {
+            DataValue::String(x) => ret.sampling.stop.push(x),
+            _ => return None,
+          }
+        }
       }
+      DataValue::None => (),
+      _ => return None,
+    };
+    Some(ret)
+  }
+
+  /// A function the agent may call, from an Object with its `name` and optionally a
+  /// `description` and the JSON Schema of its `parameters`
+  fn function(val: &DataValue) -> Option<FunctionDefinition>
+  {
+    let DataValue::Object(fields) = val
+    else
+    {
+      return None;
+    };
+    if fields
+      .keys()
+      .any(|x| !["name", "description", "parameters"].contains(&x.as_str()))
+    {
+      return None;
+    }
+    let Some(DataValue::String(name)) = fields.get("name")
+    else
+    {
+      return None;
+    };
+    let description = match fields.get("description")
+    {
+      Some(DataValue::String(x)) => Some(x.clone()),
+      Some(DataValue::None) | None => None,
+      _ => return None,
+    };
+    let arguments = match fields.get("parameters")
+    {
+      Some(x @ DataValue::Object(_)) => Some(serde_json::to_value(x).ok()?),
+      Some(DataValue::None) | None => None,
+      _ => return None,
+    };
+    Some(FunctionDefinition {
+      name: name.clone(),
+      description,
+      arguments,
+    })
+  }
 
+  /// Wrapped in an outer Option so `?` can reject values of the wrong type.
+  fn optional_float(val: DataValue) -> Option<Option<f64>>
+  {
+    match val
+    {
+      DataValue::Float(x) => Some(Some(x)),
+      DataValue::Integer(x) => Some(Some(x as f64)),
+      DataValue::None => Some(None),
       _ => None,
     }
   }
@@ -89,23 +254,32 @@ pub type DynAgent = Pin<Box<dyn Agent + Send + Sync>>;
 #[async_trait::async_trait]
 pub trait Agent
 {
-  async fn send_chat(&self, body: ChatBody) -> Result<(), AgentErr>;
+  /// Sends a message and adds it and the model's answer to the conversation, returning
+  /// the tokens the request used
+  async fn send_chat(&self, body: ChatBody) -> Result<TokenUsage, AgentErr>;
   async fn get_last_response(&self) -> Option<ChatBody>;
   async fn create_body(&self, content: String) -> ChatBody;
+  async fn get_history(&self) -> Vec<HistoryMessage>;
+  /// Replaces the whole conversation, including any system prompt.
+  async fn set_history(&self, history: Vec<HistoryMessage>) -> Result<(), AgentErr>;
+  /// Offers more functions to the model, on top of any it was created with.
+  async fn add_functions(&self, functions: Vec<FunctionDefinition>);
+  /// A message answering a function call the model made.
+  async fn create_function_result(&self, name: String, result: String) -> ChatBody;
 }
 
 #[macro_export]
 macro_rules! correct_body {
   ($agent_type:ident, $body:ident) => {
-    if let crate::ai::ChatBody::$agent_type(x) = $body
+    if let $crate::ai::ChatBody::$agent_type(x) = $body
     {
       Ok(x)
     }
     else
     {
       Err(AgentErr::IncorrectBodyType(
-        crate::ai::AgentType::$agent_type,
-        $body,
+        $crate::ai::AgentType::$agent_type,
+        Box::new($body),
       ))
     }
   };
@@ -113,34 +287,70 @@ macro_rules! correct_body {
 
 impl AgentType
 {
-  pub fn create(self, args: AgentArgs) -> DynAgent
+  /// What the build has to include for agents of this type to be created
+  pub fn capability(&self) -> Option<Capability>
   {
     match self
     {
+      AgentType::Mock => None,
+      _ => Some(Capability::Ai),
+    }
+  }
+
+  /// Whether agents of this type can be created yet, OpenRouter ones cannot
+  pub fn implemented(&self) -> bool
+  {
+    !matches!(self, AgentType::OpenRouter)
+  }
+
+  /// Name of the provider, as used to key its settings in the config
+  pub fn provider(&self) -> &'static str
+  {
+    match self
+    {
+      AgentType::OpenAi => "openai",
+      AgentType::OpenRouter => "openrouter",
+      AgentType::Mock => "mock",
+    }
+  }
+
+  /// Creates an agent, reading credentials from the environment variables configured
+  /// for its provider, if any.
+  #[cfg_attr(not(feature = "ai"), allow(unused_variables))]
+  pub fn create(self, args: AgentArgs, providers: &HashMap<String, ProviderEnv>) -> DynAgent
+  {
+    match self
+    {
+      #[cfg(feature = "ai")]
       AgentType::OpenAi =>
       {
+        let credentials = providers.get(self.provider()).map(|env| {
+          let var = |name: &Option<String>, default| {
+            std::env::var(name.as_deref().unwrap_or(default)).unwrap_or_default()
+          };
+          Credentials::new(
+            var(&env.api_key_env, "OPENAI_KEY"),
+            var(&env.base_url_env, "OPENAI_BASE_URL"),
+          )
+        });
         Box::pin(OpenAiAgent::new(
           args.model,
-          None,
+          credentials,
+          args.system_prompt,
           args
             .functions
-            .map(|funcs| {
-              funcs
-                .into_iter()
-                .map(|x| {
-                  ChatCompletionFunctionDefinition {
-                    name: x.name,
-                    description: x.description,
-                    parameters: x.arguments,
-                  }
-                })
-                .collect()
-            })
+            .map(|funcs| funcs.into_iter().map(Into::into).collect())
             .unwrap_or(vec![]),
-          args.tempurature,
+          args.sampling,
         ))
       }
+      #[cfg(not(feature = "ai"))]
+      AgentType::OpenAi =>
+      {
+        unreachable!("graphs creating OpenAi agents need the ai feature to load")
+      }
       AgentType::OpenRouter => todo!(),
+      AgentType::Mock => Box::pin(MockAgent::new(args.model)),
     }
   }
 }
@@ -151,14 +361,17 @@ impl ChatBody
   {
     match self
     {
+      #[cfg(feature = "ai")]
       ChatBody::OpenAi(message) => message.content.clone(),
       ChatBody::OpenRouter(_) => todo!(),
+      ChatBody::Mock(message) => Some(message.content.clone()),
     }
   }
   pub fn get_function_call(&self) -> Option<FunctionCall>
   {
     match self
     {
+      #[cfg(feature = "ai")]
       ChatBody::OpenAi(message) =>
       {
         message.function_call.clone().map(|x| {
@@ -169,6 +382,7 @@ impl ChatBody
         })
       }
       ChatBody::OpenRouter(_) => todo!(),
+      ChatBody::Mock(message) => message.function_call.clone(),
     }
   }
 }
@@ -0,0 +1,190 @@
+use crate::config::{AgentLimits, QueueOrdering};
+use std::{
+  collections::HashMap,
+  sync::{Arc, Mutex},
+};
+use tokio::sync::oneshot;
+
+/// Requests queued and in flight across every agent of a run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LimiterStats
+{
+  pub queued: usize,
+  pub in_flight: usize,
+}
+
+struct Waiter
+{
+  seq: u64,
+  priority: i64,
+  provider: &'static str,
+  start: oneshot::Sender<()>,
+}
+
+#[derive(Default)]
+struct State
+{
+  next_seq: u64,
+  queue: Vec<Waiter>,
+  in_flight: usize,
+  per_provider: HashMap<&'static str, usize>,
+}
+
+/// Holds back agent requests once too many are in flight, overall or to one provider,
+/// starting queued ones in arrival order or by priority as others finish.
+pub struct AgentLimiter
+{
+  limits: AgentLimits,
+  state: Mutex<State>,
+}
+
+/// A request slot, given back when dropped
+pub struct AgentPermit
+{
+  limiter: Arc<AgentLimiter>,
+  provider: &'static str,
+}
+
+impl Drop for AgentPermit
+{
+  fn drop(&mut self)
+  {
+    self.limiter.release(self.provider);
+  }
+}
+
+/// Takes the waiter out of the queue if the request is given up on before it starts,
+/// or gives back its slot if that had already been handed over.
+struct Pending
+{
+  limiter: Arc<AgentLimiter>,
+  seq: u64,
+  provider: &'static str,
+  started: bool,
+}
+
+impl Drop for Pending
+{
+  fn drop(&mut self)
+  {
+    if self.started
+    {
+      return;
+    }
+    let mut state = self.limiter.state.lock().unwrap();
+    let queued = state.queue.len();
+    state.queue.retain(|x| x.seq != self.seq);
+    if state.queue.len() == queued
+    {
+      drop(state);
+      self.limiter.release(self.provider);
+    }
+  }
+}
+
+impl Default for AgentLimiter
+{
+  fn default() -> Self
+  {
+    Self::new(AgentLimits::default())
+  }
+}
+
+impl AgentLimiter
+{
+  pub fn new(limits: AgentLimits) -> Self
+  {
+    Self {
+      limits,
+      state: Mutex::new(State::default()),
+    }
+  }
+
+  /// Waits for a slot to send a request to `provider`. Higher priorities go first when
+  /// the limits order by priority, otherwise requests start in the order they came.
+  pub async fn acquire(self: &Arc<Self>, provider: &'static str, priority: i64) -> AgentPermit
+  {
+    let (start, started) = oneshot::channel();
+    let seq = {
+      let mut state = self.state.lock().unwrap();
+      let seq = state.next_seq;
+      state.next_seq += 1;
+      state.queue.push(Waiter {
+        seq,
+        priority,
+        provider,
+        start,
+      });
+      self.dispatch(&mut state);
+      seq
+    };
+    let mut pending = Pending {
+      limiter: self.clone(),
+      seq,
+      provider,
+      started: false,
+    };
+    // the sender is only dropped after sending
+    let _ = started.await;
+    pending.started = true;
+    AgentPermit {
+      limiter: self.clone(),
+      provider,
+    }
+  }
+
+  pub fn stats(&self) -> LimiterStats
+  {
+    let state = self.state.lock().unwrap();
+    LimiterStats {
+      queued: state.queue.len(),
+      in_flight: state.in_flight,
+    }
+  }
+
+  fn release(&self, provider: &'static str)
+  {
+    let mut state = self.state.lock().unwrap();
+    state.in_flight -= 1;
+    *state.per_provider.entry(provider).or_default() -= 1;
+    self.dispatch(&mut state);
+  }
+
+  /// Starts queued requests, best first, for as long as the limits allow.
+  fn dispatch(&self, state: &mut State)
+  {
+    if self.limits.ordering == QueueOrdering::Priority
+    {
+      state
+        .queue
+        .sort_by_key(|x| (std::cmp::Reverse(x.priority), x.seq));
+    }
+    let mut i = 0;
+    while i < state.queue.len()
+    {
+      if self
+        .limits
+        .max_in_flight
+        .is_some_and(|max| state.in_flight >= max)
+      {
+        break;
+      }
+      let provider = state.queue[i].provider;
+      let busy = state.per_provider.get(provider).copied().unwrap_or(0);
+      if self
+        .limits
+        .per_provider
+        .get(provider)
+        .is_some_and(|max| busy >= *max)
+      {
+        i += 1;
+        continue;
+      }
+      let waiter = state.queue.remove(i);
+      state.in_flight += 1;
+      *state.per_provider.entry(provider).or_default() += 1;
+      // a waiter given up on takes its slot back when its Pending is dropped
+      let _ = waiter.start.send(());
+    }
+  }
+}
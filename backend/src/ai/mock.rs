@@ -0,0 +1,191 @@
+use crate::ai::{
+  estimate_tokens, Agent, AgentErr, ChatBody, FunctionCall, FunctionDefinition, HistoryMessage,
+  TokenUsage,
+};
+use crate::correct_body;
+use std::collections::VecDeque;
+use tokio::sync::Mutex;
+
+/// How [`MockAgent`] answers when it has no scripted replies left
+const DEFAULT_TEMPLATE: &str = "{model}: {message}";
+
+#[derive(Debug, Clone)]
+pub struct MockMessage
+{
+  pub role: String,
+  pub content: String,
+  pub function_call: Option<FunctionCall>,
+}
+
+/// Offline agent that answers every message with `"<model>: <message>"`, so
+/// graphs using agents can be run and tested without credentials or network.
+///
+/// A message of the form `/call <function> <json arguments>` naming one of its
+/// functions is answered with a call to that function instead, as a model would.
+pub struct MockAgent
+{
+  messages: Mutex<Vec<MockMessage>>,
+  functions: Mutex<Vec<String>>,
+  model: String,
+  /// Replies given before falling back to `template`
+  script: Mutex<VecDeque<String>>,
+  template: String,
+}
+
+impl MockAgent
+{
+  pub fn new(model: String) -> Self
+  {
+    Self::scripted(model, vec![], None)
+  }
+
+  /// An agent answering with `replies` in turn, then with `template`, each having
+  /// `{model}` and `{message}` filled in. The template defaults to
+  /// `"{model}: {message}"`.
+  pub fn scripted(model: String, replies: Vec<String>, template: Option<String>) -> Self
+  {
+    Self {
+      messages: Mutex::new(Vec::new()),
+      functions: Mutex::new(Vec::new()),
+      model,
+      script: Mutex::new(replies.into()),
+      template: template.unwrap_or_else(|| DEFAULT_TEMPLATE.to_string()),
+    }
+  }
+
+  async fn reply_to(&self, message: &str) -> String
+  {
+    let reply = self.script.lock().await.pop_front();
+    reply
+      .unwrap_or_else(|| self.template.clone())
+      .replace("{model}", &self.model)
+      .replace("{message}", message)
+  }
+
+  async fn requested_call(&self, content: &str) -> Option<FunctionCall>
+  {
+    let (name, args) = content.strip_prefix("/call ")?.split_once(' ')?;
+    self
+      .functions
+      .lock()
+      .await
+      .iter()
+      .any(|x| x == name)
+      .then(|| {
+        FunctionCall {
+          name: name.to_string(),
+          args: args.to_string(),
+        }
+      })
+  }
+}
+
+#[async_trait::async_trait]
+impl Agent for MockAgent
+{
+  /// Estimates the tokens used as a model would count them: the whole conversation as
+  /// the prompt and the reply as the completion
+  async fn send_chat(&self, body: ChatBody) -> Result<TokenUsage, AgentErr>
+  {
+    let message = correct_body!(Mock, body)?;
+    let reply = match self.requested_call(&message.content).await
+    {
+      Some(call) =>
+      {
+        MockMessage {
+          role: "assistant".to_string(),
+          content: String::new(),
+          function_call: Some(call),
+        }
+      }
+      None =>
+      {
+        MockMessage {
+          role: "assistant".to_string(),
+          content: self.reply_to(&message.content).await,
+          function_call: None,
+        }
+      }
+    };
+    let mut guard = self.messages.lock().await;
+    guard.push(message);
+    let prompt_tokens = guard
+      .iter()
+      .map(|x| estimate_tokens(&x.content))
+      .sum::<usize>();
+    let completion_tokens = estimate_tokens(&reply.content);
+    guard.push(reply);
+    Ok(TokenUsage {
+      prompt_tokens: prompt_tokens as u64,
+      completion_tokens: completion_tokens as u64,
+    })
+  }
+
+  async fn get_last_response(&self) -> Option<ChatBody>
+  {
+    self
+      .messages
+      .lock()
+      .await
+      .last()
+      .map(|x| ChatBody::Mock(x.clone()))
+  }
+
+  async fn create_body(&self, content: String) -> ChatBody
+  {
+    ChatBody::Mock(MockMessage {
+      role: "user".to_string(),
+      content,
+      function_call: None,
+    })
+  }
+
+  async fn get_history(&self) -> Vec<HistoryMessage>
+  {
+    self
+      .messages
+      .lock()
+      .await
+      .iter()
+      .map(|x| {
+        HistoryMessage {
+          role: x.role.clone(),
+          content: Some(x.content.clone()),
+        }
+      })
+      .collect()
+  }
+
+  async fn set_history(&self, history: Vec<HistoryMessage>) -> Result<(), AgentErr>
+  {
+    *self.messages.lock().await = history
+      .into_iter()
+      .map(|x| {
+        MockMessage {
+          role: x.role,
+          content: x.content.unwrap_or_default(),
+          function_call: None,
+        }
+      })
+      .collect();
+    Ok(())
+  }
+
+  async fn add_functions(&self, functions: Vec<FunctionDefinition>)
+  {
+    self
+      .functions
+      .lock()
+      .await
+      .extend(functions.into_iter().map(|x| x.name));
+  }
+
+  async fn create_function_result(&self, _name: String, result: String) -> ChatBody
+  {
+    ChatBody::Mock(MockMessage {
+      role: "function".to_string(),
+      content: result,
+      function_call: None,
+    })
+  }
+}
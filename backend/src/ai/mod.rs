@@ -1,4 +1,15 @@
 mod agent;
+mod limiter;
+mod mock;
+#[cfg(feature = "ai")]
 mod openai;
+mod tools;
+mod transcript;
+mod usage;
 
 pub use agent::*;
+pub use limiter::{AgentLimiter, AgentPermit, LimiterStats};
+pub use mock::MockAgent;
+pub use tools::Tool;
+pub use transcript::{estimate_tokens, render_transcript, TranscriptEntry, TranscriptFormat};
+pub use usage::{ModelPrice, TokenUsage};
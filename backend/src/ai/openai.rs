@@ -1,6 +1,12 @@
-use crate::ai::{Agent, AgentErr, ChatBody};
+use crate::ai::{
+  estimate_tokens, Agent, AgentErr, ChatBody, FunctionDefinition, HistoryMessage, SamplingArgs,
+  TokenUsage,
+};
 use crate::correct_body;
-use openai::chat::{ChatCompletion, ChatCompletionFunctionDefinition, ChatCompletionMessage};
+use openai::chat::{
+  ChatCompletion, ChatCompletionFunctionDefinition, ChatCompletionMessage,
+  ChatCompletionMessageRole,
+};
 use openai::Credentials;
 use tokio::sync::Mutex;
 
@@ -8,8 +14,8 @@ pub struct OpenAiAgent
 {
   credentials: Credentials,
   messages: Mutex<Vec<ChatCompletionMessage>>,
-  functions: Vec<ChatCompletionFunctionDefinition>,
-  o_tempurature: Option<f64>,
+  functions: Mutex<Vec<ChatCompletionFunctionDefinition>>,
+  sampling: SamplingArgs,
   model: String,
 }
 
@@ -18,15 +24,28 @@ impl OpenAiAgent
   pub fn new(
     model: String,
     creds: Option<Credentials>,
+    system_prompt: Option<String>,
     functions: Vec<ChatCompletionFunctionDefinition>,
-    o_tempurature: Option<f64>,
+    sampling: SamplingArgs,
   ) -> Self
   {
+    let messages = system_prompt
+      .map(|prompt| {
+        vec![ChatCompletionMessage {
+          role: ChatCompletionMessageRole::System,
+          content: Some(prompt),
+          name: None,
+          function_call: None,
+          tool_call_id: None,
+          tool_calls: None,
+        }]
+      })
+      .unwrap_or_default();
     Self {
       credentials: creds.unwrap_or(Credentials::from_env()),
-      messages: Mutex::new(Vec::new()),
-      functions,
-      o_tempurature,
+      messages: Mutex::new(messages),
+      functions: Mutex::new(functions),
+      sampling,
       model,
     }
   }
@@ -35,7 +54,8 @@ impl OpenAiAgent
 #[async_trait::async_trait]
 impl Agent for OpenAiAgent
 {
-  async fn send_chat(&self, body: ChatBody) -> Result<(), AgentErr>
+  /// The tokens used are those the API reports, estimated if it leaves them out
+  async fn send_chat(&self, body: ChatBody) -> Result<TokenUsage, AgentErr>
   {
     let message = correct_body!(OpenAi, body)?.clone();
     let mut guard = self.messages.lock().await;
@@ -44,27 +64,54 @@ impl Agent for OpenAiAgent
     let mut builder = ChatCompletion::builder(&self.model, guard.clone())
       .credentials(self.credentials.clone())
       .n(1);
-    if self.functions.len() > 0
+    let functions = self.functions.lock().await.clone();
+    if !functions.is_empty()
     {
-      builder = builder.functions(self.functions.clone())
+      builder = builder.functions(functions)
     }
-    if let Some(tempurature) = self.o_tempurature
+    if let Some(tempurature) = self.sampling.tempurature
     {
       builder = builder.temperature(tempurature as f32);
     }
+    if let Some(top_p) = self.sampling.top_p
+    {
+      builder = builder.top_p(top_p as f32);
+    }
+    if let Some(max_tokens) = self.sampling.max_tokens
+    {
+      builder = builder.max_tokens(max_tokens);
+    }
+    if !self.sampling.stop.is_empty()
+    {
+      builder = builder.stop(self.sampling.stop.clone());
+    }
 
-    let o_response = builder
-      .create()
-      .await
-      .map_err(|x| AgentErr::OpenAi(x))?
-      .choices
-      .first()
-      .cloned();
+    let completion = builder.create().await.map_err(AgentErr::OpenAi)?;
+    let o_response = completion.choices.first().cloned();
+    let usage = match completion.usage
+    {
+      Some(usage) =>
+      {
+        TokenUsage {
+          prompt_tokens: usage.prompt_tokens.into(),
+          completion_tokens: usage.completion_tokens.into(),
+        }
+      }
+      None =>
+      {
+        let tokens =
+          |x: &ChatCompletionMessage| x.content.as_deref().map(estimate_tokens).unwrap_or(0) as u64;
+        TokenUsage {
+          prompt_tokens: guard.iter().map(tokens).sum(),
+          completion_tokens: o_response.as_ref().map_or(0, |x| tokens(&x.message)),
+        }
+      }
+    };
     if let Some(response) = o_response
     {
       guard.push(response.message);
     }
-    Ok(())
+    Ok(usage)
   }
 
   async fn get_last_response(&self) -> Option<ChatBody>
@@ -79,7 +126,7 @@ impl Agent for OpenAiAgent
   async fn create_body(&self, content: String) -> ChatBody
   {
     ChatBody::OpenAi(ChatCompletionMessage {
-      role: openai::chat::ChatCompletionMessageRole::User,
+      role: ChatCompletionMessageRole::User,
       content: Some(content),
       name: None,
       function_call: None,
@@ -87,4 +134,64 @@ impl Agent for OpenAiAgent
       tool_calls: None,
     })
   }
+
+  async fn get_history(&self) -> Vec<HistoryMessage>
+  {
+    self
+      .messages
+      .lock()
+      .await
+      .iter()
+      .map(|x| {
+        HistoryMessage {
+          role: serde_json::to_value(x.role)
+            .ok()
+            .and_then(|r| r.as_str().map(str::to_string))
+            .unwrap_or_default(),
+          content: x.content.clone(),
+        }
+      })
+      .collect()
+  }
+
+  async fn set_history(&self, history: Vec<HistoryMessage>) -> Result<(), AgentErr>
+  {
+    let mut messages = Vec::with_capacity(history.len());
+    for x in history
+    {
+      let role = serde_json::from_value::<ChatCompletionMessageRole>(x.role.clone().into())
+        .map_err(|_| AgentErr::UnknownRole(x.role))?;
+      messages.push(ChatCompletionMessage {
+        role,
+        content: x.content,
+        name: None,
+        function_call: None,
+        tool_call_id: None,
+        tool_calls: None,
+      });
+    }
+    *self.messages.lock().await = messages;
+    Ok(())
+  }
+
+  async fn add_functions(&self, functions: Vec<FunctionDefinition>)
+  {
+    self
+      .functions
+      .lock()
+      .await
+      .extend(functions.into_iter().map(Into::into));
+  }
+
+  async fn create_function_result(&self, name: String, result: String) -> ChatBody
+  {
+    ChatBody::OpenAi(ChatCompletionMessage {
+      role: ChatCompletionMessageRole::Function,
+      content: Some(result),
+      name: Some(name),
+      function_call: None,
+      tool_call_id: None,
+      tool_calls: None,
+    })
+  }
 }
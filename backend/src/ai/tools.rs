@@ -0,0 +1,106 @@
+use crate::{ai::FunctionDefinition, capabilities::Capability, config::Permission};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::Path;
+
+/// Prebuilt tools that can be attached to an agent, so it can use them without the
+/// graph wiring up each call itself.
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, PartialEq)]
+pub enum Tool
+{
+  /// `read_file(path)`, outputting the file's contents
+  ReadFile,
+  /// `write_file(path, contents)`, creating or replacing the file
+  WriteFile,
+  /// `http_get(url)`, outputting the response body
+  HttpGet,
+  /// `run_<name>(inputs)` for the Complex graph at this path, outputting its outputs as
+  /// a JSON array
+  RunSubgraph(String),
+}
+
+impl Tool
+{
+  /// The function name the agent calls the tool by
+  pub fn name(&self) -> String
+  {
+    match self
+    {
+      Tool::ReadFile => "read_file".to_string(),
+      Tool::WriteFile => "write_file".to_string(),
+      Tool::HttpGet => "http_get".to_string(),
+      Tool::RunSubgraph(path) =>
+      {
+        let stem = Path::new(path)
+          .file_stem()
+          .map(|x| x.to_string_lossy())
+          .unwrap_or_default();
+        format!("run_{stem}")
+      }
+    }
+  }
+
+  pub fn definition(&self) -> FunctionDefinition
+  {
+    let string = json!({"type": "string"});
+    let (description, parameters) = match self
+    {
+      Tool::ReadFile => ("Reads a text file", json!({"path": string})),
+      Tool::WriteFile =>
+      {
+        (
+          "Writes a text file, replacing it if it exists",
+          json!({"path": string, "contents": string}),
+        )
+      }
+      Tool::HttpGet =>
+      {
+        (
+          "Fetches a URL and returns the response body",
+          json!({"url": string}),
+        )
+      }
+      Tool::RunSubgraph(_) =>
+      {
+        (
+          "Runs a graph with the given inputs and returns its outputs",
+          json!({"inputs": {"type": "array"}}),
+        )
+      }
+    };
+    let required: Vec<&String> = parameters.as_object().unwrap().keys().collect();
+    FunctionDefinition {
+      name: self.name(),
+      description: Some(description.to_string()),
+      arguments: Some(json!({
+        "type": "object",
+        "properties": parameters,
+        "required": required,
+      })),
+    }
+  }
+
+  /// What the sandbox has to allow for the tool to be attached
+  pub fn permission(&self) -> Option<Permission>
+  {
+    match self
+    {
+      Tool::ReadFile => None,
+      Tool::WriteFile => Some(Permission::FsWrites),
+      Tool::HttpGet => Some(Permission::Network),
+      // checked per node when the graph runs
+      Tool::RunSubgraph(_) => None,
+    }
+  }
+
+  /// What the build has to include for the tool to be attached
+  pub fn capability(&self) -> Option<Capability>
+  {
+    match self
+    {
+      Tool::HttpGet => Some(Capability::Http),
+      _ => None,
+    }
+  }
+}
@@ -0,0 +1,77 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, PartialEq)]
+pub enum TranscriptFormat
+{
+  Markdown,
+  Json,
+}
+
+/// One message of an agent's conversation as it is written to a transcript
+#[derive(Serialize, Debug, Clone)]
+pub struct TranscriptEntry
+{
+  pub role: String,
+  pub content: Option<String>,
+  /// When the graph recorded the message, in milliseconds since the Unix epoch. Unknown
+  /// for messages imported from another history.
+  pub at_ms: Option<i64>,
+  /// Estimated with [`estimate_tokens`], as providers do not report counts per message
+  pub tokens: usize,
+}
+
+impl TranscriptEntry
+{
+  pub fn new(role: String, content: Option<String>, at_ms: Option<i64>) -> Self
+  {
+    let tokens = content.as_deref().map(estimate_tokens).unwrap_or(0);
+    Self {
+      role,
+      content,
+      at_ms,
+      tokens,
+    }
+  }
+}
+
+/// Roughly how many tokens `text` is, at one per four characters
+pub fn estimate_tokens(text: &str) -> usize
+{
+  text.chars().count().div_ceil(4)
+}
+
+/// Renders a conversation for people to review: a heading per message with its role,
+/// time and token estimate, or the entries as a JSON document with their total.
+pub fn render_transcript(entries: &[TranscriptEntry], format: &TranscriptFormat) -> String
+{
+  let total: usize = entries.iter().map(|x| x.tokens).sum();
+  match format
+  {
+    TranscriptFormat::Json =>
+    {
+      serde_json::json!({"messages": entries, "total_tokens": total}).to_string()
+    }
+    TranscriptFormat::Markdown =>
+    {
+      let mut out = format!(
+        "# Transcript\n\n{} messages, ~{total} tokens\n",
+        entries.len()
+      );
+      for entry in entries
+      {
+        out += &format!("\n## {}", entry.role);
+        if let Some(at) = entry.at_ms
+        {
+          out += &format!(" at {at}");
+        }
+        out += &format!(" (~{} tokens)\n\n", entry.tokens);
+        if let Some(content) = &entry.content
+        {
+          out += &format!("{content}\n");
+        }
+      }
+      out
+    }
+  }
+}
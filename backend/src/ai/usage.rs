@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+use std::ops::AddAssign;
+
+/// Tokens one or more requests to a model used, as the provider reported them or
+/// estimated for agents that do not report any
+#[derive(Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenUsage
+{
+  /// Sent to the model, the whole conversation so far for each request
+  pub prompt_tokens: u64,
+  /// Answered by the model
+  pub completion_tokens: u64,
+}
+
+impl TokenUsage
+{
+  pub fn total(&self) -> u64
+  {
+    self.prompt_tokens + self.completion_tokens
+  }
+}
+
+impl AddAssign for TokenUsage
+{
+  fn add_assign(&mut self, other: Self)
+  {
+    self.prompt_tokens += other.prompt_tokens;
+    self.completion_tokens += other.completion_tokens;
+  }
+}
+
+/// What a model charges, in dollars per million tokens
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ModelPrice
+{
+  pub prompt_per_million: f64,
+  pub completion_per_million: f64,
+}
+
+impl ModelPrice
+{
+  /// What `usage` costs at this price
+  pub fn cost(&self, usage: TokenUsage) -> f64
+  {
+    (usage.prompt_tokens as f64 * self.prompt_per_million
+      + usage.completion_tokens as f64 * self.completion_per_million)
+      / 1_000_000.0
+  }
+}
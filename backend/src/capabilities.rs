@@ -0,0 +1,58 @@
+use serde::Serialize;
+
+/// A subsystem behind the cargo feature of the same name, so embedders can leave out
+/// what their graphs do not use. Graphs with nodes needing one that was not compiled
+/// in fail to load.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Capability
+{
+  /// Agents from hosted providers; `Mock` agents are always available
+  Ai,
+  /// HTTP requests and servers, and WebSocket connections
+  Http,
+  /// Spawning processes
+  Subprocess,
+}
+
+impl Capability
+{
+  pub const ALL: [Capability; 3] = [Capability::Ai, Capability::Http, Capability::Subprocess];
+
+  pub fn name(self) -> &'static str
+  {
+    match self
+    {
+      Capability::Ai => "ai",
+      Capability::Http => "http",
+      Capability::Subprocess => "subprocess",
+    }
+  }
+
+  pub fn compiled_in(self) -> bool
+  {
+    match self
+    {
+      Capability::Ai => cfg!(feature = "ai"),
+      Capability::Http => cfg!(feature = "http"),
+      Capability::Subprocess => cfg!(feature = "subprocess"),
+    }
+  }
+}
+
+impl std::fmt::Display for Capability
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+  {
+    f.write_str(self.name())
+  }
+}
+
+/// The capabilities this build includes
+pub fn compiled_in() -> Vec<Capability>
+{
+  Capability::ALL
+    .into_iter()
+    .filter(|x| x.compiled_in())
+    .collect()
+}
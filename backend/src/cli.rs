@@ -1,15 +1,512 @@
-use clap::Parser;
-use std::path::PathBuf;
+use agent_nodes_core::{
+  ai::AgentType, capabilities, eval::TraceFormat, logging::subscriber::LogFormat, Capability,
+  DataValue, FORMAT_VERSION,
+};
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand};
+use clap_complete::Shell;
+use std::{path::PathBuf, str::FromStr, time::Duration};
+use uuid::Uuid;
 
 #[derive(Parser)]
-#[command(name = "agent_nodes", about = "Runs compiled programs by the AgentNodes ui", long_about = None)]
+#[command(
+  name = "agent_nodes",
+  about = "Runs compiled programs by the AgentNodes ui",
+  long_about = None,
+  args_conflicts_with_subcommands = true,
+  subcommand_negates_reqs = true
+)]
 pub struct Cli
 {
-  #[arg(required_unless_present = "print_schemas")]
-  pub filename: Option<PathBuf>,
+  #[command(subcommand)]
+  pub command: Option<Command>,
+
+  /// Running a graph without a subcommand is the same as `run`
+  #[command(flatten)]
+  pub run: Option<RunArgs>,
+
+  /// Print the JSON schema of the graph format, with every type of the node language
+  /// under `$defs`, and exit. Given a type such as `DataValue` or `AtomicType`, print
+  /// the schema of that type alone.
+  #[arg(
+    long,
+    exclusive = true,
+    value_name = "TYPE",
+    num_args = 0..=1,
+    default_missing_value = "Complex"
+  )]
+  pub print_schemas: Option<String>,
+}
+
+#[derive(Subcommand)]
+pub enum Command
+{
+  /// Run a graph or project until its end node finishes, then serve any HTTP routes
+  /// it registered
+  Run(Box<RunArgs>),
+  /// Print a graph's title, description, author and documented inputs and outputs
+  Describe
+  {
+    filename: PathBuf,
+    /// Print the graph's record types as JSON Schemas instead, to type agent tool
+    /// arguments and other JSON by
+    #[arg(long)]
+    records_schema: bool,
+  },
+  /// Print every kind of atomic node with its category, ports and what it needs to
+  /// run
+  DescribeNodes
+  {
+    /// Print the node specs as a JSON array instead of a table
+    #[arg(long)]
+    json: bool,
+  },
+  /// Run a graph over a dataset of cases, scoring its outputs and reporting how many
+  /// passed
+  Eval(EvalArgs),
+  /// Run two graphs over the same dataset, comparing their outputs, latency and tokens
+  /// per case
+  Compare(CompareArgs),
+  /// Run the graph tests of a manifest, reporting which passed and how the outputs of
+  /// the others differ from those expected
+  Test(TestArgs),
+  /// Run a graph again each time its file or one of the Complex graphs it uses
+  /// changes, printing the outputs of every run, until interrupted
+  Watch(WatchArgs),
+  /// Load a graph and run it again and again from a prompt, with the inputs given
+  /// there, keeping its state and agent conversations between runs
+  Repl(ReplArgs),
+  /// Serve a JSON-RPC API over a WebSocket for the editor to load and run graphs
+  /// with, streaming the status of each node as it runs
+  Serve(ServeArgs),
+  /// Check a graph and the Complex graphs it uses and write them to a single bundle
+  /// file, which runs like the graph without the others
+  Compile(CompileArgs),
+  /// Print a completion script for a shell
+  Completions
+  {
+    shell: Shell
+  },
+}
+
+#[derive(Args)]
+pub struct RunArgs
+{
+  /// The graph to run, or a project: a directory with a `project.json` or an
+  /// `agentnodes.toml` with a `[project]` section, or either file
+  pub filename: PathBuf,
+
+  /// Print the graph's outputs once its end node has run
   #[arg(short, long)]
   pub print_output: bool,
 
+  /// How the outputs are written: `debug` as Rust prints them, `json` on one line,
+  /// `pretty` JSON, or `raw` with the text of each on a line of its own. Both JSON
+  /// forms are `{"outputs": [...]}`, or `{"error": ...}` if the graph failed.
+  #[arg(long, value_name = "FORMAT", default_value = "debug")]
+  pub output_format: OutputFormat,
+
+  /// Write the outputs to this file rather than stdout, which graphs print to as well
+  #[arg(long, value_name = "PATH")]
+  pub output_file: Option<PathBuf>,
+
+  /// Config file to use instead of the closest `agentnodes.toml` to the graph
+  #[arg(long, value_name = "PATH")]
+  pub config: Option<PathBuf>,
+
+  /// Refuse to run graphs using the network: agents other than `Mock`, HTTP and
+  /// sockets. Overrides the sandbox policy in the config.
+  #[arg(long)]
+  pub offline: bool,
+
+  /// Run a graph from somewhere you do not trust: no processes, and no files, hosts or
+  /// agents but those given with `--allow-path`, `--allow-host` and `--allow-agent`.
+  /// Overrides the sandbox policy in the config.
+  #[arg(long)]
+  pub untrusted: bool,
+
+  /// A directory the graph may use files under, replacing the config's sandbox paths.
+  /// Can be given more than once.
+  #[arg(long = "allow-path", value_name = "DIR")]
+  pub allow_paths: Vec<PathBuf>,
+
+  /// A host the graph may connect to or listen on, for any port unless one is given,
+  /// replacing the config's sandbox hosts. Can be given more than once.
+  #[arg(long = "allow-host", value_name = "HOST[:PORT]")]
+  pub allow_hosts: Vec<String>,
+
+  /// An agent type the graph may create, replacing the config's sandbox agents. Can be
+  /// given more than once.
+  #[arg(long = "allow-agent", value_name = "TYPE", value_parser = parse_agent_type)]
+  pub allow_agents: Vec<AgentType>,
+
+  /// Run agents and TCP connections against the stand-ins in the config's `dry_run`
+  /// section rather than providers and the network, refusing graphs using the network
+  /// otherwise
+  #[arg(long)]
+  pub dry_run: bool,
+
+  /// Stop the graph if it has not finished after this many seconds
+  #[arg(long, value_name = "SECS", value_parser = parse_secs)]
+  pub deadline: Option<Duration>,
+
+  /// Print the tasks still alive once the instance has shut down
+  #[arg(long)]
+  pub task_stats: bool,
+
+  /// Leave the run's scratch directory in place once it ends, printing where it is
+  #[arg(long)]
+  pub keep_workspace: bool,
+
+  /// What the engine logs to stderr, as a `RUST_LOG` style filter such as `debug`.
+  /// Nodes are logged as they finish at `debug`. Defaults to `info`.
+  #[arg(long, value_name = "FILTER")]
+  pub log_level: Option<String>,
+
+  /// Write log lines as `text` or as `json` objects
+  #[arg(long, value_name = "FORMAT")]
+  pub log_format: Option<LogFormat>,
+
+  /// Print a table of runs, errors, latencies and IO bytes per node once the graph has
+  /// shut down
+  #[arg(long)]
+  pub metrics: bool,
+
+  /// Serve the per node metrics in Prometheus' format on `http://ADDR/metrics` while
+  /// the graph runs
+  #[arg(long, value_name = "ADDR")]
+  pub metrics_addr: Option<String>,
+
+  /// Print the calls, tokens and cost of each agent once the graph has shut down,
+  /// priced by the config's `[pricing]`
   #[arg(long)]
-  pub print_schemas: bool,
+  pub usage: bool,
+
+  /// Write the agents' usage and cost as JSON to this file once the graph has shut
+  /// down
+  #[arg(long, value_name = "PATH")]
+  pub usage_file: Option<PathBuf>,
+
+  /// Record every node run, with its inputs, outputs and timings, and write them to
+  /// this file once the graph has shut down
+  #[arg(long, value_name = "PATH")]
+  pub trace: Option<PathBuf>,
+
+  /// Write the trace as `json` entries or in Chrome's trace event format (`chrome`)
+  #[arg(long, value_name = "FORMAT", default_value = "json")]
+  pub trace_format: TraceFormat,
+
+  /// Write the graph's variables, stored values, agent conversations and pending
+  /// inputs to this file every `--checkpoint-every` seconds and when interrupted
+  #[arg(long, value_name = "PATH")]
+  pub checkpoint: Option<PathBuf>,
+
+  /// How often to write the checkpoint
+  #[arg(long, value_name = "SECS", default_value = "60", value_parser = parse_secs)]
+  pub checkpoint_every: Duration,
+
+  /// Start the graph with the state of a checkpoint it wrote, running it again from
+  /// its start node
+  #[arg(long, value_name = "PATH")]
+  pub resume: Option<PathBuf>,
+
+  /// Pause before this node runs, reading debugger commands from stdin. Can be given
+  /// more than once.
+  #[arg(long = "break", value_name = "NODE")]
+  pub breakpoints: Vec<Uuid>,
+
+  /// Pause before the first node runs, reading debugger commands from stdin
+  #[arg(long)]
+  pub step: bool,
+
+  /// Seed the `Random` nodes so they give the same values each run
+  #[arg(long, value_name = "N")]
+  pub seed: Option<u64>,
+
+  /// Run on a single thread with each instance's nodes taking turns in an order fixed
+  /// by the graph, so races such as between a `Variable` and its readers go the same
+  /// way every run
+  #[arg(long)]
+  pub deterministic: bool,
+
+  /// Print the shutdown report: how the run ended, what was cleaned up and the nodes
+  /// left part way through. Give twice to list every node.
+  #[arg(short, long, action = clap::ArgAction::Count)]
+  pub verbose: u8,
+
+  /// A `key=value` parameter for the graph, readable through the `RuntimeInfo` node.
+  /// Can be given more than once.
+  #[arg(long = "param", value_name = "KEY=VALUE", value_parser = parse_param)]
+  pub params: Vec<(String, String)>,
+
+  /// Give the graph an input by its port number, or a project one by name, read as
+  /// JSON if it is valid JSON and as a String otherwise. Can be given more than once.
+  #[arg(long = "input", value_name = "KEY=VALUE", value_parser = parse_input)]
+  pub inputs: Vec<(String, DataValue)>,
+
+  /// JSON file with inputs, either an array of them by port or an object keyed like
+  /// `--input`, which wins over the file
+  #[arg(long, value_name = "PATH")]
+  pub input_file: Option<PathBuf>,
+
+  /// Read the inputs from stdin like `--input-file`, until it closes, so the graph can
+  /// be fed by another command
+  #[arg(long, conflicts_with_all = ["input_file", "step", "breakpoints"])]
+  pub stdin_input: bool,
+}
+
+#[derive(Args)]
+pub struct EvalArgs
+{
+  /// The graph to evaluate
+  pub filename: PathBuf,
+
+  /// JSON Lines file with a case per line: its `inputs`, and any of the `expected`
+  /// outputs, a regex under `matches` the outputs must match and `judge` criteria
+  #[arg(long, value_name = "PATH")]
+  pub dataset: PathBuf,
+
+  /// The agent type and model judging cases with `judge` criteria, e.g. `OpenAi:gpt-4o`
+  #[arg(long, value_name = "TYPE:MODEL", value_parser = parse_judge)]
+  pub judge: Option<(AgentType, String)>,
+
+  /// Also write the report as JSON to this file
+  #[arg(long, value_name = "PATH")]
+  pub report: Option<PathBuf>,
+
+  /// Config file to use instead of the closest `agentnodes.toml` to the graph
+  #[arg(long, value_name = "PATH")]
+  pub config: Option<PathBuf>,
+
+  /// Fail a case if the graph has not finished after this many seconds
+  #[arg(long, value_name = "SECS", value_parser = parse_secs)]
+  pub deadline: Option<Duration>,
+}
+
+#[derive(Args)]
+pub struct CompareArgs
+{
+  /// The graph compared against
+  pub a: PathBuf,
+  /// The graph compared
+  pub b: PathBuf,
+
+  /// JSON Lines file with the `inputs` of a case per line, as for `eval`
+  #[arg(long, value_name = "PATH")]
+  pub inputs: PathBuf,
+
+  /// Config file to use instead of the closest `agentnodes.toml` to graph `a`
+  #[arg(long, value_name = "PATH")]
+  pub config: Option<PathBuf>,
+
+  /// Give up on a run if the graph has not finished after this many seconds
+  #[arg(long, value_name = "SECS", value_parser = parse_secs)]
+  pub deadline: Option<Duration>,
+}
+
+#[derive(Args)]
+pub struct TestArgs
+{
+  /// JSON file with a `tests` array, each test giving its `name`, the `graph` to run
+  /// relative to the manifest, its `inputs`, the `expected` outputs and optionally
+  /// `mocks` for agents and TCP connections, as in the config's `dry_run` section
+  pub manifest: PathBuf,
+
+  /// Only run the tests whose name contains this
+  #[arg(long, value_name = "TEXT")]
+  pub filter: Option<String>,
+
+  /// Config file to use instead of the closest `agentnodes.toml` to the manifest
+  #[arg(long, value_name = "PATH")]
+  pub config: Option<PathBuf>,
+
+  /// Fail a test if its graph has not finished after this many seconds
+  #[arg(long, value_name = "SECS", value_parser = parse_secs)]
+  pub deadline: Option<Duration>,
+}
+
+#[derive(Args)]
+pub struct WatchArgs
+{
+  /// The graph to run
+  pub filename: PathBuf,
+
+  /// Give the graph an input by its port number, read as JSON if it is valid JSON and
+  /// as a String otherwise. Can be given more than once.
+  #[arg(long = "input", value_name = "PORT=VALUE", value_parser = parse_input)]
+  pub inputs: Vec<(String, DataValue)>,
+
+  /// How often to look for changes
+  #[arg(long, value_name = "SECS", default_value = "0.5", value_parser = parse_secs)]
+  pub interval: Duration,
+
+  /// Config file to use instead of the closest `agentnodes.toml` to the graph
+  #[arg(long, value_name = "PATH")]
+  pub config: Option<PathBuf>,
+
+  /// Stop a run if it has not finished after this many seconds
+  #[arg(long, value_name = "SECS", value_parser = parse_secs)]
+  pub deadline: Option<Duration>,
+}
+
+#[derive(Args)]
+pub struct CompileArgs
+{
+  /// The graph to compile
+  pub filename: PathBuf,
+
+  /// Where to write the bundle, the graph's path with a `.bundle` extension if not
+  /// given
+  #[arg(short, long, value_name = "PATH")]
+  pub output: Option<PathBuf>,
+
+  /// Config file to use instead of the closest `agentnodes.toml` to the graph, whose
+  /// search paths Complex graphs are found through
+  #[arg(long, value_name = "PATH")]
+  pub config: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ReplArgs
+{
+  /// The graph to load
+  pub filename: PathBuf,
+
+  /// Give the graph an input by its port number before the first run, read like the
+  /// values of `set`. Can be given more than once.
+  #[arg(long = "input", value_name = "PORT=VALUE", value_parser = parse_input)]
+  pub inputs: Vec<(String, DataValue)>,
+
+  /// Config file to use instead of the closest `agentnodes.toml` to the graph
+  #[arg(long, value_name = "PATH")]
+  pub config: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ServeArgs
+{
+  /// Address to listen on, local only by default as anyone connecting can run graphs
+  #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:7070")]
+  pub listen: String,
+
+  /// Config file for every graph, instead of the closest `agentnodes.toml` to each
+  #[arg(long, value_name = "PATH")]
+  pub config: Option<PathBuf>,
+}
+
+impl Cli
+{
+  /// The command with `--version` reporting the commit it was built from, the graph
+  /// format it reads and the optional subsystems compiled in.
+  pub fn command_with_version() -> clap::Command
+  {
+    let capabilities: Vec<&str> = capabilities::compiled_in()
+      .into_iter()
+      .map(Capability::name)
+      .collect();
+    Self::command().version(format!(
+      "{} ({}, graph format {FORMAT_VERSION}, with [{}])",
+      env!("CARGO_PKG_VERSION"),
+      env!("GIT_HASH"),
+      capabilities.join(", ")
+    ))
+  }
+
+  pub fn parse_args() -> Self
+  {
+    let matches = Self::command_with_version().get_matches();
+    Self::from_arg_matches(&matches).unwrap_or_else(|e| e.exit())
+  }
+
+  /// Writes the completion script for `shell` to stdout.
+  pub fn print_completions(shell: Shell)
+  {
+    clap_complete::generate(
+      shell,
+      &mut Self::command_with_version(),
+      env!("CARGO_BIN_NAME"),
+      &mut std::io::stdout(),
+    );
+  }
+}
+
+/// See [`RunArgs::output_format`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat
+{
+  Debug,
+  Json,
+  Pretty,
+  Raw,
+}
+
+impl FromStr for OutputFormat
+{
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err>
+  {
+    match s
+    {
+      "debug" => Ok(Self::Debug),
+      "json" => Ok(Self::Json),
+      "pretty" => Ok(Self::Pretty),
+      "raw" => Ok(Self::Raw),
+      _ => Err(format!("expected debug, json, pretty or raw, got `{s}`")),
+    }
+  }
+}
+
+fn parse_param(param: &str) -> Result<(String, String), String>
+{
+  param
+    .split_once('=')
+    .map(|(k, v)| (k.to_string(), v.to_string()))
+    .ok_or_else(|| format!("expected KEY=VALUE, got `{param}`"))
+}
+
+pub fn parse_input(input: &str) -> Result<(String, DataValue), String>
+{
+  let (name, value) = parse_param(input)?;
+  let value = serde_json::from_str(&value).unwrap_or(DataValue::String(value));
+  Ok((name, value))
+}
+
+/// A number of seconds, refusing those no timer can wait for
+fn parse_secs(given: &str) -> Result<Duration, String>
+{
+  let secs: f64 = given
+    .parse()
+    .map_err(|_| format!("expected a number of seconds, got `{given}`"))?;
+  match secs > 0.0
+  {
+    true => Duration::try_from_secs_f64(secs).map_err(|e| format!("{given} seconds: {e}")),
+    false => Err(format!("expected more than 0 seconds, got {given}")),
+  }
+}
+
+fn parse_agent_type(kind: &str) -> Result<AgentType, String>
+{
+  serde_json::from_value(kind.into()).map_err(|_| format!("unknown agent type `{kind}`"))
+}
+
+fn parse_judge(judge: &str) -> Result<(AgentType, String), String>
+{
+  let (kind, model) = judge
+    .split_once(':')
+    .ok_or_else(|| format!("expected TYPE:MODEL, got `{judge}`"))?;
+  let kind = parse_agent_type(kind)?;
+  if !kind.implemented()
+  {
+    return Err(format!("{kind:?} agents are not supported yet"));
+  }
+  match kind.capability().filter(|x| !x.compiled_in())
+  {
+    Some(capability) =>
+    {
+      Err(format!(
+        "{kind:?} agents need {capability} support, which this build does not include"
+      ))
+    }
+    None => Ok((kind, model.to_string())),
+  }
 }
@@ -0,0 +1,813 @@
+use crate::{
+  ai::{AgentType, ModelPrice},
+  capabilities::Capability,
+  eval::EvalError,
+  language::typing::DataValue,
+  logging::subscriber::LogFormat,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+  collections::HashMap,
+  path::{Component, Path, PathBuf},
+  time::Duration,
+};
+use url::Url;
+
+pub const CONFIG_FILE: &str = "agentnodes.toml";
+/// A project manifest on its own, for projects without an `agentnodes.toml`
+pub const PROJECT_FILE: &str = "project.json";
+
+/// Runtime settings read from an `agentnodes.toml`, so they can be committed next to
+/// the graphs using them. Every section is optional.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config
+{
+  /// Extra directories searched for `Complex` graphs not found next to the graph
+  /// using them, relative to the config file
+  pub search_paths: Vec<PathBuf>,
+  /// Environment variables to read each provider's credentials from, keyed by agent
+  /// type such as `openai`
+  pub providers: HashMap<String, ProviderEnv>,
+  pub sandbox: SandboxPolicy,
+  pub timeouts: Timeouts,
+  pub limits: ValueLimits,
+  pub run_limits: RunLimits,
+  pub log: LogConfig,
+  pub graphs: GraphsConfig,
+  pub agents: AgentLimits,
+  pub dry_run: DryRun,
+  pub secrets: SecretsConfig,
+  /// What models cost by name, such as `gpt-4o`, for the cost of a run's agents
+  pub pricing: HashMap<String, ModelPrice>,
+  /// Makes the directory of the config a project `run` can be given
+  pub project: Option<Project>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProviderEnv
+{
+  pub api_key_env: Option<String>,
+  pub base_url_env: Option<String>,
+}
+
+/// What graphs are allowed to do, everything being allowed by default. The lists narrow
+/// down what the permissions allow, and an empty one allows nothing of its kind.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct SandboxPolicy
+{
+  pub allow_processes: bool,
+  pub allow_network: bool,
+  pub allow_fs_writes: bool,
+  /// Directories nodes may read, write and list files under, relative to the config.
+  /// Any path if not set.
+  pub paths: Option<Vec<PathBuf>>,
+  /// Hosts nodes may connect to or listen on, as `host` for any of its ports or
+  /// `host:port`, with `*.example.com` standing for the subdomains of
+  /// `example.com`. Any host if not set.
+  pub hosts: Option<Vec<String>>,
+  /// The agent types `Create` nodes may make, any if not set
+  pub agents: Option<Vec<AgentType>>,
+}
+
+impl Default for SandboxPolicy
+{
+  fn default() -> Self
+  {
+    Self {
+      allow_processes: true,
+      allow_network: true,
+      allow_fs_writes: true,
+      paths: None,
+      hosts: None,
+      agents: None,
+    }
+  }
+}
+
+/// Something a node needs the sandbox to allow
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission
+{
+  Processes,
+  Network,
+  FsWrites,
+}
+
+impl std::fmt::Display for Permission
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+  {
+    f.write_str(match self
+    {
+      Permission::Processes => "processes",
+      Permission::Network => "the network",
+      Permission::FsWrites => "file writes",
+    })
+  }
+}
+
+impl SandboxPolicy
+{
+  pub fn allows(&self, permission: Permission) -> bool
+  {
+    match permission
+    {
+      Permission::Processes => self.allow_processes,
+      Permission::Network => self.allow_network,
+      Permission::FsWrites => self.allow_fs_writes,
+    }
+  }
+
+  /// Whether any permission is denied, so graphs are worth checking before they run
+  pub fn denies_any(&self) -> bool
+  {
+    !(self.allow_processes && self.allow_network && self.allow_fs_writes)
+  }
+
+  /// Fails unless `path` is under one of the allowed directories. Both are compared
+  /// with `..` and the symlinks along the part that exists resolved, so neither leads
+  /// a path out of its directory.
+  pub fn check_path(&self, path: &str) -> Result<(), EvalError>
+  {
+    let Some(dirs) = &self.paths
+    else
+    {
+      return Ok(());
+    };
+    let real = real_path(Path::new(path));
+    match dirs.iter().any(|dir| real.starts_with(real_path(dir)))
+    {
+      true => Ok(()),
+      false => Err(EvalError::OutsideSandbox(format!("path {path}"))),
+    }
+  }
+
+  /// Fails unless `addr`, a `host:port` to connect to or listen on, matches one of
+  /// the allowed hosts
+  pub fn check_addr(&self, addr: &str) -> Result<(), EvalError>
+  {
+    let Some(hosts) = &self.hosts
+    else
+    {
+      return Ok(());
+    };
+    let (host, port) = split_port(addr);
+    let host = host.to_ascii_lowercase();
+    let allowed = hosts.iter().any(|allowed| {
+      let (pattern, allowed_port) = split_port(allowed);
+      let pattern = pattern.to_ascii_lowercase();
+      let host_matches = match pattern.strip_prefix("*.")
+      {
+        Some(domain) => host.strip_suffix(domain).is_some_and(|x| x.ends_with('.')),
+        None => host == pattern,
+      };
+      host_matches && (allowed_port.is_none() || allowed_port == port)
+    });
+    match allowed
+    {
+      true => Ok(()),
+      false => Err(EvalError::OutsideSandbox(format!("host {addr}"))),
+    }
+  }
+
+  /// [`Self::check_addr`] for the host of an `http`, `https`, `ws` or `wss` URL, its
+  /// port being the scheme's if the URL does not give one. The URL is parsed as the
+  /// HTTP client parses it, so both agree on the host, and one without a host is
+  /// refused.
+  pub fn check_url(&self, url: &str) -> Result<(), EvalError>
+  {
+    if self.hosts.is_none()
+    {
+      return Ok(());
+    }
+    let refused = || EvalError::OutsideSandbox(format!("url {url}"));
+    let parsed = match url.contains("://")
+    {
+      true => Url::parse(url),
+      false => Url::parse(&format!("http://{url}")),
+    }
+    .map_err(|_| refused())?;
+    let (Some(host), Some(port)) = (parsed.host_str(), parsed.port_or_known_default())
+    else
+    {
+      return Err(refused());
+    };
+    self.check_addr(&format!("{host}:{port}"))
+  }
+
+  /// Fails unless agents of type `kind` may be created
+  pub fn check_agent(&self, kind: &AgentType) -> Result<(), EvalError>
+  {
+    match &self.agents
+    {
+      Some(agents) if !agents.contains(kind) =>
+      {
+        Err(EvalError::OutsideSandbox(format!("{kind:?} agents")))
+      }
+      _ => Ok(()),
+    }
+  }
+}
+
+/// `path` made absolute, with the symlinks and `..` of its longest existing ancestor
+/// resolved by the filesystem and the `..` of the rest, which cannot be symlinks, by
+/// dropping the component before
+fn real_path(path: &Path) -> PathBuf
+{
+  let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+  let Some((mut real, rest)) = path.ancestors().find_map(|dir| {
+    Some((
+      std::fs::canonicalize(dir).ok()?,
+      path.strip_prefix(dir).ok()?,
+    ))
+  })
+  else
+  {
+    return path;
+  };
+  for component in rest.components()
+  {
+    match component
+    {
+      Component::ParentDir =>
+      {
+        real.pop();
+      }
+      Component::CurDir => (),
+      x => real.push(x),
+    }
+  }
+  real
+}
+
+/// Splits `host:port` or `[v6 address]:port` into the host, without brackets, and the
+/// port if there is one. A bare IPv6 address has no port.
+fn split_port(addr: &str) -> (&str, Option<&str>)
+{
+  if let Some(rest) = addr.strip_prefix('[')
+  {
+    let (host, after) = rest.split_once(']').unwrap_or((rest, ""));
+    return (host, after.strip_prefix(':'));
+  }
+  match addr.split_once(':')
+  {
+    Some((host, port)) if !port.contains(':') => (host, Some(port)),
+    _ => (addr, None),
+  }
+}
+
+/// Timeouts applied when a node does not set its own, in seconds
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Timeouts
+{
+  /// For `Http` `Request` nodes, from sending until the whole response is read
+  pub http_request_secs: Option<f64>,
+  /// For opening TCP and WebSocket connections
+  pub connect_secs: Option<f64>,
+  /// How long running nodes get to finish after Ctrl c or SIGTERM before they are
+  /// stopped, 5 seconds if not set
+  pub shutdown_grace_secs: Option<f64>,
+}
+
+impl Timeouts
+{
+  pub fn http_request(&self) -> Option<Duration>
+  {
+    self.http_request_secs.map(Duration::from_secs_f64)
+  }
+
+  pub fn connect(&self) -> Option<Duration>
+  {
+    self.connect_secs.map(Duration::from_secs_f64)
+  }
+
+  pub fn shutdown_grace(&self) -> Duration
+  {
+    Duration::from_secs_f64(self.shutdown_grace_secs.unwrap_or(5.0))
+  }
+
+  fn check(&self) -> Result<(), String>
+  {
+    check_secs(
+      "[timeouts] http_request_secs",
+      self.http_request_secs,
+      false,
+    )?;
+    check_secs("[timeouts] connect_secs", self.connect_secs, false)?;
+    check_secs(
+      "[timeouts] shutdown_grace_secs",
+      self.shutdown_grace_secs,
+      true,
+    )
+  }
+}
+
+/// Checks `secs`, set as `key`, is a number of seconds a timer can wait for, 0 only if
+/// `zero` allows it.
+fn check_secs(key: &str, secs: Option<f64>, zero: bool) -> Result<(), String>
+{
+  let Some(secs) = secs
+  else
+  {
+    return Ok(());
+  };
+  match Duration::try_from_secs_f64(secs)
+  {
+    Ok(x) if x.is_zero() && !zero => Err(format!("{key} must be more than 0, got {secs}")),
+    Ok(_) => Ok(()),
+    Err(e) => Err(format!("{key} of {secs}: {e}")),
+  }
+}
+
+/// Largest values nodes may output, so a runaway agent or IO source fails its node
+/// instead of flooding the graph. Nothing is limited by default.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ValueLimits
+{
+  /// In bytes, for Strings and Bytes alike
+  pub max_string_len: Option<usize>,
+  pub max_array_len: Option<usize>,
+  /// How deeply Arrays and Objects may be nested in each other
+  pub max_object_depth: Option<usize>,
+}
+
+impl ValueLimits
+{
+  /// Why `value` goes over the limits, if it does.
+  pub fn exceeded_by(&self, value: &DataValue) -> Option<String>
+  {
+    self.exceeded_at(value, 0)
+  }
+
+  fn exceeded_at(&self, value: &DataValue, depth: usize) -> Option<String>
+  {
+    let over = |len: usize, limit: Option<usize>| limit.filter(|x| len > *x);
+    let children: Box<dyn Iterator<Item = &DataValue>> = match value
+    {
+      DataValue::String(x) =>
+      {
+        return over(x.len(), self.max_string_len)
+          .map(|limit| format!("String of {} bytes, limit is {limit}", x.len()));
+      }
+      DataValue::Bytes(x) =>
+      {
+        return over(x.len(), self.max_string_len)
+          .map(|limit| format!("Bytes of length {}, limit is {limit}", x.len()));
+      }
+      DataValue::Array(x) =>
+      {
+        if let Some(limit) = over(x.len(), self.max_array_len)
+        {
+          return Some(format!("Array of {} values, limit is {limit}", x.len()));
+        }
+        Box::new(x.iter())
+      }
+      DataValue::Object(x) => Box::new(x.values()),
+      _ => return None,
+    };
+    if let Some(limit) = over(depth + 1, self.max_object_depth)
+    {
+      return Some(format!("nested deeper than {limit}"));
+    }
+    children
+      .filter_map(|x| self.exceeded_at(x, depth + 1))
+      .next()
+  }
+}
+
+/// What one run may use up, counted across the Complex graphs it starts, before it is
+/// stopped with [`EvalError::LimitExceeded`]. Nothing is limited by default.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct RunLimits
+{
+  /// Node runs, each pass of a loop counting its nodes again
+  pub max_node_runs: Option<u64>,
+  /// Wall-clock time from the start of the run
+  pub max_secs: Option<f64>,
+  /// Bytes held by variables at once
+  pub max_stored_bytes: Option<u64>,
+  /// Bytes read and written by IO nodes
+  pub max_io_bytes: Option<u64>,
+  /// Requests sent to agents, including those answering their tool calls
+  pub max_agent_calls: Option<u64>,
+  /// Tokens agents are sent and answer with, as their providers count them
+  pub max_agent_tokens: Option<u64>,
+}
+
+impl RunLimits
+{
+  fn check(&self) -> Result<(), String>
+  {
+    check_secs("[run_limits] max_secs", self.max_secs, false)
+  }
+}
+
+/// Defaults for the CLI's output flags, which can only turn them on, and for how the
+/// engine logs, which `--log-level` and `--log-format` override
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig
+{
+  pub print_output: bool,
+  pub task_stats: bool,
+  /// `RUST_LOG` style filter, `info` if not set
+  pub level: Option<String>,
+  pub format: LogFormat,
+}
+
+/// How `Complex` graphs are loaded
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct GraphsConfig
+{
+  /// Load a graph again when its file has changed since it was cached, instead of
+  /// running the cached copy
+  pub reload_changed: bool,
+  /// How many Complex graphs may run inside one another, 64 if not set
+  pub max_depth: Option<usize>,
+  /// The directory the graphs of the project are in, relative to the config file and
+  /// that file's directory if not set. Without a config file it is the directory of
+  /// the graph run.
+  pub project_dir: Option<PathBuf>,
+  /// Refuse Complex graphs outside the project directory and the search paths
+  pub confine: bool,
+  /// How long a node's task waits to be triggered again before ending, 30 if not set.
+  /// Nodes only get a task once first triggered, so idle parts of big graphs hold none.
+  pub idle_node_secs: Option<f64>,
+}
+
+impl GraphsConfig
+{
+  pub fn max_depth(&self) -> usize
+  {
+    self.max_depth.unwrap_or(64)
+  }
+
+  pub fn idle_node(&self) -> std::time::Duration
+  {
+    let default = std::time::Duration::from_secs(30);
+    self.idle_node_secs.map_or(default, |x| {
+      std::time::Duration::try_from_secs_f64(x).unwrap_or(default)
+    })
+  }
+}
+
+/// Caps on agent requests in flight at once, across every agent of a run. Nothing is
+/// limited unless set
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct AgentLimits
+{
+  pub max_in_flight: Option<usize>,
+  /// Caps for single providers, keyed by agent type such as `openai`
+  pub per_provider: HashMap<String, usize>,
+  pub ordering: QueueOrdering,
+}
+
+/// Which waiting agent request goes next once there is room
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum QueueOrdering
+{
+  /// In the order they were sent
+  #[default]
+  Fifo,
+  /// Highest `Send` priority first, then in the order they were sent
+  Priority,
+}
+
+/// Stand-ins for agents and TCP connections, so graphs can be run without credentials
+/// or network, such as in CI. Only used once turned on, as `--dry-run` does, which also
+/// denies the network to the nodes without a stand-in.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct DryRun
+{
+  pub enabled: bool,
+  /// Replies every agent of a model gives in turn, instead of asking its provider.
+  /// `{model}` and `{message}` are replaced by the agent's model and the message it
+  /// answers.
+  pub responses: HashMap<String, Vec<String>>,
+  /// The reply once an agent has given all of its `responses`, `"{model}: {message}"`
+  /// if not set
+  pub default_response: Option<String>,
+  /// Files TCP connections read from instead of the network, keyed by the
+  /// `host:port` connected to and relative to the config file. Connections to anything
+  /// else read nothing, and what is written to them is dropped.
+  pub streams: HashMap<String, PathBuf>,
+}
+
+/// Where `Secret` nodes look secrets up, environment variables by default
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct SecretsConfig
+{
+  pub provider: SecretsProvider,
+  /// Put before a secret's name to get the environment variable holding it
+  pub env_prefix: String,
+  /// The `NAME=VALUE` file of the `file` provider, relative to the config file. Keep it
+  /// out of version control.
+  pub file: Option<PathBuf>,
+  /// The service the `keychain` provider's secrets are stored under
+  pub keychain_service: String,
+}
+
+impl Default for SecretsConfig
+{
+  fn default() -> Self
+  {
+    Self {
+      provider: SecretsProvider::default(),
+      env_prefix: String::new(),
+      file: None,
+      keychain_service: "agentnodes".to_string(),
+    }
+  }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretsProvider
+{
+  #[default]
+  Env,
+  File,
+  Keychain,
+}
+
+/// Something a project declares its graphs need. Whatever it does not declare, the
+/// sandbox denies while it runs.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Requirement
+{
+  Network,
+  /// Writing files
+  Fs,
+  Processes,
+  /// Agents from hosted providers, which need `network` as well
+  Agents,
+}
+
+impl Requirement
+{
+  /// What the sandbox has to allow for it
+  pub fn permission(self) -> Option<Permission>
+  {
+    match self
+    {
+      Requirement::Network => Some(Permission::Network),
+      Requirement::Fs => Some(Permission::FsWrites),
+      Requirement::Processes => Some(Permission::Processes),
+      Requirement::Agents => None,
+    }
+  }
+
+  /// What the build has to include for it
+  pub fn capability(self) -> Option<Capability>
+  {
+    match self
+    {
+      Requirement::Processes => Some(Capability::Subprocess),
+      Requirement::Agents => Some(Capability::Ai),
+      _ => None,
+    }
+  }
+}
+
+impl std::fmt::Display for Requirement
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+  {
+    f.write_str(match self
+    {
+      Requirement::Network => "network",
+      Requirement::Fs => "fs",
+      Requirement::Processes => "processes",
+      Requirement::Agents => "agents",
+    })
+  }
+}
+
+/// A graph split over several files, run with `run <project>`: the graph it starts
+/// from, where its Complex graphs are and the inputs it takes, as the `[project]`
+/// section of an `agentnodes.toml` or a `project.json` with the same fields. Paths are
+/// relative to the manifest, so the project runs the same from anywhere.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Project
+{
+  /// The graph to run
+  pub entry: PathBuf,
+  /// Directories searched for Complex graphs after the config's
+  #[serde(default)]
+  pub search_paths: Vec<PathBuf>,
+  /// The inputs of the entry graph by port
+  #[serde(default)]
+  pub inputs: Vec<ProjectInput>,
+  /// Checked against the build and the sandbox before running
+  #[serde(default)]
+  pub requires: Vec<Requirement>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectInput
+{
+  /// What to set it by with `--input NAME=VALUE`
+  pub name: String,
+  /// Used unless set on the command line, which it has to be if there is none
+  pub value: Option<DataValue>,
+}
+
+impl Project
+{
+  /// The manifest of the project at `path`, if it is one: a `project.json`, an
+  /// `agentnodes.toml` or a directory holding either, the `project.json` first.
+  pub fn find(path: &Path) -> Option<PathBuf>
+  {
+    if path.is_dir()
+    {
+      return [PROJECT_FILE, CONFIG_FILE]
+        .into_iter()
+        .map(|x| path.join(x))
+        .find(|x| x.is_file());
+    }
+    let name = path.file_name()?.to_str()?;
+    (name == PROJECT_FILE || name.ends_with(".toml")).then(|| path.to_path_buf())
+  }
+
+  /// Reads the project from a manifest [`Self::find`] gave, making its paths relative
+  /// to it. An `agentnodes.toml` is read as a config, which needs a `[project]`.
+  pub fn load(manifest: &Path) -> Result<Self, ConfigErr>
+  {
+    if manifest.file_name() != Some(PROJECT_FILE.as_ref())
+    {
+      return Config::load(manifest)?
+        .project
+        .ok_or_else(|| ConfigErr::NoProject(manifest.to_path_buf()));
+    }
+    let text =
+      std::fs::read_to_string(manifest).map_err(|e| ConfigErr::Io(manifest.to_path_buf(), e))?;
+    let mut project: Self =
+      serde_json::from_str(&text).map_err(|e| ConfigErr::Json(manifest.to_path_buf(), e))?;
+    project.resolve(manifest.parent().unwrap_or(Path::new("")));
+    Ok(project)
+  }
+
+  fn resolve(&mut self, dir: &Path)
+  {
+    self.entry = dir.join(&self.entry);
+    for path in &mut self.search_paths
+    {
+      *path = dir.join(&*path);
+    }
+  }
+
+  /// Adds the project's search paths to `config` and denies what the project does not
+  /// require. Fails if a requirement is not in the build or the sandbox denies it.
+  pub fn apply(&self, config: &mut Config) -> Result<(), String>
+  {
+    for requirement in &self.requires
+    {
+      if requirement.capability().is_some_and(|x| !x.compiled_in())
+      {
+        return Err(format!(
+          "the project requires {requirement}, which this build does not include"
+        ));
+      }
+      if requirement
+        .permission()
+        .is_some_and(|x| !config.sandbox.allows(x))
+      {
+        return Err(format!(
+          "the project requires {requirement}, which the sandbox does not allow"
+        ));
+      }
+    }
+    config
+      .search_paths
+      .extend(self.search_paths.iter().cloned());
+    let requires = |x| self.requires.contains(&x);
+    let sandbox = &mut config.sandbox;
+    sandbox.allow_network &= requires(Requirement::Network);
+    sandbox.allow_fs_writes &= requires(Requirement::Fs);
+    sandbox.allow_processes &= requires(Requirement::Processes);
+    Ok(())
+  }
+
+  /// The values of the project's inputs by port, those in `given` by name or port
+  /// taking the place of the manifest's, the last given winning
+  pub fn input_values(&self, given: &[(String, DataValue)]) -> Result<Vec<DataValue>, String>
+  {
+    let mut values: Vec<_> = self.inputs.iter().map(|x| x.value.clone()).collect();
+    for (key, value) in given
+    {
+      let port = self
+        .inputs
+        .iter()
+        .position(|x| x.name == *key)
+        .or_else(|| key.parse().ok().filter(|x| *x < values.len()))
+        .ok_or_else(|| format!("the project has no input named `{key}`"))?;
+      values[port] = Some(value.clone());
+    }
+    values
+      .into_iter()
+      .zip(&self.inputs)
+      .map(|(value, input)| {
+        value.ok_or_else(|| format!("input `{}` has no value, give it with --input", input.name))
+      })
+      .collect()
+  }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigErr
+{
+  #[error("could not read {}: {}", .0.display(), .1)]
+  Io(PathBuf, std::io::Error),
+  #[error("invalid config {}: {}", .0.display(), .1)]
+  Parse(PathBuf, toml::de::Error),
+  #[error("invalid config {}: {}", .0.display(), .1)]
+  Invalid(PathBuf, String),
+  #[error("invalid project {}: {}", .0.display(), .1)]
+  Json(PathBuf, serde_json::Error),
+  #[error("{} has no [project] section", .0.display())]
+  NoProject(PathBuf),
+}
+
+impl Config
+{
+  /// Reads a config file, making its search paths, project directory, dry run streams,
+  /// secrets file, sandbox paths and project relative to it.
+  pub fn load(path: &Path) -> Result<Self, ConfigErr>
+  {
+    let text = std::fs::read_to_string(path).map_err(|e| ConfigErr::Io(path.to_path_buf(), e))?;
+    let mut config: Self =
+      toml::from_str(&text).map_err(|e| ConfigErr::Parse(path.to_path_buf(), e))?;
+    config
+      .check()
+      .map_err(|e| ConfigErr::Invalid(path.to_path_buf(), e))?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    config.search_paths = config
+      .search_paths
+      .into_iter()
+      .map(|x| dir.join(x))
+      .collect();
+    config.graphs.project_dir = Some(match &config.graphs.project_dir
+    {
+      Some(x) => dir.join(x),
+      None => dir.to_path_buf(),
+    });
+    for path in config.dry_run.streams.values_mut()
+    {
+      *path = dir.join(&*path);
+    }
+    if let Some(path) = &mut config.secrets.file
+    {
+      *path = dir.join(&*path);
+    }
+    for path in config.sandbox.paths.iter_mut().flatten()
+    {
+      *path = dir.join(&*path);
+    }
+    if let Some(project) = &mut config.project
+    {
+      project.resolve(dir);
+    }
+    Ok(config)
+  }
+
+  /// Checks the values their types let through but nothing can use, such as negative
+  /// timeouts
+  fn check(&self) -> Result<(), String>
+  {
+    self.timeouts.check()?;
+    self.run_limits.check()
+  }
+
+  /// Finds the closest `agentnodes.toml` in `start` or one of its ancestors.
+  pub fn discover(start: &Path) -> Option<PathBuf>
+  {
+    let start = if start.as_os_str().is_empty()
+    {
+      Path::new(".")
+    }
+    else
+    {
+      start
+    };
+    let start = std::path::absolute(start).ok()?;
+    start
+      .ancestors()
+      .map(|dir| dir.join(CONFIG_FILE))
+      .find(|path| path.is_file())
+  }
+}
@@ -0,0 +1,72 @@
+use agent_nodes_core::{
+  eval::{DebugCommand, Debugger},
+  Evaluator, NodeStateLogger,
+};
+use std::{io::Write, sync::Arc};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use uuid::Uuid;
+
+const HELP: &str = "commands: c(ontinue), s(tep), i(nspect), b(reak) [NODE], d(elete) NODE";
+
+/// Reads debugger commands from stdin whenever a node is paused, writing to stderr so
+/// the graph's own output stays apart. Detaches the debugger once stdin closes.
+pub async fn run(
+  debugger: Arc<Debugger>,
+  instance: Arc<Evaluator<NodeStateLogger, NodeStateLogger>>,
+)
+{
+  let mut lines = BufReader::new(tokio::io::stdin()).lines();
+  loop
+  {
+    let pause = debugger.wait_for_pause().await;
+    eprintln!(
+      "Paused before node {} {} in {} with inputs {:?}",
+      pause.node, pause.node_type, pause.scope_id, pause.inputs
+    );
+    loop
+    {
+      eprint!("(debug) ");
+      let _ = std::io::stderr().flush();
+      let Ok(Some(line)) = lines.next_line().await
+      else
+      {
+        debugger.detach();
+        return;
+      };
+      match line.split_whitespace().collect::<Vec<_>>()[..]
+      {
+        [] => (),
+        ["c" | "continue"] =>
+        {
+          debugger.resume(DebugCommand::Continue);
+          break;
+        }
+        ["s" | "step"] =>
+        {
+          debugger.resume(DebugCommand::Step);
+          break;
+        }
+        ["i" | "inspect"] => eprint!("{}", instance.inspect().await),
+        ["b" | "break"] => eprintln!("breakpoints: {:?}", debugger.breakpoints()),
+        ["b" | "break", node] =>
+        {
+          match node.parse::<Uuid>()
+          {
+            Ok(node) => debugger.add_breakpoint(node),
+            Err(e) => eprintln!("{node}: {e}"),
+          }
+        }
+        ["d" | "delete", node] =>
+        {
+          match node.parse::<Uuid>()
+          {
+            Ok(node) if debugger.remove_breakpoint(&node) => (),
+            Ok(node) => eprintln!("no breakpoint on {node}"),
+            Err(e) => eprintln!("{node}: {e}"),
+          }
+        }
+        _ => eprintln!("{HELP}"),
+      }
+    }
+  }
+}
@@ -0,0 +1,185 @@
+use super::EvalError;
+use crate::{config::RunLimits, language::typing::DataValue};
+use serde::Serialize;
+use std::{
+  fmt,
+  sync::atomic::{AtomicU64, Ordering},
+  time::{Duration, Instant},
+};
+use tokio::sync::watch;
+
+/// Something a run uses up that [`RunLimits`] can cap
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Limit
+{
+  NodeRuns,
+  /// Wall-clock time, counted in milliseconds
+  Time,
+  StoredBytes,
+  IoBytes,
+  AgentCalls,
+  AgentTokens,
+}
+
+impl fmt::Display for Limit
+{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+  {
+    f.write_str(match self
+    {
+      Limit::NodeRuns => "node runs",
+      Limit::Time => "milliseconds",
+      Limit::StoredBytes => "bytes of variables",
+      Limit::IoBytes => "IO bytes",
+      Limit::AgentCalls => "agent calls",
+      Limit::AgentTokens => "agent tokens",
+    })
+  }
+}
+
+/// What a run has used up so far against its [`RunLimits`], kept on the root
+/// instance. The first limit to be reached is kept for [`super::Evaluator::get_outputs`]
+/// to stop the run with, even if the node that reached it handles the error.
+pub(super) struct Budget
+{
+  limits: RunLimits,
+  started: Instant,
+  node_runs: AtomicU64,
+  stored_bytes: AtomicU64,
+  io_bytes: AtomicU64,
+  agent_calls: AtomicU64,
+  agent_tokens: AtomicU64,
+  exceeded: watch::Sender<Option<Limit>>,
+}
+
+impl Budget
+{
+  pub fn new(limits: RunLimits) -> Self
+  {
+    Self {
+      limits,
+      started: Instant::now(),
+      node_runs: AtomicU64::new(0),
+      stored_bytes: AtomicU64::new(0),
+      io_bytes: AtomicU64::new(0),
+      agent_calls: AtomicU64::new(0),
+      agent_tokens: AtomicU64::new(0),
+      exceeded: watch::Sender::new(None),
+    }
+  }
+
+  fn used(&self, limit: Limit) -> &AtomicU64
+  {
+    match limit
+    {
+      Limit::NodeRuns => &self.node_runs,
+      Limit::StoredBytes => &self.stored_bytes,
+      Limit::IoBytes => &self.io_bytes,
+      Limit::AgentCalls => &self.agent_calls,
+      Limit::AgentTokens => &self.agent_tokens,
+      // never counted, only waited for
+      Limit::Time => unreachable!("time is not spent"),
+    }
+  }
+
+  fn max(&self, limit: Limit) -> Option<u64>
+  {
+    let limits = &self.limits;
+    match limit
+    {
+      Limit::NodeRuns => limits.max_node_runs,
+      Limit::Time => limits.max_secs.map(|x| (x * 1000.0) as u64),
+      Limit::StoredBytes => limits.max_stored_bytes,
+      Limit::IoBytes => limits.max_io_bytes,
+      Limit::AgentCalls => limits.max_agent_calls,
+      Limit::AgentTokens => limits.max_agent_tokens,
+    }
+  }
+
+  /// Counts `amount` more of `limit`, failing once the run has used more than it may
+  pub fn spend(&self, limit: Limit, amount: u64) -> Result<(), EvalError>
+  {
+    let used = self.used(limit).fetch_add(amount, Ordering::AcqRel) + amount;
+    match self.max(limit)
+    {
+      Some(max) if used > max => Err(self.exceed(limit, max)),
+      _ => Ok(()),
+    }
+  }
+
+  /// Gives back `amount` of `limit`, for what is held at once rather than used up
+  pub fn release(&self, limit: Limit, amount: u64)
+  {
+    // the closure never declines so this cannot fail
+    let _ = self
+      .used(limit)
+      .fetch_update(Ordering::AcqRel, Ordering::Acquire, |x| {
+        Some(x.saturating_sub(amount))
+      });
+  }
+
+  fn exceed(&self, limit: Limit, max: u64) -> EvalError
+  {
+    self.exceeded.send_if_modified(|x| {
+      let first = x.is_none();
+      if first
+      {
+        *x = Some(limit);
+      }
+      first
+    });
+    EvalError::LimitExceeded { limit, max }
+  }
+
+  /// The error for the first limit the run went over, if it has
+  pub fn exceeded(&self) -> Option<EvalError>
+  {
+    let limit = (*self.exceeded.borrow())?;
+    let max = self.max(limit).unwrap_or_default();
+    Some(EvalError::LimitExceeded { limit, max })
+  }
+
+  /// Waits until a limit is reached, the run's time limit included, returning the
+  /// error to stop it with
+  pub async fn wait(&self) -> EvalError
+  {
+    let mut exceeded = self.exceeded.subscribe();
+    let reached = async {
+      // the sender lives as long as self, so this cannot fail
+      let _ = exceeded.wait_for(Option::is_some).await;
+    };
+    let time = async {
+      // a time limit too far off for an Instant to hold is never reached
+      let deadline = self
+        .max(Limit::Time)
+        .and_then(|max| self.started.checked_add(Duration::from_millis(max)));
+      match deadline
+      {
+        Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+        None => std::future::pending().await,
+      }
+    };
+    tokio::select! {
+      _ = reached => self.exceeded().expect("waited for the limit"),
+      _ = time => self.exceed(Limit::Time, self.max(Limit::Time).unwrap_or_default()),
+    }
+  }
+}
+
+/// Roughly how many bytes `value` takes up, for [`Limit::StoredBytes`]
+pub(super) fn stored_size(value: &DataValue) -> u64
+{
+  match value
+  {
+    DataValue::None => 0,
+    DataValue::String(x) => x.len() as u64,
+    DataValue::Bytes(x) => x.len() as u64,
+    DataValue::Array(x) => x.iter().map(stored_size).sum(),
+    DataValue::Object(x) => x.iter().map(|(k, v)| k.len() as u64 + stored_size(v)).sum(),
+    DataValue::Optional(Some(x)) => stored_size(x),
+    DataValue::Result(Ok(x) | Err(x)) => stored_size(x),
+    // numbers, handles and the like, about a word each
+    _ => 8,
+  }
+}
@@ -0,0 +1,120 @@
+use super::EvalError;
+use crate::{
+  ai::Tool,
+  language::nodes::{AgentOperation, AtomicType, Complex, ControlFlow, HttpOp, NodeType},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+  collections::BTreeMap,
+  io::{BufReader, BufWriter, Read, Write},
+  path::Path,
+};
+
+/// What a bundle file starts with, telling it apart from a graph's JSON
+const MAGIC: &[u8; 4] = b"AGNB";
+
+/// A graph together with every Complex graph it uses, written by `compile` as one
+/// file that runs without the others. [`super::Evaluator::new`] loads bundles as it
+/// does graphs.
+///
+/// Graphs are keyed by their path relative to the root graph's directory, and their
+/// Complex nodes refer to one another by those keys. The file is the magic bytes then
+/// the bundle as MessagePack, each graph stored as the JSON value it would be in its
+/// own file so it keeps its `format_version` and older bundles migrate like older
+/// graphs do.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Bundle
+{
+  /// The key of the graph the bundle runs
+  pub root: String,
+  pub graphs: BTreeMap<String, Complex>,
+}
+
+/// A bundle as stored, its graphs not yet migrated
+#[derive(Serialize, Deserialize)]
+struct Stored
+{
+  root: String,
+  graphs: BTreeMap<String, serde_json::Value>,
+}
+
+impl Bundle
+{
+  /// The bundle in the file at `path`, None if it holds something else such as a
+  /// graph
+  pub fn read(path: &str) -> Result<Option<Self>, EvalError>
+  {
+    let mut file = BufReader::new(std::fs::File::open(path)?);
+    let mut magic = vec![];
+    (&mut file)
+      .take(MAGIC.len() as u64)
+      .read_to_end(&mut magic)?;
+    if magic != MAGIC
+    {
+      return Ok(None);
+    }
+    let stored: Stored = rmp_serde::from_read(file)
+      .map_err(|e| EvalError::InvalidComplexNode(path.to_string(), serde::de::Error::custom(e)))?;
+    let graphs = stored
+      .graphs
+      .into_iter()
+      .map(|(key, graph)| Ok((key.clone(), Complex::from_json(&key, graph)?)))
+      .collect::<Result<_, EvalError>>()?;
+    Ok(Some(Self {
+      root: stored.root,
+      graphs,
+    }))
+  }
+
+  pub fn save(&self, path: &Path) -> Result<(), String>
+  {
+    // as JSON values, graphs keep the ids MessagePack would otherwise write as bytes
+    let stored = Stored {
+      root: self.root.clone(),
+      graphs: self
+        .graphs
+        .iter()
+        .map(|(key, graph)| Ok((key.clone(), serde_json::to_value(graph)?)))
+        .collect::<Result<_, serde_json::Error>>()
+        .map_err(|e| format!("{}: {e}", path.display()))?,
+    };
+    let write = || -> Result<(), Box<dyn std::error::Error>> {
+      let mut file = BufWriter::new(std::fs::File::create(path)?);
+      file.write_all(MAGIC)?;
+      rmp_serde::encode::write_named(&mut file, &stored)?;
+      file.into_inner()?.sync_all()?;
+      Ok(())
+    };
+    write().map_err(|e| format!("{}: {e}", path.display()))
+  }
+}
+
+/// The paths of the graphs a node runs: a Complex graph, one wrapped by `Try` or
+/// `Timeout`, a route's handler or the graphs of `RunSubgraph` tools
+pub(super) fn graph_paths(node_type: &mut NodeType) -> Vec<&mut String>
+{
+  match node_type
+  {
+    NodeType::Complex(path) | NodeType::Atomic(AtomicType::Http(HttpOp::Route(path))) =>
+    {
+      vec![path]
+    }
+    NodeType::Atomic(AtomicType::Control(
+      ControlFlow::Try(inner) | ControlFlow::Timeout(inner, _),
+    )) => graph_paths(inner),
+    NodeType::Atomic(AtomicType::AgentOp(AgentOperation::AttachTools(tools))) =>
+    {
+      tools
+        .iter_mut()
+        .filter_map(|tool| {
+          match tool
+          {
+            Tool::RunSubgraph(path) => Some(path),
+            _ => None,
+          }
+        })
+        .collect()
+    }
+    NodeType::Atomic(_) => vec![],
+  }
+}
@@ -0,0 +1,57 @@
+use crate::language::typing::DataValue;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path};
+use uuid::Uuid;
+
+/// The state of a running instance, written by [`super::Evaluator::checkpoint`] and
+/// picked up again by [`super::Evaluator::resume`], possibly in another process. The
+/// graph runs again from its start node with the state put back, so loops carry on
+/// counting and agents carry on their conversations.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Checkpoint
+{
+  /// The graph file the instance was running
+  pub graph_path: String,
+  /// The inputs the instance was running with, then those sent to it that it had
+  /// not read yet
+  pub inputs: Vec<Vec<DataValue>>,
+  pub state: InstanceState,
+}
+
+/// What an instance and its complex runners hold between node runs. Nodes are keyed
+/// by their id in the graph file, so the state applies to any instance of the graph.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct InstanceState
+{
+  pub variables: BTreeMap<String, DataValue>,
+  /// What stateful nodes such as `Counter` held. Handles are left out, as what they
+  /// refer to does not outlive the process.
+  pub stored: BTreeMap<Uuid, DataValue>,
+  /// The conversation of each agent by the `Create` node that made it, as
+  /// `ExportHistory` gives it
+  pub agents: BTreeMap<Uuid, DataValue>,
+  /// The runners of `Complex` nodes by node
+  pub runners: BTreeMap<Uuid, InstanceState>,
+}
+
+impl Checkpoint
+{
+  pub fn load(path: &Path) -> Result<Self, String>
+  {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    serde_json::from_str(&text).map_err(|e| format!("{}: {e}", path.display()))
+  }
+
+  /// Writes the checkpoint next to `path` first and then moves it over, so a crash
+  /// part way through leaves the previous checkpoint whole.
+  pub fn save(&self, path: &Path) -> Result<(), String>
+  {
+    let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    std::fs::write(&partial, json)
+      .and_then(|()| std::fs::rename(&partial, path))
+      .map_err(|e| format!("{}: {e}", path.display()))
+  }
+}
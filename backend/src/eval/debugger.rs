@@ -0,0 +1,263 @@
+use super::{node_label, type_label, NodeHooks, NodeInfo, NodeState};
+use crate::language::typing::DataValue;
+use serde::Serialize;
+use std::{
+  collections::{BTreeMap, HashSet},
+  fmt,
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+  },
+};
+use tokio::sync::{oneshot, watch};
+use uuid::Uuid;
+
+/// Where a [`Debugger`] stopped: a node about to run
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Pause
+{
+  pub scope_id: Uuid,
+  pub node: Uuid,
+  pub node_type: String,
+  pub inputs: Vec<DataValue>,
+}
+
+/// How a paused node carries on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugCommand
+{
+  /// Run until the next breakpoint
+  Continue,
+  /// Run the paused node, then pause at whichever node starts next
+  Step,
+}
+
+/// Pauses nodes before they run, at breakpoints or while stepping, until told to carry
+/// on. Registered with [`super::Evaluator::debug`]. Breakpoints are node ids from the
+/// graph files, so a breakpoint in a Complex graph pauses every instance of it.
+pub struct Debugger
+{
+  breakpoints: Mutex<HashSet<Uuid>>,
+  stepping: AtomicBool,
+  /// One node is paused at a time, others reaching a breakpoint wait behind it
+  gate: tokio::sync::Mutex<()>,
+  paused: watch::Sender<Option<Pause>>,
+  resume: Mutex<Option<oneshot::Sender<DebugCommand>>>,
+}
+
+/// Clears the pause once its node carries on, or is cancelled while paused
+struct Paused<'a>(&'a watch::Sender<Option<Pause>>);
+
+impl Drop for Paused<'_>
+{
+  fn drop(&mut self)
+  {
+    self.0.send_replace(None);
+  }
+}
+
+impl Debugger
+{
+  pub fn new(breakpoints: impl IntoIterator<Item = Uuid>) -> Self
+  {
+    Self {
+      breakpoints: Mutex::new(breakpoints.into_iter().collect()),
+      stepping: AtomicBool::new(false),
+      gate: tokio::sync::Mutex::new(()),
+      paused: watch::Sender::new(None),
+      resume: Mutex::new(None),
+    }
+  }
+
+  pub fn add_breakpoint(&self, node: Uuid)
+  {
+    self.breakpoints.lock().unwrap().insert(node);
+  }
+
+  /// Whether there was a breakpoint on `node`
+  pub fn remove_breakpoint(&self, node: &Uuid) -> bool
+  {
+    self.breakpoints.lock().unwrap().remove(node)
+  }
+
+  pub fn breakpoints(&self) -> Vec<Uuid>
+  {
+    let mut breakpoints: Vec<Uuid> = self.breakpoints.lock().unwrap().iter().copied().collect();
+    breakpoints.sort();
+    breakpoints
+  }
+
+  /// Pauses at the next node to start, wherever it is
+  pub fn pause_next(&self)
+  {
+    self.stepping.store(true, Ordering::Release);
+  }
+
+  pub fn paused(&self) -> Option<Pause>
+  {
+    self.paused.borrow().clone()
+  }
+
+  /// Waits until a node is paused, returning at once if one already is
+  pub async fn wait_for_pause(&self) -> Pause
+  {
+    let mut paused = self.paused.subscribe();
+    // the sender lives as long as self, so this cannot fail
+    let pause = paused.wait_for(Option::is_some).await.unwrap();
+    pause.clone().unwrap()
+  }
+
+  /// Lets the paused node carry on, returning whether there was one
+  pub fn resume(&self, command: DebugCommand) -> bool
+  {
+    let Some(resume) = self.resume.lock().unwrap().take()
+    else
+    {
+      return false;
+    };
+    // cleared now rather than once the node wakes, so the next wait sees the next pause
+    self.paused.send_replace(None);
+    resume.send(command).is_ok()
+  }
+
+  /// Removes every breakpoint, stops stepping and lets the paused node carry on, so
+  /// the run goes on undisturbed
+  pub fn detach(&self)
+  {
+    self.breakpoints.lock().unwrap().clear();
+    self.stepping.store(false, Ordering::Release);
+    self.resume(DebugCommand::Continue);
+  }
+
+  fn should_pause(&self, node: &Uuid) -> bool
+  {
+    self.stepping.load(Ordering::Acquire) || self.breakpoints.lock().unwrap().contains(node)
+  }
+}
+
+#[async_trait::async_trait]
+impl NodeHooks for Debugger
+{
+  async fn on_node_start(&self, node: NodeInfo<'_>, inputs: &[DataValue]) -> Result<(), String>
+  {
+    if !self.should_pause(&node.node)
+    {
+      return Ok(());
+    }
+    let _gate = self.gate.lock().await;
+    // the debugger may have been detached while this node waited its turn
+    if !self.should_pause(&node.node)
+    {
+      return Ok(());
+    }
+    let (resume, resumed) = oneshot::channel();
+    *self.resume.lock().unwrap() = Some(resume);
+    let _paused = Paused(&self.paused);
+    self.paused.send_replace(Some(Pause {
+      scope_id: node.scope_id,
+      node: node.node,
+      node_type: type_label(node.node_type),
+      inputs: inputs.to_vec(),
+    }));
+    let command = resumed.await.unwrap_or(DebugCommand::Continue);
+    self
+      .stepping
+      .store(command == DebugCommand::Step, Ordering::Release);
+    Ok(())
+  }
+}
+
+/// A node as seen by [`super::Evaluator::inspect`]
+#[derive(Serialize, Debug, Clone)]
+pub struct NodeSnapshot
+{
+  pub node: Uuid,
+  pub name: Option<String>,
+  pub node_type: String,
+  pub state: NodeState,
+  /// What stateful nodes such as `Variable` and `Counter` hold
+  pub stored: Option<DataValue>,
+  /// The outputs of its latest run
+  pub outputs: Vec<DataValue>,
+  /// Reads of its latest outputs that have not happened yet
+  pub unread_outputs: usize,
+  /// Control flow triggers received towards its next run
+  pub triggers: usize,
+  /// Control flow triggers it needs to run
+  pub triggers_needed: usize,
+}
+
+impl fmt::Display for NodeSnapshot
+{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+  {
+    write!(
+      f,
+      "node {} {}: {:?}, triggered {}/{}",
+      node_label(self.node, self.name.as_deref()),
+      self.node_type,
+      self.state,
+      self.triggers,
+      self.triggers_needed
+    )?;
+    if let Some(stored) = &self.stored
+    {
+      write!(f, ", stored {stored}")?;
+    }
+    if self.unread_outputs > 0
+    {
+      write!(
+        f,
+        ", {} output read(s) pending of {:?}",
+        self.unread_outputs, self.outputs
+      )?;
+    }
+    Ok(())
+  }
+}
+
+/// An instance's variables and nodes as they are, and those of its complex runners
+#[derive(Serialize, Debug, Clone)]
+pub struct InstanceSnapshot
+{
+  pub scope_id: Uuid,
+  pub graph_path: String,
+  pub variables: BTreeMap<String, DataValue>,
+  /// Every node of the graph, ordered by id
+  pub nodes: Vec<NodeSnapshot>,
+  pub runners: Vec<InstanceSnapshot>,
+}
+
+impl InstanceSnapshot
+{
+  fn write_indented(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result
+  {
+    let indent = "  ".repeat(depth);
+    writeln!(
+      f,
+      "{indent}Instance {} of {}",
+      self.scope_id, self.graph_path
+    )?;
+    for (name, value) in &self.variables
+    {
+      writeln!(f, "{indent}  variable {name} = {value}")?;
+    }
+    for node in &self.nodes
+    {
+      writeln!(f, "{indent}  {node}")?;
+    }
+    for runner in &self.runners
+    {
+      runner.write_indented(f, depth + 1)?;
+    }
+    Ok(())
+  }
+}
+
+impl fmt::Display for InstanceSnapshot
+{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+  {
+    self.write_indented(f, 0)
+  }
+}
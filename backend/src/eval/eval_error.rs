@@ -1,82 +1,373 @@
+use super::{node_label, Limit};
 use crate::{
   ai::AgentErr,
-  language::typing::{ArithmaticError, DataType},
+  capabilities::Capability,
+  config::Permission,
+  http::HttpErr,
+  language::{
+    bytes::Encoding,
+    typing::{ArithmaticError, DataType, DataValue},
+  },
 };
+use serde::{Serialize, Serializer};
 use std::string::FromUtf8Error;
+use thiserror::Error;
 use tokio::sync::oneshot::error::RecvError;
 use uuid::Uuid;
 
 #[allow(unused)]
-#[derive(Debug)]
+#[derive(Debug, Error)]
 pub enum EvalError
 {
-  MathError(ArithmaticError),
-  InvalidComplexNode(String, serde_json::Error),
-  IoError(std::io::Error),
+  #[error("math error: {0}")]
+  MathError(#[from] ArithmaticError),
+  #[error("invalid complex node {0}: {1}")]
+  InvalidComplexNode(String, #[source] serde_json::Error),
+  /// A graph saved in a newer version of the format than this build reads
+  #[error("graph {path} is in format version {version}, this build reads up to {supported}")]
+  UnsupportedFormatVersion
+  {
+    path: String,
+    version: u64,
+    supported: u32,
+  },
+  #[error("io error: {0}")]
+  IoError(#[from] std::io::Error),
+  /// Graphs whose Complex nodes lead back to the first of them, found before running
+  #[error("complex graphs use each other in a cycle: {}", .0.join(" -> "))]
+  ComplexCycle(Vec<String>),
+  /// Starting another Complex graph would go over `[graphs] max_depth`
+  #[error("complex graph {path} would run more than {limit} graphs deep")]
+  ComplexTooDeep
+  {
+    path: String, limit: usize
+  },
+  /// A Complex graph outside the project directory with `[graphs] confine` set
+  #[error("complex graph {path} is outside the project directory {project}")]
+  ComplexOutsideProject
+  {
+    path: String, project: String
+  },
+  #[error("complex node {0} not found")]
   ComplexNotFound(String),
-  ChannelRecvErr(RecvError),
+  #[error("channel closed: {0}")]
+  ChannelRecvErr(#[from] RecvError),
+  #[error("no io object for handle {0}")]
   IoNotFound(Uuid),
+  /// The handle exists but refers to a different kind of IO object than the node needs
+  #[error("handle {0} refers to a different kind of io object")]
+  IoKindMismatch(Uuid),
+  #[error("no agent {0}")]
   AgentNotFound(Uuid),
+  #[error("expected inputs of type {expected:?}, got {got:?}")]
   IncorrectTyping
   {
     got: Vec<DataType>,
     expected: Vec<DataType>,
   },
+  #[error("wrong number of inputs")]
   IncorrectInputCount,
-  RegexError(regex::Error),
+  /// A run gave a different number of values than the node has outputs
+  #[error("expected {expected} output(s), got {got}")]
+  IncorrectOutputCount
+  {
+    expected: usize, got: usize
+  },
+  /// An element of the Array given to `Unzip` with other than two elements
+  #[error("expected pairs, got an Array of {0} elements")]
+  NotAPair(usize),
+  /// Graph input or output names that would make ports ambiguous
+  #[error("invalid port names: {0}")]
+  InvalidPortName(String),
+  /// A default keyed by something other than the number of one of the inputs it is for
+  #[error("default `{port}` {} is not for one of its inputs", match node { Some(x) => format!("of node {x}"), None => "of the graph".to_string() })]
+  InvalidDefault
+  {
+    node: Option<Uuid>, port: String
+  },
+  /// A `Secret` node's secret is not in the configured store
+  #[error("no secret named {0}")]
+  SecretNotFound(String),
+  /// The configured secret store could not be read
+  #[error("secrets unavailable: {0}")]
+  SecretsUnavailable(String),
+  /// A `Record` type or node naming a record the graph neither declares nor loads
+  #[error("unknown record {0}")]
+  UnknownRecord(String),
+  /// An Object given as a record with a field the record does not have, when
+  /// `expected` is None, or with one that does not cast to the field's type
+  #[error("field `{field}` of record {record} {}", match expected { Some(x) => format!("should be {x}"), None => "is not one of its fields".to_string() })]
+  InvalidField
+  {
+    record: String,
+    field: String,
+    expected: Option<DataType>,
+  },
+  /// An `Unwrap` of a Result holding this error, or of an Optional holding nothing
+  #[error("unwrapped {}", match .0 { Some(e) => format!("an error: {e}"), None => "an empty Optional".to_string() })]
+  UnwrapFailed(Option<DataValue>),
+  /// A `Cast` node whose input is declared with a type that never casts to its own
+  #[error("node {node} casts {from} to {to}, which it cannot be")]
+  InvalidCast
+  {
+    node: Uuid,
+    from: DataType,
+    to: DataType,
+  },
+  /// A node reading an output its source does not have, found before running
+  #[error("node {reader} reads output {port} of node {node}, which has {ports}")]
+  MissingOutput
+  {
+    reader: Uuid,
+    node: Uuid,
+    port: usize,
+    ports: usize,
+  },
+  #[error("invalid regex: {0}")]
+  RegexError(#[from] regex::Error),
+  #[error("pattern {1:?} not found reading handle {0}")]
   PatternNotFound(Uuid, Vec<u8>),
-  InvalidUtf8(FromUtf8Error),
+  #[error("invalid utf-8: {0}")]
+  InvalidUtf8(#[from] FromUtf8Error),
+  #[error("port {0} out of bounds")]
   PortOutOfBounds(usize),
+  #[error("node {0} not found")]
   NodeNotFound(Uuid),
+  #[error("cannot cast {} to {}", .0.0, .0.1)]
   CastError((DataType, DataType)),
-  AgentErr(AgentErr),
+  #[error("agent error: {0}")]
+  AgentErr(#[from] AgentErr),
+  #[error("http error: {0}")]
+  HttpErr(#[from] HttpErr),
+  #[error("no node listening")]
   NoListeningNode,
+  #[error("graph has no end node")]
   NoEndNode,
+  #[error("graph has no start node")]
   NoStartNode,
+  #[error("closed")]
   Closed,
+  #[error("complex node input is not connected")]
   ComplexWeakInput,
-}
-impl From<ArithmaticError> for EvalError
-{
-  fn from(value: ArithmaticError) -> Self
+  #[error("nodes {0:?} read their own outputs")]
+  SelfListen(Vec<Uuid>),
+  /// Values of these types have no ordering between them
+  #[error("cannot compare {0} with {1}")]
+  Incomparable(DataType, DataType),
+  #[error("no field at {0}")]
+  FieldNotFound(String),
+  #[error("invalid JSON: {0}")]
+  InvalidJson(String),
+  #[error("invalid uuid {0:?}")]
+  InvalidUuid(String),
+  #[error("invalid {0:?}: {1}")]
+  InvalidEncoding(Encoding, String),
+  #[error("invalid date/time: {0}")]
+  InvalidDateTime(String),
+  /// A `Random` node was given a range with nothing in it
+  #[error("no values between {0} and {1}")]
+  EmptyRange(Box<DataValue>, Box<DataValue>),
+  /// The sandbox policy in `agentnodes.toml` does not allow what the node needs
+  #[error("sandbox does not allow {0:?}")]
+  NotPermitted(Permission),
+  /// The node used a path, host or agent type outside the lists of the sandbox policy
+  #[error("sandbox does not allow {0}")]
+  OutsideSandbox(String),
+  /// The node needs a subsystem behind a cargo feature this build was made without
+  #[error("node {node} needs {capability} support, which this build does not include")]
+  NotCompiledIn
   {
-    EvalError::MathError(value)
-  }
+    node: Uuid, capability: Capability
+  },
+  #[error("timed out")]
+  TimedOut,
+  /// A [`super::NodeHooks::on_node_start`] hook refused to let the node run
+  #[error("rejected by hook: {0}")]
+  Rejected(String),
+  /// An agent called a tool with arguments it does not take
+  #[error("invalid tool arguments: {0}")]
+  InvalidToolArguments(String),
+  /// A `DataType::Enum` names an enum the graph does not declare
+  #[error("no enum named {0}")]
+  UnknownEnum(String),
+  /// An `Expr` node's expression does not parse, or reads more ports than it has
+  #[error("invalid expression {0}")]
+  InvalidExpression(String),
+  #[error("{variant:?} is not a variant of enum {name}")]
+  InvalidVariant
+  {
+    name: String, variant: String
+  },
+  /// A node output a value over the limits in `agentnodes.toml`
+  #[error("node {node} output a value over the limits: {reason}")]
+  ValueTooLarge
+  {
+    node: Uuid, reason: String
+  },
+  /// A node of the graph being run failed
+  #[error("node {} failed: {message}", node_label(*node, name.as_deref()))]
+  NodeFailed
+  {
+    node: Uuid,
+    name: Option<String>,
+    message: String,
+  },
+  /// A node inside a complex node's graph failed
+  #[error("node {} in {path} failed: {message}", node_label(*node, name.as_deref()))]
+  SubgraphFailed
+  {
+    path: String,
+    node: Uuid,
+    name: Option<String>,
+    message: String,
+  },
+  /// The run used up more of something than its `[run_limits]` allow
+  #[error("run went over its limit of {max} {limit}")]
+  LimitExceeded
+  {
+    limit: Limit, max: u64
+  },
+  /// An `Abort` node stopped the graph
+  #[error("aborted with code {code}: {message}")]
+  Aborted
+  {
+    message: String, code: i32
+  },
 }
-impl From<std::io::Error> for EvalError
+
+impl From<tokio::time::error::Elapsed> for EvalError
 {
-  fn from(value: std::io::Error) -> Self
+  fn from(_: tokio::time::error::Elapsed) -> Self
   {
-    EvalError::IoError(value)
+    Self::TimedOut
   }
 }
-impl From<RecvError> for EvalError
+
+/// How errors are sent to the UI: the variant name, the message it displays as and
+/// whichever of the node, path and port it refers to.
+#[derive(Serialize)]
+struct ErrorReport<'a>
 {
-  fn from(value: RecvError) -> Self
-  {
-    Self::ChannelRecvErr(value)
-  }
+  kind: &'a str,
+  message: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  node: Option<Uuid>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  path: Option<&'a str>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  port: Option<usize>,
 }
 
-impl From<regex::Error> for EvalError
+impl EvalError
 {
-  fn from(value: regex::Error) -> Self
+  /// The error as an `Object` value with the same fields it is serialized with, for
+  /// graphs handling it themselves.
+  pub fn to_value(&self) -> DataValue
   {
-    Self::RegexError(value)
+    serde_json::to_value(self)
+      .and_then(serde_json::from_value)
+      .unwrap_or(DataValue::None)
   }
-}
-impl From<FromUtf8Error> for EvalError
-{
-  fn from(value: FromUtf8Error) -> Self
+
+  pub fn kind(&self) -> &'static str
   {
-    Self::InvalidUtf8(value)
+    match self
+    {
+      EvalError::MathError(_) => "MathError",
+      EvalError::InvalidComplexNode(..) => "InvalidComplexNode",
+      EvalError::UnsupportedFormatVersion { .. } => "UnsupportedFormatVersion",
+      EvalError::IoError(_) => "IoError",
+      EvalError::ComplexNotFound(_) => "ComplexNotFound",
+      EvalError::ComplexCycle(_) => "ComplexCycle",
+      EvalError::ComplexTooDeep { .. } => "ComplexTooDeep",
+      EvalError::ComplexOutsideProject { .. } => "ComplexOutsideProject",
+      EvalError::ChannelRecvErr(_) => "ChannelRecvErr",
+      EvalError::IoNotFound(_) => "IoNotFound",
+      EvalError::IoKindMismatch(_) => "IoKindMismatch",
+      EvalError::AgentNotFound(_) => "AgentNotFound",
+      EvalError::IncorrectTyping { .. } => "IncorrectTyping",
+      EvalError::IncorrectInputCount => "IncorrectInputCount",
+      EvalError::IncorrectOutputCount { .. } => "IncorrectOutputCount",
+      EvalError::MissingOutput { .. } => "MissingOutput",
+      EvalError::NotAPair(_) => "NotAPair",
+      EvalError::InvalidDefault { .. } => "InvalidDefault",
+      EvalError::InvalidPortName(_) => "InvalidPortName",
+      EvalError::InvalidCast { .. } => "InvalidCast",
+      EvalError::UnwrapFailed(_) => "UnwrapFailed",
+      EvalError::UnknownRecord(_) => "UnknownRecord",
+      EvalError::SecretNotFound(_) => "SecretNotFound",
+      EvalError::SecretsUnavailable(_) => "SecretsUnavailable",
+      EvalError::InvalidField { .. } => "InvalidField",
+      EvalError::RegexError(_) => "RegexError",
+      EvalError::PatternNotFound(..) => "PatternNotFound",
+      EvalError::InvalidUtf8(_) => "InvalidUtf8",
+      EvalError::PortOutOfBounds(_) => "PortOutOfBounds",
+      EvalError::NodeNotFound(_) => "NodeNotFound",
+      EvalError::CastError(_) => "CastError",
+      EvalError::AgentErr(_) => "AgentErr",
+      EvalError::HttpErr(_) => "HttpErr",
+      EvalError::NoListeningNode => "NoListeningNode",
+      EvalError::NoEndNode => "NoEndNode",
+      EvalError::NoStartNode => "NoStartNode",
+      EvalError::Closed => "Closed",
+      EvalError::ComplexWeakInput => "ComplexWeakInput",
+      EvalError::SelfListen(_) => "SelfListen",
+      EvalError::Incomparable(..) => "Incomparable",
+      EvalError::FieldNotFound(_) => "FieldNotFound",
+      EvalError::InvalidJson(_) => "InvalidJson",
+      EvalError::InvalidUuid(_) => "InvalidUuid",
+      EvalError::InvalidEncoding(..) => "InvalidEncoding",
+      EvalError::InvalidDateTime(_) => "InvalidDateTime",
+      EvalError::EmptyRange(..) => "EmptyRange",
+      EvalError::NotPermitted(_) => "NotPermitted",
+      EvalError::OutsideSandbox(_) => "OutsideSandbox",
+      EvalError::NotCompiledIn { .. } => "NotCompiledIn",
+      EvalError::TimedOut => "TimedOut",
+      EvalError::Rejected(_) => "Rejected",
+      EvalError::InvalidToolArguments(_) => "InvalidToolArguments",
+      EvalError::UnknownEnum(_) => "UnknownEnum",
+      EvalError::InvalidExpression(_) => "InvalidExpression",
+      EvalError::InvalidVariant { .. } => "InvalidVariant",
+      EvalError::ValueTooLarge { .. } => "ValueTooLarge",
+      EvalError::NodeFailed { .. } => "NodeFailed",
+      EvalError::SubgraphFailed { .. } => "SubgraphFailed",
+      EvalError::LimitExceeded { .. } => "LimitExceeded",
+      EvalError::Aborted { .. } => "Aborted",
+    }
   }
 }
 
-impl From<AgentErr> for EvalError
+impl Serialize for EvalError
 {
-  fn from(value: AgentErr) -> Self
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>
   {
-    Self::AgentErr(value)
+    let (node, path, port) = match self
+    {
+      EvalError::InvalidDefault { node, .. } => (*node, None, None),
+      EvalError::NodeNotFound(id)
+      | EvalError::ValueTooLarge { node: id, .. }
+      | EvalError::NotCompiledIn { node: id, .. }
+      | EvalError::NodeFailed { node: id, .. }
+      | EvalError::InvalidCast { node: id, .. } => (Some(*id), None, None),
+      EvalError::SelfListen(ids) => (ids.first().copied(), None, None),
+      EvalError::InvalidComplexNode(path, _)
+      | EvalError::UnsupportedFormatVersion { path, .. }
+      | EvalError::ComplexNotFound(path)
+      | EvalError::ComplexTooDeep { path, .. }
+      | EvalError::ComplexOutsideProject { path, .. } => (None, Some(path.as_str()), None),
+      EvalError::FieldNotFound(path) => (None, Some(path.as_str()), None),
+      EvalError::SubgraphFailed { path, node, .. } => (Some(*node), Some(path.as_str()), None),
+      EvalError::PortOutOfBounds(port) => (None, None, Some(*port)),
+      EvalError::MissingOutput { node, port, .. } => (Some(*node), None, Some(*port)),
+      _ => (None, None, None),
+    };
+    ErrorReport {
+      kind: self.kind(),
+      message: self.to_string(),
+      node,
+      path,
+      port,
+    }
+    .serialize(serializer)
   }
 }
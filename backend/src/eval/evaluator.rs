@@ -1,23 +1,90 @@
-use super::{AsyncClone, EvalError, ExecutionNode, IoObject};
+#[cfg(feature = "subprocess")]
+use super::ChildProcess;
+use super::{
+  budget::{stored_size, Budget},
+  bundle::graph_paths,
+  execution_node::listed_reads,
+  AsyncClone, Bundle, Checkpoint, Debugger, EvalError, ExecutionNode, InstanceSnapshot,
+  InstanceState, IoEntry, IoObject, Limit, Metrics, NodeHooks, NodeState, Outcome, ShutdownReport,
+  StopSummary, TaskRegistry, TaskStats, TraceRecorder, UsageLedger, UsageReport,
+};
 use crate::{
-  ai::{AgentArgs, AgentType, ChatBody, DynAgent},
+  ai::{
+    render_transcript, AgentArgs, AgentLimiter, AgentType, ChatBody, DynAgent, HistoryMessage,
+    LimiterStats, MockAgent, TokenUsage, Tool, TranscriptEntry, TranscriptFormat,
+  },
+  config::{Config, Permission, RunLimits},
+  http::{Frame, HttpRequest, HttpResponse, HttpServer, RouteHandler, WebSocket},
   language::{
-    nodes::{AtomicType, Complex, ControlFlow, NodeType},
-    typing::DataValue,
+    nodes::{fill_defaults, port_by_key, with_timeout, AtomicType, Complex, ControlFlow, NodeType},
+    optimize::Optimized,
+    records::Records,
+    typing::{DataType, DataValue},
   },
-  logging::Logger,
+  logging::{node_state_logger::NodeStateLogger, Logger},
 };
+use indexmap::IndexMap;
+use rand::{rngs::StdRng, SeedableRng};
 use std::{
-  collections::{HashMap, HashSet, VecDeque},
-  sync::{atomic::AtomicBool, Arc},
+  collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+  net::SocketAddr,
+  path::{Component, Path, PathBuf},
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+  },
+  time::{Duration, SystemTime, UNIX_EPOCH},
 };
 use tokio::{
   io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
-  sync::{Notify, RwLock, RwLockWriteGuard},
-  task::{AbortHandle, JoinHandle, JoinSet},
+  net::{TcpListener, UdpSocket},
+  sync::{watch, Notify, RwLock, RwLockWriteGuard},
+  task::JoinHandle,
+  time::Instant,
 };
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
+/// Environment variable holding the run's [`Evaluator::workspace`] in processes it
+/// spawns
+pub const WORKSPACE_ENV: &str = "AGENTNODES_WORKSPACE";
+
+/// How long [`Evaluator::shutdown`] waits for an instance's tasks to end once they
+/// have been cancelled
+const TEARDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How many function calls an agent may make answering one message before its last
+/// reply is left as is
+const MAX_TOOL_CALLS: usize = 16;
+
+/// An agent and the tools attached to it
+struct RegisteredAgent
+{
+  agent: DynAgent,
+  provider: &'static str,
+  /// The graph file and id of the `Create` node that made it, for the usage report
+  origin: (String, Uuid),
+  agent_type: AgentType,
+  model: String,
+  tools: RwLock<Vec<Tool>>,
+  /// When each message of its history was recorded, for transcripts
+  recorded_at: RwLock<Vec<Option<i64>>>,
+}
+
+impl RegisteredAgent
+{
+  /// Marks the messages added since the last call as recorded now.
+  async fn stamp(&self)
+  {
+    let count = self.agent.get_history().await.len();
+    let now = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .unwrap_or_default()
+      .as_millis() as i64;
+    self.recorded_at.write().await.resize(count, Some(now));
+  }
+}
+
 async fn read_until_generic<R: AsyncRead + Unpin>(
   reader: &mut R,
   pattern: &[u8],
@@ -51,42 +118,142 @@ async fn read_until_generic<R: AsyncRead + Unpin>(
   Ok(buffer)
 }
 
+fn stream_mut<'a>(
+  registry: &'a mut HashMap<Uuid, IoEntry>,
+  id: &Uuid,
+) -> Result<&'a mut IoObject, EvalError>
+{
+  match registry.get_mut(id)
+  {
+    Some(IoEntry::Stream(io)) => Ok(io),
+    Some(_) => Err(EvalError::IoKindMismatch(*id)),
+    None => Err(EvalError::IoNotFound(*id)),
+  }
+}
+
+/// Reports each node task of `eval` as it ends until the instance is shut down, waiting
+/// for more once none are left. Waits without polling, so idle graphs use no CPU.
 async fn task_listen<TextLogger: Logger, NodeLogger: Logger>(
   eval: Arc<Evaluator<TextLogger, NodeLogger>>,
-  tasks: Vec<JoinHandle<(Uuid, Result<Vec<DataValue>, EvalError>)>>,
 ) -> ()
 {
-  let mut js = JoinSet::new();
-  let mut abort_handles: Vec<AbortHandle> = tasks.into_iter().map(|x| js.spawn(x)).collect();
-
-  while !eval.closed.load(std::sync::atomic::Ordering::Acquire)
+  loop
   {
-    if let Some(ret) = js.try_join_next()
+    // nodes end too once cancelled, they are not reported as finishing
+    let ret = tokio::select! {
+      biased;
+      _ = eval.cancel.cancelled() => return,
+      ret = eval.tasks.join_next() => ret,
+    };
+    match ret
     {
-      match ret
+      Some(Ok((id, x))) =>
       {
-        Ok(Ok((id, x))) =>
+        // logged by the ids and names of the graph file rather than the scoped ids
+        let node = eval.nodes.get(&id);
+        let static_id = node.map_or(id, |x| x.static_id);
+        let name = node.and_then(|x| x.instance.name.as_deref());
+        match x
         {
-          match x
+          Ok(v) => tracing::debug!(node = %static_id, name, outputs = ?v, "node finished"),
+          Err(e) =>
           {
-            Ok(v) => println!("Node {id} finished successfully with value(s) {:?}", v),
-            Err(e) => println!("Node {id} failed with error {e:?}"),
+            tracing::warn!(node = %static_id, name, error = %e, "node failed");
+            if !matches!(e, EvalError::Aborted { .. })
+            {
+              eval.fail(id, &e);
+            }
+            if let (Some(logger), Some(node)) = (&eval.node_logger, node)
+            {
+              logger
+                .log(&NodeStateLogger::error_string(node.static_id, &e))
+                .await;
+            }
           }
         }
-        Ok(Err(e)) => println!("Task join error {:?}", e),
-        Err(e) => println!("Task join error {:?}", e),
+      }
+      Some(Err(e)) => tracing::error!(error = %e, "node task panicked or was aborted"),
+      None =>
+      {
+        tokio::select! {
+          _ = eval.cancel.cancelled() => return,
+          _ = eval.tasks.spawned() => {},
+        }
       }
     }
-    else if js.is_empty()
-    {
-      return;
+  }
+}
+
+/// The random generators of a run's `Random` nodes, one for each node of each instance
+/// so that with a seed the values a node draws do not depend on when other nodes draw
+/// theirs
+struct NodeRngs
+{
+  seed: Option<u64>,
+  rngs: HashMap<Uuid, StdRng>,
+}
+
+impl NodeRngs
+{
+  fn new(seed: Option<u64>) -> Self
+  {
+    Self {
+      seed,
+      rngs: HashMap::new(),
     }
-    tokio::task::yield_now().await;
   }
-  for handle in abort_handles.drain(0..)
+
+  /// The generator of the node `key` names, seeded from the run's seed and the key
+  fn get(&mut self, key: Uuid) -> &mut StdRng
+  {
+    let seed = self.seed;
+    self.rngs.entry(key).or_insert_with(|| {
+      match seed
+      {
+        Some(seed) =>
+        {
+          let mut bytes = [0; 32];
+          bytes[..8].copy_from_slice(&seed.to_le_bytes());
+          bytes[8..24].copy_from_slice(key.as_bytes());
+          StdRng::from_seed(bytes)
+        }
+        None => rand::make_rng(),
+      }
+    })
+  }
+}
+
+/// `path` as written in a graph, relative to `base` unless it is absolute. Either
+/// separator is accepted so graphs saved on Windows load everywhere, and drive letter
+/// and UNC paths are kept as they are. `..` takes off the directory before it rather
+/// than being kept, so a graph reached different ways is cached once, and does not
+/// need the directory it leaves to exist.
+fn graph_relative(base: &Path, path: &str) -> PathBuf
+{
+  let bytes = path.as_bytes();
+  let drive = bytes.len() >= 3
+    && bytes[0].is_ascii_alphabetic()
+    && bytes[1] == b':'
+    && matches!(bytes[2], b'/' | b'\\');
+  if drive || path.starts_with("\\\\") || Path::new(path).is_absolute()
+  {
+    return PathBuf::from(path);
+  }
+  let mut out = base.to_path_buf();
+  for part in path
+    .split(['/', '\\'])
+    .filter(|x| !x.is_empty() && *x != ".")
   {
-    handle.abort();
+    match part == ".." && matches!(out.components().next_back(), Some(Component::Normal(_)))
+    {
+      true =>
+      {
+        out.pop();
+      }
+      false => out.push(part),
+    }
   }
+  out
 }
 
 pub struct Evaluator<TextLogger: Logger, NodeLogger: Logger>
@@ -98,22 +265,100 @@ pub struct Evaluator<TextLogger: Logger, NodeLogger: Logger>
 
   parent: Option<Arc<Self>>,
   end_node: Uuid,
+  /// The types of the graph's inputs by port
+  input_types: Arc<Vec<DataType>>,
+  /// Values for inputs a run leaves out or gives None for, by port number
+  input_defaults: Arc<HashMap<String, DataValue>>,
+  /// What the graph's inputs and outputs are called, by port
+  input_names: Arc<Vec<String>>,
+  output_names: Arc<Vec<String>>,
   inputs: (
     tokio::sync::mpsc::Sender<Vec<DataValue>>,
     RwLock<tokio::sync::mpsc::Receiver<Vec<DataValue>>>,
   ),
-  pub(crate) my_path: String,
+  /// The inputs last read from the channel, and those sent that are still in it, for
+  /// checkpoints
+  current_inputs: std::sync::Mutex<Option<Vec<DataValue>>>,
+  pending_inputs: std::sync::Mutex<VecDeque<Vec<DataValue>>>,
+  /// Agent conversations and runner state from a checkpoint, waiting for the `Create`
+  /// and `Complex` nodes they belong to to run
+  restored: std::sync::Mutex<InstanceState>,
+  /// How many Complex graphs this instance runs inside, 0 for one started directly
+  depth: usize,
+  /// Where the instance is in the run, the same every run: nil for one started
+  /// directly, otherwise derived from its caller's and the node or tool that started
+  /// it. Keys the random generators of its nodes.
+  lineage: Uuid,
+  pub(crate) my_path: PathBuf,
+  /// The graph file this evaluator was loaded from
+  graph_path: String,
+  /// When that file was last modified as it was read, None for graphs not read from one
+  modified: Option<SystemTime>,
+  /// The bundle this graph was loaded from, which the Complex graphs it uses are
+  /// taken from too
+  bundle: Option<Arc<Bundle>>,
+  /// Unique per instance, the root instance's identifies the whole run
+  run_id: Uuid,
+  started: SystemTime,
+  /// `key=value` parameters given on the command line, only set on the root
+  params: RwLock<HashMap<String, String>>,
+  /// Settings from `agentnodes.toml`, only set on the root
+  config: RwLock<Arc<Config>>,
+  /// When the whole run gives up, only set on the root
+  deadline: RwLock<Option<Instant>>,
+  /// Scratch directory of the run, made the first time it is asked for and removed on
+  /// shutdown. Only set on the root
+  workspace: RwLock<Option<PathBuf>>,
+  /// Leave the workspace in place on shutdown, only set on the root
+  keep_workspace: RwLock<bool>,
+  /// Runs every instance's nodes in a fixed order on its own task instead of spawning
+  /// them, only set on the root
+  deterministic: RwLock<bool>,
+  /// Called around every node of the run, only set on the root
+  hooks: RwLock<Vec<Arc<dyn NodeHooks>>>,
+  /// Also counts IO bytes when set, only set on the root
+  metrics: RwLock<Option<Arc<Metrics>>>,
+  /// Holds agent requests to the configured limits, only set on the root
+  agent_limiter: RwLock<Arc<AgentLimiter>>,
+  /// What the run has used up against `[run_limits]`, made for each instance but only
+  /// used on the root
+  budget: Arc<Budget>,
+  /// The calls and tokens of the run's agents, made for each instance but only used
+  /// on the root
+  usage: Arc<UsageLedger>,
+  /// The generators of the `Random` nodes, seeded with what each instance made from
+  /// this one is seeded with. Only used on the root
+  rngs: std::sync::Mutex<NodeRngs>,
   listen_handle: RwLock<Option<JoinHandle<()>>>,
-  pub(self) closed: AtomicBool,
-  io_registry: Arc<RwLock<HashMap<Uuid, IoObject>>>,
+  /// Cancelled by [`Self::shutdown`], and with the instance that started this one
+  pub(super) cancel: CancellationToken,
+  pub(super) tasks: TaskRegistry,
+  io_registry: Arc<RwLock<HashMap<Uuid, IoEntry>>>,
+
+  agent_registry: Arc<RwLock<HashMap<Uuid, Arc<RegisteredAgent>>>>,
 
-  agent_registry: Arc<RwLock<HashMap<Uuid, DynAgent>>>,
+  http_servers: RwLock<HashMap<String, Arc<HttpServer>>>,
 
   dangling_nodes: Arc<HashSet<Uuid>>,
+  /// What loading the graph folded and removed
+  optimized: Arc<Optimized>,
 
   variables: RwLock<HashMap<String, DataValue>>,
+  /// The enums declared by the graph, for `Cast` nodes to check variants against
+  enums: Arc<HashMap<String, Vec<String>>>,
+  /// The record types declared by the graph or loaded from its `types` files
+  records: Arc<Records>,
 
   pub complete: Notify,
+  /// Set once the end node has run, for the shutdown report
+  pub(super) completed: AtomicBool,
+
+  /// Set with the message and exit code once an `Abort` node runs, only used on the
+  /// root instance
+  aborted: watch::Sender<Option<(String, i32)>>,
+  /// The first node to fail in this instance and its error message, so a complex
+  /// runner's caller hears about it instead of waiting for the end node forever
+  failed: watch::Sender<Option<(Uuid, String)>>,
 
   pub node_logger: Option<Arc<NodeLogger>>,
   pub text_logger: Option<Arc<TextLogger>>,
@@ -123,29 +368,72 @@ impl<TextLogger: Logger, NodeLogger: Logger> AsyncClone for Evaluator<TextLogger
 {
   async fn clone(&self) -> Self
   {
+    let seed = self.rngs.lock().unwrap().seed;
     Self {
-      scope_id: self.scope_id.clone(),
+      scope_id: self.scope_id,
       nodes: self
         .nodes
         .iter()
-        .map(|(id, node)| (id.clone(), Arc::new((*(node.clone())).clone())))
+        .map(|(id, node)| (*id, Arc::new((*(node.clone())).clone())))
         .collect(),
       evaluator_cache: RwLock::new(self.evaluator_cache.read().await.clone()),
       complex_nodes: RwLock::new(HashMap::new()),
       parent: self.parent.clone(),
-      end_node: self.end_node.clone(),
+      end_node: self.end_node,
+      input_types: self.input_types.clone(),
+      input_defaults: self.input_defaults.clone(),
+      input_names: self.input_names.clone(),
+      output_names: self.output_names.clone(),
       inputs: {
         let channels = tokio::sync::mpsc::channel(1024);
         (channels.0, RwLock::new(channels.1))
       },
+      current_inputs: std::sync::Mutex::new(None),
+      pending_inputs: std::sync::Mutex::default(),
+      restored: std::sync::Mutex::default(),
+      depth: self.depth,
+      lineage: self.lineage,
       my_path: self.my_path.clone(),
+      graph_path: self.graph_path.clone(),
+      modified: self.modified,
+      bundle: self.bundle.clone(),
+      run_id: Uuid::new_v4(),
+      started: SystemTime::now(),
+      params: RwLock::new(self.params.read().await.clone()),
+      config: RwLock::new(self.config.read().await.clone()),
+      deadline: RwLock::new(*self.deadline.read().await),
+      workspace: RwLock::new(None),
+      keep_workspace: RwLock::new(*self.keep_workspace.read().await),
+      deterministic: RwLock::new(*self.deterministic.read().await),
+      hooks: RwLock::new(self.hooks.read().await.clone()),
+      metrics: RwLock::new(self.metrics.read().await.clone()),
+      agent_limiter: RwLock::new(self.agent_limiter.read().await.clone()),
+      budget: Arc::new(Budget::new(self.config.read().await.run_limits.clone())),
+      usage: Arc::default(),
+      rngs: std::sync::Mutex::new(NodeRngs::new(seed)),
       listen_handle: RwLock::new(None),
-      closed: AtomicBool::new(false),
+      cancel: match &self.parent
+      {
+        Some(parent) => parent.cancel.child_token(),
+        None => CancellationToken::new(),
+      },
+      tasks: match *self.root().deterministic.read().await
+      {
+        true => TaskRegistry::ordered(),
+        false => TaskRegistry::new(),
+      },
       io_registry: Arc::new(RwLock::new(HashMap::new())),
       agent_registry: Arc::new(RwLock::new(HashMap::new())),
+      http_servers: RwLock::new(HashMap::new()),
       dangling_nodes: Arc::new(self.dangling_nodes.as_ref().clone()),
+      optimized: self.optimized.clone(),
       variables: RwLock::new(HashMap::new()),
+      enums: self.enums.clone(),
+      records: self.records.clone(),
       complete: Notify::new(),
+      completed: AtomicBool::new(false),
+      aborted: watch::Sender::new(None),
+      failed: watch::Sender::new(None),
       node_logger: self.node_logger.clone(),
       text_logger: self.text_logger.clone(),
     }
@@ -160,19 +448,89 @@ impl<TextLogger: Logger, NodeLogger: Logger> Evaluator<TextLogger, NodeLogger>
     node_logger: Option<Arc<NodeLogger>>,
   ) -> Result<Arc<Self>, EvalError>
   {
+    let modified = std::fs::metadata(&path).and_then(|x| x.modified()).ok();
+    if let Some(bundle) = Bundle::read(&path)?
+    {
+      let root = bundle
+        .graphs
+        .get(&bundle.root)
+        .cloned()
+        .ok_or_else(|| EvalError::ComplexNotFound(bundle.root.clone()))?;
+      let bundle = Some(Arc::new(bundle));
+      return Self::prepare(
+        root,
+        path,
+        modified,
+        parent,
+        text_logger,
+        node_logger,
+        bundle,
+      );
+    }
+    let me = Complex::load(&path)?;
+    Self::prepare(me, path, modified, parent, text_logger, node_logger, None)
+  }
+
+  /// Prepares a graph that is already loaded, such as one made with a
+  /// [`GraphBuilder`](crate::language::builder::GraphBuilder). `path` is where it would
+  /// be saved, `Complex` nodes in it being resolved relative to that.
+  pub fn from_complex(
+    me: Complex,
+    path: String,
+    parent: Option<Arc<Self>>,
+    text_logger: Option<Arc<TextLogger>>,
+    node_logger: Option<Arc<NodeLogger>>,
+  ) -> Result<Arc<Self>, EvalError>
+  {
+    Self::prepare(me, path, None, parent, text_logger, node_logger, None)
+  }
+
+  /// `bundle` is the one `me` came from, if not the parent's
+  fn prepare(
+    mut me: Complex,
+    path: String,
+    modified: Option<SystemTime>,
+    parent: Option<Arc<Self>>,
+    text_logger: Option<Arc<TextLogger>>,
+    node_logger: Option<Arc<NodeLogger>>,
+    bundle: Option<Arc<Bundle>>,
+  ) -> Result<Arc<Self>, EvalError>
+  {
+    let bundle = bundle.or_else(|| parent.as_ref().and_then(|x| x.bundle.clone()));
     let parent_id = parent.as_ref().map(|x| x.scope_id).unwrap_or(Uuid::nil());
+    let cancel = match &parent
+    {
+      Some(parent) => parent.cancel.child_token(),
+      None => CancellationToken::new(),
+    };
     let scope_id = Uuid::new_v5(&parent_id, Uuid::new_v4().as_bytes());
-    let file = std::fs::File::open(&path)?;
-    let me = serde_json::from_reader::<std::fs::File, Complex>(file)
-      .map_err(|x| EvalError::InvalidComplexNode(path.clone(), x))?;
+    me.validate()?;
+    // defaults given by input name are looked up by number from here on
+    let input_defaults = (0..me.inputs.len())
+      .filter_map(|port| {
+        me.input_default(port)
+          .map(|x| (port.to_string(), x.clone()))
+      })
+      .collect();
+    let optimized = me.optimize();
+    if !optimized.is_empty()
+    {
+      tracing::debug!(
+        path,
+        folded = ?optimized.folded,
+        removed = ?optimized.removed,
+        "{optimized}"
+      );
+    }
 
     let mut non_dangling = HashSet::new();
     let all_ids: HashSet<Uuid> = me
       .instances
-      .iter()
-      .map(|(unscoped, _)| Self::convert_id(&scope_id, unscoped.clone()))
+      .keys()
+      .map(|unscoped| Self::convert_id(&scope_id, *unscoped))
       .collect();
 
+    let mut listed_reads = listed_reads(&me.instances);
     //wow iterators are insane
     let nodes: HashMap<Uuid, Arc<ExecutionNode>> = me
       .instances
@@ -183,12 +541,15 @@ impl<TextLogger: Logger, NodeLogger: Logger> Evaluator<TextLogger, NodeLogger>
           .inputs
           .iter()
           .map(|(t, id, socket)| {
-            non_dangling.insert(Self::convert_id(&scope_id, id.clone()));
-            (t.clone(), Self::convert_id(&scope_id, id.clone()), *socket)
+            non_dangling.insert(Self::convert_id(&scope_id, *id));
+            (t.clone(), Self::convert_id(&scope_id, *id), *socket)
           })
           .collect();
 
-        let ex = Arc::new(ExecutionNode::new(unscoped, scoped, instance, inputs));
+        let reads = listed_reads.remove(&unscoped).unwrap_or_default();
+        let ex = Arc::new(ExecutionNode::new(
+          unscoped, scoped, instance, inputs, reads,
+        ));
         (scoped, ex)
       })
       .collect();
@@ -196,32 +557,118 @@ impl<TextLogger: Logger, NodeLogger: Logger> Evaluator<TextLogger, NodeLogger>
     let dangling: HashSet<Uuid> = all_ids.difference(&non_dangling).cloned().collect();
 
     Ok(Arc::new(Self {
-      scope_id: scope_id.clone(),
+      scope_id,
       nodes,
       evaluator_cache: RwLock::new(HashMap::new()),
       complex_nodes: RwLock::new(HashMap::new()),
       parent,
       end_node: Self::convert_id(&scope_id, me.end_node),
+      input_types: Arc::new(me.inputs),
+      input_defaults: Arc::new(input_defaults),
+      input_names: Arc::new(me.input_names),
+      output_names: Arc::new(me.output_names),
       inputs: {
         let channels = tokio::sync::mpsc::channel(1024);
         (channels.0, RwLock::new(channels.1))
       },
-      my_path: std::path::Path::new(&path)
+      current_inputs: std::sync::Mutex::new(None),
+      pending_inputs: std::sync::Mutex::default(),
+      restored: std::sync::Mutex::default(),
+      depth: 0,
+      lineage: Uuid::nil(),
+      my_path: Path::new(&path)
         .parent()
-        .map(|x| x.to_str().unwrap().to_string())
+        .map(Path::to_path_buf)
         .unwrap_or_default(),
+      graph_path: path,
+      modified,
+      bundle,
+      run_id: Uuid::new_v4(),
+      started: SystemTime::now(),
+      params: RwLock::new(HashMap::new()),
+      config: RwLock::new(Arc::default()),
+      deadline: RwLock::new(None),
+      workspace: RwLock::new(None),
+      keep_workspace: RwLock::new(false),
+      deterministic: RwLock::new(false),
+      hooks: RwLock::new(vec![]),
+      metrics: RwLock::new(None),
+      agent_limiter: RwLock::new(Arc::default()),
+      budget: Arc::new(Budget::new(RunLimits::default())),
+      usage: Arc::default(),
+      rngs: std::sync::Mutex::new(NodeRngs::new(None)),
       listen_handle: RwLock::new(None),
-      closed: AtomicBool::new(false),
+      cancel,
+      tasks: TaskRegistry::new(),
       io_registry: Arc::new(RwLock::new(HashMap::new())),
       agent_registry: Arc::new(RwLock::new(HashMap::new())),
+      http_servers: RwLock::new(HashMap::new()),
       dangling_nodes: Arc::new(dangling),
+      optimized: Arc::new(optimized),
       variables: RwLock::new(HashMap::new()),
+      enums: Arc::new(me.enums),
+      records: Arc::new(me.records),
       complete: Notify::new(),
+      completed: AtomicBool::new(false),
+      aborted: watch::Sender::new(None),
+      failed: watch::Sender::new(None),
       text_logger,
       node_logger,
     }))
   }
 
+  /// The constant nodes folded and dead nodes removed as the graph was loaded
+  pub fn optimized(&self) -> &Optimized
+  {
+    &self.optimized
+  }
+
+  /// The types of the inputs the graph's start node gives, by port
+  pub fn input_types(&self) -> &[DataType]
+  {
+    &self.input_types
+  }
+
+  /// The port of the input named `key`, or numbered `key`
+  pub fn input_port(&self, key: &str) -> Option<usize>
+  {
+    port_by_key(&self.input_names, self.input_types.len(), key)
+  }
+
+  /// What input `port` is called, if it has a name
+  pub fn input_name(&self, port: usize) -> Option<&str>
+  {
+    self
+      .input_names
+      .get(port)
+      .map(String::as_str)
+      .filter(|x| !x.is_empty())
+  }
+
+  /// What the graph's outputs are called by port, empty where one has no name
+  pub fn output_names(&self) -> &[String]
+  {
+    &self.output_names
+  }
+
+  /// The value input `port` takes when a run leaves it out or gives None for it
+  pub fn input_default(&self, port: usize) -> Option<&DataValue>
+  {
+    self.input_defaults.get(&port.to_string())
+  }
+
+  /// The variants of the enum `name` declared by this graph
+  pub fn enum_variants(&self, name: &str) -> Option<&[String]>
+  {
+    self.enums.get(name).map(Vec::as_slice)
+  }
+
+  /// The record types this graph knows
+  pub fn records(&self) -> &Records
+  {
+    &self.records
+  }
+
   fn convert_id(scope: &Uuid, unscoped: Uuid) -> Uuid
   {
     Uuid::new_v5(scope, unscoped.as_bytes())
@@ -229,244 +676,1634 @@ impl<TextLogger: Logger, NodeLogger: Logger> Evaluator<TextLogger, NodeLogger>
 
   pub async fn send_inputs(&self, inputs: Vec<DataValue>)
   {
+    self
+      .pending_inputs
+      .lock()
+      .unwrap()
+      .push_back(inputs.clone());
     self.inputs.0.clone().send(inputs).await.unwrap();
   }
 
   pub async fn get_inputs(&self) -> Vec<DataValue>
   {
-    self.inputs.1.write().await.recv().await.unwrap_or_default()
+    let inputs = self.inputs.1.write().await.recv().await.unwrap_or_default();
+    let inputs = fill_defaults(&self.input_defaults, inputs, self.input_types.len());
+    self.pending_inputs.lock().unwrap().pop_front();
+    *self.current_inputs.lock().unwrap() = Some(inputs.clone());
+    inputs
   }
 
+  /// Waits for the end node to run and returns its outputs. The instance keeps running
+  /// afterwards so it can be fed new inputs; call [`Self::shutdown`] to stop it.
+  /// Waits for the end node and returns its values, or [`EvalError::Aborted`] if an
+  /// `Abort` node ran anywhere under this instance first. Once one of its nodes fails
+  /// the root returns [`EvalError::NodeFailed`] and complex runners
+  /// [`EvalError::SubgraphFailed`]. The root also returns [`EvalError::TimedOut`] once
+  /// its deadline passes or [`EvalError::LimitExceeded`] once the run goes over one of
+  /// its `[run_limits]`.
   pub async fn get_outputs(&self) -> Result<Vec<DataValue>, EvalError>
   {
-    // println!("Getoutputs");
     let node = self.nodes.get(&self.end_node).ok_or(EvalError::NoEndNode)?;
-    // println!("Got");
+    // the node that went over a limit fails too, the limit is what stopped the run
+    tokio::select! {
+      biased;
+      _ = self.wait_for_complete() => {},
+      (message, code) = self.wait_for_abort() => return Err(EvalError::Aborted { message, code }),
+      _ = self.wait_for_deadline(), if self.parent.is_none() => return Err(EvalError::TimedOut),
+      e = self.wait_for_limit(), if self.parent.is_none() => return Err(e),
+      (node, message) = self.wait_for_failure() => {
+        let name = self.node_name(&node).map(str::to_string);
+        return Err(match self.parent
+        {
+          Some(_) => EvalError::SubgraphFailed { path: self.graph_path.clone(), node, name, message },
+          None => EvalError::NodeFailed { node, name, message },
+        });
+      }
+    }
+    // a node may have gone over a limit on the way to the end node
+    if let Some(e) = self.root().budget.exceeded()
+    {
+      return Err(e);
+    }
 
     let mut out = Vec::with_capacity(node.outputs.len());
     for i in 0..node.outputs.len()
     {
-      let n = node.clone();
-      // println!("listening");
-      dbg!(n.instance.node_type.clone());
-
-      let res = n.get_output(i).await;
-      // println!("receiving");
-      out.push(res);
-
-      // out.push(
-      //   node
-      //     .clone()
-      //     .listen(i)
-      //     .await?
-      //     .await?
-      //     .ok_or(EvalError::Closed)?,
-      // );
+      // the end node lists itself as the reader of each graph output
+      out.push(node.get_output(i, node.static_id).await);
     }
     Ok(out)
   }
 
-  pub async fn shutdown(self: Arc<Self>)
-  {
-    self
-      .closed
-      .store(true, std::sync::atomic::Ordering::Release);
-    // self
-    //   .listen_handle
-    //   .write()
-    //   .await
-    //   .take()
-    //   .unwrap()
-    //   .await
-    //   .unwrap();
-  }
-  #[allow(dead_code)]
-  pub async fn print_states(&self)
+  /// Stops the instance, its complex runners and any IO they are blocked on, and waits
+  /// up to [`TEARDOWN_TIMEOUT`] for all of their tasks to finish. The report says how
+  /// the run went and what was left to clean up.
+  pub async fn shutdown(self: Arc<Self>) -> ShutdownReport
   {
-    for x in self.nodes.values()
+    let mut report = ShutdownReport {
+      scope_id: self.scope_id,
+      graph_path: self.graph_path.clone(),
+      outcome: self.outcome(),
+      ran_for: self.started.elapsed().unwrap_or_default(),
+      teardown: Duration::ZERO,
+      nodes: vec![],
+      handles_closed: 0,
+      agents_destroyed: 0,
+      servers_stopped: 0,
+      runners: vec![],
+    };
+    let start = Instant::now();
+    self.cancel.cancel();
+    if tokio::time::timeout(TEARDOWN_TIMEOUT, self.clone().teardown(&mut report))
+      .await
+      .is_err()
     {
-      println!("{}:{:?}", x.id, x.state.read().await);
+      tracing::warn!(
+        scope = %self.scope_id,
+        timeout = ?TEARDOWN_TIMEOUT,
+        "instance did not stop in time"
+      );
     }
+    report.teardown = start.elapsed();
+    for node in self.nodes.values()
+    {
+      report.nodes.push(node.report().await);
+    }
+    report.nodes.sort_by_key(|x| x.node);
+    report
   }
 
-  pub async fn instantiate(self: Arc<Self>, inputs: Vec<DataValue>) -> Arc<Self>
-  {
-    let instance = Arc::new((*self).clone().await);
-    instance.send_inputs(inputs).await;
-    let tasks = instance
-      .nodes
-      .values()
-      .map(|x| x.clone().spawn(instance.clone()))
-      .collect();
-    let start = instance
-      .nodes
-      .iter()
-      .find(|(_, node)| {
-        node.instance.node_type == NodeType::Atomic(AtomicType::Control(ControlFlow::Start))
-      })
-      .ok_or(EvalError::NoStartNode)
-      .unwrap()
-      .1;
-    start.trigger_processing(self.clone()).await;
-    *instance.listen_handle.write().await =
-      Some(tokio::task::spawn(task_listen(instance.clone(), tasks)));
-
-    instance
-  }
-
-  pub async fn get_evaluator(&self, path: &str) -> Option<Arc<Self>>
+  /// How the run has gone so far, a failure in this instance taking precedence.
+  fn outcome(&self) -> Outcome
   {
-    if let Some(e) = self.evaluator_cache.read().await.get(path)
+    if let Some((node, message)) = self.failed.borrow().clone()
     {
-      Some(e.clone())
+      return Outcome::Failed { node, message };
     }
-    else if let Some(p) = self.parent.as_ref()
+    if let Some((message, code)) = self.root().aborted.borrow().clone()
     {
-      let got = Box::pin(p.clone().get_evaluator(path)).await;
-      if let Some(e) = &got
-      {
-        self
-          .evaluator_cache
-          .write()
-          .await
-          .insert(path.to_string(), e.clone());
-      }
-      got
+      return Outcome::Aborted { message, code };
     }
-    else
+    match self.completed.load(Ordering::Acquire)
     {
-      None
+      true => Outcome::Completed,
+      false => Outcome::Cancelled,
     }
   }
 
-  pub async fn get_complex_runner(&self, id: &Uuid) -> Option<Arc<Self>>
+  async fn teardown(self: Arc<Self>, report: &mut ShutdownReport)
   {
-    self.complex_nodes.read().await.get(id).cloned()
+    let servers: Vec<Arc<HttpServer>> = self
+      .http_servers
+      .write()
+      .await
+      .drain()
+      .map(|(_, x)| x)
+      .collect();
+    for server in servers
+    {
+      server.stop().await;
+      report.servers_stopped += 1;
+    }
+    let runners: Vec<Arc<Self>> = self
+      .complex_nodes
+      .write()
+      .await
+      .drain()
+      .map(|(_, x)| x)
+      .collect();
+    for runner in runners
+    {
+      report.runners.push(Box::pin(runner.shutdown()).await);
+    }
+    self.tasks.abort_all().await;
+    let listen_handle = self.listen_handle.write().await.take();
+    if let Some(handle) = listen_handle
+    {
+      let _ = handle.await;
+    }
+    // nothing can use them any more, so they are closed now rather than with the
+    // last reference to the instance
+    report.handles_closed = self.io_registry.write().await.drain().count();
+    report.agents_destroyed = self.agent_registry.write().await.drain().count();
+    // the variables of a finished Complex graph no longer count towards the run's
+    for value in self.variables.read().await.values()
+    {
+      self
+        .root()
+        .budget
+        .release(Limit::StoredBytes, stored_size(value));
+    }
+    if self.parent.is_none()
+    {
+      self.remove_workspace().await;
+    }
+    debug_assert_eq!(
+      self.task_stats().await,
+      TaskStats::default(),
+      "tasks outlived the shutdown of instance {}",
+      self.scope_id
+    );
   }
 
-  pub async fn add_complex_runner(&self, instance: Arc<Self>, id: &Uuid)
+  /// Fails with [`EvalError::ComplexCycle`] if the Complex graphs this graph uses,
+  /// including ones wrapped by `Try` and `Timeout`, lead back to one another, which
+  /// would start instances inside each other until `[graphs] max_depth` is reached.
+  pub async fn check_cycles(self: Arc<Self>) -> Result<(), EvalError>
   {
-    self.complex_nodes.write().await.insert(*id, instance);
+    let mut chain = vec![self.graph_path.clone()];
+    self.visit_complex(&mut chain, &mut HashSet::new()).await
   }
 
-  pub async fn add_evaluator(self: Arc<Self>, path: &str, eval: Arc<Self>)
+  /// Walks the graphs used by this one, `chain` being the graphs leading to it
+  async fn visit_complex(
+    self: Arc<Self>,
+    chain: &mut Vec<String>,
+    checked: &mut HashSet<PathBuf>,
+  ) -> Result<(), EvalError>
   {
-    self
-      .evaluator_cache
-      .write()
-      .await
-      .insert(path.to_string(), eval.clone());
-    if let Some(p) = self.parent.as_ref()
+    // paths are compared as files, as graphs may reach the same one different ways
+    let key = |path: &str| std::fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+    for path in self.complex_paths().await
     {
-      Box::pin(p.clone().add_evaluator(path, eval)).await;
+      if let Some(i) = chain.iter().position(|x| key(x) == key(&path))
+      {
+        let mut cycle = chain[i..].to_vec();
+        cycle.push(path);
+        return Err(EvalError::ComplexCycle(cycle));
+      }
+      if checked.contains(&key(&path))
+      {
+        continue;
+      }
+      let graph = self.clone().read_complex(&path).await?;
+      chain.push(path.clone());
+      Box::pin(graph.visit_complex(chain, checked)).await?;
+      chain.pop();
+      checked.insert(key(&path));
     }
+    Ok(())
   }
 
-  pub async fn register_io(&self, io: IoObject) -> Uuid
+  /// The resolved paths of the Complex graphs this graph's nodes use, including ones
+  /// wrapped by `Try` and `Timeout`, sorted and each once
+  async fn complex_paths(&self) -> Vec<String>
   {
-    let mut guard = self.io_registry.write().await;
-    let mut ret = Uuid::new_v4();
-    while guard.contains_key(&ret)
+    let mut paths = vec![];
+    for node in self.nodes.values()
     {
-      ret = Uuid::new_v4();
+      let mut types = vec![&node.instance.node_type];
+      while let Some(node_type) = types.pop()
+      {
+        match node_type
+        {
+          NodeType::Complex(path) => paths.push(self.resolve_complex(path).await),
+          NodeType::Atomic(AtomicType::Control(
+            ControlFlow::Try(inner) | ControlFlow::Timeout(inner, _),
+          )) => types.push(inner),
+          NodeType::Atomic(_) => (),
+        }
+      }
     }
-    guard.insert(ret, io);
-    ret
+    paths.sort();
+    paths.dedup();
+    paths
   }
 
-  async fn find_io_registry_mut(
-    self: &Arc<Self>,
-    id: &Uuid,
-  ) -> Result<RwLockWriteGuard<'_, HashMap<Uuid, IoObject>>, EvalError>
+  /// The file of this graph and those of the Complex graphs it uses, so they can be
+  /// watched for changes. Graphs that fail to load are listed without what they use.
+  pub async fn graph_files(self: Arc<Self>) -> Vec<String>
   {
-    if self.io_registry.read().await.contains_key(id)
+    let mut files = vec![self.graph_path.clone()];
+    let mut graphs = vec![self];
+    while let Some(graph) = graphs.pop()
     {
-      return Ok(self.io_registry.write().await);
+      for path in graph.complex_paths().await
+      {
+        if files.contains(&path)
+        {
+          continue;
+        }
+        files.push(path.clone());
+        if let Ok(e) = graph.clone().read_complex(&path).await
+        {
+          graphs.push(e);
+        }
+      }
     }
+    files
+  }
 
-    let mut current = &self.parent;
-    while let Some(parent) = &current
+  /// The nodes of this graph and of the Complex graphs it uses, including ones wrapped
+  /// by `Try` and `Timeout`, needing something the sandbox of `config` does not allow,
+  /// leaving out those its dry run stands in for if it is on. Each is given as the
+  /// graph file, the node's id there and the permission, so a run can be refused
+  /// before it starts.
+  pub async fn denied_nodes(
+    self: Arc<Self>,
+    config: &Config,
+  ) -> Result<Vec<(String, Uuid, Permission)>, EvalError>
+  {
+    let policy = &config.sandbox;
+    let mut denied = vec![];
+    let mut seen = HashSet::from([self.graph_path.clone()]);
+    let mut graphs = vec![self];
+    while let Some(graph) = graphs.pop()
     {
-      if parent.io_registry.read().await.contains_key(id)
+      for node in graph.nodes.values()
       {
-        return Ok(parent.io_registry.write().await);
+        let mut types = vec![&node.instance.node_type];
+        while let Some(node_type) = types.pop()
+        {
+          match node_type
+          {
+            NodeType::Complex(path) =>
+            {
+              let path = graph.resolve_complex(path).await;
+              if seen.insert(path.clone())
+              {
+                graphs.push(graph.clone().read_complex(&path).await?);
+              }
+            }
+            NodeType::Atomic(AtomicType::Control(
+              ControlFlow::Try(inner) | ControlFlow::Timeout(inner, _),
+            )) => types.push(inner),
+            NodeType::Atomic(atomic) =>
+            {
+              if let Some(permission) = atomic
+                .permission(config.dry_run.enabled)
+                .filter(|x| !policy.allows(*x))
+              {
+                denied.push((graph.graph_path.clone(), node.static_id, permission));
+              }
+            }
+          }
+        }
       }
-      current = &parent.parent
     }
-    Err(EvalError::IoNotFound(id.clone()))
+    denied.sort_by(|a, b| (&a.0, a.1).cmp(&(&b.0, b.1)));
+    Ok(denied)
   }
 
-  pub async fn read_until(self: Arc<Self>, id: &Uuid, pattern: &[u8])
-    -> Result<Vec<u8>, EvalError>
+  /// What a graph is checked for before it is run, once its config is set: fails as
+  /// [`Self::check_cycles`] does, then returns [`Self::denied_nodes`] if the config's
+  /// sandbox denies anything. The run should be refused unless none are returned.
+  pub async fn check_before_run(
+    self: Arc<Self>,
+  ) -> Result<Vec<(String, Uuid, Permission)>, EvalError>
   {
-    let mut guard = self.find_io_registry_mut(id).await?;
-    let io = guard.get_mut(id).ok_or(EvalError::IoNotFound(id.clone()))?;
-    read_until_generic(io, pattern).await
+    self.clone().check_cycles().await?;
+    let config = self.config().await;
+    match config.sandbox.denies_any()
+    {
+      true => self.denied_nodes(&config).await,
+      false => Ok(vec![]),
+    }
   }
 
-  pub async fn read_bytes(self: Arc<Self>, id: &Uuid, buf: &mut Vec<u8>)
-    -> Result<usize, EvalError>
+  /// Stops the instance like [`Self::shutdown`], but first flushes its open streams and
+  /// gives running nodes and IO operations up to `grace` to finish. Returns what was
+  /// still running when it stopped waiting, and the shutdown report.
+  pub async fn stop(self: Arc<Self>, grace: Duration) -> (StopSummary, ShutdownReport)
   {
-    let mut guard = self.find_io_registry_mut(id).await?;
-    let io = guard.get_mut(id).ok_or(EvalError::IoNotFound(id.clone()))?;
-    io.read_buf(buf).await.map_err(EvalError::from)
+    let settled = tokio::time::timeout(grace, async {
+      self.clone().flush_all_io().await;
+      while !self.summary().await.is_idle()
+      {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+      }
+    })
+    .await;
+    let summary = match settled
+    {
+      Ok(()) => StopSummary::default(),
+      Err(_) => self.summary().await,
+    };
+    (summary, self.shutdown().await)
   }
 
-  pub async fn write_bytes(self: Arc<Self>, id: &Uuid, buf: &mut Vec<u8>) -> Result<(), EvalError>
+  /// Flushes every stream of this instance and its complex runners, ignoring handles
+  /// that cannot be flushed.
+  async fn flush_all_io(self: Arc<Self>)
   {
-    let mut guard = self.find_io_registry_mut(id).await?;
-    let io = guard.get_mut(id).ok_or(EvalError::IoNotFound(id.clone()))?;
-
-    io.write_all(buf).await.map_err(EvalError::from)
+    let ids: Vec<Uuid> = self.io_registry.read().await.keys().copied().collect();
+    for id in ids
+    {
+      let _ = self.clone().flush_io(&id).await;
+    }
+    let runners: Vec<Arc<Self>> = self.complex_nodes.read().await.values().cloned().collect();
+    for runner in runners
+    {
+      Box::pin(runner.flush_all_io()).await;
+    }
   }
 
-  pub fn find_node(&self, id: &Uuid) -> Result<Arc<ExecutionNode>, EvalError>
+  /// Nodes part way through running and IO operations in flight, for this instance and
+  /// all of its complex runners.
+  async fn summary(&self) -> StopSummary
   {
-    self
-      .nodes
-      .get(&Uuid::new_v5(&self.scope_id, id.as_bytes()))
-      .cloned()
-      .ok_or(EvalError::NodeNotFound(id.clone()))
+    let mut summary = StopSummary {
+      running_nodes: 0,
+      io_operations: self.tasks.io_operations(),
+    };
+    for node in self.nodes.values()
+    {
+      // start nodes sit in processing while waiting for the next inputs
+      let start =
+        node.instance.node_type == NodeType::Atomic(AtomicType::Control(ControlFlow::Start));
+      if !start && node.state() == NodeState::Processing
+      {
+        summary.running_nodes += 1;
+      }
+    }
+    for runner in self.complex_nodes.read().await.values()
+    {
+      let child = Box::pin(runner.summary()).await;
+      summary.running_nodes += child.running_nodes;
+      summary.io_operations += child.io_operations;
+    }
+    summary
   }
 
-  pub async fn register_agent(&self, agent_type: AgentType, args: AgentArgs) -> Uuid
+  /// Live tasks and IO operations for this instance and all of its complex runners.
+  /// Agent requests are counted for the whole run, so only on the root.
+  pub async fn task_stats(&self) -> TaskStats
   {
-    let agent = agent_type.create(args);
-    let id = Uuid::new_v4();
-    self.agent_registry.write().await.insert(id.clone(), agent);
-    id
-  }
-
-  async fn find_agent_registry_mut(
-    self: &Arc<Self>,
+    let agents = match self.parent
+    {
+      Some(_) => LimiterStats::default(),
+      None => self.agent_limiter().await.stats(),
+    };
+    let mut stats = TaskStats {
+      node_tasks: self.tasks.node_tasks(),
+      complex_runners: 0,
+      io_operations: self.tasks.io_operations(),
+      http_servers: 0,
+      agent_requests_queued: agents.queued,
+      agent_requests_in_flight: agents.in_flight,
+    };
+    for server in self.http_servers.read().await.values()
+    {
+      if server.is_running().await
+      {
+        stats.http_servers += 1;
+      }
+    }
+    for runner in self.complex_nodes.read().await.values()
+    {
+      let child = Box::pin(runner.task_stats()).await;
+      stats.node_tasks += child.node_tasks;
+      stats.io_operations += child.io_operations;
+      stats.complex_runners += child.complex_runners;
+      stats.http_servers += child.http_servers;
+      if !runner.cancel.is_cancelled()
+      {
+        stats.complex_runners += 1;
+      }
+    }
+    stats
+  }
+
+  #[allow(dead_code)]
+  pub async fn print_states(&self)
+  {
+    for x in self.nodes.values()
+    {
+      tracing::debug!(
+        node = %x.id,
+        name = x.instance.name.as_deref(),
+        state = ?x.state(),
+        "node state"
+      );
+    }
+  }
+
+  pub async fn instantiate(self: Arc<Self>, inputs: Vec<DataValue>) -> Arc<Self>
+  {
+    self
+      .start(vec![inputs], InstanceState::default(), 0, Uuid::nil())
+      .await
+  }
+
+  /// Starts an instance like [`Self::instantiate`] with the state of `checkpoint` put
+  /// back, running it again with the inputs it had.
+  pub async fn resume(self: Arc<Self>, checkpoint: Checkpoint) -> Arc<Self>
+  {
+    self
+      .start(checkpoint.inputs, checkpoint.state, 0, Uuid::nil())
+      .await
+  }
+
+  /// Starts an instance of this graph for the node or tool `from` of `caller`, a level
+  /// deeper than it and holding `state`. Fails past `[graphs] max_depth`, as graphs
+  /// using each other would otherwise start instances until memory runs out.
+  pub(crate) async fn start_nested(
+    self: Arc<Self>,
+    caller: &Self,
+    from: Uuid,
+    inputs: Vec<DataValue>,
+    state: InstanceState,
+  ) -> Result<Arc<Self>, EvalError>
+  {
+    let depth = caller.depth + 1;
+    let limit = caller.config().await.graphs.max_depth();
+    if depth > limit
+    {
+      return Err(EvalError::ComplexTooDeep {
+        path: self.graph_path.clone(),
+        limit,
+      });
+    }
+    let lineage = Uuid::new_v5(&caller.lineage, from.as_bytes());
+    Ok(self.start(vec![inputs], state, depth, lineage).await)
+  }
+
+  /// Starts an instance `depth` graphs deep at `lineage` holding `state`, sending it
+  /// each of `inputs` in turn.
+  async fn start(
+    self: Arc<Self>,
+    inputs: Vec<Vec<DataValue>>,
+    state: InstanceState,
+    depth: usize,
+    lineage: Uuid,
+  ) -> Arc<Self>
+  {
+    let mut instance = (*self).clone().await;
+    instance.depth = depth;
+    instance.lineage = lineage;
+    let instance = Arc::new(instance);
+    instance.restore(state).await;
+    for inputs in inputs
+    {
+      instance.send_inputs(inputs).await;
+    }
+    // nodes otherwise get a task as they are first triggered
+    if instance.tasks.is_ordered()
+    {
+      for node in instance.schedule()
+      {
+        node.clone().spawn(instance.clone(), &instance.tasks);
+      }
+    }
+    let start = instance
+      .nodes
+      .iter()
+      .find(|(_, node)| {
+        node.instance.node_type == NodeType::Atomic(AtomicType::Control(ControlFlow::Start))
+      })
+      .ok_or(EvalError::NoStartNode)
+      .unwrap()
+      .1;
+    start.trigger_processing(instance.clone()).await;
+    *instance.listen_handle.write().await = Some(tokio::task::spawn(task_listen(instance.clone())));
+
+    instance
+  }
+
+  /// Puts back the variables and stored values of `state`, keeping its agents and
+  /// runners until their nodes run.
+  async fn restore(&self, mut state: InstanceState)
+  {
+    *self.variables.write().await = std::mem::take(&mut state.variables).into_iter().collect();
+    for (id, value) in std::mem::take(&mut state.stored)
+    {
+      if let Ok(node) = self.find_node(&id)
+      {
+        node.set_stored(value).await;
+      }
+    }
+    *self.restored.lock().unwrap() = state;
+  }
+
+  /// The state of this instance to resume it from later, see [`Checkpoint`].
+  pub async fn checkpoint(self: Arc<Self>) -> Checkpoint
+  {
+    let mut inputs: Vec<Vec<DataValue>> = self
+      .current_inputs
+      .lock()
+      .unwrap()
+      .iter()
+      .cloned()
+      .collect();
+    inputs.extend(self.pending_inputs.lock().unwrap().iter().cloned());
+    Checkpoint {
+      // so the checkpoint can be resumed from another directory
+      graph_path: std::path::absolute(&self.graph_path)
+        .map(|x| x.display().to_string())
+        .unwrap_or_else(|_| self.graph_path.clone()),
+      inputs,
+      state: self.instance_state().await,
+    }
+  }
+
+  async fn instance_state(self: Arc<Self>) -> InstanceState
+  {
+    let mut state = InstanceState {
+      variables: self
+        .variables
+        .read()
+        .await
+        .iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect(),
+      ..Default::default()
+    };
+    for node in self.nodes.values()
+    {
+      match node.get_stored().await
+      {
+        Some(DataValue::Agent(_, id)) =>
+        {
+          if let Ok(history) = self.clone().agent_export_history(&id).await
+          {
+            state.agents.insert(node.static_id, history);
+          }
+        }
+        Some(DataValue::Handle(_)) | None => (),
+        Some(value) =>
+        {
+          state.stored.insert(node.static_id, value);
+        }
+      }
+    }
+    let runners: Vec<(Uuid, Arc<Self>)> = self
+      .complex_nodes
+      .read()
+      .await
+      .iter()
+      .filter_map(|(id, runner)| Some((self.nodes.get(id)?.static_id, runner.clone())))
+      .collect();
+    for (node, runner) in runners
+    {
+      state
+        .runners
+        .insert(node, Box::pin(runner.instance_state()).await);
+    }
+    // restored state whose nodes have not run again yet is carried over
+    let restored = self.restored.lock().unwrap();
+    for (node, history) in &restored.agents
+    {
+      state.agents.entry(*node).or_insert_with(|| history.clone());
+    }
+    for (node, runner) in &restored.runners
+    {
+      state.runners.entry(*node).or_insert_with(|| runner.clone());
+    }
+    state
+  }
+
+  /// The state a checkpoint had for the runner of `node`, if it has not been taken yet
+  pub(crate) fn take_restored_runner(&self, node: &Uuid) -> Option<InstanceState>
+  {
+    self.restored.lock().unwrap().runners.remove(node)
+  }
+
+  /// Gives the agent `id` made by `node` the conversation a checkpoint had for it.
+  pub(crate) async fn restore_agent(
+    self: Arc<Self>,
+    node: &Uuid,
+    id: &Uuid,
+  ) -> Result<(), EvalError>
+  {
+    let history = self.restored.lock().unwrap().agents.remove(node);
+    match history
+    {
+      Some(history) => self.agent_import_history(id, &history).await,
+      None => Ok(()),
+    }
+  }
+
+  /// The nodes with those they take data or control flow from first, ties and loops
+  /// broken by id, so ordered instances poll them the same way every run.
+  fn schedule(&self) -> Vec<&Arc<ExecutionNode>>
+  {
+    let by_id: BTreeMap<Uuid, &Arc<ExecutionNode>> =
+      self.nodes.values().map(|x| (x.static_id, x)).collect();
+    let mut waiting_on: BTreeMap<Uuid, BTreeSet<Uuid>> = by_id
+      .iter()
+      .map(|(id, node)| {
+        let data = node.instance.inputs.iter().map(|(_, x, _)| *x);
+        let control = node
+          .instance
+          .control_flow_in
+          .iter()
+          .flatten()
+          .map(|(x, _)| *x);
+        let before = data
+          .chain(control)
+          .filter(|x| x != id && by_id.contains_key(x))
+          .collect();
+        (*id, before)
+      })
+      .collect();
+    let mut order = Vec::with_capacity(by_id.len());
+    while !waiting_on.is_empty()
+    {
+      let next = waiting_on
+        .iter()
+        .find(|(_, before)| before.is_empty())
+        .or_else(|| waiting_on.iter().next())
+        .map(|(id, _)| *id)
+        .unwrap();
+      waiting_on.remove(&next);
+      for before in waiting_on.values_mut()
+      {
+        before.remove(&next);
+      }
+      order.push(by_id[&next]);
+    }
+    order
+  }
+
+  /// Addresses of the HTTP servers started by this instance that are still running.
+  pub async fn serving(&self) -> Vec<String>
+  {
+    self
+      .http_servers
+      .read()
+      .await
+      .values()
+      .map(|x| x.local_addr().to_string())
+      .collect()
+  }
+
+  pub async fn get_evaluator(&self, path: &str) -> Option<Arc<Self>>
+  {
+    // bound first so the read lock is let go before the write below
+    let cached = self.evaluator_cache.read().await.get(path).cloned();
+    if let Some(e) = cached
+    {
+      // a stale copy is as good as none, the caller loads the file again
+      let stale = self.config().await.graphs.reload_changed && e.changed_on_disk();
+      (!stale).then_some(e)
+    }
+    else if let Some(p) = self.parent.as_ref()
+    {
+      let got = Box::pin(p.clone().get_evaluator(path)).await;
+      if let Some(e) = &got
+      {
+        self
+          .evaluator_cache
+          .write()
+          .await
+          .insert(path.to_string(), e.clone());
+      }
+      got
+    }
+    else
+    {
+      None
+    }
+  }
+
+  /// Parsed template for the Complex graph at `path`, loading it with this instance as
+  /// its parent if no evaluator up the chain has it cached yet. With
+  /// `[graphs] reload_changed` set, a cached graph whose file has changed since is
+  /// loaded again and the UI told with [`NodeStateLogger::reload_string`].
+  pub async fn load_complex(self: Arc<Self>, path: &str) -> Result<Arc<Self>, EvalError>
+  {
+    if let Some(e) = self.get_evaluator(path).await
+    {
+      return Ok(e);
+    }
+    let reloaded = self.is_cached(path).await;
+    let e = self
+      .clone()
+      .open_complex(path, self.text_logger.clone(), self.node_logger.clone())
+      .await?;
+    self.clone().add_evaluator(path, e.clone()).await;
+    if reloaded
+    {
+      tracing::info!(path, "reloaded graph");
+      if let Some(logger) = &self.node_logger
+      {
+        logger.log(&NodeStateLogger::reload_string(path)).await;
+      }
+    }
+    Ok(e)
+  }
+
+  /// The Complex graph at `path` as [`Self::load_complex`] would give it, without
+  /// caching it when read here, so checks before a run do not leave templates behind
+  /// that are parented to this one rather than to the instance running it.
+  async fn read_complex(self: Arc<Self>, path: &str) -> Result<Arc<Self>, EvalError>
+  {
+    match self.get_evaluator(path).await
+    {
+      Some(e) => Ok(e),
+      None => self.open_complex(path, None, None).await,
+    }
+  }
+
+  /// Loads the Complex graph at `path` with this instance as its parent, from the
+  /// bundle this graph came from if it has the graph, otherwise from its file
+  async fn open_complex(
+    self: Arc<Self>,
+    path: &str,
+    text_logger: Option<Arc<TextLogger>>,
+    node_logger: Option<Arc<NodeLogger>>,
+  ) -> Result<Arc<Self>, EvalError>
+  {
+    if let Some(graph) = self.bundle.as_ref().and_then(|x| x.graphs.get(path))
+    {
+      let graph = graph.clone();
+      return Self::prepare(
+        graph,
+        path.to_string(),
+        None,
+        Some(self),
+        text_logger,
+        node_logger,
+        None,
+      );
+    }
+    self.check_confined(path).await?;
+    Evaluator::new(path.to_string(), Some(self), text_logger, node_logger)
+  }
+
+  /// This graph and the graphs it uses, as Complex nodes, route handlers or tools,
+  /// read again from their files as a [`Bundle`] that runs without them. Fails as [`Self::check_cycles`] does, or if
+  /// one of the graphs fails to load.
+  pub async fn bundle(self: Arc<Self>) -> Result<Bundle, EvalError>
+  {
+    self.clone().check_cycles().await?;
+    // keys are relative to this graph's directory where they can be
+    let base = std::fs::canonicalize(Path::new(".").join(&self.my_path)).ok();
+    let key = |path: &str| {
+      let file = std::fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+      base
+        .as_ref()
+        .and_then(|base| file.strip_prefix(base).ok())
+        .unwrap_or(&file)
+        .to_string_lossy()
+        .into_owned()
+    };
+    let root = key(&self.graph_path);
+    let mut graphs = BTreeMap::new();
+    let mut queued = HashSet::from([root.clone()]);
+    let mut pending = vec![self];
+    while let Some(graph) = pending.pop()
+    {
+      let mut complex = Complex::load(&graph.graph_path)?;
+      for instance in complex.instances.values_mut()
+      {
+        for path in graph_paths(&mut instance.node_type)
+        {
+          let resolved = graph.resolve_complex(path).await;
+          *path = key(&resolved);
+          if queued.insert(path.clone())
+          {
+            pending.push(graph.clone().read_complex(&resolved).await?);
+          }
+        }
+      }
+      graphs.insert(key(&graph.graph_path), complex);
+    }
+    Ok(Bundle { root, graphs })
+  }
+
+  /// With `[graphs] confine` set, fails unless the Complex graph at `path` is in the
+  /// project directory or a search path. Paths are compared once symlinks and `..`
+  /// are resolved, and ones that do not exist are left to fail loading.
+  async fn check_confined(&self, path: &str) -> Result<(), EvalError>
+  {
+    let config = self.config().await;
+    if !config.graphs.confine
+    {
+      return Ok(());
+    }
+    let Ok(file) = std::fs::canonicalize(path)
+    else
+    {
+      return Ok(());
+    };
+    let project = match &config.graphs.project_dir
+    {
+      Some(dir) => dir.clone(),
+      None => self.root().my_path.clone(),
+    };
+    let allowed = std::iter::once(&project)
+      .chain(&config.search_paths)
+      // a graph in the working directory has an empty one
+      .filter_map(|dir| std::fs::canonicalize(Path::new(".").join(dir)).ok())
+      .any(|dir| file.starts_with(dir));
+    match allowed
+    {
+      true => Ok(()),
+      false =>
+      {
+        Err(EvalError::ComplexOutsideProject {
+          path: path.to_string(),
+          project: project.display().to_string(),
+        })
+      }
+    }
+  }
+
+  /// Whether the file this graph was read from has been modified or removed since.
+  /// Graphs not read from a file never change.
+  pub fn changed_on_disk(&self) -> bool
+  {
+    self.modified.is_some()
+      && std::fs::metadata(&self.graph_path)
+        .and_then(|x| x.modified())
+        .ok()
+        != self.modified
+  }
+
+  async fn is_cached(&self, path: &str) -> bool
+  {
+    let mut eval = Some(self);
+    while let Some(e) = eval
+    {
+      if e.evaluator_cache.read().await.contains_key(path)
+      {
+        return true;
+      }
+      eval = e.parent.as_deref();
+    }
+    false
+  }
+
+  /// Forgets the cached Complex graph at `path` here and up the chain, so it is read
+  /// from its file again the next time it runs. Returns whether anything was cached.
+  pub async fn invalidate_complex(&self, path: &str) -> bool
+  {
+    let mut removed = false;
+    let mut eval = Some(self);
+    while let Some(e) = eval
+    {
+      removed |= e.evaluator_cache.write().await.remove(path).is_some();
+      eval = e.parent.as_deref();
+    }
+    removed
+  }
+
+  /// Forgets every cached Complex graph here and up the chain, returning their paths.
+  pub async fn clear_complex_cache(&self) -> Vec<String>
+  {
+    let mut paths = HashSet::new();
+    let mut eval = Some(self);
+    while let Some(e) = eval
+    {
+      paths.extend(
+        e.evaluator_cache
+          .write()
+          .await
+          .drain()
+          .map(|(path, _)| path),
+      );
+      eval = e.parent.as_deref();
+    }
+    let mut paths: Vec<String> = paths.into_iter().collect();
+    paths.sort();
+    paths
+  }
+
+  /// Routes `method` and `path` on the HTTP server at `addr`, starting the server if
+  /// this instance has not already. Every request runs a fresh instance of the Complex
+  /// graph at `complex`, see [`Self::handle_request`]. Returns the address the server
+  /// is bound to.
+  pub async fn register_route(
+    self: Arc<Self>,
+    addr: &str,
+    method: &str,
+    path: &str,
+    complex: &str,
+  ) -> Result<String, EvalError>
+  {
+    let rel = self.resolve_complex(complex).await;
+    // loaded now so a broken graph fails the Route node rather than every request
+    self.clone().load_complex(&rel).await?;
+
+    let server = {
+      let mut servers = self.http_servers.write().await;
+      match servers.get(addr)
+      {
+        Some(x) => x.clone(),
+        None =>
+        {
+          let server = Arc::new(HttpServer::bind(addr).await?);
+          servers.insert(addr.to_string(), server.clone());
+          server
+        }
+      }
+    };
+
+    let eval = self.clone();
+    let handler: RouteHandler = Arc::new(move |request| {
+      let (eval, rel) = (eval.clone(), rel.clone());
+      Box::pin(async move {
+        // looked up per request so an edited graph is served once reloaded
+        let served = match eval.load_complex(&rel).await
+        {
+          Ok(template) => template.handle_request(request).await,
+          Err(e) => Err(e),
+        };
+        served.map_err(|e| format!("{e:?}"))
+      })
+    });
+    server.add_route(method, path, handler).await;
+    Ok(server.local_addr().to_string())
+  }
+
+  /// Answers one request with a new instance of this graph, which gets the method,
+  /// path, query string, headers and body as inputs. A graph with one output answers
+  /// `200` with it as the body, one with three answers with them as the status,
+  /// headers and body.
+  async fn handle_request(self: Arc<Self>, request: HttpRequest)
+    -> Result<HttpResponse, EvalError>
+  {
+    let mut headers: IndexMap<_, _> = request
+      .headers
+      .into_iter()
+      .map(|(k, v)| (k, DataValue::String(v)))
+      .collect();
+    headers.sort_keys();
+    let inputs = vec![
+      DataValue::String(request.method),
+      DataValue::String(request.path),
+      DataValue::String(request.query),
+      DataValue::Object(headers),
+      DataValue::String(request.body),
+    ];
+    let instance = self.instantiate(inputs).await;
+    let outputs = instance.get_outputs().await;
+    instance.shutdown().await;
+
+    let outputs = outputs?;
+    match outputs.as_slice()
+    {
+      [body] =>
+      {
+        Ok(HttpResponse {
+          status: 200,
+          headers: HashMap::new(),
+          body: body.to_string(),
+        })
+      }
+      [DataValue::Integer(status), DataValue::Object(headers), body] =>
+      {
+        Ok(HttpResponse {
+          status: *status as u16,
+          headers: headers
+            .iter()
+            .map(|(k, v)| (k.clone(), v.to_string()))
+            .collect(),
+          body: body.to_string(),
+        })
+      }
+      _ =>
+      {
+        Err(EvalError::IncorrectTyping {
+          got: outputs.iter().map(|x| x.get_type()).collect(),
+          expected: vec![DataType::Integer, DataType::Object, DataType::String],
+        })
+      }
+    }
+  }
+
+  pub async fn get_complex_runner(&self, id: &Uuid) -> Option<Arc<Self>>
+  {
+    self.complex_nodes.read().await.get(id).cloned()
+  }
+
+  /// Keeps the runner of a Complex node so it is shut down with this instance. A runner
+  /// started while this instance was shutting down is shut down at once instead, as is
+  /// one it replaces.
+  pub async fn add_complex_runner(&self, instance: Arc<Self>, id: &Uuid)
+  {
+    let mut runners = self.complex_nodes.write().await;
+    // teardown cancels before taking the runners, so a runner added after it did sees
+    // the cancellation here
+    let stale = match self.cancel.is_cancelled()
+    {
+      true => Some(instance),
+      false => runners.insert(*id, instance),
+    };
+    drop(runners);
+    if let Some(stale) = stale
+    {
+      Box::pin(stale.shutdown()).await;
+    }
+  }
+
+  /// Shuts down the complex runner of a node, so the next run starts a fresh instance.
+  pub async fn drop_complex_runner(&self, id: &Uuid)
+  {
+    let runner = self.complex_nodes.write().await.remove(id);
+    if let Some(runner) = runner
+    {
+      Box::pin(runner.shutdown()).await;
+    }
+  }
+
+  pub async fn add_evaluator(self: Arc<Self>, path: &str, eval: Arc<Self>)
+  {
+    self
+      .evaluator_cache
+      .write()
+      .await
+      .insert(path.to_string(), eval.clone());
+    if let Some(p) = self.parent.as_ref()
+    {
+      Box::pin(p.clone().add_evaluator(path, eval)).await;
+    }
+  }
+
+  pub async fn register_io(&self, io: IoObject) -> Uuid
+  {
+    self.insert_io(IoEntry::Stream(io)).await
+  }
+
+  pub async fn register_listener(&self, listener: TcpListener) -> Uuid
+  {
+    self.insert_io(IoEntry::Listener(Arc::new(listener))).await
+  }
+
+  pub async fn register_udp(&self, socket: UdpSocket) -> Uuid
+  {
+    self.insert_io(IoEntry::Udp(Arc::new(socket))).await
+  }
+
+  pub async fn register_websocket(&self, socket: WebSocket) -> Uuid
+  {
+    self.insert_io(IoEntry::WebSocket(Arc::new(socket))).await
+  }
+
+  /// Starts `program` with `args` and extra `env` variables, its stderr going to ours
+  /// and [`WORKSPACE_ENV`] set to the run's workspace. Returns handles for the process,
+  /// its stdin and its stdout; the process is killed if it is still running when the
+  /// instance shuts down.
+  #[cfg(feature = "subprocess")]
+  pub async fn spawn_process(
+    &self,
+    program: &str,
+    args: &[String],
+    env: &HashMap<String, String>,
+  ) -> Result<(Uuid, Uuid, Uuid), EvalError>
+  {
+    let mut child = tokio::process::Command::new(program)
+      .args(args)
+      .env(WORKSPACE_ENV, self.workspace().await?)
+      .envs(env)
+      .stdin(std::process::Stdio::piped())
+      .stdout(std::process::Stdio::piped())
+      .kill_on_drop(true)
+      .spawn()?;
+    let stdin = child.stdin.take().expect("stdin is piped");
+    let stdout = child.stdout.take().expect("stdout is piped");
+    // each pipe only goes one way, reading stdin or writing stdout hits EOF or a sink
+    let stdin = self
+      .register_io(Box::pin(tokio::io::join(tokio::io::empty(), stdin)))
+      .await;
+    let stdout = self
+      .register_io(Box::pin(tokio::io::join(stdout, tokio::io::sink())))
+      .await;
+    let process = self
+      .insert_io(IoEntry::Process(Arc::new(ChildProcess {
+        child: tokio::sync::Mutex::new(child),
+        stdin,
+      })))
+      .await;
+    Ok((process, stdin, stdout))
+  }
+
+  /// Graphs with process nodes do not load without the `subprocess` feature, so this
+  /// is never reached.
+  #[cfg(not(feature = "subprocess"))]
+  pub async fn spawn_process(
+    &self,
+    _program: &str,
+    _args: &[String],
+    _env: &HashMap<String, String>,
+  ) -> Result<(Uuid, Uuid, Uuid), EvalError>
+  {
+    Err(std::io::Error::from(std::io::ErrorKind::Unsupported).into())
+  }
+
+  /// Closes the process's stdin, so programs reading until EOF can finish, and waits
+  /// for it to exit. Returns its exit code, `None` if it was ended by a signal.
+  #[cfg(feature = "subprocess")]
+  pub async fn wait_process(self: Arc<Self>, id: &Uuid) -> Result<Option<i32>, EvalError>
+  {
+    let _io = self.tasks.track_io();
+    let mut guard = self.find_io_registry_mut(id).await?;
+    let process = match guard.get(id)
+    {
+      Some(IoEntry::Process(process)) => process.clone(),
+      Some(_) => return Err(EvalError::IoKindMismatch(*id)),
+      None => return Err(EvalError::IoNotFound(*id)),
+    };
+    guard.remove(&process.stdin);
+    drop(guard);
+    let status = process.child.lock().await.wait().await?;
+    Ok(status.code())
+  }
+
+  #[cfg(not(feature = "subprocess"))]
+  pub async fn wait_process(self: Arc<Self>, _id: &Uuid) -> Result<Option<i32>, EvalError>
+  {
+    Err(std::io::Error::from(std::io::ErrorKind::Unsupported).into())
+  }
+
+  /// Removes the IO object behind `id` from whichever registry holds it, so later uses
+  /// of the handle fail with [`EvalError::IoNotFound`]. The object itself is returned
+  /// for the caller to close or drop.
+  pub async fn unregister_io(self: &Arc<Self>, id: &Uuid) -> Result<IoEntry, EvalError>
+  {
+    self
+      .find_io_registry_mut(id)
+      .await?
+      .remove(id)
+      .ok_or(EvalError::IoNotFound(*id))
+  }
+
+  /// Closes the IO object behind `id` and unregisters it. Streams are flushed and shut
+  /// down, sending FIN on TCP connections, WebSockets send a close frame and processes
+  /// are killed along with their stdin.
+  pub async fn close_io(self: Arc<Self>, id: &Uuid) -> Result<(), EvalError>
+  {
+    let _io = self.tasks.track_io();
+    match self.unregister_io(id).await?
+    {
+      IoEntry::Stream(mut io) => io.shutdown().await?,
+      IoEntry::WebSocket(socket) => socket.close().await?,
+      #[cfg(feature = "subprocess")]
+      IoEntry::Process(process) =>
+      {
+        let _ = self.unregister_io(&process.stdin).await;
+        let mut child = process.child.lock().await;
+        child.start_kill()?;
+        child.wait().await?;
+      }
+      IoEntry::Listener(_) | IoEntry::Udp(_) => (),
+    }
+    Ok(())
+  }
+
+  /// Flushes anything buffered for the stream or WebSocket behind `id`.
+  pub async fn flush_io(self: Arc<Self>, id: &Uuid) -> Result<(), EvalError>
+  {
+    let _io = self.tasks.track_io();
+    let mut guard = self.find_io_registry_mut(id).await?;
+    if let Some(IoEntry::WebSocket(socket)) = guard.get(id)
+    {
+      let socket = socket.clone();
+      drop(guard);
+      return Ok(socket.flush().await?);
+    }
+    stream_mut(&mut guard, id)?.flush().await?;
+    Ok(())
+  }
+
+  async fn insert_io(&self, io: IoEntry) -> Uuid
+  {
+    let mut guard = self.io_registry.write().await;
+    let mut ret = Uuid::new_v4();
+    while guard.contains_key(&ret)
+    {
+      ret = Uuid::new_v4();
+    }
+    guard.insert(ret, io);
+    ret
+  }
+
+  /// Waits for a connection on the listener behind `id` and registers it as a new
+  /// stream, returning its handle and the peer address. The registry is not held while
+  /// waiting, so other handles stay usable.
+  pub async fn accept(self: Arc<Self>, id: &Uuid) -> Result<(Uuid, SocketAddr), EvalError>
+  {
+    let _io = self.tasks.track_io();
+    let listener = match self.find_io_registry_mut(id).await?.get(id)
+    {
+      Some(IoEntry::Listener(listener)) => listener.clone(),
+      Some(_) => return Err(EvalError::IoKindMismatch(*id)),
+      None => return Err(EvalError::IoNotFound(*id)),
+    };
+    let (stream, addr) = listener.accept().await?;
+    Ok((self.register_io(Box::pin(stream)).await, addr))
+  }
+
+  async fn find_io_registry_mut(
+    self: &Arc<Self>,
+    id: &Uuid,
+  ) -> Result<RwLockWriteGuard<'_, HashMap<Uuid, IoEntry>>, EvalError>
+  {
+    if self.io_registry.read().await.contains_key(id)
+    {
+      return Ok(self.io_registry.write().await);
+    }
+
+    let mut current = &self.parent;
+    while let Some(parent) = &current
+    {
+      if parent.io_registry.read().await.contains_key(id)
+      {
+        return Ok(parent.io_registry.write().await);
+      }
+      current = &parent.parent
+    }
+    Err(EvalError::IoNotFound(*id))
+  }
+
+  async fn find_udp(self: &Arc<Self>, id: &Uuid) -> Result<Arc<UdpSocket>, EvalError>
+  {
+    match self.find_io_registry_mut(id).await?.get(id)
+    {
+      Some(IoEntry::Udp(socket)) => Ok(socket.clone()),
+      Some(_) => Err(EvalError::IoKindMismatch(*id)),
+      None => Err(EvalError::IoNotFound(*id)),
+    }
+  }
+
+  /// Sends `buf` as a single datagram from the UDP socket behind `id`, returning the
+  /// number of bytes sent.
+  pub async fn send_to(
+    self: Arc<Self>,
+    id: &Uuid,
+    buf: &[u8],
+    addr: &str,
+  ) -> Result<usize, EvalError>
+  {
+    let _io = self.tasks.track_io();
+    let socket = self.find_udp(id).await?;
+    socket.send_to(buf, addr).await.map_err(EvalError::from)
+  }
+
+  /// Waits for a datagram of at most `size` bytes on the UDP socket behind `id`,
+  /// returning it along with the sender's address.
+  pub async fn recv_from(
+    self: Arc<Self>,
     id: &Uuid,
-  ) -> Result<RwLockWriteGuard<'_, HashMap<Uuid, DynAgent>>, EvalError>
+    size: usize,
+  ) -> Result<(Vec<u8>, SocketAddr), EvalError>
+  {
+    let _io = self.tasks.track_io();
+    let socket = self.find_udp(id).await?;
+    let mut buf = vec![0; size];
+    let (count, addr) = socket.recv_from(&mut buf).await?;
+    buf.truncate(count);
+    Ok((buf, addr))
+  }
+
+  async fn find_websocket(self: &Arc<Self>, id: &Uuid) -> Result<Arc<WebSocket>, EvalError>
   {
-    if self.agent_registry.read().await.contains_key(id)
+    match self.find_io_registry_mut(id).await?.get(id)
     {
-      return Ok(self.agent_registry.write().await);
+      Some(IoEntry::WebSocket(socket)) => Ok(socket.clone()),
+      Some(_) => Err(EvalError::IoKindMismatch(*id)),
+      None => Err(EvalError::IoNotFound(*id)),
     }
+  }
 
-    let mut current = &self.parent;
-    while let Some(parent) = &current
+  pub async fn send_frame(self: Arc<Self>, id: &Uuid, frame: Frame) -> Result<(), EvalError>
+  {
+    let _io = self.tasks.track_io();
+    let socket = self.find_websocket(id).await?;
+    socket.send(frame).await.map_err(EvalError::from)
+  }
+
+  /// Waits for the next text or binary frame on the WebSocket behind `id`, `None` once
+  /// the connection is closed.
+  pub async fn recv_frame(self: Arc<Self>, id: &Uuid) -> Result<Option<Frame>, EvalError>
+  {
+    let _io = self.tasks.track_io();
+    let socket = self.find_websocket(id).await?;
+    socket.recv().await.map_err(EvalError::from)
+  }
+
+  pub async fn read_until(self: Arc<Self>, id: &Uuid, pattern: &[u8])
+    -> Result<Vec<u8>, EvalError>
+  {
+    let _io = self.tasks.track_io();
+    let mut guard = self.find_io_registry_mut(id).await?;
+    let io = stream_mut(&mut guard, id)?;
+    read_until_generic(io, pattern).await
+  }
+
+  /// Reads into the start of `buf`, returning how many bytes were read
+  pub async fn read_bytes(self: Arc<Self>, id: &Uuid, buf: &mut [u8]) -> Result<usize, EvalError>
+  {
+    let _io = self.tasks.track_io();
+    let mut guard = self.find_io_registry_mut(id).await?;
+    let io = stream_mut(&mut guard, id)?;
+    io.read(buf).await.map_err(EvalError::from)
+  }
+
+  pub async fn write_bytes(self: Arc<Self>, id: &Uuid, buf: &[u8]) -> Result<(), EvalError>
+  {
+    let _io = self.tasks.track_io();
+    let mut guard = self.find_io_registry_mut(id).await?;
+    let io = stream_mut(&mut guard, id)?;
+
+    io.write_all(buf).await.map_err(EvalError::from)
+  }
+
+  pub fn find_node(&self, id: &Uuid) -> Result<Arc<ExecutionNode>, EvalError>
+  {
+    self
+      .nodes
+      .get(&Uuid::new_v5(&self.scope_id, id.as_bytes()))
+      .cloned()
+      .ok_or(EvalError::NodeNotFound(*id))
+  }
+
+  /// The name of the node with `id` in the graph file, if it has one
+  pub fn node_name(&self, id: &Uuid) -> Option<&str>
+  {
+    self
+      .nodes
+      .get(&Uuid::new_v5(&self.scope_id, id.as_bytes()))
+      .and_then(|x| x.instance.name.as_deref())
+  }
+
+  /// Runs the node with `id` in the graph file again, as if control flow had reached
+  /// it, and returns the outputs of that run. A node whose latest outputs have not all
+  /// been read yet only runs once they are.
+  pub async fn rerun_node(self: Arc<Self>, id: &Uuid) -> Result<Vec<DataValue>, EvalError>
+  {
+    let node = self.find_node(id)?;
+    Ok(node.rerun(self).await)
+  }
+
+  /// Creates an agent for the graph, or a [`MockAgent`] answering with the configured
+  /// replies during a dry run.
+  pub async fn register_agent(&self, node: Uuid, agent_type: AgentType, args: AgentArgs) -> Uuid
+  {
+    let config = self.config().await;
+    let model = args.model.clone();
+    let (provider, agent): (_, DynAgent) = match config.dry_run.enabled
+    {
+      true =>
+      {
+        let replies = config
+          .dry_run
+          .responses
+          .get(&args.model)
+          .cloned()
+          .unwrap_or_default();
+        let template = config.dry_run.default_response.clone();
+        (
+          AgentType::Mock.provider(),
+          Box::pin(MockAgent::scripted(args.model, replies, template)),
+        )
+      }
+      false =>
+      {
+        (
+          agent_type.provider(),
+          agent_type.clone().create(args, &config.providers),
+        )
+      }
+    };
+    let agent = RegisteredAgent {
+      provider,
+      agent,
+      origin: (self.graph_path.clone(), node),
+      agent_type,
+      model,
+      tools: RwLock::new(vec![]),
+      recorded_at: RwLock::new(vec![]),
+    };
+    agent.stamp().await;
+    let id = Uuid::new_v4();
+    self
+      .agent_registry
+      .write()
+      .await
+      .insert(id, Arc::new(agent));
+    id
+  }
+
+  async fn find_agent(self: &Arc<Self>, id: &Uuid) -> Result<Arc<RegisteredAgent>, EvalError>
+  {
+    let mut current = Some(self);
+    while let Some(eval) = current
     {
-      if parent.agent_registry.read().await.contains_key(id)
+      if let Some(agent) = eval.agent_registry.read().await.get(id)
       {
-        return Ok(parent.agent_registry.write().await);
+        return Ok(agent.clone());
       }
-      current = &parent.parent;
+      current = eval.parent.as_ref();
+    }
+    Err(EvalError::AgentNotFound(*id))
+  }
+
+  /// Sends a message to an agent. While it answers with calls to its tools, they are
+  /// run and their results sent back, so its last response is its final answer. Each
+  /// request waits its turn under the configured
+  /// [`AgentLimits`](crate::config::AgentLimits), by `priority` if they are ordered by it.
+  pub async fn agent_send_message(
+    self: Arc<Self>,
+    id: &Uuid,
+    body: String,
+    priority: i64,
+  ) -> Result<(), EvalError>
+  {
+    let registered = self.find_agent(id).await?;
+    let agent = &registered.agent;
+    let limiter = self.agent_limiter().await;
+    let budget = &self.root().budget;
+    budget.spend(Limit::AgentCalls, 1)?;
+    let permit = limiter.acquire(registered.provider, priority).await;
+    let usage = agent.send_chat(agent.create_body(body).await).await?;
+    drop(permit);
+    registered.stamp().await;
+    self.record_usage(&registered, usage)?;
+    for _ in 0..MAX_TOOL_CALLS
+    {
+      let Some(call) = agent
+        .get_last_response()
+        .await
+        .and_then(|x| x.get_function_call())
+      else
+      {
+        break;
+      };
+      let tool = registered
+        .tools
+        .read()
+        .await
+        .iter()
+        .find(|x| x.name() == call.name)
+        .cloned();
+      let result = match tool
+      {
+        Some(tool) =>
+        {
+          self
+            .clone()
+            .run_tool(&tool, &call.args)
+            .await
+            .unwrap_or_else(|e| format!("error: {e}"))
+        }
+        None => format!("error: no tool named {}", call.name),
+      };
+      budget.spend(Limit::AgentCalls, 1)?;
+      let permit = limiter.acquire(registered.provider, priority).await;
+      let usage = agent
+        .send_chat(agent.create_function_result(call.name, result).await)
+        .await?;
+      drop(permit);
+      registered.stamp().await;
+      self.record_usage(&registered, usage)?;
     }
-    Err(EvalError::AgentNotFound(id.clone()))
+    Ok(())
   }
-  pub async fn agent_send_message(self: Arc<Self>, id: &Uuid, body: String)
-    -> Result<(), EvalError>
+
+  /// Counts one call of `registered` in the run's usage, failing once the run has
+  /// used more tokens than its `max_agent_tokens`
+  fn record_usage(&self, registered: &RegisteredAgent, usage: TokenUsage) -> Result<(), EvalError>
   {
-    let agent = &self.find_agent_registry_mut(id).await?[id];
+    let root = self.root();
+    let (graph, node) = &registered.origin;
+    root.usage.record(
+      graph,
+      *node,
+      &registered.agent_type,
+      &registered.model,
+      usage,
+    );
+    root.budget.spend(Limit::AgentTokens, usage.total())
+  }
 
-    agent
-      .send_chat(agent.create_body(body).await)
-      .await
-      .map_err(EvalError::from)
+  /// The calls, tokens and cost of the run's agents so far, by the `Create` node that
+  /// made them and priced by the config's `[pricing]`
+  pub async fn usage(&self) -> UsageReport
+  {
+    self.root().usage.report(&self.config().await.pricing)
+  }
+
+  /// Attaches tools to an agent, failing if the sandbox does not allow one of them.
+  pub async fn agent_attach_tools(
+    self: Arc<Self>,
+    id: &Uuid,
+    tools: &[Tool],
+  ) -> Result<(), EvalError>
+  {
+    let sandbox = self.config().await.sandbox.clone();
+    if let Some(permission) = tools
+      .iter()
+      .filter_map(Tool::permission)
+      .find(|x| !sandbox.allows(*x))
+    {
+      return Err(EvalError::NotPermitted(permission));
+    }
+    let registered = self.find_agent(id).await?;
+    registered
+      .agent
+      .add_functions(tools.iter().map(Tool::definition).collect())
+      .await;
+    registered.tools.write().await.extend(tools.iter().cloned());
+    Ok(())
+  }
+
+  /// Runs a tool with the JSON arguments an agent called it with, returning the text to
+  /// answer the call with.
+  async fn run_tool(self: Arc<Self>, tool: &Tool, args: &str) -> Result<String, EvalError>
+  {
+    let args: serde_json::Value =
+      serde_json::from_str(args).map_err(|e| EvalError::InvalidToolArguments(e.to_string()))?;
+    let arg = |name: &str| {
+      args[name]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| EvalError::InvalidToolArguments(format!("missing string {name:?}")))
+    };
+    let config = self.config().await;
+    match tool
+    {
+      Tool::ReadFile =>
+      {
+        let path = arg("path")?;
+        config.sandbox.check_path(&path)?;
+        Ok(tokio::fs::read_to_string(path).await?)
+      }
+      Tool::WriteFile =>
+      {
+        let path = arg("path")?;
+        config.sandbox.check_path(&path)?;
+        tokio::fs::write(path, arg("contents")?).await?;
+        Ok("done".to_string())
+      }
+      Tool::HttpGet =>
+      {
+        let timeout = config.timeouts.http_request();
+        let url = arg("url")?;
+        config.sandbox.check_url(&url)?;
+        let headers = HashMap::new();
+        let request = crate::http::send_request(&config.sandbox, "GET", &url, &headers, None);
+        Ok(with_timeout(timeout, request).await?.body)
+      }
+      Tool::RunSubgraph(path) =>
+      {
+        let inputs = match &args["inputs"]
+        {
+          serde_json::Value::Null => vec![],
+          x =>
+          {
+            serde_json::from_value(x.clone())
+              .map_err(|e| EvalError::InvalidToolArguments(e.to_string()))?
+          }
+        };
+        let path = self.resolve_complex(path).await;
+        let instance = self
+          .clone()
+          .load_complex(&path)
+          .await?
+          .start_nested(
+            &self,
+            Uuid::new_v5(&Uuid::NAMESPACE_URL, path.as_bytes()),
+            inputs,
+            InstanceState::default(),
+          )
+          .await?;
+        let outputs = instance.get_outputs().await;
+        instance.shutdown().await;
+        Ok(serde_json::to_string(&outputs?).unwrap_or_default())
+      }
+    }
   }
 
   pub async fn agent_get_last_message(
@@ -474,11 +2311,52 @@ impl<TextLogger: Logger, NodeLogger: Logger> Evaluator<TextLogger, NodeLogger>
     id: &Uuid,
   ) -> Result<Option<ChatBody>, EvalError>
   {
-    Ok(
-      self.find_agent_registry_mut(id).await?[id]
-        .get_last_response()
-        .await,
-    )
+    Ok(self.find_agent(id).await?.agent.get_last_response().await)
+  }
+
+  /// Exports an agent's conversation as an Object that can be stored and later passed
+  /// to [`Self::agent_import_history`], possibly in another process.
+  pub async fn agent_export_history(self: Arc<Self>, id: &Uuid) -> Result<DataValue, EvalError>
+  {
+    let history = self.find_agent(id).await?.agent.get_history().await;
+    Ok(HistoryMessage::to_value(history))
+  }
+
+  pub async fn agent_import_history(
+    self: Arc<Self>,
+    id: &Uuid,
+    history: &DataValue,
+  ) -> Result<(), EvalError>
+  {
+    let messages = HistoryMessage::from_value(history).ok_or(EvalError::IncorrectTyping {
+      got: vec![history.get_type()],
+      expected: vec![DataType::Object],
+    })?;
+    let registered = self.find_agent(id).await?;
+    let count = messages.len();
+    registered.agent.set_history(messages).await?;
+    *registered.recorded_at.write().await = vec![None; count];
+    Ok(())
+  }
+
+  /// Renders an agent's conversation as a transcript, see [`render_transcript`].
+  pub async fn agent_transcript(
+    self: Arc<Self>,
+    id: &Uuid,
+    format: &TranscriptFormat,
+  ) -> Result<String, EvalError>
+  {
+    let registered = self.find_agent(id).await?;
+    let recorded_at = registered.recorded_at.read().await.clone();
+    let entries: Vec<TranscriptEntry> = registered
+      .agent
+      .get_history()
+      .await
+      .into_iter()
+      .enumerate()
+      .map(|(i, x)| TranscriptEntry::new(x.role, x.content, recorded_at.get(i).copied().flatten()))
+      .collect();
+    Ok(render_transcript(&entries, format))
   }
 
   pub async fn get_variable(self: Arc<Self>, name: &str) -> DataValue
@@ -495,9 +2373,388 @@ impl<TextLogger: Logger, NodeLogger: Logger> Evaluator<TextLogger, NodeLogger>
     }
   }
 
-  pub async fn set_variable(self: Arc<Self>, name: String, value: DataValue)
+  /// Sets a variable, failing if the variables of the run then hold more than its
+  /// `max_stored_bytes`
+  pub async fn set_variable(
+    self: Arc<Self>,
+    name: String,
+    value: DataValue,
+  ) -> Result<(), EvalError>
+  {
+    let budget = &self.root().budget;
+    let size = stored_size(&value);
+    if let Some(old) = self.variables.write().await.insert(name, value)
+    {
+      budget.release(Limit::StoredBytes, stored_size(&old));
+    }
+    budget.spend(Limit::StoredBytes, size)
+  }
+
+  /// The top-level instance this one was started under, possibly itself
+  fn root(&self) -> &Self
+  {
+    let mut root = self;
+    while let Some(parent) = &root.parent
+    {
+      root = parent;
+    }
+    root
+  }
+
+  pub async fn set_params(&self, params: HashMap<String, String>)
+  {
+    *self.params.write().await = params;
+  }
+
+  pub async fn set_config(&self, config: Config)
+  {
+    *self.agent_limiter.write().await = Arc::new(AgentLimiter::new(config.agents.clone()));
+    *self.config.write().await = Arc::new(config);
+  }
+
+  async fn agent_limiter(&self) -> Arc<AgentLimiter>
+  {
+    self.root().agent_limiter.read().await.clone()
+  }
+
+  /// Fails with [`EvalError::ValueTooLarge`] if any of `node`'s outputs is over the
+  /// configured limits.
+  pub async fn check_limits(&self, node: Uuid, outputs: &[DataValue]) -> Result<(), EvalError>
+  {
+    let config = self.config().await;
+    match outputs.iter().find_map(|x| config.limits.exceeded_by(x))
+    {
+      Some(reason) => Err(EvalError::ValueTooLarge { node, reason }),
+      None => Ok(()),
+    }
+  }
+
+  /// Makes the `Random` nodes of every instance made from this one produce the same
+  /// values each run, and restarts this instance's sequences. Each node draws from a
+  /// generator of its own, so the values do not depend on the order nodes run in.
+  pub async fn set_seed(&self, seed: u64)
+  {
+    *self.rngs.lock().unwrap() = NodeRngs::new(Some(seed));
+  }
+
+  /// Runs `f` with the random generator of the node `node`, by its id in the graph.
+  pub fn with_rng<T>(&self, node: Uuid, f: impl FnOnce(&mut StdRng) -> T) -> T
+  {
+    let key = Uuid::new_v5(&self.lineage, node.as_bytes());
+    f(self.root().rngs.lock().unwrap().get(key))
+  }
+
+  /// Makes [`Self::get_outputs`] fail with [`EvalError::TimedOut`] if the graph has not
+  /// finished `limit` from now.
+  pub async fn set_deadline(&self, limit: Duration)
+  {
+    *self.deadline.write().await = Some(Instant::now() + limit);
+  }
+
+  /// Runs the nodes of every instance made from this one on the instance's own task,
+  /// one at a time in an order fixed by the graph, instead of racing them on the
+  /// runtime. With a single threaded runtime and [`Self::set_seed`], a graph not
+  /// depending on timing or the outside world then runs the same way every time.
+  pub async fn set_deterministic(&self, deterministic: bool)
+  {
+    *self.deterministic.write().await = deterministic;
+  }
+
+  /// Keeps the run's workspace on shutdown instead of removing it, printing where it
+  /// is.
+  pub async fn set_keep_workspace(&self, keep: bool)
+  {
+    *self.keep_workspace.write().await = keep;
+  }
+
+  /// The scratch directory shared by every instance in the run, made under the system
+  /// temp directory the first time it is asked for. Subprocesses are told it through
+  /// [`WORKSPACE_ENV`].
+  pub async fn workspace(&self) -> Result<PathBuf, EvalError>
+  {
+    let root = self.root();
+    let mut workspace = root.workspace.write().await;
+    if let Some(path) = &*workspace
+    {
+      return Ok(path.clone());
+    }
+    let path = std::env::temp_dir().join(format!("agentnodes-{}", root.run_id));
+    tokio::fs::create_dir_all(&path).await?;
+    *workspace = Some(path.clone());
+    Ok(path)
+  }
+
+  async fn remove_workspace(&self)
+  {
+    let Some(path) = self.workspace.write().await.take()
+    else
+    {
+      return;
+    };
+    if *self.keep_workspace.read().await
+    {
+      tracing::info!(path = %path.display(), "kept workspace");
+    }
+    else if let Err(e) = tokio::fs::remove_dir_all(&path).await
+    {
+      tracing::warn!(path = %path.display(), error = %e, "could not remove workspace");
+    }
+  }
+
+  /// Waits for the run's deadline, forever if it has none.
+  pub async fn wait_for_deadline(&self)
+  {
+    let deadline = *self.root().deadline.read().await;
+    match deadline
+    {
+      Some(deadline) => tokio::time::sleep_until(deadline).await,
+      None => std::future::pending().await,
+    }
+  }
+
+  /// The configuration of the run this instance belongs to
+  pub async fn config(&self) -> Arc<Config>
+  {
+    self.root().config.read().await.clone()
+  }
+
+  /// Registers hooks called around every node of the instances made from this one
+  /// afterwards, after any added before them.
+  pub async fn add_hooks(&self, hooks: Arc<dyn NodeHooks>)
+  {
+    self.hooks.write().await.push(hooks);
+  }
+
+  /// Records every node run of the instances made from this one afterwards, their
+  /// inputs, outputs and timings, see [`TraceRecorder`].
+  pub async fn record_trace(&self) -> Arc<TraceRecorder>
+  {
+    let recorder = Arc::new(TraceRecorder::default());
+    self.add_hooks(recorder.clone()).await;
+    recorder
+  }
+
+  /// Counts runs, errors, latencies and IO bytes per node of the instances made from
+  /// this one afterwards, see [`Metrics`].
+  pub async fn collect_metrics(&self) -> Arc<Metrics>
+  {
+    let metrics = Arc::new(Metrics::default());
+    *self.metrics.write().await = Some(metrics.clone());
+    self.add_hooks(metrics.clone()).await;
+    metrics
+  }
+
+  /// Counts bytes `node` of this instance's graph moved through a handle against the
+  /// run's `max_io_bytes`, and in the metrics if they are being collected
+  pub async fn record_io(&self, node: Uuid, read: usize, written: usize) -> Result<(), EvalError>
+  {
+    if let Some(metrics) = &*self.root().metrics.read().await
+    {
+      metrics.record_io(&self.graph_path, node, read as u64, written as u64);
+    }
+    self
+      .root()
+      .budget
+      .spend(Limit::IoBytes, (read + written) as u64)
+  }
+
+  /// Counts a node run against the run's `max_node_runs`
+  pub(super) fn record_node_run(&self) -> Result<(), EvalError>
+  {
+    self.root().budget.spend(Limit::NodeRuns, 1)
+  }
+
+  /// Waits until the run goes over one of its `[run_limits]`, returning the error it is
+  /// stopped with
+  pub async fn wait_for_limit(&self) -> EvalError
+  {
+    self.root().budget.wait().await
+  }
+
+  /// The graph file this instance runs
+  pub fn graph_path(&self) -> &str
+  {
+    &self.graph_path
+  }
+
+  /// Pauses nodes of the instances made from this one afterwards before they run, at
+  /// `breakpoints` or once told to step, see [`Debugger`].
+  pub async fn debug(&self, breakpoints: impl IntoIterator<Item = Uuid>) -> Arc<Debugger>
+  {
+    let debugger = Arc::new(Debugger::new(breakpoints));
+    self.add_hooks(debugger.clone()).await;
+    debugger
+  }
+
+  /// The variables and nodes of this instance and its complex runners as they are now,
+  /// such as while a [`Debugger`] has it paused
+  pub async fn inspect(&self) -> InstanceSnapshot
+  {
+    let mut nodes = vec![];
+    for node in self.nodes.values()
+    {
+      nodes.push(node.snapshot().await);
+    }
+    nodes.sort_by_key(|x| x.node);
+    let runners: Vec<Arc<Self>> = self.complex_nodes.read().await.values().cloned().collect();
+    let mut snapshots = vec![];
+    for runner in runners
+    {
+      snapshots.push(Box::pin(runner.inspect()).await);
+    }
+    InstanceSnapshot {
+      scope_id: self.scope_id,
+      graph_path: self.graph_path.clone(),
+      variables: self
+        .variables
+        .read()
+        .await
+        .iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect(),
+      nodes,
+      runners: snapshots,
+    }
+  }
+
+  /// The hooks of the run this instance belongs to
+  pub async fn hooks(&self) -> Vec<Arc<dyn NodeHooks>>
+  {
+    self.root().hooks.read().await.clone()
+  }
+
+  /// Resolves a `Complex` graph path used by this graph. Paths are relative to this
+  /// graph's directory, falling back to the configured search paths if nothing is
+  /// there. Graphs loaded from a bundle use the keys of the graphs in it instead.
+  pub async fn resolve_complex(&self, path: &str) -> String
+  {
+    if self
+      .bundle
+      .as_ref()
+      .is_some_and(|x| x.graphs.contains_key(path))
+    {
+      return path.to_string();
+    }
+    let local = graph_relative(&self.my_path, path);
+    if local.exists()
+    {
+      return local.to_string_lossy().into_owned();
+    }
+    self
+      .config()
+      .await
+      .search_paths
+      .iter()
+      .map(|dir| graph_relative(dir, path))
+      .find(|x| x.exists())
+      .unwrap_or(local)
+      .to_string_lossy()
+      .into_owned()
+  }
+
+  /// Provenance of the running graph as an Object: the run id and start time of the
+  /// root instance, the parameters given on the command line, the scope id and file of
+  /// the graph this instance runs, and the capabilities the build includes.
+  pub async fn runtime_info(&self) -> DataValue
+  {
+    let root = self.root();
+    let started = root
+      .started
+      .duration_since(UNIX_EPOCH)
+      .unwrap_or_default()
+      .as_millis() as i64;
+    let mut params: IndexMap<_, _> = root
+      .params
+      .read()
+      .await
+      .iter()
+      .map(|(k, v)| (k.clone(), DataValue::String(v.clone())))
+      .collect();
+    params.sort_keys();
+    DataValue::Object(IndexMap::from([
+      (
+        "run_id".to_string(),
+        DataValue::String(root.run_id.to_string()),
+      ),
+      (
+        "scope_id".to_string(),
+        DataValue::String(self.scope_id.to_string()),
+      ),
+      (
+        "graph_path".to_string(),
+        DataValue::String(self.graph_path.clone()),
+      ),
+      ("started_at_ms".to_string(), DataValue::Integer(started)),
+      ("params".to_string(), DataValue::Object(params)),
+      (
+        "capabilities".to_string(),
+        DataValue::Array(
+          crate::capabilities::compiled_in()
+            .into_iter()
+            .map(|x| DataValue::String(x.to_string()))
+            .collect(),
+        ),
+      ),
+    ]))
+  }
+
+  /// Stops the whole graph this instance belongs to: the root instance's
+  /// [`Self::get_outputs`] returns [`EvalError::Aborted`] with `message` and `code`.
+  /// Only the first abort is kept.
+  pub fn abort(&self, message: String, code: i32)
+  {
+    self.root().aborted.send_if_modified(|x| {
+      let first = x.is_none();
+      if first
+      {
+        *x = Some((message, code));
+      }
+      first
+    });
+  }
+
+  /// Waits until [`Self::abort`] is called on this instance or one below it, returning
+  /// the message and exit code.
+  pub async fn wait_for_abort(&self) -> (String, i32)
+  {
+    let mut aborted = self.aborted.subscribe();
+    // the sender lives as long as self, so this cannot fail
+    let reason = aborted
+      .wait_for(Option::is_some)
+      .await
+      .map(|x| x.clone())
+      .ok()
+      .flatten();
+    reason.expect("waited for the abort reason")
+  }
+
+  /// Records the first node failure in this instance.
+  fn fail(&self, id: Uuid, error: &EvalError)
+  {
+    let node = self.nodes.get(&id).map_or(id, |x| x.static_id);
+    self.failed.send_if_modified(|x| {
+      let first = x.is_none();
+      if first
+      {
+        *x = Some((node, error.to_string()));
+      }
+      first
+    });
+  }
+
+  /// Waits until a node of this instance fails, returning its static id and the error
+  /// message.
+  pub async fn wait_for_failure(&self) -> (Uuid, String)
   {
-    self.variables.write().await.insert(name, value);
+    let mut failed = self.failed.subscribe();
+    // the sender lives as long as self, so this cannot fail
+    let failure = failed
+      .wait_for(Option::is_some)
+      .await
+      .map(|x| x.clone())
+      .ok()
+      .flatten();
+    failure.expect("waited for the failure")
   }
 
   pub async fn wait_for_complete(&self)
@@ -1,12 +1,20 @@
-use super::{EvalError, EvaluateIt, Evaluator, Logger};
-use crate::language::nodes::{AtomicType, ControlFlow, Instance, NodeType};
+use super::lifecycle::Lifecycle;
+use super::{
+  EvalError, EvaluateIt, Evaluator, Logger, NodeHooks, NodeInfo, NodeReport, NodeSnapshot,
+  TaskRegistry,
+};
+use crate::language::nodes::{fill_defaults, AtomicType, ControlFlow, Instance, NodeType};
 use crate::language::typing::{DataType, DataValue};
 use crate::logging::node_state_logger::NodeStateLogger;
 use serde::Serialize;
-use std::ops::DerefMut;
-use std::sync::Arc;
-use tokio::sync::{Notify, RwLock};
-use tokio::task::JoinHandle;
+use std::collections::HashMap;
+use std::pin::pin;
+use std::sync::{
+  atomic::{AtomicBool, AtomicU64, Ordering},
+  Arc, Mutex,
+};
+use tokio::sync::{watch, Notify, RwLock};
+use tracing::Instrument;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Copy, PartialEq, Serialize)]
@@ -21,9 +29,10 @@ pub enum NodeState
 pub type DataInputConnection = (DataType, Uuid, usize); //(type, id, port)
 pub type OutputConnection = Uuid;
 
-pub type ControlInputConnection = (Uuid, usize);
 pub type ControlPort = Vec<(Uuid, usize)>;
 
+pub type NodeResult = (Uuid, Result<Vec<DataValue>, EvalError>);
+
 // IMPORTANT, USE Uuid v5 SO ITS SCOPED
 pub struct ExecutionNode
 {
@@ -32,84 +41,186 @@ pub struct ExecutionNode
   pub(crate) instance: Instance,
   inputs: Vec<DataInputConnection>,
   pub(super) outputs: Vec<Uuid>,
-  pub(super) state: RwLock<NodeState>,
-  trigger: NotifyCounter<usize>,
+  /// The node's state and the triggers towards its next run
+  lifecycle: Lifecycle,
   stored_value: RwLock<Option<DataValue>>,
-  output_notify: NotifyCounter<usize>,
-  current_values: RwLock<Vec<DataValue>>,
+  reads: PendingReads,
+  /// What `reads` is reset to after each run, see [`listed_reads`]
+  listed_reads: Vec<(Uuid, usize)>,
+  /// The outputs of the latest run, shared by every reader of it
+  current_values: watch::Sender<Run>,
+  /// How many runs have started, the latest numbered this
+  started: AtomicU64,
   custom_control: bool,
+  /// Whether the node has a task, started by its first trigger and ending once it has
+  /// waited `[graphs] idle_node_secs` for another
+  running: AtomicBool,
 }
 
-struct NotifyCounter<T>
+/// The outputs of one of a node's runs, numbered from 1 in the order they started. The
+/// default, numbered 0, stands for the node not having run yet.
+#[derive(Clone, Default)]
+struct Run
 {
-  notif: Notify,
-  counter: RwLock<T>,
-  start_value: T,
-  end_value: T,
-  comp_pred: Box<dyn Fn(&T, &T) -> bool>,
-  increment_pred: Box<dyn Fn(&mut T)>,
+  seq: u64,
+  values: Arc<Vec<DataValue>>,
 }
 
-unsafe impl<T> Sync for NotifyCounter<T> {}
-unsafe impl<T> Send for NotifyCounter<T> {}
+/// Reads of a node's latest run still owed by each of its readers, port by port. The
+/// node only runs again once every reader has had its share, so a reader going over the
+/// same run twice, as nodes in loops do, cannot stand in for another that has not read
+/// it yet and would otherwise get the run after, nor can its reads of one port stand in
+/// for those of another.
+struct PendingReads
+{
+  /// How many reads each reader makes of each port in one run
+  listed: HashMap<(Uuid, usize), usize>,
+  /// What is owed to the latest run, which is only ever replaced under this lock
+  owed: Mutex<HashMap<(Uuid, usize), usize>>,
+  done: Notify,
+}
 
-impl<T> NotifyCounter<T>
-where
-  T: Clone,
+impl PendingReads
 {
-  pub fn new<C, I>(start_value: T, end_value: T, increment_pred: I, comp_pred: C) -> Self
-  where
-    C: 'static + Fn(&T, &T) -> bool,
-    I: 'static + Fn(&mut T),
+  fn new(reads: &[(Uuid, usize)]) -> Self
   {
+    let mut listed = HashMap::new();
+    for read in reads
+    {
+      *listed.entry(*read).or_default() += 1;
+    }
     Self {
-      notif: Notify::const_new(),
-      start_value: start_value.clone(),
-      counter: RwLock::const_new(start_value),
-      end_value,
-      comp_pred: Box::new(comp_pred),
-      increment_pred: Box::new(increment_pred),
+      // nothing is owed before the first run
+      owed: Mutex::new(HashMap::new()),
+      listed,
+      done: Notify::new(),
     }
   }
 
-  pub async fn increment(&self) -> bool
+  /// Makes `run` the latest of `values` and owes it the listed reads, both at once so a
+  /// read is never counted against a run it did not get.
+  fn publish(&self, values: &watch::Sender<Run>, run: Run)
   {
-    let mut guard = self.counter.write().await;
-    self.increment_pred.call((guard.deref_mut(),));
-    if self.comp_pred.call((&*guard, &self.end_value))
-    {
-      self.notif.notify_one();
-      true
-    }
+    let mut owed = self.owed.lock().unwrap();
+    owed.clone_from(&self.listed);
+    values.send_replace(run);
+  }
+
+  /// The latest run that `values` has, with whether the read of `port` by `reader` was
+  /// counted against it, which it is not past the reader's share. Wakes the node once
+  /// nothing more is owed.
+  fn read(&self, values: &mut watch::Receiver<Run>, reader: Uuid, port: usize) -> (Run, bool)
+  {
+    let mut owed = self.owed.lock().unwrap();
+    let run = values.borrow_and_update().clone();
+    let Some(x) = owed.get_mut(&(reader, port)).filter(|x| **x > 0)
     else
     {
-      false
+      return (run, false);
+    };
+    *x -= 1;
+    if owed.values().all(|x| *x == 0)
+    {
+      self.done.notify_one();
     }
+    (run, true)
   }
 
-  pub async fn reset(&self)
+  fn remaining(&self) -> usize
   {
-    *self.counter.write().await = self.start_value.clone();
+    self.owed.lock().unwrap().values().sum()
   }
-  pub async fn wait(&self)
+
+  async fn wait(&self)
   {
-    if self.comp_pred.call((&self.start_value, &self.end_value))
+    loop
     {
-      return;
+      let mut done = pin!(self.done.notified());
+      done.as_mut().enable();
+      if self.remaining() == 0
+      {
+        return;
+      }
+      done.await;
     }
-    self.notif.notified().await
   }
 }
 
-fn get_counter(node_type: &NodeType, _control_flow: &Vec<ControlPort>) -> NotifyCounter<usize>
+/// The reads each node's runs are owed, by node: a `(reader, port)` for every input of
+/// a reader listed in the node's `outputs` that reads from it. The end node, listed
+/// among its own outputs once per graph output, is read port by port as the graph's
+/// outputs.
+pub(super) fn listed_reads(instances: &HashMap<Uuid, Instance>)
+  -> HashMap<Uuid, Vec<(Uuid, usize)>>
+{
+  instances
+    .iter()
+    .map(|(id, instance)| {
+      let mut readers = instance.outputs.clone();
+      readers.sort();
+      readers.dedup();
+      let reads = readers
+        .into_iter()
+        .flat_map(|reader| -> Vec<(Uuid, usize)> {
+          if reader == *id
+          {
+            let count = instance.outputs.iter().filter(|x| **x == reader).count();
+            return (0..count).map(|port| (reader, port)).collect();
+          }
+          instances
+            .get(&reader)
+            .map(|x| &x.inputs[..])
+            .unwrap_or_default()
+            .iter()
+            .filter(|(_, source, _)| source == id)
+            .map(|(_, _, port)| (reader, *port))
+            .collect()
+        })
+        .collect();
+      (*id, reads)
+    })
+    .collect()
+}
+
+/// How messages refer to a node: by its name followed by its id, or by its id alone if
+/// it has no name
+pub fn node_label(id: Uuid, name: Option<&str>) -> String
+{
+  match name
+  {
+    Some(name) => format!("{name} ({id})"),
+    None => id.to_string(),
+  }
+}
+
+/// The kind of node without its arguments, such as `BinOp` or `Complex`, so logs do not
+/// repeat the contents of `Value` nodes
+pub(super) fn type_label(node_type: &NodeType) -> String
+{
+  let kind = match node_type
+  {
+    NodeType::Atomic(x) => serde_json::to_value(x),
+    NodeType::Complex(_) => return "Complex".to_string(),
+  };
+  match kind
+  {
+    Ok(serde_json::Value::String(x)) => x,
+    Ok(serde_json::Value::Object(x)) => x.keys().next().cloned().unwrap_or_default(),
+    _ => String::new(),
+  }
+}
+
+fn get_lifecycle(node_type: &NodeType, control_flow: &[ControlPort]) -> Lifecycle
 {
   match node_type
   {
-    NodeType::Atomic(AtomicType::Control(ControlFlow::Start)) =>
+    NodeType::Atomic(AtomicType::Control(ControlFlow::Start)) => Lifecycle::new(0),
+    // a join runs once every incoming connection has triggered it
+    NodeType::Atomic(AtomicType::Control(ControlFlow::Parallel)) =>
     {
-      NotifyCounter::new(0, 0, |x| *x += 1, PartialEq::eq)
+      Lifecycle::new(control_flow.iter().map(Vec::len).sum::<usize>().max(1))
     }
-    _ => NotifyCounter::new(0, 1, |x| *x += 1, PartialEq::eq),
+    _ => Lifecycle::new(1),
   }
 }
 
@@ -118,29 +229,44 @@ impl Clone for ExecutionNode
   fn clone(&self) -> Self
   {
     Self {
-      id: self.id.clone(),
-      static_id: self.static_id.clone(),
+      id: self.id,
+      static_id: self.static_id,
       instance: self.instance.clone(),
       inputs: self.inputs.clone(),
       outputs: self.outputs.clone(),
-      state: RwLock::new(NodeState::Waiting),
-      trigger: get_counter(&self.instance.node_type, &self.instance.control_flow_in),
+      listed_reads: self.listed_reads.clone(),
+      lifecycle: get_lifecycle(&self.instance.node_type, &self.instance.control_flow_in),
       stored_value: RwLock::new(None),
-      output_notify: NotifyCounter::new(0, self.outputs.len(), |x| *x += 1, |a, b| a == b),
-      current_values: RwLock::new(vec![]),
-      custom_control: self.custom_control.clone(),
+      reads: PendingReads::new(&self.listed_reads),
+      current_values: watch::Sender::new(Run::default()),
+      started: AtomicU64::new(0),
+      custom_control: self.custom_control,
+      running: AtomicBool::new(false),
     }
   }
 }
 
 impl ExecutionNode
 {
+  pub(super) fn state(&self) -> NodeState
+  {
+    self.lifecycle.state()
+  }
+
   async fn change_state<Tl, Nl>(&self, state: NodeState, eval: Arc<Evaluator<Tl, Nl>>)
   where
     Tl: Logger,
     Nl: Logger,
   {
-    *self.state.write().await = state.clone();
+    self.lifecycle.set_state(state);
+    self.log_state(state, eval).await;
+  }
+
+  async fn log_state<Tl, Nl>(&self, state: NodeState, eval: Arc<Evaluator<Tl, Nl>>)
+  where
+    Tl: Logger,
+    Nl: Logger,
+  {
     if let Some(logger) = &eval.node_logger
     {
       logger
@@ -153,26 +279,73 @@ impl ExecutionNode
     }
   }
 
-  async fn run<'a, Tl, Nl>(
-    self: Arc<Self>,
-    eval: Arc<Evaluator<Tl, Nl>>,
-  ) -> (Uuid, Result<Vec<DataValue>, EvalError>)
+  async fn run<Tl, Nl>(self: Arc<Self>, eval: Arc<Evaluator<Tl, Nl>>) -> NodeResult
   where
     Tl: Logger,
     Nl: Logger,
   {
-    (self.id.clone(), self.process(eval).await)
+    let cancel = eval.cancel.clone();
+    // whatever the node is waiting on, a trigger, an upstream node or IO, it stops
+    // there once its instance shuts down
+    let result = tokio::select! {
+      _ = cancel.cancelled() => Ok(vec![]),
+      result = self.process(eval) => result,
+    };
+    (self.id, result)
   }
 
-  pub fn spawn<'a, Tl, Nl>(
-    self: Arc<Self>,
-    eval: Arc<Evaluator<Tl, Nl>>,
-  ) -> JoinHandle<(Uuid, Result<Vec<DataValue>, EvalError>)>
+  pub fn spawn<Tl, Nl>(self: Arc<Self>, eval: Arc<Evaluator<Tl, Nl>>, tasks: &TaskRegistry)
   where
     Tl: Logger,
     Nl: Logger,
   {
-    tokio::spawn(self.run(eval))
+    self.running.store(true, Ordering::SeqCst);
+    tasks.spawn_node(self.run(eval));
+  }
+
+  /// Spawns the node's task on `eval` unless it already has one or `eval` is shutting
+  /// down
+  fn wake<Tl, Nl>(self: &Arc<Self>, eval: &Arc<Evaluator<Tl, Nl>>)
+  where
+    Tl: Logger,
+    Nl: Logger,
+  {
+    if !eval.cancel.is_cancelled() && !self.running.swap(true, Ordering::SeqCst)
+    {
+      eval.tasks.spawn_node(self.clone().run(eval.clone()));
+    }
+  }
+
+  /// Waits for the node to be triggered and moves it to processing. Unless nodes run
+  /// in a fixed order, gives up once it has waited `[graphs] idle_node_secs`,
+  /// returning false for the task to end and leave the next trigger to start another.
+  async fn wait_for_trigger<Tl, Nl>(&self, eval: &Evaluator<Tl, Nl>) -> bool
+  where
+    Tl: Logger,
+    Nl: Logger,
+  {
+    if eval.tasks.is_ordered()
+    {
+      self.lifecycle.start().await;
+      return true;
+    }
+    let idle = eval.config().await.graphs.idle_node();
+    loop
+    {
+      if tokio::time::timeout(idle, self.lifecycle.start())
+        .await
+        .is_ok()
+      {
+        return true;
+      }
+      self.running.store(false, Ordering::SeqCst);
+      // a trigger that came in as the wait gave up either finds no task and starts
+      // one, or is left for this one to carry on with
+      if !self.lifecycle.pending() || self.running.swap(true, Ordering::SeqCst)
+      {
+        return false;
+      }
+    }
   }
 
   async fn broadcast_closed(&self)
@@ -188,10 +361,7 @@ impl ExecutionNode
     // *self.state.write().await = NodeState::Closed;
   }
 
-  async fn process<'a, Tl, Nl>(
-    &self,
-    eval: Arc<Evaluator<Tl, Nl>>,
-  ) -> Result<Vec<DataValue>, EvalError>
+  async fn process<Tl, Nl>(&self, eval: Arc<Evaluator<Tl, Nl>>) -> Result<Vec<DataValue>, EvalError>
   where
     Tl: Logger,
     Nl: Logger,
@@ -213,10 +383,10 @@ impl ExecutionNode
      *   a. node eval controls which control flow out gets triggered
      * 4. wait for all data to be retrieved
      */
-    while *(self.state.read().await) != NodeState::Closed
+    while self.state() != NodeState::Closed
     {
       // let id = tokio::task::try_id().unwrap();
-      // println!("{:?}", self.state.read().await);
+      // println!("{:?}", self.state());
       // println!("{} waiting for notif", tokio::task::try_id().unwrap());
 
       //1
@@ -226,8 +396,12 @@ impl ExecutionNode
       //   "Starting process for {} {:?}",
       //   self.static_id, self.instance.node_type
       // );
-      self.trigger.wait().await;
-      self.trigger.reset().await;
+      if !self.wait_for_trigger(&eval).await
+      {
+        return Ok(vec![]);
+      }
+      let seq = self.started.fetch_add(1, Ordering::SeqCst) + 1;
+      self.log_state(NodeState::Processing, eval.clone()).await;
       // println!(
       //   "Finish trigger wait for {} {:?}",
       //   self.static_id, self.instance.node_type
@@ -238,18 +412,39 @@ impl ExecutionNode
       //2
       // println!("{id} step 2");
       let mut inputs = Vec::with_capacity(self.inputs.len());
-      for (t, id, port) in &self.inputs
+      // joins read their inputs concurrently, everything else one at a time
+      let parallel = matches!(
+        self.instance.node_type,
+        NodeType::Atomic(AtomicType::Control(ControlFlow::Parallel))
+      );
+      let mut pulls = vec![];
+      for (_t, id, port) in &self.inputs
       {
-        if let Some(node) = eval.nodes.get(&id)
+        if let Some(node) = eval.nodes.get(id)
         {
+          if node.id == self.id
+          {
+            return Err(EvalError::SelfListen(vec![self.static_id]));
+          }
+          if node.inputs.iter().any(|(_, x, _)| *x == self.id)
+          {
+            return Err(EvalError::SelfListen(vec![self.static_id, node.static_id]));
+          }
           // 2a_1, check state
-          if *node.state.read().await == NodeState::Closed
+          if node.state() == NodeState::Closed
           {
             self.broadcast_closed().await;
             // println!("2a_1");
             return Ok(vec![]);
           }
-          inputs.push(node.get_output(*port).await);
+          if parallel
+          {
+            pulls.push(node.get_output(*port, self.static_id));
+          }
+          else
+          {
+            inputs.push(node.get_output(*port, self.static_id).await);
+          }
         }
         else
         {
@@ -257,17 +452,90 @@ impl ExecutionNode
           return Ok(vec![]);
         }
       }
+      if parallel
+      {
+        inputs = futures::future::join_all(pulls).await;
+      }
+      let inputs = fill_defaults(
+        &self.instance.default_overrides,
+        inputs,
+        self.instance.input_count(),
+      );
 
       // 5, outputs already drained, set back to waiting
-      let res = self
-        .instance
-        .node_type
-        .evaluate(eval.clone(), self, inputs)
-        .await;
+      let hooks = eval.hooks().await;
+      let started = std::time::Instant::now();
+      let span = tracing::debug_span!(
+        "node",
+        node = %self.static_id,
+        name = self.instance.name.as_deref(),
+        scope = %eval.scope_id,
+        node_type = type_label(&self.instance.node_type),
+      );
+      let res = async {
+        match self
+          .start_hooks(&hooks, &eval, &inputs)
+          .await
+          .and_then(|_| eval.record_node_run())
+        {
+          Ok(()) =>
+          {
+            self
+              .instance
+              .node_type
+              .evaluate(eval.clone(), self, inputs)
+              .await
+          }
+          Err(e) => Err(e),
+        }
+      }
+      .instrument(span.clone())
+      .await;
+      tracing::debug!(
+        parent: &span,
+        elapsed_ms = started.elapsed().as_secs_f64() * 1000.0,
+        ok = res.is_ok(),
+        "node evaluated"
+      );
+      let res = match res.and_then(|x| self.check_output_count(&eval, x))
+      {
+        Ok(outputs) =>
+        {
+          eval
+            .check_limits(self.static_id, &outputs)
+            .await
+            .map(|_| outputs)
+        }
+        Err(e) => Err(e),
+      };
+      for hook in &hooks
+      {
+        match &res
+        {
+          Ok(outputs) =>
+          {
+            for (port, value) in outputs.iter().enumerate()
+            {
+              hook.on_value(self.info(&eval), port, value).await;
+            }
+            hook.on_node_end(self.info(&eval), started.elapsed()).await;
+          }
+          Err(e) => hook.on_error(self.info(&eval), e).await,
+        }
+      }
       if let Ok(outputs) = res
       {
-        let mut guard = self.current_values.write().await;
-        *guard = outputs;
+        let run = Run {
+          seq,
+          values: Arc::new(outputs),
+        };
+        self.reads.publish(&self.current_values, run);
+        // only report completion once the outputs can actually be read
+        if self.instance.node_type == NodeType::Atomic(AtomicType::Control(ControlFlow::End))
+        {
+          eval.completed.store(true, Ordering::Release);
+          eval.complete.notify_one();
+        }
       }
       else
       {
@@ -283,69 +551,213 @@ impl ExecutionNode
         }
       }
       self.change_state(NodeState::Outputting, eval.clone()).await;
-      self.output_notify.wait().await;
-      self.output_notify.reset().await;
+      self.reads.wait().await;
       self.change_state(NodeState::Waiting, eval.clone()).await;
     }
     Ok(vec![])
   }
 
-  pub async fn trigger_processing<'a, Tl, Nl>(&self, eval: Arc<Evaluator<Tl, Nl>>)
+  /// Fails a run of an atomic node that gave a different number of values than it has
+  /// outputs, which would otherwise leave readers of the missing ones waiting. Nodes
+  /// with no outputs may still give a lone None.
+  fn check_output_count<Tl, Nl>(
+    &self,
+    eval: &Evaluator<Tl, Nl>,
+    outputs: Vec<DataValue>,
+  ) -> Result<Vec<DataValue>, EvalError>
   where
     Tl: Logger,
     Nl: Logger,
   {
-    // println!("{} triggered", self.id);
-    if *self.state.read().await == NodeState::Waiting
+    let NodeType::Atomic(atomic) = &self.instance.node_type
+    else
+    {
+      return Ok(outputs);
+    };
+    let expected = atomic.output_count(
+      self.instance.input_count(),
+      eval.input_types().len(),
+      eval.records(),
+    );
+    match outputs.as_slice()
     {
-      // println!("{} notifying", self.id);
-      if self.trigger.increment().await
+      [DataValue::None] if expected == 0 => Ok(outputs),
+      x if x.len() == expected => Ok(outputs),
+      x =>
       {
-        self.change_state(NodeState::Processing, eval.clone()).await;
+        Err(EvalError::IncorrectOutputCount {
+          expected,
+          got: x.len(),
+        })
       }
     }
   }
 
+  /// The node's state, and how many reads of its latest outputs are still to happen
+  pub(super) async fn report(&self) -> NodeReport
+  {
+    let state = match self.instance.node_type
+    {
+      // start nodes sit in processing while waiting for the next inputs
+      NodeType::Atomic(AtomicType::Control(ControlFlow::Start)) => NodeState::Waiting,
+      _ => self.state(),
+    };
+    let unread_outputs = match state
+    {
+      NodeState::Outputting => self.reads.remaining(),
+      _ => 0,
+    };
+    NodeReport {
+      node: self.static_id,
+      name: self.instance.name.clone(),
+      node_type: format!("{:?}", self.instance.node_type),
+      state,
+      unread_outputs,
+    }
+  }
+
+  /// The node's state along with what it holds and is waiting on
+  pub(super) async fn snapshot(&self) -> NodeSnapshot
+  {
+    let report = self.report().await;
+    let outputs = self.current_values.borrow().values.to_vec();
+    NodeSnapshot {
+      node: self.static_id,
+      name: self.instance.name.clone(),
+      node_type: type_label(&self.instance.node_type),
+      state: report.state,
+      stored: self.get_stored().await,
+      outputs,
+      unread_outputs: report.unread_outputs,
+      triggers: self.lifecycle.triggers(),
+      triggers_needed: self.lifecycle.needed(),
+    }
+  }
+
+  fn info<'a, Tl, Nl>(&'a self, eval: &'a Evaluator<Tl, Nl>) -> NodeInfo<'a>
+  where
+    Tl: Logger,
+    Nl: Logger,
+  {
+    NodeInfo {
+      scope_id: eval.scope_id,
+      graph_path: eval.graph_path(),
+      node: self.static_id,
+      name: self.instance.name.as_deref(),
+      node_type: &self.instance.node_type,
+    }
+  }
+
+  /// Runs the start hooks in order, stopping at the first to reject the node.
+  async fn start_hooks<Tl, Nl>(
+    &self,
+    hooks: &[Arc<dyn NodeHooks>],
+    eval: &Evaluator<Tl, Nl>,
+    inputs: &[DataValue],
+  ) -> Result<(), EvalError>
+  where
+    Tl: Logger,
+    Nl: Logger,
+  {
+    for hook in hooks
+    {
+      hook
+        .on_node_start(self.info(eval), inputs)
+        .await
+        .map_err(EvalError::Rejected)?;
+    }
+    Ok(())
+  }
+
+  pub async fn trigger_processing<Tl, Nl>(self: &Arc<Self>, eval: Arc<Evaluator<Tl, Nl>>)
+  where
+    Tl: Logger,
+    Nl: Logger,
+  {
+    // println!("{} triggered", self.id);
+    self.lifecycle.trigger();
+    self.wake(&eval);
+  }
+
   pub fn new(
     static_id: Uuid,
     scoped_id: Uuid,
     instance: Instance,
     inputs: Vec<DataInputConnection>,
+    listed_reads: Vec<(Uuid, usize)>,
   ) -> Self
   {
-    let outsize = instance.outputs.len();
     let outputs = instance.outputs.clone();
+    let reads = PendingReads::new(&listed_reads);
     Self {
       id: scoped_id,
       static_id,
-      trigger: get_counter(&instance.node_type, &instance.control_flow_in),
-      custom_control: match &instance.node_type
-      {
-        NodeType::Atomic(AtomicType::Control(ControlFlow::If)) => true,
-        _ => false,
-      },
+      lifecycle: get_lifecycle(&instance.node_type, &instance.control_flow_in),
+      custom_control: matches!(
+        &instance.node_type,
+        NodeType::Atomic(AtomicType::Control(ControlFlow::If))
+      ),
       instance,
       inputs,
       outputs,
-      state: RwLock::new(NodeState::Waiting),
       stored_value: RwLock::new(None),
-      output_notify: NotifyCounter::new(0, outsize, |x| *x += 1, |a, b| a == b),
-      current_values: RwLock::new(vec![]),
+      reads,
+      listed_reads,
+      current_values: watch::Sender::new(Run::default()),
+      started: AtomicU64::new(0),
+      running: AtomicBool::new(false),
     }
   }
 
-  pub async fn close(&self)
+  /// The value on `port` from the run `reader` is owed, waiting for it to run first if
+  /// it has not yet. The node only runs again once each of its readers has made its
+  /// listed reads, so every reader of one run gets the same value. A reader that has
+  /// had its share of the latest run waits for the next if the node is running or
+  /// triggered, and otherwise goes over the latest again.
+  pub async fn get_output(&self, port: usize, reader: Uuid) -> DataValue
   {
-    self.broadcast_closed().await;
+    let mut values = self.current_values.subscribe();
+    loop
+    {
+      let (run, counted) = self.reads.read(&mut values, reader, port);
+      if run.seq > 0 && (counted || !self.next_run_coming(run.seq))
+      {
+        return run.values.get(port).cloned().unwrap_or(DataValue::None);
+      }
+      // the sender lives as long as self, so this cannot fail
+      if values.changed().await.is_err()
+      {
+        return DataValue::None;
+      }
+    }
   }
 
-  pub async fn get_output(&self, port: usize) -> DataValue
+  /// Whether a run after `seq` has started or been triggered. Nodes that need no
+  /// trigger, like `Start`, are always about to run again, so their latest run is the
+  /// one to read.
+  fn next_run_coming(&self, seq: u64) -> bool
   {
-    let guard = self.current_values.read().await;
-    let output = guard[port].clone();
+    self.lifecycle.needed() > 0
+      && (self.started.load(Ordering::SeqCst) > seq || self.lifecycle.pending())
+  }
 
-    self.output_notify.increment().await;
-    output
+  /// Triggers the node as one of its incoming control flow connections would and waits
+  /// for the outputs of the run that follows.
+  pub(super) async fn rerun<Tl, Nl>(
+    self: &Arc<Self>,
+    eval: Arc<Evaluator<Tl, Nl>>,
+  ) -> Vec<DataValue>
+  where
+    Tl: Logger,
+    Nl: Logger,
+  {
+    let mut values = self.current_values.subscribe();
+    values.mark_unchanged();
+    self.trigger_processing(eval).await;
+    // the sender lives as long as self, so this cannot fail
+    let _ = values.changed().await;
+    let outputs = values.borrow().values.to_vec();
+    outputs
   }
 
   pub async fn get_stored(&self) -> Option<DataValue>
@@ -361,7 +773,7 @@ impl ExecutionNode
     ret
   }
 
-  pub async fn trigger_connected<'a, Tl, Nl>(
+  pub async fn trigger_connected<Tl, Nl>(
     &self,
     eval: Arc<Evaluator<Tl, Nl>>,
     port: usize,
@@ -0,0 +1,40 @@
+use super::EvalError;
+use crate::language::{nodes::NodeType, typing::DataValue};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// The node a hook is called for
+pub struct NodeInfo<'a>
+{
+  /// The instance running the node, as in `RuntimeInfo`'s `scope_id`
+  pub scope_id: Uuid,
+  /// The graph file the node is in
+  pub graph_path: &'a str,
+  /// The node's id in its graph file
+  pub node: Uuid,
+  pub name: Option<&'a str>,
+  pub node_type: &'a NodeType,
+}
+
+/// Callbacks around every node an instance runs, including inside its Complex nodes,
+/// registered with [`super::Evaluator::add_hooks`]. Every method does nothing by
+/// default. They are awaited by the node's task, so slow hooks slow the graph down.
+#[async_trait::async_trait]
+pub trait NodeHooks: Send + Sync + 'static
+{
+  /// Called with the node's inputs before it runs. Returning an error fails the node
+  /// with [`EvalError::Rejected`] instead of running it.
+  async fn on_node_start(&self, _node: NodeInfo<'_>, _inputs: &[DataValue]) -> Result<(), String>
+  {
+    Ok(())
+  }
+
+  /// Called with each output of a node that succeeded, before [`Self::on_node_end`]
+  async fn on_value(&self, _node: NodeInfo<'_>, _port: usize, _value: &DataValue) {}
+
+  /// Called once a node has succeeded, with how long it took to run
+  async fn on_node_end(&self, _node: NodeInfo<'_>, _elapsed: Duration) {}
+
+  /// Called when a node fails, rejections by [`Self::on_node_start`] included
+  async fn on_error(&self, _node: NodeInfo<'_>, _error: &EvalError) {}
+}
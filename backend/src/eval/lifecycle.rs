@@ -0,0 +1,143 @@
+use super::NodeState;
+use std::pin::pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::Notify;
+
+/// Bits of the packed word holding the state, the triggers are counted above them
+const STATE_BITS: u32 = 8;
+const STATE_MASK: u64 = (1 << STATE_BITS) - 1;
+
+/// A node's state together with the control flow triggers it has received towards its
+/// next runs. Both live in one word so a run starts by taking its triggers and moving
+/// to processing in a single compare and swap, and a trigger landing at the same time
+/// is either taken by that run or left for the next, never lost or counted twice.
+pub(super) struct Lifecycle
+{
+  word: AtomicU64,
+  needed: usize,
+  ready: Notify,
+}
+
+impl NodeState
+{
+  const fn to_bits(self) -> u64
+  {
+    match self
+    {
+      NodeState::Processing => 0,
+      NodeState::Waiting => 1,
+      NodeState::Outputting => 2,
+      NodeState::Closed => 3,
+    }
+  }
+
+  const fn from_bits(bits: u64) -> Self
+  {
+    match bits & STATE_MASK
+    {
+      0 => NodeState::Processing,
+      1 => NodeState::Waiting,
+      2 => NodeState::Outputting,
+      _ => NodeState::Closed,
+    }
+  }
+}
+
+const fn triggers(word: u64) -> usize
+{
+  (word >> STATE_BITS) as usize
+}
+
+impl Lifecycle
+{
+  /// A waiting node that runs once it has been triggered `needed` times
+  pub fn new(needed: usize) -> Self
+  {
+    Self {
+      word: AtomicU64::new(NodeState::Waiting.to_bits()),
+      needed,
+      ready: Notify::new(),
+    }
+  }
+
+  pub fn state(&self) -> NodeState
+  {
+    NodeState::from_bits(self.word.load(Ordering::Acquire))
+  }
+
+  /// How many triggers have come in that no run has taken yet
+  pub fn triggers(&self) -> usize
+  {
+    triggers(self.word.load(Ordering::Acquire))
+  }
+
+  pub fn needed(&self) -> usize
+  {
+    self.needed
+  }
+
+  /// Whether any triggers are waiting for the next run
+  pub fn pending(&self) -> bool
+  {
+    self.triggers() > 0
+  }
+
+  /// Moves the node to `state`, keeping the triggers it has received
+  pub fn set_state(&self, state: NodeState)
+  {
+    // the closure never declines so this cannot fail
+    let _ = self
+      .word
+      .fetch_update(Ordering::AcqRel, Ordering::Acquire, |word| {
+        Some(word & !STATE_MASK | state.to_bits())
+      });
+  }
+
+  /// Counts a trigger. One that arrives while the node is busy, say from a join whose
+  /// inputs it has just read, is kept for the run after.
+  pub fn trigger(&self)
+  {
+    let previous = self.word.fetch_add(1 << STATE_BITS, Ordering::AcqRel);
+    if triggers(previous) + 1 >= self.needed
+    {
+      // the permit is kept if nothing is waiting yet, so the wakeup is not lost
+      self.ready.notify_one();
+    }
+  }
+
+  /// Takes the triggers for one run and moves the node to processing, if it has as
+  /// many as it needs. Nodes that need none, like `Start`, take whatever they have.
+  pub fn try_start(&self) -> bool
+  {
+    self
+      .word
+      .fetch_update(Ordering::AcqRel, Ordering::Acquire, |word| {
+        let triggers = triggers(word);
+        let left = match self.needed
+        {
+          0 => 0,
+          needed => triggers.checked_sub(needed)?,
+        };
+        (NodeState::from_bits(word) != NodeState::Closed)
+          .then_some((left as u64) << STATE_BITS | NodeState::Processing.to_bits())
+      })
+      .is_ok()
+  }
+
+  /// Waits until [`Self::try_start`] succeeds. Dropping the future before then leaves
+  /// the triggers in place.
+  pub async fn start(&self)
+  {
+    loop
+    {
+      // registered before checking so a trigger landing in between still wakes us
+      let mut notified = pin!(self.ready.notified());
+      notified.as_mut().enable();
+      if self.try_start()
+      {
+        return;
+      }
+      notified.await;
+    }
+  }
+}
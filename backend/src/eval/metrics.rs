@@ -0,0 +1,241 @@
+use super::{type_label, EvalError, NodeHooks, NodeInfo};
+use serde::Serialize;
+use std::{
+  collections::BTreeMap,
+  fmt::{self, Write},
+  sync::Mutex,
+  time::Duration,
+};
+use uuid::Uuid;
+
+/// Upper bounds of the latency histogram buckets, in seconds
+pub const LATENCY_BUCKETS: [f64; 11] = [
+  0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 60.0,
+];
+
+/// What one node of one graph has done across every instance of the graph
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct NodeMetrics
+{
+  pub node_type: String,
+  /// Runs that ended, failed ones included
+  pub evaluations: u64,
+  pub errors: u64,
+  /// Runs that succeeded per bucket of [`LATENCY_BUCKETS`], not cumulative, with the
+  /// slower ones in the last
+  pub latency_buckets: [u64; LATENCY_BUCKETS.len() + 1],
+  /// Total time taken by the runs that succeeded
+  pub latency_sum: Duration,
+  pub latency_max: Duration,
+  pub bytes_read: u64,
+  pub bytes_written: u64,
+}
+
+/// Counts runs, errors, latencies and IO bytes per node, registered with
+/// [`super::Evaluator::collect_metrics`]. Nodes are told apart by their graph file and
+/// their id in it.
+#[derive(Default)]
+pub struct Metrics
+{
+  nodes: Mutex<BTreeMap<(String, Uuid), NodeMetrics>>,
+}
+
+impl Metrics
+{
+  fn update(&self, graph: &str, node: Uuid, f: impl FnOnce(&mut NodeMetrics))
+  {
+    let mut nodes = self.nodes.lock().unwrap();
+    f(nodes.entry((graph.to_string(), node)).or_default())
+  }
+
+  /// Counts bytes a node moved through a handle
+  pub fn record_io(&self, graph: &str, node: Uuid, read: u64, written: u64)
+  {
+    self.update(graph, node, |x| {
+      x.bytes_read += read;
+      x.bytes_written += written;
+    });
+  }
+
+  /// The metrics of every node that has run, by graph and node id
+  pub fn snapshot(&self) -> BTreeMap<(String, Uuid), NodeMetrics>
+  {
+    self.nodes.lock().unwrap().clone()
+  }
+
+  /// The metrics in Prometheus' text exposition format
+  pub fn prometheus(&self) -> String
+  {
+    let nodes: Vec<(String, NodeMetrics)> = self
+      .snapshot()
+      .into_iter()
+      .map(|((graph, node), x)| {
+        let labels = format!(
+          "graph=\"{}\",node=\"{node}\",node_type=\"{}\"",
+          escape_label(&graph),
+          escape_label(&x.node_type)
+        );
+        (labels, x)
+      })
+      .collect();
+    // writing to a String cannot fail
+    let mut out = String::new();
+    let _ = writeln!(
+      out,
+      "# HELP agentnodes_node_evaluations_total Node runs that ended, failed ones included"
+    );
+    let _ = writeln!(out, "# TYPE agentnodes_node_evaluations_total counter");
+    for (labels, x) in &nodes
+    {
+      let _ = writeln!(
+        out,
+        "agentnodes_node_evaluations_total{{{labels}}} {}",
+        x.evaluations
+      );
+    }
+    let _ = writeln!(
+      out,
+      "# HELP agentnodes_node_errors_total Node runs that failed"
+    );
+    let _ = writeln!(out, "# TYPE agentnodes_node_errors_total counter");
+    for (labels, x) in &nodes
+    {
+      let _ = writeln!(out, "agentnodes_node_errors_total{{{labels}}} {}", x.errors);
+    }
+    let _ = writeln!(
+      out,
+      "# HELP agentnodes_node_duration_seconds How long node runs that succeeded took"
+    );
+    let _ = writeln!(out, "# TYPE agentnodes_node_duration_seconds histogram");
+    for (labels, x) in &nodes
+    {
+      let mut count = 0;
+      for (i, bucket) in x.latency_buckets.iter().enumerate()
+      {
+        count += bucket;
+        let le = match LATENCY_BUCKETS.get(i)
+        {
+          Some(le) => le.to_string(),
+          None => "+Inf".to_string(),
+        };
+        let _ = writeln!(
+          out,
+          "agentnodes_node_duration_seconds_bucket{{{labels},le=\"{le}\"}} {count}"
+        );
+      }
+      let _ = writeln!(
+        out,
+        "agentnodes_node_duration_seconds_sum{{{labels}}} {}",
+        x.latency_sum.as_secs_f64()
+      );
+      let _ = writeln!(
+        out,
+        "agentnodes_node_duration_seconds_count{{{labels}}} {count}"
+      );
+    }
+    let _ = writeln!(
+      out,
+      "# HELP agentnodes_node_io_bytes_total Bytes nodes read from or wrote to handles"
+    );
+    let _ = writeln!(out, "# TYPE agentnodes_node_io_bytes_total counter");
+    for (labels, x) in &nodes
+    {
+      let _ = writeln!(
+        out,
+        "agentnodes_node_io_bytes_total{{{labels},direction=\"read\"}} {}",
+        x.bytes_read
+      );
+      let _ = writeln!(
+        out,
+        "agentnodes_node_io_bytes_total{{{labels},direction=\"written\"}} {}",
+        x.bytes_written
+      );
+    }
+    out
+  }
+
+  /// A table of the metrics with a row per node, for printing once a run is over
+  pub fn summary(&self) -> MetricsSummary
+  {
+    MetricsSummary(self.snapshot())
+  }
+}
+
+/// Prometheus label values escape backslashes, quotes and newlines
+fn escape_label(value: &str) -> String
+{
+  value
+    .replace('\\', "\\\\")
+    .replace('"', "\\\"")
+    .replace('\n', "\\n")
+}
+
+#[async_trait::async_trait]
+impl NodeHooks for Metrics
+{
+  async fn on_node_end(&self, node: NodeInfo<'_>, elapsed: Duration)
+  {
+    let bucket = LATENCY_BUCKETS
+      .iter()
+      .position(|x| elapsed.as_secs_f64() <= *x)
+      .unwrap_or(LATENCY_BUCKETS.len());
+    self.update(node.graph_path, node.node, |x| {
+      x.node_type = type_label(node.node_type);
+      x.evaluations += 1;
+      x.latency_buckets[bucket] += 1;
+      x.latency_sum += elapsed;
+      x.latency_max = x.latency_max.max(elapsed);
+    });
+  }
+
+  async fn on_error(&self, node: NodeInfo<'_>, _error: &EvalError)
+  {
+    self.update(node.graph_path, node.node, |x| {
+      x.node_type = type_label(node.node_type);
+      x.evaluations += 1;
+      x.errors += 1;
+    });
+  }
+}
+
+/// See [`Metrics::summary`]
+pub struct MetricsSummary(BTreeMap<(String, Uuid), NodeMetrics>);
+
+impl fmt::Display for MetricsSummary
+{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+  {
+    writeln!(
+      f,
+      "{:<36}  {:<12} {:>6} {:>6} {:>10} {:>10} {:>10} {:>10}",
+      "node", "type", "runs", "errors", "mean ms", "max ms", "read", "written"
+    )?;
+    let mut graph = None;
+    for ((path, node), x) in &self.0
+    {
+      if graph != Some(path)
+      {
+        writeln!(f, "{path}")?;
+        graph = Some(path);
+      }
+      let succeeded = x.evaluations - x.errors;
+      let mean = match succeeded
+      {
+        0 => 0.0,
+        n => x.latency_sum.as_secs_f64() * 1000.0 / n as f64,
+      };
+      writeln!(
+        f,
+        "{node}  {:<12} {:>6} {:>6} {:>10.3} {:>10.3} {:>10} {:>10}",
+        x.node_type,
+        x.evaluations,
+        x.errors,
+        mean,
+        x.latency_max.as_secs_f64() * 1000.0,
+        x.bytes_read,
+        x.bytes_written
+      )?;
+    }
+    Ok(())
+  }
+}
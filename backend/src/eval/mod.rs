@@ -1,24 +1,72 @@
+mod budget;
+mod bundle;
+mod checkpoint;
+mod debugger;
 mod eval_error;
 mod evaluator;
 mod execution_node;
+mod hooks;
+mod lifecycle;
+mod metrics;
+mod report;
+mod task_registry;
+mod trace;
+mod usage;
+#[allow(dead_code)]
 mod waiters;
-use crate::{language::typing::DataValue, logging::Logger};
+use crate::{http::WebSocket, language::typing::DataValue, logging::Logger};
+pub use budget::*;
+pub use bundle::*;
+pub use checkpoint::*;
+pub use debugger::*;
 pub use eval_error::*;
 pub use evaluator::*;
 pub use execution_node::*;
+pub use hooks::*;
+pub use metrics::*;
+pub use report::*;
 use std::{pin::Pin, sync::Arc};
-use tokio::io::{AsyncRead, AsyncWrite};
+pub use task_registry::*;
+use tokio::{
+  io::{AsyncRead, AsyncWrite},
+  net::{TcpListener, UdpSocket},
+};
+pub use trace::*;
+pub use usage::*;
 
 pub trait Asyncio: AsyncRead + AsyncWrite + Send + Sync {}
 impl<T> Asyncio for T where T: AsyncRead + AsyncWrite + Send + Sync {}
 pub type IoObject = Pin<Box<dyn Asyncio>>;
 
-pub trait AsyncClone
+/// Everything a `Handle` can refer to. Only streams can be read from or written to,
+/// listeners hand out new stream handles through [`Evaluator::accept`] and UDP sockets
+/// are used through [`Evaluator::send_to`] and [`Evaluator::recv_from`], WebSockets
+/// through [`Evaluator::send_frame`] and [`Evaluator::recv_frame`].
+pub enum IoEntry
+{
+  Stream(IoObject),
+  Listener(Arc<TcpListener>),
+  Udp(Arc<UdpSocket>),
+  WebSocket(Arc<WebSocket>),
+  #[cfg(feature = "subprocess")]
+  Process(Arc<ChildProcess>),
+}
+
+/// A running subprocess. Its stdin and stdout are registered as separate stream
+/// handles, this entry is only used to wait for it to exit.
+#[cfg(feature = "subprocess")]
+pub struct ChildProcess
+{
+  pub child: tokio::sync::Mutex<tokio::process::Child>,
+  pub stdin: uuid::Uuid,
+}
+
+pub(crate) trait AsyncClone
 {
   async fn clone(&self) -> Self;
 }
 
-pub trait EvaluateIt
+pub(crate) trait EvaluateIt
 {
   async fn evaluate<Tl, Nl>(
     &self,
@@ -0,0 +1,133 @@
+use super::{node_label, NodeState};
+use serde::Serialize;
+use std::{fmt, time::Duration};
+use uuid::Uuid;
+
+/// How an instance's run went, as of it shutting down
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub enum Outcome
+{
+  /// Its end node ran
+  Completed,
+  /// One of its nodes failed, the first being `node`
+  Failed
+  {
+    node: Uuid, message: String
+  },
+  /// An `Abort` node stopped the run
+  Aborted
+  {
+    message: String, code: i32
+  },
+  /// It was shut down before its end node ran
+  Cancelled,
+}
+
+/// Where a node was left when its instance shut down
+#[derive(Serialize, Debug, Clone)]
+pub struct NodeReport
+{
+  pub node: Uuid,
+  pub name: Option<String>,
+  pub node_type: String,
+  pub state: NodeState,
+  /// Reads of its latest outputs that never happened
+  pub unread_outputs: usize,
+}
+
+impl NodeReport
+{
+  /// Whether the node was left part way through something
+  pub fn unfinished(&self) -> bool
+  {
+    self.state == NodeState::Processing || self.unread_outputs > 0
+  }
+}
+
+/// What [`super::Evaluator::shutdown`] found and cleaned up, including for the complex
+/// runners the instance started.
+#[derive(Serialize, Debug, Clone)]
+pub struct ShutdownReport
+{
+  pub scope_id: Uuid,
+  pub graph_path: String,
+  pub outcome: Outcome,
+  /// From the instance being made until it was shut down
+  pub ran_for: Duration,
+  pub teardown: Duration,
+  /// Every node of the graph, ordered by id
+  pub nodes: Vec<NodeReport>,
+  pub handles_closed: usize,
+  pub agents_destroyed: usize,
+  pub servers_stopped: usize,
+  pub runners: Vec<ShutdownReport>,
+}
+
+impl ShutdownReport
+{
+  /// Whether this instance or any of its runners failed, was aborted or left nodes
+  /// unfinished
+  pub fn is_clean(&self) -> bool
+  {
+    matches!(self.outcome, Outcome::Completed | Outcome::Cancelled)
+      && !self.nodes.iter().any(NodeReport::unfinished)
+      && self.runners.iter().all(Self::is_clean)
+  }
+
+  fn write_indented(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result
+  {
+    let indent = "  ".repeat(depth);
+    let outcome = match &self.outcome
+    {
+      Outcome::Completed => "completed".to_string(),
+      Outcome::Failed { node, message } => format!("failed at node {node}: {message}"),
+      Outcome::Aborted { message, code } => format!("aborted with code {code}: {message}"),
+      Outcome::Cancelled => "cancelled".to_string(),
+    };
+    writeln!(
+      f,
+      "{indent}Instance {} of {} {outcome}",
+      self.scope_id, self.graph_path
+    )?;
+    writeln!(
+      f,
+      "{indent}  ran for {:?}, teardown took {:?}",
+      self.ran_for, self.teardown
+    )?;
+    writeln!(
+      f,
+      "{indent}  closed {} handle(s), destroyed {} agent(s), stopped {} server(s)",
+      self.handles_closed, self.agents_destroyed, self.servers_stopped
+    )?;
+    // every node with {:#}, otherwise only the ones left part way through
+    let all = f.alternate();
+    for node in self.nodes.iter().filter(|x| all || x.unfinished())
+    {
+      write!(
+        f,
+        "{indent}  node {} {}: {:?}",
+        node_label(node.node, node.name.as_deref()),
+        node.node_type,
+        node.state
+      )?;
+      match node.unread_outputs
+      {
+        0 => writeln!(f)?,
+        n => writeln!(f, ", {n} output read(s) pending")?,
+      }
+    }
+    for runner in &self.runners
+    {
+      runner.write_indented(f, depth + 1)?;
+    }
+    Ok(())
+  }
+}
+
+impl fmt::Display for ShutdownReport
+{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+  {
+    self.write_indented(f, 0)
+  }
+}
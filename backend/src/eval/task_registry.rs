@@ -0,0 +1,218 @@
+use super::NodeResult;
+use serde::Serialize;
+use std::task::{Context, Poll};
+use std::{
+  future::Future,
+  pin::Pin,
+  sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, Mutex,
+  },
+};
+use tokio::{
+  sync::Notify,
+  task::{JoinError, JoinSet},
+};
+
+/// Counts of work still alive for an instance and everything it started. After
+/// [`super::Evaluator::shutdown`] every field is expected to be zero.
+#[derive(Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TaskStats
+{
+  pub node_tasks: usize,
+  pub complex_runners: usize,
+  pub io_operations: usize,
+  pub http_servers: usize,
+  /// Agent requests of the whole run waiting for room under the configured limits
+  pub agent_requests_queued: usize,
+  pub agent_requests_in_flight: usize,
+}
+
+/// What was still running when [`super::Evaluator::stop`] gave up waiting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StopSummary
+{
+  pub running_nodes: usize,
+  pub io_operations: usize,
+}
+
+impl StopSummary
+{
+  pub fn is_idle(&self) -> bool
+  {
+    *self == Self::default()
+  }
+}
+
+/// Owns every task spawned for one instance so they can be aborted and awaited together
+/// instead of being detached.
+pub struct TaskRegistry
+{
+  node_tasks: Mutex<NodeTasks>,
+  io_operations: Arc<AtomicUsize>,
+  /// Notified on every node task spawned
+  spawned: Notify,
+  /// Kept apart from the tasks, which are locked while ordered nodes are polled
+  ordered: bool,
+}
+
+type NodeFuture = Pin<Box<dyn Future<Output = NodeResult> + Send>>;
+
+enum NodeTasks
+{
+  /// Tasks of their own, racing on the runtime's threads
+  Spawned(JoinSet<NodeResult>),
+  /// Futures polled in the order they were added by whoever joins them, every one of
+  /// them each time any is woken
+  Ordered(Vec<NodeFuture>),
+}
+
+impl NodeTasks
+{
+  fn poll_join_next(&mut self, cx: &mut Context<'_>)
+    -> Poll<Option<Result<NodeResult, JoinError>>>
+  {
+    match self
+    {
+      Self::Spawned(tasks) => tasks.poll_join_next(cx),
+      Self::Ordered(tasks) if tasks.is_empty() => Poll::Ready(None),
+      Self::Ordered(tasks) =>
+      {
+        for i in 0..tasks.len()
+        {
+          if let Poll::Ready(result) = tasks[i].as_mut().poll(cx)
+          {
+            // the ones after it are polled on the next call, which starts over
+            drop(tasks.remove(i));
+            return Poll::Ready(Some(Ok(result)));
+          }
+        }
+        Poll::Pending
+      }
+    }
+  }
+
+  fn len(&self) -> usize
+  {
+    match self
+    {
+      Self::Spawned(tasks) => tasks.len(),
+      Self::Ordered(tasks) => tasks.len(),
+    }
+  }
+}
+
+/// Marks an IO operation as in flight until dropped, including when the task running it
+/// is aborted part way through.
+pub struct IoGuard(Arc<AtomicUsize>);
+
+impl Drop for IoGuard
+{
+  fn drop(&mut self)
+  {
+    self.0.fetch_sub(1, Ordering::AcqRel);
+  }
+}
+
+impl Default for TaskRegistry
+{
+  fn default() -> Self
+  {
+    Self::new()
+  }
+}
+
+impl TaskRegistry
+{
+  pub fn new() -> Self
+  {
+    Self {
+      node_tasks: Mutex::new(NodeTasks::Spawned(JoinSet::new())),
+      io_operations: Arc::new(AtomicUsize::new(0)),
+      spawned: Notify::new(),
+      ordered: false,
+    }
+  }
+
+  /// A registry running its nodes on the task calling [`Self::join_next`] rather than
+  /// spawning them, polling them in the order they were added so the same graph makes
+  /// the same progress every run. A node panicking takes that task down with it.
+  pub fn ordered() -> Self
+  {
+    Self {
+      node_tasks: Mutex::new(NodeTasks::Ordered(vec![])),
+      io_operations: Arc::new(AtomicUsize::new(0)),
+      spawned: Notify::new(),
+      ordered: true,
+    }
+  }
+
+  pub fn spawn_node<F>(&self, task: F)
+  where
+    F: Future<Output = NodeResult> + Send + 'static,
+  {
+    match &mut *self.node_tasks.lock().unwrap()
+    {
+      NodeTasks::Spawned(tasks) =>
+      {
+        tasks.spawn(task);
+      }
+      NodeTasks::Ordered(tasks) => tasks.push(Box::pin(task)),
+    }
+    self.spawned.notify_one();
+  }
+
+  /// Waits for a node task to be spawned, returning at once if one was since the last
+  /// call
+  pub async fn spawned(&self)
+  {
+    self.spawned.notified().await
+  }
+
+  pub fn is_ordered(&self) -> bool
+  {
+    self.ordered
+  }
+
+  /// Waits for the next node task to end, or returns None once there are none left.
+  /// The set is only locked while polled, so tasks can be spawned meanwhile. Ordered
+  /// nodes only make progress while this is awaited.
+  pub async fn join_next(&self) -> Option<Result<NodeResult, JoinError>>
+  {
+    std::future::poll_fn(|cx| self.node_tasks.lock().unwrap().poll_join_next(cx)).await
+  }
+
+  /// Aborts every node task and waits until they have all stopped.
+  pub async fn abort_all(&self)
+  {
+    let mut tasks = match &mut *self.node_tasks.lock().unwrap()
+    {
+      NodeTasks::Spawned(tasks) => std::mem::take(tasks),
+      // dropping a future is all it takes to stop it
+      NodeTasks::Ordered(tasks) =>
+      {
+        tasks.clear();
+        return;
+      }
+    };
+    tasks.abort_all();
+    while tasks.join_next().await.is_some()
+    {}
+  }
+
+  pub fn track_io(&self) -> IoGuard
+  {
+    self.io_operations.fetch_add(1, Ordering::AcqRel);
+    IoGuard(self.io_operations.clone())
+  }
+
+  pub fn node_tasks(&self) -> usize
+  {
+    self.node_tasks.lock().unwrap().len()
+  }
+
+  pub fn io_operations(&self) -> usize
+  {
+    self.io_operations.load(Ordering::Acquire)
+  }
+}
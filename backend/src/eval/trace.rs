@@ -0,0 +1,244 @@
+use super::{type_label, EvalError, NodeHooks, NodeInfo};
+use crate::language::typing::DataValue;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+  collections::HashMap,
+  str::FromStr,
+  sync::Mutex,
+  time::{Duration, Instant},
+};
+use uuid::Uuid;
+
+/// How [`TraceRecorder::render`] writes a trace
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TraceFormat
+{
+  /// An array of [`TraceEntry`]
+  #[default]
+  Json,
+  /// Chrome's trace event format, for `chrome://tracing` or Perfetto, with a row per
+  /// instance
+  Chrome,
+}
+
+impl FromStr for TraceFormat
+{
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err>
+  {
+    match s
+    {
+      "json" => Ok(Self::Json),
+      "chrome" => Ok(Self::Chrome),
+      _ => Err(format!("expected json or chrome, got `{s}`")),
+    }
+  }
+}
+
+/// One run of a node
+#[derive(Serialize, Debug, Clone)]
+pub struct TraceEntry
+{
+  /// The instance running the node
+  pub scope_id: Uuid,
+  /// The node's id in its graph file
+  pub node: Uuid,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub name: Option<String>,
+  /// The kind of node, such as `BinOp`
+  pub node_type: String,
+  /// How many times the node had run in its instance, this run included
+  pub iteration: usize,
+  /// When the run started, in microseconds since the recording did
+  pub start_us: u64,
+  pub duration_us: u64,
+  pub inputs: Vec<DataValue>,
+  pub outputs: Vec<DataValue>,
+  pub error: Option<String>,
+  /// Whether the run ended, runs cut short by the instance shutting down have not
+  pub finished: bool,
+}
+
+/// Records every node run of the instances it is registered on, see
+/// [`super::Evaluator::record_trace`].
+pub struct TraceRecorder
+{
+  started: Instant,
+  /// Runs started but not yet ended, by instance and node
+  running: Mutex<HashMap<(Uuid, Uuid), TraceEntry>>,
+  iterations: Mutex<HashMap<(Uuid, Uuid), usize>>,
+  finished: Mutex<Vec<TraceEntry>>,
+}
+
+impl Default for TraceRecorder
+{
+  fn default() -> Self
+  {
+    Self {
+      started: Instant::now(),
+      running: Mutex::default(),
+      iterations: Mutex::default(),
+      finished: Mutex::default(),
+    }
+  }
+}
+
+impl TraceRecorder
+{
+  fn micros(duration: Duration) -> u64
+  {
+    duration.as_micros() as u64
+  }
+
+  /// Every run recorded so far, unfinished ones included, ordered by when they started
+  pub fn entries(&self) -> Vec<TraceEntry>
+  {
+    let mut entries = self.finished.lock().unwrap().clone();
+    let now = Self::micros(self.started.elapsed());
+    entries.extend(self.running.lock().unwrap().values().map(|x| {
+      TraceEntry {
+        duration_us: now - x.start_us,
+        ..x.clone()
+      }
+    }));
+    entries.sort_by_key(|x| x.start_us);
+    entries
+  }
+
+  pub fn render(&self, format: TraceFormat) -> String
+  {
+    let entries = self.entries();
+    let trace = match format
+    {
+      TraceFormat::Json => serde_json::to_value(&entries).unwrap_or_default(),
+      TraceFormat::Chrome => chrome_trace(&entries),
+    };
+    serde_json::to_string_pretty(&trace).unwrap_or_default()
+  }
+
+  /// Starts recording a run of `node`, counting it as the node's next iteration
+  fn start(&self, node: &NodeInfo<'_>, inputs: Vec<DataValue>)
+  {
+    let key = (node.scope_id, node.node);
+    let iteration = {
+      let mut iterations = self.iterations.lock().unwrap();
+      let count = iterations.entry(key).or_default();
+      *count += 1;
+      *count
+    };
+    let entry = TraceEntry {
+      scope_id: node.scope_id,
+      node: node.node,
+      name: node.name.map(str::to_string),
+      node_type: type_label(node.node_type),
+      iteration,
+      start_us: Self::micros(self.started.elapsed()),
+      duration_us: 0,
+      inputs,
+      outputs: vec![],
+      error: None,
+      finished: false,
+    };
+    self.running.lock().unwrap().insert(key, entry);
+  }
+
+  fn finish(&self, node: &NodeInfo<'_>, error: Option<String>)
+  {
+    let key = (node.scope_id, node.node);
+    // a node rejected by an earlier hook never reached this one's start
+    if !self.running.lock().unwrap().contains_key(&key)
+    {
+      self.start(node, vec![]);
+    }
+    let Some(mut entry) = self.running.lock().unwrap().remove(&key)
+    else
+    {
+      return;
+    };
+    entry.duration_us = Self::micros(self.started.elapsed()) - entry.start_us;
+    entry.error = error;
+    entry.finished = true;
+    self.finished.lock().unwrap().push(entry);
+  }
+}
+
+/// Complete events, with each instance as a thread named after its scope id and each
+/// run named after its node, or the node's type if it has no name
+fn chrome_trace(entries: &[TraceEntry]) -> Value
+{
+  let mut threads: Vec<Uuid> = vec![];
+  let mut events = vec![];
+  for entry in entries
+  {
+    let tid = match threads.iter().position(|x| *x == entry.scope_id)
+    {
+      Some(tid) => tid,
+      None =>
+      {
+        threads.push(entry.scope_id);
+        events.push(json!({
+          "name": "thread_name",
+          "ph": "M",
+          "pid": 1,
+          "tid": threads.len() - 1,
+          "args": { "name": entry.scope_id.to_string() },
+        }));
+        threads.len() - 1
+      }
+    };
+    events.push(json!({
+      "name": entry.name.as_ref().unwrap_or(&entry.node_type),
+      "cat": "node",
+      "ph": "X",
+      "ts": entry.start_us,
+      "dur": entry.duration_us,
+      "pid": 1,
+      "tid": tid,
+      "args": {
+        "node": entry.node,
+        "node_type": entry.node_type,
+        "iteration": entry.iteration,
+        "inputs": entry.inputs,
+        "outputs": entry.outputs,
+        "error": entry.error,
+        "finished": entry.finished,
+      },
+    }));
+  }
+  json!({ "traceEvents": events, "displayTimeUnit": "ms" })
+}
+
+#[async_trait::async_trait]
+impl NodeHooks for TraceRecorder
+{
+  async fn on_node_start(&self, node: NodeInfo<'_>, inputs: &[DataValue]) -> Result<(), String>
+  {
+    self.start(&node, inputs.to_vec());
+    Ok(())
+  }
+
+  async fn on_value(&self, node: NodeInfo<'_>, _port: usize, value: &DataValue)
+  {
+    if let Some(entry) = self
+      .running
+      .lock()
+      .unwrap()
+      .get_mut(&(node.scope_id, node.node))
+    {
+      entry.outputs.push(value.clone());
+    }
+  }
+
+  async fn on_node_end(&self, node: NodeInfo<'_>, _elapsed: Duration)
+  {
+    self.finish(&node, None);
+  }
+
+  async fn on_error(&self, node: NodeInfo<'_>, error: &EvalError)
+  {
+    self.finish(&node, Some(error.to_string()));
+  }
+}
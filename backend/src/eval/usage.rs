@@ -0,0 +1,145 @@
+use crate::ai::{AgentType, ModelPrice, TokenUsage};
+use serde::Serialize;
+use std::{
+  collections::{BTreeMap, HashMap},
+  fmt,
+  sync::Mutex,
+};
+use uuid::Uuid;
+
+/// What the agents one `Create` node made used over a run
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct AgentUsage
+{
+  /// The graph file holding the node
+  pub graph: String,
+  pub node: Uuid,
+  pub agent_type: AgentType,
+  pub model: String,
+  /// Requests sent, including those answering tool calls
+  pub calls: u64,
+  #[serde(flatten)]
+  pub tokens: TokenUsage,
+  /// In dollars, None if the config's `[pricing]` has no price for the model
+  pub cost: Option<f64>,
+}
+
+/// Token use and cost of every agent of a run, see [`super::Evaluator::usage`]
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct UsageReport
+{
+  pub agents: Vec<AgentUsage>,
+  pub calls: u64,
+  #[serde(flatten)]
+  pub tokens: TokenUsage,
+  /// Of the agents whose models have a price, None if none do
+  pub cost: Option<f64>,
+}
+
+/// Counts the calls and tokens of a run's agents by the node that created them, kept
+/// on the root instance
+#[derive(Default)]
+pub(super) struct UsageLedger
+{
+  agents: Mutex<BTreeMap<(String, Uuid), AgentUsage>>,
+}
+
+impl UsageLedger
+{
+  /// Counts a call of an agent of type `agent_type` running `model`, made by `node` of
+  /// `graph`
+  pub fn record(
+    &self,
+    graph: &str,
+    node: Uuid,
+    agent_type: &AgentType,
+    model: &str,
+    usage: TokenUsage,
+  )
+  {
+    let mut agents = self.agents.lock().unwrap();
+    let entry = agents.entry((graph.to_string(), node)).or_insert_with(|| {
+      AgentUsage {
+        graph: graph.to_string(),
+        node,
+        agent_type: agent_type.clone(),
+        model: model.to_string(),
+        calls: 0,
+        tokens: TokenUsage::default(),
+        cost: None,
+      }
+    });
+    entry.calls += 1;
+    entry.tokens += usage;
+  }
+
+  /// What has been used so far, priced by `pricing`
+  pub fn report(&self, pricing: &HashMap<String, ModelPrice>) -> UsageReport
+  {
+    let agents: Vec<AgentUsage> = self
+      .agents
+      .lock()
+      .unwrap()
+      .values()
+      .map(|x| {
+        AgentUsage {
+          cost: pricing.get(&x.model).map(|price| price.cost(x.tokens)),
+          ..x.clone()
+        }
+      })
+      .collect();
+    let mut tokens = TokenUsage::default();
+    for agent in &agents
+    {
+      tokens += agent.tokens;
+    }
+    UsageReport {
+      calls: agents.iter().map(|x| x.calls).sum(),
+      tokens,
+      cost: agents.iter().filter_map(|x| x.cost).reduce(|a, b| a + b),
+      agents,
+    }
+  }
+}
+
+impl fmt::Display for UsageReport
+{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+  {
+    let cost = |x: Option<f64>| x.map_or("-".to_string(), |x| format!("${x:.4}"));
+    writeln!(
+      f,
+      "{:<36}  {:<20} {:>6} {:>10} {:>10} {:>10}",
+      "node", "model", "calls", "prompt", "completion", "cost"
+    )?;
+    let mut graph = None;
+    for x in &self.agents
+    {
+      if graph != Some(&x.graph)
+      {
+        writeln!(f, "{}", x.graph)?;
+        graph = Some(&x.graph);
+      }
+      writeln!(
+        f,
+        "{}  {:<20} {:>6} {:>10} {:>10} {:>10}",
+        x.node,
+        x.model,
+        x.calls,
+        x.tokens.prompt_tokens,
+        x.tokens.completion_tokens,
+        cost(x.cost)
+      )?;
+    }
+    writeln!(
+      f,
+      "{:<36}  {:<20} {:>6} {:>10} {:>10} {:>10}",
+      "total",
+      "",
+      self.calls,
+      self.tokens.prompt_tokens,
+      self.tokens.completion_tokens,
+      cost(self.cost)
+    )
+  }
+}
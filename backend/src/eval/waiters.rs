@@ -5,7 +5,7 @@ use tokio::sync::Notify;
 #[async_trait::async_trait]
 pub trait Waiter
 {
-  async fn wait(&self, triggers: &Vec<Notify>);
+  async fn wait(&self, triggers: &[Notify]);
 }
 
 pub struct AllWaiter;
@@ -13,7 +13,7 @@ pub struct AllWaiter;
 #[async_trait::async_trait]
 impl Waiter for AllWaiter
 {
-  async fn wait(&self, triggers: &Vec<Notify>)
+  async fn wait(&self, triggers: &[Notify])
   {
     for x in triggers
     {
@@ -27,17 +27,14 @@ pub struct OneWaiter;
 #[async_trait::async_trait]
 impl Waiter for OneWaiter
 {
-  async fn wait(&self, triggers: &Vec<Notify>)
+  async fn wait(&self, triggers: &[Notify])
   {
     let mut unordered: FuturesUnordered<_> = triggers.iter().map(|x| x.notified()).collect();
     unordered.next().await;
   }
 }
 
-pub fn get_waiter(node_type: &NodeType) -> Box<dyn Waiter>
+pub fn get_waiter(_node_type: &NodeType) -> Box<dyn Waiter>
 {
-  match node_type
-  {
-    _ => Box::new(OneWaiter),
-  }
+  Box::new(OneWaiter)
 }
@@ -0,0 +1,194 @@
+//! A C ABI for hosts that cannot link the crate directly, declared in
+//! `include/agentnodes.h`. Each graph handle owns the runtime its instances run on.
+//! Panics are caught at each entry point, as unwinding into the host aborts it.
+
+use crate::{Config, DataValue, Evaluator, NodeStateLogger};
+use std::{
+  any::Any,
+  ffi::{c_char, c_int, CStr, CString},
+  panic::{catch_unwind, AssertUnwindSafe},
+  path::Path,
+  sync::Arc,
+};
+
+/// A graph loaded by [`agentnodes_load`]
+pub struct AgentNodesGraph
+{
+  runtime: tokio::runtime::Runtime,
+  graph: Arc<Evaluator<NodeStateLogger, NodeStateLogger>>,
+  /// The outputs or error of the last run, as JSON
+  output: CString,
+}
+
+/// What a caught panic was raised with
+fn panic_message(payload: &(dyn Any + Send)) -> String
+{
+  match (
+    payload.downcast_ref::<&str>(),
+    payload.downcast_ref::<String>(),
+  )
+  {
+    (Some(x), _) => x.to_string(),
+    (_, Some(x)) => x.clone(),
+    _ => "panicked".to_string(),
+  }
+}
+
+/// Loads the graph file at `path`, with the `agentnodes.toml` closest to it if any.
+/// Returns null if either cannot be read, or if `run` would refuse the graph: the
+/// Complex graphs it uses lead back to one another or the config's sandbox denies one
+/// of its nodes.
+///
+/// # Safety
+///
+/// `path` must be a valid nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn agentnodes_load(path: *const c_char) -> *mut AgentNodesGraph
+{
+  catch_unwind(|| load(path)).unwrap_or(std::ptr::null_mut())
+}
+
+unsafe fn load(path: *const c_char) -> *mut AgentNodesGraph
+{
+  if path.is_null()
+  {
+    return std::ptr::null_mut();
+  }
+  let Ok(path) = CStr::from_ptr(path).to_str()
+  else
+  {
+    return std::ptr::null_mut();
+  };
+  let config = match Config::discover(Path::new(path).parent().unwrap_or(Path::new("")))
+  {
+    Some(config) => Config::load(&config).ok(),
+    None => Some(Config::default()),
+  };
+  let (Some(config), Ok(runtime)) = (config, tokio::runtime::Runtime::new())
+  else
+  {
+    return std::ptr::null_mut();
+  };
+  let Ok(graph) = Evaluator::new(path.to_string(), None, None, None)
+  else
+  {
+    return std::ptr::null_mut();
+  };
+  runtime.block_on(graph.set_config(config));
+  match runtime.block_on(graph.clone().check_before_run())
+  {
+    Ok(denied) if denied.is_empty() => (),
+    _ => return std::ptr::null_mut(),
+  }
+  Box::into_raw(Box::new(AgentNodesGraph {
+    runtime,
+    graph,
+    output: CString::default(),
+  }))
+}
+
+/// Runs the graph to completion with `inputs_json`, a JSON array of its inputs as in
+/// an `eval` dataset, or none if null. Returns 0 if it succeeded and -1 if it failed;
+/// either way the result can then be read with [`agentnodes_get_output_json`].
+///
+/// # Safety
+///
+/// `graph` must come from [`agentnodes_load`] and not have been freed, and
+/// `inputs_json` must be null or a valid nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn agentnodes_run(
+  graph: *mut AgentNodesGraph,
+  inputs_json: *const c_char,
+) -> c_int
+{
+  let Some(handle) = graph.as_mut()
+  else
+  {
+    return -1;
+  };
+  let inputs = match inputs_json.is_null()
+  {
+    true => Ok(vec![]),
+    false =>
+    {
+      CStr::from_ptr(inputs_json)
+        .to_str()
+        .map_err(|e| e.to_string())
+        .and_then(|x| serde_json::from_str::<Vec<DataValue>>(x).map_err(|e| e.to_string()))
+    }
+  };
+  let result = match inputs
+  {
+    Ok(inputs) =>
+    {
+      catch_unwind(AssertUnwindSafe(|| {
+        handle.runtime.block_on(run(&handle.graph, inputs))
+      }))
+      .unwrap_or_else(|payload| {
+        Err(serde_json::json!({"kind": "Panicked", "message": panic_message(&*payload)}))
+      })
+    }
+    Err(e) => Err(serde_json::json!({"kind": "InvalidInputs", "message": e})),
+  };
+  let (json, code) = match result
+  {
+    Ok(outputs) => (serde_json::to_string(&outputs), 0),
+    Err(error) =>
+    {
+      (
+        serde_json::to_string(&serde_json::json!({ "error": error })),
+        -1,
+      )
+    }
+  };
+  // JSON escapes control characters, so it never contains a nul
+  handle.output = CString::new(json.unwrap_or_default()).unwrap_or_default();
+  code
+}
+
+async fn run(
+  graph: &Arc<Evaluator<NodeStateLogger, NodeStateLogger>>,
+  inputs: Vec<DataValue>,
+) -> Result<Vec<DataValue>, serde_json::Value>
+{
+  let instance = graph.clone().instantiate(inputs).await;
+  let outputs = instance
+    .get_outputs()
+    .await
+    .map_err(|e| serde_json::to_value(e).unwrap_or_default());
+  instance.shutdown().await;
+  outputs
+}
+
+/// The result of the last [`agentnodes_run`]: a JSON array of the outputs, or an
+/// object with the `error` if it failed. Empty before the first run. The string is
+/// owned by the graph and valid until it is run again or freed.
+///
+/// # Safety
+///
+/// `graph` must come from [`agentnodes_load`] and not have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn agentnodes_get_output_json(graph: *const AgentNodesGraph)
+  -> *const c_char
+{
+  match graph.as_ref()
+  {
+    Some(handle) => handle.output.as_ptr(),
+    None => std::ptr::null(),
+  }
+}
+
+/// Frees a graph loaded by [`agentnodes_load`]. Does nothing if `graph` is null.
+///
+/// # Safety
+///
+/// `graph` must come from [`agentnodes_load`] and not already have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn agentnodes_free(graph: *mut AgentNodesGraph)
+{
+  if !graph.is_null()
+  {
+    // there is no one to report a panic while freeing to
+    let _ = catch_unwind(AssertUnwindSafe(|| drop(Box::from_raw(graph))));
+  }
+}
@@ -0,0 +1,535 @@
+use crate::cli::{CompareArgs, EvalArgs, TestArgs};
+use agent_nodes_core::{
+  ai::{AgentArgs, AgentType},
+  config::DryRun,
+  Config, DataValue, EvalError, Evaluator, NodeStateLogger,
+};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{
+  path::{Path, PathBuf},
+  sync::Arc,
+  time::{Duration, Instant},
+};
+
+type Graph = Arc<Evaluator<NodeStateLogger, NodeStateLogger>>;
+
+/// One line of an `eval` dataset. Every check given must pass for the case to pass; a
+/// case without any only has to run without failing.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Case
+{
+  #[serde(default)]
+  pub inputs: Vec<DataValue>,
+  /// Outputs the graph must produce, compared like the `Eq` node does
+  pub expected: Option<Vec<DataValue>>,
+  /// A regex that must match the outputs, written one per line
+  pub matches: Option<String>,
+  /// Criteria the judge agent is asked whether the outputs meet
+  pub judge: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Check
+{
+  pub kind: &'static str,
+  pub passed: bool,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub detail: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CaseReport
+{
+  pub case: usize,
+  pub passed: bool,
+  pub latency_ms: u128,
+  pub tokens: u64,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub cost: Option<f64>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub outputs: Option<Vec<DataValue>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub error: Option<String>,
+  pub checks: Vec<Check>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Report
+{
+  pub graph: String,
+  pub passed: usize,
+  pub total: usize,
+  pub cases: Vec<CaseReport>,
+}
+
+/// Reads a JSON Lines dataset, skipping blank lines.
+pub fn read_dataset(path: &Path) -> Result<Vec<Case>, String>
+{
+  let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+  text
+    .lines()
+    .enumerate()
+    .filter(|(_, line)| !line.trim().is_empty())
+    .map(|(i, line)| {
+      serde_json::from_str(line).map_err(|e| format!("{}:{}: {e}", path.display(), i + 1))
+    })
+    .collect()
+}
+
+/// One run of a graph
+pub struct CaseRun
+{
+  /// The outputs, or the error the graph failed with
+  pub outputs: Result<Vec<DataValue>, String>,
+  pub latency: Duration,
+  /// Prompt and completion tokens of its agents' calls, as `--usage` counts them
+  pub tokens: u64,
+  /// What the calls cost, None if none of the models have a price
+  pub cost: Option<f64>,
+}
+
+/// Tokens and, if priced, cost, as `compare` prints them
+fn usage(tokens: u64, cost: Option<f64>) -> String
+{
+  match cost
+  {
+    Some(cost) => format!("{tokens} tokens ${cost:.4}"),
+    None => format!("{tokens} tokens"),
+  }
+}
+
+/// Loads a graph to run cases with, exiting if it cannot be.
+pub async fn load_graph(path: &Path, config: &Config) -> Graph
+{
+  let graph = Evaluator::<NodeStateLogger, NodeStateLogger>::new(
+    path.to_str().unwrap().to_string(),
+    None,
+    None,
+    None,
+  )
+  .unwrap_or_else(|e| {
+    eprintln!("{}: {e}", path.display());
+    std::process::exit(2)
+  });
+  graph.set_config(config.clone()).await;
+  if let Err(e) = graph.clone().check_cycles().await
+  {
+    eprintln!("{}: {e}", path.display());
+    std::process::exit(2)
+  }
+  graph
+}
+
+/// Runs the graph once with `inputs`.
+pub async fn run_case(graph: &Graph, inputs: Vec<DataValue>, deadline: Option<Duration>)
+  -> CaseRun
+{
+  if let Some(deadline) = deadline
+  {
+    graph.set_deadline(deadline).await;
+  }
+  let start = Instant::now();
+  let instance = graph.clone().instantiate(inputs).await;
+  let outputs = instance.get_outputs().await.map_err(|e| e.to_string());
+  let latency = start.elapsed();
+  let usage = instance.usage().await;
+  instance.shutdown().await;
+  CaseRun {
+    outputs,
+    latency,
+    tokens: usage.tokens.total(),
+    cost: usage.cost,
+  }
+}
+
+/// Asks a fresh agent whether `outputs` meet `criteria`, passing if its answer starts
+/// with `PASS`.
+async fn judge(
+  judge: &(AgentType, String),
+  config: &Config,
+  criteria: &str,
+  inputs: &[DataValue],
+  outputs: &[DataValue],
+) -> Check
+{
+  let args = AgentArgs::from_values(&[DataValue::String(judge.1.clone())])
+    .expect("a model name is valid agent args");
+  let agent = judge.0.clone().create(args, &config.providers);
+  let prompt = format!(
+    "{criteria}\n\nInputs: {}\nOutputs: {}\n\nAnswer PASS if the outputs meet the criteria \
+     above, otherwise FAIL, followed by your reasoning.",
+    serde_json::to_string(inputs).unwrap_or_default(),
+    serde_json::to_string(outputs).unwrap_or_default(),
+  );
+  let answer = match agent.send_chat(agent.create_body(prompt).await).await
+  {
+    Ok(_) =>
+    {
+      agent
+        .get_last_response()
+        .await
+        .and_then(|x| x.get_content())
+    }
+    Err(e) =>
+    {
+      return Check {
+        kind: "judge",
+        passed: false,
+        detail: Some(EvalError::from(e).to_string()),
+      }
+    }
+  };
+  let answer = answer.unwrap_or_default();
+  Check {
+    kind: "judge",
+    passed: answer.trim_start().to_uppercase().starts_with("PASS"),
+    detail: Some(answer),
+  }
+}
+
+async fn score(
+  case: &Case,
+  outputs: &[DataValue],
+  judge_agent: Option<&(AgentType, String)>,
+  config: &Config,
+) -> Vec<Check>
+{
+  let mut checks = vec![];
+  if let Some(expected) = &case.expected
+  {
+    let passed = expected.len() == outputs.len()
+      && expected.iter().zip(outputs).all(|(a, b)| a.equals(b, 0.0));
+    checks.push(Check {
+      kind: "exact",
+      passed,
+      detail: (!passed).then(|| format!("expected {expected:?}")),
+    });
+  }
+  if let Some(pattern) = &case.matches
+  {
+    let text = outputs
+      .iter()
+      .map(ToString::to_string)
+      .collect::<Vec<_>>()
+      .join("\n");
+    checks.push(match Regex::new(pattern)
+    {
+      Ok(regex) =>
+      {
+        let passed = regex.is_match(&text);
+        Check {
+          kind: "regex",
+          passed,
+          detail: (!passed).then(|| format!("{pattern:?} did not match")),
+        }
+      }
+      Err(e) =>
+      {
+        Check {
+          kind: "regex",
+          passed: false,
+          detail: Some(e.to_string()),
+        }
+      }
+    });
+  }
+  if let Some(criteria) = &case.judge
+  {
+    checks.push(match judge_agent
+    {
+      Some(agent) => judge(agent, config, criteria, &case.inputs, outputs).await,
+      None =>
+      {
+        Check {
+          kind: "judge",
+          passed: false,
+          detail: Some("no --judge given".to_string()),
+        }
+      }
+    });
+  }
+  checks
+}
+
+/// Runs every case of the dataset through the graph, printing a line per case and a
+/// summary. Exits with 1 if any case failed.
+pub async fn evaluate(args: EvalArgs, config: Config)
+{
+  let cases = read_dataset(&args.dataset).unwrap_or_else(|e| {
+    eprintln!("{e}");
+    std::process::exit(2)
+  });
+  let graph = load_graph(&args.filename, &config).await;
+  let deadline = args.deadline;
+
+  let mut reports = vec![];
+  for (i, case) in cases.iter().enumerate()
+  {
+    let run = run_case(&graph, case.inputs.clone(), deadline).await;
+    let (checks, error) = match &run.outputs
+    {
+      Ok(outputs) =>
+      {
+        (
+          score(case, outputs, args.judge.as_ref(), &config).await,
+          None,
+        )
+      }
+      Err(e) => (vec![], Some(e.clone())),
+    };
+    let passed = error.is_none() && checks.iter().all(|x| x.passed);
+    let report = CaseReport {
+      case: i + 1,
+      passed,
+      latency_ms: run.latency.as_millis(),
+      tokens: run.tokens,
+      cost: run.cost,
+      outputs: run.outputs.ok(),
+      error,
+      checks,
+    };
+    print_case(&report);
+    reports.push(report);
+  }
+
+  let report = Report {
+    graph: args.filename.display().to_string(),
+    passed: reports.iter().filter(|x| x.passed).count(),
+    total: reports.len(),
+    cases: reports,
+  };
+  println!("{}/{} cases passed", report.passed, report.total);
+  if let Some(path) = &args.report
+  {
+    let json = serde_json::to_string_pretty(&report).unwrap();
+    if let Err(e) = std::fs::write(path, json)
+    {
+      eprintln!("{}: {e}", path.display());
+      std::process::exit(2);
+    }
+  }
+  std::process::exit(if report.passed == report.total { 0 } else { 1 });
+}
+
+fn print_case(report: &CaseReport)
+{
+  let verdict = if report.passed { "pass" } else { "FAIL" };
+  println!("case {}: {verdict} in {}ms", report.case, report.latency_ms);
+  if let Some(error) = &report.error
+  {
+    println!("  {error}");
+  }
+  for check in report.checks.iter().filter(|x| !x.passed)
+  {
+    match &check.detail
+    {
+      Some(detail) => println!("  {}: {detail}", check.kind),
+      None => println!("  {} failed", check.kind),
+    }
+  }
+  if !report.passed
+  {
+    if let Some(outputs) = &report.outputs
+    {
+      println!("  got {outputs:?}");
+    }
+  }
+}
+
+/// Runs two graphs over the same dataset, printing for each case whether their outputs
+/// differ and how their latency, tokens and cost compare, then the totals. Exits with 1 if
+/// any case's outputs differ.
+pub async fn compare(args: CompareArgs, config: Config)
+{
+  let cases = read_dataset(&args.inputs).unwrap_or_else(|e| {
+    eprintln!("{e}");
+    std::process::exit(2)
+  });
+  let a = load_graph(&args.a, &config).await;
+  let b = load_graph(&args.b, &config).await;
+  let deadline = args.deadline;
+
+  let mut same = 0;
+  let mut totals = [(Duration::ZERO, 0, None); 2];
+  for (i, case) in cases.iter().enumerate()
+  {
+    let runs = [
+      run_case(&a, case.inputs.clone(), deadline).await,
+      run_case(&b, case.inputs.clone(), deadline).await,
+    ];
+    let matching = match (&runs[0].outputs, &runs[1].outputs)
+    {
+      (Ok(x), Ok(y)) => x.len() == y.len() && x.iter().zip(y).all(|(x, y)| x.equals(y, 0.0)),
+      (Err(x), Err(y)) => x == y,
+      _ => false,
+    };
+    println!(
+      "case {}: {}, a {}ms {}, b {}ms {}",
+      i + 1,
+      if matching { "same" } else { "DIFFERENT" },
+      runs[0].latency.as_millis(),
+      usage(runs[0].tokens, runs[0].cost),
+      runs[1].latency.as_millis(),
+      usage(runs[1].tokens, runs[1].cost)
+    );
+    if matching
+    {
+      same += 1;
+    }
+    else
+    {
+      for (name, run) in ["a", "b"].iter().zip(&runs)
+      {
+        match &run.outputs
+        {
+          Ok(outputs) => println!("  {name}: {outputs:?}"),
+          Err(e) => println!("  {name}: {e}"),
+        }
+      }
+    }
+    for (total, run) in totals.iter_mut().zip(&runs)
+    {
+      total.0 += run.latency;
+      total.1 += run.tokens;
+      if let Some(cost) = run.cost
+      {
+        total.2 = Some(total.2.unwrap_or(0.0) + cost);
+      }
+    }
+  }
+  println!(
+    "{same}/{} cases the same, a {}ms {}, b {}ms {}",
+    cases.len(),
+    totals[0].0.as_millis(),
+    usage(totals[0].1, totals[0].2),
+    totals[1].0.as_millis(),
+    usage(totals[1].1, totals[1].2)
+  );
+  std::process::exit(if same == cases.len() { 0 } else { 1 });
+}
+
+/// A `test` manifest
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TestManifest
+{
+  pub tests: Vec<GraphTest>,
+}
+
+/// One graph run and the outputs it must give
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct GraphTest
+{
+  pub name: String,
+  /// The graph to run, relative to the manifest
+  pub graph: PathBuf,
+  #[serde(default)]
+  pub inputs: Vec<DataValue>,
+  /// Compared like the `Eq` node does
+  pub expected: Vec<DataValue>,
+  /// Runs the graph dry with these replies and streams, stream files being relative
+  /// to the manifest
+  pub mocks: Option<DryRun>,
+}
+
+/// Reads a test manifest, making its paths absolute.
+pub fn read_manifest(path: &Path) -> Result<TestManifest, String>
+{
+  let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+  let mut manifest: TestManifest =
+    serde_json::from_str(&text).map_err(|e| format!("{}: {e}", path.display()))?;
+  let dir = std::path::absolute(path.parent().unwrap_or(Path::new("")))
+    .map_err(|e| format!("{}: {e}", path.display()))?;
+  for test in &mut manifest.tests
+  {
+    test.graph = dir.join(&test.graph);
+    for stream in test.mocks.iter_mut().flat_map(|x| x.streams.values_mut())
+    {
+      *stream = dir.join(&*stream);
+    }
+  }
+  Ok(manifest)
+}
+
+/// How `got` differs from `expected`, a line per output that does not match.
+pub fn diff_outputs(expected: &[DataValue], got: &[DataValue]) -> Vec<String>
+{
+  (0..expected.len().max(got.len()))
+    .filter_map(|i| {
+      match (expected.get(i), got.get(i))
+      {
+        (Some(x), Some(y)) if x.equals(y, 0.0) => None,
+        (Some(x), Some(y)) => Some(format!("output {i}: expected {x:?}, got {y:?}")),
+        (Some(x), None) => Some(format!("output {i}: expected {x:?}, got nothing")),
+        (None, Some(y)) => Some(format!("output {i}: expected nothing, got {y:?}")),
+        (None, None) => None,
+      }
+    })
+    .collect()
+}
+
+/// Runs the tests of a manifest, each with a freshly loaded graph, printing a line per
+/// test with the differences of those failing and a summary. Exits with 1 if any
+/// failed.
+pub async fn test(args: TestArgs, config: Config)
+{
+  let manifest = read_manifest(&args.manifest).unwrap_or_else(|e| {
+    eprintln!("{e}");
+    std::process::exit(2)
+  });
+  let deadline = args.deadline;
+  let tests: Vec<GraphTest> = manifest
+    .tests
+    .into_iter()
+    .filter(|x| {
+      args
+        .filter
+        .as_ref()
+        .is_none_or(|f| x.name.contains(f.as_str()))
+    })
+    .collect();
+
+  let mut passed = 0;
+  for test in &tests
+  {
+    let mut config = config.clone();
+    if let Some(mocks) = &test.mocks
+    {
+      config.dry_run = DryRun {
+        enabled: true,
+        ..mocks.clone()
+      };
+      config.sandbox.allow_network = false;
+    }
+    let graph = load_graph(&test.graph, &config).await;
+    let run = run_case(&graph, test.inputs.clone(), deadline).await;
+    let problems = match &run.outputs
+    {
+      Ok(outputs) => diff_outputs(&test.expected, outputs),
+      Err(e) => vec![e.clone()],
+    };
+    let verdict = match problems.is_empty()
+    {
+      true => "pass",
+      false => "FAIL",
+    };
+    println!(
+      "test {}: {verdict} in {}ms",
+      test.name,
+      run.latency.as_millis()
+    );
+    for problem in &problems
+    {
+      println!("  {problem}");
+    }
+    if problems.is_empty()
+    {
+      passed += 1;
+    }
+  }
+  println!("{passed}/{} tests passed", tests.len());
+  std::process::exit(if passed == tests.len() { 0 } else { 1 });
+}
@@ -0,0 +1,131 @@
+use crate::config::SandboxPolicy;
+#[cfg(feature = "http")]
+use reqwest::{
+  header::{HeaderMap, HeaderName, HeaderValue},
+  redirect::Policy,
+  Client, Method,
+};
+use std::collections::HashMap;
+#[cfg(feature = "http")]
+use std::sync::OnceLock;
+
+#[allow(dead_code)]
+#[derive(Debug, thiserror::Error)]
+pub enum HttpErr
+{
+  #[cfg(feature = "http")]
+  #[error("request failed: {0}")]
+  Request(#[from] reqwest::Error),
+  #[error("http support was not compiled in")]
+  NotCompiledIn,
+  #[error("invalid method {0:?}")]
+  InvalidMethod(String),
+  #[error("invalid header {0:?}")]
+  InvalidHeader(String),
+  #[error("could not bind: {0}")]
+  Bind(std::io::Error),
+  #[error("invalid url {0:?}")]
+  InvalidUrl(String),
+  #[cfg(feature = "http")]
+  #[error("websocket error: {0}")]
+  WebSocket(#[from] tokio_websockets::Error),
+}
+
+pub struct HttpResponse
+{
+  pub status: u16,
+  /// Repeated headers are joined with `", "`
+  pub headers: HashMap<String, String>,
+  pub body: String,
+}
+
+/// Shared between every instance so connections to the same host are reused, unless
+/// `sandbox` limits the hosts: then each request gets a client of its own whose
+/// redirects are checked against them, so an allowed host cannot send it elsewhere.
+#[cfg(feature = "http")]
+fn client(sandbox: &SandboxPolicy) -> Result<Client, HttpErr>
+{
+  static CLIENT: OnceLock<Client> = OnceLock::new();
+  if sandbox.hosts.is_none()
+  {
+    return Ok(CLIENT.get_or_init(Client::new).clone());
+  }
+  let sandbox = sandbox.clone();
+  let policy = Policy::custom(move |attempt| {
+    // reqwest's default limit
+    if attempt.previous().len() >= 10
+    {
+      return attempt.error("too many redirects");
+    }
+    match sandbox.check_url(attempt.url().as_str())
+    {
+      Ok(()) => attempt.follow(),
+      Err(e) => attempt.error(e),
+    }
+  });
+  Ok(Client::builder().redirect(policy).build()?)
+}
+
+/// Sends a request to `url`, following redirects only to hosts `sandbox` allows
+#[cfg(feature = "http")]
+pub async fn send_request(
+  sandbox: &SandboxPolicy,
+  method: &str,
+  url: &str,
+  headers: &HashMap<String, String>,
+  body: Option<String>,
+) -> Result<HttpResponse, HttpErr>
+{
+  let method = Method::from_bytes(method.to_uppercase().as_bytes())
+    .map_err(|_| HttpErr::InvalidMethod(method.to_string()))?;
+
+  let mut header_map = HeaderMap::new();
+  for (name, value) in headers
+  {
+    header_map.insert(
+      HeaderName::from_bytes(name.as_bytes()).map_err(|_| HttpErr::InvalidHeader(name.clone()))?,
+      HeaderValue::from_str(value).map_err(|_| HttpErr::InvalidHeader(name.clone()))?,
+    );
+  }
+
+  let mut request = client(sandbox)?.request(method, url).headers(header_map);
+  if let Some(body) = body
+  {
+    request = request.body(body);
+  }
+  let response = request.send().await?;
+
+  let status = response.status().as_u16();
+  let mut headers: HashMap<String, String> = HashMap::new();
+  for (name, value) in response.headers()
+  {
+    let value = String::from_utf8_lossy(value.as_bytes());
+    headers
+      .entry(name.to_string())
+      .and_modify(|x| {
+        x.push_str(", ");
+        x.push_str(&value);
+      })
+      .or_insert_with(|| value.to_string());
+  }
+  let body = response.text().await?;
+  Ok(HttpResponse {
+    status,
+    headers,
+    body,
+  })
+}
+
+/// Graphs sending requests do not load without the `http` feature, so this is only
+/// reached by agents' `http_get` tool if the check is bypassed.
+#[cfg(not(feature = "http"))]
+pub async fn send_request(
+  _sandbox: &SandboxPolicy,
+  _method: &str,
+  _url: &str,
+  _headers: &HashMap<String, String>,
+  _body: Option<String>,
+) -> Result<HttpResponse, HttpErr>
+{
+  Err(HttpErr::NotCompiledIn)
+}
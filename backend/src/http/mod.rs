@@ -0,0 +1,7 @@
+mod client;
+mod server;
+mod websocket;
+
+pub use client::*;
+pub use server::*;
+pub use websocket::*;
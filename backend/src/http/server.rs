@@ -0,0 +1,184 @@
+use super::{HttpErr, HttpResponse};
+#[cfg(feature = "http")]
+use http_body_util::{BodyExt, Full};
+#[cfg(feature = "http")]
+use hyper::{body::Bytes, server::conn::http1, service::service_fn, Request, Response};
+#[cfg(feature = "http")]
+use hyper_util::rt::TokioIo;
+#[cfg(feature = "http")]
+use std::convert::Infallible;
+use std::{collections::HashMap, future::Future, net::SocketAddr, pin::Pin, sync::Arc};
+#[cfg(feature = "http")]
+use tokio::task::JoinSet;
+use tokio::{
+  net::TcpListener,
+  sync::{Mutex, RwLock},
+  task::JoinHandle,
+};
+
+pub struct HttpRequest
+{
+  pub method: String,
+  pub path: String,
+  /// The raw query string, without the leading `?`
+  pub query: String,
+  pub headers: HashMap<String, String>,
+  pub body: String,
+}
+
+pub type RouteFuture = Pin<Box<dyn Future<Output = Result<HttpResponse, String>> + Send>>;
+pub type RouteHandler = Arc<dyn Fn(HttpRequest) -> RouteFuture + Send + Sync>;
+
+type Routes = Arc<RwLock<HashMap<(String, String), RouteHandler>>>;
+
+/// An HTTP/1 server answering requests through handlers registered per method and
+/// path. Requests without a matching route get a 404.
+pub struct HttpServer
+{
+  addr: SocketAddr,
+  routes: Routes,
+  task: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl HttpServer
+{
+  pub async fn bind(addr: &str) -> Result<Self, HttpErr>
+  {
+    if !cfg!(feature = "http")
+    {
+      return Err(HttpErr::NotCompiledIn);
+    }
+    let listener = TcpListener::bind(addr).await.map_err(HttpErr::Bind)?;
+    let addr = listener.local_addr().map_err(HttpErr::Bind)?;
+    let routes: Routes = Arc::new(RwLock::new(HashMap::new()));
+    let task = tokio::spawn(accept_loop(listener, routes.clone()));
+    Ok(Self {
+      addr,
+      routes,
+      task: Mutex::new(Some(task)),
+    })
+  }
+
+  pub fn local_addr(&self) -> SocketAddr
+  {
+    self.addr
+  }
+
+  /// Replaces any handler already registered for the same method and path.
+  pub async fn add_route(&self, method: &str, path: &str, handler: RouteHandler)
+  {
+    self
+      .routes
+      .write()
+      .await
+      .insert((method.to_uppercase(), path.to_string()), handler);
+  }
+
+  /// Stops accepting, drops open connections and waits for the server task to end.
+  pub async fn stop(&self)
+  {
+    if let Some(task) = self.task.lock().await.take()
+    {
+      task.abort();
+      let _ = task.await;
+    }
+    self.routes.write().await.clear();
+  }
+
+  pub async fn is_running(&self) -> bool
+  {
+    self.task.lock().await.is_some()
+  }
+}
+
+#[cfg(not(feature = "http"))]
+async fn accept_loop(_listener: TcpListener, _routes: Routes) {}
+
+#[cfg(feature = "http")]
+async fn accept_loop(listener: TcpListener, routes: Routes)
+{
+  // connections live in here so aborting the server aborts them as well
+  let mut connections = JoinSet::new();
+  loop
+  {
+    let stream = tokio::select! {
+      accepted = listener.accept() => match accepted
+      {
+        Ok((stream, _)) => stream,
+        Err(_) => continue,
+      },
+      Some(_) = connections.join_next() => continue,
+    };
+    let routes = routes.clone();
+    connections.spawn(async move {
+      let service = service_fn(move |req| handle(routes.clone(), req));
+      let _ = http1::Builder::new()
+        .serve_connection(TokioIo::new(stream), service)
+        .await;
+    });
+  }
+}
+
+#[cfg(feature = "http")]
+async fn handle(
+  routes: Routes,
+  req: Request<hyper::body::Incoming>,
+) -> Result<Response<Full<Bytes>>, Infallible>
+{
+  let (parts, body) = req.into_parts();
+  let body = match body.collect().await
+  {
+    Ok(x) => String::from_utf8_lossy(&x.to_bytes()).to_string(),
+    Err(e) => return Ok(respond(400, HashMap::new(), e.to_string())),
+  };
+  let request = HttpRequest {
+    method: parts.method.to_string(),
+    path: parts.uri.path().to_string(),
+    query: parts.uri.query().unwrap_or_default().to_string(),
+    headers: parts
+      .headers
+      .iter()
+      .map(|(k, v)| {
+        (
+          k.to_string(),
+          String::from_utf8_lossy(v.as_bytes()).to_string(),
+        )
+      })
+      .collect(),
+    body,
+  };
+
+  let handler = routes
+    .read()
+    .await
+    .get(&(request.method.clone(), request.path.clone()))
+    .cloned();
+  let Some(handler) = handler
+  else
+  {
+    let message = format!("no route for {} {}", request.method, request.path);
+    return Ok(respond(404, HashMap::new(), message));
+  };
+  Ok(match handler(request).await
+  {
+    Ok(x) => respond(x.status, x.headers, x.body),
+    Err(e) => respond(500, HashMap::new(), e),
+  })
+}
+
+#[cfg(feature = "http")]
+fn respond(status: u16, headers: HashMap<String, String>, body: String) -> Response<Full<Bytes>>
+{
+  let mut builder = Response::builder().status(status);
+  for (k, v) in headers
+  {
+    builder = builder.header(k, v);
+  }
+  builder
+    .body(Full::new(Bytes::from(body)))
+    .unwrap_or_else(|e| {
+      let mut response = Response::new(Full::new(Bytes::from(e.to_string())));
+      *response.status_mut() = hyper::StatusCode::INTERNAL_SERVER_ERROR;
+      response
+    })
+}
@@ -0,0 +1,129 @@
+use super::HttpErr;
+#[cfg(feature = "http")]
+use futures::{
+  stream::{SplitSink, SplitStream},
+  SinkExt, StreamExt,
+};
+#[cfg(feature = "http")]
+use tokio::{net::TcpStream, sync::Mutex};
+#[cfg(feature = "http")]
+use tokio_websockets::{ClientBuilder, MaybeTlsStream, Message, WebSocketStream};
+
+#[cfg(feature = "http")]
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+pub enum Frame
+{
+  Text(String),
+  Binary(Vec<u8>),
+}
+
+/// A client WebSocket connection. Sending and receiving lock separate halves, so a
+/// node waiting for a frame doesn't hold up another one sending.
+#[cfg(feature = "http")]
+pub struct WebSocket
+{
+  sink: Mutex<SplitSink<WsStream, Message>>,
+  stream: Mutex<SplitStream<WsStream>>,
+}
+
+#[cfg(feature = "http")]
+impl WebSocket
+{
+  /// Connects to a `ws://` or `wss://` URL.
+  pub async fn connect(url: &str) -> Result<Self, HttpErr>
+  {
+    let (stream, _) = ClientBuilder::new()
+      .uri(url)
+      .map_err(|_| HttpErr::InvalidUrl(url.to_string()))?
+      .connect()
+      .await?;
+    let (sink, stream) = stream.split();
+    Ok(Self {
+      sink: Mutex::new(sink),
+      stream: Mutex::new(stream),
+    })
+  }
+
+  pub async fn send(&self, frame: Frame) -> Result<(), HttpErr>
+  {
+    let message = match frame
+    {
+      Frame::Text(x) => Message::text(x),
+      Frame::Binary(x) => Message::binary(x),
+    };
+    self.sink.lock().await.send(message).await?;
+    Ok(())
+  }
+
+  pub async fn flush(&self) -> Result<(), HttpErr>
+  {
+    self.sink.lock().await.flush().await?;
+    Ok(())
+  }
+
+  /// Sends a close frame, after which no more frames can be sent
+  pub async fn close(&self) -> Result<(), HttpErr>
+  {
+    self.sink.lock().await.close().await?;
+    Ok(())
+  }
+
+  /// Waits for the next text or binary frame, answering pings on the way. Returns
+  /// `None` once the server has closed the connection.
+  pub async fn recv(&self) -> Result<Option<Frame>, HttpErr>
+  {
+    let mut stream = self.stream.lock().await;
+    while let Some(message) = stream.next().await
+    {
+      let message = message?;
+      if let Some(text) = message.as_text()
+      {
+        return Ok(Some(Frame::Text(text.to_string())));
+      }
+      if message.is_binary()
+      {
+        return Ok(Some(Frame::Binary(message.into_payload().to_vec())));
+      }
+      if message.is_close()
+      {
+        break;
+      }
+    }
+    Ok(None)
+  }
+}
+
+/// Graphs opening WebSockets do not load without the `http` feature, so there are
+/// never any connections.
+#[cfg(not(feature = "http"))]
+pub struct WebSocket(std::convert::Infallible);
+
+#[cfg(not(feature = "http"))]
+impl WebSocket
+{
+  pub async fn connect(_url: &str) -> Result<Self, HttpErr>
+  {
+    Err(HttpErr::NotCompiledIn)
+  }
+
+  pub async fn send(&self, _frame: Frame) -> Result<(), HttpErr>
+  {
+    match self.0 {}
+  }
+
+  pub async fn flush(&self) -> Result<(), HttpErr>
+  {
+    match self.0 {}
+  }
+
+  pub async fn close(&self) -> Result<(), HttpErr>
+  {
+    match self.0 {}
+  }
+
+  pub async fn recv(&self) -> Result<Option<Frame>, HttpErr>
+  {
+    match self.0 {}
+  }
+}
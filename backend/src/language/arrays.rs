@@ -0,0 +1,464 @@
+use super::{
+  nodes::NodeType,
+  typing::{DataType, DataValue},
+};
+use crate::eval::EvalError;
+use indexmap::IndexMap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, PartialEq)]
+pub enum ArrayOp
+{
+  /// Sorts an Array, comparing the given field instead of the elements themselves
+  /// when one is set. The sort is stable, so equal elements keep their order.
+  Sort(SortOrder, Option<String>),
+  /// Removes repeated elements, keeping the first of each
+  Unique,
+  /// Whether the Array on port 0 holds the value on port 1
+  Contains,
+  /// Splits an Array of Objects into an Object of Arrays keyed by the given field, in
+  /// the order each key first appears. Field values that aren't Strings are keyed by
+  /// their printed form.
+  GroupBy(String),
+  /// Elements found in either Array. Like the other set operations, the result
+  /// follows the order of the first Array, holds no repeats and compares elements the
+  /// same way as `Eq`.
+  Union,
+  /// Elements of the first Array that are also in the second
+  Intersection,
+  /// Elements of the first Array that are not in the second
+  Difference,
+  /// Reduces an Array to one value, using the given field of each element instead of
+  /// the elements themselves when one is set
+  Aggregate(Aggregate, Option<String>),
+  /// Number of elements, as an Integer
+  Length,
+  /// The element at the Integer index on port 1, counting from the end when negative
+  Index,
+  /// Elements from the start index on port 1 up to but not including the end index on
+  /// port 2. Negative indices count from the end, a None end means the end of the
+  /// Array, and indices past either end are clamped to it.
+  Slice,
+  /// The Array with the value on port 1 added at its end
+  Append,
+  /// The elements of the second Array after those of the first
+  Concat,
+  Reverse,
+  /// Replaces each element that is an Array with its elements, one level deep
+  Flatten,
+  /// The elements before the Integer index on port 1 on port 0, and the rest on
+  /// port 1. Like `Slice`, a negative index counts from the end and one past either
+  /// end is clamped to it.
+  Split,
+  /// An Array of pairs, two element Arrays, as an Array of their first elements on
+  /// port 0 and one of their second on port 1
+  Unzip,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, PartialEq)]
+pub enum Aggregate
+{
+  /// Sum of Integers and Floats, `0` for an empty Array
+  Sum,
+  /// Mean as a Float, `None` for an empty Array
+  Avg,
+  /// Smallest element by the `Sort` ordering, `None` for an empty Array
+  Min,
+  /// Largest element by the `Sort` ordering, `None` for an empty Array
+  Max,
+  Count,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, PartialEq)]
+pub enum SortOrder
+{
+  Ascending,
+  Descending,
+}
+
+fn expect_array(inputs: &[DataValue], expected: Vec<DataType>)
+  -> Result<&Vec<DataValue>, EvalError>
+{
+  if inputs.len() != expected.len()
+  {
+    return Err(EvalError::IncorrectInputCount);
+  }
+  match &inputs[0]
+  {
+    DataValue::Array(x) => Ok(x),
+    _ =>
+    {
+      Err(EvalError::IncorrectTyping {
+        got: inputs.iter().map(|x| x.get_type()).collect(),
+        expected,
+      })
+    }
+  }
+}
+
+fn unique<'a>(values: impl Iterator<Item = &'a DataValue>) -> Vec<DataValue>
+{
+  let mut unique: Vec<DataValue> = Vec::new();
+  for x in values
+  {
+    if !unique.iter().any(|y| y.equals(x, 0.0))
+    {
+      unique.push(x.clone());
+    }
+  }
+  unique
+}
+
+fn expect_arrays(inputs: &[DataValue]) -> Result<(&Vec<DataValue>, &Vec<DataValue>), EvalError>
+{
+  match inputs
+  {
+    [DataValue::Array(a), DataValue::Array(b)] => Ok((a, b)),
+    [_, _] =>
+    {
+      Err(EvalError::IncorrectTyping {
+        got: inputs.iter().map(|x| x.get_type()).collect(),
+        expected: vec![DataType::Array, DataType::Array],
+      })
+    }
+    _ => Err(EvalError::IncorrectInputCount),
+  }
+}
+
+/// `index` as a position in an Array of `len` elements, counting from the end when
+/// negative, clamped to `0..=len`.
+fn clamp_index(index: i64, len: usize) -> usize
+{
+  match index
+  {
+    i if i < 0 => len.saturating_sub(i.unsigned_abs() as usize),
+    i => (i as usize).min(len),
+  }
+}
+
+fn element_key<'a>(value: &'a DataValue, key: &Option<String>) -> Result<&'a DataValue, EvalError>
+{
+  match (key, value)
+  {
+    (None, _) => Ok(value),
+    (Some(key), DataValue::Object(fields)) =>
+    {
+      fields
+        .get(key)
+        .ok_or_else(|| EvalError::FieldNotFound(key.clone()))
+    }
+    (Some(_), _) =>
+    {
+      Err(EvalError::IncorrectTyping {
+        got: vec![value.get_type()],
+        expected: vec![DataType::Object],
+      })
+    }
+  }
+}
+
+fn aggregate(op: Aggregate, values: Vec<&DataValue>) -> Result<DataValue, EvalError>
+{
+  let count = values.len();
+  match op
+  {
+    Aggregate::Count => Ok(DataValue::Integer(count as i64)),
+    Aggregate::Sum | Aggregate::Avg =>
+    {
+      let mut sum = DataValue::Integer(0);
+      for x in values
+      {
+        if !matches!(x, DataValue::Integer(_) | DataValue::Float(_))
+        {
+          return Err(EvalError::IncorrectTyping {
+            got: vec![x.get_type()],
+            expected: vec![DataType::Float],
+          });
+        }
+        sum = (sum + x.clone())?;
+      }
+      match op
+      {
+        Aggregate::Avg if count == 0 => Ok(DataValue::None),
+        Aggregate::Avg =>
+        {
+          Ok((sum.try_cast(DataType::Float).unwrap() / DataValue::Float(count as f64))?)
+        }
+        _ => Ok(sum),
+      }
+    }
+    Aggregate::Min | Aggregate::Max =>
+    {
+      let mut best: Option<&DataValue> = None;
+      for x in values
+      {
+        best = match best
+        {
+          None => Some(x),
+          Some(b) =>
+          {
+            let ord = x
+              .compare(b)
+              .ok_or(EvalError::Incomparable(x.get_type(), b.get_type()))?;
+            let better = match op
+            {
+              Aggregate::Min => ord == Ordering::Less,
+              _ => ord == Ordering::Greater,
+            };
+            Some(if better { x } else { b })
+          }
+        };
+      }
+      Ok(best.cloned().unwrap_or(DataValue::None))
+    }
+  }
+}
+
+impl NodeType
+{
+  pub(super) fn eval_array(op: ArrayOp, inputs: Vec<DataValue>)
+    -> Result<Vec<DataValue>, EvalError>
+  {
+    match op
+    {
+      ArrayOp::Sort(order, key) =>
+      {
+        let array = expect_array(&inputs, vec![DataType::Array])?;
+        let keys = array
+          .iter()
+          .map(|x| element_key(x, &key))
+          .collect::<Result<Vec<_>, _>>()?;
+
+        let mut error = None;
+        let mut indices = (0..array.len()).collect::<Vec<_>>();
+        indices.sort_by(|&a, &b| {
+          let ord = keys[a].compare(keys[b]).unwrap_or_else(|| {
+            error.get_or_insert(EvalError::Incomparable(
+              keys[a].get_type(),
+              keys[b].get_type(),
+            ));
+            Ordering::Equal
+          });
+          match order
+          {
+            SortOrder::Ascending => ord,
+            SortOrder::Descending => ord.reverse(),
+          }
+        });
+        if let Some(e) = error
+        {
+          return Err(e);
+        }
+        Ok(vec![DataValue::Array(
+          indices.into_iter().map(|i| array[i].clone()).collect(),
+        )])
+      }
+      ArrayOp::Unique =>
+      {
+        let array = expect_array(&inputs, vec![DataType::Array])?;
+        Ok(vec![DataValue::Array(unique(array.iter()))])
+      }
+      ArrayOp::Contains =>
+      {
+        if inputs.len() != 2
+        {
+          return Err(EvalError::IncorrectInputCount);
+        }
+        let array = expect_array(&inputs, vec![DataType::Array, inputs[1].get_type()])?;
+        Ok(vec![DataValue::Boolean(
+          array.iter().any(|x| x.equals(&inputs[1], 0.0)),
+        )])
+      }
+      ArrayOp::Union =>
+      {
+        let (a, b) = expect_arrays(&inputs)?;
+        Ok(vec![DataValue::Array(unique(a.iter().chain(b)))])
+      }
+      ArrayOp::Intersection =>
+      {
+        let (a, b) = expect_arrays(&inputs)?;
+        Ok(vec![DataValue::Array(unique(
+          a.iter().filter(|x| b.iter().any(|y| y.equals(x, 0.0))),
+        ))])
+      }
+      ArrayOp::Difference =>
+      {
+        let (a, b) = expect_arrays(&inputs)?;
+        Ok(vec![DataValue::Array(unique(
+          a.iter().filter(|x| !b.iter().any(|y| y.equals(x, 0.0))),
+        ))])
+      }
+      ArrayOp::GroupBy(key) =>
+      {
+        let array = expect_array(&inputs, vec![DataType::Array])?;
+        let mut groups: IndexMap<String, DataValue> = IndexMap::new();
+        for x in array
+        {
+          let group = match element_key(x, &Some(key.clone()))?
+          {
+            DataValue::String(s) => s.clone(),
+            other => other.to_string(),
+          };
+          if let DataValue::Array(members) = groups
+            .entry(group)
+            .or_insert_with(|| DataValue::Array(vec![]))
+          {
+            members.push(x.clone());
+          }
+        }
+        Ok(vec![DataValue::Object(groups)])
+      }
+      ArrayOp::Aggregate(op, key) =>
+      {
+        let array = expect_array(&inputs, vec![DataType::Array])?;
+        let values = array
+          .iter()
+          .map(|x| element_key(x, &key))
+          .collect::<Result<Vec<_>, _>>()?;
+        Ok(vec![aggregate(op, values)?])
+      }
+      ArrayOp::Length =>
+      {
+        let array = expect_array(&inputs, vec![DataType::Array])?;
+        Ok(vec![DataValue::Integer(array.len() as i64)])
+      }
+      ArrayOp::Index =>
+      {
+        let array = expect_array(&inputs, vec![DataType::Array, DataType::Integer])?;
+        let DataValue::Integer(index) = inputs[1]
+        else
+        {
+          return Err(EvalError::IncorrectTyping {
+            got: inputs.iter().map(|x| x.get_type()).collect(),
+            expected: vec![DataType::Array, DataType::Integer],
+          });
+        };
+        let position = match index < 0
+        {
+          true => array.len().checked_sub(index.unsigned_abs() as usize),
+          false => Some(index as usize),
+        };
+        position
+          .and_then(|i| array.get(i))
+          .map(|x| vec![x.clone()])
+          .ok_or_else(|| EvalError::FieldNotFound(index.to_string()))
+      }
+      ArrayOp::Slice =>
+      {
+        let expected = vec![DataType::Array, DataType::Integer, DataType::Integer];
+        let array = expect_array(&inputs, expected.clone())?;
+        let (start, end) = match (&inputs[1], &inputs[2])
+        {
+          (DataValue::Integer(start), DataValue::Integer(end)) =>
+          {
+            (
+              clamp_index(*start, array.len()),
+              clamp_index(*end, array.len()),
+            )
+          }
+          (DataValue::Integer(start), DataValue::None) =>
+          {
+            (clamp_index(*start, array.len()), array.len())
+          }
+          _ =>
+          {
+            return Err(EvalError::IncorrectTyping {
+              got: inputs.iter().map(|x| x.get_type()).collect(),
+              expected,
+            })
+          }
+        };
+        Ok(vec![DataValue::Array(
+          array
+            .get(start..end.max(start))
+            .unwrap_or_default()
+            .to_vec(),
+        )])
+      }
+      ArrayOp::Append =>
+      {
+        if inputs.len() != 2
+        {
+          return Err(EvalError::IncorrectInputCount);
+        }
+        let array = expect_array(&inputs, vec![DataType::Array, inputs[1].get_type()])?;
+        let mut array = array.clone();
+        array.push(inputs[1].clone());
+        Ok(vec![DataValue::Array(array)])
+      }
+      ArrayOp::Concat =>
+      {
+        let (a, b) = expect_arrays(&inputs)?;
+        Ok(vec![DataValue::Array(a.iter().chain(b).cloned().collect())])
+      }
+      ArrayOp::Reverse =>
+      {
+        let array = expect_array(&inputs, vec![DataType::Array])?;
+        Ok(vec![DataValue::Array(
+          array.iter().rev().cloned().collect(),
+        )])
+      }
+      ArrayOp::Flatten =>
+      {
+        let array = expect_array(&inputs, vec![DataType::Array])?;
+        let flat = array
+          .iter()
+          .flat_map(|x| {
+            match x
+            {
+              DataValue::Array(items) => items.clone(),
+              other => vec![other.clone()],
+            }
+          })
+          .collect();
+        Ok(vec![DataValue::Array(flat)])
+      }
+      ArrayOp::Split =>
+      {
+        let expected = vec![DataType::Array, DataType::Integer];
+        let array = expect_array(&inputs, expected.clone())?;
+        let DataValue::Integer(index) = inputs[1]
+        else
+        {
+          return Err(EvalError::IncorrectTyping {
+            got: inputs.iter().map(|x| x.get_type()).collect(),
+            expected,
+          });
+        };
+        let (before, after) = array.split_at(clamp_index(index, array.len()));
+        Ok(vec![
+          DataValue::Array(before.to_vec()),
+          DataValue::Array(after.to_vec()),
+        ])
+      }
+      ArrayOp::Unzip =>
+      {
+        let array = expect_array(&inputs, vec![DataType::Array])?;
+        let mut firsts = Vec::with_capacity(array.len());
+        let mut seconds = Vec::with_capacity(array.len());
+        for x in array
+        {
+          match x
+          {
+            DataValue::Array(pair) if pair.len() == 2 =>
+            {
+              firsts.push(pair[0].clone());
+              seconds.push(pair[1].clone());
+            }
+            DataValue::Array(pair) => return Err(EvalError::NotAPair(pair.len())),
+            _ =>
+            {
+              return Err(EvalError::IncorrectTyping {
+                got: vec![x.get_type()],
+                expected: vec![DataType::Array],
+              })
+            }
+          }
+        }
+        Ok(vec![DataValue::Array(firsts), DataValue::Array(seconds)])
+      }
+    }
+  }
+}
@@ -0,0 +1,155 @@
+use super::{
+  nodes::{AtomicType, Complex, ControlFlow, Instance, NodeType},
+  typing::DataType,
+};
+use crate::eval::EvalError;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Builds a [`Complex`] graph in code rather than writing its JSON by hand. Node ids
+/// are generated as nodes are added, and [`Self::build`] checks the result the same
+/// way loading a graph file does.
+///
+/// ```
+/// use agent_nodes_core::language::{
+///   builder::GraphBuilder,
+///   nodes::{AtomicType, ControlFlow, NodeType},
+/// };
+/// use agent_nodes_core::{DataType, DataValue};
+///
+/// let mut graph = GraphBuilder::new(vec![], vec![DataType::Integer]);
+/// let start = graph.add_node(NodeType::Atomic(AtomicType::Control(ControlFlow::Start)));
+/// let value = graph.add_node(NodeType::Atomic(AtomicType::Value(DataValue::Integer(42))));
+/// let end = graph.add_node(NodeType::Atomic(AtomicType::Control(ControlFlow::End)));
+/// graph
+///   .trigger(start, 0, value)
+///   .trigger(value, 0, end)
+///   .connect(value, 0, end, DataType::Integer)
+///   .set_end(end);
+/// let complex = graph.build().unwrap();
+/// assert_eq!(complex.instances.len(), 3);
+/// ```
+pub struct GraphBuilder
+{
+  inputs: Vec<DataType>,
+  outputs: Vec<DataType>,
+  instances: HashMap<Uuid, Instance>,
+  end: Option<Uuid>,
+  /// Connections naming nodes that were never added, reported by `build`
+  unknown: Option<Uuid>,
+}
+
+impl GraphBuilder
+{
+  /// A graph taking and returning values of these types
+  pub fn new(inputs: Vec<DataType>, outputs: Vec<DataType>) -> Self
+  {
+    Self {
+      inputs,
+      outputs,
+      instances: HashMap::new(),
+      end: None,
+      unknown: None,
+    }
+  }
+
+  /// Adds a node and returns its id.
+  pub fn add_node(&mut self, node_type: NodeType) -> Uuid
+  {
+    let id = Uuid::new_v4();
+    self.instances.insert(id, Instance::new(node_type));
+    id
+  }
+
+  /// Feeds output `port` of `from` into the next input of `to`, inputs being numbered
+  /// in the order they are connected.
+  pub fn connect(&mut self, from: Uuid, port: usize, to: Uuid, data_type: DataType) -> &mut Self
+  {
+    if self.check_added(&[from, to])
+    {
+      if let Some(source) = self.instances.get_mut(&from)
+      {
+        source.outputs.push(to);
+      }
+      if let Some(target) = self.instances.get_mut(&to)
+      {
+        target.inputs.push((data_type, from, port));
+      }
+    }
+    self
+  }
+
+  /// Makes `to` run once `from` has finished, through `from`'s control port `port`.
+  pub fn trigger(&mut self, from: Uuid, port: usize, to: Uuid) -> &mut Self
+  {
+    if self.check_added(&[from, to])
+    {
+      if let Some(source) = self.instances.get_mut(&from)
+      {
+        if source.control_flow_out.len() <= port
+        {
+          source.control_flow_out.resize(port + 1, vec![]);
+        }
+        source.control_flow_out[port].push((to, 0));
+      }
+      if let Some(target) = self.instances.get_mut(&to)
+      {
+        if target.control_flow_in.is_empty()
+        {
+          target.control_flow_in.push(vec![]);
+        }
+        target.control_flow_in[0].push((from, port));
+      }
+    }
+    self
+  }
+
+  /// Whether every one of `ids` was added, remembering the first that was not.
+  fn check_added(&mut self, ids: &[Uuid]) -> bool
+  {
+    match ids.iter().find(|x| !self.instances.contains_key(x))
+    {
+      Some(missing) =>
+      {
+        self.unknown.get_or_insert(*missing);
+        false
+      }
+      None => true,
+    }
+  }
+
+  /// Sets the node whose inputs become the graph's outputs.
+  pub fn set_end(&mut self, node: Uuid) -> &mut Self
+  {
+    self.end = Some(node);
+    self
+  }
+
+  /// The finished graph, or why it could not run: a connection to a node never added,
+  /// a missing start or end node, or any problem [`Complex::validate`] finds.
+  pub fn build(mut self) -> Result<Complex, EvalError>
+  {
+    if let Some(id) = self.unknown
+    {
+      return Err(EvalError::NodeNotFound(id));
+    }
+    let end = self.end.ok_or(EvalError::NoEndNode)?;
+    let start = NodeType::Atomic(AtomicType::Control(ControlFlow::Start));
+    if !self.instances.values().any(|x| x.node_type == start)
+    {
+      return Err(EvalError::NoStartNode);
+    }
+    // the graph's outputs are read from the end node once per port
+    let end_node = self
+      .instances
+      .get_mut(&end)
+      .ok_or(EvalError::NodeNotFound(end))?;
+    end_node
+      .outputs
+      .extend(std::iter::repeat_n(end, self.outputs.len()));
+
+    let complex = Complex::new(self.inputs, self.outputs, end, self.instances);
+    complex.validate()?;
+    Ok(complex)
+  }
+}
@@ -0,0 +1,140 @@
+use super::{
+  nodes::NodeType,
+  typing::{DataType, DataValue},
+};
+use crate::eval::EvalError;
+use base64::{
+  alphabet,
+  engine::{general_purpose::STANDARD, DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+  Engine,
+};
+use percent_encoding::{percent_decode_str, percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The URL-safe alphabet, written unpadded as in JWTs and read with or without padding
+const BASE64_URL: GeneralPurpose = GeneralPurpose::new(
+  &alphabet::URL_SAFE,
+  GeneralPurposeConfig::new()
+    .with_encode_padding(false)
+    .with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Everything but the unreserved characters of RFC 3986
+const URL_RESERVED: &AsciiSet = &NON_ALPHANUMERIC
+  .remove(b'-')
+  .remove(b'.')
+  .remove(b'_')
+  .remove(b'~');
+
+/// How Bytes are written as a String
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, JsonSchema, PartialEq, Eq)]
+pub enum Encoding
+{
+  /// The bytes are the String's own, and must be valid UTF-8 to make one
+  Utf8,
+  /// Standard base64 with padding
+  Base64,
+  /// Base64 with `-` and `_` in place of `+` and `/` and no padding, safe in URLs and
+  /// file names
+  Base64Url,
+  /// Two lowercase hex digits per byte, either case being read
+  Hex,
+  /// Percent-encoding of everything but letters, digits and `-._~`, for a single
+  /// query parameter or path segment. Malformed escapes are kept as they are when
+  /// decoding.
+  Url,
+}
+
+impl Encoding
+{
+  pub fn encode(self, bytes: &[u8]) -> Result<String, EvalError>
+  {
+    match self
+    {
+      Encoding::Utf8 => Ok(String::from_utf8(bytes.to_vec())?),
+      Encoding::Base64 => Ok(STANDARD.encode(bytes)),
+      Encoding::Base64Url => Ok(BASE64_URL.encode(bytes)),
+      Encoding::Hex => Ok(bytes.iter().map(|x| format!("{x:02x}")).collect()),
+      Encoding::Url => Ok(percent_encode(bytes, URL_RESERVED).to_string()),
+    }
+  }
+
+  pub fn decode(self, text: &str) -> Result<Vec<u8>, EvalError>
+  {
+    let invalid = |message: String| EvalError::InvalidEncoding(self, message);
+    match self
+    {
+      Encoding::Utf8 => Ok(text.as_bytes().to_vec()),
+      Encoding::Base64 => STANDARD.decode(text).map_err(|e| invalid(e.to_string())),
+      Encoding::Base64Url => BASE64_URL.decode(text).map_err(|e| invalid(e.to_string())),
+      Encoding::Url => Ok(percent_decode_str(text).collect()),
+      Encoding::Hex =>
+      {
+        if !text.len().is_multiple_of(2)
+        {
+          return Err(invalid(format!("odd number of digits in {text:?}")));
+        }
+        (0..text.len())
+          .step_by(2)
+          .map(|i| {
+            text
+              .get(i..i + 2)
+              .filter(|x| x.bytes().all(|x| x.is_ascii_hexdigit()))
+              .and_then(|x| u8::from_str_radix(x, 16).ok())
+              .ok_or_else(|| invalid(format!("{text:?} is not hex")))
+          })
+          .collect()
+      }
+    }
+  }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, PartialEq)]
+pub enum BytesOp
+{
+  /// Writes Bytes, or the UTF-8 of a String, as a String in the given encoding
+  Encode(Encoding),
+  /// Reads a String written in the given encoding into Bytes
+  Decode(Encoding),
+  /// Reads a String written in the given encoding into the String its bytes are the
+  /// UTF-8 of, as for text sent as base64 or in a URL
+  DecodeText(Encoding),
+}
+
+impl NodeType
+{
+  pub(super) fn eval_bytes(op: BytesOp, inputs: Vec<DataValue>)
+    -> Result<Vec<DataValue>, EvalError>
+  {
+    let value = match (op, inputs.as_slice())
+    {
+      (BytesOp::Encode(encoding), [DataValue::Bytes(x)]) => DataValue::String(encoding.encode(x)?),
+      (BytesOp::Encode(encoding), [DataValue::String(x)]) =>
+      {
+        DataValue::String(encoding.encode(x.as_bytes())?)
+      }
+      (BytesOp::Decode(encoding), [DataValue::String(x)]) => DataValue::Bytes(encoding.decode(x)?),
+      (BytesOp::DecodeText(encoding), [DataValue::String(x)]) =>
+      {
+        DataValue::String(String::from_utf8(encoding.decode(x)?)?)
+      }
+      (BytesOp::Encode(_), [_]) =>
+      {
+        return Err(EvalError::IncorrectTyping {
+          got: inputs.iter().map(|x| x.get_type()).collect(),
+          expected: vec![DataType::Bytes],
+        })
+      }
+      (BytesOp::Decode(_) | BytesOp::DecodeText(_), [_]) =>
+      {
+        return Err(EvalError::IncorrectTyping {
+          got: inputs.iter().map(|x| x.get_type()).collect(),
+          expected: vec![DataType::String],
+        })
+      }
+      _ => return Err(EvalError::IncorrectInputCount),
+    };
+    Ok(vec![value])
+  }
+}
@@ -0,0 +1,323 @@
+use super::{
+  nodes::NodeType,
+  typing::{DataType, DataValue},
+};
+use crate::eval::EvalError;
+use chrono::{
+  format::{Item, Parsed, StrftimeItems},
+  SecondsFormat, Utc,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::{self, Write};
+
+/// A point in time to the millisecond, in UTC, between the years 0 and 9999. Written
+/// as RFC 3339, such as `2024-05-01T09:30:00Z`, when printed or serialized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DateTime(chrono::DateTime<Utc>);
+
+/// The first and last milliseconds of the years 0 to 9999, the range RFC 3339 can write
+const MIN_MILLIS: i64 = -62_167_219_200_000;
+const MAX_MILLIS: i64 = 253_402_300_799_999;
+
+/// `pattern` as chrono reads it, failing on directives it does not know. `%f` stands
+/// for the three digits of the milliseconds rather than chrono's nanoseconds.
+fn strftime(pattern: &str) -> Result<Vec<Item<'static>>, String>
+{
+  let mut translated = String::with_capacity(pattern.len());
+  let mut chars = pattern.chars();
+  while let Some(c) = chars.next()
+  {
+    translated.push(c);
+    if c == '%'
+    {
+      match chars.next()
+      {
+        Some('f') => translated += "3f",
+        Some(x) => translated.push(x),
+        None => return Err("pattern ends with %".to_string()),
+      }
+    }
+  }
+  let items: Vec<Item<'static>> = StrftimeItems::new(&translated)
+    .map(|x| x.to_owned())
+    .collect();
+  match items.contains(&Item::Error)
+  {
+    true => Err(format!("unknown directive in {pattern:?}")),
+    false => Ok(items),
+  }
+}
+
+impl DateTime
+{
+  /// The time `millis` milliseconds after the Unix epoch, None if that falls outside
+  /// the years 0 to 9999
+  pub fn from_millis(millis: i64) -> Option<Self>
+  {
+    (MIN_MILLIS..=MAX_MILLIS)
+      .contains(&millis)
+      .then(|| chrono::DateTime::from_timestamp_millis(millis))
+      .flatten()
+      .map(Self)
+  }
+
+  /// Milliseconds since the Unix epoch, negative before it
+  pub fn millis(self) -> i64
+  {
+    self.0.timestamp_millis()
+  }
+
+  pub fn now() -> Self
+  {
+    let millis = Utc::now().timestamp_millis();
+    Self::from_millis(millis.clamp(MIN_MILLIS, MAX_MILLIS)).expect("clamped to the range")
+  }
+
+  /// The time `millis` milliseconds later, or earlier if negative
+  pub fn checked_add(self, millis: i64) -> Option<Self>
+  {
+    self
+      .millis()
+      .checked_add(millis)
+      .and_then(Self::from_millis)
+  }
+
+  /// A time chrono read, to the millisecond
+  fn from_chrono(time: chrono::DateTime<Utc>) -> Result<Self, String>
+  {
+    Self::from_millis(time.timestamp_millis())
+      .ok_or_else(|| "outside the years 0 to 9999".to_string())
+  }
+
+  /// Writes the time following `pattern`, a chrono strftime pattern such as
+  /// `%d/%m/%Y %H:%M`, except that `%f` is the three digit milliseconds.
+  pub fn format(self, pattern: &str) -> Result<String, String>
+  {
+    let items = strftime(pattern)?;
+    let mut out = String::new();
+    write!(out, "{}", self.0.format_with_items(items.iter()))
+      .map_err(|_| format!("cannot write {self} following {pattern:?}"))?;
+    Ok(out)
+  }
+
+  /// Reads a time written following `pattern`, with the directives of
+  /// [`Self::format`]. Fields the pattern leaves out are taken from 1970-01-01T00:00Z,
+  /// and the time is converted to UTC if the pattern has an offset.
+  pub fn parse(text: &str, pattern: &str) -> Result<Self, String>
+  {
+    let mut parsed = Parsed::new();
+    chrono::format::parse(&mut parsed, text, strftime(pattern)?.iter())
+      .map_err(|e| e.to_string())?;
+    if parsed.timestamp().is_none()
+    {
+      fill_from_epoch(&mut parsed).map_err(|e| e.to_string())?;
+    }
+    let time = match parsed.offset()
+    {
+      Some(_) => parsed.to_datetime().map(|x| x.with_timezone(&Utc)),
+      None => parsed.to_naive_datetime_with_offset(0).map(|x| x.and_utc()),
+    };
+    Self::from_chrono(time.map_err(|e| e.to_string())?)
+  }
+
+  /// Reads an RFC 3339 time such as `2024-05-01T09:30:00.250+02:00`, converting it to
+  /// UTC. Fractions of a second past the millisecond are dropped.
+  pub fn parse_rfc3339(text: &str) -> Result<Self, String>
+  {
+    let time = chrono::DateTime::parse_from_rfc3339(text).map_err(|e| e.to_string())?;
+    Self::from_chrono(time.with_timezone(&Utc))
+  }
+}
+
+/// Sets the fields of 1970-01-01T00:00 a pattern did not give, leaving those of dates
+/// written as a day of the year or a week alone
+fn fill_from_epoch(parsed: &mut Parsed) -> chrono::ParseResult<()>
+{
+  if parsed.year().is_none() && parsed.year_mod_100().is_none() && parsed.isoyear().is_none()
+  {
+    parsed.set_year(1970)?;
+  }
+  let by_week = parsed.isoweek().is_some()
+    || parsed.week_from_sun().is_some()
+    || parsed.week_from_mon().is_some();
+  if parsed.ordinal().is_none() && !by_week
+  {
+    if parsed.month().is_none()
+    {
+      parsed.set_month(1)?;
+    }
+    if parsed.day().is_none()
+    {
+      parsed.set_day(1)?;
+    }
+  }
+  if parsed.hour_div_12().is_none() && parsed.hour_mod_12().is_none()
+  {
+    parsed.set_hour(0)?;
+  }
+  if parsed.minute().is_none()
+  {
+    parsed.set_minute(0)?;
+  }
+  if parsed.second().is_none()
+  {
+    parsed.set_second(0)?;
+  }
+  Ok(())
+}
+
+impl fmt::Display for DateTime
+{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+  {
+    let precision = match self.millis().rem_euclid(1000)
+    {
+      0 => SecondsFormat::Secs,
+      _ => SecondsFormat::Millis,
+    };
+    write!(f, "{}", self.0.to_rfc3339_opts(precision, true))
+  }
+}
+
+impl Serialize for DateTime
+{
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>
+  {
+    serializer.collect_str(self)
+  }
+}
+
+impl<'de> Deserialize<'de> for DateTime
+{
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>
+  {
+    let text = String::deserialize(deserializer)?;
+    Self::parse_rfc3339(&text).map_err(serde::de::Error::custom)
+  }
+}
+
+impl JsonSchema for DateTime
+{
+  fn schema_name() -> std::borrow::Cow<'static, str>
+  {
+    "DateTime".into()
+  }
+
+  fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema
+  {
+    schemars::json_schema!({ "type": "string", "format": "date-time" })
+  }
+}
+
+/// Durations are Integer milliseconds, as the `Stopwatch` node counts them.
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, PartialEq)]
+pub enum DateTimeOp
+{
+  /// The current time
+  Now,
+  /// Writes a DateTime as a String following a pattern, RFC 3339 if None. See
+  /// [`DateTime::format`] for the directives.
+  Format(Option<String>),
+  /// Reads a String written following a pattern into a DateTime, RFC 3339 with any
+  /// offset if None
+  Parse(Option<String>),
+  /// The DateTime on port 0 the milliseconds on port 1 later
+  Add,
+  /// The DateTime on port 0 the milliseconds on port 1 earlier, or the milliseconds
+  /// from the DateTime on port 1 to the one on port 0
+  Sub,
+  /// -1, 0 or 1 as the DateTime on port 0 is before, the same as or after the one on
+  /// port 1
+  Compare,
+}
+
+fn shifted(time: DateTime, millis: i64) -> Result<DataValue, EvalError>
+{
+  time
+    .checked_add(millis)
+    .map(DataValue::DateTime)
+    .ok_or_else(|| {
+      EvalError::InvalidDateTime(format!("{time} shifted by {millis}ms is out of range"))
+    })
+}
+
+impl NodeType
+{
+  pub(super) fn eval_date_time(
+    op: DateTimeOp,
+    inputs: Vec<DataValue>,
+  ) -> Result<Vec<DataValue>, EvalError>
+  {
+    let value = match (op, inputs.as_slice())
+    {
+      (DateTimeOp::Now, []) => DataValue::DateTime(DateTime::now()),
+      (DateTimeOp::Format(pattern), [DataValue::DateTime(x)]) =>
+      {
+        match pattern
+        {
+          Some(pattern) =>
+          {
+            DataValue::String(x.format(&pattern).map_err(EvalError::InvalidDateTime)?)
+          }
+          None => DataValue::String(x.to_string()),
+        }
+      }
+      (DateTimeOp::Parse(pattern), [DataValue::String(x)]) =>
+      {
+        let parsed = match pattern
+        {
+          Some(pattern) => DateTime::parse(x, &pattern),
+          None => DateTime::parse_rfc3339(x),
+        };
+        DataValue::DateTime(parsed.map_err(|e| EvalError::InvalidDateTime(format!("{x:?}: {e}")))?)
+      }
+      (DateTimeOp::Add, [DataValue::DateTime(x), DataValue::Integer(millis)]) =>
+      {
+        shifted(*x, *millis)?
+      }
+      (DateTimeOp::Sub, [DataValue::DateTime(x), DataValue::Integer(millis)]) =>
+      {
+        shifted(*x, millis.checked_neg().unwrap_or(i64::MAX))?
+      }
+      (DateTimeOp::Sub, [DataValue::DateTime(a), DataValue::DateTime(b)]) =>
+      {
+        DataValue::Integer(a.millis() - b.millis())
+      }
+      (DateTimeOp::Compare, [DataValue::DateTime(a), DataValue::DateTime(b)]) =>
+      {
+        DataValue::Integer(a.cmp(b) as i64)
+      }
+      (DateTimeOp::Format(_), [_]) =>
+      {
+        return Err(EvalError::IncorrectTyping {
+          got: inputs.iter().map(|x| x.get_type()).collect(),
+          expected: vec![DataType::DateTime],
+        })
+      }
+      (DateTimeOp::Parse(_), [_]) =>
+      {
+        return Err(EvalError::IncorrectTyping {
+          got: inputs.iter().map(|x| x.get_type()).collect(),
+          expected: vec![DataType::String],
+        })
+      }
+      (DateTimeOp::Add | DateTimeOp::Sub, [_, _]) =>
+      {
+        return Err(EvalError::IncorrectTyping {
+          got: inputs.iter().map(|x| x.get_type()).collect(),
+          expected: vec![DataType::DateTime, DataType::Integer],
+        })
+      }
+      (DateTimeOp::Compare, [_, _]) =>
+      {
+        return Err(EvalError::IncorrectTyping {
+          got: inputs.iter().map(|x| x.get_type()).collect(),
+          expected: vec![DataType::DateTime, DataType::DateTime],
+        })
+      }
+      _ => return Err(EvalError::IncorrectInputCount),
+    };
+    Ok(vec![value])
+  }
+}
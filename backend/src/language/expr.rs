@@ -0,0 +1,534 @@
+use super::{
+  nodes::NodeType,
+  typing::{DataType, DataValue},
+};
+use crate::eval::EvalError;
+
+/// A parsed `Expr` node expression.
+///
+/// Input ports are read as `a`, `b`, `c` and so on, port 0 being `a`. Literals are
+/// integers, floats, `"strings"` (with `\"`, `\\`, `\n` and `\t` escapes), `true`,
+/// `false` and `none`. From loosest to tightest binding the operators are `||`, `&&`,
+/// `==` `!=`, `<` `<=` `>` `>=`, `+` `-`, `*` `/` `%`, the right associative `**`, and
+/// the prefixes `-` and `!`. Arithmetic works like the `BinOp` nodes, equality like the
+/// `Eq` node and ordering like `Sort`. The functions are `len(x)` of a String, Array or
+/// Object, `abs(x)`, `min(x, ...)`, `max(x, ...)`, `str(x)`, `int(x)` and `float(x)`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr
+{
+  Literal(DataValue),
+  Port(usize),
+  Neg(Box<Expr>),
+  Not(Box<Expr>),
+  Binary(BinaryOp, Box<Expr>, Box<Expr>),
+  Call(Function, Vec<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp
+{
+  Or,
+  And,
+  Eq,
+  Neq,
+  Lt,
+  Le,
+  Gt,
+  Ge,
+  Add,
+  Sub,
+  Mul,
+  Div,
+  Mod,
+  Pow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Function
+{
+  Len,
+  Abs,
+  Min,
+  Max,
+  Str,
+  Int,
+  Float,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token
+{
+  Number(DataValue),
+  String(String),
+  Ident(String),
+  Op(&'static str),
+  Open,
+  Close,
+  Comma,
+}
+
+const OPERATORS: [&str; 18] = [
+  "**", "==", "!=", "<=", ">=", "&&", "||", "<", ">", "+", "-", "*", "/", "%", "!", "(", ")", ",",
+];
+
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, String>
+{
+  let mut tokens = vec![];
+  let mut chars = source.char_indices().peekable();
+  while let Some(&(at, c)) = chars.peek()
+  {
+    if c.is_whitespace()
+    {
+      chars.next();
+    }
+    else if c.is_ascii_digit()
+    {
+      let mut text = String::new();
+      while let Some(&(_, c)) = chars.peek()
+      {
+        if !(c.is_ascii_digit() || c == '.')
+        {
+          break;
+        }
+        text.push(c);
+        chars.next();
+      }
+      let number = match text.contains('.')
+      {
+        true => text.parse().map(DataValue::Float).ok(),
+        false => text.parse().map(DataValue::Integer).ok(),
+      };
+      tokens.push((
+        at,
+        Token::Number(number.ok_or_else(|| format!("invalid number {text} at {at}"))?),
+      ));
+    }
+    else if c == '"'
+    {
+      chars.next();
+      let mut text = String::new();
+      loop
+      {
+        match chars.next()
+        {
+          Some((_, '"')) => break,
+          Some((_, '\\')) =>
+          {
+            match chars.next()
+            {
+              Some((_, 'n')) => text.push('\n'),
+              Some((_, 't')) => text.push('\t'),
+              Some((_, c @ ('"' | '\\'))) => text.push(c),
+              _ => return Err(format!("invalid escape in string at {at}")),
+            }
+          }
+          Some((_, c)) => text.push(c),
+          None => return Err(format!("unterminated string at {at}")),
+        }
+      }
+      tokens.push((at, Token::String(text)));
+    }
+    else if c.is_ascii_alphabetic() || c == '_'
+    {
+      let mut text = String::new();
+      while let Some(&(_, c)) = chars.peek()
+      {
+        if !(c.is_ascii_alphanumeric() || c == '_')
+        {
+          break;
+        }
+        text.push(c);
+        chars.next();
+      }
+      tokens.push((at, Token::Ident(text)));
+    }
+    else
+    {
+      let op = OPERATORS
+        .into_iter()
+        .find(|op| source[at..].starts_with(op))
+        .ok_or_else(|| format!("unexpected {c:?} at {at}"))?;
+      for _ in 0..op.len()
+      {
+        chars.next();
+      }
+      tokens.push((
+        at,
+        match op
+        {
+          "(" => Token::Open,
+          ")" => Token::Close,
+          "," => Token::Comma,
+          _ => Token::Op(op),
+        },
+      ));
+    }
+  }
+  Ok(tokens)
+}
+
+/// Binary operators by precedence level, loosest first
+const LEVELS: [&[(&str, BinaryOp)]; 6] = [
+  &[("||", BinaryOp::Or)],
+  &[("&&", BinaryOp::And)],
+  &[("==", BinaryOp::Eq), ("!=", BinaryOp::Neq)],
+  &[
+    ("<", BinaryOp::Lt),
+    ("<=", BinaryOp::Le),
+    (">", BinaryOp::Gt),
+    (">=", BinaryOp::Ge),
+  ],
+  &[("+", BinaryOp::Add), ("-", BinaryOp::Sub)],
+  &[
+    ("*", BinaryOp::Mul),
+    ("/", BinaryOp::Div),
+    ("%", BinaryOp::Mod),
+  ],
+];
+
+struct Parser
+{
+  tokens: Vec<(usize, Token)>,
+  next: usize,
+  end: usize,
+}
+
+impl Parser
+{
+  fn peek(&self) -> Option<&Token>
+  {
+    self.tokens.get(self.next).map(|(_, x)| x)
+  }
+
+  fn position(&self) -> usize
+  {
+    self
+      .tokens
+      .get(self.next)
+      .map(|(at, _)| *at)
+      .unwrap_or(self.end)
+  }
+
+  fn error(&self, expected: &str) -> String
+  {
+    match self.peek()
+    {
+      Some(_) => format!("expected {expected} at {}", self.position()),
+      None => format!("expected {expected} at the end"),
+    }
+  }
+
+  fn eat(&mut self, token: &Token) -> bool
+  {
+    let found = self.peek() == Some(token);
+    if found
+    {
+      self.next += 1;
+    }
+    found
+  }
+
+  fn binary(&mut self, level: usize) -> Result<Expr, String>
+  {
+    let Some(ops) = LEVELS.get(level)
+    else
+    {
+      return self.power();
+    };
+    let mut lhs = self.binary(level + 1)?;
+    while let Some(&(_, op)) = ops
+      .iter()
+      .find(|(text, _)| self.peek() == Some(&Token::Op(text)))
+    {
+      self.next += 1;
+      let rhs = self.binary(level + 1)?;
+      lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+    }
+    Ok(lhs)
+  }
+
+  fn power(&mut self) -> Result<Expr, String>
+  {
+    let base = self.unary()?;
+    match self.eat(&Token::Op("**"))
+    {
+      // the exponent may itself be a power, so `2 ** 3 ** 2` is `2 ** 9`
+      true =>
+      {
+        Ok(Expr::Binary(
+          BinaryOp::Pow,
+          Box::new(base),
+          Box::new(self.power()?),
+        ))
+      }
+      false => Ok(base),
+    }
+  }
+
+  fn unary(&mut self) -> Result<Expr, String>
+  {
+    if self.eat(&Token::Op("-"))
+    {
+      return Ok(Expr::Neg(Box::new(self.unary()?)));
+    }
+    if self.eat(&Token::Op("!"))
+    {
+      return Ok(Expr::Not(Box::new(self.unary()?)));
+    }
+    self.atom()
+  }
+
+  fn atom(&mut self) -> Result<Expr, String>
+  {
+    let at = self.position();
+    let token = self.peek().cloned().ok_or_else(|| self.error("a value"))?;
+    self.next += 1;
+    match token
+    {
+      Token::Number(x) => Ok(Expr::Literal(x)),
+      Token::String(x) => Ok(Expr::Literal(DataValue::String(x))),
+      Token::Open =>
+      {
+        let inner = self.binary(0)?;
+        match self.eat(&Token::Close)
+        {
+          true => Ok(inner),
+          false => Err(self.error("`)`")),
+        }
+      }
+      Token::Ident(name) if self.eat(&Token::Open) =>
+      {
+        let function = match name.as_str()
+        {
+          "len" => Function::Len,
+          "abs" => Function::Abs,
+          "min" => Function::Min,
+          "max" => Function::Max,
+          "str" => Function::Str,
+          "int" => Function::Int,
+          "float" => Function::Float,
+          _ => return Err(format!("unknown function {name} at {at}")),
+        };
+        let mut args = vec![];
+        if !self.eat(&Token::Close)
+        {
+          loop
+          {
+            args.push(self.binary(0)?);
+            if self.eat(&Token::Close)
+            {
+              break;
+            }
+            if !self.eat(&Token::Comma)
+            {
+              return Err(self.error("`,` or `)`"));
+            }
+          }
+        }
+        let arity_ok = match function
+        {
+          Function::Min | Function::Max => !args.is_empty(),
+          _ => args.len() == 1,
+        };
+        match arity_ok
+        {
+          true => Ok(Expr::Call(function, args)),
+          false => Err(format!("wrong number of arguments to {name} at {at}")),
+        }
+      }
+      Token::Ident(name) =>
+      {
+        match name.as_str()
+        {
+          "true" => Ok(Expr::Literal(DataValue::Boolean(true))),
+          "false" => Ok(Expr::Literal(DataValue::Boolean(false))),
+          "none" => Ok(Expr::Literal(DataValue::None)),
+          port if port.len() == 1 && port.as_bytes()[0].is_ascii_lowercase() =>
+          {
+            Ok(Expr::Port((port.as_bytes()[0] - b'a') as usize))
+          }
+          _ => Err(format!("unknown name {name} at {at}")),
+        }
+      }
+      _ =>
+      {
+        self.next -= 1;
+        Err(self.error("a value"))
+      }
+    }
+  }
+}
+
+impl Expr
+{
+  /// Fails with [`EvalError::InvalidExpression`] naming what was expected and where, as
+  /// a byte offset into `source`.
+  pub fn parse(source: &str) -> Result<Self, EvalError>
+  {
+    let invalid = |reason: String| EvalError::InvalidExpression(format!("{source:?}: {reason}"));
+    let mut parser = Parser {
+      tokens: tokenize(source).map_err(invalid)?,
+      next: 0,
+      end: source.len(),
+    };
+    let expr = parser.binary(0).map_err(invalid)?;
+    match parser.peek()
+    {
+      None => Ok(expr),
+      Some(_) => Err(invalid(parser.error("an operator"))),
+    }
+  }
+
+  /// How many input ports the expression reads, which is one more than the highest
+  /// port it names
+  pub fn port_count(&self) -> usize
+  {
+    match self
+    {
+      Expr::Literal(_) => 0,
+      Expr::Port(port) => port + 1,
+      Expr::Neg(x) | Expr::Not(x) => x.port_count(),
+      Expr::Binary(_, lhs, rhs) => lhs.port_count().max(rhs.port_count()),
+      Expr::Call(_, args) => args.iter().map(Expr::port_count).max().unwrap_or(0),
+    }
+  }
+
+  pub fn evaluate(&self, inputs: &[DataValue]) -> Result<DataValue, EvalError>
+  {
+    match self
+    {
+      Expr::Literal(x) => Ok(x.clone()),
+      Expr::Port(port) =>
+      {
+        inputs
+          .get(*port)
+          .cloned()
+          .ok_or(EvalError::IncorrectInputCount)
+      }
+      // like the `Neg` node
+      Expr::Neg(x) => Ok((x.evaluate(inputs)? * DataValue::Integer(-1))?),
+      Expr::Not(x) => Ok(DataValue::Boolean(!truthy(x.evaluate(inputs)?)?)),
+      Expr::Binary(BinaryOp::And, lhs, rhs) =>
+      {
+        Ok(DataValue::Boolean(
+          truthy(lhs.evaluate(inputs)?)? && truthy(rhs.evaluate(inputs)?)?,
+        ))
+      }
+      Expr::Binary(BinaryOp::Or, lhs, rhs) =>
+      {
+        Ok(DataValue::Boolean(
+          truthy(lhs.evaluate(inputs)?)? || truthy(rhs.evaluate(inputs)?)?,
+        ))
+      }
+      Expr::Binary(op, lhs, rhs) => binary(*op, lhs.evaluate(inputs)?, rhs.evaluate(inputs)?),
+      Expr::Call(function, args) =>
+      {
+        let args = args
+          .iter()
+          .map(|x| x.evaluate(inputs))
+          .collect::<Result<Vec<_>, _>>()?;
+        call(*function, args)
+      }
+    }
+  }
+}
+
+/// Casts like the logic nodes do, so `none` is false
+fn truthy(value: DataValue) -> Result<bool, EvalError>
+{
+  match value
+    .try_cast(DataType::Boolean)
+    .map_err(EvalError::CastError)?
+  {
+    DataValue::Boolean(x) => Ok(x),
+    _ => unreachable!("cast to Boolean"),
+  }
+}
+
+fn binary(op: BinaryOp, lhs: DataValue, rhs: DataValue) -> Result<DataValue, EvalError>
+{
+  let ordered = |lhs: DataValue, rhs: DataValue| {
+    lhs
+      .compare(&rhs)
+      .ok_or_else(|| EvalError::Incomparable(lhs.get_type(), rhs.get_type()))
+  };
+  Ok(match op
+  {
+    BinaryOp::Add => (lhs + rhs)?,
+    BinaryOp::Sub => (lhs - rhs)?,
+    BinaryOp::Mul => (lhs * rhs)?,
+    BinaryOp::Div => (lhs / rhs)?,
+    BinaryOp::Mod => (lhs % rhs)?,
+    BinaryOp::Pow => lhs.pow(&rhs)?,
+    BinaryOp::Eq => DataValue::Boolean(lhs.equals(&rhs, 0.0)),
+    BinaryOp::Neq => DataValue::Boolean(!lhs.equals(&rhs, 0.0)),
+    BinaryOp::Lt => DataValue::Boolean(ordered(lhs, rhs)?.is_lt()),
+    BinaryOp::Le => DataValue::Boolean(ordered(lhs, rhs)?.is_le()),
+    BinaryOp::Gt => DataValue::Boolean(ordered(lhs, rhs)?.is_gt()),
+    BinaryOp::Ge => DataValue::Boolean(ordered(lhs, rhs)?.is_ge()),
+    BinaryOp::And | BinaryOp::Or => unreachable!("short circuits"),
+  })
+}
+
+fn call(function: Function, args: Vec<DataValue>) -> Result<DataValue, EvalError>
+{
+  let mismatch = |expected: DataType| {
+    EvalError::IncorrectTyping {
+      got: args.iter().map(|x| x.get_type()).collect(),
+      expected: vec![expected],
+    }
+  };
+  match (function, &args[..])
+  {
+    (Function::Len, [DataValue::String(x)]) => Ok(DataValue::Integer(x.chars().count() as i64)),
+    (Function::Len, [DataValue::Array(x)]) => Ok(DataValue::Integer(x.len() as i64)),
+    (Function::Len, [DataValue::Object(x)]) => Ok(DataValue::Integer(x.len() as i64)),
+    (Function::Len, _) => Err(mismatch(DataType::Array)),
+    (Function::Abs, [DataValue::Integer(x)]) => Ok(DataValue::Integer(x.abs())),
+    (Function::Abs, [DataValue::Float(x)]) => Ok(DataValue::Float(x.abs())),
+    (Function::Abs, _) => Err(mismatch(DataType::Float)),
+    (Function::Min | Function::Max, _) =>
+    {
+      let mut best = args[0].clone();
+      for x in &args[1..]
+      {
+        let order = x
+          .compare(&best)
+          .ok_or_else(|| EvalError::Incomparable(x.get_type(), best.get_type()))?;
+        if (function == Function::Min && order.is_lt())
+          || (function == Function::Max && order.is_gt())
+        {
+          best = x.clone();
+        }
+      }
+      Ok(best)
+    }
+    (Function::Str, [x]) => Ok(DataValue::String(x.to_string())),
+    (Function::Int, [DataValue::String(x)]) =>
+    {
+      x.trim()
+        .parse()
+        .map(DataValue::Integer)
+        .map_err(|_| mismatch(DataType::Integer))
+    }
+    (Function::Float, [DataValue::String(x)]) =>
+    {
+      x.trim()
+        .parse()
+        .map(DataValue::Float)
+        .map_err(|_| mismatch(DataType::Float))
+    }
+    (Function::Int, [x]) => x.try_cast(DataType::Integer).map_err(EvalError::CastError),
+    (Function::Float, [x]) => x.try_cast(DataType::Float).map_err(EvalError::CastError),
+    (Function::Str | Function::Int | Function::Float, _) => Err(EvalError::IncorrectInputCount),
+  }
+}
+
+impl NodeType
+{
+  pub(super) fn eval_expr(source: &str, inputs: Vec<DataValue>)
+    -> Result<Vec<DataValue>, EvalError>
+  {
+    Ok(vec![Expr::parse(source)?.evaluate(&inputs)?])
+  }
+}
@@ -0,0 +1,115 @@
+use super::{
+  nodes::NodeType,
+  typing::{DataType, DataValue},
+};
+use crate::{config::SandboxPolicy, eval::EvalError};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Filesystem operations on String paths, relative ones resolving against the working
+/// directory like `Open(File)`.
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, PartialEq)]
+pub enum FsOp
+{
+  /// Outputs the paths of the entries in a directory as an Array of Strings, sorted
+  ListDir,
+  /// Outputs whether anything exists at the path
+  Exists,
+  /// Deletes a file, or a directory along with everything in it
+  Delete,
+  /// Moves the path on port 0 to the path on port 1, replacing a file already there
+  Rename,
+  /// Creates a directory along with any missing parents, doing nothing if it exists
+  MkDir,
+}
+
+/// The `count` String paths of `inputs`, failing if the sandbox does not allow one
+fn expect_paths<'a>(
+  inputs: &'a [DataValue],
+  count: usize,
+  sandbox: &SandboxPolicy,
+) -> Result<Vec<&'a str>, EvalError>
+{
+  if inputs.len() != count
+  {
+    return Err(EvalError::IncorrectInputCount);
+  }
+  inputs
+    .iter()
+    .map(|x| {
+      match x
+      {
+        DataValue::String(path) =>
+        {
+          sandbox.check_path(path)?;
+          Ok(path.as_str())
+        }
+        _ =>
+        {
+          Err(EvalError::IncorrectTyping {
+            got: inputs.iter().map(|x| x.get_type()).collect(),
+            expected: vec![DataType::String; count],
+          })
+        }
+      }
+    })
+    .collect()
+}
+
+impl NodeType
+{
+  pub(super) async fn eval_fs(
+    op: FsOp,
+    sandbox: &SandboxPolicy,
+    inputs: Vec<DataValue>,
+  ) -> Result<Vec<DataValue>, EvalError>
+  {
+    match op
+    {
+      FsOp::ListDir =>
+      {
+        let path = expect_paths(&inputs, 1, sandbox)?[0];
+        let mut entries = tokio::fs::read_dir(path).await?;
+        let mut paths = Vec::new();
+        while let Some(entry) = entries.next_entry().await?
+        {
+          paths.push(entry.path().to_string_lossy().into_owned());
+        }
+        paths.sort();
+        Ok(vec![DataValue::Array(
+          paths.into_iter().map(DataValue::String).collect(),
+        )])
+      }
+      FsOp::Exists =>
+      {
+        let path = expect_paths(&inputs, 1, sandbox)?[0];
+        Ok(vec![DataValue::Boolean(tokio::fs::try_exists(path).await?)])
+      }
+      FsOp::Delete =>
+      {
+        let path = expect_paths(&inputs, 1, sandbox)?[0];
+        if tokio::fs::symlink_metadata(path).await?.is_dir()
+        {
+          tokio::fs::remove_dir_all(path).await?;
+        }
+        else
+        {
+          tokio::fs::remove_file(path).await?;
+        }
+        Ok(vec![DataValue::None])
+      }
+      FsOp::Rename =>
+      {
+        let paths = expect_paths(&inputs, 2, sandbox)?;
+        tokio::fs::rename(paths[0], paths[1]).await?;
+        Ok(vec![DataValue::None])
+      }
+      FsOp::MkDir =>
+      {
+        let path = expect_paths(&inputs, 1, sandbox)?[0];
+        tokio::fs::create_dir_all(path).await?;
+        Ok(vec![DataValue::None])
+      }
+    }
+  }
+}
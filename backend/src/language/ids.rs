@@ -0,0 +1,78 @@
+use super::{
+  nodes::NodeType,
+  typing::{DataType, DataValue},
+};
+use crate::eval::EvalError;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// UUIDs are passed around graphs as Strings in the usual hyphenated form.
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, PartialEq)]
+pub enum IdOp
+{
+  /// Derives a UUID v5 from the namespace UUID on port 0 (None for the nil UUID) and
+  /// the name on port 1, the same way the runtime derives scoped node ids. The same
+  /// namespace and name always give the same UUID.
+  V5,
+  /// Outputs the UUID of a Handle or Agent as a String
+  ToString,
+  /// Checks that a String is a UUID, outputting it in lowercase hyphenated form
+  Parse,
+}
+
+fn parse(value: &str) -> Result<Uuid, EvalError>
+{
+  Uuid::parse_str(value).map_err(|_| EvalError::InvalidUuid(value.to_string()))
+}
+
+impl NodeType
+{
+  pub(super) fn eval_id(op: IdOp, inputs: Vec<DataValue>) -> Result<Vec<DataValue>, EvalError>
+  {
+    let id = match (op, inputs.as_slice())
+    {
+      (IdOp::V5, [namespace, DataValue::String(name)]) =>
+      {
+        let namespace = match namespace
+        {
+          DataValue::String(x) => parse(x)?,
+          DataValue::None => Uuid::nil(),
+          _ =>
+          {
+            return Err(EvalError::IncorrectTyping {
+              got: inputs.iter().map(|x| x.get_type()).collect(),
+              expected: vec![DataType::String, DataType::String],
+            })
+          }
+        };
+        Uuid::new_v5(&namespace, name.as_bytes())
+      }
+      (IdOp::ToString, [DataValue::Handle(id) | DataValue::Agent(_, id)]) => *id,
+      (IdOp::Parse, [DataValue::String(x)]) => parse(x)?,
+      (IdOp::V5, [_, _]) =>
+      {
+        return Err(EvalError::IncorrectTyping {
+          got: inputs.iter().map(|x| x.get_type()).collect(),
+          expected: vec![DataType::String, DataType::String],
+        })
+      }
+      (IdOp::ToString, [_]) =>
+      {
+        return Err(EvalError::IncorrectTyping {
+          got: inputs.iter().map(|x| x.get_type()).collect(),
+          expected: vec![DataType::Handle],
+        })
+      }
+      (IdOp::Parse, [_]) =>
+      {
+        return Err(EvalError::IncorrectTyping {
+          got: inputs.iter().map(|x| x.get_type()).collect(),
+          expected: vec![DataType::String],
+        })
+      }
+      _ => return Err(EvalError::IncorrectInputCount),
+    };
+    Ok(vec![DataValue::String(id.hyphenated().to_string())])
+  }
+}
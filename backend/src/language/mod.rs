@@ -1,2 +1,17 @@
+pub mod arrays;
+pub mod builder;
+pub mod bytes;
+pub mod dates;
+pub mod expr;
+pub mod fs;
+pub mod ids;
 pub mod nodes;
+pub mod objects;
+pub mod optimize;
+pub mod patterns;
+pub mod random;
+pub mod records;
+pub mod registry;
+pub mod schema;
 pub mod typing;
+pub mod wrapped;
@@ -1,14 +1,30 @@
-use super::typing::{DataType, DataValue};
-use crate::ai::{AgentArgs, AgentType};
-use crate::eval::{
-  ControlInputConnection, ControlPort, DataInputConnection, EvalError, OutputConnection,
-};
+use super::arrays::ArrayOp;
+use super::bytes::BytesOp;
+use super::dates::DateTimeOp;
+use super::expr::Expr;
+use super::fs::FsOp;
+use super::ids::IdOp;
+use super::objects::ObjectOp;
+use super::random::RandomOp;
+use super::records::{self, RecordOp, Records};
+use super::typing::{ArithmaticError, DataType, DataValue};
+use super::wrapped::WrapOp;
+use crate::ai::{AgentArgs, AgentType, Tool, TranscriptFormat};
+use crate::capabilities::Capability;
+use crate::config::Permission;
+use crate::eval::{ControlPort, DataInputConnection, EvalError, OutputConnection};
 use crate::eval::{EvaluateIt, Evaluator, ExecutionNode};
+use crate::http::{self, Frame, WebSocket};
 use crate::logging::Logger;
+use crate::secrets;
+use indexmap::IndexMap;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::ops::{BitAnd, BitOr, BitXor, Mul};
+use std::collections::HashMap;
+use std::future::Future;
+use std::ops::{BitAnd, BitOr, BitXor};
 use std::sync::Arc;
+use std::time::Duration;
 use std::vec;
 use tokio::io::{AsyncBufReadExt, BufReader};
 use uuid::Uuid;
@@ -28,7 +44,142 @@ pub enum AtomicType
   IsNone,
   LogicalOp(AtomicLogic),
   AgentOp(AgentOperation),
+  Array(ArrayOp),
+  Bytes(BytesOp),
+  DateTime(DateTimeOp),
+  Fs(FsOp),
+  Http(HttpOp),
+  Id(IdOp),
+  Object(ObjectOp),
+  Process(ProcessOp),
+  Random(RandomOp),
+  Record(RecordOp),
+  Wrap(WrapOp),
+  /// Outputs the value on port 1 if the condition on port 0 is true, otherwise the
+  /// one on port 2. Unlike `If` both values are computed either way.
+  Select,
+  /// Outputs how many times it has run, counting this run. A true value on the
+  /// optional reset input restarts the count, so that run outputs 1.
+  Counter,
+  /// Outputs the milliseconds elapsed since it first ran, 0 on that first run. A true
+  /// value on the optional reset input restarts it from this run.
+  Stopwatch,
+  /// Stops the whole graph, Complex callers included, with the message on port 0 and
+  /// the exit code on the optional port 1 (1 if not given). The CLI prints the message
+  /// and exits with the code.
+  Abort,
+  /// Outputs an Object describing the run: `run_id`, `scope_id`, `graph_path`,
+  /// `started_at_ms` (milliseconds since the Unix epoch), `params`, the `--param`
+  /// values given on the command line, and `capabilities`, the optional subsystems
+  /// this build includes such as `"http"`
+  RuntimeInfo,
+  /// Outputs the value of the environment variable, None if it is not set
+  Env(String),
+  /// Outputs the named secret from the store `[secrets]` in `agentnodes.toml` picks,
+  /// failing if it has none by that name. Like any value it shows in traces and logs.
+  Secret(String),
+  /// Outputs the path of the run's scratch directory, shared with its Complex nodes
+  /// and removed once the run ends unless `--keep-workspace` is given. Processes the
+  /// graph spawns find it in `AGENTNODES_WORKSPACE`.
+  TempDir,
+  /// Evaluates an expression over its inputs, read as `a`, `b`, `c` and so on, such as
+  /// `a * 2 + len(b)`, outputting the result. See [`Expr`] for the syntax.
+  Expr(String),
+  /// Parses the JSON in a String into the matching value: Objects, Arrays, Strings,
+  /// Integers or Floats, Booleans and None for `null`. Surrounding whitespace and a
+  /// Markdown code fence around the JSON, as agents often reply with, are ignored.
+  JsonParse,
+  /// Renders any value as JSON, with Object keys sorted and indented if `pretty`
+  JsonStringify
+  {
+    pretty: bool,
+  },
+  /// Whether the regex on port 0 matches anywhere in the String on port 1
+  RegexMatch,
+  /// The first match of the regex on port 0 in the String on port 1, None if there is
+  /// none
+  RegexFind,
+  /// The groups of the first match of the regex on port 0 in the String on port 1: an
+  /// Object keyed by group name if the regex names any, otherwise an Array of the
+  /// numbered groups. Groups that took no part in the match are None, and so is the
+  /// whole output if nothing matched.
+  RegexCaptures,
 }
+impl AtomicType
+{
+  /// What the sandbox policy has to allow for this node to run, nothing for the nodes
+  /// a dry run stands in for when `dry_run` is set
+  pub(crate) fn permission(&self, dry_run: bool) -> Option<Permission>
+  {
+    match self
+    {
+      AtomicType::AgentOp(AgentOperation::Create(_))
+      | AtomicType::Io(AtomicIo::Open(IoType::TcpSocket))
+        if dry_run =>
+      {
+        None
+      }
+      AtomicType::Process(_) => Some(Permission::Processes),
+      AtomicType::Http(_)
+      | AtomicType::Io(AtomicIo::Open(
+        IoType::TcpSocket | IoType::TcpListener | IoType::UdpSocket | IoType::WebSocket,
+      )) => Some(Permission::Network),
+      AtomicType::AgentOp(AgentOperation::Create(kind)) if *kind != AgentType::Mock =>
+      {
+        Some(Permission::Network)
+      }
+      AtomicType::AgentOp(AgentOperation::ExportTranscript(_)) => Some(Permission::FsWrites),
+      AtomicType::AgentOp(AgentOperation::AttachTools(tools)) =>
+      {
+        tools.iter().find_map(Tool::permission)
+      }
+      AtomicType::Io(AtomicIo::Open(IoType::File(mode))) if *mode != FileMode::Read =>
+      {
+        Some(Permission::FsWrites)
+      }
+      AtomicType::Fs(op) if *op != FsOp::ListDir && *op != FsOp::Exists =>
+      {
+        Some(Permission::FsWrites)
+      }
+      _ => None,
+    }
+  }
+
+  /// What the build has to include for this node to run
+  pub(crate) fn capability(&self) -> Option<Capability>
+  {
+    match self
+    {
+      AtomicType::Process(_) => Some(Capability::Subprocess),
+      AtomicType::Http(_) | AtomicType::Io(AtomicIo::Open(IoType::WebSocket)) =>
+      {
+        Some(Capability::Http)
+      }
+      AtomicType::AgentOp(AgentOperation::Create(kind)) => kind.capability(),
+      AtomicType::AgentOp(AgentOperation::AttachTools(tools)) =>
+      {
+        tools.iter().find_map(Tool::capability)
+      }
+      _ => None,
+    }
+  }
+}
+
+/// Applies an optional time limit to a fallible future
+pub(crate) async fn with_timeout<T, E>(
+  limit: Option<Duration>,
+  future: impl Future<Output = Result<T, E>>,
+) -> Result<T, EvalError>
+where
+  EvalError: From<E>,
+{
+  match limit
+  {
+    Some(limit) => Ok(tokio::time::timeout(limit, future).await??),
+    None => Ok(future.await?),
+  }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, PartialEq)]
 
 pub enum Variable
@@ -41,8 +192,45 @@ pub enum Variable
 pub enum AgentOperation
 {
   Create(AgentType),
+  /// Takes an agent, a message and optionally an Integer priority, higher going first
+  /// when agent requests are limited and ordered by priority
   Send,
   Recieve,
+  ExportHistory,
+  ImportHistory,
+  /// Takes an agent and attaches these tools to it, outputting the agent. When it calls
+  /// one in answer to a `Send`, the tool is run and the result sent back to it before
+  /// the `Send` finishes. Fails if the sandbox does not allow what a tool needs.
+  AttachTools(Vec<Tool>),
+  /// Takes an agent and a path (a String, or None) and renders the agent's conversation
+  /// with each message's role, time and estimated tokens, outputting it and writing it
+  /// to the path if given
+  ExportTranscript(TranscriptFormat),
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, PartialEq)]
+pub enum HttpOp
+{
+  /// Takes the method, URL, headers (an Object of Strings, or None) and body (a
+  /// String, or None), outputting the status, response headers and response body
+  Request,
+  /// Takes an address, method and path and routes matching requests to the given
+  /// Complex graph, starting a server on the address if needed. Outputs the address
+  /// the server is bound to. Once the graph finishes the CLI keeps serving until
+  /// interrupted.
+  Route(String),
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, PartialEq)]
+pub enum ProcessOp
+{
+  /// Takes the program, its arguments (an Array of Strings, or None) and extra
+  /// environment variables (an Object, or None). Outputs handles for the process, its
+  /// stdin and its stdout, to be used with `Write`, `Read` and `GetLine`.
+  Spawn,
+  /// Waits for a process handle to exit, closing its stdin first, and outputs the exit
+  /// code, or None if it was ended by a signal
+  Wait,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, PartialEq)]
@@ -52,6 +240,18 @@ pub enum ControlFlow
   End,
   Loop(LoopNodes),
   If,
+  /// Runs the wrapped node with this node's inputs. Port 0 is its output (an `Array` if
+  /// it has several) and port 1 is `None`; if it fails port 0 is `None` and port 1 the
+  /// error as an `Object` with `kind` and `message`, and the graph carries on.
+  Try(Box<NodeType>),
+  /// Runs the wrapped node with this node's inputs, giving up after the given number of
+  /// milliseconds. The outputs are as for `Try`, with a `TimedOut` error when it took
+  /// too long; other failures are not caught.
+  Timeout(Box<NodeType>, u64),
+  /// Joins branches started together: waits until every control connection into it has
+  /// triggered rather than just one, then reads all of its inputs at once and outputs
+  /// them as a single `Array` in port order.
+  Parallel,
 }
 #[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, PartialEq)]
 pub enum LoopNodes
@@ -67,8 +267,11 @@ pub enum AtomicLogic
   Or,
   Xor,
   Not,
+  /// Equal by [`DataValue::equals`]; floats must match exactly
   Eq,
   Neq,
+  /// Equal by [`DataValue::equals`], floats being equal within the given epsilon
+  ApproxEq(f64),
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, PartialEq)]
@@ -76,16 +279,103 @@ pub enum AtomicIo
 {
   ConsoleInput,
   Open(IoType),
+  /// Reads at most the Integer on port 1 of bytes from a handle, outputting the Bytes
+  /// read, empty at the end of a stream
   Read,
+  /// Writes the String or Bytes on port 1 to a handle
   Write,
   GetLine,
+  /// Waits for a connection on a `TcpListener` handle, outputting the connection's
+  /// handle and the peer address
+  Accept,
+  /// Sends the String or Bytes on port 1 as one datagram from a `UdpSocket` handle to
+  /// the address on port 2, outputting the number of bytes sent
+  SendTo,
+  /// Waits for a datagram of at most the given size on a `UdpSocket` handle, outputting
+  /// its contents as a String and the sender's address
+  RecvFrom,
+  /// Sends a frame on a `WebSocket` handle, a text frame for a String or a binary
+  /// frame for Bytes, or an Array of Bytes or of Integers from 0 to 255
+  SendFrame,
+  /// Waits for the next frame on a `WebSocket` handle, outputting a String for a text
+  /// frame, Bytes for a binary one and None once the connection is closed
+  RecvFrame,
+  /// Closes a handle, after which it can no longer be used. Streams are flushed and
+  /// shut down, WebSockets send a close frame and processes are killed.
+  Close,
+  /// Writes out anything buffered for a stream or WebSocket handle
+  Flush,
 }
 
+// deserialized through the impl below, which also reads the plain `"File"` graphs
+// were saved with before files had modes
 #[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, PartialEq)]
+#[serde(remote = "Self")]
 pub enum IoType
 {
-  File,
+  /// Opens the path on port 0 in the given mode
+  File(FileMode),
   TcpSocket,
+  /// Binds to the given host and port, connections are taken with `Accept`
+  TcpListener,
+  /// Binds to the given host and port, used with `SendTo` and `RecvFrom`
+  UdpSocket,
+  /// Connects to a `ws://` or `wss://` URL, used with `SendFrame` and `RecvFrame`
+  WebSocket,
+}
+
+impl Serialize for IoType
+{
+  fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>
+  {
+    IoType::serialize(self, serializer)
+  }
+}
+
+impl<'de> Deserialize<'de> for IoType
+{
+  fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>
+  {
+    let value = serde_json::Value::deserialize(deserializer)?;
+    match value.as_str()
+    {
+      // files could only be read then
+      Some("File") => Ok(IoType::File(FileMode::Read)),
+      _ => IoType::deserialize(value).map_err(serde::de::Error::custom),
+    }
+  }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, JsonSchema, PartialEq)]
+pub enum FileMode
+{
+  /// Reads an existing file
+  Read,
+  /// Reads and writes an existing file from its start, overwriting in place
+  Write,
+  /// Writes to the end of the file, creating it if missing
+  Append,
+  /// Reads and writes a new file, failing if it already exists
+  Create,
+  /// Writes the file from empty, creating it if missing
+  Truncate,
+}
+
+impl FileMode
+{
+  pub fn options(self) -> tokio::fs::OpenOptions
+  {
+    let mut options = tokio::fs::OpenOptions::new();
+    match self
+    {
+      FileMode::Read => options.read(true),
+      FileMode::Write => options.read(true).write(true),
+      FileMode::Append => options.append(true).create(true),
+      FileMode::Create => options.read(true).write(true).create_new(true),
+      FileMode::Truncate => options.write(true).create(true).truncate(true),
+    };
+    options
+  }
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, PartialEq)]
@@ -99,10 +389,81 @@ pub enum AtomicBinOp
   Mod,
 }
 
+/// Applied to each input on its own, outputting one value per input. Apart from `Neg`
+/// and `Abs`, which keep Integers as they are, they output Floats, or Integers for the
+/// rounding operations. Results outside the range of an Integer are undefined.
 #[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, PartialEq)]
 pub enum AtomicUnaryOp
 {
   Neg,
+  Abs,
+  Sqrt,
+  Floor,
+  Ceil,
+  /// Rounds halfway values away from zero
+  Round,
+  /// Natural logarithm
+  Ln,
+  Exp,
+  /// In radians, like the other trigonometric operations
+  Sin,
+  Cos,
+  Tan,
+  Asin,
+  Acos,
+  Atan,
+}
+
+impl AtomicUnaryOp
+{
+  fn apply(&self, x: DataValue) -> Result<DataValue, ArithmaticError>
+  {
+    let float = match x
+    {
+      DataValue::Integer(i) => i as f64,
+      DataValue::Float(f) => f,
+      _ => return Err(ArithmaticError::NotANumber(x)),
+    };
+    let undefined = || ArithmaticError::Undefined(format!("{self:?}"), x.clone());
+    // i64::MAX as f64 rounds up to 2^63, just past the range
+    let rounded = |f: f64| {
+      match (i64::MIN as f64..i64::MAX as f64).contains(&f)
+      {
+        true => Ok(DataValue::Integer(f as i64)),
+        false => Err(undefined()),
+      }
+    };
+    let integer = |i: Option<i64>| i.map(DataValue::Integer).ok_or_else(undefined);
+    let result = match (self, &x)
+    {
+      (AtomicUnaryOp::Neg, DataValue::Integer(i)) => return integer(i.checked_neg()),
+      (AtomicUnaryOp::Neg, _) => -float,
+      (AtomicUnaryOp::Abs, DataValue::Integer(i)) => return integer(i.checked_abs()),
+      (
+        AtomicUnaryOp::Floor | AtomicUnaryOp::Ceil | AtomicUnaryOp::Round,
+        DataValue::Integer(_),
+      ) => return Ok(x),
+      (AtomicUnaryOp::Floor, _) => return rounded(float.floor()),
+      (AtomicUnaryOp::Ceil, _) => return rounded(float.ceil()),
+      (AtomicUnaryOp::Round, _) => return rounded(float.round()),
+      (AtomicUnaryOp::Abs, _) => float.abs(),
+      (AtomicUnaryOp::Sqrt, _) => float.sqrt(),
+      (AtomicUnaryOp::Ln, _) => float.ln(),
+      (AtomicUnaryOp::Exp, _) => float.exp(),
+      (AtomicUnaryOp::Sin, _) => float.sin(),
+      (AtomicUnaryOp::Cos, _) => float.cos(),
+      (AtomicUnaryOp::Tan, _) => float.tan(),
+      (AtomicUnaryOp::Asin, _) => float.asin(),
+      (AtomicUnaryOp::Acos, _) => float.acos(),
+      (AtomicUnaryOp::Atan, _) => float.atan(),
+    };
+    // only a NaN in gives a NaN out, otherwise the input was out of the domain
+    match result.is_nan() && !float.is_nan()
+    {
+      true => Err(ArithmaticError::Undefined(format!("{self:?}"), x)),
+      false => Ok(DataValue::Float(result)),
+    }
+  }
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, PartialEq)]
@@ -115,22 +476,751 @@ pub enum NodeType
 #[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, PartialEq)]
 pub struct Instance
 {
+  /// What errors, logs and traces call the node besides its id. Connections in the
+  /// graph file may refer to the node by it, see [`Complex::from_json`].
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub name: Option<String>,
   pub node_type: NodeType,
-  default_overrides: std::collections::HashMap<String, DataValue>,
+  /// Values for the node's inputs by port number, such as `"1"`, used when nothing is
+  /// connected to the port or what is connected gives None
+  #[serde(default, skip_serializing_if = "std::collections::HashMap::is_empty")]
+  pub default_overrides: std::collections::HashMap<String, DataValue>,
   pub outputs: Vec<OutputConnection>,
   pub control_flow_in: Vec<ControlPort>,
   pub control_flow_out: Vec<ControlPort>,
   pub inputs: Vec<DataInputConnection>,
 }
 
+impl Instance
+{
+  /// A node of this type with nothing connected to it
+  pub fn new(node_type: NodeType) -> Self
+  {
+    Self {
+      name: None,
+      node_type,
+      default_overrides: std::collections::HashMap::new(),
+      outputs: vec![],
+      control_flow_in: vec![],
+      control_flow_out: vec![],
+      inputs: vec![],
+    }
+  }
+
+  /// How many inputs the node runs with: those connected, and any after them that
+  /// have a default
+  pub fn input_count(&self) -> usize
+  {
+    default_ports(&self.default_overrides)
+      .map(|x| x + 1)
+      .max()
+      .unwrap_or(0)
+      .max(self.inputs.len())
+  }
+}
+
+/// The port named `key` among `names`, or numbered `key` among `count`
+pub fn port_by_key(names: &[String], count: usize, key: &str) -> Option<usize>
+{
+  names
+    .iter()
+    .position(|x| !x.is_empty() && x == key)
+    .or_else(|| key.parse().ok().filter(|x| *x < count))
+}
+
+fn default_ports(
+  defaults: &std::collections::HashMap<String, DataValue>,
+) -> impl Iterator<Item = usize> + '_
+{
+  defaults.keys().filter_map(|x| x.parse().ok())
+}
+
+/// `values` padded out to `count` with None, then with each None that has a default in
+/// `defaults` replaced by it
+pub fn fill_defaults(
+  defaults: &std::collections::HashMap<String, DataValue>,
+  mut values: Vec<DataValue>,
+  count: usize,
+) -> Vec<DataValue>
+{
+  if values.len() < count
+  {
+    values.resize(count, DataValue::None);
+  }
+  for (port, value) in values.iter_mut().enumerate()
+  {
+    if *value == DataValue::None
+    {
+      if let Some(default) = defaults.get(&port.to_string())
+      {
+        *value = default.clone();
+      }
+    }
+  }
+  values
+}
+
+/// Version of the graph JSON format this build reads, bumped on breaking changes
+pub const FORMAT_VERSION: u32 = 2;
+
+/// Upgrades a graph from the version one above its index to the next, so graphs saved
+/// by older builds keep loading. Bumping [`FORMAT_VERSION`] takes one more.
+const MIGRATIONS: [Migration; FORMAT_VERSION as usize - 1] = [file_modes];
+
+type Migration = fn(&mut serde_json::Map<String, serde_json::Value>);
+
+/// Version 2 gave `Open` nodes' `File` the mode to open in, which was always reading
+fn file_modes(graph: &mut serde_json::Map<String, serde_json::Value>)
+{
+  fn visit(value: &mut serde_json::Value)
+  {
+    let serde_json::Value::Object(fields) = value
+    else
+    {
+      return;
+    };
+    for (key, value) in fields
+    {
+      match (key.as_str(), value.as_str())
+      {
+        ("Open", Some("File")) => *value = serde_json::json!({"File": "Read"}),
+        // what Value nodes hold is data, not nodes
+        ("Value", _) => (),
+        _ => visit(value),
+      }
+    }
+  }
+  if let Some(serde_json::Value::Object(instances)) = graph.get_mut("instances")
+  {
+    for instance in instances.values_mut()
+    {
+      if let Some(node_type) = instance.get_mut("node_type")
+      {
+        visit(node_type);
+      }
+    }
+  }
+}
+
+/// Graphs saved before the version was recorded
+fn first_version() -> u32
+{
+  1
+}
+
+/// Replaces the names the graph refers to nodes by with the ids of the instances
+/// carrying them. Anything that is not a string naming a node is left for
+/// deserializing to report.
+fn resolve_names(graph: &mut serde_json::Map<String, serde_json::Value>) -> Result<(), String>
+{
+  use serde_json::Value;
+  let mut ids = std::collections::HashMap::new();
+  for (id, instance) in graph
+    .get("instances")
+    .and_then(Value::as_object)
+    .into_iter()
+    .flatten()
+  {
+    if let Some(name) = instance.get("name").and_then(Value::as_str)
+    {
+      if ids.insert(name.to_string(), id.clone()).is_some()
+      {
+        return Err(format!("more than one node is named `{name}`"));
+      }
+    }
+  }
+  let resolve = |reference: &mut Value| -> Result<(), String> {
+    match reference
+    {
+      Value::String(x) if Uuid::parse_str(x).is_err() =>
+      {
+        let id = ids
+          .get(x.as_str())
+          .ok_or_else(|| format!("no node is named `{x}`"))?;
+        *x = id.clone();
+        Ok(())
+      }
+      _ => Ok(()),
+    }
+  };
+  if let Some(end) = graph.get_mut("end_node")
+  {
+    resolve(end)?;
+  }
+  for instance in graph
+    .get_mut("instances")
+    .and_then(Value::as_object_mut)
+    .into_iter()
+    .flat_map(|x| x.values_mut())
+  {
+    if let Some(x) = instance.pointer_mut("/node_type/Atomic/Control/Loop/Continue")
+    {
+      resolve(x)?;
+    }
+    for x in instance
+      .get_mut("outputs")
+      .and_then(Value::as_array_mut)
+      .into_iter()
+      .flatten()
+    {
+      resolve(x)?;
+    }
+    // inputs are a type, node and port, control connections a node and port
+    let inputs = instance.get_mut("inputs").and_then(Value::as_array_mut);
+    for x in inputs.into_iter().flatten().filter_map(|x| x.get_mut(1))
+    {
+      resolve(x)?;
+    }
+    for key in ["control_flow_in", "control_flow_out"]
+    {
+      let ports = instance.get_mut(key).and_then(Value::as_array_mut);
+      for x in ports
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_array_mut)
+        .flatten()
+        .filter_map(|x| x.get_mut(0))
+      {
+        resolve(x)?;
+      }
+    }
+  }
+  Ok(())
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, PartialEq)]
 pub struct Complex
 {
+  /// The version of the format the graph was saved in. [`Complex::from_json`]
+  /// migrates older ones to [`FORMAT_VERSION`].
+  #[serde(default = "first_version")]
+  pub format_version: u32,
   pub inputs: Vec<DataType>,
   pub outputs: Vec<DataType>,
   pub end_node: Uuid,
-  defaults: std::collections::HashMap<String, DataValue>,
+  /// Values for the graph's inputs by port number, such as `"0"`, used when a run
+  /// leaves one out or gives None for it
+  #[serde(default, skip_serializing_if = "std::collections::HashMap::is_empty")]
+  pub defaults: std::collections::HashMap<String, DataValue>,
   pub instances: std::collections::HashMap<uuid::Uuid, Instance>,
+  /// Enum types used by `DataType::Enum`, by name, with the variants their values may
+  /// take
+  #[serde(default, skip_serializing_if = "std::collections::HashMap::is_empty")]
+  pub enums: std::collections::HashMap<String, Vec<String>>,
+  /// Record types used by `DataType::Record` and the `Record` nodes, by name, with
+  /// their fields in order
+  #[serde(default, skip_serializing_if = "std::collections::HashMap::is_empty")]
+  pub records: Records,
+  /// Schema files of record types shared with other graphs, such as a project's, by
+  /// path relative to the graph. [`Complex::load`] adds the records they define that
+  /// the graph does not declare itself.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub types: Vec<String>,
+  /// Documentation shown by `describe`, none of which is needed to run the graph
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub title: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub description: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub author: Option<String>,
+  /// What each input is called, by port. Runs may give inputs and `defaults` by name as
+  /// well as by number, and the editor labels sockets with them.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub input_names: Vec<String>,
+  /// What each output is called, by port
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub output_names: Vec<String>,
+  /// What each input is for, by port
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub input_docs: Vec<String>,
+  /// What each output is for, by port
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub output_docs: Vec<String>,
+}
+
+impl Complex
+{
+  /// A graph without enums or documentation
+  pub fn new(
+    inputs: Vec<DataType>,
+    outputs: Vec<DataType>,
+    end_node: Uuid,
+    instances: std::collections::HashMap<Uuid, Instance>,
+  ) -> Self
+  {
+    Self {
+      format_version: FORMAT_VERSION,
+      inputs,
+      outputs,
+      end_node,
+      defaults: std::collections::HashMap::new(),
+      instances,
+      enums: std::collections::HashMap::new(),
+      records: Records::new(),
+      types: vec![],
+      title: None,
+      description: None,
+      author: None,
+      input_names: vec![],
+      output_names: vec![],
+      input_docs: vec![],
+      output_docs: vec![],
+    }
+  }
+
+  /// The port of the input named `key`, or numbered `key`
+  pub fn input_port(&self, key: &str) -> Option<usize>
+  {
+    port_by_key(&self.input_names, self.inputs.len(), key)
+  }
+
+  /// The default for input `port`, which `defaults` may give by its name or number
+  pub fn input_default(&self, port: usize) -> Option<&DataValue>
+  {
+    self
+      .defaults
+      .iter()
+      .find(|(key, _)| self.input_port(key) == Some(port))
+      .map(|(_, value)| value)
+  }
+
+  /// Reads the graph file at `path`, migrating it if it is in an older version of
+  /// the format
+  pub fn load(path: &str) -> Result<Self, EvalError>
+  {
+    let file = std::fs::File::open(path)?;
+    let json = serde_json::from_reader(std::io::BufReader::new(file))
+      .map_err(|e| EvalError::InvalidComplexNode(path.to_string(), e))?;
+    let mut graph = Self::from_json(path, json)?;
+    let dir = std::path::Path::new(path)
+      .parent()
+      .unwrap_or(std::path::Path::new(""));
+    for types in &graph.types
+    {
+      let types = dir.join(types).to_string_lossy().into_owned();
+      let file = std::fs::File::open(&types)?;
+      let records: Records = serde_json::from_reader(std::io::BufReader::new(file))
+        .map_err(|e| EvalError::InvalidComplexNode(types, e))?;
+      for (name, fields) in records
+      {
+        graph.records.entry(name).or_insert(fields);
+      }
+    }
+    Ok(graph)
+  }
+
+  /// Reads a graph saved in this or an older version of the format, migrating it to
+  /// [`FORMAT_VERSION`]. Graphs from newer builds are refused rather than misread.
+  ///
+  /// Wherever the graph refers to a node, as in `end_node`, connections and `Continue`
+  /// nodes, it may give the node's `name` instead of its id.
+  pub fn from_json(path: &str, mut json: serde_json::Value) -> Result<Self, EvalError>
+  {
+    if let serde_json::Value::Object(graph) = &mut json
+    {
+      // anything but a number is left for deserializing to report
+      let version = match graph.get("format_version")
+      {
+        None => first_version() as u64,
+        Some(x) => x.as_u64().unwrap_or(FORMAT_VERSION as u64),
+      };
+      if version > FORMAT_VERSION as u64
+      {
+        return Err(EvalError::UnsupportedFormatVersion {
+          path: path.to_string(),
+          version,
+          supported: FORMAT_VERSION,
+        });
+      }
+      if version < FORMAT_VERSION as u64
+      {
+        for migrate in MIGRATIONS.iter().skip(version.saturating_sub(1) as usize)
+        {
+          migrate(graph);
+        }
+        graph.insert("format_version".to_string(), FORMAT_VERSION.into());
+      }
+      resolve_names(graph).map_err(|e| {
+        EvalError::InvalidComplexNode(path.to_string(), serde::de::Error::custom(e))
+      })?;
+    }
+    serde_json::from_value(json).map_err(|e| EvalError::InvalidComplexNode(path.to_string(), e))
+  }
+
+  /// A summary of the graph for people using it: its title, description and author,
+  /// then the name, type and documentation of each input and output.
+  pub fn describe(&self) -> String
+  {
+    let mut out = String::new();
+    for line in [&self.title, &self.description].into_iter().flatten()
+    {
+      out += &format!("{line}\n");
+    }
+    if let Some(author) = &self.author
+    {
+      out += &format!("Author: {author}\n");
+    }
+    for (name, types, names, docs) in [
+      ("Inputs", &self.inputs, &self.input_names, &self.input_docs),
+      (
+        "Outputs",
+        &self.outputs,
+        &self.output_names,
+        &self.output_docs,
+      ),
+    ]
+    {
+      out += &format!("{name}:\n");
+      for (i, data_type) in types.iter().enumerate()
+      {
+        match names.get(i).filter(|x| !x.is_empty())
+        {
+          Some(port) => out += &format!("  {i} {port}: {data_type}"),
+          None => out += &format!("  {i}: {data_type}"),
+        }
+        if let Some(default) = self.input_default(i).filter(|_| name == "Inputs")
+        {
+          out += &format!(" = {default}");
+        }
+        match docs.get(i).filter(|x| !x.is_empty())
+        {
+          Some(doc) => out += &format!(" - {doc}\n"),
+          None => out += "\n",
+        }
+      }
+    }
+    if !self.records.is_empty()
+    {
+      out += "Records:\n";
+      let mut names: Vec<_> = self.records.keys().collect();
+      names.sort();
+      for name in names
+      {
+        let fields: Vec<_> = self.records[name]
+          .iter()
+          .map(|(field, data_type)| format!("{field}: {data_type}"))
+          .collect();
+        out += &format!("  {name} {{ {} }}\n", fields.join(", "));
+      }
+    }
+    out
+  }
+
+  /// Checks the graph can be run, rejecting it with the first problem one of the
+  /// `validate_*` checks below finds.
+  pub fn validate(&self) -> Result<(), EvalError>
+  {
+    self.validate_listeners()?;
+    self.validate_enums()?;
+    self.validate_capabilities()?;
+    self.validate_port_names()?;
+    self.validate_defaults()?;
+    self.validate_casts()?;
+    self.validate_outputs()?;
+    self.validate_expressions()
+  }
+
+  /// Checks the graph for nodes that read their own outputs, either directly or
+  /// through a node that reads from them in turn. Either case would leave both
+  /// nodes waiting on each other forever.
+  fn validate_listeners(&self) -> Result<(), EvalError>
+  {
+    for (id, instance) in &self.instances
+    {
+      for (_, upstream, _) in &instance.inputs
+      {
+        if upstream == id
+        {
+          return Err(EvalError::SelfListen(vec![*id]));
+        }
+        let mutual = self
+          .instances
+          .get(upstream)
+          .map(|other| other.inputs.iter().any(|(_, x, _)| x == id))
+          .unwrap_or(false);
+        if mutual
+        {
+          return Err(EvalError::SelfListen(vec![*id, *upstream]));
+        }
+      }
+    }
+    Ok(())
+  }
+
+  /// Checks no two inputs or outputs share a name, that no name reads as a port
+  /// number and that there are no more names than ports.
+  fn validate_port_names(&self) -> Result<(), EvalError>
+  {
+    for (side, names, ports) in [
+      ("input", &self.input_names, self.inputs.len()),
+      ("output", &self.output_names, self.outputs.len()),
+    ]
+    {
+      if names.len() > ports
+      {
+        return Err(EvalError::InvalidPortName(format!(
+          "{} {side} names for {ports} {side}s",
+          names.len()
+        )));
+      }
+      for (i, name) in names.iter().enumerate().filter(|(_, x)| !x.is_empty())
+      {
+        if name.parse::<usize>().is_ok()
+        {
+          return Err(EvalError::InvalidPortName(format!(
+            "{side} {i} is named `{name}`, which reads as a port number"
+          )));
+        }
+        if names[..i].contains(name)
+        {
+          return Err(EvalError::InvalidPortName(format!(
+            "more than one {side} is named `{name}`"
+          )));
+        }
+      }
+    }
+    Ok(())
+  }
+
+  /// Checks defaults are keyed by port number, and the graph's by the number or name
+  /// of one of its inputs.
+  fn validate_defaults(&self) -> Result<(), EvalError>
+  {
+    let graph = self.defaults.keys().map(|x| (None, x));
+    let nodes = self
+      .instances
+      .iter()
+      .flat_map(|(id, x)| x.default_overrides.keys().map(|key| (Some(*id), key)));
+    for (node, key) in graph.chain(nodes)
+    {
+      let valid = match node
+      {
+        None => self.input_port(key).is_some(),
+        Some(_) => key.parse::<usize>().is_ok(),
+      };
+      if !valid
+      {
+        return Err(EvalError::InvalidDefault {
+          node,
+          port: key.clone(),
+        });
+      }
+    }
+    Ok(())
+  }
+
+  /// Checks each `Cast` node reads a type that can cast to its own, going by the type
+  /// its input is declared with.
+  fn validate_casts(&self) -> Result<(), EvalError>
+  {
+    for (id, instance) in &self.instances
+    {
+      let NodeType::Atomic(AtomicType::Cast(to)) = &instance.node_type
+      else
+      {
+        continue;
+      };
+      let Some((from, _, _)) = instance.inputs.first()
+      else
+      {
+        continue;
+      };
+      if !from.casts_to(to)
+      {
+        return Err(EvalError::InvalidCast {
+          node: *id,
+          from: from.clone(),
+          to: to.clone(),
+        });
+      }
+    }
+    Ok(())
+  }
+
+  /// Checks every node reads outputs its atomic sources have. What a Complex node
+  /// gives is only known once its graph is loaded.
+  fn validate_outputs(&self) -> Result<(), EvalError>
+  {
+    for (id, instance) in &self.instances
+    {
+      for (_, source, port) in &instance.inputs
+      {
+        let Some(NodeType::Atomic(atomic)) = self.instances.get(source).map(|x| &x.node_type)
+        else
+        {
+          continue;
+        };
+        let ports = atomic.output_count(
+          self.instances[source].input_count(),
+          self.inputs.len(),
+          &self.records,
+        );
+        if *port >= ports
+        {
+          return Err(EvalError::MissingOutput {
+            reader: *id,
+            node: *source,
+            port: *port,
+            ports,
+          });
+        }
+      }
+    }
+    Ok(())
+  }
+
+  /// Checks every `Expr` node's expression parses and only reads connected ports.
+  fn validate_expressions(&self) -> Result<(), EvalError>
+  {
+    for instance in self.instances.values()
+    {
+      let mut types = vec![&instance.node_type];
+      while let Some(node_type) = types.pop()
+      {
+        match node_type
+        {
+          NodeType::Atomic(AtomicType::Control(
+            ControlFlow::Try(inner) | ControlFlow::Timeout(inner, _),
+          )) => types.push(inner),
+          NodeType::Atomic(AtomicType::Expr(source)) =>
+          {
+            let ports = Expr::parse(source)?.port_count();
+            if ports > instance.input_count()
+            {
+              return Err(EvalError::InvalidExpression(format!(
+                "{source:?}: reads {ports} inputs but {} are connected",
+                instance.input_count()
+              )));
+            }
+          }
+          _ => (),
+        }
+      }
+    }
+    Ok(())
+  }
+
+  /// Checks the build includes everything the graph's nodes need.
+  fn validate_capabilities(&self) -> Result<(), EvalError>
+  {
+    for (id, instance) in &self.instances
+    {
+      let mut types = vec![&instance.node_type];
+      while let Some(node_type) = types.pop()
+      {
+        match node_type
+        {
+          NodeType::Atomic(AtomicType::Control(
+            ControlFlow::Try(inner) | ControlFlow::Timeout(inner, _),
+          )) => types.push(inner),
+          NodeType::Atomic(atomic) =>
+          {
+            if let Some(capability) = atomic.capability().filter(|x| !x.compiled_in())
+            {
+              return Err(EvalError::NotCompiledIn {
+                node: *id,
+                capability,
+              });
+            }
+          }
+          NodeType::Complex(_) => (),
+        }
+      }
+    }
+    Ok(())
+  }
+
+  /// Checks the `Enum` or `Record` named in `data_type`, or in the types it is made of,
+  /// is declared
+  fn declared(&self, data_type: &DataType) -> Result<(), EvalError>
+  {
+    match data_type
+    {
+      DataType::Enum(name) if !self.enums.contains_key(name) =>
+      {
+        Err(EvalError::UnknownEnum(name.clone()))
+      }
+      DataType::Record(name) => records::fields(&self.records, name).map(|_| ()),
+      DataType::Map(inner) | DataType::Optional(inner) => self.declared(inner),
+      DataType::Result(ok, err) => self.declared(ok).and_then(|()| self.declared(err)),
+      _ => Ok(()),
+    }
+  }
+
+  /// Checks every `Enum` and `Record` type used in the graph, record fields included,
+  /// is declared, and that `Value` nodes read as an enum hold one of its variants.
+  fn validate_enums(&self) -> Result<(), EvalError>
+  {
+    let declared = |data_type: &DataType| self.declared(data_type);
+    for data_type in self
+      .inputs
+      .iter()
+      .chain(&self.outputs)
+      .chain(self.records.values().flat_map(|x| x.values()))
+    {
+      declared(data_type)?;
+    }
+    for instance in self.instances.values()
+    {
+      let mut types = vec![&instance.node_type];
+      while let Some(node_type) = types.pop()
+      {
+        match node_type
+        {
+          NodeType::Atomic(AtomicType::Cast(to_type)) => declared(to_type)?,
+          NodeType::Atomic(AtomicType::Record(
+            RecordOp::Construct(name) | RecordOp::Destructure(name),
+          )) => declared(&DataType::Record(name.clone()))?,
+          NodeType::Atomic(AtomicType::Control(
+            ControlFlow::Try(inner) | ControlFlow::Timeout(inner, _),
+          )) => types.push(inner),
+          _ => (),
+        }
+      }
+      for (data_type, upstream, _) in &instance.inputs
+      {
+        declared(data_type)?;
+        let DataType::Enum(name) = data_type
+        else
+        {
+          continue;
+        };
+        if let Some(NodeType::Atomic(AtomicType::Value(value))) =
+          self.instances.get(upstream).map(|x| &x.node_type)
+        {
+          variant_of(name, &self.enums[name], value)?;
+        }
+      }
+    }
+    Ok(())
+  }
+}
+
+/// `value` if it is one of the variants of the enum `name`, for casting to it.
+pub fn variant_of(
+  name: &str,
+  variants: &[String],
+  value: &DataValue,
+) -> Result<DataValue, EvalError>
+{
+  match value
+  {
+    DataValue::String(x) if variants.contains(x) => Ok(value.clone()),
+    DataValue::String(x) =>
+    {
+      Err(EvalError::InvalidVariant {
+        name: name.to_string(),
+        variant: x.clone(),
+      })
+    }
+    _ =>
+    {
+      Err(EvalError::CastError((
+        value.get_type(),
+        DataType::Enum(name.to_string()),
+      )))
+    }
+  }
 }
 
 impl EvaluateIt for NodeType
@@ -161,28 +1251,15 @@ impl EvaluateIt for NodeType
         else
         {
           // println!("In complex eval");
-          let rel = format!("{}{}{}", eval.my_path, std::path::MAIN_SEPARATOR, path);
+          let rel = eval.resolve_complex(path).await;
 
-          let opt_e = eval.get_evaluator(&rel).await;
-          if let Some(e) = opt_e
-          {
-            let i = e.instantiate(inputs).await;
-            eval.add_complex_runner(i.clone(), &node.id).await;
-            i.get_outputs().await
-          }
-          else
-          {
-            let e = Evaluator::new(
-              rel.clone(),
-              Some(eval.clone()),
-              eval.text_logger.clone(),
-              eval.node_logger.clone(),
-            )?;
-            eval.clone().add_evaluator(&rel, e.clone()).await;
-            let i = e.instantiate(inputs).await;
-            eval.add_complex_runner(i.clone(), &node.id).await;
-            i.get_outputs().await
-          }
+          let e = eval.clone().load_complex(&rel).await?;
+          let state = eval
+            .take_restored_runner(&node.static_id)
+            .unwrap_or_default();
+          let i = e.start_nested(&eval, node.static_id, inputs, state).await?;
+          eval.add_complex_runner(i.clone(), &node.id).await;
+          i.get_outputs().await
         }
       }
     }
@@ -191,7 +1268,7 @@ impl EvaluateIt for NodeType
 
 impl NodeType
 {
-  async fn eval_atomic<'a, Tl, Nl>(
+  async fn eval_atomic<Tl, Nl>(
     atomic_type: AtomicType,
     eval: Arc<Evaluator<Tl, Nl>>,
     node: &ExecutionNode,
@@ -201,6 +1278,14 @@ impl NodeType
     Tl: Logger + Send + Sync + 'static,
     Nl: Logger + Send + Sync + 'static,
   {
+    let config = eval.config().await;
+    if let Some(permission) = atomic_type.permission(config.dry_run.enabled)
+    {
+      if !config.sandbox.allows(permission)
+      {
+        return Err(EvalError::NotPermitted(permission));
+      }
+    }
     match atomic_type
     {
       AtomicType::Print =>
@@ -233,7 +1318,7 @@ impl NodeType
         if let (DataValue::String(pattern), DataValue::String(replace), DataValue::String(input)) =
           (&inputs[0], &inputs[1], &inputs[2])
         {
-          let regex = regex::Regex::new(&pattern).map_err(EvalError::from)?;
+          let regex = regex::Regex::new(pattern).map_err(EvalError::from)?;
           let ret = regex.replace(input, replace).to_string();
           Ok(vec![DataValue::String(ret)])
         }
@@ -247,15 +1332,28 @@ impl NodeType
       }
       AtomicType::Io(io) => Self::eval_io(io, node, eval, inputs).await,
       AtomicType::Variable(action, name) => Self::eval_variable(eval, inputs, &name, action).await,
+      AtomicType::Cast(DataType::Enum(name)) =>
+      {
+        let value = inputs.first().ok_or(EvalError::IncorrectInputCount)?;
+        let variants = eval
+          .enum_variants(&name)
+          .ok_or_else(|| EvalError::UnknownEnum(name.clone()))?;
+        variant_of(&name, variants, value).map(|x| vec![x])
+      }
+      AtomicType::Cast(DataType::Record(name)) =>
+      {
+        let value = inputs.first().ok_or(EvalError::IncorrectInputCount)?;
+        records::record_of(eval.records(), &name, value).map(|x| vec![x])
+      }
       AtomicType::Cast(to_type) =>
       {
         inputs
-          .get(0)
+          .first()
           .ok_or(EvalError::IncorrectInputCount)?
           .clone()
           .try_cast(to_type)
           .map(|x| vec![x])
-          .map_err(|t| EvalError::CastError(t))
+          .map_err(EvalError::CastError)
       }
       AtomicType::UnaryOp(unop) =>
       {
@@ -277,10 +1375,142 @@ impl NodeType
         Ok(vec![DataValue::Boolean(inputs[0].is_none())])
       }
       AtomicType::AgentOp(op) => Self::eval_agent(op, inputs, node, eval).await,
+      AtomicType::Select =>
+      {
+        if inputs.len() != 3
+        {
+          return Err(EvalError::IncorrectInputCount);
+        }
+        let condition = inputs[0]
+          .try_cast(DataType::Boolean)
+          .map_err(EvalError::CastError)?;
+        let mut inputs = inputs;
+        if condition == DataValue::Boolean(true)
+        {
+          Ok(vec![inputs.swap_remove(1)])
+        }
+        else
+        {
+          Ok(vec![inputs.swap_remove(2)])
+        }
+      }
+      AtomicType::Counter =>
+      {
+        let count = match (Self::reset_requested(&inputs)?, node.get_stored().await)
+        {
+          (false, Some(DataValue::Integer(count))) => count + 1,
+          _ => 1,
+        };
+        node.set_stored(DataValue::Integer(count)).await;
+        Ok(vec![DataValue::Integer(count)])
+      }
+      AtomicType::Stopwatch =>
+      {
+        let now = std::time::SystemTime::now()
+          .duration_since(std::time::UNIX_EPOCH)
+          .unwrap_or_default()
+          .as_millis() as i64;
+        match (Self::reset_requested(&inputs)?, node.get_stored().await)
+        {
+          (false, Some(DataValue::Integer(start))) =>
+          {
+            // the wall clock can be stepped back, never report a negative time
+            Ok(vec![DataValue::Integer((now - start).max(0))])
+          }
+          _ =>
+          {
+            node.set_stored(DataValue::Integer(now)).await;
+            Ok(vec![DataValue::Integer(0)])
+          }
+        }
+      }
+      AtomicType::Abort =>
+      {
+        let (message, code) = match inputs.as_slice()
+        {
+          [DataValue::String(message)] => (message.clone(), 1),
+          [DataValue::String(message), DataValue::Integer(code)] => (message.clone(), *code as i32),
+          [_] | [_, _] =>
+          {
+            return Err(EvalError::IncorrectTyping {
+              got: inputs.into_iter().map(|x| x.get_type()).collect(),
+              expected: vec![DataType::String, DataType::Integer],
+            })
+          }
+          _ => return Err(EvalError::IncorrectInputCount),
+        };
+        eval.abort(message.clone(), code);
+        Err(EvalError::Aborted { message, code })
+      }
+      AtomicType::RuntimeInfo => Ok(vec![eval.runtime_info().await]),
+      AtomicType::Env(name) =>
+      {
+        Ok(vec![
+          std::env::var(name).map_or(DataValue::None, DataValue::String)
+        ])
+      }
+      AtomicType::Secret(name) =>
+      {
+        let config = eval.config().await;
+        let lookup = name.clone();
+        // the keychain runs a tool to ask
+        let found =
+          tokio::task::spawn_blocking(move || secrets::store(&config.secrets)?.get(&lookup))
+            .await
+            .map_err(|e| EvalError::SecretsUnavailable(e.to_string()))?
+            .map_err(EvalError::SecretsUnavailable)?;
+        found
+          .map(|x| vec![DataValue::String(x)])
+          .ok_or(EvalError::SecretNotFound(name))
+      }
+      AtomicType::TempDir =>
+      {
+        let path = eval.workspace().await?;
+        Ok(vec![DataValue::String(path.to_string_lossy().into_owned())])
+      }
+      AtomicType::Array(op) => Self::eval_array(op, inputs),
+      AtomicType::Bytes(op) => Self::eval_bytes(op, inputs),
+      AtomicType::DateTime(op) => Self::eval_date_time(op, inputs),
+      AtomicType::Id(op) => Self::eval_id(op, inputs),
+      AtomicType::Object(op) => Self::eval_object(op, inputs),
+      AtomicType::Random(op) =>
+      {
+        eval.with_rng(node.static_id, |rng| Self::eval_random(op, rng, inputs))
+      }
+      AtomicType::Wrap(op) => Self::eval_wrap(op, inputs),
+      AtomicType::Record(op) => Self::eval_record(op, eval.records(), inputs),
+      AtomicType::JsonParse => Self::json_parse(inputs),
+      AtomicType::JsonStringify { pretty } => Self::json_stringify(inputs, pretty),
+      AtomicType::RegexMatch => Self::regex_match(inputs),
+      AtomicType::RegexFind => Self::regex_find(inputs),
+      AtomicType::RegexCaptures => Self::regex_captures(inputs),
+      AtomicType::Expr(source) => Self::eval_expr(&source, inputs),
+      AtomicType::Fs(op) => Self::eval_fs(op, &config.sandbox, inputs).await,
+      AtomicType::Http(op) => Self::eval_http(op, eval, inputs).await,
+      AtomicType::Process(op) => Self::eval_process(op, eval, inputs).await,
+    }
+  }
+
+  /// Reads the optional reset input of `Counter` and `Stopwatch`
+  fn reset_requested(inputs: &[DataValue]) -> Result<bool, EvalError>
+  {
+    match inputs
+    {
+      [] => Ok(false),
+      [reset] =>
+      {
+        Ok(
+          reset
+            .try_cast(DataType::Boolean)
+            .map_err(EvalError::CastError)?
+            == DataValue::Boolean(true),
+        )
+      }
+      _ => Err(EvalError::IncorrectInputCount),
     }
   }
 
-  fn eval_bin_op(
+  pub(super) fn eval_bin_op(
     atomic_bin_op: AtomicBinOp,
     inputs: Vec<DataValue>,
   ) -> Result<Vec<DataValue>, EvalError>
@@ -297,7 +1527,7 @@ impl NodeType
     }
   }
 
-  async fn eval_control<'a, Tl, Nl>(
+  async fn eval_control<Tl, Nl>(
     control_flow: ControlFlow,
     eval: Arc<Evaluator<Tl, Nl>>,
     node: &ExecutionNode,
@@ -313,13 +1543,12 @@ impl NodeType
       ControlFlow::End =>
       {
         tokio::task::yield_now().await;
-        eval.complete.notify_one();
         Ok(inputs)
       }
       ControlFlow::Loop(lp_type) => Self::eval_loop(eval, lp_type).await,
       ControlFlow::If =>
       {
-        if Some(DataValue::Boolean(true)) == inputs.get(0).cloned()
+        if Some(DataValue::Boolean(true)) == inputs.first().cloned()
         {
           // trigger true port
           node.trigger_connected(eval, 1).await?;
@@ -329,12 +1558,69 @@ impl NodeType
           // trigger false port
           node.trigger_connected(eval, 0).await?;
         }
-        Ok(vec![DataValue::None])
+        Ok(vec![DataValue::None])
+      }
+      ControlFlow::Try(inner) =>
+      {
+        let result = Box::pin(inner.evaluate(eval.clone(), node, inputs)).await;
+        Self::caught(eval, node, &inner, result).await
+      }
+      ControlFlow::Parallel => Ok(vec![DataValue::Array(inputs)]),
+      ControlFlow::Timeout(inner, millis) =>
+      {
+        let limit = Some(Duration::from_millis(millis));
+        match with_timeout(limit, Box::pin(inner.evaluate(eval.clone(), node, inputs))).await
+        {
+          Err(EvalError::TimedOut) =>
+          {
+            Self::caught(eval, node, &inner, Err(EvalError::TimedOut)).await
+          }
+          result => Self::caught(eval, node, &inner, Ok(result?)).await,
+        }
+      }
+    }
+  }
+
+  /// The outputs of a node catching `inner`'s failures: its value and None, or None and
+  /// the error. Aborting the graph is never caught.
+  async fn caught<Tl, Nl>(
+    eval: Arc<Evaluator<Tl, Nl>>,
+    node: &ExecutionNode,
+    inner: &NodeType,
+    result: Result<Vec<DataValue>, EvalError>,
+  ) -> Result<Vec<DataValue>, EvalError>
+  where
+    Tl: Logger + Send + Sync + 'static,
+    Nl: Logger + Send + Sync + 'static,
+  {
+    match result
+    {
+      Ok(mut values) =>
+      {
+        let value = if values.len() == 1
+        {
+          values.remove(0)
+        }
+        else
+        {
+          DataValue::Array(values)
+        };
+        Ok(vec![value, DataValue::None])
+      }
+      Err(e @ EvalError::Aborted { .. }) => Err(e),
+      Err(e) =>
+      {
+        // a failed or abandoned runner is not reused
+        if matches!(inner, NodeType::Complex(_))
+        {
+          eval.drop_complex_runner(&node.id).await;
+        }
+        Ok(vec![DataValue::None, e.to_value()])
       }
     }
   }
 
-  async fn eval_loop<'a, Tl, Nl>(
+  async fn eval_loop<Tl, Nl>(
     eval: Arc<Evaluator<Tl, Nl>>,
     lp_type: LoopNodes,
   ) -> Result<Vec<DataValue>, EvalError>
@@ -356,7 +1642,7 @@ impl NodeType
     }
   }
 
-  async fn eval_variable<'a, Tl, Nl>(
+  async fn eval_variable<Tl, Nl>(
     eval: Arc<Evaluator<Tl, Nl>>,
     inputs: Vec<DataValue>,
     name: &str,
@@ -372,7 +1658,9 @@ impl NodeType
       {
         // println!("{:?}: {} = {}", action, name, inputs[0].clone());
 
-        eval.set_variable(name.to_string(), inputs[0].clone()).await;
+        eval
+          .set_variable(name.to_string(), inputs[0].clone())
+          .await?;
 
         Ok(vec![])
       }
@@ -385,7 +1673,7 @@ impl NodeType
       }
     }
   }
-  async fn eval_io<'a, Tl, Nl>(
+  async fn eval_io<Tl, Nl>(
     io: AtomicIo,
     node: &ExecutionNode,
     eval: Arc<Evaluator<Tl, Nl>>,
@@ -405,25 +1693,65 @@ impl NodeType
           Some(x) => Ok(vec![x]),
           None =>
           {
+            let config = eval.config().await;
             let handle = match io_type
             {
-              IoType::File =>
+              IoType::File(mode) =>
               {
                 let path = format!("{}", inputs[0]);
+                config.sandbox.check_path(&path)?;
+                eval
+                  .register_io(Box::pin(mode.options().open(path).await?))
+                  .await
+              }
+              IoType::TcpSocket if eval.config().await.dry_run.enabled =>
+              {
+                // reads come from the configured file, writes go nowhere
+                let addr = format!("{}:{}", inputs[0], inputs[1]);
+                let source: Box<dyn tokio::io::AsyncRead + Send + Sync + Unpin> =
+                  match eval.config().await.dry_run.streams.get(&addr)
+                  {
+                    Some(path) => Box::new(tokio::fs::File::open(path).await?),
+                    None => Box::new(tokio::io::empty()),
+                  };
                 eval
-                  .register_io(Box::pin(tokio::fs::File::open(path).await?))
+                  .register_io(Box::pin(tokio::io::join(source, tokio::io::sink())))
                   .await
               }
               IoType::TcpSocket =>
               {
+                let addr = format!("{}:{}", inputs[0], inputs[1]);
+                config.sandbox.check_addr(&addr)?;
+                let timeout = config.timeouts.connect();
+                let stream = with_timeout(timeout, tokio::net::TcpStream::connect(addr)).await?;
+                eval.register_io(Box::pin(stream)).await
+              }
+              IoType::UdpSocket =>
+              {
+                let addr = format!("{}:{}", inputs[0], inputs[1]);
+                config.sandbox.check_addr(&addr)?;
+                eval
+                  .register_udp(tokio::net::UdpSocket::bind(addr).await?)
+                  .await
+              }
+              IoType::WebSocket =>
+              {
+                let url = format!("{}", inputs[0]);
+                config.sandbox.check_url(&url)?;
+                let timeout = config.timeouts.connect();
+                let socket = with_timeout(timeout, WebSocket::connect(&url)).await?;
+                eval.register_websocket(socket).await
+              }
+              IoType::TcpListener =>
+              {
+                let addr = format!("{}:{}", inputs[0], inputs[1]);
+                config.sandbox.check_addr(&addr)?;
                 eval
-                  .register_io(Box::pin(
-                    tokio::net::TcpStream::connect(format!("{}:{}", inputs[0], inputs[1])).await?,
-                  ))
+                  .register_listener(tokio::net::TcpListener::bind(addr).await?)
                   .await
               }
             };
-            node.set_stored(DataValue::Handle(handle.clone())).await;
+            node.set_stored(DataValue::Handle(handle)).await;
             Ok(vec![DataValue::Handle(handle)])
           }
         }
@@ -432,9 +1760,14 @@ impl NodeType
       {
         if let DataValue::Handle(handle) = inputs[0]
         {
-          let bytes = eval.read_until(&handle, b"\n").await?;
-          let s = String::from_utf8(bytes)?.trim_end_matches('\r').to_string();
-          Ok(vec![DataValue::String(s)])
+          let mut bytes = eval.clone().read_until(&handle, b"\n").await?;
+          eval.record_io(node.static_id, bytes.len(), 0).await?;
+          // lines end in a plain "\n" whichever ending the source used
+          if bytes.ends_with(b"\r\n")
+          {
+            bytes.remove(bytes.len() - 2);
+          }
+          Ok(vec![DataValue::String(String::from_utf8(bytes)?)])
         }
         else
         {
@@ -448,13 +1781,11 @@ impl NodeType
       {
         if let (DataValue::Handle(h), DataValue::Integer(size)) = (&inputs[0], &inputs[1])
         {
-          let mut buf = Vec::new();
-          buf.resize(*size as usize, 0);
-          let count = eval.read_bytes(h, &mut buf).await?;
-          buf.resize(count, 0);
-          Ok(vec![DataValue::Array(
-            buf.into_iter().map(|x| DataValue::Byte(x)).collect(),
-          )])
+          let mut buf = vec![0; *size as usize];
+          let count = eval.clone().read_bytes(h, &mut buf).await?;
+          eval.record_io(node.static_id, count, 0).await?;
+          buf.truncate(count);
+          Ok(vec![DataValue::Bytes(buf)])
         }
         else
         {
@@ -466,10 +1797,118 @@ impl NodeType
       }
       AtomicIo::Write =>
       {
-        if let (DataValue::String(s), DataValue::Handle(h)) = (&inputs[1], &inputs[0])
+        let bytes = match &inputs[1]
+        {
+          DataValue::String(s) => Some(s.as_bytes()),
+          DataValue::Bytes(b) => Some(b.as_slice()),
+          _ => None,
+        };
+        if let (Some(bytes), DataValue::Handle(h)) = (bytes, &inputs[0])
+        {
+          eval.clone().write_bytes(h, bytes).await?;
+          eval.record_io(node.static_id, 0, bytes.len()).await?;
+          Ok(vec![DataValue::None])
+        }
+        else
+        {
+          Err(EvalError::IncorrectTyping {
+            got: inputs.into_iter().map(|x| x.get_type()).collect(),
+            expected: vec![DataType::Handle, DataType::String],
+          })
+        }
+      }
+      AtomicIo::Accept =>
+      {
+        if let DataValue::Handle(listener) = inputs[0]
+        {
+          let (handle, addr) = eval.accept(&listener).await?;
+          Ok(vec![
+            DataValue::Handle(handle),
+            DataValue::String(addr.to_string()),
+          ])
+        }
+        else
+        {
+          Err(EvalError::IncorrectTyping {
+            got: vec![inputs[0].get_type()],
+            expected: vec![DataType::Handle],
+          })
+        }
+      }
+      AtomicIo::SendTo =>
+      {
+        let bytes = match &inputs[1]
+        {
+          DataValue::String(s) => Some(s.as_bytes()),
+          DataValue::Bytes(b) => Some(b.as_slice()),
+          _ => None,
+        };
+        if let (DataValue::Handle(h), Some(bytes), DataValue::String(addr)) =
+          (&inputs[0], bytes, &inputs[2])
+        {
+          let count = eval.clone().send_to(h, bytes, addr).await?;
+          eval.record_io(node.static_id, 0, count).await?;
+          Ok(vec![DataValue::Integer(count as i64)])
+        }
+        else
+        {
+          Err(EvalError::IncorrectTyping {
+            got: inputs.into_iter().map(|x| x.get_type()).collect(),
+            expected: vec![DataType::Handle, DataType::String, DataType::String],
+          })
+        }
+      }
+      AtomicIo::RecvFrom =>
+      {
+        if let (DataValue::Handle(h), DataValue::Integer(size)) = (&inputs[0], &inputs[1])
+        {
+          let (buf, addr) = eval.clone().recv_from(h, *size as usize).await?;
+          eval.record_io(node.static_id, buf.len(), 0).await?;
+          Ok(vec![
+            DataValue::String(String::from_utf8(buf)?),
+            DataValue::String(addr.to_string()),
+          ])
+        }
+        else
+        {
+          Err(EvalError::IncorrectTyping {
+            got: inputs.into_iter().map(|x| x.get_type()).collect(),
+            expected: vec![DataType::Handle, DataType::Integer],
+          })
+        }
+      }
+      AtomicIo::SendFrame =>
+      {
+        let frame = match &inputs[1]
         {
-          let mut bytes = s.bytes().collect();
-          eval.write_bytes(h, &mut bytes).await?;
+          DataValue::String(s) => Some(Frame::Text(s.clone())),
+          DataValue::Bytes(b) => Some(Frame::Binary(b.clone())),
+          DataValue::Array(items) =>
+          {
+            items
+              .iter()
+              .map(|x| {
+                match x
+                {
+                  DataValue::Byte(b) => Some(*b),
+                  DataValue::Integer(i) => u8::try_from(*i).ok(),
+                  _ => None,
+                }
+              })
+              .collect::<Option<Vec<u8>>>()
+              .map(Frame::Binary)
+          }
+          _ => None,
+        };
+        if let (DataValue::Handle(h), Some(frame)) = (&inputs[0], frame)
+        {
+          let size = match &frame
+          {
+            Frame::Text(s) => s.len(),
+            Frame::Binary(b) => b.len(),
+          };
+          eval.clone().send_frame(h, frame).await?;
+          eval.record_io(node.static_id, 0, size).await?;
           Ok(vec![DataValue::None])
         }
         else
@@ -480,38 +1919,227 @@ impl NodeType
           })
         }
       }
+      AtomicIo::RecvFrame =>
+      {
+        if let DataValue::Handle(h) = &inputs[0]
+        {
+          let (size, value) = match eval.clone().recv_frame(h).await?
+          {
+            Some(Frame::Text(s)) => (s.len(), DataValue::String(s)),
+            Some(Frame::Binary(b)) => (b.len(), DataValue::Bytes(b)),
+            None => (0, DataValue::None),
+          };
+          eval.record_io(node.static_id, size, 0).await?;
+          Ok(vec![value])
+        }
+        else
+        {
+          Err(EvalError::IncorrectTyping {
+            got: vec![inputs[0].get_type()],
+            expected: vec![DataType::Handle],
+          })
+        }
+      }
+      AtomicIo::Close | AtomicIo::Flush =>
+      {
+        if let [DataValue::Handle(handle)] = inputs.as_slice()
+        {
+          if io == AtomicIo::Close
+          {
+            eval.close_io(handle).await?;
+          }
+          else
+          {
+            eval.flush_io(handle).await?;
+          }
+          Ok(vec![DataValue::None])
+        }
+        else
+        {
+          Err(EvalError::IncorrectTyping {
+            got: inputs.into_iter().map(|x| x.get_type()).collect(),
+            expected: vec![DataType::Handle],
+          })
+        }
+      }
       AtomicIo::ConsoleInput =>
       {
         let mut buf = String::new();
         BufReader::new(tokio::io::stdin())
           .read_line(&mut buf)
           .await
-          .map_err(|x| EvalError::IoError(x))?;
+          .map_err(EvalError::IoError)?;
         Ok(vec![DataValue::String(buf)])
       }
     }
   }
 
-  async fn eval_unary(
-    atomic_unary_op: AtomicUnaryOp,
+  async fn eval_http<Tl, Nl>(
+    op: HttpOp,
+    eval: Arc<Evaluator<Tl, Nl>>,
+    inputs: Vec<DataValue>,
+  ) -> Result<Vec<DataValue>, EvalError>
+  where
+    Tl: Logger + Send + Sync + 'static,
+    Nl: Logger + Send + Sync + 'static,
+  {
+    match op
+    {
+      HttpOp::Route(complex) =>
+      {
+        if let [DataValue::String(addr), DataValue::String(method), DataValue::String(path)] =
+          inputs.as_slice()
+        {
+          eval.config().await.sandbox.check_addr(addr)?;
+          let bound = eval.register_route(addr, method, path, &complex).await?;
+          Ok(vec![DataValue::String(bound)])
+        }
+        else
+        {
+          Err(EvalError::IncorrectTyping {
+            got: inputs.into_iter().map(|x| x.get_type()).collect(),
+            expected: vec![DataType::String, DataType::String, DataType::String],
+          })
+        }
+      }
+      HttpOp::Request =>
+      {
+        if inputs.len() != 4
+        {
+          return Err(EvalError::IncorrectInputCount);
+        }
+        let headers = match &inputs[2]
+        {
+          DataValue::Object(x) => Some(x.iter().map(|(k, v)| (k.clone(), v.to_string())).collect()),
+          DataValue::None => Some(HashMap::new()),
+          _ => None,
+        };
+        let body = match &inputs[3]
+        {
+          DataValue::String(x) => Some(Some(x.clone())),
+          DataValue::None => Some(None),
+          _ => None,
+        };
+        if let (DataValue::String(method), DataValue::String(url), Some(headers), Some(body)) =
+          (&inputs[0], &inputs[1], headers, body)
+        {
+          let config = eval.config().await;
+          config.sandbox.check_url(url)?;
+          let timeout = config.timeouts.http_request();
+          let response = with_timeout(
+            timeout,
+            http::send_request(&config.sandbox, method, url, &headers, body),
+          )
+          .await?;
+          let mut headers: IndexMap<_, _> = response
+            .headers
+            .into_iter()
+            .map(|(k, v)| (k, DataValue::String(v)))
+            .collect();
+          headers.sort_keys();
+          Ok(vec![
+            DataValue::Integer(response.status as i64),
+            DataValue::Object(headers),
+            DataValue::String(response.body),
+          ])
+        }
+        else
+        {
+          Err(EvalError::IncorrectTyping {
+            got: inputs.into_iter().map(|x| x.get_type()).collect(),
+            expected: vec![
+              DataType::String,
+              DataType::String,
+              DataType::Object,
+              DataType::String,
+            ],
+          })
+        }
+      }
+    }
+  }
+
+  async fn eval_process<Tl, Nl>(
+    op: ProcessOp,
+    eval: Arc<Evaluator<Tl, Nl>>,
     inputs: Vec<DataValue>,
   ) -> Result<Vec<DataValue>, EvalError>
+  where
+    Tl: Logger + Send + Sync + 'static,
+    Nl: Logger + Send + Sync + 'static,
   {
-    match atomic_unary_op
+    match op
     {
-      AtomicUnaryOp::Neg =>
+      ProcessOp::Spawn =>
+      {
+        if inputs.len() != 3
+        {
+          return Err(EvalError::IncorrectInputCount);
+        }
+        let args = match &inputs[1]
+        {
+          DataValue::Array(x) => Some(x.iter().map(|x| x.to_string()).collect()),
+          DataValue::None => Some(vec![]),
+          _ => None,
+        };
+        let env = match &inputs[2]
+        {
+          DataValue::Object(x) => Some(x.iter().map(|(k, v)| (k.clone(), v.to_string())).collect()),
+          DataValue::None => Some(HashMap::new()),
+          _ => None,
+        };
+        if let (DataValue::String(program), Some(args), Some(env)) = (&inputs[0], args, env)
+        {
+          let (process, stdin, stdout) = eval.spawn_process(program, &args, &env).await?;
+          Ok(vec![
+            DataValue::Handle(process),
+            DataValue::Handle(stdin),
+            DataValue::Handle(stdout),
+          ])
+        }
+        else
+        {
+          Err(EvalError::IncorrectTyping {
+            got: inputs.into_iter().map(|x| x.get_type()).collect(),
+            expected: vec![DataType::String, DataType::Array, DataType::Object],
+          })
+        }
+      }
+      ProcessOp::Wait =>
       {
-        let mut outputs = Vec::with_capacity(inputs.len());
-        for x in inputs.into_iter().map(|x| x.mul(DataValue::Integer(-1)))
+        if let Some(DataValue::Handle(process)) = inputs.first()
+        {
+          Ok(vec![eval
+            .wait_process(process)
+            .await?
+            .map(|x| DataValue::Integer(x as i64))
+            .unwrap_or(DataValue::None)])
+        }
+        else
         {
-          outputs.push(x?);
+          Err(EvalError::IncorrectTyping {
+            got: inputs.into_iter().map(|x| x.get_type()).collect(),
+            expected: vec![DataType::Handle],
+          })
         }
-        Ok(outputs)
       }
     }
   }
 
-  async fn eval_agent<'a, Tl, Nl>(
+  async fn eval_unary(
+    atomic_unary_op: AtomicUnaryOp,
+    inputs: Vec<DataValue>,
+  ) -> Result<Vec<DataValue>, EvalError>
+  {
+    let mut outputs = Vec::with_capacity(inputs.len());
+    for x in inputs
+    {
+      outputs.push(atomic_unary_op.apply(x)?);
+    }
+    Ok(outputs)
+  }
+
+  async fn eval_agent<Tl, Nl>(
     agent_op: AgentOperation,
     inputs: Vec<DataValue>,
     node: &ExecutionNode,
@@ -530,45 +2158,78 @@ impl NodeType
           return Ok(vec![agent]);
         }
 
+        eval.config().await.sandbox.check_agent(&agent_type)?;
         if let Some(args) = AgentArgs::from_values(&inputs)
         {
-          let ret = DataValue::Agent(
-            agent_type.clone(),
-            eval.register_agent(agent_type, args).await,
-          );
+          let id = eval
+            .register_agent(node.static_id, agent_type.clone(), args)
+            .await;
+          eval.clone().restore_agent(&node.static_id, &id).await?;
+          let ret = DataValue::Agent(agent_type, id);
           node.set_stored(ret.clone()).await;
           Ok(vec![ret])
         }
         else
         {
-          todo!()
+          Err(EvalError::IncorrectTyping {
+            got: inputs.into_iter().map(|x| x.get_type()).collect(),
+            expected: vec![
+              DataType::String,
+              DataType::Array,
+              DataType::Float,
+              DataType::String,
+              DataType::Float,
+              DataType::Integer,
+              DataType::Array,
+            ],
+          })
         }
       }
       AgentOperation::Send =>
       {
-        let args = (inputs.get(0).cloned(), inputs.get(1).cloned());
-        if let (Some(DataValue::Agent(_, id)), Some(DataValue::String(message))) = args
-        {
-          eval.agent_send_message(&id, message).await?;
-          Ok(vec![DataValue::None])
-        }
-        else
+        let args = (
+          inputs.first().cloned(),
+          inputs.get(1).cloned(),
+          inputs.get(2).cloned().unwrap_or(DataValue::None),
+        );
+        match args
         {
-          Err(EvalError::IncorrectTyping {
-            got: inputs.into_iter().map(|x| x.get_type()).collect(),
-            expected: vec![DataType::Agent(AgentType::OpenAi), DataType::String],
-          })
+          (
+            Some(DataValue::Agent(_, id)),
+            Some(DataValue::String(message)),
+            priority @ (DataValue::Integer(_) | DataValue::None),
+          ) =>
+          {
+            let priority = match priority
+            {
+              DataValue::Integer(x) => x,
+              _ => 0,
+            };
+            eval.agent_send_message(&id, message, priority).await?;
+            Ok(vec![DataValue::None])
+          }
+          _ =>
+          {
+            Err(EvalError::IncorrectTyping {
+              got: inputs.into_iter().map(|x| x.get_type()).collect(),
+              expected: vec![
+                DataType::Agent(AgentType::OpenAi),
+                DataType::String,
+                DataType::Integer,
+              ],
+            })
+          }
         }
       }
       AgentOperation::Recieve =>
       {
-        if let Some(DataValue::Agent(_, id)) = inputs.get(0)
+        if let Some(DataValue::Agent(_, id)) = inputs.first()
         {
           Ok(vec![eval
             .agent_get_last_message(id)
             .await?
             .and_then(|x| x.get_content())
-            .map(|x| DataValue::String(x))
+            .map(DataValue::String)
             .unwrap_or(DataValue::None)])
         }
         else
@@ -576,6 +2237,77 @@ impl NodeType
           todo!()
         }
       }
+      AgentOperation::ExportHistory =>
+      {
+        if let Some(DataValue::Agent(_, id)) = inputs.first()
+        {
+          Ok(vec![eval.agent_export_history(id).await?])
+        }
+        else
+        {
+          Err(EvalError::IncorrectTyping {
+            got: inputs.into_iter().map(|x| x.get_type()).collect(),
+            expected: vec![DataType::Agent(AgentType::OpenAi)],
+          })
+        }
+      }
+      AgentOperation::ExportTranscript(format) =>
+      {
+        match (inputs.first(), inputs.get(1).unwrap_or(&DataValue::None))
+        {
+          (Some(DataValue::Agent(_, id)), path @ (DataValue::String(_) | DataValue::None)) =>
+          {
+            if let DataValue::String(path) = path
+            {
+              eval.config().await.sandbox.check_path(path)?;
+            }
+            let transcript = eval.agent_transcript(id, &format).await?;
+            if let DataValue::String(path) = path
+            {
+              tokio::fs::write(path, &transcript).await?;
+            }
+            Ok(vec![DataValue::String(transcript)])
+          }
+          _ =>
+          {
+            Err(EvalError::IncorrectTyping {
+              got: inputs.into_iter().map(|x| x.get_type()).collect(),
+              expected: vec![DataType::Agent(AgentType::OpenAi), DataType::String],
+            })
+          }
+        }
+      }
+      AgentOperation::AttachTools(tools) =>
+      {
+        if let Some(agent @ DataValue::Agent(_, id)) = inputs.first()
+        {
+          eval.agent_attach_tools(id, &tools).await?;
+          Ok(vec![agent.clone()])
+        }
+        else
+        {
+          Err(EvalError::IncorrectTyping {
+            got: inputs.into_iter().map(|x| x.get_type()).collect(),
+            expected: vec![DataType::Agent(AgentType::OpenAi)],
+          })
+        }
+      }
+      AgentOperation::ImportHistory =>
+      {
+        if let (Some(agent @ DataValue::Agent(_, id)), Some(history)) =
+          (inputs.first(), inputs.get(1))
+        {
+          eval.agent_import_history(id, history).await?;
+          Ok(vec![agent.clone()])
+        }
+        else
+        {
+          Err(EvalError::IncorrectTyping {
+            got: inputs.into_iter().map(|x| x.get_type()).collect(),
+            expected: vec![DataType::Agent(AgentType::OpenAi), DataType::Object],
+          })
+        }
+      }
     }
   }
 
@@ -584,33 +2316,27 @@ impl NodeType
     inputs: Vec<DataValue>,
   ) -> Result<Vec<DataValue>, EvalError>
   {
-    if logical_op == AtomicLogic::Eq
+    let epsilon = match logical_op
     {
-      if inputs.len() != 2
-      {
-        return Err(EvalError::IncorrectInputCount);
-      }
-      else
-      {
-        return Ok(vec![DataValue::Boolean(inputs[0] == inputs[1])]);
-      }
-    }
-    else if logical_op == AtomicLogic::Neq
+      AtomicLogic::Eq | AtomicLogic::Neq => Some(0.0),
+      AtomicLogic::ApproxEq(epsilon) => Some(epsilon),
+      _ => None,
+    };
+    if let Some(epsilon) = epsilon
     {
       if inputs.len() != 2
       {
         return Err(EvalError::IncorrectInputCount);
       }
-      else
-      {
-        return Ok(vec![DataValue::Boolean(inputs[0] == inputs[1])]);
-      }
+      let equal = inputs[0].equals(&inputs[1], epsilon);
+      return Ok(vec![DataValue::Boolean(
+        equal != (logical_op == AtomicLogic::Neq),
+      )]);
     }
     let mut bools = Vec::with_capacity(inputs.len());
-    for res_bool in inputs.iter().cloned().map(|x| {
-      x.try_cast(DataType::Boolean)
-        .map_err(|e| EvalError::CastError(e))
-    })
+    for res_bool in inputs
+      .iter()
+      .map(|x| x.try_cast(DataType::Boolean).map_err(EvalError::CastError))
     {
       if let DataValue::Boolean(b) = res_bool?
       {
@@ -652,10 +2378,46 @@ impl NodeType
         )])
       }
       AtomicLogic::Not => Ok(bools.into_iter().map(|x| DataValue::Boolean(!x)).collect()),
-      AtomicLogic::Eq | AtomicLogic::Neq =>
+      AtomicLogic::Eq | AtomicLogic::Neq | AtomicLogic::ApproxEq(_) =>
       {
-        todo!()
+        unreachable!()
       }
     }
   }
 }
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  fn id(n: u128) -> Uuid
+  {
+    Uuid::from_u128(n)
+  }
+
+  #[test]
+  fn nodes_reading_their_own_outputs_are_rejected()
+  {
+    let graph: Complex =
+      serde_json::from_str(include_str!("../../tests/graphs/self_listen.json")).unwrap();
+    assert!(matches!(
+      graph.validate(),
+      Err(EvalError::SelfListen(ids)) if ids == [id(2)]
+    ));
+  }
+
+  #[test]
+  fn nodes_reading_each_other_are_rejected()
+  {
+    let graph: Complex =
+      serde_json::from_str(include_str!("../../tests/graphs/mutual_listen.json")).unwrap();
+    let Err(EvalError::SelfListen(mut ids)) = graph.validate()
+    else
+    {
+      panic!("the graph passed validation");
+    };
+    ids.sort();
+    assert_eq!(ids, [id(2), id(3)]);
+  }
+}
@@ -0,0 +1,168 @@
+use super::{
+  nodes::NodeType,
+  typing::{DataType, DataValue},
+};
+use crate::eval::EvalError;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, PartialEq)]
+pub enum ObjectOp
+{
+  /// Outputs the value at each key path, in order, one per port. A path is a list of
+  /// keys separated by `.`, such as `user.address.city`; numeric parts index into
+  /// Arrays, as in `items.0.name`.
+  Destructure(Vec<String>),
+  /// Outputs the value at a key path, as in `choices.0.message.content`
+  GetField(String),
+  /// Takes an Object and a value and outputs the Object with the value at a key path,
+  /// adding Objects for keys missing along the way
+  SetField(String),
+  /// Outputs whether there is a value at a key path
+  HasField(String),
+  /// Outputs the keys of an Object as an Array of Strings, sorted
+  Keys,
+}
+
+impl NodeType
+{
+  pub(super) fn eval_object(
+    op: ObjectOp,
+    inputs: Vec<DataValue>,
+  ) -> Result<Vec<DataValue>, EvalError>
+  {
+    if let ObjectOp::SetField(path) = op
+    {
+      let mut inputs = inputs.into_iter();
+      return match (inputs.next(), inputs.next(), inputs.next())
+      {
+        (Some(mut object @ DataValue::Object(_)), Some(value), None) =>
+        {
+          match object.set_path(&path, value)
+          {
+            true => Ok(vec![object]),
+            false => Err(EvalError::FieldNotFound(path)),
+          }
+        }
+        (Some(object), Some(value), None) =>
+        {
+          Err(EvalError::IncorrectTyping {
+            got: vec![object.get_type(), value.get_type()],
+            expected: vec![DataType::Object, value.get_type()],
+          })
+        }
+        _ => Err(EvalError::IncorrectInputCount),
+      };
+    }
+
+    let (object, fields) = match inputs.as_slice()
+    {
+      [x @ DataValue::Object(fields)] => (x, fields),
+      [_] =>
+      {
+        return Err(EvalError::IncorrectTyping {
+          got: inputs.iter().map(|x| x.get_type()).collect(),
+          expected: vec![DataType::Object],
+        })
+      }
+      _ => return Err(EvalError::IncorrectInputCount),
+    };
+    let field = |path: &String| {
+      object
+        .get_path(path)
+        .cloned()
+        .ok_or_else(|| EvalError::FieldNotFound(path.clone()))
+    };
+    match op
+    {
+      ObjectOp::Destructure(paths) => paths.iter().map(field).collect(),
+      ObjectOp::GetField(path) => Ok(vec![field(&path)?]),
+      ObjectOp::HasField(path) => Ok(vec![DataValue::Boolean(object.get_path(&path).is_some())]),
+      ObjectOp::Keys =>
+      {
+        let mut keys: Vec<&String> = fields.keys().collect();
+        keys.sort();
+        Ok(vec![DataValue::Array(
+          keys
+            .into_iter()
+            .map(|k| DataValue::String(k.clone()))
+            .collect(),
+        )])
+      }
+      ObjectOp::SetField(_) => unreachable!(),
+    }
+  }
+
+  pub(super) fn json_parse(inputs: Vec<DataValue>) -> Result<Vec<DataValue>, EvalError>
+  {
+    let text = match inputs.as_slice()
+    {
+      [DataValue::String(x)] => x,
+      [_] =>
+      {
+        return Err(EvalError::IncorrectTyping {
+          got: inputs.iter().map(|x| x.get_type()).collect(),
+          expected: vec![DataType::String],
+        })
+      }
+      _ => return Err(EvalError::IncorrectInputCount),
+    };
+    let text = strip_code_fence(text.trim());
+    serde_json::from_str(text)
+      .map(|x| vec![x])
+      .map_err(|e| EvalError::InvalidJson(e.to_string()))
+  }
+
+  pub(super) fn json_stringify(
+    inputs: Vec<DataValue>,
+    pretty: bool,
+  ) -> Result<Vec<DataValue>, EvalError>
+  {
+    let [value] = inputs.as_slice()
+    else
+    {
+      return Err(EvalError::IncorrectInputCount);
+    };
+    let json = sorted_json(value).map_err(|e| EvalError::InvalidJson(e.to_string()))?;
+    let text = match pretty
+    {
+      true => serde_json::to_string_pretty(&json),
+      false => serde_json::to_string(&json),
+    };
+    Ok(vec![DataValue::String(
+      text.map_err(|e| EvalError::InvalidJson(e.to_string()))?,
+    )])
+  }
+}
+
+/// `value` as JSON with the keys of every Object sorted, whatever order they were added
+/// in.
+fn sorted_json(value: &DataValue) -> Result<serde_json::Value, serde_json::Error>
+{
+  match value
+  {
+    DataValue::Object(fields) =>
+    {
+      let mut keys: Vec<&String> = fields.keys().collect();
+      keys.sort();
+      keys
+        .into_iter()
+        .map(|k| Ok((k.clone(), sorted_json(&fields[k])?)))
+        .collect()
+    }
+    DataValue::Array(items) => items.iter().map(sorted_json).collect(),
+    _ => serde_json::to_value(value),
+  }
+}
+
+/// The contents of a ```` ``` ```` fenced block making up the whole of `text`, with
+/// any language tag after the opening fence dropped, or `text` if it is not one.
+fn strip_code_fence(text: &str) -> &str
+{
+  text
+    .strip_prefix("```")
+    .and_then(|x| x.strip_suffix("```"))
+    .and_then(|x| x.split_once('\n'))
+    .map(|(_, body)| body.trim())
+    .unwrap_or(text)
+}
@@ -0,0 +1,264 @@
+//! Rewrites a graph as it is loaded so that running it takes fewer tasks and channels,
+//! without changing what it does.
+
+use super::nodes::{AtomicType, Complex, ControlFlow, Instance, LoopNodes, NodeType};
+use serde::Serialize;
+use std::{
+  collections::{HashMap, HashSet},
+  fmt::Display,
+};
+use uuid::Uuid;
+
+/// What [`Complex::optimize`] changed in a graph
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct Optimized
+{
+  /// `BinOp` nodes replaced by a `Value` of what they would output
+  pub folded: Vec<Uuid>,
+  /// Nodes taken out of the graph, each a `Value` no longer read once the nodes
+  /// reading it were folded or a node that could never run
+  pub removed: Vec<Uuid>,
+}
+
+impl Optimized
+{
+  pub fn is_empty(&self) -> bool
+  {
+    self.folded.is_empty() && self.removed.is_empty()
+  }
+}
+
+impl Display for Optimized
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+  {
+    write!(
+      f,
+      "folded {} constant node(s), removed {} node(s)",
+      self.folded.len(),
+      self.removed.len()
+    )
+  }
+}
+
+fn is_value(instance: &Instance) -> bool
+{
+  matches!(instance.node_type, NodeType::Atomic(AtomicType::Value(_)))
+}
+
+impl Complex
+{
+  /// Folds constants and removes dead nodes, see [`Optimized`]:
+  ///
+  /// - a `BinOp` whose inputs all come from `Value` nodes that run becomes a `Value`
+  ///   itself, so chains of them fold down to their last node. Operations that would
+  ///   fail are left to fail when the graph runs.
+  /// - a `Value` nothing reads any more, triggered by a single connection, is taken out
+  ///   of the control flow, its predecessor triggering its successors directly.
+  /// - nodes that no start node's control flow reaches are removed, unless a node that
+  ///   is kept reads from them, waits on their reads or joins on them.
+  pub fn optimize(&mut self) -> Optimized
+  {
+    let mut optimized = Optimized::default();
+    let runs = self.reachable(self.starts(), |_, _| ());
+    self.fold(&runs, &mut optimized);
+    while let Some(id) = self.bypassable()
+    {
+      self.bypass(id);
+    }
+    let mut roots = self.starts();
+    roots.push(self.end_node);
+    let kept = self.reachable(roots, |instance, next| {
+      next.extend(instance.inputs.iter().map(|(_, id, _)| *id));
+      next.extend(&instance.outputs);
+      if instance.node_type == NodeType::Atomic(AtomicType::Control(ControlFlow::Parallel))
+      {
+        next.extend(instance.control_flow_in.iter().flatten().map(|(id, _)| *id));
+      }
+    });
+    let mut removed: Vec<Uuid> = self
+      .instances
+      .keys()
+      .filter(|x| !kept.contains(x))
+      .copied()
+      .collect();
+    removed.sort();
+    for id in &removed
+    {
+      self.instances.remove(id);
+    }
+    for instance in self.instances.values_mut()
+    {
+      for port in &mut instance.control_flow_in
+      {
+        port.retain(|(id, _)| kept.contains(id));
+      }
+    }
+    optimized.removed = removed;
+    optimized
+  }
+
+  fn starts(&self) -> Vec<Uuid>
+  {
+    self
+      .instances
+      .iter()
+      .filter(|(_, x)| x.node_type == NodeType::Atomic(AtomicType::Control(ControlFlow::Start)))
+      .map(|(id, _)| *id)
+      .collect()
+  }
+
+  /// The nodes reached from `roots` through control flow, including the loops
+  /// `Continue` nodes go back to, and through whatever else `more` adds to the nodes to
+  /// visit next
+  fn reachable(&self, roots: Vec<Uuid>, more: impl Fn(&Instance, &mut Vec<Uuid>)) -> HashSet<Uuid>
+  {
+    let mut seen = HashSet::new();
+    let mut stack = roots;
+    while let Some(id) = stack.pop()
+    {
+      let Some(instance) = self.instances.get(&id)
+      else
+      {
+        continue;
+      };
+      if !seen.insert(id)
+      {
+        continue;
+      }
+      stack.extend(
+        instance
+          .control_flow_out
+          .iter()
+          .flatten()
+          .map(|(id, _)| *id),
+      );
+      if let NodeType::Atomic(AtomicType::Control(ControlFlow::Loop(LoopNodes::Continue(start)))) =
+        &instance.node_type
+      {
+        stack.push(*start);
+      }
+      more(instance, &mut stack);
+    }
+    seen
+  }
+
+  /// Folds `BinOp` nodes reading only from `Value` nodes in `runs` until none are left
+  fn fold(&mut self, runs: &HashSet<Uuid>, optimized: &mut Optimized)
+  {
+    loop
+    {
+      let constant = |id: &Uuid| runs.contains(id) && self.instances.get(id).is_some_and(is_value);
+      let next = self.instances.iter().find_map(|(id, instance)| {
+        let NodeType::Atomic(AtomicType::BinOp(op)) = &instance.node_type
+        else
+        {
+          return None;
+        };
+        if instance.inputs.len() != 2 || !instance.inputs.iter().all(|(_, x, _)| constant(x))
+        {
+          return None;
+        }
+        let inputs = instance
+          .inputs
+          .iter()
+          .map(|(_, x, _)| {
+            match &self.instances[x].node_type
+            {
+              NodeType::Atomic(AtomicType::Value(value)) => value.clone(),
+              _ => unreachable!("checked to be a value"),
+            }
+          })
+          .collect();
+        let value = NodeType::eval_bin_op(op.clone(), inputs).ok()?.pop()?;
+        Some((*id, value))
+      });
+      let Some((id, value)) = next
+      else
+      {
+        return;
+      };
+      let instance = self.instances.get_mut(&id).expect("found above");
+      instance.node_type = NodeType::Atomic(AtomicType::Value(value));
+      let sources: Vec<Uuid> = instance.inputs.drain(..).map(|(_, x, _)| x).collect();
+      for source in sources
+      {
+        let outputs = &mut self.instances.get_mut(&source).expect("read above").outputs;
+        if let Some(read) = outputs.iter().position(|x| *x == id)
+        {
+          outputs.remove(read);
+        }
+      }
+      optimized.folded.push(id);
+    }
+  }
+
+  /// A `Value` that nothing reads, triggered by exactly one connection and triggering
+  /// on a single port, which can be taken out of the control flow as it is
+  fn bypassable(&self) -> Option<Uuid>
+  {
+    let mut incoming: HashMap<Uuid, usize> = HashMap::new();
+    for (id, _) in self
+      .instances
+      .values()
+      .flat_map(|x| x.control_flow_out.iter().flatten())
+    {
+      *incoming.entry(*id).or_default() += 1;
+    }
+    let mut candidates: Vec<Uuid> = self
+      .instances
+      .iter()
+      .filter(|(id, x)| {
+        is_value(x)
+          && x.outputs.is_empty()
+          && x.control_flow_out.len() <= 1
+          && **id != self.end_node
+          && incoming.get(id) == Some(&1)
+          && !x.control_flow_out.iter().flatten().any(|(to, _)| to == *id)
+      })
+      .map(|(id, _)| *id)
+      .collect();
+    // in a fixed order so the same graph always optimizes the same way
+    candidates.sort();
+    candidates.into_iter().next()
+  }
+
+  /// Makes the node triggering `id` trigger what `id` triggers instead
+  fn bypass(&mut self, id: Uuid)
+  {
+    let successors = self.instances[&id]
+      .control_flow_out
+      .first()
+      .cloned()
+      .unwrap_or_default();
+    let mut from = None;
+    for (source, instance) in self.instances.iter_mut()
+    {
+      for (port, targets) in instance.control_flow_out.iter_mut().enumerate()
+      {
+        if let Some(at) = targets.iter().position(|(x, _)| *x == id)
+        {
+          targets.splice(at..=at, successors.iter().cloned());
+          from = Some((*source, port));
+        }
+      }
+    }
+    for (successor, _) in &successors
+    {
+      if let (Some(instance), Some(from)) = (self.instances.get_mut(successor), from)
+      {
+        for entry in instance.control_flow_in.iter_mut().flatten()
+        {
+          if entry.0 == id
+          {
+            *entry = from;
+          }
+        }
+      }
+    }
+    if let Some(instance) = self.instances.get_mut(&id)
+    {
+      instance.control_flow_out.clear();
+    }
+  }
+}
@@ -0,0 +1,72 @@
+use super::{
+  nodes::NodeType,
+  typing::{DataType, DataValue},
+};
+use crate::eval::EvalError;
+use indexmap::IndexMap;
+use regex::Regex;
+
+/// The pattern on port 0 compiled, and the String on port 1 to search
+fn pattern_and_text(inputs: &[DataValue]) -> Result<(Regex, &str), EvalError>
+{
+  match inputs
+  {
+    [DataValue::String(pattern), DataValue::String(text)] => Ok((Regex::new(pattern)?, text)),
+    [_, _] =>
+    {
+      Err(EvalError::IncorrectTyping {
+        got: inputs.iter().map(|x| x.get_type()).collect(),
+        expected: vec![DataType::String, DataType::String],
+      })
+    }
+    _ => Err(EvalError::IncorrectInputCount),
+  }
+}
+
+fn optional_string(text: Option<&str>) -> DataValue
+{
+  text
+    .map(|x| DataValue::String(x.to_string()))
+    .unwrap_or(DataValue::None)
+}
+
+impl NodeType
+{
+  pub(super) fn regex_match(inputs: Vec<DataValue>) -> Result<Vec<DataValue>, EvalError>
+  {
+    let (regex, text) = pattern_and_text(&inputs)?;
+    Ok(vec![DataValue::Boolean(regex.is_match(text))])
+  }
+
+  pub(super) fn regex_find(inputs: Vec<DataValue>) -> Result<Vec<DataValue>, EvalError>
+  {
+    let (regex, text) = pattern_and_text(&inputs)?;
+    Ok(vec![optional_string(regex.find(text).map(|x| x.as_str()))])
+  }
+
+  pub(super) fn regex_captures(inputs: Vec<DataValue>) -> Result<Vec<DataValue>, EvalError>
+  {
+    let (regex, text) = pattern_and_text(&inputs)?;
+    let Some(captures) = regex.captures(text)
+    else
+    {
+      return Ok(vec![DataValue::None]);
+    };
+    let group = |i: usize| optional_string(captures.get(i).map(|x| x.as_str()));
+    let named: IndexMap<String, DataValue> = regex
+      .capture_names()
+      .enumerate()
+      .filter_map(|(i, name)| Some((name?.to_string(), group(i))))
+      .collect();
+    match named.is_empty()
+    {
+      true =>
+      {
+        Ok(vec![DataValue::Array(
+          (1..captures.len()).map(group).collect(),
+        )])
+      }
+      false => Ok(vec![DataValue::Object(named)]),
+    }
+  }
+}
@@ -0,0 +1,96 @@
+use super::{
+  nodes::NodeType,
+  typing::{DataType, DataValue},
+};
+use crate::eval::EvalError;
+use rand::{seq::IndexedRandom, Rng, RngExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Builder;
+
+/// Nodes drawing from random generators of their own, which give the same values each
+/// run once a seed is set with `--seed` or [`crate::Evaluator::set_seed`]
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, PartialEq)]
+pub enum RandomOp
+{
+  /// An Integer from the min on port 0 to the max on port 1, both included
+  Int,
+  /// A Float from 0 up to but not including 1, or from the min on the optional port 0
+  /// up to but not including the max on port 1
+  Float,
+  /// An element of an Array picked at random, None if it is empty
+  Choice,
+  /// A random UUID v4, as a String
+  UuidV4,
+}
+
+impl NodeType
+{
+  pub(super) fn eval_random(
+    op: RandomOp,
+    rng: &mut impl Rng,
+    inputs: Vec<DataValue>,
+  ) -> Result<Vec<DataValue>, EvalError>
+  {
+    let value = match (op, inputs.as_slice())
+    {
+      (RandomOp::Int, [DataValue::Integer(min), DataValue::Integer(max)]) =>
+      {
+        if min > max
+        {
+          return Err(EvalError::EmptyRange(
+            inputs[0].clone().into(),
+            inputs[1].clone().into(),
+          ));
+        }
+        DataValue::Integer(rng.random_range(*min..=*max))
+      }
+      (RandomOp::Float, []) => DataValue::Float(rng.random()),
+      (RandomOp::Float, [min, max]) =>
+      {
+        let (Ok(DataValue::Float(low)), Ok(DataValue::Float(high))) =
+          (min.try_cast(DataType::Float), max.try_cast(DataType::Float))
+        else
+        {
+          return Err(EvalError::IncorrectTyping {
+            got: inputs.iter().map(|x| x.get_type()).collect(),
+            expected: vec![DataType::Float, DataType::Float],
+          });
+        };
+        if !(low < high && (high - low).is_finite())
+        {
+          return Err(EvalError::EmptyRange(
+            min.clone().into(),
+            max.clone().into(),
+          ));
+        }
+        DataValue::Float(rng.random_range(low..high))
+      }
+      (RandomOp::Choice, [DataValue::Array(x)]) =>
+      {
+        x.choose(rng).cloned().unwrap_or(DataValue::None)
+      }
+      (RandomOp::UuidV4, []) =>
+      {
+        let id = Builder::from_random_bytes(rng.random()).into_uuid();
+        DataValue::String(id.hyphenated().to_string())
+      }
+      (RandomOp::Int, [_, _]) =>
+      {
+        return Err(EvalError::IncorrectTyping {
+          got: inputs.iter().map(|x| x.get_type()).collect(),
+          expected: vec![DataType::Integer, DataType::Integer],
+        })
+      }
+      (RandomOp::Choice, [_]) =>
+      {
+        return Err(EvalError::IncorrectTyping {
+          got: inputs.iter().map(|x| x.get_type()).collect(),
+          expected: vec![DataType::Array],
+        })
+      }
+      _ => return Err(EvalError::IncorrectInputCount),
+    };
+    Ok(vec![value])
+  }
+}
@@ -0,0 +1,186 @@
+use super::{
+  nodes::NodeType,
+  typing::{DataType, DataValue},
+};
+use crate::eval::EvalError;
+use indexmap::IndexMap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+/// Record types by name, each with its fields in order and their types
+pub type Records = HashMap<String, IndexMap<String, DataType>>;
+
+/// Builds and takes apart values of the graph's record types, which are Objects
+/// holding the record's fields.
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, PartialEq)]
+pub enum RecordOp
+{
+  /// Takes a value for each field of the named record, in the order the record lists
+  /// them, and outputs the record
+  Construct(String),
+  /// Checks an Object against the named record and outputs each of its fields in
+  /// order, one per port
+  Destructure(String),
+}
+
+/// The definition of the record `name`
+pub fn fields<'a>(
+  records: &'a Records,
+  name: &str,
+) -> Result<&'a IndexMap<String, DataType>, EvalError>
+{
+  records
+    .get(name)
+    .ok_or_else(|| EvalError::UnknownRecord(name.to_string()))
+}
+
+/// `value` as the record `name`: an Object with each of its fields cast to the field's
+/// type, in the record's order. A field left out counts as None, so `Optional` fields
+/// may be, and fields the record does not have are refused.
+pub fn record_of(records: &Records, name: &str, value: &DataValue) -> Result<DataValue, EvalError>
+{
+  checked_fields(records, name, value).map(DataValue::Object)
+}
+
+fn checked_fields(
+  records: &Records,
+  name: &str,
+  value: &DataValue,
+) -> Result<IndexMap<String, DataValue>, EvalError>
+{
+  let DataValue::Object(given) = value
+  else
+  {
+    return Err(EvalError::CastError((
+      value.get_type(),
+      DataType::Record(name.to_string()),
+    )));
+  };
+  let fields = fields(records, name)?;
+  if let Some(field) = given.keys().find(|x| !fields.contains_key(*x))
+  {
+    return Err(EvalError::InvalidField {
+      record: name.to_string(),
+      field: field.clone(),
+      expected: None,
+    });
+  }
+  fields
+    .iter()
+    .map(|(field, data_type)| {
+      let value = given.get(field).unwrap_or(&DataValue::None);
+      let cast = match data_type
+      {
+        DataType::Record(inner) => record_of(records, inner, value).ok(),
+        _ => value.try_cast(data_type.clone()).ok(),
+      };
+      let value = cast.ok_or_else(|| {
+        EvalError::InvalidField {
+          record: name.to_string(),
+          field: field.clone(),
+          expected: Some(data_type.clone()),
+        }
+      })?;
+      Ok((field.clone(), value))
+    })
+    .collect()
+}
+
+/// A JSON Schema for each record under `$defs`, records referring to each other by
+/// `$ref`, for typing agent tool arguments and the like
+pub fn json_schema(records: &Records) -> Value
+{
+  let mut names: Vec<_> = records.keys().collect();
+  names.sort();
+  let defs: Map<String, Value> = names
+    .into_iter()
+    .map(|name| {
+      let fields = &records[name];
+      let properties: Map<String, Value> = fields
+        .iter()
+        .map(|(field, data_type)| (field.clone(), type_schema(data_type)))
+        .collect();
+      let required: Vec<&String> = fields
+        .iter()
+        .filter(|(_, x)| !matches!(x, DataType::Optional(_) | DataType::Any))
+        .map(|(field, _)| field)
+        .collect();
+      let schema = json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false,
+      });
+      (name.clone(), schema)
+    })
+    .collect();
+  json!({ "$defs": defs })
+}
+
+/// What JSON a value of `data_type` serializes as
+fn type_schema(data_type: &DataType) -> Value
+{
+  match data_type
+  {
+    DataType::String | DataType::Enum(_) | DataType::DateTime | DataType::Handle =>
+    {
+      json!({"type": "string"})
+    }
+    DataType::Integer | DataType::Byte => json!({"type": "integer"}),
+    DataType::Float => json!({"type": "number"}),
+    DataType::Boolean => json!({"type": "boolean"}),
+    DataType::Array | DataType::Bytes => json!({"type": "array"}),
+    DataType::Object => json!({"type": "object"}),
+    DataType::Map(values) => json!({"type": "object", "additionalProperties": type_schema(values)}),
+    DataType::Record(name) => json!({"$ref": format!("#/$defs/{name}")}),
+    DataType::Optional(inner) => json!({"anyOf": [type_schema(inner), {"type": "null"}]}),
+    DataType::Result(ok, err) =>
+    {
+      json!({"oneOf": [
+        {"type": "object", "properties": {"Ok": type_schema(ok)}, "required": ["Ok"]},
+        {"type": "object", "properties": {"Err": type_schema(err)}, "required": ["Err"]},
+      ]})
+    }
+    DataType::None => json!({"type": "null"}),
+    DataType::Agent(_) | DataType::Any => json!({}),
+  }
+}
+
+impl NodeType
+{
+  pub(super) fn eval_record(
+    op: RecordOp,
+    records: &Records,
+    inputs: Vec<DataValue>,
+  ) -> Result<Vec<DataValue>, EvalError>
+  {
+    match op
+    {
+      RecordOp::Construct(name) =>
+      {
+        let fields = fields(records, &name)?;
+        if inputs.len() != fields.len()
+        {
+          return Err(EvalError::IncorrectInputCount);
+        }
+        let object = fields.keys().cloned().zip(inputs).collect();
+        Ok(vec![record_of(records, &name, &DataValue::Object(object))?])
+      }
+      RecordOp::Destructure(name) =>
+      {
+        let [value] = inputs.as_slice()
+        else
+        {
+          return Err(EvalError::IncorrectInputCount);
+        };
+        Ok(
+          checked_fields(records, &name, value)?
+            .into_values()
+            .collect(),
+        )
+      }
+    }
+  }
+}
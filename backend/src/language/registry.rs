@@ -0,0 +1,1549 @@
+//! What each atomic node takes and gives, for the editor and graph validators to
+//! build on rather than knowing it themselves.
+
+use super::{
+  arrays::{Aggregate, ArrayOp, SortOrder},
+  bytes::{BytesOp, Encoding},
+  dates::DateTimeOp,
+  fs::FsOp,
+  ids::IdOp,
+  nodes::{
+    AgentOperation, AtomicBinOp, AtomicIo, AtomicLogic, AtomicType, AtomicUnaryOp, ControlFlow,
+    FileMode, HttpOp, IoType, LoopNodes, NodeType, ProcessOp, Variable,
+  },
+  objects::ObjectOp,
+  random::RandomOp,
+  records::{RecordOp, Records},
+  typing::{DataType as T, DataValue},
+  wrapped::WrapOp,
+};
+use crate::ai::{AgentType, TranscriptFormat};
+use crate::capabilities::Capability;
+use crate::config::Permission;
+use serde::Serialize;
+use std::fmt::Display;
+use uuid::Uuid;
+
+/// Where the editor's palette files a node
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Category
+{
+  Values,
+  Math,
+  Logic,
+  Text,
+  Control,
+  Variables,
+  Io,
+  Agents,
+  Arrays,
+  Bytes,
+  Dates,
+  Files,
+  Http,
+  Ids,
+  Objects,
+  Processes,
+  Random,
+  Runtime,
+}
+
+impl Category
+{
+  pub fn name(self) -> &'static str
+  {
+    match self
+    {
+      Category::Values => "values",
+      Category::Math => "math",
+      Category::Logic => "logic",
+      Category::Text => "text",
+      Category::Control => "control",
+      Category::Variables => "variables",
+      Category::Io => "io",
+      Category::Agents => "agents",
+      Category::Arrays => "arrays",
+      Category::Bytes => "bytes",
+      Category::Dates => "dates",
+      Category::Files => "files",
+      Category::Http => "http",
+      Category::Ids => "ids",
+      Category::Objects => "objects",
+      Category::Processes => "processes",
+      Category::Random => "random",
+      Category::Runtime => "runtime",
+    }
+  }
+}
+
+/// An input or output of a node
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Port
+{
+  pub name: String,
+  /// The types it takes or gives, any type if empty
+  pub types: Vec<T>,
+  /// An input the node runs without, as None
+  pub optional: bool,
+}
+
+/// What a node takes and gives and what it needs to run
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct NodeSpec
+{
+  pub node_type: AtomicType,
+  pub name: String,
+  pub category: Category,
+  /// Data inputs by port. Outputs of only None, such as that of `Print`, are left out.
+  pub inputs: Vec<Port>,
+  pub outputs: Vec<Port>,
+  /// Takes any number of inputs like the last one
+  pub variadic_inputs: bool,
+  /// Gives an output for each input, or for each graph input in the case of `Start`
+  pub variadic_outputs: bool,
+  /// Keeps something between runs, which checkpoints save
+  pub stateful: bool,
+  /// Reaches outside the graph: the console, files, sockets, servers or processes
+  pub io: bool,
+  /// Creates or uses an agent
+  pub agent: bool,
+  /// What the sandbox policy has to allow for it to run
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub permission: Option<Permission>,
+  /// What the build has to include for it to run
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub capability: Option<Capability>,
+}
+
+impl Display for Port
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+  {
+    write!(f, "{}", self.name)?;
+    if !self.types.is_empty()
+    {
+      let types: Vec<String> = self.types.iter().map(|x| x.to_string()).collect();
+      write!(f, ": {}", types.join(" | "))?;
+    }
+    if self.optional
+    {
+      write!(f, "?")?;
+    }
+    Ok(())
+  }
+}
+
+/// One line: the name, category, ports and what the node needs
+impl Display for NodeSpec
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+  {
+    let ports = |ports: &[Port], variadic: bool| {
+      let mut ports: Vec<String> = ports.iter().map(|x| x.to_string()).collect();
+      if variadic
+      {
+        ports.push("...".to_string());
+      }
+      ports.join(", ")
+    };
+    write!(
+      f,
+      "{:<28} {:<9} ({}) -> ({})",
+      self.name,
+      self.category.name(),
+      ports(&self.inputs, self.variadic_inputs),
+      ports(&self.outputs, self.variadic_outputs)
+    )?;
+    let flags = [
+      (self.stateful, "stateful"),
+      (self.io, "io"),
+      (self.agent, "agent"),
+    ];
+    for (_, flag) in flags.iter().filter(|(set, _)| *set)
+    {
+      write!(f, " [{flag}]")?;
+    }
+    if let Some(permission) = self.permission
+    {
+      write!(f, " [needs {permission}]")?;
+    }
+    if let Some(capability) = self.capability
+    {
+      write!(f, " [needs the {} feature]", capability.name())?;
+    }
+    Ok(())
+  }
+}
+
+fn port<const N: usize>(name: &str, types: [T; N]) -> Port
+{
+  Port {
+    name: name.to_string(),
+    types: types.into(),
+    optional: false,
+  }
+}
+
+fn optional<const N: usize>(name: &str, types: [T; N]) -> Port
+{
+  Port {
+    optional: true,
+    ..port(name, types)
+  }
+}
+
+fn any(name: &str) -> Port
+{
+  port(name, [])
+}
+
+fn agent(name: &str) -> Port
+{
+  port(
+    name,
+    [
+      T::Agent(AgentType::OpenAi),
+      T::Agent(AgentType::OpenRouter),
+      T::Agent(AgentType::Mock),
+    ],
+  )
+}
+
+fn handle(name: &str) -> Port
+{
+  port(name, [T::Handle])
+}
+
+fn string(name: &str) -> Port
+{
+  port(name, [T::String])
+}
+
+fn number(name: &str) -> Port
+{
+  port(name, [T::Integer, T::Float])
+}
+
+fn boolean(name: &str) -> Port
+{
+  port(name, [T::Boolean])
+}
+
+fn array(name: &str) -> Port
+{
+  port(name, [T::Array])
+}
+
+fn lowercase(x: impl std::fmt::Debug) -> String
+{
+  format!("{x:?}").to_lowercase()
+}
+
+impl AtomicType
+{
+  /// How many values a run of the node gives with `inputs` inputs connected, in a
+  /// graph taking `graph_inputs` and knowing `records`. Outputs the spec leaves out for
+  /// only ever being None are not counted.
+  pub fn output_count(&self, inputs: usize, graph_inputs: usize, records: &Records) -> usize
+  {
+    match self
+    {
+      AtomicType::Control(ControlFlow::Start) => graph_inputs,
+      AtomicType::Control(ControlFlow::End) => inputs,
+      // an unknown record fails validation before this matters
+      AtomicType::Record(RecordOp::Destructure(name)) => records.get(name).map_or(0, |x| x.len()),
+      _ =>
+      {
+        let spec = self.spec();
+        match spec.variadic_outputs
+        {
+          true => inputs,
+          false => spec.outputs.len(),
+        }
+      }
+    }
+  }
+
+  /// What the node takes and gives, for the arguments it was given. A `Try` or
+  /// `Timeout` takes the inputs of the atomic node it wraps.
+  pub fn spec(&self) -> NodeSpec
+  {
+    let spec = |name: &str, category: Category, inputs: Vec<Port>, outputs: Vec<Port>| {
+      NodeSpec {
+        node_type: self.clone(),
+        name: name.to_string(),
+        category,
+        inputs,
+        outputs,
+        variadic_inputs: false,
+        variadic_outputs: false,
+        stateful: false,
+        io: false,
+        agent: false,
+        permission: self.permission(false),
+        capability: self.capability(),
+      }
+    };
+    match self
+    {
+      AtomicType::Print =>
+      {
+        NodeSpec {
+          variadic_inputs: true,
+          io: true,
+          ..spec("Print", Category::Io, vec![any("value")], vec![])
+        }
+      }
+      AtomicType::Replace =>
+      {
+        spec(
+          "Replace",
+          Category::Text,
+          vec![string("pattern"), string("replacement"), string("text")],
+          vec![string("text")],
+        )
+      }
+      AtomicType::BinOp(op) =>
+      {
+        let operand = |name| {
+          match op
+          {
+            AtomicBinOp::Add => port(name, [T::Integer, T::Float, T::String]),
+            _ => number(name),
+          }
+        };
+        spec(
+          &format!("{op:?}"),
+          Category::Math,
+          vec![operand("a"), operand("b")],
+          vec![operand("result")],
+        )
+      }
+      AtomicType::UnaryOp(op) =>
+      {
+        NodeSpec {
+          variadic_inputs: true,
+          variadic_outputs: true,
+          ..spec(
+            &format!("{op:?}"),
+            Category::Math,
+            vec![number("x")],
+            vec![number("result")],
+          )
+        }
+      }
+      AtomicType::Value(value) =>
+      {
+        spec(
+          &format!("{} value", value.get_type()),
+          Category::Values,
+          vec![],
+          vec![port("value", [value.get_type()])],
+        )
+      }
+      AtomicType::Control(control) =>
+      {
+        match control
+        {
+          ControlFlow::Start =>
+          {
+            NodeSpec {
+              variadic_outputs: true,
+              ..spec("Start", Category::Control, vec![], vec![])
+            }
+          }
+          ControlFlow::End =>
+          {
+            NodeSpec {
+              variadic_inputs: true,
+              ..spec("End", Category::Control, vec![any("output")], vec![])
+            }
+          }
+          ControlFlow::Loop(LoopNodes::Start) =>
+          {
+            spec("Loop start", Category::Control, vec![], vec![])
+          }
+          ControlFlow::Loop(LoopNodes::Continue(_)) =>
+          {
+            spec("Loop continue", Category::Control, vec![], vec![])
+          }
+          ControlFlow::If => spec("If", Category::Control, vec![boolean("condition")], vec![]),
+          ControlFlow::Try(inner) | ControlFlow::Timeout(inner, _) =>
+          {
+            let name = match control
+            {
+              ControlFlow::Try(_) => "Try",
+              _ => "Timeout",
+            };
+            let outputs = vec![any("value"), port("error", [T::Object, T::None])];
+            match inner.as_ref()
+            {
+              NodeType::Atomic(inner) =>
+              {
+                let inner = inner.spec();
+                NodeSpec {
+                  variadic_inputs: inner.variadic_inputs,
+                  stateful: inner.stateful,
+                  io: inner.io,
+                  agent: inner.agent,
+                  permission: inner.permission,
+                  capability: inner.capability,
+                  ..spec(name, Category::Control, inner.inputs, outputs)
+                }
+              }
+              // what a Complex graph takes is only known once it is loaded
+              NodeType::Complex(_) =>
+              {
+                NodeSpec {
+                  variadic_inputs: true,
+                  ..spec(name, Category::Control, vec![any("input")], outputs)
+                }
+              }
+            }
+          }
+          ControlFlow::Parallel =>
+          {
+            NodeSpec {
+              variadic_inputs: true,
+              ..spec(
+                "Parallel",
+                Category::Control,
+                vec![any("value")],
+                vec![array("values")],
+              )
+            }
+          }
+        }
+      }
+      AtomicType::Variable(Variable::Set, _) =>
+      {
+        NodeSpec {
+          stateful: true,
+          ..spec(
+            "Set variable",
+            Category::Variables,
+            vec![any("value")],
+            vec![],
+          )
+        }
+      }
+      AtomicType::Variable(Variable::Get, _) =>
+      {
+        NodeSpec {
+          stateful: true,
+          ..spec(
+            "Get variable",
+            Category::Variables,
+            vec![],
+            vec![any("value")],
+          )
+        }
+      }
+      AtomicType::Io(io) =>
+      {
+        let (name, inputs, outputs) = match io
+        {
+          AtomicIo::ConsoleInput => ("Console input".to_string(), vec![], vec![string("line")]),
+          AtomicIo::Open(kind) =>
+          {
+            let (name, inputs) = match kind
+            {
+              IoType::File(mode) =>
+              {
+                (
+                  format!("Open file ({})", lowercase(mode)),
+                  vec![string("path")],
+                )
+              }
+              IoType::TcpSocket =>
+              {
+                (
+                  "Open TCP socket".to_string(),
+                  vec![string("host"), port("port", [T::Integer])],
+                )
+              }
+              IoType::TcpListener =>
+              {
+                (
+                  "Open TCP listener".to_string(),
+                  vec![string("host"), port("port", [T::Integer])],
+                )
+              }
+              IoType::UdpSocket =>
+              {
+                (
+                  "Open UDP socket".to_string(),
+                  vec![string("host"), port("port", [T::Integer])],
+                )
+              }
+              IoType::WebSocket => ("Open WebSocket".to_string(), vec![string("url")]),
+            };
+            return NodeSpec {
+              stateful: true,
+              io: true,
+              ..spec(&name, Category::Io, inputs, vec![handle("handle")])
+            };
+          }
+          AtomicIo::Read =>
+          {
+            (
+              "Read".to_string(),
+              vec![handle("handle"), port("size", [T::Integer])],
+              vec![port("bytes", [T::Bytes])],
+            )
+          }
+          AtomicIo::Write =>
+          {
+            (
+              "Write".to_string(),
+              vec![handle("handle"), port("data", [T::String, T::Bytes])],
+              vec![],
+            )
+          }
+          AtomicIo::GetLine =>
+          {
+            (
+              "Get line".to_string(),
+              vec![handle("handle")],
+              vec![string("line")],
+            )
+          }
+          AtomicIo::Accept =>
+          {
+            (
+              "Accept".to_string(),
+              vec![handle("listener")],
+              vec![handle("connection"), string("peer")],
+            )
+          }
+          AtomicIo::SendTo =>
+          {
+            (
+              "Send to".to_string(),
+              vec![
+                handle("socket"),
+                port("data", [T::String, T::Bytes]),
+                string("address"),
+              ],
+              vec![port("sent", [T::Integer])],
+            )
+          }
+          AtomicIo::RecvFrom =>
+          {
+            (
+              "Receive from".to_string(),
+              vec![handle("socket"), port("size", [T::Integer])],
+              vec![string("data"), string("sender")],
+            )
+          }
+          AtomicIo::SendFrame =>
+          {
+            (
+              "Send frame".to_string(),
+              vec![
+                handle("socket"),
+                port("frame", [T::String, T::Bytes, T::Array]),
+              ],
+              vec![],
+            )
+          }
+          AtomicIo::RecvFrame =>
+          {
+            (
+              "Receive frame".to_string(),
+              vec![handle("socket")],
+              vec![port("frame", [T::String, T::Bytes, T::None])],
+            )
+          }
+          AtomicIo::Close => ("Close".to_string(), vec![handle("handle")], vec![]),
+          AtomicIo::Flush => ("Flush".to_string(), vec![handle("handle")], vec![]),
+        };
+        NodeSpec {
+          io: true,
+          ..spec(&name, Category::Io, inputs, outputs)
+        }
+      }
+      AtomicType::Cast(to) =>
+      {
+        spec(
+          &format!("Cast to {to}"),
+          Category::Values,
+          vec![any("value")],
+          vec![port("value", [to.clone()])],
+        )
+      }
+      AtomicType::IsNone =>
+      {
+        spec(
+          "Is none",
+          Category::Logic,
+          vec![any("value")],
+          vec![boolean("none")],
+        )
+      }
+      AtomicType::LogicalOp(op) =>
+      {
+        match op
+        {
+          AtomicLogic::And | AtomicLogic::Or | AtomicLogic::Xor =>
+          {
+            NodeSpec {
+              variadic_inputs: true,
+              ..spec(
+                &format!("{op:?}"),
+                Category::Logic,
+                vec![boolean("a"), boolean("b")],
+                vec![boolean("result")],
+              )
+            }
+          }
+          AtomicLogic::Not =>
+          {
+            NodeSpec {
+              variadic_inputs: true,
+              variadic_outputs: true,
+              ..spec(
+                "Not",
+                Category::Logic,
+                vec![boolean("value")],
+                vec![boolean("result")],
+              )
+            }
+          }
+          AtomicLogic::Eq | AtomicLogic::Neq | AtomicLogic::ApproxEq(_) =>
+          {
+            let name = match op
+            {
+              AtomicLogic::Eq => "Equal",
+              AtomicLogic::Neq => "Not equal",
+              _ => "Approximately equal",
+            };
+            spec(
+              name,
+              Category::Logic,
+              vec![any("a"), any("b")],
+              vec![boolean("result")],
+            )
+          }
+        }
+      }
+      AtomicType::AgentOp(op) =>
+      {
+        let (name, inputs, outputs) = match op
+        {
+          AgentOperation::Create(kind) =>
+          {
+            let inputs = vec![
+              string("model"),
+              optional("functions", [T::Array]),
+              optional("temperature", [T::Float, T::Integer]),
+              optional("system_prompt", [T::String]),
+              optional("top_p", [T::Float, T::Integer]),
+              optional("max_tokens", [T::Integer]),
+              optional("stop", [T::String, T::Array]),
+            ];
+            return NodeSpec {
+              stateful: true,
+              agent: true,
+              ..spec(
+                &format!("Create {kind:?} agent"),
+                Category::Agents,
+                inputs,
+                vec![port("agent", [T::Agent(kind.clone())])],
+              )
+            };
+          }
+          AgentOperation::Send =>
+          {
+            (
+              "Send".to_string(),
+              vec![
+                agent("agent"),
+                string("message"),
+                optional("priority", [T::Integer]),
+              ],
+              vec![],
+            )
+          }
+          AgentOperation::Recieve =>
+          {
+            (
+              "Receive".to_string(),
+              vec![agent("agent")],
+              vec![port("message", [T::String, T::None])],
+            )
+          }
+          AgentOperation::ExportHistory =>
+          {
+            (
+              "Export history".to_string(),
+              vec![agent("agent")],
+              vec![port("history", [T::Object])],
+            )
+          }
+          AgentOperation::ImportHistory =>
+          {
+            (
+              "Import history".to_string(),
+              vec![agent("agent"), port("history", [T::Object])],
+              vec![agent("agent")],
+            )
+          }
+          AgentOperation::AttachTools(_) =>
+          {
+            (
+              "Attach tools".to_string(),
+              vec![agent("agent")],
+              vec![agent("agent")],
+            )
+          }
+          AgentOperation::ExportTranscript(format) =>
+          {
+            (
+              format!("Export {} transcript", lowercase(format)),
+              vec![agent("agent"), optional("path", [T::String])],
+              vec![string("transcript")],
+            )
+          }
+        };
+        NodeSpec {
+          agent: true,
+          ..spec(&name, Category::Agents, inputs, outputs)
+        }
+      }
+      AtomicType::Array(op) =>
+      {
+        let (name, inputs, outputs) = match op
+        {
+          ArrayOp::Sort(order, _) =>
+          {
+            (
+              format!("Sort {}", lowercase(order)),
+              vec![array("array")],
+              vec![array("sorted")],
+            )
+          }
+          ArrayOp::Unique =>
+          {
+            (
+              "Unique".to_string(),
+              vec![array("array")],
+              vec![array("unique")],
+            )
+          }
+          ArrayOp::Contains =>
+          {
+            (
+              "Contains".to_string(),
+              vec![array("array"), any("value")],
+              vec![boolean("contains")],
+            )
+          }
+          ArrayOp::GroupBy(_) =>
+          {
+            (
+              "Group by".to_string(),
+              vec![array("array")],
+              vec![port("groups", [T::Object])],
+            )
+          }
+          ArrayOp::Union | ArrayOp::Intersection | ArrayOp::Difference | ArrayOp::Concat =>
+          {
+            (
+              format!("{op:?}"),
+              vec![array("a"), array("b")],
+              vec![array("result")],
+            )
+          }
+          ArrayOp::Aggregate(aggregate, _) =>
+          {
+            let output = match aggregate
+            {
+              Aggregate::Count => port("count", [T::Integer]),
+              Aggregate::Sum => number("sum"),
+              Aggregate::Avg => port("average", [T::Float, T::None]),
+              Aggregate::Min | Aggregate::Max => any(&lowercase(aggregate)),
+            };
+            (format!("{aggregate:?}"), vec![array("array")], vec![output])
+          }
+          ArrayOp::Length =>
+          {
+            (
+              "Length".to_string(),
+              vec![array("array")],
+              vec![port("length", [T::Integer])],
+            )
+          }
+          ArrayOp::Index =>
+          {
+            (
+              "Index".to_string(),
+              vec![array("array"), port("index", [T::Integer])],
+              vec![any("element")],
+            )
+          }
+          ArrayOp::Slice =>
+          {
+            (
+              "Slice".to_string(),
+              vec![
+                array("array"),
+                port("start", [T::Integer]),
+                port("end", [T::Integer, T::None]),
+              ],
+              vec![array("slice")],
+            )
+          }
+          ArrayOp::Append =>
+          {
+            (
+              "Append".to_string(),
+              vec![array("array"), any("value")],
+              vec![array("array")],
+            )
+          }
+          ArrayOp::Reverse =>
+          {
+            (
+              "Reverse".to_string(),
+              vec![array("array")],
+              vec![array("reversed")],
+            )
+          }
+          ArrayOp::Flatten =>
+          {
+            (
+              "Flatten".to_string(),
+              vec![array("array")],
+              vec![array("flat")],
+            )
+          }
+          ArrayOp::Split =>
+          {
+            (
+              "Split".to_string(),
+              vec![array("array"), port("index", [T::Integer])],
+              vec![array("before"), array("after")],
+            )
+          }
+          ArrayOp::Unzip =>
+          {
+            (
+              "Unzip".to_string(),
+              vec![array("pairs")],
+              vec![array("firsts"), array("seconds")],
+            )
+          }
+        };
+        spec(&name, Category::Arrays, inputs, outputs)
+      }
+      AtomicType::Bytes(op) =>
+      {
+        let (name, inputs, outputs) = match op
+        {
+          BytesOp::Encode(encoding) =>
+          {
+            (
+              format!("Encode {encoding:?}"),
+              vec![port("data", [T::Bytes, T::String])],
+              vec![string("text")],
+            )
+          }
+          BytesOp::Decode(encoding) =>
+          {
+            (
+              format!("Decode {encoding:?}"),
+              vec![string("text")],
+              vec![port("data", [T::Bytes])],
+            )
+          }
+          BytesOp::DecodeText(encoding) =>
+          {
+            (
+              format!("Decode {encoding:?} text"),
+              vec![string("text")],
+              vec![string("text")],
+            )
+          }
+        };
+        spec(&name, Category::Bytes, inputs, outputs)
+      }
+      AtomicType::DateTime(op) =>
+      {
+        let date = |name| port(name, [T::DateTime]);
+        let (name, inputs, outputs) = match op
+        {
+          DateTimeOp::Now => ("Now", vec![], vec![date("now")]),
+          DateTimeOp::Format(_) => ("Format date", vec![date("date")], vec![string("text")]),
+          DateTimeOp::Parse(_) => ("Parse date", vec![string("text")], vec![date("date")]),
+          DateTimeOp::Add =>
+          {
+            (
+              "Add duration",
+              vec![date("date"), port("ms", [T::Integer])],
+              vec![date("date")],
+            )
+          }
+          DateTimeOp::Sub =>
+          {
+            (
+              "Subtract duration",
+              vec![date("date"), port("other", [T::Integer, T::DateTime])],
+              vec![port("result", [T::DateTime, T::Integer])],
+            )
+          }
+          DateTimeOp::Compare =>
+          {
+            (
+              "Compare dates",
+              vec![date("a"), date("b")],
+              vec![port("order", [T::Integer])],
+            )
+          }
+        };
+        spec(name, Category::Dates, inputs, outputs)
+      }
+      AtomicType::Fs(op) =>
+      {
+        let (name, inputs, outputs) = match op
+        {
+          FsOp::ListDir =>
+          {
+            (
+              "List directory",
+              vec![string("path")],
+              vec![array("entries")],
+            )
+          }
+          FsOp::Exists => ("Exists", vec![string("path")], vec![boolean("exists")]),
+          FsOp::Delete => ("Delete", vec![string("path")], vec![]),
+          FsOp::Rename => ("Rename", vec![string("from"), string("to")], vec![]),
+          FsOp::MkDir => ("Make directory", vec![string("path")], vec![]),
+        };
+        NodeSpec {
+          io: true,
+          ..spec(name, Category::Files, inputs, outputs)
+        }
+      }
+      AtomicType::Http(op) =>
+      {
+        let (name, inputs, outputs) = match op
+        {
+          HttpOp::Request =>
+          {
+            (
+              "HTTP request",
+              vec![
+                string("method"),
+                string("url"),
+                optional("headers", [T::Object]),
+                optional("body", [T::String]),
+              ],
+              vec![
+                port("status", [T::Integer]),
+                port("headers", [T::Object]),
+                string("body"),
+              ],
+            )
+          }
+          HttpOp::Route(_) =>
+          {
+            (
+              "HTTP route",
+              vec![string("address"), string("method"), string("path")],
+              vec![string("address")],
+            )
+          }
+        };
+        NodeSpec {
+          io: true,
+          ..spec(name, Category::Http, inputs, outputs)
+        }
+      }
+      AtomicType::Id(op) =>
+      {
+        let (name, inputs) = match op
+        {
+          IdOp::V5 =>
+          {
+            (
+              "UUID v5",
+              vec![port("namespace", [T::String, T::None]), string("name")],
+            )
+          }
+          IdOp::ToString =>
+          {
+            (
+              "UUID to string",
+              vec![port(
+                "value",
+                [
+                  T::Handle,
+                  T::Agent(AgentType::OpenAi),
+                  T::Agent(AgentType::OpenRouter),
+                  T::Agent(AgentType::Mock),
+                ],
+              )],
+            )
+          }
+          IdOp::Parse => ("Parse UUID", vec![string("text")]),
+        };
+        spec(name, Category::Ids, inputs, vec![string("uuid")])
+      }
+      AtomicType::Object(op) =>
+      {
+        let object = || port("object", [T::Object, T::Array]);
+        let (name, inputs, outputs) = match op
+        {
+          ObjectOp::Destructure(paths) =>
+          {
+            (
+              "Destructure",
+              vec![object()],
+              paths.iter().map(|x| any(x)).collect(),
+            )
+          }
+          ObjectOp::GetField(_) => ("Get field", vec![object()], vec![any("value")]),
+          ObjectOp::SetField(_) =>
+          {
+            (
+              "Set field",
+              vec![object(), any("value")],
+              vec![port("object", [T::Object])],
+            )
+          }
+          ObjectOp::HasField(_) => ("Has field", vec![object()], vec![boolean("has")]),
+          ObjectOp::Keys =>
+          {
+            (
+              "Keys",
+              vec![port("object", [T::Object])],
+              vec![array("keys")],
+            )
+          }
+        };
+        spec(name, Category::Objects, inputs, outputs)
+      }
+      AtomicType::Process(op) =>
+      {
+        let (name, inputs, outputs) = match op
+        {
+          ProcessOp::Spawn =>
+          {
+            (
+              "Spawn process",
+              vec![
+                string("program"),
+                port("args", [T::Array, T::None]),
+                port("env", [T::Object, T::None]),
+              ],
+              vec![handle("process"), handle("stdin"), handle("stdout")],
+            )
+          }
+          ProcessOp::Wait =>
+          {
+            (
+              "Wait for process",
+              vec![handle("process")],
+              vec![port("code", [T::Integer, T::None])],
+            )
+          }
+        };
+        NodeSpec {
+          io: true,
+          ..spec(name, Category::Processes, inputs, outputs)
+        }
+      }
+      AtomicType::Random(op) =>
+      {
+        let (name, inputs, outputs) = match op
+        {
+          RandomOp::Int =>
+          {
+            (
+              "Random integer",
+              vec![port("min", [T::Integer]), port("max", [T::Integer])],
+              vec![port("value", [T::Integer])],
+            )
+          }
+          RandomOp::Float =>
+          {
+            (
+              "Random float",
+              vec![optional("min", [T::Float]), optional("max", [T::Float])],
+              vec![port("value", [T::Float])],
+            )
+          }
+          RandomOp::Choice => ("Random choice", vec![array("array")], vec![any("element")]),
+          RandomOp::UuidV4 => ("Random UUID", vec![], vec![string("uuid")]),
+        };
+        spec(name, Category::Random, inputs, outputs)
+      }
+      AtomicType::Record(op) =>
+      {
+        // the ports are the record's fields, which only the graph knows
+        match op
+        {
+          RecordOp::Construct(name) =>
+          {
+            NodeSpec {
+              variadic_inputs: true,
+              ..spec(
+                &format!("Construct {name}"),
+                Category::Objects,
+                vec![any("field")],
+                vec![port("record", [T::Record(name.clone())])],
+              )
+            }
+          }
+          RecordOp::Destructure(name) =>
+          {
+            spec(
+              &format!("Destructure {name}"),
+              Category::Objects,
+              vec![port("record", [T::Record(name.clone()), T::Object])],
+              vec![],
+            )
+          }
+        }
+      }
+      AtomicType::Wrap(op) =>
+      {
+        let optional_of = || T::Optional(Box::new(T::Any));
+        let result_of = || T::Result(Box::new(T::Any), Box::new(T::Any));
+        let wrapped = || port("wrapped", [optional_of(), result_of()]);
+        let (name, inputs, outputs) = match op
+        {
+          WrapOp::Some =>
+          {
+            (
+              "Some",
+              vec![any("value")],
+              vec![port("optional", [optional_of()])],
+            )
+          }
+          WrapOp::Nothing => ("Nothing", vec![], vec![port("optional", [optional_of()])]),
+          WrapOp::Ok =>
+          {
+            (
+              "Ok",
+              vec![any("value")],
+              vec![port("result", [result_of()])],
+            )
+          }
+          WrapOp::Err =>
+          {
+            (
+              "Err",
+              vec![any("error")],
+              vec![port("result", [result_of()])],
+            )
+          }
+          WrapOp::Unwrap => ("Unwrap", vec![wrapped()], vec![any("value")]),
+          WrapOp::UnwrapOr =>
+          {
+            (
+              "Unwrap or",
+              vec![wrapped(), any("fallback")],
+              vec![any("value")],
+            )
+          }
+          WrapOp::Match =>
+          {
+            (
+              "Match",
+              vec![wrapped()],
+              vec![boolean("has_value"), any("value"), any("error")],
+            )
+          }
+        };
+        spec(name, Category::Values, inputs, outputs)
+      }
+      AtomicType::Select =>
+      {
+        spec(
+          "Select",
+          Category::Logic,
+          vec![boolean("condition"), any("if_true"), any("if_false")],
+          vec![any("value")],
+        )
+      }
+      AtomicType::Counter =>
+      {
+        NodeSpec {
+          stateful: true,
+          ..spec(
+            "Counter",
+            Category::Runtime,
+            vec![optional("reset", [T::Boolean])],
+            vec![port("count", [T::Integer])],
+          )
+        }
+      }
+      AtomicType::Stopwatch =>
+      {
+        NodeSpec {
+          stateful: true,
+          ..spec(
+            "Stopwatch",
+            Category::Runtime,
+            vec![optional("reset", [T::Boolean])],
+            vec![port("elapsed", [T::Integer])],
+          )
+        }
+      }
+      AtomicType::Abort =>
+      {
+        spec(
+          "Abort",
+          Category::Runtime,
+          vec![string("message"), optional("code", [T::Integer])],
+          vec![],
+        )
+      }
+      AtomicType::RuntimeInfo =>
+      {
+        spec(
+          "Runtime info",
+          Category::Runtime,
+          vec![],
+          vec![port("info", [T::Object])],
+        )
+      }
+      AtomicType::Env(_) =>
+      {
+        spec(
+          "Environment variable",
+          Category::Runtime,
+          vec![],
+          vec![port("value", [T::String, T::None])],
+        )
+      }
+      AtomicType::Secret(_) => spec("Secret", Category::Runtime, vec![], vec![string("value")]),
+      AtomicType::TempDir =>
+      {
+        NodeSpec {
+          io: true,
+          ..spec("Temp dir", Category::Runtime, vec![], vec![string("path")])
+        }
+      }
+      AtomicType::Expr(_) =>
+      {
+        NodeSpec {
+          variadic_inputs: true,
+          ..spec(
+            "Expression",
+            Category::Math,
+            vec![any("a")],
+            vec![any("value")],
+          )
+        }
+      }
+      AtomicType::JsonParse =>
+      {
+        spec(
+          "Parse JSON",
+          Category::Text,
+          vec![string("json")],
+          vec![any("value")],
+        )
+      }
+      AtomicType::JsonStringify { .. } =>
+      {
+        spec(
+          "Stringify JSON",
+          Category::Text,
+          vec![any("value")],
+          vec![string("json")],
+        )
+      }
+      AtomicType::RegexMatch =>
+      {
+        spec(
+          "Regex match",
+          Category::Text,
+          vec![string("regex"), string("text")],
+          vec![boolean("matches")],
+        )
+      }
+      AtomicType::RegexFind =>
+      {
+        spec(
+          "Regex find",
+          Category::Text,
+          vec![string("regex"), string("text")],
+          vec![port("match", [T::String, T::None])],
+        )
+      }
+      AtomicType::RegexCaptures =>
+      {
+        spec(
+          "Regex captures",
+          Category::Text,
+          vec![string("regex"), string("text")],
+          vec![port("groups", [T::Object, T::Array, T::None])],
+        )
+      }
+    }
+  }
+}
+
+/// A spec for each kind of atomic node, with placeholder arguments where a node takes
+/// them: a value of each basic type, casts to them, each file mode, agent provider,
+/// encoding and so on
+pub fn registry() -> Vec<NodeSpec>
+{
+  let mut nodes = vec![AtomicType::Print, AtomicType::Replace];
+  nodes.extend(
+    [
+      AtomicBinOp::Add,
+      AtomicBinOp::Sub,
+      AtomicBinOp::Mul,
+      AtomicBinOp::Div,
+      AtomicBinOp::Pow,
+      AtomicBinOp::Mod,
+    ]
+    .map(AtomicType::BinOp),
+  );
+  nodes.extend(
+    [
+      AtomicUnaryOp::Neg,
+      AtomicUnaryOp::Abs,
+      AtomicUnaryOp::Sqrt,
+      AtomicUnaryOp::Floor,
+      AtomicUnaryOp::Ceil,
+      AtomicUnaryOp::Round,
+      AtomicUnaryOp::Ln,
+      AtomicUnaryOp::Exp,
+      AtomicUnaryOp::Sin,
+      AtomicUnaryOp::Cos,
+      AtomicUnaryOp::Tan,
+      AtomicUnaryOp::Asin,
+      AtomicUnaryOp::Acos,
+      AtomicUnaryOp::Atan,
+    ]
+    .map(AtomicType::UnaryOp),
+  );
+  nodes.extend(
+    [
+      DataValue::String(String::new()),
+      DataValue::Integer(0),
+      DataValue::Float(0.0),
+      DataValue::Boolean(false),
+    ]
+    .map(AtomicType::Value),
+  );
+  nodes.extend(
+    [
+      ControlFlow::Start,
+      ControlFlow::End,
+      ControlFlow::Loop(LoopNodes::Start),
+      ControlFlow::Loop(LoopNodes::Continue(Uuid::nil())),
+      ControlFlow::If,
+      ControlFlow::Try(Box::new(NodeType::Complex(String::new()))),
+      ControlFlow::Timeout(Box::new(NodeType::Complex(String::new())), 1000),
+      ControlFlow::Parallel,
+    ]
+    .map(AtomicType::Control),
+  );
+  nodes.extend([Variable::Set, Variable::Get].map(|x| AtomicType::Variable(x, String::new())));
+  nodes.extend(
+    [
+      AtomicIo::ConsoleInput,
+      AtomicIo::Open(IoType::TcpSocket),
+      AtomicIo::Open(IoType::TcpListener),
+      AtomicIo::Open(IoType::UdpSocket),
+      AtomicIo::Open(IoType::WebSocket),
+    ]
+    .map(AtomicType::Io),
+  );
+  nodes.extend(
+    [
+      FileMode::Read,
+      FileMode::Write,
+      FileMode::Append,
+      FileMode::Create,
+      FileMode::Truncate,
+    ]
+    .map(|x| AtomicType::Io(AtomicIo::Open(IoType::File(x)))),
+  );
+  nodes.extend(
+    [
+      AtomicIo::Read,
+      AtomicIo::Write,
+      AtomicIo::GetLine,
+      AtomicIo::Accept,
+      AtomicIo::SendTo,
+      AtomicIo::RecvFrom,
+      AtomicIo::SendFrame,
+      AtomicIo::RecvFrame,
+      AtomicIo::Close,
+      AtomicIo::Flush,
+    ]
+    .map(AtomicType::Io),
+  );
+  nodes.extend(
+    [
+      T::String,
+      T::Integer,
+      T::Float,
+      T::Boolean,
+      T::Array,
+      T::Byte,
+    ]
+    .map(AtomicType::Cast),
+  );
+  nodes.push(AtomicType::IsNone);
+  nodes.extend(
+    [
+      AtomicLogic::And,
+      AtomicLogic::Or,
+      AtomicLogic::Xor,
+      AtomicLogic::Not,
+      AtomicLogic::Eq,
+      AtomicLogic::Neq,
+      AtomicLogic::ApproxEq(1e-9),
+    ]
+    .map(AtomicType::LogicalOp),
+  );
+  nodes.extend(
+    [AgentType::OpenAi, AgentType::OpenRouter, AgentType::Mock]
+      .map(|x| AtomicType::AgentOp(AgentOperation::Create(x))),
+  );
+  nodes.extend(
+    [
+      AgentOperation::Send,
+      AgentOperation::Recieve,
+      AgentOperation::ExportHistory,
+      AgentOperation::ImportHistory,
+      AgentOperation::AttachTools(vec![]),
+      AgentOperation::ExportTranscript(TranscriptFormat::Markdown),
+      AgentOperation::ExportTranscript(TranscriptFormat::Json),
+    ]
+    .map(AtomicType::AgentOp),
+  );
+  nodes.extend(
+    [
+      ArrayOp::Sort(SortOrder::Ascending, None),
+      ArrayOp::Sort(SortOrder::Descending, None),
+      ArrayOp::Unique,
+      ArrayOp::Contains,
+      ArrayOp::GroupBy(String::new()),
+      ArrayOp::Union,
+      ArrayOp::Intersection,
+      ArrayOp::Difference,
+      ArrayOp::Aggregate(Aggregate::Sum, None),
+      ArrayOp::Aggregate(Aggregate::Avg, None),
+      ArrayOp::Aggregate(Aggregate::Min, None),
+      ArrayOp::Aggregate(Aggregate::Max, None),
+      ArrayOp::Aggregate(Aggregate::Count, None),
+      ArrayOp::Length,
+      ArrayOp::Index,
+      ArrayOp::Slice,
+      ArrayOp::Append,
+      ArrayOp::Concat,
+      ArrayOp::Reverse,
+      ArrayOp::Flatten,
+      ArrayOp::Split,
+      ArrayOp::Unzip,
+    ]
+    .map(AtomicType::Array),
+  );
+  for encoding in [
+    Encoding::Utf8,
+    Encoding::Base64,
+    Encoding::Base64Url,
+    Encoding::Hex,
+    Encoding::Url,
+  ]
+  {
+    nodes.extend(
+      [
+        BytesOp::Encode(encoding),
+        BytesOp::Decode(encoding),
+        BytesOp::DecodeText(encoding),
+      ]
+      .map(AtomicType::Bytes),
+    );
+  }
+  nodes.extend(
+    [
+      DateTimeOp::Now,
+      DateTimeOp::Format(None),
+      DateTimeOp::Parse(None),
+      DateTimeOp::Add,
+      DateTimeOp::Sub,
+      DateTimeOp::Compare,
+    ]
+    .map(AtomicType::DateTime),
+  );
+  nodes.extend(
+    [
+      FsOp::ListDir,
+      FsOp::Exists,
+      FsOp::Delete,
+      FsOp::Rename,
+      FsOp::MkDir,
+    ]
+    .map(AtomicType::Fs),
+  );
+  nodes.extend([HttpOp::Request, HttpOp::Route(String::new())].map(AtomicType::Http));
+  nodes.extend([IdOp::V5, IdOp::ToString, IdOp::Parse].map(AtomicType::Id));
+  nodes.extend(
+    [
+      ObjectOp::Destructure(vec![]),
+      ObjectOp::GetField(String::new()),
+      ObjectOp::SetField(String::new()),
+      ObjectOp::HasField(String::new()),
+      ObjectOp::Keys,
+    ]
+    .map(AtomicType::Object),
+  );
+  nodes.extend([ProcessOp::Spawn, ProcessOp::Wait].map(AtomicType::Process));
+  nodes.extend(
+    [
+      RandomOp::Int,
+      RandomOp::Float,
+      RandomOp::Choice,
+      RandomOp::UuidV4,
+    ]
+    .map(AtomicType::Random),
+  );
+  nodes.extend(
+    [
+      RecordOp::Construct(String::new()),
+      RecordOp::Destructure(String::new()),
+    ]
+    .map(AtomicType::Record),
+  );
+  nodes.extend(
+    [
+      WrapOp::Some,
+      WrapOp::Nothing,
+      WrapOp::Ok,
+      WrapOp::Err,
+      WrapOp::Unwrap,
+      WrapOp::UnwrapOr,
+      WrapOp::Match,
+    ]
+    .map(AtomicType::Wrap),
+  );
+  nodes.extend([
+    AtomicType::Select,
+    AtomicType::Counter,
+    AtomicType::Stopwatch,
+    AtomicType::Abort,
+    AtomicType::RuntimeInfo,
+    AtomicType::Env(String::new()),
+    AtomicType::Secret(String::new()),
+    AtomicType::TempDir,
+    AtomicType::Expr(String::new()),
+    AtomicType::JsonParse,
+    AtomicType::JsonStringify { pretty: false },
+    AtomicType::RegexMatch,
+    AtomicType::RegexFind,
+    AtomicType::RegexCaptures,
+  ]);
+  nodes.iter().map(AtomicType::spec).collect()
+}
@@ -0,0 +1,109 @@
+//! JSON schemas of the graph format, for the editor to build its node palette from.
+
+use super::{
+  nodes::{AtomicType, Complex, Instance, NodeType},
+  typing::{DataType, DataValue},
+};
+use schemars::{Schema, SchemaGenerator};
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+
+/// The schema of a graph file, a [`Complex`], with every type of the node language
+/// under `$defs`: values, types, instances and each family of atomic nodes.
+pub fn bundled() -> Schema
+{
+  let mut schema = schemars::schema_for!(Complex);
+  // a graph file uses them all already, but the palette should not depend on that
+  let mut generator = SchemaGenerator::default();
+  generator.subschema_for::<DataValue>();
+  generator.subschema_for::<DataType>();
+  generator.subschema_for::<Instance>();
+  generator.subschema_for::<NodeType>();
+  generator.subschema_for::<AtomicType>();
+  let defs = schema
+    .ensure_object()
+    .entry("$defs")
+    .or_insert_with(|| Value::Object(Map::new()));
+  if let Value::Object(defs) = defs
+  {
+    for (name, def) in generator.definitions()
+    {
+      defs.entry(name).or_insert_with(|| def.clone());
+    }
+  }
+  schema
+}
+
+/// The types [`schema_for`] can give: `Complex` and those under the bundle's `$defs`
+pub fn names() -> Vec<String>
+{
+  let bundle = bundled();
+  let defs = bundle.get("$defs").and_then(Value::as_object);
+  std::iter::once("Complex".to_string())
+    .chain(defs.into_iter().flat_map(|x| x.keys().cloned()))
+    .collect()
+}
+
+/// The schema of the type `name` on its own, such as `DataValue` or `ArrayOp`, with
+/// only the `$defs` it refers to. `Complex` gives the whole [`bundled`] schema.
+pub fn schema_for(name: &str) -> Option<Schema>
+{
+  let bundle = bundled();
+  if name == "Complex"
+  {
+    return Some(bundle);
+  }
+  let defs = bundle.get("$defs")?.as_object()?;
+  let mut schema = defs.get(name)?.as_object()?.clone();
+  let mut used = BTreeSet::new();
+  let mut pending = vec![Value::Object(schema.clone())];
+  while let Some(value) = pending.pop()
+  {
+    for reference in references(&value)
+    {
+      if used.insert(reference.clone())
+      {
+        pending.extend(defs.get(&reference).cloned());
+      }
+    }
+  }
+  let mut document = Map::new();
+  document.extend(
+    bundle
+      .get("$schema")
+      .map(|x| ("$schema".to_string(), x.clone())),
+  );
+  document.insert("title".to_string(), Value::String(name.to_string()));
+  document.append(&mut schema);
+  if !used.is_empty()
+  {
+    let used = used
+      .into_iter()
+      .filter_map(|x| Some((x.clone(), defs.get(&x)?.clone())))
+      .collect();
+    document.insert("$defs".to_string(), Value::Object(used));
+  }
+  Some(document.into())
+}
+
+/// The names of the `$defs` that `value` refers to directly
+fn references(value: &Value) -> Vec<String>
+{
+  match value
+  {
+    Value::Object(x) =>
+    {
+      let mut found: Vec<String> = x
+        .get("$ref")
+        .and_then(Value::as_str)
+        .and_then(|x| x.strip_prefix("#/$defs/"))
+        .map(str::to_string)
+        .into_iter()
+        .collect();
+      found.extend(x.values().flat_map(references));
+      found
+    }
+    Value::Array(x) => x.iter().flat_map(references).collect(),
+    _ => vec![],
+  }
+}
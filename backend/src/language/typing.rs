@@ -1,18 +1,26 @@
+use super::dates::DateTime;
 use crate::ai::AgentType;
+use indexmap::IndexMap;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::{
-  collections::HashMap,
+  cmp::Ordering,
   fmt::Display,
   ops::{Add, Div, Mul, Rem, Sub},
 };
 use uuid::Uuid;
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, thiserror::Error)]
 pub enum ArithmaticError
 {
-  InvalidCombo(DataValue, DataValue),
+  #[error("cannot combine {0:?} and {1:?}")]
+  InvalidCombo(Box<DataValue>, Box<DataValue>),
+  #[error("division by zero")]
   DivByZero,
+  #[error("{0:?} is not a number")]
+  NotANumber(DataValue),
+  #[error("{0} is undefined for {1:?}")]
+  Undefined(String, DataValue),
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema, Eq, Hash)]
@@ -24,10 +32,29 @@ pub enum DataType
   Float,
   Boolean,
   Byte,
+  Bytes,
   Handle,
   Object,
+  /// An Object whose values are all of the given type. Its keys are Strings like any
+  /// Object's.
+  Map(Box<DataType>),
+  DateTime,
   Agent(AgentType),
+  /// One of the enums the graph declares, by name. Its values are `String`s holding
+  /// one of the enum's variants.
+  Enum(String),
   None,
+  /// Whatever type the value happens to have. Every value casts to it unchanged, and
+  /// a port declared as it passes validation with anything.
+  Any,
+  /// A value of the given type or nothing, made and taken apart by the `Wrap` nodes.
+  /// Unlike a port that may give None, nothing here is not a value of the inner type.
+  Optional(Box<DataType>),
+  /// Either a value of the first type or an error of the second
+  Result(Box<DataType>, Box<DataType>),
+  /// One of the record types the graph declares or loads from its `types` files, by
+  /// name. Its values are Objects holding the record's fields.
+  Record(String),
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema)]
@@ -40,10 +67,23 @@ pub enum DataValue
   Boolean(bool),
   Byte(u8),
   Array(Vec<DataValue>),
+  /// Raw binary data, as IO reads it. Serialized as an Array of numbers, which reads
+  /// back as an Array
+  Bytes(Vec<u8>),
   Handle(Uuid),
-  Object(HashMap<String, DataValue>),
+  /// Keeps its keys in the order they were added, which is the order they print and
+  /// serialize in
+  Object(IndexMap<String, DataValue>),
   Agent(AgentType, Uuid),
+  /// Only made by nodes and casts, an RFC 3339 String in JSON reads as a String
+  DateTime(DateTime),
   None,
+  /// An `Optional` holding a value or nothing. Serialized as the value or null, which
+  /// reads back as the bare value or None
+  Optional(Option<Box<DataValue>>),
+  /// A `Result`, serialized as `{"Ok": value}` or `{"Err": error}`, which reads back as
+  /// an Object
+  Result(Result<Box<DataValue>, Box<DataValue>>),
 }
 impl Display for DataType
 {
@@ -65,9 +105,15 @@ impl Display for DataValue
       DataValue::Handle(x) => write!(f, "{x}"),
       DataValue::Array(x) => write!(f, "{}", serde_json::to_string(x).unwrap()),
       DataValue::Byte(x) => write!(f, "{x:x}"),
+      DataValue::Bytes(x) => x.iter().try_for_each(|x| write!(f, "{x:02x}")),
       DataValue::Object(x) => write!(f, "{}", serde_json::to_string(x).unwrap()),
       DataValue::Agent(t, id) => write!(f, "{t:?}:{id}"),
+      DataValue::DateTime(x) => write!(f, "{x}"),
       DataValue::None => Ok(()),
+      DataValue::Optional(Some(x)) => write!(f, "Some({x})"),
+      DataValue::Optional(None) => write!(f, "Nothing"),
+      DataValue::Result(Ok(x)) => write!(f, "Ok({x})"),
+      DataValue::Result(Err(e)) => write!(f, "Err({e})"),
     }
   }
 }
@@ -82,12 +128,12 @@ impl Add for DataValue
     {
       (Self::Float(x), Self::Float(y)) => Ok(DataValue::Float(x + y)),
       (Self::Integer(x), Self::Integer(y)) => Ok(DataValue::Integer(x + y)),
-      (Self::String(x), Self::String(y)) => Ok(DataValue::String(x.clone() + &y)),
+      (Self::String(x), Self::String(y)) => Ok(DataValue::String(x.clone() + y)),
       (Self::Float(x), Self::Integer(y)) => Ok(DataValue::Float(x + *y as f64)),
       (Self::Integer(x), Self::Float(y)) => Ok(DataValue::Float(*x as f64 + y)),
       (Self::String(x), y) => Ok(DataValue::String(format!("{x}{y}"))),
       (x, Self::String(y)) => Ok(DataValue::String(format!("{x}{y}"))),
-      _ => Err(ArithmaticError::InvalidCombo(self, rhs)),
+      _ => Err(ArithmaticError::InvalidCombo(self.into(), rhs.into())),
     }
   }
 }
@@ -104,7 +150,7 @@ impl Sub for DataValue
       (Self::Integer(x), Self::Integer(y)) => Ok(DataValue::Integer(x - y)),
       (Self::Float(x), Self::Integer(y)) => Ok(DataValue::Float(x - *y as f64)),
       (Self::Integer(x), Self::Float(y)) => Ok(DataValue::Float(*x as f64 - y)),
-      _ => Err(ArithmaticError::InvalidCombo(self, rhs)),
+      _ => Err(ArithmaticError::InvalidCombo(self.into(), rhs.into())),
     }
   }
 }
@@ -121,7 +167,7 @@ impl Mul for DataValue
       (Self::Integer(x), Self::Integer(y)) => Ok(DataValue::Integer(x * y)),
       (Self::Float(x), Self::Integer(y)) => Ok(DataValue::Float(x * *y as f64)),
       (Self::Integer(x), Self::Float(y)) => Ok(DataValue::Float(*x as f64 * y)),
-      _ => Err(ArithmaticError::InvalidCombo(self, rhs)),
+      _ => Err(ArithmaticError::InvalidCombo(self.into(), rhs.into())),
     }
   }
 }
@@ -178,7 +224,7 @@ impl Div for DataValue
           Ok(DataValue::Float(*x as f64 / y))
         }
       }
-      _ => Err(ArithmaticError::InvalidCombo(self, rhs)),
+      _ => Err(ArithmaticError::InvalidCombo(self.into(), rhs.into())),
     }
   }
 }
@@ -235,7 +281,65 @@ impl Rem for DataValue
           Ok(DataValue::Float(*x as f64 % y))
         }
       }
-      _ => Err(ArithmaticError::InvalidCombo(self, rhs)),
+      _ => Err(ArithmaticError::InvalidCombo(self.into(), rhs.into())),
+    }
+  }
+}
+
+impl DataType
+{
+  /// Whether a value of this type may cast to `to`. Validation rejects `Cast` nodes
+  /// reading a type this says no to, and [`DataValue::try_cast`] fails for the same
+  /// pairs. A yes can still fail at run time, say a String that is not a number.
+  ///
+  /// | from        | to                                                         |
+  /// |-------------|------------------------------------------------------------|
+  /// | any type    | itself and `Any`                                           |
+  /// | `Any`       | any type, decided by the value                             |
+  /// | `None`      | `Boolean`, as false                                        |
+  /// | `Integer`   | `Float`, `String`, `Byte` up to 255, `DateTime` as millis  |
+  /// | `Float`     | `Integer` truncated, `String`                              |
+  /// | `Boolean`   | `String`                                                   |
+  /// | `String`    | `Integer`, `Float`, `Boolean` parsed, `Array` of its characters, `Bytes` as UTF-8, `DateTime` as RFC 3339, an `Enum` |
+  /// | `Array`     | `String` joining its items, `Bytes` of Bytes or Integers   |
+  /// | `Byte`      | `Integer`                                                  |
+  /// | `Bytes`     | `String` as UTF-8, `Array` of Bytes                        |
+  /// | `DateTime`  | `String` as RFC 3339, `Integer` as millis                  |
+  /// | `Object`    | a `Map` whose values all cast, a `Record` it has the fields of |
+  /// | `Record`    | `Object`                                                   |
+  /// | `Map`       | `Object`, a `Map` of a type its values cast to             |
+  /// | `Enum`      | `String`                                                   |
+  /// | `None`      | any `Optional`, as nothing                                 |
+  /// | `Optional`  | an `Optional` of a type its value casts to                 |
+  /// | `Result`    | a `Result` whose value and error types cast                |
+  /// | other types | an `Optional` of a type they cast to, holding the value    |
+  pub fn casts_to(&self, to: &DataType) -> bool
+  {
+    use DataType as T;
+    match (self, to)
+    {
+      _ if self == to => true,
+      (T::Any, _) | (_, T::Any) => true,
+      (T::None, T::Boolean) => true,
+      (T::Integer, T::Float | T::String | T::Byte | T::DateTime) => true,
+      (T::Float, T::Integer | T::String) => true,
+      (T::Boolean, T::String) => true,
+      (
+        T::String,
+        T::Integer | T::Float | T::Boolean | T::Array | T::Bytes | T::DateTime | T::Enum(_),
+      ) => true,
+      (T::Array, T::String | T::Bytes) => true,
+      (T::Byte, T::Integer) => true,
+      (T::Bytes, T::String | T::Array) => true,
+      (T::DateTime, T::String | T::Integer) => true,
+      (T::Object, T::Map(_) | T::Record(_)) | (T::Map(_) | T::Record(_), T::Object) => true,
+      (T::Map(from), T::Map(to)) => from.casts_to(to),
+      (T::Enum(_), T::String) => true,
+      (T::None, T::Optional(_)) => true,
+      (T::Optional(from), T::Optional(to)) => from.casts_to(to),
+      (T::Result(ok, err), T::Result(to_ok, to_err)) => ok.casts_to(to_ok) && err.casts_to(to_err),
+      (from, T::Optional(to)) => from.casts_to(to),
+      _ => false,
     }
   }
 }
@@ -258,12 +362,15 @@ impl DataValue
           Ok(DataValue::Integer(b.pow(e as u32)))
         }
       }
-      (&DataValue::Float(b), &DataValue::Integer(e)) =>
+      (&DataValue::Float(b), &DataValue::Integer(e)) => Ok(DataValue::Float(b.powi(e as i32))),
+      (&DataValue::Integer(b), &DataValue::Float(e)) => Ok(DataValue::Float((b as f64).powf(e))),
+      _ =>
       {
-        Ok(DataValue::Float((b as f64).powi(e as i32)))
+        Err(ArithmaticError::InvalidCombo(
+          self.clone().into(),
+          power.clone().into(),
+        ))
       }
-      (&DataValue::Integer(b), &DataValue::Float(e)) => Ok(DataValue::Float((b as f64).powf(e))),
-      _ => Err(ArithmaticError::InvalidCombo(self.clone(), power.clone())),
     }
   }
   pub fn get_type(&self) -> DataType
@@ -275,29 +382,307 @@ impl DataValue
       DataValue::Float(_) => DataType::Float,
       DataValue::Boolean(_) => DataType::Boolean,
       DataValue::Byte(_) => DataType::Byte,
+      DataValue::Bytes(_) => DataType::Bytes,
       DataValue::Array(_) => DataType::Array,
       DataValue::Handle(_) => DataType::Handle,
       DataValue::Object(_) => DataType::Object,
       DataValue::Agent(t, _) => DataType::Agent(t.clone()),
+      DataValue::DateTime(_) => DataType::DateTime,
       DataValue::None => DataType::None,
+      // the side that is not there could have been anything
+      DataValue::Optional(x) =>
+      {
+        DataType::Optional(Box::new(x.as_ref().map_or(DataType::Any, |x| x.get_type())))
+      }
+      DataValue::Result(Ok(x)) => DataType::Result(Box::new(x.get_type()), Box::new(DataType::Any)),
+      DataValue::Result(Err(e)) =>
+      {
+        DataType::Result(Box::new(DataType::Any), Box::new(e.get_type()))
+      }
     }
   }
 
   pub fn try_cast(&self, to_type: DataType) -> Result<DataValue, (DataType, DataType)>
   {
-    if self.get_type() == to_type
+    if self.get_type() == to_type || to_type == DataType::Any
     {
       return Ok(self.clone());
     }
 
     match (self, &to_type)
     {
+      // enum values are already strings, checking the variant needs the graph's enums
+      (DataValue::String(_), DataType::Enum(_)) => Ok(self.clone()),
+      // and so does checking a record's fields
+      (DataValue::Object(_), DataType::Record(_)) => Ok(self.clone()),
       (DataValue::None, DataType::Boolean) => Ok(DataValue::Boolean(false)),
-      (DataValue::Integer(x), DataType::Float) => Ok(DataValue::Float(x.clone() as f64)),
+      (DataValue::Integer(x), DataType::Float) => Ok(DataValue::Float(*x as f64)),
       (DataValue::Float(x), DataType::Integer) => Ok(DataValue::Integer(x.trunc() as i64)),
+      (DataValue::Integer(_) | DataValue::Float(_) | DataValue::Boolean(_), DataType::String) =>
+      {
+        Ok(DataValue::String(self.to_string()))
+      }
+      (DataValue::String(x), DataType::Integer) =>
+      {
+        x.trim()
+          .parse()
+          .map(DataValue::Integer)
+          .map_err(|_| (DataType::String, to_type))
+      }
+      (DataValue::String(x), DataType::Float) =>
+      {
+        x.trim()
+          .parse()
+          .map(DataValue::Float)
+          .map_err(|_| (DataType::String, to_type))
+      }
+      (DataValue::String(x), DataType::Boolean) =>
+      {
+        match x.trim()
+        {
+          "true" => Ok(DataValue::Boolean(true)),
+          "false" => Ok(DataValue::Boolean(false)),
+          _ => Err((DataType::String, to_type)),
+        }
+      }
+      // Strings split into and join back from their characters, other items joining as
+      // they print
+      (DataValue::String(x), DataType::Array) =>
+      {
+        Ok(DataValue::Array(
+          x.chars()
+            .map(|c| DataValue::String(c.to_string()))
+            .collect(),
+        ))
+      }
+      (DataValue::Array(x), DataType::String) =>
+      {
+        Ok(DataValue::String(x.iter().map(|x| x.to_string()).collect()))
+      }
+      (DataValue::Byte(x), DataType::Integer) => Ok(DataValue::Integer(*x as i64)),
+      (DataValue::Integer(x), DataType::Byte) =>
+      {
+        u8::try_from(*x)
+          .map(DataValue::Byte)
+          .map_err(|_| (DataType::Integer, to_type))
+      }
+      // DateTimes cast to and from RFC 3339 Strings and milliseconds since the epoch
+      (DataValue::String(x), DataType::DateTime) =>
+      {
+        DateTime::parse_rfc3339(x)
+          .map(DataValue::DateTime)
+          .map_err(|_| (DataType::String, to_type))
+      }
+      (DataValue::Integer(x), DataType::DateTime) =>
+      {
+        DateTime::from_millis(*x)
+          .map(DataValue::DateTime)
+          .ok_or((DataType::Integer, to_type))
+      }
+      (DataValue::DateTime(x), DataType::String) => Ok(DataValue::String(x.to_string())),
+      (DataValue::DateTime(x), DataType::Integer) => Ok(DataValue::Integer(x.millis())),
+      // Bytes cast to and from UTF-8 Strings and Arrays of Bytes or Integers up to 255
+      (DataValue::String(x), DataType::Bytes) => Ok(DataValue::Bytes(x.as_bytes().to_vec())),
+      (DataValue::Bytes(x), DataType::String) =>
+      {
+        String::from_utf8(x.clone())
+          .map(DataValue::String)
+          .map_err(|_| (DataType::Bytes, to_type))
+      }
+      (DataValue::Array(x), DataType::Bytes) =>
+      {
+        x.iter()
+          .map(|x| {
+            match x
+            {
+              DataValue::Byte(b) => Some(*b),
+              DataValue::Integer(i) => u8::try_from(*i).ok(),
+              _ => None,
+            }
+          })
+          .collect::<Option<_>>()
+          .map(DataValue::Bytes)
+          .ok_or((DataType::Array, to_type))
+      }
+      (DataValue::Bytes(x), DataType::Array) =>
+      {
+        Ok(DataValue::Array(
+          x.iter().copied().map(DataValue::Byte).collect(),
+        ))
+      }
+      // every value is cast, keeping the keys and their order
+      (DataValue::Object(fields), DataType::Map(value_type)) =>
+      {
+        fields
+          .iter()
+          .map(|(k, v)| Ok((k.clone(), v.try_cast((**value_type).clone())?)))
+          .collect::<Result<_, (DataType, DataType)>>()
+          .map(DataValue::Object)
+          .map_err(|_| (DataType::Object, to_type.clone()))
+      }
+      (DataValue::None, DataType::Optional(_)) => Ok(DataValue::Optional(None)),
+      (DataValue::Optional(x), DataType::Optional(inner)) =>
+      {
+        match x
+        {
+          Some(x) =>
+          {
+            x.try_cast((**inner).clone())
+              .map(|x| DataValue::Optional(Some(Box::new(x))))
+              .map_err(|_| (self.get_type(), to_type.clone()))
+          }
+          None => Ok(self.clone()),
+        }
+      }
+      (DataValue::Result(x), DataType::Result(ok, err)) =>
+      {
+        match x
+        {
+          Ok(x) =>
+          {
+            x.try_cast((**ok).clone())
+              .map(|x| DataValue::Result(Ok(Box::new(x))))
+          }
+          Err(e) =>
+          {
+            e.try_cast((**err).clone())
+              .map(|e| DataValue::Result(Err(Box::new(e))))
+          }
+        }
+        .map_err(|_| (self.get_type(), to_type.clone()))
+      }
+      (_, DataType::Optional(inner)) =>
+      {
+        self
+          .try_cast((**inner).clone())
+          .map(|x| DataValue::Optional(Some(Box::new(x))))
+          .map_err(|_| (self.get_type(), to_type.clone()))
+      }
       _ => Err((self.get_type(), to_type)),
     }
   }
+
+  /// Equality used by the `Eq`, `Neq` and `ApproxEq` nodes.
+  ///
+  /// - `Integer` and `Float` compare by numeric value, floats being equal when they
+  ///   differ by at most `epsilon`. `NaN` is never equal to anything, itself included.
+  ///   An `Integer` is compared with a whole `Float` exactly, and with any other after
+  ///   converting it to a float, which loses precision past 2^53.
+  /// - `None` is only equal to `None`, it is not cast to `false` here.
+  /// - `Handle`s are equal when they refer to the same registered IO object, and
+  ///   `Agent`s when they are the same agent instance; two handles opened on the same
+  ///   file, or two agents created with the same arguments, are not equal.
+  /// - `Array`s and `Object`s compare element-wise with the same rules, as do the
+  ///   values in `Optional`s and `Result`s.
+  /// - Any other mix of types is unequal.
+  pub fn equals(&self, other: &Self, epsilon: f64) -> bool
+  {
+    match (self, other)
+    {
+      (DataValue::Integer(a), DataValue::Integer(b)) => a == b,
+      (DataValue::Float(a), DataValue::Float(b)) => a == b || (a - b).abs() <= epsilon,
+      (DataValue::Integer(a), DataValue::Float(b))
+      | (DataValue::Float(b), DataValue::Integer(a)) =>
+      {
+        if b.fract() == 0.0
+        {
+          // as integers, as Integers past 2^53 have no float of the same value
+          return i128::from(*a).abs_diff(*b as i128) as f64 <= epsilon;
+        }
+        let a = *a as f64;
+        a == *b || (a - b).abs() <= epsilon
+      }
+      (DataValue::Handle(a), DataValue::Handle(b)) => a == b,
+      (DataValue::Agent(_, a), DataValue::Agent(_, b)) => a == b,
+      (DataValue::Array(a), DataValue::Array(b)) =>
+      {
+        a.len() == b.len() && a.iter().zip(b).all(|(x, y)| x.equals(y, epsilon))
+      }
+      (DataValue::Object(a), DataValue::Object(b)) =>
+      {
+        a.len() == b.len()
+          && a
+            .iter()
+            .all(|(k, x)| b.get(k).is_some_and(|y| x.equals(y, epsilon)))
+      }
+      (DataValue::None, DataValue::None) => true,
+      (DataValue::Optional(Some(a)), DataValue::Optional(Some(b)))
+      | (DataValue::Result(Ok(a)), DataValue::Result(Ok(b)))
+      | (DataValue::Result(Err(a)), DataValue::Result(Err(b))) => a.equals(b, epsilon),
+      _ => self == other,
+    }
+  }
+
+  /// Ordering used by `Sort`. Numbers compare by value across `Integer` and `Float`,
+  /// `String`s and `Bytes` lexicographically, `DateTime`s earliest first and `false`
+  /// sorts before `true`. Any other pair, or a `NaN`, has no ordering.
+  pub fn compare(&self, other: &Self) -> Option<Ordering>
+  {
+    match (self, other)
+    {
+      (DataValue::Integer(a), DataValue::Integer(b)) => Some(a.cmp(b)),
+      (DataValue::Float(a), DataValue::Float(b)) => a.partial_cmp(b),
+      (DataValue::Integer(a), DataValue::Float(b)) => (*a as f64).partial_cmp(b),
+      (DataValue::Float(a), DataValue::Integer(b)) => a.partial_cmp(&(*b as f64)),
+      (DataValue::String(a), DataValue::String(b)) => Some(a.cmp(b)),
+      (DataValue::Boolean(a), DataValue::Boolean(b)) => Some(a.cmp(b)),
+      (DataValue::Byte(a), DataValue::Byte(b)) => Some(a.cmp(b)),
+      (DataValue::Bytes(a), DataValue::Bytes(b)) => Some(a.cmp(b)),
+      (DataValue::DateTime(a), DataValue::DateTime(b)) => Some(a.cmp(b)),
+      _ => None,
+    }
+  }
+  /// Follows a `.` separated key path through nested Objects, with numeric parts
+  /// indexing into Arrays.
+  pub fn get_path(&self, path: &str) -> Option<&DataValue>
+  {
+    path.split('.').try_fold(self, |value, key| {
+      match value
+      {
+        DataValue::Object(fields) => fields.get(key),
+        DataValue::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+        _ => None,
+      }
+    })
+  }
+  /// Sets the value at a key path as read by [`Self::get_path`], adding Objects for keys
+  /// missing along the way. Returns false if the path runs into a value that is not an
+  /// Object or Array, or past the end of an Array.
+  pub fn set_path(&mut self, path: &str, value: DataValue) -> bool
+  {
+    let (key, rest) = match path.split_once('.')
+    {
+      Some((key, rest)) => (key, Some(rest)),
+      None => (path, None),
+    };
+    let slot = match self
+    {
+      DataValue::Object(fields) =>
+      {
+        fields
+          .entry(key.to_string())
+          .or_insert_with(|| DataValue::Object(IndexMap::new()))
+      }
+      DataValue::Array(items) =>
+      {
+        match key.parse::<usize>().ok().and_then(|i| items.get_mut(i))
+        {
+          Some(x) => x,
+          None => return false,
+        }
+      }
+      _ => return false,
+    };
+    match rest
+    {
+      Some(rest) => slot.set_path(rest, value),
+      None =>
+      {
+        *slot = value;
+        true
+      }
+    }
+  }
   pub fn is_none(&self) -> bool
   {
     *self == DataValue::None
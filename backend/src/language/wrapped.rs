@@ -0,0 +1,90 @@
+use super::{
+  nodes::NodeType,
+  typing::{DataType, DataValue},
+};
+use crate::eval::EvalError;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Makes and takes apart `Optional` and `Result` values. The taking apart nodes read
+/// either kind, a Result's error standing where an Optional has nothing.
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, PartialEq)]
+pub enum WrapOp
+{
+  /// An Optional holding the value on port 0
+  Some,
+  /// An Optional holding nothing
+  Nothing,
+  /// A Result holding the value on port 0
+  Ok,
+  /// A Result holding the error on port 0
+  Err,
+  /// The value in an Optional or Result, failing with `UnwrapFailed` if it holds
+  /// nothing or an error
+  Unwrap,
+  /// The value in an Optional or Result, or the fallback on port 1 if it holds nothing
+  /// or an error
+  UnwrapOr,
+  /// Outputs whether an Optional or Result holds a value, the value and the error,
+  /// None standing in for whichever is not there
+  Match,
+}
+
+/// The value in an Optional or Result, or what stands in its place
+fn open(wrapped: DataValue) -> Result<Result<DataValue, Option<DataValue>>, EvalError>
+{
+  match wrapped
+  {
+    DataValue::Optional(x) => Ok(x.map(|x| *x).ok_or(None)),
+    DataValue::Result(x) => Ok(x.map(|x| *x).map_err(|e| Some(*e))),
+    other =>
+    {
+      Err(EvalError::IncorrectTyping {
+        got: vec![other.get_type()],
+        expected: vec![
+          DataType::Optional(Box::new(DataType::Any)),
+          DataType::Result(Box::new(DataType::Any), Box::new(DataType::Any)),
+        ],
+      })
+    }
+  }
+}
+
+impl NodeType
+{
+  pub(super) fn eval_wrap(op: WrapOp, inputs: Vec<DataValue>) -> Result<Vec<DataValue>, EvalError>
+  {
+    let mut inputs = inputs.into_iter();
+    let mut next = || inputs.next().ok_or(EvalError::IncorrectInputCount);
+    let output = match op
+    {
+      WrapOp::Some => DataValue::Optional(Some(Box::new(next()?))),
+      WrapOp::Nothing => DataValue::Optional(None),
+      WrapOp::Ok => DataValue::Result(Ok(Box::new(next()?))),
+      WrapOp::Err => DataValue::Result(Err(Box::new(next()?))),
+      WrapOp::Unwrap => open(next()?)?.map_err(EvalError::UnwrapFailed)?,
+      WrapOp::UnwrapOr =>
+      {
+        let wrapped = next()?;
+        let fallback = next()?;
+        open(wrapped)?.unwrap_or(fallback)
+      }
+      WrapOp::Match =>
+      {
+        return Ok(match open(next()?)?
+        {
+          Ok(x) => vec![DataValue::Boolean(true), x, DataValue::None],
+          Err(e) =>
+          {
+            vec![
+              DataValue::Boolean(false),
+              DataValue::None,
+              e.unwrap_or(DataValue::None),
+            ]
+          }
+        });
+      }
+    };
+    Ok(vec![output])
+  }
+}
@@ -0,0 +1,53 @@
+//! The AgentNodes engine: loading graphs saved by the editor and running them.
+//!
+//! A graph file deserializes to a [`Complex`]. [`Evaluator::new`] loads one and
+//! [`Evaluator::instantiate`] starts it with its inputs; [`Evaluator::get_outputs`]
+//! then waits for its end node, and [`Evaluator::shutdown`] stops everything it
+//! started. Values passed in and out are [`DataValue`]s and failures are
+//! [`EvalError`]s.
+//!
+//! ```no_run
+//! use agent_nodes_core::{Config, DataValue, Evaluator, NodeStateLogger};
+//!
+//! # async fn run() -> Result<(), agent_nodes_core::EvalError> {
+//! let graph = Evaluator::<NodeStateLogger, NodeStateLogger>::new(
+//!   "examples/subgraphs/double.json".to_string(),
+//!   None,
+//!   None,
+//!   None,
+//! )?;
+//! graph.set_config(Config::default()).await;
+//! let instance = graph.instantiate(vec![DataValue::Integer(21)]).await;
+//! let outputs = instance.get_outputs().await?;
+//! instance.shutdown().await;
+//! assert_eq!(outputs, vec![DataValue::Integer(42)]);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The hosted agent providers, HTTP and subprocess support are behind the `ai`, `http`
+//! and `subprocess` cargo features, all on by default. [`capabilities::compiled_in`]
+//! reports which a build has.
+//!
+//! Hosts in other languages can load the crate as a shared library and drive graphs
+//! through the C functions in [`ffi`].
+
+pub mod ai;
+pub mod capabilities;
+pub mod config;
+pub mod eval;
+pub mod ffi;
+pub mod http;
+pub mod language;
+pub mod logging;
+pub mod secrets;
+
+pub use capabilities::Capability;
+pub use config::Config;
+pub use eval::{EvalError, Evaluator, NodeHooks};
+pub use language::{
+  builder::GraphBuilder,
+  nodes::{Complex, FORMAT_VERSION},
+  typing::{DataType, DataValue},
+};
+pub use logging::node_state_logger::NodeStateLogger;
@@ -1,3 +1,4 @@
 pub mod logger_trait;
 pub mod node_state_logger;
+pub mod subscriber;
 pub use logger_trait::Logger;
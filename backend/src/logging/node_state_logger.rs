@@ -1,4 +1,4 @@
-use crate::eval::NodeState;
+use crate::eval::{EvalError, NodeState};
 use crate::language::nodes::NodeType;
 use crate::logging::Logger;
 use futures::{Sink, SinkExt};
@@ -16,6 +16,20 @@ struct SendInfo
   state: String,
 }
 
+#[derive(Serialize)]
+struct ReloadInfo<'a>
+{
+  reloaded: &'a str,
+}
+
+#[derive(Serialize)]
+struct ErrorInfo<'a>
+{
+  node_id: Uuid,
+  error: &'a EvalError,
+}
+
+#[allow(dead_code)]
 pub struct NodeStateLogger
 {
   sender: UnboundedSender<String>,
@@ -25,6 +39,7 @@ pub struct NodeStateLogger
 
 unsafe impl Sync for NodeStateLogger {}
 
+#[allow(dead_code)]
 impl NodeStateLogger
 {
   async fn runner_loop<
@@ -58,7 +73,7 @@ impl NodeStateLogger
   {
     tokio::select! {
       _ = canceled.cancelled() => {
-        println!("Closing down runner");
+        tracing::debug!("closing down runner");
         reciever.close();
         while let Some(msg) = reciever.recv().await
         {
@@ -103,6 +118,18 @@ impl NodeStateLogger
     .unwrap()
   }
 
+  /// Reports a node failing, for the UI to show next to it
+  pub fn error_string(node_id: Uuid, error: &EvalError) -> String
+  {
+    serde_json::to_string(&ErrorInfo { node_id, error }).unwrap()
+  }
+
+  /// Reports the graph at `path` being loaded again after its file changed
+  pub fn reload_string(path: &str) -> String
+  {
+    serde_json::to_string(&ReloadInfo { reloaded: path }).unwrap()
+  }
+
   pub async fn shutdown(&self)
   {
     self.my_cancel.cancel();
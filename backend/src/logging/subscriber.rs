@@ -0,0 +1,55 @@
+use serde::Deserialize;
+use std::str::FromStr;
+use tracing_subscriber::EnvFilter;
+
+/// How the engine's log lines are written to stderr
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat
+{
+  /// One human readable line per event
+  #[default]
+  Text,
+  /// One JSON object per line, holding the event's fields and, under `span`, the name
+  /// and fields of the innermost span it happened in, such as the node being evaluated
+  Json,
+}
+
+impl FromStr for LogFormat
+{
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err>
+  {
+    match s
+    {
+      "text" => Ok(Self::Text),
+      "json" => Ok(Self::Json),
+      _ => Err(format!("expected text or json, got `{s}`")),
+    }
+  }
+}
+
+/// Sends the engine's `tracing` events to stderr, keeping stdout for the graph's own
+/// output. `filter` takes `RUST_LOG` style directives such as `debug` or
+/// `agent_nodes_core::eval=trace`. Fails if a subscriber is already set.
+pub fn init(filter: &str, format: LogFormat) -> Result<(), String>
+{
+  let builder = tracing_subscriber::fmt()
+    .with_env_filter(EnvFilter::try_new(filter).map_err(|e| e.to_string())?)
+    .with_writer(std::io::stderr);
+  match format
+  {
+    LogFormat::Text => builder.try_init(),
+    LogFormat::Json =>
+    {
+      builder
+        .json()
+        .flatten_event(true)
+        .with_current_span(true)
+        .with_span_list(false)
+        .try_init()
+    }
+  }
+  .map_err(|e| e.to_string())
+}
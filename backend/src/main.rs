@@ -1,60 +1,800 @@
-#![feature(fn_traits)]
-#![feature(get_mut_unchecked)]
-
-mod ai;
 mod cli;
-mod eval;
-mod language;
-mod logging;
-
-use crate::logging::node_state_logger::NodeStateLogger;
-use clap::Parser;
-use cli::Cli;
-use eval::Evaluator;
-use std::sync::Arc;
-use tokio::net::TcpStream;
+mod console;
+mod harness;
+mod repl;
+mod serve;
+mod watch;
+
+use agent_nodes_core::{
+  config::{Project, PROJECT_FILE},
+  eval::{Checkpoint, Metrics},
+  http::{HttpResponse, HttpServer, RouteHandler},
+  language::{records, registry, schema},
+  logging::subscriber::{self, LogFormat},
+  Complex, Config, DataValue, EvalError, Evaluator, NodeStateLogger,
+};
+use cli::{Cli, Command, CompileArgs, OutputFormat, RunArgs};
+use std::{
+  collections::HashMap,
+  path::{Path, PathBuf},
+  sync::Arc,
+  time::Duration,
+};
 use tokio::signal::ctrl_c;
-use tokio_websockets::ClientBuilder;
+#[cfg(unix)]
+use tokio::signal::unix::{signal, SignalKind};
+
+fn main()
+{
+  dotenvy::dotenv().ok();
+  let cli = Cli::parse_args();
+  let deterministic = match (&cli.command, &cli.run)
+  {
+    (Some(Command::Run(args)), _) => args.deterministic,
+    (None, Some(args)) => args.deterministic,
+    _ => false,
+  };
+  let runtime = match deterministic
+  {
+    true => tokio::runtime::Builder::new_current_thread(),
+    false => tokio::runtime::Builder::new_multi_thread(),
+  }
+  .enable_all()
+  .build()
+  .unwrap_or_else(|e| {
+    eprintln!("failed to start the runtime: {e}");
+    std::process::exit(2)
+  });
+  runtime.block_on(dispatch(cli));
+}
+
+async fn dispatch(cli: Cli)
+{
+  match (cli.command, cli.run)
+  {
+    (Some(Command::Run(args)), _) => run(*args).await,
+    (None, Some(args)) => run(args).await,
+    (
+      Some(Command::Describe {
+        filename,
+        records_schema,
+      }),
+      _,
+    ) => describe(&filename, records_schema),
+    (Some(Command::DescribeNodes { json }), _) => describe_nodes(json),
+    (Some(Command::Eval(args)), _) =>
+    {
+      let config = load_config(args.config.clone(), &args.filename);
+      init_logging(None, None, &config);
+      harness::evaluate(args, config).await
+    }
+    (Some(Command::Compare(args)), _) =>
+    {
+      let config = load_config(args.config.clone(), &args.a);
+      init_logging(None, None, &config);
+      harness::compare(args, config).await
+    }
+    (Some(Command::Test(args)), _) =>
+    {
+      let config = load_config(args.config.clone(), &args.manifest);
+      init_logging(None, None, &config);
+      harness::test(args, config).await
+    }
+    (Some(Command::Watch(args)), _) =>
+    {
+      let config = load_config(args.config.clone(), &args.filename);
+      init_logging(None, None, &config);
+      watch::watch(args, config).await
+    }
+    (Some(Command::Repl(args)), _) =>
+    {
+      let config = load_config(args.config.clone(), &args.filename);
+      init_logging(None, None, &config);
+      repl::repl(args, config).await
+    }
+    (Some(Command::Serve(args)), _) =>
+    {
+      let config = args.config.as_deref().map(|path| {
+        Config::load(path).unwrap_or_else(|e| {
+          eprintln!("{e}");
+          std::process::exit(2)
+        })
+      });
+      init_logging(None, None, config.as_ref().unwrap_or(&Config::default()));
+      serve::serve(args, config).await
+    }
+    (Some(Command::Compile(args)), _) =>
+    {
+      let config = load_config(args.config.clone(), &args.filename);
+      init_logging(None, None, &config);
+      compile(args, config).await
+    }
+    (Some(Command::Completions { shell }), _) => Cli::print_completions(shell),
+    // clap requires a graph unless --print-schemas was given
+    (None, None) => print_schema(cli.print_schemas.as_deref().unwrap_or("Complex")),
+  }
+}
+
+fn describe(filename: &Path, records_schema: bool)
+{
+  match Complex::load(&filename.to_string_lossy())
+  {
+    Ok(graph) if records_schema =>
+    {
+      let schema = records::json_schema(&graph.records);
+      println!("{}", serde_json::to_string_pretty(&schema).unwrap());
+    }
+    Ok(graph) => print!("{}", graph.describe()),
+    Err(e) =>
+    {
+      eprintln!("{}: {e}", filename.display());
+      std::process::exit(2);
+    }
+  }
+}
+
+/// Writes the graph and the Complex graphs it uses to one bundle
+async fn compile(args: CompileArgs, config: Config)
+{
+  let output = args
+    .output
+    .unwrap_or_else(|| args.filename.with_extension("bundle"));
+  let bundle = async {
+    let graph = Evaluator::<NodeStateLogger, NodeStateLogger>::new(
+      args.filename.to_string_lossy().into_owned(),
+      None,
+      None,
+      None,
+    )?;
+    graph.set_config(config).await;
+    graph.bundle().await
+  };
+  let bundle = bundle.await.unwrap_or_else(|e| {
+    eprintln!("{}: {e}", args.filename.display());
+    std::process::exit(2)
+  });
+  if let Err(e) = bundle.save(&output)
+  {
+    eprintln!("{e}");
+    std::process::exit(2);
+  }
+  eprintln!(
+    "compiled {} graph(s) into {}",
+    bundle.graphs.len(),
+    output.display()
+  );
+}
+
+fn describe_nodes(json: bool)
+{
+  let nodes = registry::registry();
+  if json
+  {
+    println!("{}", serde_json::to_string_pretty(&nodes).unwrap());
+  }
+  else
+  {
+    for node in nodes
+    {
+      println!("{node}");
+    }
+  }
+}
+
+fn print_schema(name: &str)
+{
+  match schema::schema_for(name)
+  {
+    Some(schema) => println!("{}\n", serde_json::to_string_pretty(&schema).unwrap()),
+    None =>
+    {
+      eprintln!(
+        "--print-schemas: no type `{name}`, expected one of {}",
+        schema::names().join(", ")
+      );
+      std::process::exit(2);
+    }
+  }
+}
+
+/// The config file at `explicit` if given, which wins over one found next to the graph.
+fn find_config(explicit: Option<PathBuf>, graph: &Path) -> Option<PathBuf>
+{
+  explicit.or_else(|| Config::discover(graph.parent().unwrap_or(Path::new(""))))
+}
+
+/// The config [`find_config`] finds
+fn load_config(explicit: Option<PathBuf>, graph: &Path) -> Config
+{
+  read_config(find_config(explicit, graph))
+}
+
+/// The config at `path`, or the default one if there is none
+fn read_config(path: Option<PathBuf>) -> Config
+{
+  match path
+  {
+    Some(path) =>
+    {
+      Config::load(&path).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(2)
+      })
+    }
+    None => Config::default(),
+  }
+}
+
+/// Writes the graph's outputs, or what it failed with, as `format` to `file` or stdout.
+/// JSON also keys the outputs by the graph's `output_names`, where it has them.
+fn write_outputs(
+  outputs: &Result<Vec<DataValue>, EvalError>,
+  names: &[String],
+  format: OutputFormat,
+  file: Option<&Path>,
+)
+{
+  let json = || {
+    match outputs
+    {
+      Ok(outputs) if names.iter().any(|x| !x.is_empty()) =>
+      {
+        let named: serde_json::Map<_, _> = names
+          .iter()
+          .zip(outputs)
+          .filter(|(name, _)| !name.is_empty())
+          .map(|(name, x)| (name.clone(), serde_json::json!(x)))
+          .collect();
+        serde_json::json!({ "outputs": outputs, "named": named })
+      }
+      Ok(outputs) => serde_json::json!({ "outputs": outputs }),
+      Err(e) => serde_json::json!({ "error": e }),
+    }
+  };
+  let text = match format
+  {
+    OutputFormat::Debug => format!("{outputs:?}\n"),
+    OutputFormat::Json => format!("{}\n", json()),
+    OutputFormat::Pretty => format!("{:#}\n", json()),
+    OutputFormat::Raw =>
+    {
+      match outputs
+      {
+        Ok(outputs) => outputs.iter().map(|x| format!("{x}\n")).collect(),
+        // left to what is printed to stderr
+        Err(_) => String::new(),
+      }
+    }
+  };
+  match file
+  {
+    Some(path) =>
+    {
+      if let Err(e) = std::fs::write(path, text)
+      {
+        eprintln!("{}: {e}", path.display());
+      }
+    }
+    None => print!("{text}"),
+  }
+}
+
+/// The inputs in a `--input-file` or given with `--stdin-input`, keyed like `--input`
+/// flags
+fn parse_inputs(text: &str) -> Result<Vec<(String, DataValue)>, String>
+{
+  let value = |x| serde_json::from_value::<DataValue>(x).map_err(|e| e.to_string());
+  match serde_json::from_str(text).map_err(|e| e.to_string())?
+  {
+    serde_json::Value::Array(inputs) =>
+    {
+      inputs
+        .into_iter()
+        .enumerate()
+        .map(|(port, x)| Ok((port.to_string(), value(x)?)))
+        .collect()
+    }
+    serde_json::Value::Object(inputs) =>
+    {
+      inputs
+        .into_iter()
+        .map(|(key, x)| Ok((key, value(x)?)))
+        .collect()
+    }
+    _ => Err("expected an array of inputs or an object of them".to_string()),
+  }
+}
 
-#[tokio::main]
-async fn main()
+/// The values for `graph`'s inputs from inputs `given` by port number or name, the
+/// last given winning. Every port without a default in the graph needs one, as the start node
+/// would give nothing otherwise.
+fn graph_inputs(
+  graph: &Evaluator<NodeStateLogger, NodeStateLogger>,
+  given: &[(String, DataValue)],
+) -> Result<Vec<DataValue>, String>
 {
-  dotenvy::dotenv().unwrap();
-  let cli = Cli::parse();
+  let types = graph.input_types();
+  let mut values = vec![None; types.len()];
+  for (key, value) in given
+  {
+    let port = graph.input_port(key).ok_or_else(|| {
+      match types.len()
+      {
+        0 => format!("no input `{key}`, the graph takes none"),
+        n => format!("no input `{key}`, the graph's are numbered 0 to {}", n - 1),
+      }
+    })?;
+    values[port] = Some(value.clone());
+  }
+  values
+    .into_iter()
+    .zip(types)
+    .enumerate()
+    .map(|(port, (value, data_type))| {
+      value
+        .or_else(|| graph.input_default(port).cloned())
+        .ok_or_else(|| {
+          match graph.input_name(port)
+          {
+            Some(name) =>
+            {
+              format!(
+                "input {port} {name} ({data_type}) has no value, give it with --input {name}=VALUE"
+              )
+            }
+            None =>
+            {
+              format!("input {port} ({data_type}) has no value, give it with --input {port}=VALUE")
+            }
+          }
+        })
+    })
+    .collect()
+}
 
-  if cli.print_schemas
+/// Logs to stderr at the flags' level and format, falling back to the config's.
+fn init_logging(level: Option<String>, format: Option<LogFormat>, config: &Config)
+{
+  let level = level
+    .or_else(|| config.log.level.clone())
+    .unwrap_or_else(|| "info".to_string());
+  if let Err(e) = subscriber::init(&level, format.unwrap_or(config.log.format))
   {
-    println!(
-      "{}\n",
-      serde_json::to_string_pretty(&schemars::schema_for!(crate::language::nodes::Complex))
-        .unwrap()
-    );
-    return;
+    eprintln!("--log-level: {e}");
+    std::process::exit(2)
   }
+}
+
+async fn run(args: RunArgs)
+{
+  let manifest = Project::find(&args.filename);
+  let project = manifest.as_ref().map(|path| {
+    Project::load(path).unwrap_or_else(|e| {
+      eprintln!("{e}");
+      std::process::exit(2)
+    })
+  });
+  let filename = match &project
+  {
+    Some(project) => project.entry.clone(),
+    None => args.filename,
+  };
+  // a project's agentnodes.toml is its config, and a project.json uses the closest
+  let explicit = args.config.or_else(|| {
+    manifest
+      .clone()
+      .filter(|x| x.file_name() != Some(PROJECT_FILE.as_ref()))
+  });
+  let config_path = find_config(explicit, manifest.as_deref().unwrap_or(&filename));
+  let mut config = read_config(config_path.clone());
+  init_logging(args.log_level, args.log_format, &config);
+  let read = match (&args.input_file, args.stdin_input)
+  {
+    (Some(path), _) => Some((path.display().to_string(), std::fs::read_to_string(path))),
+    (None, true) =>
+    {
+      Some((
+        "--stdin-input".to_string(),
+        std::io::read_to_string(std::io::stdin()),
+      ))
+    }
+    (None, false) => None,
+  };
+  let mut given = match read
+  {
+    Some((source, text)) =>
+    {
+      let inputs = text
+        .map_err(|e| e.to_string())
+        .and_then(|x| parse_inputs(&x));
+      inputs.unwrap_or_else(|e| {
+        eprintln!("{source}: {e}");
+        std::process::exit(2)
+      })
+    }
+    None => vec![],
+  };
+  given.extend(args.inputs);
+  let project_inputs = match (&project, &manifest)
+  {
+    (Some(project), Some(manifest)) =>
+    {
+      let inputs = project
+        .apply(&mut config)
+        .and_then(|()| project.input_values(&given));
+      Some(inputs.unwrap_or_else(|e| {
+        eprintln!("{}: {e}", manifest.display());
+        std::process::exit(2)
+      }))
+    }
+    _ => None,
+  };
+  let print_output = args.print_output || config.log.print_output;
+  let task_stats = args.task_stats || config.log.task_stats;
 
   // console_subscriber::init();
   let eval = Evaluator::<NodeStateLogger, NodeStateLogger>::new(
-    cli.filename.unwrap().to_str().unwrap().to_string(),
+    filename.to_str().unwrap().to_string(),
     None,
     None,
     None,
   )
-  .unwrap();
-  let instance = eval.instantiate(vec![]).await;
+  .unwrap_or_else(|e| {
+    eprintln!("{}: {e}", filename.display());
+    std::process::exit(2)
+  });
+  let output_names = eval.output_names().to_vec();
+  eval.set_params(args.params.into_iter().collect()).await;
+  eval.set_keep_workspace(args.keep_workspace).await;
+  if let Some(seed) = args.seed
+  {
+    eval.set_seed(seed).await;
+  }
+  eval.set_deterministic(args.deterministic).await;
+  if args.dry_run
+  {
+    config.dry_run.enabled = true;
+  }
+  // what nodes the sandbox denies are refused by, flags first as they deny the most
+  let refused_by = match (args.dry_run, args.offline, args.untrusted, &manifest)
+  {
+    (true, ..) => "--dry-run".to_string(),
+    (_, true, ..) => "--offline".to_string(),
+    (_, _, true, _) => "--untrusted".to_string(),
+    (.., Some(manifest)) => manifest.display().to_string(),
+    _ =>
+    {
+      config_path
+        .as_ref()
+        .map_or("sandbox".to_string(), |x| x.display().to_string())
+    }
+  };
+  if args.offline || args.dry_run
+  {
+    config.sandbox.allow_network = false;
+  }
+  let sandbox = &mut config.sandbox;
+  if args.untrusted
+  {
+    sandbox.allow_processes = false;
+    sandbox.paths = Some(vec![]);
+    sandbox.hosts = Some(vec![]);
+    sandbox.agents = Some(vec![]);
+  }
+  if !args.allow_paths.is_empty()
+  {
+    sandbox.paths = Some(args.allow_paths.clone());
+  }
+  if !args.allow_hosts.is_empty()
+  {
+    sandbox.hosts = Some(args.allow_hosts.clone());
+  }
+  if !args.allow_agents.is_empty()
+  {
+    sandbox.agents = Some(args.allow_agents.clone());
+  }
+  let grace = config.timeouts.shutdown_grace();
+  // set first, as Complex graphs are found through its search paths
+  eval.set_config(config).await;
+  let denied = eval.clone().check_before_run().await.unwrap_or_else(|e| {
+    eprintln!("{}: {e}", filename.display());
+    std::process::exit(2)
+  });
+  for (path, node, permission) in &denied
+  {
+    eprintln!("{refused_by}: node {node} in {path} needs {permission}");
+  }
+  if !denied.is_empty()
+  {
+    std::process::exit(2);
+  }
+  if let Some(deadline) = args.deadline
+  {
+    eval.set_deadline(deadline).await;
+  }
+  let trace = match args.trace
+  {
+    Some(_) => Some(eval.record_trace().await),
+    None => None,
+  };
+  let metrics = match args.metrics || args.metrics_addr.is_some()
+  {
+    true => Some(eval.collect_metrics().await),
+    false => None,
+  };
+  let metrics_server = match (&args.metrics_addr, &metrics)
+  {
+    (Some(addr), Some(metrics)) => Some(serve_metrics(addr, metrics.clone()).await),
+    _ => None,
+  };
+  let debugger = match args.step || !args.breakpoints.is_empty()
+  {
+    true => Some(eval.debug(args.breakpoints).await),
+    false => None,
+  };
+  if let Some(debugger) = &debugger
+  {
+    if args.step
+    {
+      debugger.pause_next();
+    }
+  }
+  let instance = match &args.resume
+  {
+    Some(path) =>
+    {
+      let checkpoint = Checkpoint::load(path).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(2)
+      });
+      if !same_file(Path::new(&checkpoint.graph_path), &filename)
+      {
+        eprintln!(
+          "{}: checkpoint of {}, not {}",
+          path.display(),
+          checkpoint.graph_path,
+          filename.display()
+        );
+        std::process::exit(2);
+      }
+      eval.resume(checkpoint).await
+    }
+    None =>
+    {
+      let inputs = project_inputs.unwrap_or_else(|| {
+        graph_inputs(&eval, &given).unwrap_or_else(|e| {
+          eprintln!("{}: {e}", filename.display());
+          std::process::exit(2)
+        })
+      });
+      eval.instantiate(inputs).await
+    }
+  };
+  if let Some(debugger) = debugger
+  {
+    tokio::spawn(console::run(debugger, instance.clone()));
+  }
+  let checkpoints = args.checkpoint.clone().map(|path| {
+    let every = args.checkpoint_every;
+    tokio::spawn(write_checkpoints(instance.clone(), path, every))
+  });
 
-  tokio::select! {
-    _ = ctrl_c() => {println!("Ctrl c, shutting down");},
-    _ = instance.wait_for_complete() => {
-      if cli.print_output
+  let mut exit_code = 0;
+  let mut interrupted = false;
+  let stopped = tokio::select! {
+    (signal, code) = termination() => {
+      eprintln!("{signal}, shutting down");
+      interrupted = true;
+      exit_code = code;
+      true
+    },
+    outputs = instance.get_outputs() => {
+      if print_output || args.output_file.is_some()
+      {
+        write_outputs(&outputs, &output_names, args.output_format, args.output_file.as_deref());
+      }
+      match outputs
       {
-        println!("{:?}", instance.get_outputs().await);
+        Err(EvalError::Aborted { message, code }) =>
+        {
+          eprintln!("Aborted: {message}");
+          exit_code = code;
+          true
+        }
+        Err(EvalError::TimedOut) =>
+        {
+          eprintln!("Deadline reached, stopping");
+          exit_code = 124;
+          true
+        }
+        Err(e @ EvalError::LimitExceeded { .. }) =>
+        {
+          eprintln!("{e}, stopping");
+          exit_code = 3;
+          true
+        }
+        Err(e) =>
+        {
+          eprintln!("{e}");
+          exit_code = 1;
+          true
+        }
+        Ok(_) => false,
+      }
+    }
+  };
+
+  // graphs that registered HTTP routes keep answering them until interrupted
+  let serving = instance.serving().await;
+  if !stopped && !serving.is_empty()
+  {
+    println!("Serving HTTP on {}, Ctrl c to stop", serving.join(", "));
+    tokio::select! {
+      (signal, code) = termination() => {
+        eprintln!("{signal}, shutting down");
+        interrupted = true;
+        exit_code = code;
+      },
+      (message, code) = instance.wait_for_abort() => {
+        eprintln!("Aborted: {message}");
+        exit_code = code;
+      }
+      _ = instance.wait_for_deadline() => {
+        eprintln!("Deadline reached, stopping");
+        exit_code = 124;
+      }
+      e = instance.wait_for_limit() => {
+        eprintln!("{e}, stopping");
+        exit_code = 3;
       }
-      else
+    }
+  }
+
+  if let Some(task) = checkpoints
+  {
+    task.abort();
+  }
+  if let (true, Some(path)) = (interrupted, &args.checkpoint)
+  {
+    save_checkpoint(&instance, path).await;
+  }
+  let report = if interrupted
+  {
+    let (left, report) = instance.clone().stop(grace).await;
+    if !left.is_idle()
+    {
+      eprintln!(
+        "Grace period of {grace:?} over, stopped {} running node(s) and {} IO operation(s)",
+        left.running_nodes, left.io_operations
+      );
+    }
+    report
+  }
+  else
+  {
+    instance.clone().shutdown().await
+  };
+  if let (Some(path), Some(trace)) = (&args.trace, trace)
+  {
+    if let Err(e) = std::fs::write(path, trace.render(args.trace_format))
+    {
+      eprintln!("{}: {e}", path.display());
+    }
+  }
+  if let Some(server) = metrics_server
+  {
+    server.stop().await;
+  }
+  match args.verbose
+  {
+    0 => (),
+    1 => print!("{report}"),
+    _ => print!("{report:#}"),
+  }
+  if task_stats
+  {
+    println!("{:?}", instance.task_stats().await);
+  }
+  if let (true, Some(metrics)) = (args.metrics, metrics)
+  {
+    print!("{}", metrics.summary());
+  }
+  if args.usage || args.usage_file.is_some()
+  {
+    let usage = instance.usage().await;
+    if args.usage
+    {
+      print!("{usage}");
+    }
+    if let Some(path) = &args.usage_file
+    {
+      let json = serde_json::to_string_pretty(&usage).unwrap();
+      if let Err(e) = std::fs::write(path, json)
       {
-        let _ = instance.get_outputs().await;
+        eprintln!("{}: {e}", path.display());
       }
     }
   }
+  std::process::exit(exit_code);
+}
+
+/// Whether two paths name the same file, comparing them as given if either is missing
+fn same_file(a: &Path, b: &Path) -> bool
+{
+  match (std::fs::canonicalize(a), std::fs::canonicalize(b))
+  {
+    (Ok(a), Ok(b)) => a == b,
+    _ => a == b,
+  }
+}
+
+async fn save_checkpoint(instance: &Arc<Evaluator<NodeStateLogger, NodeStateLogger>>, path: &Path)
+{
+  if let Err(e) = instance.clone().checkpoint().await.save(path)
+  {
+    eprintln!("--checkpoint: {e}");
+  }
+}
+
+/// Checkpoints `instance` to `path` every `every` until aborted.
+async fn write_checkpoints(
+  instance: Arc<Evaluator<NodeStateLogger, NodeStateLogger>>,
+  path: PathBuf,
+  every: Duration,
+)
+{
+  loop
+  {
+    tokio::time::sleep(every).await;
+    save_checkpoint(&instance, &path).await;
+  }
+}
+
+/// Serves `metrics` on `GET /metrics` at `addr` until stopped.
+async fn serve_metrics(addr: &str, metrics: Arc<Metrics>) -> HttpServer
+{
+  let server = HttpServer::bind(addr).await.unwrap_or_else(|e| {
+    eprintln!("--metrics-addr {addr}: {e}");
+    std::process::exit(2)
+  });
+  let handler: RouteHandler = Arc::new(move |_| {
+    let body = metrics.prometheus();
+    Box::pin(async move {
+      Ok(HttpResponse {
+        status: 200,
+        headers: HashMap::from([(
+          "content-type".to_string(),
+          "text/plain; version=0.0.4".to_string(),
+        )]),
+        body,
+      })
+    })
+  });
+  server.add_route("GET", "/metrics", handler).await;
+  eprintln!("Serving metrics on http://{}/metrics", server.local_addr());
+  server
+}
+
+/// Resolves on Ctrl c or SIGTERM with the signal's name and the code to exit with,
+/// 128 plus its number as shells report it.
+#[cfg(unix)]
+async fn termination() -> (&'static str, i32)
+{
+  let mut terminate = signal(SignalKind::terminate()).expect("failed to listen for SIGTERM");
+  tokio::select! {
+    _ = ctrl_c() => ("Ctrl c", 130),
+    _ = terminate.recv() => ("SIGTERM", 143),
+  }
+}
 
-  instance.shutdown().await;
+/// Resolves on Ctrl c, the only termination signal outside unix.
+#[cfg(not(unix))]
+async fn termination() -> (&'static str, i32)
+{
+  // failing to listen leaves the run to finish on its own
+  match ctrl_c().await
+  {
+    Ok(()) => ("Ctrl c", 130),
+    Err(_) => std::future::pending().await,
+  }
 }
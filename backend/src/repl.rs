@@ -0,0 +1,194 @@
+use crate::{
+  cli::{parse_input, ReplArgs},
+  graph_inputs,
+};
+use agent_nodes_core::{
+  eval::{node_label, Checkpoint, InstanceState},
+  Config, Evaluator, NodeStateLogger,
+};
+use futures::FutureExt;
+use std::{future::Future, io::Write, sync::Arc};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use uuid::Uuid;
+
+type Graph = Arc<Evaluator<NodeStateLogger, NodeStateLogger>>;
+
+const HELP: &str = "commands: s(et) PORT VALUE, r(un), in(puts), i(nspect) [NODE], t(rigger) \
+                    NODE, q(uit)";
+
+/// Starts an instance holding `state` that waits for inputs.
+async fn start(graph: &Graph, state: InstanceState) -> Graph
+{
+  let checkpoint = Checkpoint {
+    graph_path: graph.graph_path().to_string(),
+    inputs: vec![],
+    state,
+  };
+  graph.clone().resume(checkpoint).await
+}
+
+/// The REPL's instance, started again with its state whenever one of its nodes fails, as
+/// the failed node would not run again.
+struct Session
+{
+  graph: Graph,
+  instance: Graph,
+}
+
+impl Session
+{
+  /// Waits for `task` on the instance, giving up if a node fails or on Ctrl c, which
+  /// leaves the instance running.
+  async fn wait<T>(&mut self, task: impl Future<Output = T>) -> Option<T>
+  {
+    let failure = tokio::select! {
+      x = task => return Some(x),
+      failure = self.instance.wait_for_failure() => failure,
+      _ = tokio::signal::ctrl_c() => {
+        eprintln!("interrupted, the instance is still running");
+        return None;
+      }
+    };
+    let (node, message) = failure;
+    let node = node_label(node, self.instance.node_name(&node));
+    eprintln!("node {node} failed: {message}");
+    eprintln!("starting the graph again with the state it had");
+    let state = self.instance.clone().checkpoint().await.state;
+    self.instance.clone().shutdown().await;
+    self.instance = start(&self.graph, state).await;
+    None
+  }
+}
+
+/// Reads commands from stdin until it closes, running the graph on the inputs set so
+/// far each time, with its variables, stored values and agent conversations kept from
+/// one run to the next. Outputs go to stdout and everything else to stderr.
+pub async fn repl(args: ReplArgs, config: Config)
+{
+  let path = args.filename.to_string_lossy().into_owned();
+  let graph = Evaluator::new(path.clone(), None, None, None).unwrap_or_else(|e| {
+    eprintln!("{path}: {e}");
+    std::process::exit(2)
+  });
+  graph.set_config(config).await;
+  if let Err(e) = graph.clone().check_cycles().await
+  {
+    eprintln!("{path}: {e}");
+    std::process::exit(2)
+  }
+  let mut given = args.inputs;
+  let instance = start(&graph, InstanceState::default()).await;
+  let mut session = Session { graph, instance };
+  eprintln!("{HELP}");
+
+  let mut lines = BufReader::new(tokio::io::stdin()).lines();
+  loop
+  {
+    eprint!("(repl) ");
+    let _ = std::io::stderr().flush();
+    let Ok(Some(line)) = lines.next_line().await
+    else
+    {
+      break;
+    };
+    let line = line.trim();
+    let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+    match (command, rest.trim())
+    {
+      ("", _) => (),
+      ("s" | "set", rest) =>
+      {
+        match rest.split_once(' ')
+        {
+          Some((port, value)) =>
+          {
+            match parse_input(&format!("{port}={}", value.trim()))
+            {
+              Ok(input) => given.push(input),
+              Err(e) => eprintln!("{e}"),
+            }
+          }
+          None => eprintln!("{HELP}"),
+        }
+      }
+      ("r" | "run", "") =>
+      {
+        let inputs = match graph_inputs(&session.graph, &given)
+        {
+          Ok(inputs) => inputs,
+          Err(e) =>
+          {
+            eprintln!("{e}");
+            continue;
+          }
+        };
+        let instance = session.instance.clone();
+        // a triggered node can run through to the end node, whose outputs would
+        // otherwise be taken as those of this run
+        let _ = instance.get_outputs().now_or_never();
+        let run = async {
+          instance.send_inputs(inputs).await;
+          instance.get_outputs().await
+        };
+        if let Some(outputs) = session.wait(run).await
+        {
+          println!("{outputs:?}");
+        }
+      }
+      ("in" | "inputs", "") =>
+      {
+        for (port, data_type) in session.graph.input_types().iter().enumerate()
+        {
+          let value = given
+            .iter()
+            .rev()
+            .find(|(key, _)| key.parse() == Ok(port))
+            .map(|(_, value)| value);
+          match (value, session.graph.input_default(port))
+          {
+            (Some(value), _) => eprintln!("{port} ({data_type}) = {value}"),
+            (None, Some(default)) => eprintln!("{port} ({data_type}) = {default}, the default"),
+            (None, None) => eprintln!("{port} ({data_type}) not set"),
+          }
+        }
+      }
+      ("i" | "inspect", "") => eprint!("{}", session.instance.inspect().await),
+      ("i" | "inspect", node) =>
+      {
+        match node.parse::<Uuid>()
+        {
+          Ok(node) =>
+          {
+            let snapshot = session.instance.inspect().await;
+            match snapshot.nodes.iter().find(|x| x.node == node)
+            {
+              Some(x) => eprintln!("{x}, outputs {:?}", x.outputs),
+              None => eprintln!("no node {node} in {}", snapshot.graph_path),
+            }
+          }
+          Err(e) => eprintln!("{node}: {e}"),
+        }
+      }
+      ("t" | "trigger", node) =>
+      {
+        match node.parse::<Uuid>()
+        {
+          Ok(node) =>
+          {
+            let rerun = session.instance.clone().rerun_node(&node);
+            match session.wait(rerun).await
+            {
+              Some(Ok(outputs)) => println!("{outputs:?}"),
+              Some(Err(e)) => eprintln!("{e}"),
+              None => (),
+            }
+          }
+          Err(e) => eprintln!("{node}: {e}"),
+        }
+      }
+      ("q" | "quit", "") => break,
+      _ => eprintln!("{HELP}"),
+    }
+  }
+  session.instance.shutdown().await;
+}
@@ -0,0 +1,136 @@
+//! Where `Secret` nodes get API keys and the like, so graphs name them instead of
+//! holding them in `Value` nodes committed with the graph.
+
+use crate::config::{SecretsConfig, SecretsProvider};
+use std::path::PathBuf;
+use std::process::Command;
+
+/// A place secrets are kept, looked up by name
+pub trait SecretStore: Send + Sync
+{
+  /// The secret called `name`, None if the store has no such secret. Fails if the
+  /// store itself cannot be read.
+  fn get(&self, name: &str) -> Result<Option<String>, String>;
+}
+
+/// Environment variables, each secret's name after `prefix`
+pub struct EnvStore
+{
+  pub prefix: String,
+}
+
+/// A file of `NAME=VALUE` lines, as in a `.env` file. Blank lines and lines starting
+/// with `#` are skipped and values may be quoted.
+pub struct FileStore
+{
+  pub path: PathBuf,
+}
+
+/// The OS keychain, secrets being the passwords stored for `service` with their name
+/// as the account: `security` on macOS and `secret-tool` elsewhere
+pub struct KeychainStore
+{
+  pub service: String,
+}
+
+impl SecretStore for EnvStore
+{
+  fn get(&self, name: &str) -> Result<Option<String>, String>
+  {
+    Ok(std::env::var(format!("{}{name}", self.prefix)).ok())
+  }
+}
+
+impl SecretStore for FileStore
+{
+  fn get(&self, name: &str) -> Result<Option<String>, String>
+  {
+    let text = std::fs::read_to_string(&self.path)
+      .map_err(|e| format!("could not read {}: {e}", self.path.display()))?;
+    let value = text
+      .lines()
+      .map(str::trim)
+      .filter(|x| !x.is_empty() && !x.starts_with('#'))
+      .filter_map(|x| x.split_once('='))
+      .find(|(key, _)| key.trim() == name)
+      .map(|(_, value)| {
+        let value = value.trim();
+        ['"', '\'']
+          .into_iter()
+          .find_map(|quote| value.strip_prefix(quote)?.strip_suffix(quote))
+          .unwrap_or(value)
+          .to_string()
+      });
+    Ok(value)
+  }
+}
+
+impl SecretStore for KeychainStore
+{
+  fn get(&self, name: &str) -> Result<Option<String>, String>
+  {
+    let mut command = match cfg!(target_os = "macos")
+    {
+      true =>
+      {
+        let mut command = Command::new("security");
+        command.args([
+          "find-generic-password",
+          "-s",
+          &self.service,
+          "-a",
+          name,
+          "-w",
+        ]);
+        command
+      }
+      false =>
+      {
+        let mut command = Command::new("secret-tool");
+        command.args(["lookup", "service", &self.service, "account", name]);
+        command
+      }
+    };
+    let output = command
+      .output()
+      .map_err(|e| format!("could not run the keychain tool: {e}"))?;
+    // both tools fail the same way for a missing entry as for a locked keychain
+    match output.status.success()
+    {
+      true =>
+      {
+        let value = String::from_utf8_lossy(&output.stdout);
+        Ok(Some(value.trim_end_matches(['\r', '\n']).to_string()))
+      }
+      false => Ok(None),
+    }
+  }
+}
+
+/// The store `config` picks
+pub fn store(config: &SecretsConfig) -> Result<Box<dyn SecretStore>, String>
+{
+  Ok(match config.provider
+  {
+    SecretsProvider::Env =>
+    {
+      Box::new(EnvStore {
+        prefix: config.env_prefix.clone(),
+      })
+    }
+    SecretsProvider::File =>
+    {
+      let path = config
+        .file
+        .clone()
+        .ok_or("the secrets provider is `file` but no `file` is set")?;
+      Box::new(FileStore { path })
+    }
+    SecretsProvider::Keychain =>
+    {
+      Box::new(KeychainStore {
+        service: config.keychain_service.clone(),
+      })
+    }
+  })
+}
@@ -0,0 +1,460 @@
+//! A JSON-RPC 2.0 API over a WebSocket, one request or response per text frame, for
+//! the editor to run graphs without parsing the output of the binary. Methods:
+//!
+//! - `load {path}` checks the graph can run and returns its `graph` id, title, and input
+//!   and output types
+//! - `run {graph, inputs}` starts it with `inputs` by port and returns the `run` id
+//! - `outputs {run}` waits for the run to end and returns its outputs
+//! - `cancel {run}` stops the run, returning whether it was still going
+//!
+//! Every run sends `node` notifications as its nodes start, output values, finish and
+//! fail, and a `finished` one with its outputs or error once it ends.
+
+use crate::{cli::ServeArgs, graph_inputs, termination};
+use agent_nodes_core::{
+  eval::{NodeHooks, NodeInfo},
+  Complex, Config, DataValue, EvalError, Evaluator, NodeStateLogger,
+};
+use futures::{SinkExt, StreamExt};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+use std::{
+  collections::HashMap,
+  path::{Path, PathBuf},
+  sync::{Arc, Mutex},
+  time::Duration,
+};
+use tokio::{
+  net::{TcpListener, TcpStream},
+  sync::{
+    mpsc::{unbounded_channel, UnboundedSender},
+    watch,
+  },
+};
+use tokio_util::sync::CancellationToken;
+use tokio_websockets::{Message, ServerBuilder};
+use uuid::Uuid;
+
+type Graph = Arc<Evaluator<NodeStateLogger, NodeStateLogger>>;
+
+/// The outputs of a run, or what it failed with as the C API reports it
+type Outcome = Result<Vec<DataValue>, Value>;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// A graph that cannot be loaded, or a graph or run this connection does not have
+const NOT_LOADED: i64 = -32000;
+/// The run failed, was cancelled or ran past its deadline, with the error as `data`
+const RUN_FAILED: i64 = -32001;
+
+struct RpcError
+{
+  code: i64,
+  message: String,
+  data: Option<Value>,
+}
+
+impl RpcError
+{
+  fn new(code: i64, message: impl Into<String>) -> Self
+  {
+    Self {
+      code,
+      message: message.into(),
+      data: None,
+    }
+  }
+}
+
+fn response(id: Value, result: Result<Value, RpcError>) -> Value
+{
+  match result
+  {
+    Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+    Err(e) =>
+    {
+      let mut error = json!({ "code": e.code, "message": e.message });
+      if let Some(data) = e.data
+      {
+        error["data"] = data;
+      }
+      json!({ "jsonrpc": "2.0", "id": id, "error": error })
+    }
+  }
+}
+
+fn notification(method: &str, params: Value) -> Value
+{
+  json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+fn params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError>
+{
+  serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+#[derive(Deserialize)]
+struct LoadParams
+{
+  path: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct RunParams
+{
+  graph: Uuid,
+  #[serde(default)]
+  inputs: Vec<DataValue>,
+}
+
+#[derive(Deserialize)]
+struct RunId
+{
+  run: Uuid,
+}
+
+/// Sends each node run of one run to its connection as `node` notifications
+struct NodeEvents
+{
+  run: Uuid,
+  messages: UnboundedSender<Value>,
+}
+
+impl NodeEvents
+{
+  fn send(&self, node: NodeInfo<'_>, event: &str, detail: Value)
+  {
+    let mut params = json!({
+      "run": self.run,
+      "scope_id": node.scope_id,
+      "graph": node.graph_path,
+      "node": node.node,
+      "node_type": node.node_type,
+      "event": event,
+    });
+    if let (Value::Object(params), Value::Object(detail)) = (&mut params, detail)
+    {
+      params.extend(detail);
+    }
+    // the connection closing drops the receiver, the run is cancelled then anyway
+    let _ = self.messages.send(notification("node", params));
+  }
+}
+
+#[async_trait::async_trait]
+impl NodeHooks for NodeEvents
+{
+  async fn on_node_start(&self, node: NodeInfo<'_>, inputs: &[DataValue]) -> Result<(), String>
+  {
+    self.send(node, "started", json!({ "inputs": inputs }));
+    Ok(())
+  }
+
+  async fn on_value(&self, node: NodeInfo<'_>, port: usize, value: &DataValue)
+  {
+    self.send(node, "value", json!({ "port": port, "value": value }));
+  }
+
+  async fn on_node_end(&self, node: NodeInfo<'_>, elapsed: Duration)
+  {
+    let elapsed_ms = elapsed.as_secs_f64() * 1000.0;
+    self.send(node, "finished", json!({ "elapsed_ms": elapsed_ms }));
+  }
+
+  async fn on_error(&self, node: NodeInfo<'_>, error: &EvalError)
+  {
+    self.send(node, "failed", json!({ "error": error.to_string() }));
+  }
+}
+
+struct Run
+{
+  cancel: CancellationToken,
+  /// `None` until the run ends
+  outcome: watch::Receiver<Option<Outcome>>,
+}
+
+/// What one client has loaded and started. Graphs and runs are only known to the
+/// connection that made them, and its runs are stopped once it closes.
+struct Connection
+{
+  /// The config given to `serve`, otherwise that closest to each graph is used
+  config: Option<Config>,
+  graphs: Mutex<HashMap<Uuid, PathBuf>>,
+  runs: Mutex<HashMap<Uuid, Run>>,
+  messages: UnboundedSender<Value>,
+}
+
+impl Connection
+{
+  fn config_for(&self, path: &Path) -> Result<Config, RpcError>
+  {
+    if let Some(config) = &self.config
+    {
+      return Ok(config.clone());
+    }
+    match Config::discover(path.parent().unwrap_or(Path::new("")))
+    {
+      Some(file) => Config::load(&file).map_err(|e| RpcError::new(NOT_LOADED, e.to_string())),
+      None => Ok(Config::default()),
+    }
+  }
+
+  /// Loads the graph at `path` afresh, so runs pick up edits saved since `load`.
+  async fn evaluator(&self, path: &Path) -> Result<Graph, RpcError>
+  {
+    let failed = |e: EvalError| RpcError::new(NOT_LOADED, format!("{}: {e}", path.display()));
+    let config = self.config_for(path)?;
+    let graph =
+      Evaluator::new(path.to_string_lossy().into_owned(), None, None, None).map_err(failed)?;
+    graph.set_config(config).await;
+    graph.clone().check_cycles().await.map_err(failed)?;
+    Ok(graph)
+  }
+
+  /// Answers a request, or returns `None` for a notification, which has no `id`.
+  async fn handle(self: Arc<Self>, text: &str) -> Option<Value>
+  {
+    let request: Value = match serde_json::from_str(text)
+    {
+      Ok(request) => request,
+      Err(e) =>
+      {
+        return Some(response(
+          Value::Null,
+          Err(RpcError::new(PARSE_ERROR, e.to_string())),
+        ))
+      }
+    };
+    let id = request.get("id").cloned();
+    let Some(method) = request.get("method").and_then(Value::as_str)
+    else
+    {
+      let error = RpcError::new(INVALID_REQUEST, "expected a method");
+      return Some(response(id.unwrap_or_default(), Err(error)));
+    };
+    let params = request.get("params").cloned().unwrap_or_default();
+    let result = match method
+    {
+      "load" => self.load(params).await,
+      "run" => self.run(params).await,
+      "outputs" => self.outputs(params).await,
+      "cancel" => self.cancel(params).await,
+      _ =>
+      {
+        Err(RpcError::new(
+          METHOD_NOT_FOUND,
+          format!("no method `{method}`"),
+        ))
+      }
+    };
+    Some(response(id?, result))
+  }
+
+  async fn load(&self, params: Value) -> Result<Value, RpcError>
+  {
+    let LoadParams { path } = self::params(params)?;
+    self.evaluator(&path).await?;
+    let graph = Complex::load(&path.to_string_lossy())
+      .map_err(|e| RpcError::new(NOT_LOADED, format!("{}: {e}", path.display())))?;
+    let id = Uuid::new_v4();
+    self.graphs.lock().unwrap().insert(id, path);
+    Ok(json!({
+      "graph": id,
+      "title": graph.title,
+      "inputs": graph.inputs,
+      "outputs": graph.outputs,
+    }))
+  }
+
+  async fn run(self: Arc<Self>, params: Value) -> Result<Value, RpcError>
+  {
+    let RunParams { graph, inputs } = self::params(params)?;
+    let path = self.graphs.lock().unwrap().get(&graph).cloned();
+    let path = path.ok_or_else(|| RpcError::new(NOT_LOADED, format!("no graph {graph}")))?;
+    let graph = self.evaluator(&path).await?;
+    let given: Vec<(String, DataValue)> = inputs
+      .into_iter()
+      .enumerate()
+      .map(|(port, value)| (port.to_string(), value))
+      .collect();
+    let inputs = graph_inputs(&graph, &given).map_err(|e| RpcError::new(INVALID_PARAMS, e))?;
+
+    let run = Uuid::new_v4();
+    graph
+      .add_hooks(Arc::new(NodeEvents {
+        run,
+        messages: self.messages.clone(),
+      }))
+      .await;
+    let grace = graph.config().await.timeouts.shutdown_grace();
+    let instance = graph.instantiate(inputs).await;
+    let cancel = CancellationToken::new();
+    let (outcome, outcome_rx) = watch::channel(None);
+    self.runs.lock().unwrap().insert(
+      run,
+      Run {
+        cancel: cancel.clone(),
+        outcome: outcome_rx,
+      },
+    );
+    let messages = self.messages.clone();
+    tokio::spawn(async move {
+      let ended = wait_for_run(&instance, &cancel).await;
+      let params = match &ended
+      {
+        Ok(outputs) => json!({ "run": run, "outputs": outputs }),
+        Err(error) => json!({ "run": run, "error": error }),
+      };
+      let _ = messages.send(notification("finished", params));
+      outcome.send_replace(Some(ended));
+      match cancel.is_cancelled()
+      {
+        true => drop(instance.stop(grace).await),
+        false => drop(instance.shutdown().await),
+      }
+    });
+    Ok(json!({ "run": run }))
+  }
+
+  fn outcome(&self, run: &Uuid) -> Result<watch::Receiver<Option<Outcome>>, RpcError>
+  {
+    match self.runs.lock().unwrap().get(run)
+    {
+      Some(x) => Ok(x.outcome.clone()),
+      None => Err(RpcError::new(NOT_LOADED, format!("no run {run}"))),
+    }
+  }
+
+  async fn outputs(&self, params: Value) -> Result<Value, RpcError>
+  {
+    let RunId { run } = self::params(params)?;
+    let mut outcome = self.outcome(&run)?;
+    // the sender is only dropped once it has sent the outcome
+    let ended = outcome
+      .wait_for(Option::is_some)
+      .await
+      .map(|x| x.clone())
+      .ok()
+      .flatten();
+    match ended
+    {
+      Some(Ok(outputs)) => Ok(json!(outputs)),
+      Some(Err(error)) =>
+      {
+        Err(RpcError {
+          data: Some(error),
+          ..RpcError::new(RUN_FAILED, format!("run {run} failed"))
+        })
+      }
+      None => Err(RpcError::new(RUN_FAILED, format!("run {run} was lost"))),
+    }
+  }
+
+  async fn cancel(&self, params: Value) -> Result<Value, RpcError>
+  {
+    let RunId { run } = self::params(params)?;
+    let mut outcome = self.outcome(&run)?;
+    let running = outcome.borrow().is_none();
+    if let Some(x) = self.runs.lock().unwrap().get(&run)
+    {
+      x.cancel.cancel();
+    }
+    let _ = outcome.wait_for(Option::is_some).await;
+    Ok(json!(running))
+  }
+
+  fn close(&self)
+  {
+    for run in self.runs.lock().unwrap().values()
+    {
+      run.cancel.cancel();
+    }
+  }
+}
+
+/// Waits for the run to end one way or another.
+async fn wait_for_run(instance: &Graph, cancel: &CancellationToken) -> Outcome
+{
+  tokio::select! {
+    outputs = instance.get_outputs() => outputs.map_err(|e| serde_json::to_value(e).unwrap_or_default()),
+    _ = cancel.cancelled() => Err(json!({"kind": "Cancelled"})),
+  }
+}
+
+async fn connection(stream: TcpStream, config: Option<Config>)
+{
+  let Ok((_, socket)) = ServerBuilder::new().accept(stream).await
+  else
+  {
+    return;
+  };
+  let (mut sink, mut incoming) = socket.split();
+  let (messages, mut outgoing) = unbounded_channel::<Value>();
+  let writer = tokio::spawn(async move {
+    while let Some(message) = outgoing.recv().await
+    {
+      if sink.send(Message::text(message.to_string())).await.is_err()
+      {
+        break;
+      }
+    }
+  });
+  let connection = Arc::new(Connection {
+    config,
+    graphs: Mutex::default(),
+    runs: Mutex::default(),
+    messages,
+  });
+  while let Some(Ok(message)) = incoming.next().await
+  {
+    // pings and closing are answered by the socket itself
+    let Some(text) = message.as_text().map(str::to_string)
+    else
+    {
+      continue;
+    };
+    // requests are answered as they finish, so `outputs` does not hold up the rest
+    let connection = connection.clone();
+    tokio::spawn(async move {
+      if let Some(response) = connection.clone().handle(&text).await
+      {
+        let _ = connection.messages.send(response);
+      }
+    });
+  }
+  connection.close();
+  writer.abort();
+}
+
+/// Accepts connections until interrupted.
+pub async fn serve(args: ServeArgs, config: Option<Config>)
+{
+  let listener = TcpListener::bind(&args.listen).await.unwrap_or_else(|e| {
+    eprintln!("{}: {e}", args.listen);
+    std::process::exit(2)
+  });
+  match listener.local_addr()
+  {
+    Ok(addr) => eprintln!("Serving the control API on ws://{addr}, Ctrl c to stop"),
+    Err(_) => eprintln!("Serving the control API on ws://{}", args.listen),
+  }
+  loop
+  {
+    tokio::select! {
+      accepted = listener.accept() => {
+        if let Ok((stream, _)) = accepted
+        {
+          tokio::spawn(connection(stream, config.clone()));
+        }
+      }
+      // stopping the control API is how it is meant to end, so not an error
+      (signal, _) = termination() => {
+        eprintln!("{signal}, shutting down");
+        return;
+      }
+    }
+  }
+}
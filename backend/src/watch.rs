@@ -0,0 +1,157 @@
+use crate::{cli::WatchArgs, graph_inputs, termination};
+use agent_nodes_core::{Config, EvalError, Evaluator, NodeStateLogger};
+use std::{
+  path::Path,
+  sync::Arc,
+  time::{Duration, SystemTime},
+};
+use tokio_util::sync::CancellationToken;
+
+type Graph = Arc<Evaluator<NodeStateLogger, NodeStateLogger>>;
+
+/// Files with when each was last modified, `None` for those that cannot be read
+type Stamps = Vec<(String, Option<SystemTime>)>;
+
+fn stamps(files: &[String]) -> Stamps
+{
+  files.iter().map(|x| (x.clone(), modified(x))).collect()
+}
+
+fn modified(path: &str) -> Option<SystemTime>
+{
+  std::fs::metadata(path).and_then(|x| x.modified()).ok()
+}
+
+/// Checks `stamps` every `interval` until one of the files changes, returning it.
+async fn changed(stamps: &Stamps, interval: Duration) -> String
+{
+  loop
+  {
+    tokio::time::sleep(interval).await;
+    if let Some((path, _)) = stamps.iter().find(|(path, at)| modified(path) != *at)
+    {
+      return path.clone();
+    }
+  }
+}
+
+/// Loads the graph afresh, with nothing cached, and checks it can run.
+async fn load(path: &Path, config: &Config) -> Result<Graph, String>
+{
+  let graph = Evaluator::new(path.to_string_lossy().into_owned(), None, None, None)
+    .map_err(|e| e.to_string())?;
+  graph.set_config(config.clone()).await;
+  graph
+    .clone()
+    .check_cycles()
+    .await
+    .map_err(|e| e.to_string())?;
+  Ok(graph)
+}
+
+/// How a run of the graph ended
+enum Ended
+{
+  Finished,
+  Changed(String),
+  Interrupted,
+}
+
+/// Runs the graph once, stopping it early if a file of `stamps` changes.
+async fn run(
+  graph: Graph,
+  args: &WatchArgs,
+  stamps: &Stamps,
+  interval: Duration,
+  stop: &CancellationToken,
+) -> Ended
+{
+  let inputs = match graph_inputs(&graph, &args.inputs)
+  {
+    Ok(inputs) => inputs,
+    Err(e) =>
+    {
+      eprintln!("{}: {e}", args.filename.display());
+      return Ended::Finished;
+    }
+  };
+  if let Some(deadline) = args.deadline
+  {
+    graph.set_deadline(deadline).await;
+  }
+  let instance = graph.instantiate(inputs).await;
+  let ended = tokio::select! {
+    outputs = instance.get_outputs() => {
+      match outputs
+      {
+        Err(e @ EvalError::NodeFailed { .. }) => eprintln!("{e}"),
+        outputs => println!("{outputs:?}"),
+      }
+      Ended::Finished
+    }
+    path = changed(stamps, interval) => Ended::Changed(path),
+    _ = stop.cancelled() => Ended::Interrupted,
+  };
+  instance.shutdown().await;
+  ended
+}
+
+/// Runs the graph, then again whenever it or a Complex graph it uses changes. A run
+/// still going when something changes is stopped for the new one.
+pub async fn watch(args: WatchArgs, config: Config)
+{
+  let interval = args.interval;
+  // listened for throughout, a signal between runs would be missed otherwise
+  let stop = CancellationToken::new();
+  tokio::spawn({
+    let stop = stop.clone();
+    async move {
+      termination().await;
+      stop.cancel();
+    }
+  });
+  let mut files = vec![args.filename.to_string_lossy().into_owned()];
+  loop
+  {
+    // taken before loading, so changes made meanwhile start another run
+    let mut watched = stamps(&files);
+    let ended = match load(&args.filename, &config).await
+    {
+      Ok(graph) =>
+      {
+        files = graph.clone().graph_files().await;
+        watched.retain(|(path, _)| files.contains(path));
+        let new: Vec<String> = files
+          .iter()
+          .filter(|x| !watched.iter().any(|(path, _)| path == *x))
+          .cloned()
+          .collect();
+        watched.extend(stamps(&new));
+        eprintln!(
+          "running {}, watching {} file(s)",
+          args.filename.display(),
+          files.len()
+        );
+        run(graph, &args, &watched, interval, &stop).await
+      }
+      Err(e) =>
+      {
+        eprintln!("{}: {e}", args.filename.display());
+        Ended::Finished
+      }
+    };
+    let path = match ended
+    {
+      Ended::Changed(path) => path,
+      Ended::Interrupted => return,
+      Ended::Finished =>
+      {
+        tokio::select! {
+          path = changed(&watched, interval) => path,
+          _ = stop.cancelled() => return,
+        }
+      }
+    };
+    eprintln!("{path} changed");
+  }
+}
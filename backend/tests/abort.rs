@@ -0,0 +1,42 @@
+mod common;
+
+use common::{graph, run_graph_output};
+
+#[test]
+fn abort_sets_exit_status()
+{
+  for name in ["abort.json", "abort_nested.json"]
+  {
+    let output = run_graph_output(&graph(name), &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(3), "{name}: {stdout}");
+    assert!(
+      String::from_utf8_lossy(&output.stderr).contains("Aborted: guard failed"),
+      "{name}"
+    );
+    assert!(
+      stdout.contains(r#"Err(Aborted { message: "guard failed", code: 3 })"#),
+      "{name}: {stdout}"
+    );
+    assert!(!stdout.contains("unreachable"), "{name}: {stdout}");
+  }
+}
+
+#[test]
+fn failed_node_sets_exit_status()
+{
+  let output = run_graph_output(&graph("node_fails.json"), &[]);
+  let stdout = String::from_utf8_lossy(&output.stdout);
+  assert_eq!(output.status.code(), Some(1), "{stdout}");
+  let stderr = String::from_utf8_lossy(&output.stderr);
+  assert!(
+    stderr.contains("node parse (00000000-0000-0000-0000-000000000003) failed: "),
+    "{stderr}"
+  );
+  // logged by the id in the graph file, not the one scoped to the instance
+  assert!(
+    stderr.contains(r#"node failed node=00000000-0000-0000-0000-000000000003 name="parse""#),
+    "{stderr}"
+  );
+  assert!(stdout.contains("Err(NodeFailed {"), "{stdout}");
+}
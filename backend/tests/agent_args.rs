@@ -0,0 +1,159 @@
+mod common;
+
+use agent_nodes_core::{
+  ai::{AgentArgs, AgentType},
+  language::nodes::{AgentOperation, AtomicType, ControlFlow},
+  DataType, DataValue, GraphBuilder,
+};
+use common::{atomic, run_graph};
+use indexmap::IndexMap;
+use std::{fs, path::PathBuf};
+
+fn string(x: &str) -> DataValue
+{
+  DataValue::String(x.to_string())
+}
+
+fn object(fields: &[(&str, DataValue)]) -> DataValue
+{
+  DataValue::Object(
+    fields
+      .iter()
+      .map(|(k, v)| (k.to_string(), v.clone()))
+      .collect::<IndexMap<_, _>>(),
+  )
+}
+
+fn function(name: &str) -> DataValue
+{
+  object(&[
+    ("name", string(name)),
+    ("description", string("Looks up the weather")),
+    (
+      "parameters",
+      object(&[("type", string("object")), ("properties", object(&[]))]),
+    ),
+  ])
+}
+
+#[test]
+fn create_inputs_are_read_in_port_order()
+{
+  let args = |vals: Vec<DataValue>| AgentArgs::from_values(&vals).is_some();
+  assert!(args(vec![string("gpt-4o")]));
+  assert!(args(vec![
+    string("gpt-4o"),
+    DataValue::Array(vec![
+      function("weather"),
+      object(&[("name", string("time"))])
+    ]),
+    DataValue::Float(0.2),
+    string("Answer briefly"),
+    DataValue::Integer(1),
+    DataValue::Integer(256),
+    DataValue::Array(vec![string("END")]),
+  ]));
+  assert!(args(vec![
+    string("gpt-4o"),
+    DataValue::None,
+    DataValue::None,
+    DataValue::None,
+    DataValue::None,
+    DataValue::None,
+    string("END"),
+  ]));
+
+  assert!(!args(vec![]));
+  assert!(!args(vec![DataValue::Integer(4)]));
+  assert!(!args(vec![
+    string("gpt-4o"),
+    DataValue::Array(vec![string("weather")])
+  ]));
+  // functions need a name and take no other fields
+  assert!(!args(vec![
+    string("gpt-4o"),
+    DataValue::Array(vec![object(&[("description", string("nameless"))])]),
+  ]));
+  assert!(!args(vec![
+    string("gpt-4o"),
+    DataValue::Array(vec![object(&[
+      ("name", string("x")),
+      ("args", object(&[]))
+    ])]),
+  ]));
+  assert!(!args(vec![
+    string("gpt-4o"),
+    DataValue::None,
+    string("hot")
+  ]));
+  assert!(!args(vec![
+    string("gpt-4o"),
+    DataValue::None,
+    DataValue::None,
+    DataValue::None,
+    DataValue::None,
+    DataValue::Integer(0),
+  ]));
+}
+
+/// A graph creating a Mock agent from its seven inputs, sending it `hi` and
+/// outputting the reply
+fn mock_conversation(name: &str) -> PathBuf
+{
+  let mut graph = GraphBuilder::new(vec![DataType::Any; 7], vec![DataType::Any]);
+  let start = graph.add_node(atomic(AtomicType::Control(ControlFlow::Start)));
+  let create = graph.add_node(atomic(AtomicType::AgentOp(AgentOperation::Create(
+    AgentType::Mock,
+  ))));
+  let message = graph.add_node(atomic(AtomicType::Value(string("hi"))));
+  let send = graph.add_node(atomic(AtomicType::AgentOp(AgentOperation::Send)));
+  let receive = graph.add_node(atomic(AtomicType::AgentOp(AgentOperation::Recieve)));
+  let end = graph.add_node(atomic(AtomicType::Control(ControlFlow::End)));
+  graph
+    .trigger(start, 0, create)
+    .trigger(create, 0, message)
+    .trigger(message, 0, send)
+    .trigger(send, 0, receive)
+    .trigger(receive, 0, end);
+  for port in 0..7
+  {
+    graph.connect(start, port, create, DataType::Any);
+  }
+  graph
+    .connect(create, 0, send, DataType::Agent(AgentType::Mock))
+    .connect(message, 0, send, DataType::String)
+    .connect(create, 0, receive, DataType::Agent(AgentType::Mock))
+    .connect(receive, 0, end, DataType::Any)
+    .set_end(end);
+  let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+  fs::write(
+    &path,
+    serde_json::to_string(&graph.build().unwrap()).unwrap(),
+  )
+  .unwrap();
+  path
+}
+
+#[test]
+fn mock_agents_ignore_the_settings_they_cannot_use()
+{
+  let path = mock_conversation("agent_args_mock.json");
+  let functions = serde_json::to_string(&DataValue::Array(vec![function("weather")])).unwrap();
+  let inputs = [
+    "0=echo".to_string(),
+    format!("1={functions}"),
+    "2=0.2".to_string(),
+    "3=Only ever answer in French".to_string(),
+    "4=null".to_string(),
+    "5=16".to_string(),
+    "6=[\"END\"]".to_string(),
+  ];
+  let mut args = vec![];
+  for input in &inputs
+  {
+    args.extend(["--input", input.as_str()]);
+  }
+  let out = run_graph(&path, &args);
+  // the reply is what it would be without a system prompt
+  assert!(out.contains(r#"Ok([String("echo: hi")])"#), "{out}");
+}
@@ -0,0 +1,137 @@
+use agent_nodes_core::{
+  ai::{AgentLimiter, LimiterStats},
+  config::{AgentLimits, QueueOrdering},
+};
+use std::{
+  collections::HashMap,
+  sync::{Arc, Mutex},
+  time::Duration,
+};
+
+fn limiter(max_in_flight: Option<usize>, per_provider: &[(&str, usize)]) -> Arc<AgentLimiter>
+{
+  Arc::new(AgentLimiter::new(AgentLimits {
+    max_in_flight,
+    per_provider: per_provider
+      .iter()
+      .map(|(k, v)| (k.to_string(), *v))
+      .collect::<HashMap<_, _>>(),
+    ordering: QueueOrdering::Fifo,
+  }))
+}
+
+/// Lets spawned requests reach the limiter
+async fn settle()
+{
+  tokio::time::sleep(Duration::from_millis(20)).await;
+}
+
+fn stats(queued: usize, in_flight: usize) -> LimiterStats
+{
+  LimiterStats { queued, in_flight }
+}
+
+/// Queues a request that records `name` once it gets a slot, holding it briefly
+fn request(
+  limiter: &Arc<AgentLimiter>,
+  priority: i64,
+  name: &'static str,
+  order: &Arc<Mutex<Vec<&'static str>>>,
+) -> tokio::task::JoinHandle<()>
+{
+  let (limiter, order) = (limiter.clone(), order.clone());
+  tokio::spawn(async move {
+    let _permit = limiter.acquire("mock", priority).await;
+    order.lock().unwrap().push(name);
+    tokio::time::sleep(Duration::from_millis(5)).await;
+  })
+}
+
+#[tokio::test]
+async fn requests_over_the_limit_wait_in_order()
+{
+  let limiter = limiter(Some(1), &[]);
+  let order = Arc::new(Mutex::new(vec![]));
+  let held = limiter.acquire("mock", 0).await;
+
+  let first = request(&limiter, 0, "first", &order);
+  settle().await;
+  let second = request(&limiter, 9, "second", &order);
+  settle().await;
+  assert_eq!(limiter.stats(), stats(2, 1));
+
+  drop(held);
+  first.await.unwrap();
+  second.await.unwrap();
+  assert_eq!(*order.lock().unwrap(), vec!["first", "second"]);
+  assert_eq!(limiter.stats(), stats(0, 0));
+}
+
+#[tokio::test]
+async fn priority_ordering_starts_highest_first()
+{
+  let limiter = Arc::new(AgentLimiter::new(AgentLimits {
+    max_in_flight: Some(1),
+    ordering: QueueOrdering::Priority,
+    ..AgentLimits::default()
+  }));
+  let order = Arc::new(Mutex::new(vec![]));
+  let held = limiter.acquire("mock", 0).await;
+
+  let tasks = [
+    request(&limiter, 1, "low", &order),
+    request(&limiter, 5, "high", &order),
+    request(&limiter, 1, "low again", &order),
+  ];
+  settle().await;
+  drop(held);
+  for task in tasks
+  {
+    task.await.unwrap();
+  }
+  assert_eq!(*order.lock().unwrap(), vec!["high", "low", "low again"]);
+}
+
+#[tokio::test]
+async fn provider_cap_leaves_other_providers_running()
+{
+  let limiter = limiter(None, &[("openai", 1)]);
+  let held = limiter.acquire("openai", 0).await;
+  let waiting = {
+    let limiter = limiter.clone();
+    tokio::spawn(async move {
+      let _permit = limiter.acquire("openai", 0).await;
+    })
+  };
+  settle().await;
+
+  let mock = tokio::time::timeout(Duration::from_secs(1), limiter.acquire("mock", 0))
+    .await
+    .expect("other providers are not held back");
+  assert_eq!(limiter.stats(), stats(1, 2));
+
+  drop((held, mock));
+  waiting.await.unwrap();
+  assert_eq!(limiter.stats(), stats(0, 0));
+}
+
+#[tokio::test]
+async fn abandoned_requests_leave_the_queue()
+{
+  let limiter = limiter(Some(1), &[]);
+  let held = limiter.acquire("mock", 0).await;
+  let waiting = {
+    let limiter = limiter.clone();
+    tokio::spawn(async move {
+      let _permit = limiter.acquire("mock", 0).await;
+    })
+  };
+  settle().await;
+  assert_eq!(limiter.stats(), stats(1, 1));
+
+  waiting.abort();
+  let _ = waiting.await;
+  assert_eq!(limiter.stats(), stats(0, 1));
+  drop(held);
+  assert_eq!(limiter.stats(), stats(0, 0));
+}
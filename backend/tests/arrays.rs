@@ -0,0 +1,87 @@
+mod common;
+
+use common::{graph, run_graph};
+
+#[test]
+fn sort_unique_contains()
+{
+  let out = run_graph(&graph("arrays.json"), &[]);
+  let expected = [
+    // numbers sort by value across Integer and Float
+    "Array([Integer(-4), Float(1.5), Integer(2), Integer(3)])",
+    // objects sorted by their "age" field, descending
+    r#"Array([Object({"age": Integer(41)}), Object({"age": Integer(30)}), Object({"age": Integer(25)})])"#,
+    // 1.0 counts as a repeat of 1
+    "Array([Integer(1), Integer(2), Integer(3)])",
+    "Boolean(true)",
+    "Boolean(false)",
+  ]
+  .join(", ");
+  assert!(out.contains(&format!("Ok([{expected}])")), "{out}");
+}
+
+#[test]
+fn group_by_and_aggregates()
+{
+  let out = run_graph(&graph("aggregates.json"), &[]);
+  // groups are an Object, so only check each group on its own
+  assert!(
+    out.contains(
+      r#""red": Array([Object({"team": String("red")}), Object({"team": String("red")})])"#
+    ),
+    "{out}"
+  );
+  assert!(
+    out.contains(r#""blue": Array([Object({"team": String("blue")})])"#),
+    "{out}"
+  );
+  let expected = [
+    // Sum, Avg, Min, Max and Count of [3, 1.5, 2, -4]
+    "Float(2.5)",
+    "Float(0.625)",
+    "Integer(-4)",
+    "Integer(3)",
+    "Integer(4)",
+    // Sum of the "score" fields
+    "Integer(96)",
+    // Avg of an empty Array
+    "None",
+  ]
+  .join(", ");
+  assert!(out.contains(&format!("}}), {expected}])")), "{out}");
+}
+
+#[test]
+fn set_operations()
+{
+  let out = run_graph(&graph("sets.json"), &[]);
+  let expected = [
+    // Union keeps the first Array's order and drops the repeated "a" and 1.0
+    r#"Array([String("b"), String("a"), String("c"), Integer(1), String("d")])"#,
+    // Intersection
+    r#"Array([String("b"), String("c"), Integer(1)])"#,
+    // Difference
+    r#"Array([String("a")])"#,
+  ]
+  .join(", ");
+  assert!(out.contains(&format!("Ok([{expected}])")), "{out}");
+}
+
+#[test]
+fn index_slice_and_combine()
+{
+  let out = run_graph(&graph("array_ops.json"), &[]);
+  let expected = [
+    // Flatten of [1, [2, 3], 4]
+    "Array([Integer(1), Integer(2), Integer(3), Integer(4)])",
+    "Integer(4)",
+    // index -1 is the last element
+    "Integer(4)",
+    // from 1 to a None end
+    "Array([Integer(2), Integer(3), Integer(4)])",
+    // Append 5, then Concat with the Reverse
+    "Array([Integer(1), Integer(2), Integer(3), Integer(4), Integer(5), Integer(4), Integer(3), Integer(2), Integer(1)])",
+  ]
+  .join(", ");
+  assert!(out.contains(&format!("Ok([{expected}])")), "{out}");
+}
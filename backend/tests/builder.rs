@@ -0,0 +1,61 @@
+mod common;
+
+use agent_nodes_core::{
+  language::nodes::{AtomicBinOp, AtomicType, ControlFlow},
+  DataType, DataValue, EvalError, Evaluator, GraphBuilder, NodeStateLogger,
+};
+use common::atomic;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn built_graphs_run()
+{
+  let mut graph = GraphBuilder::new(vec![DataType::Integer], vec![DataType::Integer]);
+  let start = graph.add_node(atomic(AtomicType::Control(ControlFlow::Start)));
+  let value = graph.add_node(atomic(AtomicType::Value(DataValue::Integer(20))));
+  let add = graph.add_node(atomic(AtomicType::BinOp(AtomicBinOp::Add)));
+  let end = graph.add_node(atomic(AtomicType::Control(ControlFlow::End)));
+  graph
+    .trigger(start, 0, value)
+    .trigger(value, 0, add)
+    .trigger(add, 0, end)
+    .connect(start, 0, add, DataType::Integer)
+    .connect(value, 0, add, DataType::Integer)
+    .connect(add, 0, end, DataType::Integer)
+    .set_end(end);
+  let complex = graph.build().unwrap();
+
+  let eval = Evaluator::<NodeStateLogger, NodeStateLogger>::from_complex(
+    complex,
+    "built.json".to_string(),
+    None,
+    None,
+    None,
+  )
+  .unwrap();
+  let instance = eval.instantiate(vec![DataValue::Integer(22)]).await;
+  let outputs = instance.get_outputs().await.unwrap();
+  instance.clone().shutdown().await;
+  assert_eq!(outputs, vec![DataValue::Integer(42)]);
+}
+
+#[test]
+fn build_checks_the_graph()
+{
+  let mut graph = GraphBuilder::new(vec![], vec![]);
+  graph.add_node(atomic(AtomicType::Control(ControlFlow::Start)));
+  assert!(matches!(graph.build(), Err(EvalError::NoEndNode)));
+
+  let mut graph = GraphBuilder::new(vec![], vec![]);
+  let start = graph.add_node(atomic(AtomicType::Control(ControlFlow::Start)));
+  let missing = uuid::Uuid::new_v4();
+  graph.trigger(start, 0, missing).set_end(start);
+  assert!(matches!(graph.build(), Err(EvalError::NodeNotFound(id)) if id == missing));
+
+  let mut graph = GraphBuilder::new(vec![], vec![]);
+  let start = graph.add_node(atomic(AtomicType::Control(ControlFlow::Start)));
+  let print = graph.add_node(atomic(AtomicType::Print));
+  graph
+    .connect(print, 0, print, DataType::String)
+    .set_end(start);
+  assert!(matches!(graph.build(), Err(EvalError::SelfListen(_))));
+}
@@ -0,0 +1,35 @@
+mod common;
+
+use common::{graph, run_graph};
+
+#[test]
+fn encode_decode_and_file_round_trip()
+{
+  let out = run_graph(&graph("bytes.json"), &[]);
+  let expected = [
+    r#"String("AP9B")"#,
+    r#"String("hi")"#,
+    r#"String("68c3a96c6c6f")"#,
+    // written to a file and read back as they were
+    "Bytes([0, 255, 65])",
+    r#"String("invalid Hex: \"0g\" is not hex")"#,
+  ]
+  .join(", ");
+  assert!(out.contains(&format!("Ok([{expected}])")), "{out}");
+}
+
+#[test]
+fn base64_hex_and_url_encodings()
+{
+  let out = run_graph(&graph("encodings.json"), &[]);
+  let expected = [
+    // as for a basic auth header
+    r#"String("dXNlcjpwYXNz")"#,
+    r#"String("a%20b%26c%3Dd%2F%C3%A9")"#,
+    r#"String("a b&c=d/é")"#,
+    r#"String("-_8")"#,
+    "Bytes([251, 255])",
+  ]
+  .join(", ");
+  assert!(out.contains(&format!("Ok([{expected}])")), "{out}");
+}
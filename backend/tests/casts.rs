@@ -0,0 +1,135 @@
+use agent_nodes_core::{
+  language::nodes::{AtomicType, ControlFlow, NodeType},
+  DataType, DataValue, EvalError, GraphBuilder,
+};
+
+fn cast(value: DataValue, to: DataType) -> Result<DataValue, (DataType, DataType)>
+{
+  value.try_cast(to)
+}
+
+#[test]
+fn strings_cast_to_and_from_scalars()
+{
+  let string = |x: &str| DataValue::String(x.to_string());
+  assert_eq!(
+    cast(string(" 42 "), DataType::Integer),
+    Ok(DataValue::Integer(42))
+  );
+  assert_eq!(
+    cast(string("2.5"), DataType::Float),
+    Ok(DataValue::Float(2.5))
+  );
+  assert_eq!(
+    cast(string("true"), DataType::Boolean),
+    Ok(DataValue::Boolean(true))
+  );
+  assert_eq!(
+    cast(string("forty"), DataType::Integer),
+    Err((DataType::String, DataType::Integer))
+  );
+  assert_eq!(cast(string("yes"), DataType::Boolean).ok(), None);
+  assert_eq!(
+    cast(DataValue::Integer(7), DataType::String),
+    Ok(string("7"))
+  );
+  assert_eq!(
+    cast(DataValue::Float(0.5), DataType::String),
+    Ok(string("0.5"))
+  );
+  assert_eq!(
+    cast(DataValue::Boolean(false), DataType::String),
+    Ok(string("false"))
+  );
+}
+
+#[test]
+fn arrays_and_bytes_cast()
+{
+  let string = |x: &str| DataValue::String(x.to_string());
+  let chars = DataValue::Array(vec![string("h"), string("i")]);
+  assert_eq!(cast(string("hi"), DataType::Array), Ok(chars.clone()));
+  assert_eq!(cast(chars, DataType::String), Ok(string("hi")));
+  assert_eq!(
+    cast(
+      DataValue::Array(vec![DataValue::Integer(1), string("a")]),
+      DataType::String
+    ),
+    Ok(string("1a"))
+  );
+  assert_eq!(
+    cast(DataValue::Byte(255), DataType::Integer),
+    Ok(DataValue::Integer(255))
+  );
+  assert_eq!(
+    cast(DataValue::Integer(10), DataType::Byte),
+    Ok(DataValue::Byte(10))
+  );
+  assert_eq!(cast(DataValue::Integer(256), DataType::Byte).ok(), None);
+  assert_eq!(cast(DataValue::None, DataType::Any), Ok(DataValue::None));
+}
+
+#[test]
+fn matrix_covers_every_cast()
+{
+  let values = [
+    DataValue::String("1".to_string()),
+    DataValue::Integer(1),
+    DataValue::Float(1.0),
+    DataValue::Boolean(true),
+    DataValue::Byte(1),
+    DataValue::Array(vec![]),
+    DataValue::Bytes(vec![]),
+    DataValue::None,
+  ];
+  let types = [
+    DataType::String,
+    DataType::Integer,
+    DataType::Float,
+    DataType::Boolean,
+    DataType::Byte,
+    DataType::Array,
+    DataType::Bytes,
+    DataType::Handle,
+    DataType::Object,
+    DataType::None,
+    DataType::Any,
+  ];
+  for value in &values
+  {
+    for to in &types
+    {
+      // the matrix may allow casts a value fails at, never the other way round
+      if value.try_cast(to.clone()).is_ok()
+      {
+        assert!(value.get_type().casts_to(to), "{value:?} to {to}");
+      }
+    }
+  }
+  assert!(DataType::Any.casts_to(&DataType::Handle));
+}
+
+#[test]
+fn validation_rejects_impossible_casts()
+{
+  let build = |from: DataType| {
+    let mut graph = GraphBuilder::new(vec![from.clone()], vec![DataType::Integer]);
+    let start = graph.add_node(NodeType::Atomic(AtomicType::Control(ControlFlow::Start)));
+    let cast = graph.add_node(NodeType::Atomic(AtomicType::Cast(DataType::Integer)));
+    let end = graph.add_node(NodeType::Atomic(AtomicType::Control(ControlFlow::End)));
+    graph
+      .trigger(start, 0, cast)
+      .trigger(cast, 0, end)
+      .connect(start, 0, cast, from)
+      .connect(cast, 0, end, DataType::Integer)
+      .set_end(end);
+    (cast, graph.build())
+  };
+  assert!(build(DataType::String).1.is_ok());
+  assert!(build(DataType::Any).1.is_ok());
+  let (cast, result) = build(DataType::Handle);
+  assert!(matches!(
+    result,
+    Err(EvalError::InvalidCast { node, from: DataType::Handle, to: DataType::Integer }) if node == cast
+  ));
+}
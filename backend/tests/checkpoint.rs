@@ -0,0 +1,144 @@
+mod common;
+
+use agent_nodes_core::{eval::Checkpoint, DataValue, Evaluator, NodeStateLogger};
+use common::{example, graph, interrupt, run_graph_output, spawn_graph, wait_for_interrupted};
+use std::{fs, path::Path, sync::Arc, thread, time::Duration};
+use uuid::Uuid;
+
+fn load(path: &Path) -> Arc<Evaluator<NodeStateLogger, NodeStateLogger>>
+{
+  Evaluator::new(path.to_str().unwrap().to_string(), None, None, None).unwrap()
+}
+
+fn node(n: u128) -> Uuid
+{
+  Uuid::from_u128(n)
+}
+
+/// The messages of a conversation as `ExportHistory` gives it
+fn messages(history: &DataValue) -> &[DataValue]
+{
+  match history
+  {
+    DataValue::Object(x) =>
+    {
+      match &x["messages"]
+      {
+        DataValue::Array(messages) => messages,
+        _ => panic!("{history}"),
+      }
+    }
+    _ => panic!("{history}"),
+  }
+}
+
+#[tokio::test]
+async fn resumed_counters_carry_on()
+{
+  let eval = load(&graph("counter_stopwatch.json"));
+  let instance = eval.clone().instantiate(vec![]).await;
+  instance.get_outputs().await.unwrap();
+  let mut checkpoint = instance.clone().checkpoint().await;
+  instance.shutdown().await;
+  assert_eq!(checkpoint.inputs, [Vec::<DataValue>::new()]);
+  // the loop counter stops at 3
+  assert_eq!(checkpoint.state.stored[&node(3)], DataValue::Integer(3));
+
+  // as if stopped after the first iteration
+  checkpoint
+    .state
+    .stored
+    .insert(node(3), DataValue::Integer(1));
+  let trace = eval.record_trace().await;
+  let instance = eval.resume(checkpoint).await;
+  let outputs = instance.get_outputs().await.unwrap();
+  instance.shutdown().await;
+  assert_eq!(outputs[0], DataValue::Integer(3));
+  let counts: Vec<usize> = trace
+    .entries()
+    .iter()
+    .filter(|x| x.node == node(3))
+    .map(|x| x.iteration)
+    .collect();
+  assert_eq!(counts, [1, 2]);
+}
+
+#[tokio::test]
+async fn agents_resume_their_conversations()
+{
+  let eval = load(&example("two_agent_conversation.json"));
+  let instance = eval.clone().instantiate(vec![]).await;
+  instance.get_outputs().await.unwrap();
+  let checkpoint = instance.clone().checkpoint().await;
+  instance.shutdown().await;
+  // both Create nodes made an agent
+  assert_eq!(checkpoint.state.agents.len(), 2, "{checkpoint:#?}");
+  let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("conversation.checkpoint.json");
+  checkpoint.save(&path).unwrap();
+  let checkpoint = Checkpoint::load(&path).unwrap();
+
+  let instance = eval.resume(checkpoint.clone()).await;
+  instance.get_outputs().await.unwrap();
+  let resumed = instance.clone().checkpoint().await;
+  instance.shutdown().await;
+  for (node, history) in &checkpoint.state.agents
+  {
+    let before = messages(history);
+    let after = messages(&resumed.state.agents[node]);
+    // the conversation went on from where it was
+    assert_eq!(after.len(), before.len() * 2, "{resumed:#?}");
+    assert_eq!(after[..before.len()], before[..]);
+  }
+}
+
+#[test]
+fn interrupted_runs_write_a_checkpoint_to_resume()
+{
+  let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("checkpoint");
+  fs::create_dir_all(&dir).unwrap();
+  let path = dir.join("hangs.json");
+  let _ = fs::remove_file(&path);
+  let hangs = graph("subgraphs/hangs.json");
+  // the listener is accepting when interrupted, no need to wait for it
+  let config = dir.join("agentnodes.toml");
+  fs::write(&config, "[timeouts]\nshutdown_grace_secs = 0.1\n").unwrap();
+
+  let child = spawn_graph(
+    &hangs,
+    &[
+      "--config",
+      config.to_str().unwrap(),
+      "--checkpoint",
+      path.to_str().unwrap(),
+      "--checkpoint-every",
+      "0.05",
+    ],
+  );
+  while !path.exists()
+  {
+    thread::sleep(Duration::from_millis(20));
+  }
+  interrupt(&child);
+  wait_for_interrupted(child, &hangs);
+  let checkpoint = Checkpoint::load(&path).unwrap();
+  assert!(
+    checkpoint.graph_path.ends_with("subgraphs/hangs.json"),
+    "{checkpoint:?}"
+  );
+  // the listener's handle is not kept
+  assert!(checkpoint.state.stored.is_empty(), "{checkpoint:?}");
+
+  let output = run_graph_output(
+    &hangs,
+    &["--resume", path.to_str().unwrap(), "--deadline", "0.2"],
+  );
+  assert_eq!(output.status.code(), Some(124));
+
+  let output = run_graph_output(
+    &example("subgraph.json"),
+    &["--resume", path.to_str().unwrap()],
+  );
+  assert_eq!(output.status.code(), Some(2));
+  let stderr = String::from_utf8(output.stderr).unwrap();
+  assert!(stderr.contains(": checkpoint of "), "{stderr}");
+}
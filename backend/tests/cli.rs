@@ -0,0 +1,272 @@
+use std::process::Command;
+
+fn backend(args: &[&str]) -> String
+{
+  let output = Command::new(env!("CARGO_BIN_EXE_backend"))
+    .args(args)
+    .output()
+    .expect("failed to start backend");
+  assert!(
+    output.status.success(),
+    "{:?} exited with {}",
+    args,
+    output.status
+  );
+  String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn version_names_commit_format_and_features()
+{
+  let out = backend(&["--version"]);
+  assert!(
+    out.starts_with(concat!("agent_nodes ", env!("CARGO_PKG_VERSION"), " (")),
+    "{out}"
+  );
+  let features: Vec<String> = agent_nodes_core::capabilities::compiled_in()
+    .iter()
+    .map(ToString::to_string)
+    .collect();
+  let tail = format!(", graph format 2, with [{}])", features.join(", "));
+  assert!(out.trim_end().ends_with(&tail), "{out}");
+}
+
+#[test]
+fn completions()
+{
+  let out = backend(&["completions", "bash"]);
+  assert!(out.contains("complete -F _backend"), "{out}");
+  assert!(out.contains("completions"), "{out}");
+}
+
+#[test]
+fn run_subcommand()
+{
+  let path = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/subgraph.json");
+  assert!(backend(&["run", path, "--print-output"]).contains("Ok([Integer(84)])"));
+}
+
+#[test]
+fn durations_must_be_positive_numbers()
+{
+  let path = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/subgraph.json");
+  for (flag, expected) in [
+    ("--deadline=-1", "expected more than 0 seconds, got -1"),
+    ("--deadline=NaN", "expected more than 0 seconds, got NaN"),
+    (
+      "--checkpoint-every=0",
+      "expected more than 0 seconds, got 0",
+    ),
+    ("--deadline=1e300", "1e300 seconds: "),
+    (
+      "--deadline=soon",
+      "expected a number of seconds, got `soon`",
+    ),
+  ]
+  {
+    let output = Command::new(env!("CARGO_BIN_EXE_backend"))
+      .args(["run", path, flag])
+      .output()
+      .expect("failed to start backend");
+    assert_eq!(output.status.code(), Some(2), "{flag}");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains(expected), "{flag}: {stderr}");
+  }
+}
+
+#[test]
+fn describe_shows_graph_documentation()
+{
+  let path = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/examples/subgraphs/double.json"
+  );
+  assert_eq!(
+    backend(&["describe", path]),
+    concat!(
+      "Double\n",
+      "Multiplies a number by two.\n",
+      "Author: AgentNodes examples\n",
+      "Inputs:\n",
+      "  0: Integer - the number to double\n",
+      "Outputs:\n",
+      "  0: Integer - twice the input\n"
+    )
+  );
+}
+
+#[test]
+fn eval_scores_a_dataset()
+{
+  let dir = env!("CARGO_MANIFEST_DIR");
+  let report = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("eval_report.json");
+  let output = Command::new(env!("CARGO_BIN_EXE_backend"))
+    .args([
+      "eval",
+      &format!("{dir}/examples/subgraphs/double.json"),
+      "--dataset",
+      &format!("{dir}/tests/data/double.jsonl"),
+      // the mock answers with its model name first, so this judge always passes
+      "--judge",
+      "Mock:PASS",
+      "--report",
+      report.to_str().unwrap(),
+    ])
+    .output()
+    .unwrap();
+  // one case expects the wrong value
+  assert_eq!(output.status.code(), Some(1));
+  let out = String::from_utf8(output.stdout).unwrap();
+  assert!(
+    out.contains("case 2: FAIL in ") && out.contains("  exact: expected [Integer(5)]\n"),
+    "{out}"
+  );
+  assert!(out.ends_with("3/4 cases passed\n"), "{out}");
+
+  let report: serde_json::Value =
+    serde_json::from_str(&std::fs::read_to_string(report).unwrap()).unwrap();
+  assert_eq!(report["passed"], 3);
+  assert_eq!(report["cases"][1]["outputs"][0], 4);
+  assert_eq!(report["cases"][2]["checks"][0]["kind"], "regex");
+  assert_eq!(report["cases"][3]["checks"][0]["kind"], "judge");
+}
+
+#[test]
+fn eval_refuses_judges_that_cannot_be_created()
+{
+  let dir = env!("CARGO_MANIFEST_DIR");
+  let output = Command::new(env!("CARGO_BIN_EXE_backend"))
+    .args([
+      "eval",
+      &format!("{dir}/examples/subgraphs/double.json"),
+      "--dataset",
+      &format!("{dir}/tests/data/double.jsonl"),
+      "--judge",
+      "OpenRouter:some-model",
+    ])
+    .output()
+    .unwrap();
+  assert_eq!(output.status.code(), Some(2));
+  let stderr = String::from_utf8(output.stderr).unwrap();
+  assert!(
+    stderr.contains("OpenRouter agents are not supported yet"),
+    "{stderr}"
+  );
+}
+
+#[test]
+fn compare_diffs_two_graphs()
+{
+  let dir = env!("CARGO_MANIFEST_DIR");
+  let output = Command::new(env!("CARGO_BIN_EXE_backend"))
+    .args([
+      "compare",
+      &format!("{dir}/examples/subgraphs/double.json"),
+      // squares its input, sending a message to a mock agent first
+      &format!("{dir}/tests/graphs/square.json"),
+      "--inputs",
+      &format!("{dir}/tests/data/numbers.jsonl"),
+    ])
+    .output()
+    .unwrap();
+  assert_eq!(output.status.code(), Some(1));
+  let out = String::from_utf8(output.stdout).unwrap();
+  let lines: Vec<&str> = out.lines().collect();
+  assert!(lines[0].starts_with("case 1: same, a "), "{out}");
+  assert!(lines[0].ends_with("ms 3 tokens"), "{out}");
+  assert!(lines[1].starts_with("case 2: DIFFERENT, a "), "{out}");
+  assert_eq!(lines[2..4], ["  a: [Integer(6)]", "  b: [Integer(9)]"]);
+  assert!(lines[4].starts_with("1/2 cases the same, a "), "{out}");
+  assert!(lines[4].ends_with("ms 6 tokens"), "{out}");
+}
+
+#[test]
+fn compare_prices_agent_calls()
+{
+  let dir = env!("CARGO_MANIFEST_DIR");
+  let config = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("compare_priced.toml");
+  std::fs::write(
+    &config,
+    "[pricing.m]\nprompt_per_million = 1000000\ncompletion_per_million = 1000000\n",
+  )
+  .unwrap();
+  let output = Command::new(env!("CARGO_BIN_EXE_backend"))
+    .args([
+      "compare",
+      &format!("{dir}/examples/subgraphs/double.json"),
+      &format!("{dir}/tests/graphs/square.json"),
+      "--inputs",
+      &format!("{dir}/tests/data/numbers.jsonl"),
+      "--config",
+      config.to_str().unwrap(),
+    ])
+    .output()
+    .unwrap();
+  let out = String::from_utf8(output.stdout).unwrap();
+  let lines: Vec<&str> = out.lines().collect();
+  // the graph without agents has nothing to price
+  assert!(lines[0].contains("ms 0 tokens, b "), "{out}");
+  assert!(lines[0].ends_with("ms 3 tokens $3.0000"), "{out}");
+  assert!(lines[4].ends_with("ms 6 tokens $6.0000"), "{out}");
+}
+
+#[test]
+fn test_runs_a_manifest()
+{
+  let dir = env!("CARGO_MANIFEST_DIR");
+  let output = Command::new(env!("CARGO_BIN_EXE_backend"))
+    .args(["test", &format!("{dir}/tests/data/graph_tests.json")])
+    .output()
+    .unwrap();
+  // one test expects the wrong value
+  assert_eq!(output.status.code(), Some(1));
+  let out = String::from_utf8(output.stdout).unwrap();
+  assert!(out.contains("test double 21: pass in "), "{out}");
+  assert!(
+    out.contains("test double 2: FAIL in ")
+      && out.contains("ms\n  output 0: expected Integer(5), got Integer(4)\n"),
+    "{out}"
+  );
+  // the agents answered from the mocks
+  assert!(out.contains("test conversation: pass in "), "{out}");
+  assert!(out.ends_with("2/3 tests passed\n"), "{out}");
+
+  let out = backend(&[
+    "test",
+    &format!("{dir}/tests/data/graph_tests.json"),
+    "--filter",
+    "21",
+  ]);
+  assert!(out.ends_with("1/1 tests passed\n"), "{out}");
+}
+
+#[test]
+fn schemas_cover_the_node_language()
+{
+  let bundle: serde_json::Value = serde_json::from_str(&backend(&["--print-schemas"])).unwrap();
+  assert_eq!(bundle["title"], "Complex");
+  for name in ["DataValue", "DataType", "AtomicType", "Instance", "ArrayOp"]
+  {
+    assert!(bundle["$defs"][name].is_object(), "{name}");
+  }
+
+  // a type alone, with only what it refers to
+  let schema: serde_json::Value =
+    serde_json::from_str(&backend(&["--print-schemas", "ArrayOp"])).unwrap();
+  assert_eq!(schema["title"], "ArrayOp");
+  let defs: Vec<&String> = schema["$defs"].as_object().unwrap().keys().collect();
+  assert!(
+    defs.len() < bundle["$defs"].as_object().unwrap().len(),
+    "{defs:?}"
+  );
+  assert!(
+    defs.iter().all(|x| bundle["$defs"][x.as_str()].is_object()),
+    "{defs:?}"
+  );
+
+  let output = Command::new(env!("CARGO_BIN_EXE_backend"))
+    .args(["--print-schemas", "Nope"])
+    .output()
+    .unwrap();
+  assert_eq!(output.status.code(), Some(2));
+}
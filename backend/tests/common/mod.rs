@@ -0,0 +1,163 @@
+// each test binary uses a different subset of these helpers
+#![allow(dead_code)]
+
+use agent_nodes_core::language::nodes::{AtomicType, NodeType};
+use std::{
+  path::{Path, PathBuf},
+  process::{Child, Command, Output, Stdio},
+  time::{Duration, Instant},
+};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+pub fn example(name: &str) -> PathBuf
+{
+  Path::new(env!("CARGO_MANIFEST_DIR"))
+    .join("examples")
+    .join(name)
+}
+
+/// A graph from `tests/graphs`, which hold fixtures that are not meant as examples.
+pub fn graph(name: &str) -> PathBuf
+{
+  Path::new(env!("CARGO_MANIFEST_DIR"))
+    .join("tests")
+    .join("graphs")
+    .join(name)
+}
+
+fn command(path: &Path, args: &[&str]) -> Command
+{
+  let mut command = Command::new(env!("CARGO_BIN_EXE_backend"));
+  command
+    .arg(path)
+    .arg("--print-output")
+    .args(args)
+    .current_dir(env!("CARGO_MANIFEST_DIR"))
+    .stdin(Stdio::null())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::null());
+  command
+}
+
+/// Starts the binary on a graph file with `--print-output` and any extra `args`, with
+/// stdout piped.
+pub fn spawn_graph(path: &Path, args: &[&str]) -> Child
+{
+  command(path, args)
+    .spawn()
+    .expect("failed to start backend")
+}
+
+/// Sends SIGINT to a graph started with [`spawn_graph`], as Ctrl c would.
+pub fn interrupt(child: &Child)
+{
+  send_signal(child, "-INT");
+}
+
+/// Sends SIGTERM to a graph started with [`spawn_graph`].
+pub fn terminate(child: &Child)
+{
+  send_signal(child, "-TERM");
+}
+
+fn send_signal(child: &Child, signal: &str)
+{
+  let status = Command::new("kill")
+    .args([signal, &child.id().to_string()])
+    .status()
+    .unwrap();
+  assert!(status.success());
+}
+
+/// Runs a graph file through the binary with `--print-output` and any extra `args`,
+/// returning its stdout. Fails the test if the graph does not finish within [`TIMEOUT`].
+pub fn run_graph(path: &Path, args: &[&str]) -> String
+{
+  wait_for_exit(spawn_graph(path, args), path)
+}
+
+/// Starts a graph like [`spawn_graph`] but with stderr piped too.
+pub fn spawn_graph_piped(path: &Path, args: &[&str]) -> Child
+{
+  command(path, args)
+    .stderr(Stdio::piped())
+    .spawn()
+    .expect("failed to start backend")
+}
+
+/// Starts a graph like [`spawn_graph_piped`] with stdin piped as well, for graphs
+/// reading commands from it.
+pub fn spawn_graph_interactive(path: &Path, args: &[&str]) -> Child
+{
+  command(path, args)
+    .stdin(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .expect("failed to start backend")
+}
+
+/// Runs a graph like [`run_graph`] but with stderr piped too, returning its output
+/// whatever the exit status.
+pub fn run_graph_output(path: &Path, args: &[&str]) -> Output
+{
+  wait_for_output(spawn_graph_piped(path, args), path)
+}
+
+/// Waits for a graph started with [`spawn_graph`] to exit successfully and returns
+/// the rest of its stdout.
+pub fn wait_for_exit(child: Child, path: &Path) -> String
+{
+  let output = wait_for_output(child, path);
+  assert!(
+    output.status.success(),
+    "{} exited with {}",
+    path.display(),
+    output.status
+  );
+  String::from_utf8(output.stdout).unwrap()
+}
+
+/// Waits for a graph stopped with [`interrupt`] to exit with the code for Ctrl c and
+/// returns the rest of its stdout.
+pub fn wait_for_interrupted(child: Child, path: &Path) -> String
+{
+  let output = wait_for_output(child, path);
+  assert_eq!(
+    output.status.code(),
+    Some(130),
+    "{} exited with {}",
+    path.display(),
+    output.status
+  );
+  String::from_utf8(output.stdout).unwrap()
+}
+
+/// Waits for a graph to exit and returns its output whatever the exit status. Fails the
+/// test if it does not finish within [`TIMEOUT`].
+pub fn wait_for_output(mut child: Child, path: &Path) -> Output
+{
+  let start = Instant::now();
+  while child.try_wait().unwrap().is_none()
+  {
+    if start.elapsed() > TIMEOUT
+    {
+      child.kill().unwrap();
+      let output = child.wait_with_output().unwrap();
+      panic!(
+        "{} did not finish within {:?}, output:\n{}",
+        path.display(),
+        TIMEOUT,
+        String::from_utf8_lossy(&output.stdout)
+      );
+    }
+    std::thread::sleep(Duration::from_millis(10));
+  }
+  child.wait_with_output().unwrap()
+}
+
+/// A node of an atomic type, for graphs put together with `GraphBuilder`
+pub fn atomic(atomic: AtomicType) -> NodeType
+{
+  NodeType::Atomic(atomic)
+}
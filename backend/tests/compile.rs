@@ -0,0 +1,91 @@
+mod common;
+
+use agent_nodes_core::{eval::Bundle, language::nodes::NodeType};
+use common::{example, graph, run_graph};
+use std::{
+  fs,
+  path::{Path, PathBuf},
+  process::{Command, Output},
+};
+
+fn compile(path: &Path, output: &Path) -> Output
+{
+  Command::new(env!("CARGO_BIN_EXE_backend"))
+    .arg("compile")
+    .arg(path)
+    .arg("--output")
+    .arg(output)
+    .output()
+    .unwrap()
+}
+
+fn dir(name: &str) -> PathBuf
+{
+  let dir = Path::new(env!("CARGO_TARGET_TMPDIR"))
+    .join("compile")
+    .join(name);
+  let _ = fs::remove_dir_all(&dir);
+  fs::create_dir_all(&dir).unwrap();
+  dir
+}
+
+#[test]
+fn bundles_run_without_the_graphs_they_came_from()
+{
+  let dir = dir("moved");
+  fs::create_dir_all(dir.join("subgraphs")).unwrap();
+  for name in ["subgraph.json", "subgraphs/double.json"]
+  {
+    fs::copy(example(name), dir.join(name)).unwrap();
+  }
+  let bundle = dir.join("subgraph.bundle");
+  let output = compile(&dir.join("subgraph.json"), &bundle);
+  assert!(output.status.success());
+  let stderr = String::from_utf8(output.stderr).unwrap();
+  assert!(stderr.starts_with("compiled 2 graph(s) into "), "{stderr}");
+
+  let loaded = Bundle::read(bundle.to_str().unwrap()).unwrap().unwrap();
+  assert_eq!(loaded.root, "subgraph.json");
+  let keys: Vec<&String> = loaded.graphs.keys().collect();
+  assert_eq!(keys, ["subgraph.json", "subgraphs/double.json"]);
+  assert!(loaded.graphs["subgraph.json"]
+    .instances
+    .values()
+    .any(|x| x.node_type == NodeType::Complex("subgraphs/double.json".to_string())));
+
+  // smaller than the graphs' files together
+  let sources: u64 = ["subgraph.json", "subgraphs/double.json"]
+    .iter()
+    .map(|x| fs::metadata(dir.join(x)).unwrap().len())
+    .sum();
+  assert!(fs::metadata(&bundle).unwrap().len() < sources);
+
+  let expected = run_graph(&example("subgraph.json"), &[]);
+  fs::remove_file(dir.join("subgraph.json")).unwrap();
+  fs::remove_dir_all(dir.join("subgraphs")).unwrap();
+  assert_eq!(run_graph(&bundle, &[]), expected);
+}
+
+#[test]
+fn bundles_take_the_graphs_of_tools()
+{
+  let bundle = dir("tools").join("tools.bundle");
+  assert!(compile(&graph("tools.json"), &bundle).status.success());
+  let loaded = Bundle::read(bundle.to_str().unwrap()).unwrap().unwrap();
+  assert_eq!(loaded.graphs.len(), 2, "{:?}", loaded.graphs.keys());
+  assert_eq!(
+    run_graph(&bundle, &[]),
+    run_graph(&graph("tools.json"), &[])
+  );
+}
+
+#[test]
+fn graphs_that_fail_checks_are_not_compiled()
+{
+  let bundle = dir("cycle").join("ping.bundle");
+  let output = compile(&graph("ping.json"), &bundle);
+  assert_eq!(output.status.code(), Some(2));
+  let stderr = String::from_utf8(output.stderr).unwrap();
+  assert!(stderr.contains("use each other in a cycle"), "{stderr}");
+  assert!(!bundle.exists());
+}
@@ -0,0 +1,211 @@
+mod common;
+
+use common::{example, run_graph, run_graph_output, spawn_graph_piped};
+use std::{
+  fs,
+  io::{BufRead, BufReader},
+  path::Path,
+};
+
+#[test]
+fn discovered_config_adds_search_paths_and_log_settings()
+{
+  let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("config_search");
+  let _ = fs::remove_dir_all(&dir);
+  fs::create_dir_all(dir.join("graphs")).unwrap();
+  fs::create_dir_all(dir.join("lib")).unwrap();
+  fs::write(
+    dir.join("agentnodes.toml"),
+    "search_paths = [\"lib\"]\n\n[log]\ntask_stats = true\n",
+  )
+  .unwrap();
+  fs::copy(
+    example("subgraphs/double.json"),
+    dir.join("lib/double.json"),
+  )
+  .unwrap();
+  // the graph sits a directory below the config and has no copy of the subgraph
+  let graph = fs::read_to_string(example("subgraph.json"))
+    .unwrap()
+    .replace("subgraphs/double.json", "double.json");
+  let path = dir.join("graphs/subgraph.json");
+  fs::write(&path, graph).unwrap();
+
+  let out = run_graph(&path, &[]);
+  assert!(out.contains("84\nOk([Integer(84)])"), "{out}");
+  assert!(out.contains("TaskStats {"), "{out}");
+}
+
+#[test]
+fn sandbox_denies_processes()
+{
+  let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("config_sandbox");
+  fs::create_dir_all(&dir).unwrap();
+  let config = dir.join("strict.toml");
+  fs::write(&config, "[sandbox]\nallow_processes = false\n").unwrap();
+
+  let path = example("subprocess.json");
+  let output = run_graph_output(&path, &["--config", config.to_str().unwrap()]);
+  assert_eq!(output.status.code(), Some(2));
+  let stderr = String::from_utf8(output.stderr).unwrap();
+  assert!(
+    stderr.starts_with(&format!("{}: node ", config.display())),
+    "{stderr}"
+  );
+  assert!(stderr.ends_with("needs processes\n"), "{stderr}");
+}
+
+#[test]
+fn negative_shutdown_grace_is_refused()
+{
+  let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("config_grace");
+  fs::create_dir_all(&dir).unwrap();
+  let config = dir.join("grace.toml");
+  fs::write(&config, "[timeouts]\nshutdown_grace_secs = -1\n").unwrap();
+
+  let path = example("subgraphs/double.json");
+  let output = run_graph_output(&path, &["--config", config.to_str().unwrap()]);
+  assert_eq!(output.status.code(), Some(2));
+  let stderr = String::from_utf8(output.stderr).unwrap();
+  assert!(
+    stderr.starts_with(&format!(
+      "invalid config {}: [timeouts] shutdown_grace_secs of -1: ",
+      config.display()
+    )),
+    "{stderr}"
+  );
+}
+
+#[test]
+fn unusable_http_timeouts_are_refused()
+{
+  let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("config_http_timeouts");
+  fs::create_dir_all(&dir).unwrap();
+  let path = example("subgraphs/double.json");
+  for (setting, message) in [
+    (
+      "http_request_secs = 0",
+      "http_request_secs must be more than 0, got 0",
+    ),
+    ("connect_secs = -2.5", "connect_secs of -2.5: "),
+    ("connect_secs = nan", "connect_secs of NaN: "),
+    ("http_request_secs = inf", "http_request_secs of inf: "),
+  ]
+  {
+    let config = dir.join("timeouts.toml");
+    fs::write(&config, format!("[timeouts]\n{setting}\n")).unwrap();
+    let output = run_graph_output(&path, &["--config", config.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(2), "{setting}");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+      stderr.starts_with(&format!(
+        "invalid config {}: [timeouts] {message}",
+        config.display()
+      )),
+      "{stderr}"
+    );
+  }
+}
+
+#[test]
+fn value_limits_fail_the_producing_node()
+{
+  let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("config_limits");
+  fs::create_dir_all(&dir).unwrap();
+  let config = dir.join("limits.toml");
+  fs::write(&config, "[limits]\nmax_string_len = 10\n").unwrap();
+
+  let path = example("two_agent_conversation.json");
+  let mut child = spawn_graph_piped(&path, &["--config", config.to_str().unwrap()]);
+  let stderr = BufReader::new(child.stderr.take().unwrap());
+  let failure = stderr
+    .lines()
+    .map(|x| x.unwrap())
+    .find(|x| x.contains("node failed"));
+  child.kill().unwrap();
+  child.wait().unwrap();
+  // the Value node holding the opening message
+  assert!(
+    failure.as_ref().is_some_and(|x| x.ends_with(
+      "node 00000000-0000-0000-0000-000000000007 output a value over the limits: String of 20 bytes, limit is 10"
+    )),
+    "{failure:?}"
+  );
+}
+
+#[test]
+fn json_logs_carry_the_node_span()
+{
+  let output = run_graph_output(
+    &example("subgraph.json"),
+    &["--log-level", "debug", "--log-format", "json"],
+  );
+  let stdout = String::from_utf8(output.stdout).unwrap();
+  let stderr = String::from_utf8(output.stderr).unwrap();
+  assert!(output.status.success(), "{stderr}");
+  // logs stay off stdout
+  assert_eq!(stdout, "84\nOk([Integer(84)])\n");
+  let evaluated: Vec<serde_json::Value> = stderr
+    .lines()
+    .map(|x| serde_json::from_str(x).unwrap())
+    .filter(|x: &serde_json::Value| x["message"] == "node evaluated")
+    .collect();
+  let print = evaluated
+    .iter()
+    .find(|x| x["span"]["node_type"] == "Print")
+    .expect(&stderr);
+  assert_eq!(print["span"]["name"], "node");
+  assert_eq!(print["level"], "DEBUG");
+  assert_eq!(
+    print["span"]["node"],
+    "00000000-0000-0000-0000-000000000005"
+  );
+  assert!(print["timestamp"].is_string(), "{print}");
+  assert_eq!(print["ok"], true);
+  assert!(print["elapsed_ms"].is_f64(), "{print}");
+}
+
+#[test]
+fn confined_graphs_stay_in_the_project()
+{
+  let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("config_confine");
+  let _ = fs::remove_dir_all(&dir);
+  fs::create_dir_all(dir.join("project")).unwrap();
+  fs::create_dir_all(dir.join("shared")).unwrap();
+  fs::copy(
+    example("subgraphs/double.json"),
+    dir.join("shared/double.json"),
+  )
+  .unwrap();
+  // `missing` does not exist, the `..` after it takes it off again
+  let graph = fs::read_to_string(example("subgraph.json"))
+    .unwrap()
+    .replace("subgraphs/double.json", "missing/../../shared/double.json");
+  let path = dir.join("project/subgraph.json");
+  fs::write(&path, graph).unwrap();
+
+  let out = run_graph(&path, &[]);
+  assert!(out.contains("Ok([Integer(84)])"), "{out}");
+
+  fs::write(
+    dir.join("project/agentnodes.toml"),
+    "[graphs]\nconfine = true\n",
+  )
+  .unwrap();
+  let output = run_graph_output(&path, &[]);
+  assert_eq!(output.status.code(), Some(2));
+  let stderr = String::from_utf8(output.stderr).unwrap();
+  assert!(
+    stderr.contains("shared/double.json is outside the project directory"),
+    "{stderr}"
+  );
+
+  // the project can be made wider
+  fs::write(
+    dir.join("project/agentnodes.toml"),
+    "[graphs]\nconfine = true\nproject_dir = \"..\"\n",
+  )
+  .unwrap();
+  let out = run_graph(&path, &[]);
+  assert!(out.contains("Ok([Integer(84)])"), "{out}");
+}
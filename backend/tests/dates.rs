@@ -0,0 +1,46 @@
+mod common;
+
+use agent_nodes_core::language::dates::DateTime;
+use common::{graph, run_graph};
+
+#[test]
+fn parse_shift_format_and_compare()
+{
+  let out = run_graph(&graph("dates.json"), &[]);
+  let expected = [
+    // parsed from -01:00 into UTC, then a day later past the leap day
+    r#"String("2024-03-01T00:30:00.500Z")"#,
+    r#"String("01/03/2024 00:30")"#,
+    "Integer(86400000)",
+    "Integer(-1)",
+    // now is after 2024
+    "Integer(1)",
+    // cast from a String and on to milliseconds since the epoch
+    "Integer(1000)",
+    r#"String("invalid date/time: \"2023-02-29\": input is out of range")"#,
+  ]
+  .join(", ");
+  assert!(out.contains(&format!("Ok([{expected}])")), "{out}");
+}
+
+#[test]
+fn patterns_take_milliseconds_and_fill_in_the_epoch()
+{
+  let time = DateTime::parse("01/03/2024 00:30:00.250", "%d/%m/%Y %H:%M:%S.%f").unwrap();
+  assert_eq!(time.to_string(), "2024-03-01T00:30:00.250Z");
+  assert_eq!(time.format("%Y %f %%").unwrap(), "2024 250 %");
+  // fields left out are those of the epoch, and offsets are taken off
+  assert_eq!(
+    DateTime::parse("12:30", "%H:%M").unwrap().to_string(),
+    "1970-01-01T12:30:00Z"
+  );
+  assert_eq!(
+    DateTime::parse("2024-03-01 09:30 +0200", "%Y-%m-%d %H:%M %z")
+      .unwrap()
+      .to_string(),
+    "2024-03-01T07:30:00Z"
+  );
+  assert!(time.format("%Q").is_err());
+  assert!(time.format("%").is_err());
+  assert!(DateTime::parse("10000-01-01", "%Y-%m-%d").is_err());
+}
@@ -0,0 +1,110 @@
+mod common;
+
+use agent_nodes_core::{
+  eval::{DebugCommand, NodeState},
+  Config, DataValue, Evaluator, NodeStateLogger,
+};
+use common::{example, spawn_graph_interactive, wait_for_output};
+use std::{
+  io::{BufRead, BufReader, Write},
+  time::Duration,
+};
+use uuid::Uuid;
+
+const VALUE: Uuid = Uuid::from_u128(2);
+const MUL: Uuid = Uuid::from_u128(3);
+const END: Uuid = Uuid::from_u128(4);
+
+async fn within<T>(future: impl std::future::Future<Output = T>) -> T
+{
+  tokio::time::timeout(Duration::from_secs(5), future)
+    .await
+    .expect("timed out")
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn breakpoints_pause_until_resumed()
+{
+  let graph = Evaluator::<NodeStateLogger, NodeStateLogger>::new(
+    example("subgraphs/double.json")
+      .to_str()
+      .unwrap()
+      .to_string(),
+    None,
+    None,
+    None,
+  )
+  .unwrap();
+  graph.set_config(Config::default()).await;
+  let debugger = graph.debug([MUL]).await;
+  let instance = graph.instantiate(vec![DataValue::Integer(21)]).await;
+
+  let pause = within(debugger.wait_for_pause()).await;
+  assert_eq!((pause.node, pause.node_type.as_str()), (MUL, "BinOp"));
+  assert_eq!(
+    pause.inputs,
+    [DataValue::Integer(21), DataValue::Integer(2)]
+  );
+  let snapshot = instance.inspect().await;
+  let node = |id| snapshot.nodes.iter().find(|x| x.node == id).unwrap();
+  assert_eq!(node(MUL).state, NodeState::Processing, "{snapshot}");
+  assert_eq!(node(VALUE).outputs, [DataValue::Integer(2)], "{snapshot}");
+  assert!(
+    tokio::time::timeout(Duration::from_millis(50), instance.get_outputs())
+      .await
+      .is_err(),
+    "the graph ran past the breakpoint"
+  );
+
+  // stepping pauses at the next node to start, the end node
+  assert!(debugger.resume(DebugCommand::Step));
+  let pause = within(debugger.wait_for_pause()).await;
+  assert_eq!(pause.node, END);
+  assert_eq!(pause.inputs, [DataValue::Integer(42)]);
+
+  assert!(debugger.resume(DebugCommand::Continue));
+  assert_eq!(
+    within(instance.get_outputs()).await.unwrap(),
+    [DataValue::Integer(42)]
+  );
+  assert_eq!(debugger.paused(), None);
+  instance.shutdown().await;
+}
+
+#[test]
+fn cli_debugger_reads_commands_from_stdin()
+{
+  let path = example("subgraph.json");
+  // the Print node
+  let mut child = spawn_graph_interactive(&path, &["--break", &Uuid::from_u128(5).to_string()]);
+  let mut stdin = child.stdin.take().unwrap();
+  let mut stderr = BufReader::new(child.stderr.take().unwrap()).lines();
+  let mut until = |text: &str| {
+    stderr
+      .by_ref()
+      .map(|x| x.unwrap())
+      .find(|x| x.contains(text))
+      .unwrap_or_else(|| panic!("no line with {text:?}"))
+  };
+
+  let paused = until("Paused before node");
+  assert!(
+    paused.contains("00000000-0000-0000-0000-000000000005 Print")
+      && paused.ends_with("with inputs [Integer(84)]"),
+    "{paused}"
+  );
+  writeln!(stdin, "inspect").unwrap();
+  let print = until("node 00000000-0000-0000-0000-000000000005");
+  assert!(
+    print.ends_with("Print: Processing, triggered 0/1"),
+    "{print}"
+  );
+  writeln!(stdin, "continue").unwrap();
+
+  let output = wait_for_output(child, &path);
+  assert!(output.status.success());
+  assert_eq!(
+    String::from_utf8(output.stdout).unwrap(),
+    "84\nOk([Integer(84)])\n"
+  );
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use agent_nodes_core::{Complex, EvalError};
+use common::{graph, run_graph};
+use std::process::Command;
+
+#[test]
+fn defaults_fill_inputs_left_out()
+{
+  let path = graph("defaults.json");
+  // input 1 defaults to 10 and the multiplier's unconnected port to 3
+  let out = run_graph(&path, &["--input", "0=2"]);
+  assert!(out.ends_with("Ok([Integer(36)])\n"), "{out}");
+  let out = run_graph(&path, &["--input", "0=2", "--input", "1=1"]);
+  assert!(out.ends_with("Ok([Integer(9)])\n"), "{out}");
+}
+
+#[test]
+fn describe_shows_input_defaults()
+{
+  let output = Command::new(env!("CARGO_BIN_EXE_backend"))
+    .args(["describe", graph("defaults.json").to_str().unwrap()])
+    .output()
+    .unwrap();
+  assert_eq!(
+    String::from_utf8(output.stdout).unwrap(),
+    concat!(
+      "Inputs:\n",
+      "  0: Integer - the amount\n",
+      "  1: Integer = 10 - what to add to it\n",
+      "Outputs:\n",
+      "  0: Integer\n"
+    )
+  );
+}
+
+#[test]
+fn defaults_must_be_for_an_input()
+{
+  let mut json: serde_json::Value =
+    serde_json::from_str(&std::fs::read_to_string(graph("defaults.json")).unwrap()).unwrap();
+  json["defaults"] = serde_json::json!({"2": 1});
+  let graph = Complex::from_json("defaults.json", json).unwrap();
+  assert!(matches!(
+    graph.validate(),
+    Err(EvalError::InvalidDefault { node: None, port }) if port == "2"
+  ));
+}
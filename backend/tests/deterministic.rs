@@ -0,0 +1,46 @@
+mod common;
+
+use agent_nodes_core::{Evaluator, NodeStateLogger};
+use common::{graph, run_graph};
+use uuid::Uuid;
+
+/// The nodes of a deterministic run of `name` in the order they started
+async fn run_order(name: &str) -> Vec<(Uuid, usize)>
+{
+  let eval = Evaluator::<NodeStateLogger, NodeStateLogger>::new(
+    graph(name).to_str().unwrap().to_string(),
+    None,
+    None,
+    None,
+  )
+  .unwrap();
+  eval.set_deterministic(true).await;
+  let trace = eval.record_trace().await;
+  let instance = eval.instantiate(vec![]).await;
+  instance.get_outputs().await.unwrap();
+  instance.shutdown().await;
+  trace
+    .entries()
+    .into_iter()
+    .map(|x| (x.node, x.iteration))
+    .collect()
+}
+
+#[tokio::test]
+async fn deterministic_runs_start_nodes_in_the_same_order()
+{
+  // branches joined by a Parallel node, which race when spawned
+  let first = run_order("parallel.json").await;
+  assert!(first.len() > 1);
+  for _ in 0..5
+  {
+    assert_eq!(run_order("parallel.json").await, first);
+  }
+}
+
+#[test]
+fn deterministic_flag_runs_graphs()
+{
+  let out = run_graph(&graph("fan_out.json"), &["--deterministic"]);
+  assert_eq!(out, "shared\nshared\nOk([String(\"shared\")])\n");
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use common::{example, run_graph, run_graph_output};
+use std::{fs, path::Path};
+
+#[test]
+fn dry_run_answers_with_scripted_replies()
+{
+  let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("dry_run_agents");
+  fs::create_dir_all(&dir).unwrap();
+  // the same conversation between agents that would ask OpenAI
+  let graph = fs::read_to_string(example("two_agent_conversation.json"))
+    .unwrap()
+    .replace("\"Mock\"", "\"OpenAi\"");
+  let path = dir.join("conversation.json");
+  fs::write(&path, graph).unwrap();
+  let config = dir.join("agentnodes.toml");
+  fs::write(
+    &config,
+    "[dry_run]\ndefault_response = \"{model} heard {message}\"\n\n\
+     [dry_run.responses]\nalice = [\"I am {model}\"]\n",
+  )
+  .unwrap();
+
+  let out = run_graph(&path, &["--dry-run", "--config", config.to_str().unwrap()]);
+  assert!(out.contains("I am alice\nbob heard I am alice\n"), "{out}");
+}
+
+#[test]
+fn dry_run_reads_tcp_connections_from_files()
+{
+  let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("dry_run_streams");
+  fs::create_dir_all(&dir).unwrap();
+  fs::write(dir.join("reply.txt"), "hello from the fixture\n").unwrap();
+  let config = dir.join("agentnodes.toml");
+  fs::write(
+    &config,
+    "[dry_run.streams]\n\"127.0.0.1:7878\" = \"reply.txt\"\n",
+  )
+  .unwrap();
+
+  // nothing listens on the port, the fixture answers instead
+  let out = run_graph(
+    &example("tcp_echo_client.json"),
+    &["--dry-run", "--config", config.to_str().unwrap()],
+  );
+  assert!(out.contains("hello from the fixture\n"), "{out}");
+}
+
+#[test]
+fn dry_run_refuses_other_network_nodes()
+{
+  let output = run_graph_output(&example("http_request.json"), &["--dry-run"]);
+  assert_eq!(output.status.code(), Some(2));
+  let stderr = String::from_utf8(output.stderr).unwrap();
+  assert!(stderr.starts_with("--dry-run: node "), "{stderr}");
+  assert!(stderr.ends_with("needs the network\n"), "{stderr}");
+}
@@ -0,0 +1,26 @@
+mod common;
+
+use common::{graph, run_graph, run_graph_output};
+
+#[test]
+fn enums_cast_to_and_from_string()
+{
+  let out = run_graph(&graph("enums.json"), &[]);
+  assert!(
+    out.contains(r#"Ok([String("happy"), String("happy"), Object({"#),
+    "{out}"
+  );
+  assert!(out.contains(r#""kind": String("InvalidVariant")"#), "{out}");
+}
+
+#[test]
+fn invalid_variant_is_rejected_on_load()
+{
+  let output = run_graph_output(&graph("bad_variant.json"), &[]);
+  assert_eq!(output.status.code(), Some(2));
+  let stderr = String::from_utf8(output.stderr).unwrap();
+  assert!(
+    stderr.ends_with("\"angry\" is not a variant of enum Mood\n"),
+    "{stderr}"
+  );
+}
@@ -0,0 +1,288 @@
+mod common;
+
+use common::{example, run_graph};
+use std::{
+  io::{BufRead, BufReader, Read, Write},
+  net::{TcpListener, TcpStream, UdpSocket},
+  path::Path,
+  time::{Duration, Instant},
+};
+
+#[test]
+fn file_lines()
+{
+  let out = run_graph(&example("file_lines.json"), &[]);
+  assert!(
+    out.contains("first line\nsecond line\nthird line\n"),
+    "{out}"
+  );
+}
+
+#[test]
+fn retry_pipeline()
+{
+  let out = run_graph(&example("retry_pipeline.json"), &[]);
+  assert!(
+    out.contains("attempt failed: 1\nattempt failed: 2\nsucceeded on attempt 3\n"),
+    "{out}"
+  );
+  assert!(out.contains("Ok([Integer(3)])"), "{out}");
+}
+
+#[test]
+fn two_agent_conversation()
+{
+  let out = run_graph(&example("two_agent_conversation.json"), &[]);
+  assert!(out.contains("alice: Hello, who is there?\n"), "{out}");
+  assert!(
+    out.contains(r#"Ok([String("bob: alice: Hello, who is there?")])"#),
+    "{out}"
+  );
+}
+
+#[test]
+fn tcp_echo_client()
+{
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let port = listener.local_addr().unwrap().port();
+  let server = std::thread::spawn(move || {
+    let (stream, _) = listener.accept().unwrap();
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line).unwrap();
+    (&stream).write_all(line.as_bytes()).unwrap();
+  });
+
+  // the example talks to a fixed port, point it at the one we got instead
+  let mut graph: serde_json::Value =
+    serde_json::from_str(&std::fs::read_to_string(example("tcp_echo_client.json")).unwrap())
+      .unwrap();
+  graph["instances"]["00000000-0000-0000-0000-000000000003"]["node_type"]["Atomic"]["Value"] =
+    port.into();
+  let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("tcp_echo_client.json");
+  std::fs::write(&path, graph.to_string()).unwrap();
+
+  let out = run_graph(&path, &[]);
+  server.join().unwrap();
+  assert!(
+    out.contains(r#"Ok([String("hello from agent nodes\n")])"#),
+    "{out}"
+  );
+}
+
+/// Writes `line` to the server and returns what it sends back, retrying the connection
+/// while the graph is still starting up.
+fn echo(port: u16, line: &str) -> String
+{
+  let start = Instant::now();
+  let stream = loop
+  {
+    match TcpStream::connect(("127.0.0.1", port))
+    {
+      Ok(stream) => break stream,
+      Err(e) if start.elapsed() > Duration::from_secs(5) => panic!("{e}"),
+      Err(_) => std::thread::sleep(Duration::from_millis(20)),
+    }
+  };
+  (&stream).write_all(line.as_bytes()).unwrap();
+  let mut reply = String::new();
+  BufReader::new(&stream).read_line(&mut reply).unwrap();
+  reply
+}
+
+#[test]
+fn tcp_echo_server()
+{
+  // find a free port for the server to bind instead of the fixed one in the example
+  let port = TcpListener::bind("127.0.0.1:0")
+    .unwrap()
+    .local_addr()
+    .unwrap()
+    .port();
+  let mut graph: serde_json::Value =
+    serde_json::from_str(&std::fs::read_to_string(example("tcp_echo_server.json")).unwrap())
+      .unwrap();
+  graph["instances"]["00000000-0000-0000-0000-000000000003"]["node_type"]["Atomic"]["Value"] =
+    port.into();
+  let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("tcp_echo_server.json");
+  std::fs::write(&path, graph.to_string()).unwrap();
+
+  let client = std::thread::spawn(move || {
+    assert_eq!(echo(port, "hello\n"), "hello\n");
+    assert_eq!(echo(port, "again\n"), "again\n");
+    assert_eq!(echo(port, "quit\n"), "quit\n");
+  });
+  let out = run_graph(&path, &[]);
+  client.join().unwrap();
+  assert!(out.contains(r#"Ok([String("server stopped")])"#), "{out}");
+}
+
+#[test]
+fn udp_ack()
+{
+  let port = UdpSocket::bind("127.0.0.1:0")
+    .unwrap()
+    .local_addr()
+    .unwrap()
+    .port();
+  let mut graph: serde_json::Value =
+    serde_json::from_str(&std::fs::read_to_string(example("udp_ack.json")).unwrap()).unwrap();
+  graph["instances"]["00000000-0000-0000-0000-000000000003"]["node_type"]["Atomic"]["Value"] =
+    port.into();
+  let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("udp_ack.json");
+  std::fs::write(&path, graph.to_string()).unwrap();
+
+  let client = std::thread::spawn(move || {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket
+      .set_read_timeout(Some(Duration::from_millis(50)))
+      .unwrap();
+    let start = Instant::now();
+    let mut buf = [0; 64];
+    // datagrams sent before the graph has bound its socket are dropped, keep trying
+    loop
+    {
+      assert!(start.elapsed() < Duration::from_secs(5), "no reply");
+      socket.send_to(b"ping", ("127.0.0.1", port)).unwrap();
+      if let Ok((count, _)) = socket.recv_from(&mut buf)
+      {
+        return String::from_utf8(buf[..count].to_vec()).unwrap();
+      }
+    }
+  });
+  let out = run_graph(&path, &[]);
+  assert_eq!(client.join().unwrap(), "ack: ping");
+  assert!(out.contains(r#"Ok([String("ping")])"#), "{out}");
+}
+
+#[test]
+fn http_request()
+{
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let port = listener.local_addr().unwrap().port();
+  let server = std::thread::spawn(move || {
+    let (stream, _) = listener.accept().unwrap();
+    let mut reader = BufReader::new(&stream);
+    let mut request = String::new();
+    let mut length = 0;
+    loop
+    {
+      let mut line = String::new();
+      reader.read_line(&mut line).unwrap();
+      if let Some(x) = line.to_lowercase().strip_prefix("content-length: ")
+      {
+        length = x.trim().parse().unwrap();
+      }
+      request.push_str(&line);
+      if line == "\r\n"
+      {
+        break;
+      }
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).unwrap();
+    let body = String::from_utf8(body).unwrap();
+    let reply = format!("you sent: {body}");
+    write!(
+      &stream,
+      "HTTP/1.1 201 Created\r\nx-reply: yes\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{reply}",
+      reply.len()
+    )
+    .unwrap();
+    (request, body)
+  });
+
+  let mut graph: serde_json::Value =
+    serde_json::from_str(&std::fs::read_to_string(example("http_request.json")).unwrap()).unwrap();
+  graph["instances"]["00000000-0000-0000-0000-000000000003"]["node_type"]["Atomic"]["Value"] =
+    format!("http://127.0.0.1:{port}/echo").into();
+  let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("http_request.json");
+  std::fs::write(&path, graph.to_string()).unwrap();
+
+  let out = run_graph(&path, &[]);
+  let (request, body) = server.join().unwrap();
+  assert!(request.starts_with("POST /echo HTTP/1.1\r\n"), "{request}");
+  assert!(request.contains("x-agent: agent nodes\r\n"), "{request}");
+  assert_eq!(body, "hello over http");
+  assert!(
+    out.contains("you sent: hello over http\nOk([Integer(201), Object({"),
+    "{out}"
+  );
+  assert!(out.contains(r#""x-reply": String("yes")"#), "{out}");
+  assert!(
+    out.ends_with(
+      r#"}), String("you sent: hello over http")])
+"#
+    ),
+    "{out}"
+  );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn websocket_client()
+{
+  use futures::{SinkExt, StreamExt};
+
+  let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+  let port = listener.local_addr().unwrap().port();
+  // echoes every frame back, prefixing text ones so the reply is told apart
+  let server = tokio::spawn(async move {
+    let (stream, _) = listener.accept().await.unwrap();
+    let (_, mut socket) = tokio_websockets::ServerBuilder::new()
+      .accept(stream)
+      .await
+      .unwrap();
+    while let Some(Ok(message)) = socket.next().await
+    {
+      let reply = match message.as_text()
+      {
+        Some(text) => tokio_websockets::Message::text(format!("echo: {text}")),
+        None if message.is_binary() => message,
+        None => continue,
+      };
+      socket.send(reply).await.unwrap();
+    }
+  });
+
+  let mut graph: serde_json::Value =
+    serde_json::from_str(&std::fs::read_to_string(example("websocket_client.json")).unwrap())
+      .unwrap();
+  graph["instances"]["00000000-0000-0000-0000-000000000002"]["node_type"]["Atomic"]["Value"] =
+    format!("ws://127.0.0.1:{port}").into();
+  let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("websocket_client.json");
+  std::fs::write(&path, graph.to_string()).unwrap();
+
+  let out = tokio::task::spawn_blocking(move || run_graph(&path, &[]))
+    .await
+    .unwrap();
+  server.abort();
+  assert!(
+    out.contains(r#"Ok([String("echo: hello over websocket"), Bytes([0, 1, 2, 255])])"#),
+    "{out}"
+  );
+}
+
+#[test]
+fn subprocess()
+{
+  let out = run_graph(&example("subprocess.json"), &[]);
+  assert!(
+    out.contains(r#"Ok([String("hello, world\n"), Integer(3)])"#),
+    "{out}"
+  );
+}
+
+#[test]
+fn subgraph()
+{
+  let out = run_graph(&example("subgraph.json"), &[]);
+  assert!(out.contains("84\nOk([Integer(84)])"), "{out}");
+}
+
+#[test]
+fn project()
+{
+  let out = run_graph(&example("project"), &[]);
+  assert!(out.contains("Ok([Integer(42)])"), "{out}");
+  let out = run_graph(&example("project"), &["--input", "number=5"]);
+  assert!(out.contains("Ok([Integer(10)])"), "{out}");
+}
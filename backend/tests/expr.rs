@@ -0,0 +1,129 @@
+mod common;
+
+use agent_nodes_core::{
+  language::{
+    expr::Expr,
+    nodes::{AtomicType, ControlFlow},
+  },
+  DataType, DataValue, EvalError, Evaluator, GraphBuilder, NodeStateLogger,
+};
+use common::atomic;
+
+/// A graph passing its two inputs to an `Expr` node and outputting the result
+fn expr_graph(source: &str) -> GraphBuilder
+{
+  let mut graph = GraphBuilder::new(
+    vec![DataType::Integer, DataType::String],
+    vec![DataType::Integer],
+  );
+  let start = graph.add_node(atomic(AtomicType::Control(ControlFlow::Start)));
+  let expr = graph.add_node(atomic(AtomicType::Expr(source.to_string())));
+  let end = graph.add_node(atomic(AtomicType::Control(ControlFlow::End)));
+  graph
+    .trigger(start, 0, expr)
+    .trigger(expr, 0, end)
+    .connect(start, 0, expr, DataType::Integer)
+    .connect(start, 1, expr, DataType::String)
+    .connect(expr, 0, end, DataType::Integer)
+    .set_end(end);
+  graph
+}
+
+fn eval(source: &str, inputs: &[DataValue]) -> Result<DataValue, EvalError>
+{
+  Expr::parse(source)?.evaluate(inputs)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn expr_nodes_read_their_inputs()
+{
+  let complex = expr_graph("a * 2 + len(b)").build().unwrap();
+  let eval = Evaluator::<NodeStateLogger, NodeStateLogger>::from_complex(
+    complex,
+    "expr.json".to_string(),
+    None,
+    None,
+    None,
+  )
+  .unwrap();
+  let instance = eval
+    .instantiate(vec![
+      DataValue::Integer(20),
+      DataValue::String("ab".to_string()),
+    ])
+    .await;
+  let outputs = instance.get_outputs().await.unwrap();
+  instance.clone().shutdown().await;
+  assert_eq!(outputs, vec![DataValue::Integer(42)]);
+}
+
+#[test]
+fn invalid_expressions_do_not_load()
+{
+  let error = expr_graph("a * (2 + b").build().unwrap_err();
+  assert_eq!(
+    error.to_string(),
+    r#"invalid expression "a * (2 + b": expected `)` at the end"#
+  );
+  let error = expr_graph("a + c").build().unwrap_err();
+  assert_eq!(
+    error.to_string(),
+    r#"invalid expression "a + c": reads 3 inputs but 2 are connected"#
+  );
+}
+
+#[test]
+fn precedence_and_associativity()
+{
+  assert_eq!(eval("1 + 2 * 3", &[]).unwrap(), DataValue::Integer(7));
+  assert_eq!(eval("(1 + 2) * 3", &[]).unwrap(), DataValue::Integer(9));
+  assert_eq!(eval("10 - 4 - 3", &[]).unwrap(), DataValue::Integer(3));
+  assert_eq!(eval("2 ** 3 ** 2", &[]).unwrap(), DataValue::Integer(512));
+  assert_eq!(
+    eval("-a % 4", &[DataValue::Integer(6)]).unwrap(),
+    DataValue::Integer(-2)
+  );
+  assert_eq!(
+    eval("1 < 2 && 2 <= 2 || false", &[]).unwrap(),
+    DataValue::Boolean(true)
+  );
+  assert_eq!(eval("!none", &[]).unwrap(), DataValue::Boolean(true));
+}
+
+#[test]
+fn values_and_functions()
+{
+  let inputs = [
+    DataValue::Float(1.5),
+    DataValue::Array(vec![DataValue::Integer(1), DataValue::Integer(2)]),
+  ];
+  assert_eq!(eval("a + 1", &inputs).unwrap(), DataValue::Float(2.5));
+  assert_eq!(
+    eval("len(b) == 2", &inputs).unwrap(),
+    DataValue::Boolean(true)
+  );
+  assert_eq!(
+    eval("max(3, a, 7.5)", &inputs).unwrap(),
+    DataValue::Float(7.5)
+  );
+  assert_eq!(
+    eval("min(abs(-4), 9)", &inputs).unwrap(),
+    DataValue::Integer(4)
+  );
+  assert_eq!(
+    eval(r#""n=" + str(int("41") + 1)"#, &inputs).unwrap(),
+    DataValue::String("n=42".to_string())
+  );
+  assert_eq!(
+    eval("float(3) / 2", &inputs).unwrap(),
+    DataValue::Float(1.5)
+  );
+  assert!(matches!(
+    eval(r#"a < "x""#, &inputs),
+    Err(EvalError::Incomparable(DataType::Float, DataType::String))
+  ));
+  assert!(matches!(
+    eval("frobnicate(a)", &inputs),
+    Err(EvalError::InvalidExpression(_))
+  ));
+}
@@ -0,0 +1,84 @@
+use agent_nodes_core::ffi::{
+  agentnodes_free, agentnodes_get_output_json, agentnodes_load, agentnodes_run,
+};
+use std::ffi::{CStr, CString};
+
+fn output(graph: *const agent_nodes_core::ffi::AgentNodesGraph) -> String
+{
+  unsafe { CStr::from_ptr(agentnodes_get_output_json(graph)) }
+    .to_str()
+    .unwrap()
+    .to_string()
+}
+
+#[test]
+fn graphs_run_through_the_c_abi()
+{
+  let path = CString::new(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/examples/subgraphs/double.json"
+  ))
+  .unwrap();
+  unsafe {
+    let graph = agentnodes_load(path.as_ptr());
+    assert!(!graph.is_null());
+    assert_eq!(output(graph), "");
+
+    let inputs = CString::new("[21]").unwrap();
+    let code = agentnodes_run(graph, inputs.as_ptr());
+    assert_eq!(code, 0, "{}", output(graph));
+    assert_eq!(output(graph), "[42]");
+
+    // the same graph can be run again
+    let inputs = CString::new("[5]").unwrap();
+    let code = agentnodes_run(graph, inputs.as_ptr());
+    assert_eq!(code, 0, "{}", output(graph));
+    assert_eq!(output(graph), "[10]");
+
+    let inputs = CString::new("not json").unwrap();
+    assert_eq!(agentnodes_run(graph, inputs.as_ptr()), -1);
+    assert!(
+      output(graph).starts_with(r#"{"error":"#),
+      "{}",
+      output(graph)
+    );
+    agentnodes_free(graph);
+  }
+}
+
+#[test]
+fn refused_graphs_load_as_null()
+{
+  let dir = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("ffi_refused");
+  let _ = std::fs::remove_dir_all(&dir);
+  std::fs::create_dir_all(&dir).unwrap();
+  std::fs::write(
+    dir.join("agentnodes.toml"),
+    "[sandbox]\nallow_processes = false\n",
+  )
+  .unwrap();
+  let graph = dir.join("subprocess.json");
+  std::fs::copy(
+    concat!(env!("CARGO_MANIFEST_DIR"), "/examples/subprocess.json"),
+    &graph,
+  )
+  .unwrap();
+  let path = CString::new(graph.to_str().unwrap()).unwrap();
+  assert!(unsafe { agentnodes_load(path.as_ptr()) }.is_null());
+
+  // Complex graphs using each other
+  let path = CString::new(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/graphs/ping.json"
+  ))
+  .unwrap();
+  assert!(unsafe { agentnodes_load(path.as_ptr()) }.is_null());
+}
+
+#[test]
+fn missing_graphs_load_as_null()
+{
+  let path = CString::new("/nonexistent/graph.json").unwrap();
+  assert!(unsafe { agentnodes_load(path.as_ptr()) }.is_null());
+  unsafe { agentnodes_free(std::ptr::null_mut()) };
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use agent_nodes_core::language::nodes::{FileMode, IoType};
+use common::{example, graph, run_graph};
+use std::{fs, path::Path};
+
+#[test]
+fn open_modes()
+{
+  let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("file_modes");
+  let _ = fs::remove_dir_all(&dir);
+  fs::create_dir_all(&dir).unwrap();
+  fs::write(dir.join("write.txt"), "old contents\n").unwrap();
+  fs::write(dir.join("append.txt"), "first\n").unwrap();
+  fs::write(dir.join("truncate.txt"), "old contents\n").unwrap();
+
+  // the graph writes "new\n" to each file, opened in the mode its name says
+  let mut graph: serde_json::Value =
+    serde_json::from_str(&fs::read_to_string(graph("file_modes.json")).unwrap()).unwrap();
+  for (id, name) in [
+    (3, "write.txt"),
+    (6, "append.txt"),
+    (9, "create.txt"),
+    (12, "truncate.txt"),
+  ]
+  {
+    graph["instances"][format!("00000000-0000-0000-0000-0000000000{id:02}")]["node_type"]
+      ["Atomic"]["Value"] = dir.join(name).to_str().unwrap().into();
+  }
+  let path = dir.join("file_modes.json");
+  fs::write(&path, graph.to_string()).unwrap();
+
+  let out = run_graph(&path, &[]);
+  assert!(out.contains(r#"Ok([String("done")])"#), "{out}");
+  let read = |name| fs::read_to_string(dir.join(name)).unwrap();
+  assert_eq!(read("write.txt"), "new\ncontents\n");
+  assert_eq!(read("append.txt"), "first\nnew\n");
+  assert_eq!(read("create.txt"), "new\n");
+  assert_eq!(read("truncate.txt"), "new\n");
+}
+
+#[test]
+fn files_opened_before_modes_are_read()
+{
+  assert_eq!(
+    serde_json::from_str::<IoType>(r#""File""#).unwrap(),
+    IoType::File(FileMode::Read)
+  );
+  let saved = fs::read_to_string(example("file_lines.json")).unwrap();
+  let old = saved.replace(r#""Open": {"File": "Read"}"#, r#""Open": "File""#);
+  assert_ne!(old, saved);
+  let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("file_lines_unmoded.json");
+  fs::write(&path, old).unwrap();
+  assert_eq!(
+    run_graph(&path, &[]),
+    run_graph(&example("file_lines.json"), &[])
+  );
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use agent_nodes_core::{Complex, Evaluator, NodeStateLogger, FORMAT_VERSION};
+use common::{example, graph, run_graph, run_graph_output};
+use serde_json::Value;
+use std::{fs, path::Path};
+
+/// `subgraphs/double.json` saved with `version`, or without one if None
+fn double(version: Option<u64>) -> Value
+{
+  let mut graph: Value =
+    serde_json::from_str(&fs::read_to_string(example("subgraphs/double.json")).unwrap()).unwrap();
+  if let Some(version) = version
+  {
+    graph["format_version"] = version.into();
+  }
+  graph
+}
+
+#[test]
+fn graphs_without_a_version_are_the_first()
+{
+  let graph = Complex::from_json("double.json", double(None)).unwrap();
+  assert_eq!(graph.format_version, FORMAT_VERSION);
+  // and are saved with it
+  let saved = serde_json::to_value(&graph).unwrap();
+  assert_eq!(saved["format_version"], FORMAT_VERSION);
+  let current = Complex::from_json("double.json", double(Some(FORMAT_VERSION.into()))).unwrap();
+  assert_eq!(current, graph);
+}
+
+#[test]
+fn graphs_from_newer_builds_are_refused()
+{
+  let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("format_version");
+  fs::create_dir_all(&dir).unwrap();
+  let path = dir.join("future.json");
+  let version = u64::from(FORMAT_VERSION) + 1;
+  fs::write(&path, double(Some(version)).to_string()).unwrap();
+
+  let err = Evaluator::<NodeStateLogger, NodeStateLogger>::new(
+    path.to_str().unwrap().to_string(),
+    None,
+    None,
+    None,
+  )
+  .err()
+  .unwrap();
+  assert_eq!(err.kind(), "UnsupportedFormatVersion");
+  let report = serde_json::to_value(&err).unwrap();
+  assert_eq!(report["path"], path.to_str().unwrap());
+
+  let output = run_graph_output(&path, &[]);
+  assert_eq!(output.status.code(), Some(2));
+  let stderr = String::from_utf8(output.stderr).unwrap();
+  assert!(
+    stderr.contains(&format!(
+      "is in format version {version}, this build reads up to {FORMAT_VERSION}"
+    )),
+    "{stderr}"
+  );
+}
+
+#[test]
+fn graphs_in_the_first_format_are_migrated()
+{
+  // file_lines.json as saved before `File` took the mode to open in
+  let path = graph("file_lines_v1.json");
+  let mut saved: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+  let instance = |id: u8| format!("00000000-0000-0000-0000-0000000000{id:02}");
+  // data that only looks like a node is left alone
+  saved["instances"][instance(6)]["node_type"]["Atomic"]["Value"] =
+    serde_json::json!({"Open": "File"});
+  let migrated =
+    serde_json::to_value(Complex::from_json("file_lines_v1.json", saved).unwrap()).unwrap();
+  assert_eq!(migrated["format_version"], FORMAT_VERSION);
+  assert_eq!(
+    migrated["instances"][instance(3)]["node_type"]["Atomic"]["Io"]["Open"],
+    serde_json::json!({"File": "Read"})
+  );
+  assert_eq!(
+    migrated["instances"][instance(6)]["node_type"]["Atomic"]["Value"],
+    serde_json::json!({"Open": "File"})
+  );
+
+  assert_eq!(
+    run_graph(&path, &[]),
+    run_graph(&example("file_lines.json"), &[])
+  );
+}
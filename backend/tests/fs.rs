@@ -0,0 +1,39 @@
+mod common;
+
+use common::{graph, run_graph};
+use std::{fs, path::Path};
+
+#[test]
+fn directory_operations()
+{
+  let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("fs_ops");
+  let _ = fs::remove_dir_all(&dir);
+  fs::create_dir_all(&dir).unwrap();
+  fs::write(dir.join("a.txt"), "a").unwrap();
+
+  // every path in the graph starts with BASE, point them into the fresh directory
+  let base = dir.to_str().unwrap();
+  let path = dir.join("fs_ops.json");
+  fs::write(
+    &path,
+    fs::read_to_string(graph("fs_ops.json"))
+      .unwrap()
+      .replace("BASE", base),
+  )
+  .unwrap();
+
+  let out = run_graph(&path, &[]);
+  let expected = format!(
+    concat!(
+      // after making sub/deeper and moving a.txt into sub
+      r#"Ok([Array([String("{0}/fs_ops.json"), String("{0}/sub")]), "#,
+      r#"Array([String("{0}/sub/b.txt"), String("{0}/sub/deeper")]), "#,
+      // a.txt is gone, then so is sub once deleted
+      r#"Boolean(false), Boolean(false), "#,
+      r#"Array([String("{0}/fs_ops.json")])])"#
+    ),
+    base
+  );
+  assert!(out.contains(&expected), "{out}");
+  assert!(!dir.join("sub").exists());
+}
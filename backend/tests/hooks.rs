@@ -0,0 +1,134 @@
+use agent_nodes_core::{
+  eval::NodeInfo,
+  language::nodes::{AtomicBinOp, AtomicType, NodeType},
+  Config, DataValue, EvalError, Evaluator, NodeHooks, NodeStateLogger,
+};
+use std::{
+  path::Path,
+  sync::{Arc, Mutex},
+  time::Duration,
+};
+
+#[derive(Default)]
+struct Recorder
+{
+  events: Mutex<Vec<String>>,
+  /// Reject nodes of this type
+  reject: Option<NodeType>,
+}
+
+impl Recorder
+{
+  fn push(&self, event: String)
+  {
+    self.events.lock().unwrap().push(event);
+  }
+}
+
+fn name(node_type: &NodeType) -> String
+{
+  format!("{node_type:?}")
+}
+
+#[async_trait::async_trait]
+impl NodeHooks for Recorder
+{
+  async fn on_node_start(&self, node: NodeInfo<'_>, inputs: &[DataValue]) -> Result<(), String>
+  {
+    self.push(format!("start {} {inputs:?}", name(node.node_type)));
+    match Some(node.node_type) == self.reject.as_ref()
+    {
+      true => Err("not allowed".to_string()),
+      false => Ok(()),
+    }
+  }
+
+  async fn on_value(&self, node: NodeInfo<'_>, port: usize, value: &DataValue)
+  {
+    self.push(format!("value {} {port} {value:?}", name(node.node_type)));
+  }
+
+  async fn on_node_end(&self, node: NodeInfo<'_>, _elapsed: Duration)
+  {
+    self.push(format!("end {}", name(node.node_type)));
+  }
+
+  async fn on_error(&self, node: NodeInfo<'_>, error: &EvalError)
+  {
+    self.push(format!("error {} {error}", name(node.node_type)));
+  }
+}
+
+async fn double(hooks: Arc<Recorder>) -> Result<Vec<DataValue>, String>
+{
+  let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/subgraphs/double.json");
+  let graph = Evaluator::<NodeStateLogger, NodeStateLogger>::new(
+    path.to_str().unwrap().to_string(),
+    None,
+    None,
+    None,
+  )
+  .unwrap();
+  graph.set_config(Config::default()).await;
+  graph.add_hooks(hooks).await;
+  let instance = graph.instantiate(vec![DataValue::Integer(21)]).await;
+  let outputs = instance.get_outputs().await.map_err(|e| {
+    match e
+    {
+      EvalError::NodeFailed { message, .. } => message,
+      e => e.to_string(),
+    }
+  });
+  instance.clone().shutdown().await;
+  outputs
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn hooks_see_every_node()
+{
+  let hooks = Arc::new(Recorder::default());
+  assert_eq!(
+    double(hooks.clone()).await,
+    Ok(vec![DataValue::Integer(42)])
+  );
+  let events = hooks.events.lock().unwrap().clone();
+  let mul = name(&NodeType::Atomic(AtomicType::BinOp(AtomicBinOp::Mul)));
+  let at = |event: &str| {
+    events
+      .iter()
+      .position(|x| x == event)
+      .unwrap_or_else(|| panic!("no {event:?} in {events:#?}"))
+  };
+  let start = at(&format!("start {mul} [Integer(21), Integer(2)]"));
+  let value = at(&format!("value {mul} 0 Integer(42)"));
+  let end = at(&format!("end {mul}"));
+  assert!(start < value && value < end, "{events:#?}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn start_hooks_can_reject_nodes()
+{
+  let mul = NodeType::Atomic(AtomicType::BinOp(AtomicBinOp::Mul));
+  let hooks = Arc::new(Recorder {
+    reject: Some(mul.clone()),
+    ..Default::default()
+  });
+  assert_eq!(
+    double(hooks.clone()).await,
+    Err("rejected by hook: not allowed".to_string())
+  );
+  let events = hooks.events.lock().unwrap().clone();
+  assert!(
+    events.contains(&format!(
+      "error {} rejected by hook: not allowed",
+      name(&mul)
+    )),
+    "{events:#?}"
+  );
+  assert!(
+    !events
+      .iter()
+      .any(|x| x.starts_with(&format!("end {}", name(&mul)))),
+    "{events:#?}"
+  );
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use common::{example, interrupt, spawn_graph, wait_for_interrupted};
+use std::{
+  io::{BufRead, BufReader, Read, Write},
+  net::{TcpListener, TcpStream},
+  path::Path,
+};
+
+/// Sends a raw request and returns the whole response.
+fn request(addr: &str, method: &str, path: &str, body: &str) -> String
+{
+  let mut stream = TcpStream::connect(addr).unwrap();
+  write!(
+    stream,
+    "{method} {path} HTTP/1.1\r\nhost: {addr}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+    body.len()
+  )
+  .unwrap();
+  let mut response = String::new();
+  stream.read_to_string(&mut response).unwrap();
+  response
+}
+
+#[test]
+fn routes_run_subgraphs_until_interrupted()
+{
+  let port = TcpListener::bind("127.0.0.1:0")
+    .unwrap()
+    .local_addr()
+    .unwrap()
+    .port();
+  let addr = format!("127.0.0.1:{port}");
+  let mut graph: serde_json::Value =
+    serde_json::from_str(&std::fs::read_to_string(example("http_server.json")).unwrap()).unwrap();
+  graph["instances"]["00000000-0000-0000-0000-000000000002"]["node_type"]["Atomic"]["Value"] =
+    addr.clone().into();
+  // the routes' subgraph paths are relative, so copy them along with the graph
+  let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("http_server");
+  std::fs::create_dir_all(dir.join("subgraphs")).unwrap();
+  for name in ["greet.json", "health.json"]
+  {
+    std::fs::copy(
+      example("subgraphs").join(name),
+      dir.join("subgraphs").join(name),
+    )
+    .unwrap();
+  }
+  let path = dir.join("http_server.json");
+  std::fs::write(&path, graph.to_string()).unwrap();
+
+  let mut child = spawn_graph(&path, &["--task-stats"]);
+  let mut stdout = BufReader::new(child.stdout.take().unwrap());
+  let mut line = String::new();
+  while !line.starts_with("Serving HTTP on")
+  {
+    line.clear();
+    assert_ne!(
+      stdout.read_line(&mut line).unwrap(),
+      0,
+      "graph exited early"
+    );
+  }
+
+  let greet = request(&addr, "POST", "/greet", "bob");
+  assert!(greet.starts_with("HTTP/1.1 200 OK\r\n"), "{greet}");
+  assert!(greet.contains("content-type: text/plain\r\n"), "{greet}");
+  assert!(greet.ends_with("\r\n\r\nhello, bob"), "{greet}");
+
+  let health = request(&addr, "GET", "/health", "");
+  assert!(health.starts_with("HTTP/1.1 200 OK\r\n"), "{health}");
+  assert!(health.ends_with("\r\n\r\nok"), "{health}");
+
+  let missing = request(&addr, "GET", "/missing", "");
+  assert!(
+    missing.starts_with("HTTP/1.1 404 Not Found\r\n"),
+    "{missing}"
+  );
+
+  interrupt(&child);
+  wait_for_interrupted(child, &path);
+  let mut rest = String::new();
+  stdout.read_to_string(&mut rest).unwrap();
+  assert!(
+    rest.contains(
+      "TaskStats { node_tasks: 0, complex_runners: 0, io_operations: 0, http_servers: 0, agent_requests_queued: 0, agent_requests_in_flight: 0 }"
+    ),
+    "{rest}"
+  );
+}
@@ -0,0 +1,33 @@
+mod common;
+
+use common::{graph, spawn_graph};
+use std::time::Duration;
+
+/// User and system CPU time used by a process so far, in clock ticks.
+fn cpu_ticks(pid: u32) -> u64
+{
+  let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).unwrap();
+  // fields after the parenthesised command name, utime and stime are the 12th and 13th
+  let fields: Vec<&str> = stat
+    .rsplit_once(')')
+    .unwrap()
+    .1
+    .split_whitespace()
+    .collect();
+  fields[11].parse::<u64>().unwrap() + fields[12].parse::<u64>().unwrap()
+}
+
+#[test]
+fn waiting_graph_uses_no_cpu()
+{
+  // blocks accepting a connection that never comes
+  let mut child = spawn_graph(&graph("subgraphs/hangs.json"), &[]);
+  std::thread::sleep(Duration::from_millis(300));
+  let before = cpu_ticks(child.id());
+  std::thread::sleep(Duration::from_secs(1));
+  let used = cpu_ticks(child.id()) - before;
+  child.kill().unwrap();
+  child.wait().unwrap();
+  // a busy loop would use around 100 ticks a second
+  assert!(used < 20, "used {used} ticks while idle");
+}
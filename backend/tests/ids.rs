@@ -0,0 +1,26 @@
+mod common;
+
+use common::{graph, run_graph};
+
+#[test]
+fn uuid_v5_and_conversions()
+{
+  let out = run_graph(&graph("ids.json"), &[]);
+  let expected = concat!(
+    // v5 of "python.org" in the DNS namespace, then in the nil namespace
+    r#"Ok([String("886313e1-3b8a-5372-9b90-0c9aee199e5d"), "#,
+    r#"String("93128362-2d8d-548e-84ff-e93cd1378be5"), "#,
+    // an uppercase UUID parsed back to lowercase
+    r#"String("6ba7b810-9dad-11d1-80b4-00c04fd430c8"), String(""#
+  );
+  assert!(out.starts_with(expected), "{out}");
+  // a handle's id as a String, which parses back to itself
+  let ids: Vec<&str> = out[expected.len()..]
+    .trim_end()
+    .trim_end_matches(r#"")])"#)
+    .split(r#""), String(""#)
+    .collect();
+  assert_eq!(ids.len(), 2, "{out}");
+  assert_eq!(ids[0], ids[1], "{out}");
+  assert_eq!(ids[0].len(), 36, "{out}");
+}
@@ -0,0 +1,57 @@
+mod common;
+
+use common::{example, run_graph, run_graph_output, spawn_graph_interactive, wait_for_exit};
+use std::{fs, io::Write, path::Path};
+
+#[test]
+fn inputs_are_given_by_port()
+{
+  let double = example("subgraphs/double.json");
+  let out = run_graph(&double, &["--input", "0=21"]);
+  assert!(out.contains("Ok([Integer(42)])"), "{out}");
+
+  // a file of them, which the flags win over
+  let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("inputs");
+  fs::create_dir_all(&dir).unwrap();
+  let file = dir.join("inputs.json");
+  fs::write(&file, "[4]").unwrap();
+  let file = file.to_str().unwrap();
+  let out = run_graph(&double, &["--input-file", file]);
+  assert!(out.contains("Ok([Integer(8)])"), "{out}");
+  let out = run_graph(&double, &["--input-file", file, "--input", "0=5"]);
+  assert!(out.contains("Ok([Integer(10)])"), "{out}");
+}
+
+#[test]
+fn inputs_are_read_from_stdin()
+{
+  let double = example("subgraphs/double.json");
+  let mut child = spawn_graph_interactive(&double, &["--stdin-input"]);
+  let mut stdin = child.stdin.take().unwrap();
+  stdin.write_all(b"{\"0\": 7}\n").unwrap();
+  // read until it closes
+  drop(stdin);
+  let out = wait_for_exit(child, &double);
+  assert!(out.contains("Ok([Integer(14)])"), "{out}");
+}
+
+#[test]
+fn missing_and_unknown_inputs_are_refused()
+{
+  for (args, expected) in [
+    (
+      &[][..],
+      "input 0 (Integer) has no value, give it with --input 0=VALUE\n",
+    ),
+    (
+      &["--input", "0=1", "--input", "1=2"][..],
+      "no input `1`, the graph's are numbered 0 to 0\n",
+    ),
+  ]
+  {
+    let output = run_graph_output(&example("subgraphs/double.json"), args);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.ends_with(expected), "{stderr}");
+  }
+}
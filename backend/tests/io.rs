@@ -0,0 +1,42 @@
+mod common;
+
+use common::{graph, run_graph};
+use std::{
+  fs,
+  io::{Read, Write},
+  net::TcpListener,
+  path::Path,
+};
+
+#[test]
+fn close_and_flush()
+{
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let port = listener.local_addr().unwrap().port();
+  // reads the first connection to EOF, which only comes once the graph closes it, and
+  // answers on the second
+  let server = std::thread::spawn(move || {
+    let (mut first, _) = listener.accept().unwrap();
+    let mut sent = String::new();
+    first.read_to_string(&mut sent).unwrap();
+    let (mut second, _) = listener.accept().unwrap();
+    write!(second, "got: {sent}").unwrap();
+  });
+
+  let dir = Path::new(env!("CARGO_TARGET_TMPDIR"));
+  let mut graph: serde_json::Value =
+    serde_json::from_str(&fs::read_to_string(graph("close_flush.json")).unwrap()).unwrap();
+  let instances = &mut graph["instances"];
+  instances["00000000-0000-0000-0000-000000000003"]["node_type"]["Atomic"]["Value"] = port.into();
+  instances["00000000-0000-0000-0000-000000000011"]["node_type"]["Atomic"]["Value"] =
+    dir.join("flushed.txt").to_str().unwrap().into();
+  let path = dir.join("close_flush.json");
+  fs::write(&path, graph.to_string()).unwrap();
+
+  let out = run_graph(&path, &[]);
+  server.join().unwrap();
+  assert!(
+    out.contains(r#"Ok([String("got: sent before closing\n"), String("sent before closing\n")])"#),
+    "{out}"
+  );
+}
@@ -0,0 +1,16 @@
+mod common;
+
+use common::{graph, run_graph};
+
+#[test]
+fn json_parses_into_values_and_back()
+{
+  let out = run_graph(&graph("json.json"), &[]);
+  // the code fence around the reply is dropped and its fields can be destructured
+  assert!(out.contains(r#"Ok([String("hi"), Integer(2), "#), "{out}");
+  assert!(
+    out.contains(r#"String("{\n  \"choices\": [\n    {\n      \"message\": {\n        \"content\": \"hi\"\n      }\n    }\n  ],\n  \"n\": 2\n}")"#),
+    "{out}"
+  );
+  assert!(out.contains(r#"String("InvalidJson")])"#), "{out}");
+}
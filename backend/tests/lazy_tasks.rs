@@ -0,0 +1,36 @@
+mod common;
+
+use agent_nodes_core::{Config, DataValue, Evaluator, NodeStateLogger};
+use common::graph;
+use std::time::Duration;
+
+#[tokio::test]
+async fn nodes_get_tasks_once_triggered_and_drop_them_when_idle()
+{
+  let eval = Evaluator::<NodeStateLogger, NodeStateLogger>::new(
+    graph("branch.json").to_str().unwrap().to_string(),
+    None,
+    None,
+    None,
+  )
+  .unwrap();
+  let mut config = Config::default();
+  config.graphs.idle_node_secs = Some(0.2);
+  eval.set_config(config).await;
+  let yes = [DataValue::String("yes".to_string())];
+
+  let instance = eval.instantiate(vec![DataValue::Boolean(true)]).await;
+  assert_eq!(instance.get_outputs().await.unwrap(), yes);
+  // the false branch was never taken
+  assert_eq!(instance.task_stats().await.node_tasks, 4);
+
+  // only the start node, waiting for inputs, outlasts the idle time
+  tokio::time::sleep(Duration::from_millis(800)).await;
+  assert_eq!(instance.task_stats().await.node_tasks, 1);
+
+  // and the others start again when next triggered
+  instance.send_inputs(vec![DataValue::Boolean(true)]).await;
+  assert_eq!(instance.get_outputs().await.unwrap(), yes);
+  assert_eq!(instance.task_stats().await.node_tasks, 4);
+  instance.shutdown().await;
+}
@@ -0,0 +1,44 @@
+use agent_nodes_core::{eval::Outcome, Config, DataValue, Evaluator, NodeStateLogger};
+use std::path::Path;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn graphs_run_embedded()
+{
+  let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/subgraphs/double.json");
+  let graph = Evaluator::<NodeStateLogger, NodeStateLogger>::new(
+    path.to_str().unwrap().to_string(),
+    None,
+    None,
+    None,
+  )
+  .unwrap();
+  graph.set_config(Config::default()).await;
+  let instance = graph.instantiate(vec![DataValue::Integer(21)]).await;
+  let outputs = instance.get_outputs().await.unwrap();
+  let report = instance.clone().shutdown().await;
+  assert_eq!(outputs, vec![DataValue::Integer(42)]);
+  assert_eq!(report.outcome, Outcome::Completed);
+  assert_eq!(report.nodes.len(), 4);
+  assert!(report.is_clean(), "{report:#}");
+}
+
+#[test]
+#[cfg(not(feature = "subprocess"))]
+fn graphs_needing_left_out_features_do_not_load()
+{
+  let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/subprocess.json");
+  let error = Evaluator::<NodeStateLogger, NodeStateLogger>::new(
+    path.to_str().unwrap().to_string(),
+    None,
+    None,
+    None,
+  )
+  .err()
+  .unwrap();
+  assert!(
+    error
+      .to_string()
+      .ends_with("needs subprocess support, which this build does not include"),
+    "{error}"
+  );
+}
@@ -0,0 +1,94 @@
+mod common;
+
+use agent_nodes_core::{
+  language::nodes::{AtomicBinOp, AtomicType, ControlFlow},
+  Complex, DataType, DataValue, Evaluator, GraphBuilder, NodeStateLogger,
+};
+use common::atomic;
+use std::{sync::Arc, time::Duration};
+
+const BRANCHES: i64 = 32;
+
+/// Adds the input to each of `BRANCHES` values on separate branches, all of which
+/// trigger one join at about the same time
+fn fan_in() -> Complex
+{
+  let mut graph = GraphBuilder::new(vec![DataType::Integer], vec![DataType::Array]);
+  let start = graph.add_node(atomic(AtomicType::Control(ControlFlow::Start)));
+  let join = graph.add_node(atomic(AtomicType::Control(ControlFlow::Parallel)));
+  let end = graph.add_node(atomic(AtomicType::Control(ControlFlow::End)));
+  for i in 0..BRANCHES
+  {
+    let value = graph.add_node(atomic(AtomicType::Value(DataValue::Integer(i))));
+    let add = graph.add_node(atomic(AtomicType::BinOp(AtomicBinOp::Add)));
+    graph
+      .trigger(start, 0, value)
+      .trigger(value, 0, add)
+      .trigger(add, 0, join)
+      .connect(start, 0, add, DataType::Integer)
+      .connect(value, 0, add, DataType::Integer)
+      .connect(add, 0, join, DataType::Integer);
+  }
+  graph
+    .trigger(join, 0, end)
+    .connect(join, 0, end, DataType::Array)
+    .set_end(end);
+  graph.build().unwrap()
+}
+
+fn expected(input: i64) -> Vec<DataValue>
+{
+  vec![DataValue::Array(
+    (0..BRANCHES)
+      .map(|i| DataValue::Integer(input + i))
+      .collect(),
+  )]
+}
+
+async fn evaluator() -> Arc<Evaluator<NodeStateLogger, NodeStateLogger>>
+{
+  Evaluator::from_complex(fan_in(), "fan_in.json".to_string(), None, None, None).unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn joins_count_every_trigger_across_runs()
+{
+  let eval = evaluator().await;
+  let instance = eval.instantiate(vec![DataValue::Integer(0)]).await;
+  for round in 0..100
+  {
+    if round > 0
+    {
+      instance.send_inputs(vec![DataValue::Integer(round)]).await;
+    }
+    // a lost trigger leaves the join waiting forever, a duplicated one runs it early
+    let outputs = tokio::time::timeout(Duration::from_secs(10), instance.get_outputs())
+      .await
+      .unwrap_or_else(|_| panic!("round {round} stalled"))
+      .unwrap();
+    assert_eq!(outputs, expected(round), "round {round}");
+  }
+  instance.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn concurrent_instances_do_not_lose_triggers()
+{
+  let eval = evaluator().await;
+  let runs = (0..16).map(|i| {
+    let eval = eval.clone();
+    tokio::spawn(async move {
+      let instance = eval.instantiate(vec![DataValue::Integer(i)]).await;
+      let outputs = tokio::time::timeout(Duration::from_secs(10), instance.get_outputs())
+        .await
+        .unwrap_or_else(|_| panic!("instance {i} stalled"))
+        .unwrap();
+      instance.shutdown().await;
+      assert_eq!(outputs, expected(i));
+    })
+  });
+  for run in runs.collect::<Vec<_>>()
+  {
+    run.await.unwrap();
+  }
+}
@@ -0,0 +1,130 @@
+mod common;
+
+use agent_nodes_core::{
+  language::nodes::{AtomicLogic, AtomicType, ControlFlow},
+  DataType, DataValue, Evaluator, GraphBuilder, NodeStateLogger,
+};
+use common::{atomic, graph, run_graph};
+
+#[test]
+fn equality_semantics()
+{
+  let cases = [
+    ("0.1 + 0.2 == 0.3", false),
+    ("0.1 + 0.2 ~= 0.3 within 1e-9", true),
+    ("Integer 1 == Float 1.0", true),
+    ("None == None", true),
+    ("None == false", false),
+    ("an agent equals itself", true),
+    ("two agents created from the same arguments differ", true),
+    ("a handle equals itself", true),
+    ("two handles on the same file are equal", false),
+  ];
+  let out = run_graph(&graph("equality.json"), &[]);
+  let expected = cases
+    .iter()
+    .map(|(_, x)| format!("Boolean({x})"))
+    .collect::<Vec<_>>()
+    .join(", ");
+  assert!(
+    out.contains(&format!("Ok([{expected}])")),
+    "expected {cases:?}, got {out}"
+  );
+}
+
+#[test]
+fn integers_equal_whole_floats_exactly()
+{
+  let float = DataValue::Float(9007199254740992.0);
+  assert!(DataValue::Integer(9007199254740992).equals(&float, 0.0));
+  assert!(!DataValue::Integer(9007199254740993).equals(&float, 0.0));
+  assert!(DataValue::Integer(9007199254740993).equals(&float, 1.0));
+  assert!(DataValue::Integer(2).equals(&DataValue::Float(2.5), 0.5));
+  assert!(!DataValue::Integer(2).equals(&DataValue::Float(2.5), 0.0));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn neq_is_true_for_unequal_inputs()
+{
+  let mut graph = GraphBuilder::new(vec![], vec![DataType::Boolean, DataType::Boolean]);
+  let start = graph.add_node(atomic(AtomicType::Control(ControlFlow::Start)));
+  let one = graph.add_node(atomic(AtomicType::Value(DataValue::Integer(1))));
+  let two = graph.add_node(atomic(AtomicType::Value(DataValue::Integer(2))));
+  let unequal = graph.add_node(atomic(AtomicType::LogicalOp(AtomicLogic::Neq)));
+  let equal = graph.add_node(atomic(AtomicType::LogicalOp(AtomicLogic::Neq)));
+  let end = graph.add_node(atomic(AtomicType::Control(ControlFlow::End)));
+  graph
+    .trigger(start, 0, one)
+    .trigger(one, 0, two)
+    .trigger(two, 0, unequal)
+    .trigger(unequal, 0, equal)
+    .trigger(equal, 0, end)
+    .connect(one, 0, unequal, DataType::Integer)
+    .connect(two, 0, unequal, DataType::Integer)
+    .connect(one, 0, equal, DataType::Integer)
+    .connect(one, 0, equal, DataType::Integer)
+    .connect(unequal, 0, end, DataType::Boolean)
+    .connect(equal, 0, end, DataType::Boolean)
+    .set_end(end);
+
+  let eval = Evaluator::<NodeStateLogger, NodeStateLogger>::from_complex(
+    graph.build().unwrap(),
+    "neq.json".to_string(),
+    None,
+    None,
+    None,
+  )
+  .unwrap();
+  let instance = eval.instantiate(vec![]).await;
+  let outputs = instance.get_outputs().await.unwrap();
+  instance.clone().shutdown().await;
+  assert_eq!(
+    outputs,
+    vec![DataValue::Boolean(true), DataValue::Boolean(false)]
+  );
+}
+
+#[test]
+fn select_between_values()
+{
+  let out = run_graph(&graph("select.json"), &[]);
+  // conditions are true, false and None, which counts as false
+  assert!(
+    out.contains(r#"Ok([String("then"), String("else"), String("else")])"#),
+    "{out}"
+  );
+}
+
+#[test]
+fn unary_math()
+{
+  let out = run_graph(&graph("unary.json"), &[]);
+  let expected = [
+    // Abs of -2.5, Sqrt of 16, then Floor, Ceil and Round of -2.5
+    "Float(2.5)",
+    "Float(4.0)",
+    "Integer(-3)",
+    "Integer(-2)",
+    "Integer(-3)",
+    // Exp and Cos of 0
+    "Float(1.0)",
+    "Float(1.0)",
+    r#"String("math error: Sqrt is undefined for Integer(-1)")"#,
+  ]
+  .join(", ");
+  assert!(out.contains(&format!("Ok([{expected}])")), "{out}");
+}
+
+#[test]
+fn unary_math_out_of_integer_range()
+{
+  let out = run_graph(&graph("unary_overflow.json"), &[]);
+  // Abs and Neg of i64::MIN, then Round of 1e300
+  let expected = [
+    r#"String("math error: Abs is undefined for Integer(-9223372036854775808)")"#,
+    r#"String("math error: Neg is undefined for Integer(-9223372036854775808)")"#,
+    r#"String("math error: Round is undefined for Float(1e300)")"#,
+  ]
+  .join(", ");
+  assert!(out.contains(&format!("Ok([{expected}])")), "{out}");
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use common::{example, graph, run_graph, spawn_graph_piped};
+use std::{
+  io::{BufRead, BufReader, Read, Write},
+  net::TcpStream,
+  time::Duration,
+};
+
+fn scrape(addr: &str) -> String
+{
+  let mut stream = TcpStream::connect(addr).unwrap();
+  write!(
+    stream,
+    "GET /metrics HTTP/1.1\r\nhost: {addr}\r\nconnection: close\r\n\r\n"
+  )
+  .unwrap();
+  let mut response = String::new();
+  stream.read_to_string(&mut response).unwrap();
+  response
+}
+
+#[test]
+fn summary_counts_runs_and_bytes_per_node()
+{
+  let out = run_graph(&example("file_lines.json"), &["--metrics"]);
+  // the GetLine node, run once per line and once more to find the end of the file
+  let get_line: Vec<&str> = out
+    .lines()
+    .find(|x| x.starts_with("00000000-0000-0000-0000-000000000005"))
+    .unwrap_or_else(|| panic!("{out}"))
+    .split_whitespace()
+    .collect();
+  assert_eq!(get_line[1..4], ["Io", "4", "0"], "{out}");
+  assert_eq!(get_line[6..], ["34", "0"], "{out}");
+  assert!(out.contains("examples/file_lines.json\n"), "{out}");
+}
+
+#[test]
+fn metrics_are_served_while_the_graph_runs()
+{
+  // the listener is blocked accepting until interrupted
+  let path = graph("subgraphs/hangs.json");
+  let mut child = spawn_graph_piped(&path, &["--metrics-addr", "127.0.0.1:0"]);
+  let mut stderr = BufReader::new(child.stderr.take().unwrap());
+  let mut line = String::new();
+  while !line.starts_with("Serving metrics on")
+  {
+    line.clear();
+    assert_ne!(
+      stderr.read_line(&mut line).unwrap(),
+      0,
+      "no metrics address"
+    );
+  }
+  let addr = line
+    .trim_end()
+    .trim_start_matches("Serving metrics on http://")
+    .trim_end_matches("/metrics")
+    .to_string();
+
+  // the listener is opened once the graph has started
+  let open = "node=\"00000000-0000-0000-0000-000000000004\",node_type=\"Io\"} 1\n";
+  let mut response = scrape(&addr);
+  for _ in 0..100
+  {
+    if response.contains(open)
+    {
+      break;
+    }
+    std::thread::sleep(Duration::from_millis(20));
+    response = scrape(&addr);
+  }
+  assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+  assert!(
+    response.contains("# TYPE agentnodes_node_evaluations_total counter\n"),
+    "{response}"
+  );
+  assert!(
+    response.contains("# TYPE agentnodes_node_duration_seconds histogram\n"),
+    "{response}"
+  );
+  assert!(
+    response.contains("node=\"00000000-0000-0000-0000-000000000001\",node_type=\"Control\"} 1\n"),
+    "{response}"
+  );
+
+  assert!(response.contains(open), "{response}");
+  child.kill().unwrap();
+  child.wait().unwrap();
+}
@@ -0,0 +1,86 @@
+mod common;
+
+use agent_nodes_core::{
+  eval::node_label, Complex, DataValue, EvalError, Evaluator, NodeStateLogger,
+};
+use common::{graph, run_graph};
+use serde_json::Value;
+use std::fs;
+use uuid::Uuid;
+
+fn named() -> Value
+{
+  serde_json::from_str(&fs::read_to_string(graph("named.json")).unwrap()).unwrap()
+}
+
+#[test]
+fn connections_refer_to_nodes_by_name()
+{
+  let loaded = Complex::from_json("named.json", named()).unwrap();
+  let divide = Uuid::from_u128(3);
+  assert_eq!(loaded.end_node, Uuid::from_u128(4));
+  assert_eq!(loaded.instances[&divide].name.as_deref(), Some("divide"));
+  assert_eq!(loaded.instances[&divide].inputs[1].1, Uuid::from_u128(1));
+  assert_eq!(loaded.instances[&Uuid::from_u128(1)].outputs, [divide]);
+
+  let out = run_graph(&graph("named.json"), &["--input", "0=3"]);
+  assert!(out.contains("Integer(4)"), "{out}");
+}
+
+#[test]
+fn names_must_pick_out_one_node()
+{
+  let mut unknown = named();
+  unknown["end_node"] = "finish".into();
+  let err = Complex::from_json("named.json", unknown).unwrap_err();
+  assert!(
+    err.to_string().contains("no node is named `finish`"),
+    "{err}"
+  );
+
+  let mut twice = named();
+  twice["instances"]["00000000-0000-0000-0000-000000000002"]["name"] = "divide".into();
+  let err = Complex::from_json("named.json", twice).unwrap_err();
+  assert_eq!(err.kind(), "InvalidComplexNode");
+  assert!(
+    err
+      .to_string()
+      .contains("more than one node is named `divide`"),
+    "{err}"
+  );
+}
+
+#[tokio::test]
+async fn failures_name_the_node()
+{
+  let eval = Evaluator::<NodeStateLogger, NodeStateLogger>::new(
+    graph("named.json").to_str().unwrap().to_string(),
+    None,
+    None,
+    None,
+  )
+  .unwrap();
+  let instance = eval.instantiate(vec![DataValue::Integer(0)]).await;
+  let (node, message) = instance.wait_for_failure().await;
+  assert_eq!(instance.node_name(&node), Some("divide"));
+  assert_eq!(
+    node_label(node, instance.node_name(&node)),
+    format!("divide ({node})")
+  );
+  assert!(message.contains("division by zero"), "{message}");
+
+  let snapshot = instance.inspect().await;
+  let divide = snapshot.nodes.iter().find(|x| x.node == node).unwrap();
+  assert!(divide.to_string().starts_with("node divide ("), "{divide}");
+  instance.shutdown().await;
+
+  let failed = EvalError::SubgraphFailed {
+    path: "named.json".to_string(),
+    node,
+    name: Some("divide".to_string()),
+    message,
+  };
+  assert!(failed
+    .to_string()
+    .starts_with(&format!("node divide ({node}) in named.json failed")));
+}
@@ -0,0 +1,39 @@
+mod common;
+
+use common::{graph, run_graph};
+
+#[test]
+fn destructure_key_paths()
+{
+  let out = run_graph(&graph("destructure.json"), &[]);
+  assert!(
+    out.contains(r#"Ok([String("London"), String("cake"), String("ada")])"#),
+    "{out}"
+  );
+}
+
+#[test]
+fn get_set_and_list_fields()
+{
+  let out = run_graph(&graph("fields.json"), &[]);
+  assert!(
+    out.contains(
+      r#"Ok([String("hi"), Boolean(true), Boolean(false), Array([String("choices"), String("meta"), String("model")]), String("m")])"#
+    ),
+    "{out}"
+  );
+}
+
+#[test]
+fn objects_keep_key_order()
+{
+  let out = run_graph(&graph("ordered.json"), &[]);
+  let expected = [
+    // parsed keys in document order, added ones after them
+    r#"Object({"zeta": Integer(1), "alpha": Object({"b": Integer(2), "a": Integer(3)}), "mid": String("added")})"#,
+    r#"Object({"x": Integer(1), "y": Integer(2)})"#,
+    r#"String("cannot cast Object to Map(Integer)")"#,
+  ]
+  .join(", ");
+  assert!(out.contains(&format!("Ok([{expected}])")), "{out}");
+}
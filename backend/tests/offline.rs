@@ -0,0 +1,31 @@
+mod common;
+
+use common::{example, graph, run_graph, run_graph_output};
+
+#[test]
+fn offline_refuses_network_nodes_before_running()
+{
+  // the listener is inside a subgraph wrapped by a Timeout node
+  let output = run_graph_output(&graph("timeout.json"), &["--offline"]);
+  assert_eq!(output.status.code(), Some(2));
+  assert!(output.stdout.is_empty());
+  let stderr = String::from_utf8(output.stderr).unwrap();
+  assert!(
+    stderr.starts_with("--offline: node 00000000-0000-0000-0000-000000000004 in "),
+    "{stderr}"
+  );
+  assert!(
+    stderr.ends_with("subgraphs/hangs.json needs the network\n"),
+    "{stderr}"
+  );
+}
+
+#[test]
+fn offline_allows_mock_agents()
+{
+  let out = run_graph(&example("two_agent_conversation.json"), &["--offline"]);
+  assert!(
+    out.contains(r#"Ok([String("bob: alice: Hello, who is there?")])"#),
+    "{out}"
+  );
+}
@@ -0,0 +1,65 @@
+mod common;
+
+use agent_nodes_core::{language::typing::DataValue, Complex, Evaluator, NodeStateLogger};
+use common::{graph, run_graph};
+use uuid::Uuid;
+
+fn node(n: u128) -> Uuid
+{
+  Uuid::from_u128(n)
+}
+
+#[test]
+fn constants_fold_and_dead_nodes_go()
+{
+  let mut loaded = Complex::load(graph("constants.json").to_str().unwrap()).unwrap();
+  let optimized = loaded.optimize();
+  assert_eq!(optimized.folded, [node(4), node(5)]);
+  // the values and the folded sum are no longer read, the print and lone value never
+  // run
+  assert_eq!(
+    optimized.removed,
+    [node(2), node(3), node(4), node(7), node(8)]
+  );
+  assert_eq!(
+    optimized.to_string(),
+    "folded 2 constant node(s), removed 5 node(s)"
+  );
+
+  let mut ids: Vec<&Uuid> = loaded.instances.keys().collect();
+  ids.sort();
+  assert_eq!(ids, [&node(1), &node(5), &node(6)]);
+  // start triggers the folded product directly
+  assert_eq!(
+    loaded.instances[&node(1)].control_flow_out,
+    [vec![(node(5), 0)]]
+  );
+  assert_eq!(
+    loaded.instances[&node(5)].control_flow_in,
+    [vec![(node(1), 0)]]
+  );
+  assert!(loaded.instances[&node(5)].inputs.is_empty());
+  assert_eq!(loaded.optimize(), Default::default());
+}
+
+#[tokio::test]
+async fn optimized_graphs_give_the_same_outputs()
+{
+  let eval = Evaluator::<NodeStateLogger, NodeStateLogger>::new(
+    graph("constants.json").to_str().unwrap().to_string(),
+    None,
+    None,
+    None,
+  )
+  .unwrap();
+  assert_eq!(eval.optimized().removed.len(), 5);
+  let instance = eval.instantiate(vec![]).await;
+  assert_eq!(
+    instance.get_outputs().await.unwrap(),
+    [DataValue::Integer(15)]
+  );
+  instance.shutdown().await;
+
+  let out = run_graph(&graph("constants.json"), &[]);
+  assert!(out.contains("Integer(15)"), "{out}");
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use common::{example, graph, run_graph, run_graph_output};
+use std::{fs, path::Path};
+
+#[test]
+fn outputs_are_written_as_json_or_raw()
+{
+  let conversation = example("two_agent_conversation.json");
+  let out = run_graph(&conversation, &["--output-format", "json"]);
+  assert!(
+    out.ends_with("{\"outputs\":[\"bob: alice: Hello, who is there?\"]}\n"),
+    "{out}"
+  );
+  let out = run_graph(&conversation, &["--output-format", "raw"]);
+  assert!(
+    out.ends_with("\nbob: alice: Hello, who is there?\n"),
+    "{out}"
+  );
+
+  // failures are reported the way the UI reads errors
+  let output = run_graph_output(&graph("abort.json"), &["--output-format", "pretty"]);
+  let stdout = String::from_utf8(output.stdout).unwrap();
+  assert!(
+    stdout.ends_with(concat!(
+      "{\n",
+      "  \"error\": {\n",
+      "    \"kind\": \"Aborted\",\n",
+      "    \"message\": \"aborted with code 3: guard failed\"\n",
+      "  }\n",
+      "}\n"
+    )),
+    "{stdout}"
+  );
+}
+
+#[test]
+fn outputs_are_written_to_a_file()
+{
+  let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("output_file");
+  fs::create_dir_all(&dir).unwrap();
+  let path = dir.join("outputs.json");
+  let _ = fs::remove_file(&path);
+  let out = run_graph(
+    &example("subgraph.json"),
+    &[
+      "--output-format",
+      "json",
+      "--output-file",
+      path.to_str().unwrap(),
+    ],
+  );
+  // the graph still prints what it prints
+  assert_eq!(out, "84\n");
+  assert_eq!(fs::read_to_string(&path).unwrap(), "{\"outputs\":[84]}\n");
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use agent_nodes_core::{
+  language::{
+    arrays::ArrayOp,
+    nodes::{AtomicType, ControlFlow},
+  },
+  DataType, DataValue, EvalError, Evaluator, GraphBuilder, NodeStateLogger,
+};
+use common::atomic;
+
+fn array(values: impl IntoIterator<Item = DataValue>) -> DataValue
+{
+  DataValue::Array(values.into_iter().collect())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn split_and_unzip_give_two_outputs()
+{
+  let mut graph = GraphBuilder::new(
+    vec![DataType::Array],
+    vec![DataType::Array, DataType::Array, DataType::Array],
+  );
+  let start = graph.add_node(atomic(AtomicType::Control(ControlFlow::Start)));
+  let unzip = graph.add_node(atomic(AtomicType::Array(ArrayOp::Unzip)));
+  let index = graph.add_node(atomic(AtomicType::Value(DataValue::Integer(-2))));
+  let split = graph.add_node(atomic(AtomicType::Array(ArrayOp::Split)));
+  let end = graph.add_node(atomic(AtomicType::Control(ControlFlow::End)));
+  graph
+    .trigger(start, 0, unzip)
+    .trigger(unzip, 0, index)
+    .trigger(index, 0, split)
+    .trigger(split, 0, end)
+    .connect(start, 0, unzip, DataType::Array)
+    .connect(unzip, 0, split, DataType::Array)
+    .connect(index, 0, split, DataType::Integer)
+    .connect(unzip, 1, end, DataType::Array)
+    .connect(split, 0, end, DataType::Array)
+    .connect(split, 1, end, DataType::Array)
+    .set_end(end);
+  let eval = Evaluator::<NodeStateLogger, NodeStateLogger>::from_complex(
+    graph.build().unwrap(),
+    "outputs.json".to_string(),
+    None,
+    None,
+    None,
+  )
+  .unwrap();
+
+  let pairs =
+    array((1..=3).map(|i| array([DataValue::Integer(i), DataValue::String(i.to_string())])));
+  let instance = eval.instantiate(vec![pairs]).await;
+  let outputs = instance.get_outputs().await.unwrap();
+  instance.shutdown().await;
+  assert_eq!(
+    outputs,
+    [
+      array(["1", "2", "3"].map(|x| DataValue::String(x.to_string()))),
+      array([DataValue::Integer(1)]),
+      array([DataValue::Integer(2), DataValue::Integer(3)]),
+    ]
+  );
+}
+
+#[test]
+fn reading_an_output_a_node_does_not_have_fails_to_load()
+{
+  let mut graph = GraphBuilder::new(vec![], vec![DataType::Integer]);
+  let start = graph.add_node(atomic(AtomicType::Control(ControlFlow::Start)));
+  let value = graph.add_node(atomic(AtomicType::Value(DataValue::Integer(1))));
+  let end = graph.add_node(atomic(AtomicType::Control(ControlFlow::End)));
+  graph
+    .trigger(start, 0, value)
+    .trigger(value, 0, end)
+    .connect(value, 1, end, DataType::Integer)
+    .set_end(end);
+  assert!(matches!(
+    graph.build(),
+    Err(EvalError::MissingOutput { reader, node, port: 1, ports: 1 }) if reader == end && node == value
+  ));
+}
@@ -0,0 +1,28 @@
+mod common;
+
+use common::{graph, run_graph};
+
+#[test]
+fn parallel_waits_for_every_branch()
+{
+  let out = run_graph(&graph("parallel.json"), &[]);
+  // the slow branch prints before triggering the join, and its value is on port 0
+  assert!(
+    out.ends_with(concat!(
+      "slow branch done\n",
+      r#"Ok([Array([String("slow branch done"), String("fast")])])"#,
+      "\n"
+    )),
+    "{out}"
+  );
+}
+
+#[test]
+fn readers_wait_for_a_value_shared_between_them()
+{
+  let out = run_graph(&graph("fan_out.json"), &[]);
+  assert!(
+    out.ends_with("shared\nshared\nOk([String(\"shared\")])\n"),
+    "{out}"
+  );
+}
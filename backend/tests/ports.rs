@@ -0,0 +1,71 @@
+mod common;
+
+use agent_nodes_core::{Complex, EvalError};
+use common::{graph, run_graph, run_graph_output};
+use std::process::Command;
+
+#[test]
+fn inputs_are_given_by_name()
+{
+  let path = graph("named_ports.json");
+  let out = run_graph(&path, &["--input", "amount=2"]);
+  assert!(out.ends_with("Ok([Integer(36)])\n"), "{out}");
+  let out = run_graph(&path, &["--input", "amount=2", "--input", "step=1"]);
+  assert!(out.ends_with("Ok([Integer(9)])\n"), "{out}");
+  // numbers still work for named inputs
+  let out = run_graph(&path, &["--input", "0=2", "--input", "1=1"]);
+  assert!(out.ends_with("Ok([Integer(9)])\n"), "{out}");
+}
+
+#[test]
+fn missing_input_is_named()
+{
+  let output = run_graph_output(&graph("named_ports.json"), &[]);
+  assert!(!output.status.success());
+  let err = String::from_utf8(output.stderr).unwrap();
+  assert!(err.contains("--input amount=VALUE"), "{err}");
+}
+
+#[test]
+fn json_outputs_are_keyed_by_name()
+{
+  let out = run_graph(
+    &graph("named_ports.json"),
+    &["--input", "amount=2", "--output-format", "json"],
+  );
+  let json: serde_json::Value = serde_json::from_str(out.lines().last().unwrap()).unwrap();
+  assert_eq!(json["outputs"], serde_json::json!([36]));
+  assert_eq!(json["named"], serde_json::json!({ "total": 36 }));
+}
+
+#[test]
+fn describe_shows_port_names()
+{
+  let output = Command::new(env!("CARGO_BIN_EXE_backend"))
+    .args(["describe", graph("named_ports.json").to_str().unwrap()])
+    .output()
+    .unwrap();
+  assert_eq!(
+    String::from_utf8(output.stdout).unwrap(),
+    concat!(
+      "Inputs:\n",
+      "  0 amount: Integer - the amount\n",
+      "  1 step: Integer = 10 - what to add to it\n",
+      "Outputs:\n",
+      "  0 total: Integer\n"
+    )
+  );
+}
+
+#[test]
+fn port_names_must_be_distinct()
+{
+  let mut json: serde_json::Value =
+    serde_json::from_str(&std::fs::read_to_string(graph("named_ports.json")).unwrap()).unwrap();
+  json["input_names"] = serde_json::json!(["amount", "amount"]);
+  let graph = Complex::from_json("named_ports.json", json).unwrap();
+  assert!(matches!(
+    graph.validate(),
+    Err(EvalError::InvalidPortName(message)) if message.contains("`amount`")
+  ));
+}
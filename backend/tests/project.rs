@@ -0,0 +1,69 @@
+mod common;
+
+use common::{example, run_graph, run_graph_output};
+use std::{fs, path::Path};
+
+/// A directory with a `project.json` running `entry`, and an `agentnodes.toml` holding
+/// `config`
+fn project(name: &str, entry: &str, requires: &str, config: &str) -> std::path::PathBuf
+{
+  let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+  let _ = fs::remove_dir_all(&dir);
+  fs::create_dir_all(&dir).unwrap();
+  let entry = example(entry);
+  fs::write(
+    dir.join("project.json"),
+    format!(
+      r#"{{"entry": {:?}, "requires": {requires}, "inputs": [{{"name": "n"}}]}}"#,
+      entry.to_str().unwrap()
+    ),
+  )
+  .unwrap();
+  fs::write(dir.join("agentnodes.toml"), config).unwrap();
+  dir
+}
+
+/// What the run of the project at `dir` was refused with
+fn refusal(dir: &Path, args: &[&str]) -> String
+{
+  let output = run_graph_output(dir, args);
+  assert_eq!(output.status.code(), Some(2));
+  String::from_utf8(output.stderr).unwrap()
+}
+
+#[test]
+fn project_json_inputs_are_given_by_name()
+{
+  let dir = project("project_inputs", "subgraphs/double.json", "[]", "");
+  let out = run_graph(&dir, &["--input", "n=4"]);
+  assert!(out.contains("Ok([Integer(8)])"), "{out}");
+
+  let stderr = refusal(&dir, &[]);
+  assert!(
+    stderr.ends_with("input `n` has no value, give it with --input\n"),
+    "{stderr}"
+  );
+  let output = run_graph_output(&example("subgraph.json"), &["--input", "n=4"]);
+  assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn projects_only_get_what_they_require()
+{
+  let dir = project("project_undeclared", "http_request.json", "[]", "");
+  let stderr = refusal(&dir, &["--input", "n=1"]);
+  assert!(stderr.contains("project.json: node "), "{stderr}");
+  assert!(stderr.ends_with("needs the network\n"), "{stderr}");
+
+  let dir = project(
+    "project_denied",
+    "http_request.json",
+    r#"["network"]"#,
+    "[sandbox]\nallow_network = false\n",
+  );
+  let stderr = refusal(&dir, &["--input", "n=1"]);
+  assert!(
+    stderr.ends_with("the project requires network, which the sandbox does not allow\n"),
+    "{stderr}"
+  );
+}
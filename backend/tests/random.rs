@@ -0,0 +1,51 @@
+mod common;
+
+use agent_nodes_core::{Evaluator, NodeStateLogger};
+use common::{graph, run_graph};
+use rand::RngExt;
+use uuid::Uuid;
+
+#[test]
+fn same_seed_gives_same_values()
+{
+  let path = graph("random.json");
+  let first = run_graph(&path, &["--seed", "7"]);
+  assert!(first.contains("Ok([Integer("), "{first}");
+  // a range of one value, and nothing to choose from in an empty Array
+  assert!(first.contains("Integer(4), Float("), "{first}");
+  assert!(first.contains("Boolean(true), String("), "{first}");
+  assert_eq!(run_graph(&path, &["--seed", "7"]), first);
+  assert_ne!(run_graph(&path, &["--seed", "8"]), first);
+}
+
+#[test]
+fn unseeded_runs_differ()
+{
+  let path = graph("random.json");
+  assert_ne!(run_graph(&path, &[]), run_graph(&path, &[]));
+}
+
+#[tokio::test]
+async fn seeded_values_do_not_depend_on_which_node_draws_first()
+{
+  let eval = Evaluator::<NodeStateLogger, NodeStateLogger>::new(
+    graph("random.json").to_str().unwrap().to_string(),
+    None,
+    None,
+    None,
+  )
+  .unwrap();
+  eval.set_seed(7).await;
+  // the graph's start and a value, nodes that draw nothing themselves
+  let (a, b) = (Uuid::from_u128(1), Uuid::from_u128(2));
+  let draw = |instance: &Evaluator<_, _>, node| instance.with_rng(node, |rng| rng.random::<u64>());
+
+  let instance = eval.clone().instantiate(vec![]).await;
+  let in_order = [draw(&instance, a), draw(&instance, b), draw(&instance, a)];
+  instance.shutdown().await;
+  let instance = eval.clone().instantiate(vec![]).await;
+  let reversed = [draw(&instance, b), draw(&instance, a), draw(&instance, a)];
+  instance.shutdown().await;
+  assert_eq!(in_order, [reversed[1], reversed[0], reversed[2]]);
+  assert_ne!(in_order[0], in_order[1]);
+}
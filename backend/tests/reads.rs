@@ -0,0 +1,186 @@
+mod common;
+
+use agent_nodes_core::{
+  language::nodes::{AtomicType, AtomicUnaryOp, ControlFlow},
+  DataType, DataValue, Evaluator, GraphBuilder, NodeStateLogger,
+};
+use common::atomic;
+use std::time::Duration;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn readers_get_the_run_they_were_listed_for()
+{
+  // a counter read by two nodes, only the first of which control flow reaches
+  let mut graph = GraphBuilder::new(vec![], vec![DataType::Integer]);
+  let start = graph.add_node(atomic(AtomicType::Control(ControlFlow::Start)));
+  let counter = graph.add_node(atomic(AtomicType::Counter));
+  let first = graph.add_node(atomic(AtomicType::Expr("a".to_string())));
+  let second = graph.add_node(atomic(AtomicType::Expr("a".to_string())));
+  let end = graph.add_node(atomic(AtomicType::Control(ControlFlow::End)));
+  graph
+    .trigger(start, 0, counter)
+    .trigger(counter, 0, first)
+    .trigger(first, 0, end)
+    .connect(counter, 0, first, DataType::Integer)
+    .connect(counter, 0, second, DataType::Integer)
+    .connect(first, 0, end, DataType::Integer)
+    .set_end(end);
+  let eval = Evaluator::<NodeStateLogger, NodeStateLogger>::from_complex(
+    graph.build().unwrap(),
+    "reads.json".to_string(),
+    None,
+    None,
+    None,
+  )
+  .unwrap();
+  let instance = eval.instantiate(vec![]).await;
+  assert_eq!(
+    instance.get_outputs().await.unwrap(),
+    [DataValue::Integer(1)]
+  );
+
+  // the first reader going over the first run again does not free the counter
+  assert_eq!(
+    instance.clone().rerun_node(&first).await.unwrap(),
+    [DataValue::Integer(1)]
+  );
+  let rerun = tokio::spawn({
+    let instance = instance.clone();
+    async move { instance.rerun_node(&counter).await }
+  });
+  tokio::time::sleep(Duration::from_millis(100)).await;
+  assert!(!rerun.is_finished());
+
+  // so the second reader still gets the run it was listed for, then the counter moves on
+  assert_eq!(
+    instance.clone().rerun_node(&second).await.unwrap(),
+    [DataValue::Integer(1)]
+  );
+  let rerun = tokio::time::timeout(Duration::from_secs(5), rerun)
+    .await
+    .unwrap()
+    .unwrap();
+  assert_eq!(rerun.unwrap(), [DataValue::Integer(2)]);
+  instance.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn readers_owe_each_port_they_read()
+{
+  // a negation of the counter twice over, both outputs read by the first reader and the
+  // second by another, only the first of which control flow reaches
+  let mut graph = GraphBuilder::new(vec![], vec![DataType::Integer]);
+  let start = graph.add_node(atomic(AtomicType::Control(ControlFlow::Start)));
+  let counter = graph.add_node(atomic(AtomicType::Counter));
+  let negate = graph.add_node(atomic(AtomicType::UnaryOp(AtomicUnaryOp::Neg)));
+  let first = graph.add_node(atomic(AtomicType::Expr("a + b".to_string())));
+  let second = graph.add_node(atomic(AtomicType::Expr("a".to_string())));
+  let end = graph.add_node(atomic(AtomicType::Control(ControlFlow::End)));
+  graph
+    .trigger(start, 0, counter)
+    .trigger(counter, 0, negate)
+    .trigger(negate, 0, first)
+    .trigger(first, 0, end)
+    .connect(counter, 0, negate, DataType::Integer)
+    .connect(counter, 0, negate, DataType::Integer)
+    .connect(negate, 0, first, DataType::Integer)
+    .connect(negate, 1, first, DataType::Integer)
+    .connect(negate, 1, second, DataType::Integer)
+    .connect(first, 0, end, DataType::Integer)
+    .set_end(end);
+  let eval = Evaluator::<NodeStateLogger, NodeStateLogger>::from_complex(
+    graph.build().unwrap(),
+    "reads_ports.json".to_string(),
+    None,
+    None,
+    None,
+  )
+  .unwrap();
+  let instance = eval.instantiate(vec![]).await;
+  assert_eq!(
+    instance.get_outputs().await.unwrap(),
+    [DataValue::Integer(-2)]
+  );
+
+  // the first reader's reads of both ports again do not cover the second's of one
+  assert_eq!(
+    instance.clone().rerun_node(&first).await.unwrap(),
+    [DataValue::Integer(-2)]
+  );
+  let rerun = tokio::spawn({
+    let instance = instance.clone();
+    async move { instance.rerun_node(&negate).await }
+  });
+  tokio::time::sleep(Duration::from_millis(100)).await;
+  assert!(!rerun.is_finished());
+
+  assert_eq!(
+    instance.clone().rerun_node(&second).await.unwrap(),
+    [DataValue::Integer(-1)]
+  );
+  let rerun = tokio::time::timeout(Duration::from_secs(5), rerun)
+    .await
+    .unwrap()
+    .unwrap();
+  assert_eq!(
+    rerun.unwrap(),
+    [DataValue::Integer(-1), DataValue::Integer(-1)]
+  );
+  instance.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn readers_wait_for_a_run_that_is_coming()
+{
+  // a join of two counters read by a node control flow does not reach
+  let mut graph = GraphBuilder::new(vec![], vec![DataType::Any]);
+  let start = graph.add_node(atomic(AtomicType::Control(ControlFlow::Start)));
+  let left = graph.add_node(atomic(AtomicType::Counter));
+  let right = graph.add_node(atomic(AtomicType::Counter));
+  let join = graph.add_node(atomic(AtomicType::Control(ControlFlow::Parallel)));
+  let reader = graph.add_node(atomic(AtomicType::Control(ControlFlow::Parallel)));
+  let end = graph.add_node(atomic(AtomicType::Control(ControlFlow::End)));
+  graph
+    .trigger(start, 0, left)
+    .trigger(start, 0, right)
+    .trigger(left, 0, join)
+    .trigger(right, 0, join)
+    .trigger(join, 0, end)
+    .connect(left, 0, join, DataType::Integer)
+    .connect(join, 0, end, DataType::Any)
+    .connect(join, 0, reader, DataType::Any)
+    .set_end(end);
+  let eval = Evaluator::<NodeStateLogger, NodeStateLogger>::from_complex(
+    graph.build().unwrap(),
+    "reads_coming.json".to_string(),
+    None,
+    None,
+    None,
+  )
+  .unwrap();
+  let instance = eval.instantiate(vec![]).await;
+  let array = |x| DataValue::Array(vec![DataValue::Integer(x)]);
+  assert_eq!(instance.get_outputs().await.unwrap(), [array(1)]);
+  assert_eq!(
+    instance.clone().rerun_node(&reader).await.unwrap(),
+    [DataValue::Array(vec![array(1)])]
+  );
+
+  // the join has one of the two triggers of its next run, so the reader waits for it
+  // rather than going over the first run again
+  instance.clone().rerun_node(&left).await.unwrap();
+  let rerun = tokio::spawn({
+    let instance = instance.clone();
+    async move { instance.rerun_node(&reader).await }
+  });
+  tokio::time::sleep(Duration::from_millis(100)).await;
+  assert!(!rerun.is_finished());
+
+  instance.clone().rerun_node(&right).await.unwrap();
+  let rerun = tokio::time::timeout(Duration::from_secs(5), rerun)
+    .await
+    .unwrap()
+    .unwrap();
+  assert_eq!(rerun.unwrap(), [DataValue::Array(vec![array(2)])]);
+  instance.shutdown().await;
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use agent_nodes_core::{
+  language::records::{self, Records},
+  Complex, DataType, DataValue, EvalError,
+};
+use common::{graph, run_graph};
+use indexmap::IndexMap;
+use std::process::Command;
+
+fn person() -> Records
+{
+  let fields = IndexMap::from([
+    ("name".to_string(), DataType::String),
+    ("age".to_string(), DataType::Integer),
+    (
+      "email".to_string(),
+      DataType::Optional(Box::new(DataType::String)),
+    ),
+  ]);
+  Records::from([("Person".to_string(), fields)])
+}
+
+fn object(fields: &[(&str, DataValue)]) -> DataValue
+{
+  DataValue::Object(
+    fields
+      .iter()
+      .map(|(k, v)| (k.to_string(), v.clone()))
+      .collect(),
+  )
+}
+
+#[test]
+fn records_are_constructed_from_shared_types()
+{
+  // Person comes from person_types.json, and the age String casts to its Integer
+  let out = run_graph(
+    &graph("records.json"),
+    &["--input", "name=Ann", "--input", "age=42"],
+  );
+  assert!(
+    out.ends_with(concat!(
+      r#"Ok([Object({"name": String("Ann"), "age": Integer(42), "email": Optional(None)}), "#,
+      r#"String("Ann"), Integer(42)])"#,
+      "\n"
+    )),
+    "{out}"
+  );
+}
+
+#[test]
+fn objects_are_checked_against_the_record()
+{
+  let records = person();
+  let ann = object(&[
+    ("age", DataValue::Integer(42)),
+    ("name", DataValue::String("Ann".to_string())),
+  ]);
+  // fields come out in the record's order, the missing Optional as nothing
+  assert_eq!(
+    records::record_of(&records, "Person", &ann).unwrap(),
+    object(&[
+      ("name", DataValue::String("Ann".to_string())),
+      ("age", DataValue::Integer(42)),
+      ("email", DataValue::Optional(None)),
+    ])
+  );
+  let old = object(&[
+    ("name", DataValue::String("Bo".to_string())),
+    ("age", DataValue::String("old".to_string())),
+  ]);
+  assert!(matches!(
+    records::record_of(&records, "Person", &old),
+    Err(EvalError::InvalidField { field, expected: Some(DataType::Integer), .. }) if field == "age"
+  ));
+  let extra = object(&[
+    ("name", DataValue::String("Cy".to_string())),
+    ("age", DataValue::Integer(1)),
+    ("pet", DataValue::String("cat".to_string())),
+  ]);
+  assert!(matches!(
+    records::record_of(&records, "Person", &extra),
+    Err(EvalError::InvalidField { field, expected: None, .. }) if field == "pet"
+  ));
+  assert!(matches!(
+    records::record_of(&records, "Robot", &extra),
+    Err(EvalError::UnknownRecord(name)) if name == "Robot"
+  ));
+}
+
+#[test]
+fn records_must_be_defined()
+{
+  // read without its types file the graph names a record it does not have
+  let json = serde_json::from_str(&std::fs::read_to_string(graph("records.json")).unwrap());
+  let graph = Complex::from_json("records.json", json.unwrap()).unwrap();
+  assert!(matches!(
+    graph.validate(),
+    Err(EvalError::UnknownRecord(name)) if name == "Person"
+  ));
+}
+
+#[test]
+fn records_print_as_json_schemas()
+{
+  let output = Command::new(env!("CARGO_BIN_EXE_backend"))
+    .args([
+      "describe",
+      "--records-schema",
+      graph("records.json").to_str().unwrap(),
+    ])
+    .output()
+    .unwrap();
+  let schema: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+  let person = &schema["$defs"]["Person"];
+  assert_eq!(person["properties"]["age"]["type"], "integer");
+  assert_eq!(person["required"], serde_json::json!(["name", "age"]));
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use agent_nodes_core::{config::GraphsConfig, Config, EvalError, Evaluator, NodeStateLogger};
+use common::{example, graph, run_graph_output};
+use std::sync::Arc;
+
+fn load(name: &str) -> Arc<Evaluator<NodeStateLogger, NodeStateLogger>>
+{
+  Evaluator::new(graph(name).to_str().unwrap().to_string(), None, None, None).unwrap()
+}
+
+#[tokio::test]
+async fn cycles_are_found_before_running()
+{
+  let eval = load("ping.json");
+  eval.set_config(Config::default()).await;
+  match eval.check_cycles().await
+  {
+    Err(EvalError::ComplexCycle(cycle)) =>
+    {
+      assert_eq!(cycle.len(), 3, "{cycle:?}");
+      assert!(cycle[0].ends_with("ping.json"), "{cycle:?}");
+      assert!(cycle[1].ends_with("subgraphs/pong.json"), "{cycle:?}");
+      assert!(cycle[2].ends_with("ping.json"), "{cycle:?}");
+    }
+    other => panic!("{other:?}"),
+  }
+  let eval = Evaluator::<NodeStateLogger, NodeStateLogger>::new(
+    example("subgraph.json").to_str().unwrap().to_string(),
+    None,
+    None,
+    None,
+  )
+  .unwrap();
+  eval.set_config(Config::default()).await;
+  eval.check_cycles().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn nesting_stops_at_max_depth()
+{
+  let eval = load("ping.json");
+  eval
+    .set_config(Config {
+      graphs: GraphsConfig {
+        max_depth: Some(4),
+        ..GraphsConfig::default()
+      },
+      ..Config::default()
+    })
+    .await;
+  let instance = eval.instantiate(vec![]).await;
+  let outputs = instance.get_outputs().await.map_err(|e| e.to_string());
+  instance.clone().shutdown().await;
+  let message = outputs.unwrap_err();
+  assert!(
+    message.contains("would run more than 4 graphs deep"),
+    "{message}"
+  );
+}
+
+#[test]
+fn cyclic_graphs_are_refused()
+{
+  let output = run_graph_output(&graph("ping.json"), &[]);
+  assert_eq!(output.status.code(), Some(2));
+  let stderr = String::from_utf8(output.stderr).unwrap();
+  assert!(
+    stderr.contains("complex graphs use each other in a cycle: "),
+    "{stderr}"
+  );
+}
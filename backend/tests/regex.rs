@@ -0,0 +1,21 @@
+mod common;
+
+use common::{graph, run_graph};
+
+#[test]
+fn match_find_and_capture()
+{
+  let out = run_graph(&graph("regex.json"), &[]);
+  let expected = [
+    "Boolean(true)",
+    r#"String("42")"#,
+    // named groups make an Object
+    r#"String("{\"level\":\"high\",\"value\":\"42\"}")"#,
+    // unnamed ones an Array, with None for a group that did not take part
+    r#"Array([String("Answer"), String("42"), None])"#,
+    // nothing found
+    "Boolean(true)",
+  ]
+  .join(", ");
+  assert!(out.contains(&format!("Ok([{expected}])")), "{out}");
+}
@@ -0,0 +1,159 @@
+use agent_nodes_core::language::{
+  nodes::AtomicType,
+  registry::{registry, Category},
+  schema,
+};
+use serde_json::Value;
+use std::{collections::BTreeSet, process::Command};
+
+/// The variant names of an enum's schema, one of unit variants or `oneOf` the others
+fn variants(def: &Value) -> Vec<String>
+{
+  let mut names = vec![];
+  let one_of = match def["oneOf"].as_array()
+  {
+    Some(x) => x.clone(),
+    None => vec![def.clone()],
+  };
+  for variant in &one_of
+  {
+    if let Some(x) = variant["enum"].as_array()
+    {
+      names.extend(x.iter().map(|x| x.as_str().unwrap().to_string()));
+    }
+    else if let Some(x) = variant["const"].as_str()
+    {
+      names.push(x.to_string());
+    }
+    else
+    {
+      names.push(variant["required"][0].as_str().unwrap().to_string());
+    }
+  }
+  names
+}
+
+/// `Family/Op` for a node of one of `families`, as in `Array/Sort`, otherwise its
+/// variant
+fn kind(node: &AtomicType, families: &BTreeSet<String>) -> String
+{
+  match serde_json::to_value(node).unwrap()
+  {
+    Value::String(x) => x,
+    Value::Object(x) =>
+    {
+      let (family, op) = x.into_iter().next().unwrap();
+      match op
+      {
+        _ if !families.contains(&family) => family,
+        Value::String(op) => format!("{family}/{op}"),
+        Value::Object(op) => format!("{family}/{}", op.keys().next().unwrap()),
+        op => panic!("{op}"),
+      }
+    }
+    x => panic!("{x}"),
+  }
+}
+
+#[test]
+fn registry_describes_every_node()
+{
+  let bundle = serde_json::to_value(schema::bundled()).unwrap();
+  let defs = &bundle["$defs"];
+  let mut expected = BTreeSet::new();
+  let mut families = BTreeSet::new();
+  for variant in defs["AtomicType"]["oneOf"].as_array().unwrap()
+  {
+    match variant["properties"].as_object()
+    {
+      Some(x) =>
+      {
+        let (family, def) = x.iter().next().unwrap();
+        match def["$ref"]
+          .as_str()
+          .and_then(|x| x.strip_prefix("#/$defs/"))
+        {
+          Some(ops) if ops != "DataValue" && ops != "DataType" =>
+          {
+            families.insert(family.clone());
+            expected.extend(variants(&defs[ops]).iter().map(|x| format!("{family}/{x}")))
+          }
+          _ =>
+          {
+            expected.insert(family.clone());
+          }
+        }
+      }
+      None => expected.extend(variants(variant)),
+    }
+  }
+
+  let nodes = registry();
+  let described: BTreeSet<String> = nodes
+    .iter()
+    .map(|x| kind(&x.node_type, &families))
+    .collect();
+  assert_eq!(described, expected);
+  for node in &nodes
+  {
+    assert_eq!(node.node_type.spec(), *node);
+  }
+}
+
+#[test]
+fn specs_follow_node_arguments()
+{
+  let nodes = registry();
+  let counter = nodes
+    .iter()
+    .find(|x| x.node_type == AtomicType::Counter)
+    .unwrap();
+  assert_eq!(counter.category, Category::Runtime);
+  assert!(counter.stateful && !counter.io);
+  assert!(counter.inputs[0].optional);
+
+  let destructure: AtomicType =
+    serde_json::from_str(r#"{"Object": {"Destructure": ["a", "b.c"]}}"#).unwrap();
+  let names: Vec<String> = destructure
+    .spec()
+    .outputs
+    .into_iter()
+    .map(|x| x.name)
+    .collect();
+  assert_eq!(names, ["a", "b.c"]);
+
+  // a Try takes what the node it wraps takes
+  let guarded: AtomicType =
+    serde_json::from_str(r#"{"Control": {"Try": {"Atomic": {"Process": "Spawn"}}}}"#).unwrap();
+  let spec = guarded.spec();
+  assert_eq!(spec.inputs.len(), 3);
+  assert!(spec.io);
+  assert!(spec.permission.is_some());
+}
+
+#[test]
+fn describe_nodes_prints_the_registry()
+{
+  let run = |args: &[&str]| {
+    let output = Command::new(env!("CARGO_BIN_EXE_backend"))
+      .arg("describe-nodes")
+      .args(args)
+      .output()
+      .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+  };
+  let table = run(&[]);
+  assert_eq!(table.lines().count(), registry().len());
+  let counter = table.lines().find(|x| x.starts_with("Counter ")).unwrap();
+  assert!(counter.ends_with("[stateful]"), "{counter}");
+
+  let json: Vec<Value> = serde_json::from_str(&run(&["--json"])).unwrap();
+  assert_eq!(json.len(), registry().len());
+  let spawn = json
+    .iter()
+    .find(|x| x["node_type"] == serde_json::json!({ "Process": "Spawn" }))
+    .unwrap();
+  assert_eq!(spawn["category"], "processes");
+  assert_eq!(spawn["permission"], "processes");
+}
@@ -0,0 +1,165 @@
+use agent_nodes_core::{
+  config::GraphsConfig, logging::Logger, Config, DataValue, Evaluator, NodeStateLogger,
+};
+use std::{
+  fs,
+  path::{Path, PathBuf},
+  sync::{Arc, Mutex},
+  time::{Duration, SystemTime},
+};
+
+#[derive(Default)]
+struct Messages(Mutex<Vec<String>>);
+
+#[async_trait::async_trait]
+impl Logger for Messages
+{
+  async fn log(&self, message: &str)
+  {
+    self.0.lock().unwrap().push(message.to_string());
+  }
+}
+
+type Graph = Arc<Evaluator<NodeStateLogger, Messages>>;
+
+/// Copies `subgraph.json`, which doubles 21 twice with `subgraphs/double.json`, to `dir`
+fn copy_example(dir: &str) -> PathBuf
+{
+  let examples = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples");
+  let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(dir);
+  let _ = fs::remove_dir_all(&dir);
+  fs::create_dir_all(dir.join("subgraphs")).unwrap();
+  for file in ["subgraph.json", "subgraphs/double.json"]
+  {
+    fs::copy(examples.join(file), dir.join(file)).unwrap();
+  }
+  dir
+}
+
+/// Makes `double.json` multiply by three, dating it later so the change shows even on
+/// file systems with coarse modification times
+fn edit_double(dir: &Path)
+{
+  let path = dir.join("subgraphs/double.json");
+  let edited = fs::read_to_string(&path)
+    .unwrap()
+    .replace(r#""Value": 2"#, r#""Value": 3"#);
+  fs::write(&path, edited).unwrap();
+  fs::File::options()
+    .write(true)
+    .open(&path)
+    .unwrap()
+    .set_modified(SystemTime::now() + Duration::from_secs(10))
+    .unwrap();
+}
+
+async fn load(dir: &Path, reload_changed: bool) -> (Graph, Arc<Messages>)
+{
+  let messages = Arc::new(Messages::default());
+  let graph = Evaluator::new(
+    dir.join("subgraph.json").to_str().unwrap().to_string(),
+    None,
+    None,
+    Some(messages.clone()),
+  )
+  .unwrap();
+  graph
+    .set_config(Config {
+      graphs: GraphsConfig {
+        reload_changed,
+        ..GraphsConfig::default()
+      },
+      ..Config::default()
+    })
+    .await;
+  (graph, messages)
+}
+
+/// A finished run of `subgraph.json`, which keeps `double.json` cached for as long as
+/// it is alive, and the path that is cached under
+async fn run(graph: &Graph) -> (Graph, String)
+{
+  let instance = graph.clone().instantiate(vec![]).await;
+  assert_eq!(
+    instance.get_outputs().await.unwrap(),
+    vec![DataValue::Integer(84)]
+  );
+  let path = instance.resolve_complex("subgraphs/double.json").await;
+  (instance, path)
+}
+
+/// What the cached copy of `double.json` doubles 21 to
+async fn double(instance: &Graph, path: &str) -> Vec<DataValue>
+{
+  let template = instance.clone().load_complex(path).await.unwrap();
+  let run = template.instantiate(vec![DataValue::Integer(21)]).await;
+  let outputs = run.get_outputs().await.unwrap();
+  run.shutdown().await;
+  outputs
+}
+
+fn reloads(messages: &Messages) -> Vec<String>
+{
+  messages
+    .0
+    .lock()
+    .unwrap()
+    .iter()
+    .filter(|x| x.contains("reloaded"))
+    .cloned()
+    .collect()
+}
+
+#[tokio::test]
+async fn cached_subgraph_is_kept_by_default()
+{
+  let dir = copy_example("reload_off");
+  let (graph, messages) = load(&dir, false).await;
+  let (instance, path) = run(&graph).await;
+
+  edit_double(&dir);
+  assert_eq!(double(&instance, &path).await, vec![DataValue::Integer(42)]);
+  assert!(reloads(&messages).is_empty());
+
+  // until the cache is told to forget it
+  assert_eq!(instance.clear_complex_cache().await, vec![path.clone()]);
+  assert_eq!(double(&instance, &path).await, vec![DataValue::Integer(63)]);
+  instance.shutdown().await;
+}
+
+#[tokio::test]
+async fn changed_subgraph_is_reloaded_and_reported()
+{
+  let dir = copy_example("reload_on");
+  let (graph, messages) = load(&dir, true).await;
+  let (instance, path) = run(&graph).await;
+  assert_eq!(double(&instance, &path).await, vec![DataValue::Integer(42)]);
+  assert!(reloads(&messages).is_empty());
+
+  edit_double(&dir);
+  assert_eq!(double(&instance, &path).await, vec![DataValue::Integer(63)]);
+  assert_eq!(
+    reloads(&messages),
+    vec![NodeStateLogger::reload_string(&path)]
+  );
+
+  // the reloaded copy is cached in turn
+  assert_eq!(double(&instance, &path).await, vec![DataValue::Integer(63)]);
+  assert_eq!(reloads(&messages).len(), 1);
+  instance.shutdown().await;
+}
+
+#[tokio::test]
+async fn invalidated_subgraph_is_read_again()
+{
+  let dir = copy_example("reload_invalidate");
+  let (graph, _) = load(&dir, false).await;
+  let (instance, path) = run(&graph).await;
+  let cached = instance.clone().load_complex(&path).await.unwrap();
+
+  assert!(instance.invalidate_complex(&path).await);
+  assert!(!instance.invalidate_complex(&path).await);
+  let loaded = instance.clone().load_complex(&path).await.unwrap();
+  assert!(!Arc::ptr_eq(&cached, &loaded));
+  instance.shutdown().await;
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use common::{example, wait_for_output};
+use std::{
+  io::Write,
+  process::{Command, Stdio},
+};
+
+#[test]
+fn repl_runs_graphs_on_the_inputs_set()
+{
+  let double = example("subgraphs/double.json");
+  let mut child = Command::new(env!("CARGO_BIN_EXE_backend"))
+    .args(["repl", double.to_str().unwrap(), "--input", "0=21"])
+    .current_dir(env!("CARGO_MANIFEST_DIR"))
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .unwrap();
+  let mut stdin = child.stdin.take().unwrap();
+  // the value node of the doubling graph, which gives the 2
+  stdin
+    .write_all(
+      b"run\nset 0 7\nrun\ntrigger 00000000-0000-0000-0000-000000000002\n\
+        inspect 00000000-0000-0000-0000-000000000002\nquit\n",
+    )
+    .unwrap();
+  drop(stdin);
+
+  let output = wait_for_output(child, &double);
+  assert!(output.status.success());
+  let stdout = String::from_utf8(output.stdout).unwrap();
+  assert_eq!(
+    stdout,
+    "Ok([Integer(42)])\nOk([Integer(14)])\n[Integer(2)]\n"
+  );
+  let stderr = String::from_utf8(output.stderr).unwrap();
+  assert!(
+    stderr.contains("node 00000000-0000-0000-0000-000000000002 Value: "),
+    "{stderr}"
+  );
+}
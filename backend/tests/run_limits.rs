@@ -0,0 +1,158 @@
+mod common;
+
+use agent_nodes_core::{
+  language::nodes::{AtomicType, ControlFlow, LoopNodes, Variable},
+  DataType, DataValue, GraphBuilder,
+};
+use common::{atomic, run_graph_output};
+use std::{
+  fs,
+  path::{Path, PathBuf},
+};
+
+/// Writes `graph` and an `agentnodes.toml` holding `limits` under `[run_limits]` to a
+/// directory of their own, returning the graph's path
+fn write(name: &str, graph: GraphBuilder, limits: &str) -> PathBuf
+{
+  let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+  let _ = fs::remove_dir_all(&dir);
+  fs::create_dir_all(&dir).unwrap();
+  fs::write(
+    dir.join("agentnodes.toml"),
+    format!("[run_limits]\n{limits}\n"),
+  )
+  .unwrap();
+  let path = dir.join("graph.json");
+  fs::write(
+    &path,
+    serde_json::to_string(&graph.build().unwrap()).unwrap(),
+  )
+  .unwrap();
+  path
+}
+
+/// A loop around a Counter that never ends
+fn runaway_loop() -> GraphBuilder
+{
+  let mut graph = GraphBuilder::new(vec![], vec![]);
+  let start = graph.add_node(atomic(AtomicType::Control(ControlFlow::Start)));
+  let loop_start = graph.add_node(atomic(AtomicType::Control(ControlFlow::Loop(
+    LoopNodes::Start,
+  ))));
+  let counter = graph.add_node(atomic(AtomicType::Counter));
+  let again = graph.add_node(atomic(AtomicType::Control(ControlFlow::Loop(
+    LoopNodes::Continue(loop_start),
+  ))));
+  let end = graph.add_node(atomic(AtomicType::Control(ControlFlow::End)));
+  graph
+    .trigger(start, 0, loop_start)
+    .trigger(loop_start, 0, counter)
+    .trigger(counter, 0, again)
+    .set_end(end);
+  graph
+}
+
+#[test]
+fn node_runs_stop_a_runaway_loop()
+{
+  let path = write("limits_node_runs", runaway_loop(), "max_node_runs = 50");
+  let output = run_graph_output(&path, &[]);
+  assert_eq!(output.status.code(), Some(3));
+  let stderr = String::from_utf8(output.stderr).unwrap();
+  assert!(
+    stderr.contains("run went over its limit of 50 node runs, stopping"),
+    "{stderr}"
+  );
+}
+
+#[test]
+fn time_limit_stops_the_run()
+{
+  let path = write("limits_time", runaway_loop(), "max_secs = 0.2");
+  let output = run_graph_output(&path, &[]);
+  assert_eq!(output.status.code(), Some(3));
+  let stderr = String::from_utf8(output.stderr).unwrap();
+  assert!(
+    stderr.contains("run went over its limit of 200 milliseconds, stopping"),
+    "{stderr}"
+  );
+}
+
+#[test]
+fn stored_bytes_stop_the_run_even_when_caught()
+{
+  let mut graph = GraphBuilder::new(vec![], vec![DataType::Any]);
+  let start = graph.add_node(atomic(AtomicType::Control(ControlFlow::Start)));
+  let value = graph.add_node(atomic(AtomicType::Value(DataValue::String("x".repeat(64)))));
+  let set = graph.add_node(atomic(AtomicType::Control(ControlFlow::Try(Box::new(
+    atomic(AtomicType::Variable(Variable::Set, "big".to_string())),
+  )))));
+  let end = graph.add_node(atomic(AtomicType::Control(ControlFlow::End)));
+  graph
+    .trigger(start, 0, value)
+    .trigger(value, 0, set)
+    .trigger(set, 0, end)
+    .connect(value, 0, set, DataType::String)
+    .connect(set, 1, end, DataType::Any)
+    .set_end(end);
+  let path = write("limits_stored", graph, "max_stored_bytes = 32");
+  let output = run_graph_output(&path, &[]);
+  assert_eq!(output.status.code(), Some(3));
+  let stderr = String::from_utf8(output.stderr).unwrap();
+  assert!(
+    stderr.contains("run went over its limit of 32 bytes of variables"),
+    "{stderr}"
+  );
+}
+
+/// A graph outputting 7 straight away
+fn seven() -> GraphBuilder
+{
+  let mut graph = GraphBuilder::new(vec![], vec![DataType::Integer]);
+  let start = graph.add_node(atomic(AtomicType::Control(ControlFlow::Start)));
+  let value = graph.add_node(atomic(AtomicType::Value(DataValue::Integer(7))));
+  let end = graph.add_node(atomic(AtomicType::Control(ControlFlow::End)));
+  graph
+    .trigger(start, 0, value)
+    .trigger(value, 0, end)
+    .connect(value, 0, end, DataType::Integer)
+    .set_end(end);
+  graph
+}
+
+#[test]
+fn runs_within_their_limits_finish()
+{
+  let limits = "max_node_runs = 10\nmax_secs = 30\nmax_io_bytes = 0\nmax_agent_calls = 0";
+  let path = write("limits_within", seven(), limits);
+  let output = run_graph_output(&path, &[]);
+  assert_eq!(output.status.code(), Some(0));
+  let stdout = String::from_utf8(output.stdout).unwrap();
+  assert!(stdout.contains("Ok([Integer(7)])"), "{stdout}");
+
+  // too far off to ever be reached
+  let path = write("limits_far_off", seven(), "max_secs = 1e15");
+  let output = run_graph_output(&path, &[]);
+  assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn unusable_time_limits_are_refused()
+{
+  for (limit, message) in [
+    ("max_secs = -1", "max_secs of -1: "),
+    ("max_secs = 0", "max_secs must be more than 0, got 0"),
+    ("max_secs = nan", "max_secs of NaN: "),
+    ("max_secs = 1e20", "max_secs of 100000000000000000000: "),
+  ]
+  {
+    let path = write("limits_unusable", seven(), limit);
+    let output = run_graph_output(&path, &[]);
+    assert_eq!(output.status.code(), Some(2), "{limit}");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+      stderr.contains(&format!("[run_limits] {message}")),
+      "{stderr}"
+    );
+  }
+}
@@ -0,0 +1,33 @@
+mod common;
+
+use common::{graph, run_graph};
+
+#[test]
+fn runtime_info()
+{
+  let out = run_graph(
+    &graph("runtime_info.json"),
+    &["--param", "env=test", "--param", "query=a=b"],
+  );
+  let fields: Vec<&str> = out
+    .trim_end()
+    .strip_prefix(r#"Ok([String(""#)
+    .and_then(|x| x.strip_suffix(r#"")])"#))
+    .unwrap_or_else(|| panic!("{out}"))
+    .split(r#""), String(""#)
+    .collect();
+  let [run_id, path, env, query, sub_run_id, sub_path] = fields[..]
+  else
+  {
+    panic!("{out}")
+  };
+  assert_eq!(run_id.len(), 36, "{out}");
+  // a Complex node reports the run it belongs to but its own graph file
+  assert_eq!(sub_run_id, run_id, "{out}");
+  assert!(path.ends_with("tests/graphs/runtime_info.json"), "{out}");
+  assert!(
+    sub_path.ends_with("tests/graphs/subgraphs/runtime_info.json"),
+    "{out}"
+  );
+  assert_eq!((env, query), ("test", "a=b"), "{out}");
+}
@@ -0,0 +1,242 @@
+mod common;
+
+use agent_nodes_core::{
+  ai::AgentType,
+  config::SandboxPolicy,
+  http::send_request,
+  language::{
+    fs::FsOp,
+    nodes::{AtomicType, ControlFlow},
+  },
+  DataType, GraphBuilder,
+};
+use common::{atomic, example, run_graph, run_graph_output};
+use std::{collections::HashMap, error::Error, fs, path::Path};
+use tokio::{
+  io::{AsyncReadExt, AsyncWriteExt},
+  net::TcpListener,
+};
+
+#[test]
+fn paths_outside_the_allowed_directories_are_refused()
+{
+  let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("sandbox_paths");
+  let _ = fs::remove_dir_all(&dir);
+  fs::create_dir_all(dir.join("allowed/sub")).unwrap();
+  let policy = SandboxPolicy {
+    paths: Some(vec![dir.join("allowed")]),
+    ..SandboxPolicy::default()
+  };
+  let path = |x: &str| dir.join(x).to_string_lossy().into_owned();
+  assert!(policy.check_path(&path("allowed/sub/new.txt")).is_ok());
+  assert!(policy
+    .check_path(&path("allowed/sub/../new/x/../y.txt"))
+    .is_ok());
+  assert!(policy.check_path(&path("allowed/../secret.txt")).is_err());
+  assert!(policy
+    .check_path(&path("allowed/missing/../../secret.txt"))
+    .is_err());
+  #[cfg(unix)]
+  {
+    // the link is inside the directory but what it points at is not
+    std::os::unix::fs::symlink(std::env::temp_dir(), dir.join("allowed/out")).unwrap();
+    assert!(policy.check_path(&path("allowed/out/file.txt")).is_err());
+  }
+  assert!(policy.check_path(&path("allowed-not/file.txt")).is_err());
+  assert!(SandboxPolicy::default().check_path("/etc/passwd").is_ok());
+}
+
+#[test]
+fn hosts_match_by_name_port_and_subdomain()
+{
+  let policy = SandboxPolicy {
+    hosts: Some(vec![
+      "localhost".to_string(),
+      "api.openai.com:443".to_string(),
+      "*.Example.com".to_string(),
+      "[::1]:8080".to_string(),
+    ]),
+    ..SandboxPolicy::default()
+  };
+  assert!(policy.check_addr("localhost:9000").is_ok());
+  assert!(policy.check_addr("LOCALHOST:1").is_ok());
+  assert!(policy.check_addr("api.openai.com:443").is_ok());
+  assert!(policy.check_addr("api.openai.com:80").is_err());
+  assert!(policy.check_addr("a.b.example.com:80").is_ok());
+  assert!(policy.check_addr("example.com:80").is_err());
+  assert!(policy.check_addr("notexample.com:80").is_err());
+  assert!(policy.check_addr("[::1]:8080").is_ok());
+  assert!(policy.check_addr("[::1]:8081").is_err());
+
+  assert!(policy.check_url("https://api.openai.com/v1/chat").is_ok());
+  assert!(policy.check_url("http://api.openai.com/v1/chat").is_err());
+  assert!(policy.check_url("ws://user@localhost:3000/socket").is_ok());
+  assert!(policy.check_url("http://evil.com/?to=localhost").is_err());
+  assert!(policy.check_url("http://localhost@evil.com/").is_err());
+  // the client reads the backslash as ending the host, as browsers do
+  assert!(policy.check_url("http://evil.com\\@localhost/").is_err());
+  assert!(policy.check_url("localhost:3000/path").is_ok());
+  assert!(policy.check_url("file:///etc/passwd").is_err());
+}
+
+/// Serves `/to` as a redirect to `location` and anything else as `ok` until the test
+/// ends, returning its port
+async fn redirecting_server(location: impl Fn(u16) -> String + Send + 'static) -> u16
+{
+  let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+  let port = listener.local_addr().unwrap().port();
+  tokio::spawn(async move {
+    while let Ok((mut stream, _)) = listener.accept().await
+    {
+      let mut request = vec![0; 1024];
+      let read = stream.read(&mut request).await.unwrap();
+      let request = String::from_utf8_lossy(&request[..read]);
+      let response = match request.starts_with("GET /to ")
+      {
+        true =>
+        {
+          format!(
+            "HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\n\r\n",
+            location(port)
+          )
+        }
+        false => "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok".to_string(),
+      };
+      let _ = stream.write_all(response.as_bytes()).await;
+    }
+  });
+  port
+}
+
+#[tokio::test]
+async fn redirects_are_followed_only_to_allowed_hosts()
+{
+  let policy = SandboxPolicy {
+    hosts: Some(vec!["127.0.0.1".to_string()]),
+    ..SandboxPolicy::default()
+  };
+  let headers = HashMap::new();
+
+  let port = redirecting_server(|port| format!("http://127.0.0.1:{port}/done")).await;
+  let url = format!("http://127.0.0.1:{port}/to");
+  let response = send_request(&policy, "GET", &url, &headers, None)
+    .await
+    .unwrap();
+  assert_eq!(response.body, "ok");
+
+  let port = redirecting_server(|port| format!("http://localhost:{port}/done")).await;
+  let url = format!("http://127.0.0.1:{port}/to");
+  let error = send_request(&policy, "GET", &url, &headers, None)
+    .await
+    .err()
+    .expect("the redirect leaves the sandbox");
+  let mut messages = vec![];
+  let mut source: Option<&dyn Error> = Some(&error);
+  while let Some(error) = source
+  {
+    messages.push(error.to_string());
+    source = error.source();
+  }
+  assert!(
+    messages
+      .iter()
+      .any(|x| x.starts_with("sandbox does not allow host localhost:")),
+    "{messages:?}"
+  );
+}
+
+#[test]
+fn agent_types_are_limited_to_the_listed_ones()
+{
+  let policy = SandboxPolicy {
+    agents: Some(vec![AgentType::Mock]),
+    ..SandboxPolicy::default()
+  };
+  assert!(policy.check_agent(&AgentType::Mock).is_ok());
+  let error = policy.check_agent(&AgentType::OpenAi).unwrap_err();
+  assert_eq!(error.to_string(), "sandbox does not allow OpenAi agents");
+}
+
+#[test]
+fn untrusted_runs_only_reach_allowed_paths()
+{
+  let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("sandbox_untrusted");
+  let _ = fs::remove_dir_all(&dir);
+  fs::create_dir_all(dir.join("data")).unwrap();
+  fs::write(dir.join("data/in.txt"), "").unwrap();
+
+  let mut graph = GraphBuilder::new(vec![DataType::String; 2], vec![DataType::Any; 2]);
+  let start = graph.add_node(atomic(AtomicType::Control(ControlFlow::Start)));
+  let exists = |graph: &mut GraphBuilder| {
+    graph.add_node(atomic(AtomicType::Control(ControlFlow::Try(Box::new(
+      atomic(AtomicType::Fs(FsOp::Exists)),
+    )))))
+  };
+  let inside = exists(&mut graph);
+  let outside = exists(&mut graph);
+  let end = graph.add_node(atomic(AtomicType::Control(ControlFlow::End)));
+  graph
+    .trigger(start, 0, inside)
+    .trigger(inside, 0, outside)
+    .trigger(outside, 0, end)
+    .connect(start, 0, inside, DataType::String)
+    .connect(start, 1, outside, DataType::String)
+    .connect(inside, 0, end, DataType::Any)
+    .connect(outside, 1, end, DataType::Any)
+    .set_end(end);
+  let path = dir.join("untrusted.json");
+  fs::write(
+    &path,
+    serde_json::to_string(&graph.build().unwrap()).unwrap(),
+  )
+  .unwrap();
+
+  let inside = format!("0={}", dir.join("data/in.txt").display());
+  let outside = format!("1={}", path.display());
+  let allowed = dir.join("data");
+  let args = [
+    "--untrusted",
+    "--allow-path",
+    allowed.to_str().unwrap(),
+    "--input",
+    &inside,
+    "--input",
+    &outside,
+  ];
+  let out = run_graph(&path, &args);
+  assert!(
+    out.contains(r#"Ok([Boolean(true), Object({"kind": String("OutsideSandbox")"#),
+    "{out}"
+  );
+}
+
+#[test]
+fn untrusted_refuses_processes_before_running()
+{
+  let output = run_graph_output(&example("subprocess.json"), &["--untrusted"]);
+  assert_eq!(output.status.code(), Some(2));
+  let stderr = String::from_utf8(output.stderr).unwrap();
+  assert!(stderr.starts_with("--untrusted: node "), "{stderr}");
+  assert!(stderr.ends_with("needs processes\n"), "{stderr}");
+}
+
+#[test]
+fn a_sandbox_set_in_the_config_refuses_processes_before_running()
+{
+  let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("sandbox_config");
+  let _ = fs::remove_dir_all(&dir);
+  fs::create_dir_all(&dir).unwrap();
+  let path = dir.join("subprocess.json");
+  fs::copy(example("subprocess.json"), &path).unwrap();
+  let config = dir.join("agentnodes.toml");
+  fs::write(&config, "[sandbox]\nallow_processes = false\n").unwrap();
+
+  let output = run_graph_output(&path, &[]);
+  assert_eq!(output.status.code(), Some(2));
+  let stderr = String::from_utf8(output.stderr).unwrap();
+  assert!(
+    stderr.starts_with(&format!("{}: node ", config.display())),
+    "{stderr}"
+  );
+  assert!(stderr.ends_with("needs processes\n"), "{stderr}");
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use agent_nodes_core::{
+  language::nodes::{AtomicType, ControlFlow},
+  secrets::{FileStore, SecretStore},
+  DataType, GraphBuilder,
+};
+use common::{atomic, run_graph};
+use std::{fs, path::Path};
+
+#[test]
+fn env_and_secret_nodes_read_what_the_config_points_at()
+{
+  let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("secrets");
+  let _ = fs::remove_dir_all(&dir);
+  fs::create_dir_all(&dir).unwrap();
+  fs::write(
+    dir.join("agentnodes.toml"),
+    "[secrets]\nprovider = \"file\"\nfile = \"secrets.env\"\n",
+  )
+  .unwrap();
+  fs::write(dir.join("secrets.env"), "# keys\nAPI_KEY = \"s3cret\"\n").unwrap();
+
+  let mut graph = GraphBuilder::new(vec![], vec![DataType::Any; 4]);
+  let start = graph.add_node(atomic(AtomicType::Control(ControlFlow::Start)));
+  // cargo sets it for the tests, and the graph inherits it
+  let set = graph.add_node(atomic(AtomicType::Env("CARGO_PKG_NAME".to_string())));
+  let unset = graph.add_node(atomic(AtomicType::Env("AGENTNODES_NOT_SET".to_string())));
+  let secret = graph.add_node(atomic(AtomicType::Secret("API_KEY".to_string())));
+  let missing = graph.add_node(atomic(AtomicType::Control(ControlFlow::Try(Box::new(
+    atomic(AtomicType::Secret("OTHER_KEY".to_string())),
+  )))));
+  let end = graph.add_node(atomic(AtomicType::Control(ControlFlow::End)));
+  graph
+    .trigger(start, 0, set)
+    .trigger(set, 0, unset)
+    .trigger(unset, 0, secret)
+    .trigger(secret, 0, missing)
+    .trigger(missing, 0, end)
+    .connect(set, 0, end, DataType::Any)
+    .connect(unset, 0, end, DataType::Any)
+    .connect(secret, 0, end, DataType::Any)
+    .connect(missing, 1, end, DataType::Any)
+    .set_end(end);
+  let path = dir.join("secrets.json");
+  fs::write(
+    &path,
+    serde_json::to_string(&graph.build().unwrap()).unwrap(),
+  )
+  .unwrap();
+
+  let out = run_graph(&path, &[]);
+  assert!(
+    out.contains(
+      r#"Ok([String("backend"), None, String("s3cret"), Object({"kind": String("SecretNotFound")"#
+    ),
+    "{out}"
+  );
+}
+
+#[test]
+fn secret_files_skip_comments_and_unquote()
+{
+  let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("store.env");
+  fs::write(
+    &path,
+    "# A=commented\nA='single'\n\nB = plain value \nC=\"x=y\"\n",
+  )
+  .unwrap();
+  let store = FileStore { path };
+  assert_eq!(store.get("A").unwrap().as_deref(), Some("single"));
+  assert_eq!(store.get("B").unwrap().as_deref(), Some("plain value"));
+  assert_eq!(store.get("C").unwrap().as_deref(), Some("x=y"));
+  assert_eq!(store.get("D").unwrap(), None);
+  let gone = FileStore {
+    path: Path::new(env!("CARGO_TARGET_TMPDIR")).join("no_such.env"),
+  };
+  assert!(gone.get("A").is_err());
+}
@@ -0,0 +1,149 @@
+mod common;
+
+use common::{example, graph, interrupt, wait_for_output};
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::{
+  io::{BufRead, BufReader},
+  process::{Child, Command, Stdio},
+};
+use tokio::net::TcpStream;
+use tokio_websockets::{ClientBuilder, MaybeTlsStream, Message, WebSocketStream};
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Starts `serve` on a free port, returning it with the URL to connect to.
+fn spawn_server() -> (Child, String)
+{
+  let mut child = Command::new(env!("CARGO_BIN_EXE_backend"))
+    .args(["serve", "--listen", "127.0.0.1:0"])
+    .current_dir(env!("CARGO_MANIFEST_DIR"))
+    .stdin(Stdio::null())
+    .stdout(Stdio::null())
+    .stderr(Stdio::piped())
+    .spawn()
+    .unwrap();
+  let mut line = String::new();
+  BufReader::new(child.stderr.as_mut().unwrap())
+    .read_line(&mut line)
+    .unwrap();
+  let url = line
+    .split_whitespace()
+    .find(|x| x.starts_with("ws://"))
+    .unwrap_or_else(|| panic!("{line}"))
+    .trim_end_matches(',')
+    .to_string();
+  (child, url)
+}
+
+async fn connect(url: &str) -> Socket
+{
+  let uri = url.try_into().unwrap();
+  ClientBuilder::from_uri(uri).connect().await.unwrap().0
+}
+
+async fn send(socket: &mut Socket, id: u64, method: &str, params: Value)
+{
+  let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+  socket
+    .send(Message::text(request.to_string()))
+    .await
+    .unwrap();
+}
+
+async fn receive(socket: &mut Socket) -> Value
+{
+  let message = socket.next().await.unwrap().unwrap();
+  serde_json::from_str(message.as_text().unwrap()).unwrap()
+}
+
+/// Reads messages until the response to `id`, returning it with the notifications
+/// before it.
+async fn response(socket: &mut Socket, id: u64) -> (Value, Vec<Value>)
+{
+  let mut notifications = vec![];
+  loop
+  {
+    let message = receive(socket).await;
+    if message["id"] == id
+    {
+      return (message, notifications);
+    }
+    notifications.push(message);
+  }
+}
+
+#[tokio::test]
+async fn serve_runs_graphs_streaming_node_events()
+{
+  let (child, url) = spawn_server();
+  let mut socket = connect(&url).await;
+  let double = example("subgraphs/double.json");
+
+  send(&mut socket, 1, "load", json!({ "path": double })).await;
+  let (loaded, _) = response(&mut socket, 1).await;
+  assert_eq!(loaded["result"]["inputs"], json!(["Integer"]), "{loaded}");
+  let graph = loaded["result"]["graph"].clone();
+
+  send(
+    &mut socket,
+    2,
+    "run",
+    json!({ "graph": graph, "inputs": [21] }),
+  )
+  .await;
+  let (started, _) = response(&mut socket, 2).await;
+  let run = started["result"]["run"].clone();
+  send(&mut socket, 3, "outputs", json!({ "run": run })).await;
+  let (outputs, notifications) = response(&mut socket, 3).await;
+  assert_eq!(outputs["result"], json!([42]), "{outputs}");
+  // the multiplication reported its value, and the run its end
+  assert!(
+    notifications.iter().any(|x| {
+      x["method"] == "node"
+        && x["params"]["node"] == "00000000-0000-0000-0000-000000000003"
+        && x["params"]["event"] == "value"
+        && x["params"]["value"] == 42
+    }),
+    "{notifications:#?}"
+  );
+  assert!(
+    notifications
+      .iter()
+      .any(|x| x["method"] == "finished" && x["params"]["outputs"] == json!([42])),
+    "{notifications:#?}"
+  );
+
+  send(&mut socket, 4, "explode", json!({})).await;
+  let (unknown, _) = response(&mut socket, 4).await;
+  assert_eq!(unknown["error"]["code"], -32601, "{unknown}");
+
+  interrupt(&child);
+  assert!(wait_for_output(child, &double).status.success());
+}
+
+#[tokio::test]
+async fn serve_cancels_runs()
+{
+  let (child, url) = spawn_server();
+  let mut socket = connect(&url).await;
+  let hangs = graph("subgraphs/hangs.json");
+
+  send(&mut socket, 1, "load", json!({ "path": hangs })).await;
+  let (loaded, _) = response(&mut socket, 1).await;
+  let graph = loaded["result"]["graph"].clone();
+  send(&mut socket, 2, "run", json!({ "graph": graph })).await;
+  let (started, _) = response(&mut socket, 2).await;
+  let run = started["result"]["run"].clone();
+
+  send(&mut socket, 3, "cancel", json!({ "run": run })).await;
+  let (cancelled, _) = response(&mut socket, 3).await;
+  assert_eq!(cancelled["result"], true, "{cancelled}");
+  send(&mut socket, 4, "outputs", json!({ "run": run })).await;
+  let (outputs, _) = response(&mut socket, 4).await;
+  assert_eq!(outputs["error"]["code"], -32001, "{outputs}");
+  assert_eq!(outputs["error"]["data"]["kind"], "Cancelled", "{outputs}");
+
+  interrupt(&child);
+  assert!(wait_for_output(child, &hangs).status.success());
+}
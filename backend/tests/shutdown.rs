@@ -0,0 +1,178 @@
+mod common;
+
+use agent_nodes_core::{eval::TaskStats, DataValue, Evaluator, NodeStateLogger};
+use common::{
+  example, graph, run_graph, run_graph_output, spawn_graph_piped, terminate, wait_for_output,
+};
+use std::{
+  net::{TcpListener, TcpStream},
+  path::Path,
+  time::{Duration, Instant},
+};
+use uuid::Uuid;
+
+const NO_TASKS: &str =
+  "TaskStats { node_tasks: 0, complex_runners: 0, io_operations: 0, http_servers: 0, agent_requests_queued: 0, agent_requests_in_flight: 0 }";
+
+#[test]
+fn no_tasks_outlive_instance()
+{
+  let out = run_graph(&example("retry_pipeline.json"), &["--task-stats"]);
+  assert!(out.contains(NO_TASKS), "{out}");
+}
+
+#[tokio::test]
+async fn complex_runners_shut_down_with_parent()
+{
+  let eval = Evaluator::<NodeStateLogger, NodeStateLogger>::new(
+    example("subgraph.json").to_str().unwrap().to_string(),
+    None,
+    None,
+    None,
+  )
+  .unwrap();
+  let instance = eval.instantiate(vec![]).await;
+  instance.get_outputs().await.unwrap();
+  // held on to here, as the parent lets go of them while shutting down
+  let mut runners = vec![];
+  for id in [3, 4]
+  {
+    // runners are kept by the id of the node in the instance, not in the file
+    let id = Uuid::new_v5(&instance.scope_id, Uuid::from_u128(id).as_bytes());
+    let runner = instance.get_complex_runner(&id).await;
+    runners.push(runner.expect("the Complex node started a runner"));
+  }
+  assert!(instance.task_stats().await.complex_runners > 0);
+
+  let report = instance.clone().shutdown().await;
+  assert_eq!(report.runners.len(), 2, "{report:?}");
+  assert!(report.is_clean(), "{report:?}");
+  for runner in runners
+  {
+    let stopped = report.runners.iter().any(|x| x.scope_id == runner.scope_id);
+    assert!(stopped, "{report:?}");
+    assert_eq!(runner.task_stats().await, TaskStats::default());
+  }
+}
+
+#[test]
+fn open_handles_do_not_keep_tasks_alive()
+{
+  let out = run_graph(&example("file_lines.json"), &["--task-stats"]);
+  assert!(out.contains(NO_TASKS), "{out}");
+}
+
+#[test]
+fn sigterm_stops_blocked_nodes_after_grace_period()
+{
+  // the server blocks accepting a connection until it is stopped
+  let port = TcpListener::bind("127.0.0.1:0")
+    .unwrap()
+    .local_addr()
+    .unwrap()
+    .port();
+  let mut graph: serde_json::Value =
+    serde_json::from_str(&std::fs::read_to_string(example("tcp_echo_server.json")).unwrap())
+      .unwrap();
+  graph["instances"]["00000000-0000-0000-0000-000000000003"]["node_type"]["Atomic"]["Value"] =
+    port.into();
+  let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("sigterm");
+  std::fs::create_dir_all(&dir).unwrap();
+  let path = dir.join("tcp_echo_server.json");
+  std::fs::write(&path, graph.to_string()).unwrap();
+  std::fs::write(
+    dir.join("agentnodes.toml"),
+    "[timeouts]\nshutdown_grace_secs = 0.2\n",
+  )
+  .unwrap();
+
+  let child = spawn_graph_piped(&path, &["--task-stats"]);
+  // wait until it is listening before stopping it, which leaves it reading from us
+  let start = Instant::now();
+  while TcpStream::connect(("127.0.0.1", port)).is_err()
+  {
+    assert!(
+      start.elapsed() < Duration::from_secs(5),
+      "server never started"
+    );
+    std::thread::sleep(Duration::from_millis(20));
+  }
+  terminate(&child);
+  let output = wait_for_output(child, &path);
+  let stdout = String::from_utf8(output.stdout).unwrap();
+  let stderr = String::from_utf8(output.stderr).unwrap();
+  assert_eq!(output.status.code(), Some(143), "{stderr}");
+  // on stderr, so it stays out of the outputs
+  assert!(stderr.contains("SIGTERM, shutting down\n"), "{stderr}");
+  assert!(!stdout.contains("shutting down"), "{stdout}");
+  assert!(stdout.contains(NO_TASKS), "{stdout}");
+  assert!(
+    stderr.contains("Grace period of 200ms over, stopped "),
+    "{stderr}"
+  );
+}
+
+#[test]
+fn cancelled_nodes_stop_quietly()
+{
+  // the listener is blocked accepting when the deadline shuts the run down
+  let output = run_graph_output(
+    &graph("subgraphs/hangs.json"),
+    &["--deadline", "0.2", "--task-stats", "--log-level", "debug"],
+  );
+  let stdout = String::from_utf8(output.stdout).unwrap();
+  let stderr = String::from_utf8(output.stderr).unwrap();
+  assert!(stdout.contains(NO_TASKS), "{stdout}");
+  assert!(!stderr.contains("node finished"), "{stderr}");
+  assert!(!stderr.contains("node failed"), "{stderr}");
+}
+
+#[test]
+fn verbose_run_prints_shutdown_report()
+{
+  let out = run_graph(&example("subgraph.json"), &["-v"]);
+  assert!(out.contains("examples/subgraph.json completed\n"), "{out}");
+  // both complex runners are reported under it
+  assert_eq!(out.matches("  Instance ").count(), 2, "{out}");
+
+  let out = run_graph(&example("file_lines.json"), &["-v"]);
+  assert!(out.contains("closed 1 handle(s)"), "{out}");
+
+  let stdout = |args| String::from_utf8(run_graph_output(&graph("abort.json"), args).stdout);
+  let out = stdout(&["-v"]).unwrap();
+  assert!(out.contains("aborted with code 3: guard failed\n"), "{out}");
+  // the Abort node never got to finish
+  assert!(out.contains("Atomic(Abort): Processing\n"), "{out}");
+  assert!(!out.contains("Atomic(Print)"), "{out}");
+  let out = stdout(&["-vv"]).unwrap();
+  assert!(out.contains("Atomic(Print): Waiting\n"), "{out}");
+}
+
+#[tokio::test]
+async fn runners_added_during_shutdown_are_shut_down()
+{
+  let eval = Evaluator::<NodeStateLogger, NodeStateLogger>::new(
+    example("subgraph.json").to_str().unwrap().to_string(),
+    None,
+    None,
+    None,
+  )
+  .unwrap();
+  let instance = eval.instantiate(vec![]).await;
+  instance.get_outputs().await.unwrap();
+  instance.clone().shutdown().await;
+
+  // as a Complex node part way through starting its runner would
+  let runner = instance
+    .clone()
+    .load_complex(&instance.resolve_complex("subgraphs/double.json").await)
+    .await
+    .unwrap()
+    .instantiate(vec![DataValue::Integer(1)])
+    .await;
+  instance
+    .add_complex_runner(runner.clone(), &Uuid::new_v4())
+    .await;
+  assert_eq!(instance.task_stats().await, TaskStats::default());
+  assert_eq!(runner.task_stats().await, TaskStats::default());
+}
@@ -0,0 +1,26 @@
+mod common;
+
+use common::{graph, run_graph};
+
+#[test]
+fn counter_and_stopwatch()
+{
+  let out = run_graph(&graph("counter_stopwatch.json"), &[]);
+  let lines: Vec<&str> = out.lines().collect();
+  // each of the three iterations prints the resetting counter then the stopwatch
+  assert_eq!(lines.len(), 7, "{out}");
+  let counts: Vec<&str> = lines[..6].iter().step_by(2).copied().collect();
+  assert_eq!(counts, ["1", "1", "2"], "{out}");
+  let elapsed: Vec<i64> = lines[..6]
+    .iter()
+    .skip(1)
+    .step_by(2)
+    .map(|x| x.parse().unwrap())
+    .collect();
+  assert_eq!(elapsed[0], 0, "{out}");
+  assert!(elapsed.windows(2).all(|x| x[0] <= x[1]), "{out}");
+  assert!(
+    lines[6].starts_with("Ok([Integer(3), Integer(2), Integer("),
+    "{out}"
+  );
+}
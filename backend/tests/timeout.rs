@@ -0,0 +1,30 @@
+mod common;
+
+use common::{graph, run_graph, run_graph_output};
+
+#[test]
+fn timeout_gives_up_on_hung_subgraph()
+{
+  let out = run_graph(&graph("timeout.json"), &["--task-stats"]);
+  assert!(
+    out.contains(concat!(
+      r#"Ok([None, String("TimedOut"), "#,
+      r#"String("6ba7b810-9dad-11d1-80b4-00c04fd430c8"), None])"#
+    )),
+    "{out}"
+  );
+  // the abandoned subgraph was shut down rather than left accepting
+  assert!(
+    out.contains("complex_runners: 0, io_operations: 0"),
+    "{out}"
+  );
+}
+
+#[test]
+fn deadline_stops_the_run()
+{
+  let output = run_graph_output(&graph("subgraphs/hangs.json"), &["--deadline", "0.2"]);
+  assert_eq!(output.status.code(), Some(124));
+  let stderr = String::from_utf8(output.stderr).unwrap();
+  assert!(stderr.contains("Deadline reached, stopping"), "{stderr}");
+}
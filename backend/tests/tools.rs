@@ -0,0 +1,28 @@
+mod common;
+
+use common::{graph, run_graph, run_graph_output};
+
+#[test]
+fn send_runs_the_tools_an_agent_calls()
+{
+  let out = run_graph(&graph("tools.json"), &[]);
+  assert!(
+    out.contains(concat!(
+      r#"Ok([String("mock: first line\nsecond line\nthird line\n"), "#,
+      r#"String("mock: [42]")])"#
+    )),
+    "{out}"
+  );
+}
+
+#[test]
+fn network_tools_are_refused_offline()
+{
+  let output = run_graph_output(&graph("tools.json"), &["--offline"]);
+  assert_eq!(output.status.code(), Some(2));
+  let stderr = String::from_utf8(output.stderr).unwrap();
+  assert!(
+    stderr.starts_with("--offline: node 00000000-0000-0000-0000-000000000004 in "),
+    "{stderr}"
+  );
+}
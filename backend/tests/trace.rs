@@ -0,0 +1,72 @@
+mod common;
+
+use common::{example, graph, run_graph};
+use serde_json::Value;
+use std::{fs, path::Path};
+
+/// Runs a graph with `--trace` and any extra `args`, returning the trace written
+fn trace(graph: &Path, name: &str, args: &[&str]) -> Value
+{
+  let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("trace");
+  fs::create_dir_all(&dir).unwrap();
+  let path = dir.join(name);
+  let _ = fs::remove_file(&path);
+  run_graph(
+    graph,
+    &[&["--trace", path.to_str().unwrap()], args].concat(),
+  );
+  serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap()
+}
+
+#[test]
+fn trace_counts_iterations_of_looping_nodes()
+{
+  let trace = trace(&graph("counter_stopwatch.json"), "loop.json", &[]);
+  let entries = trace.as_array().unwrap();
+  // the counter counting loop iterations
+  let counter: Vec<(u64, Value)> = entries
+    .iter()
+    .filter(|x| x["node"] == "00000000-0000-0000-0000-000000000003")
+    .map(|x| (x["iteration"].as_u64().unwrap(), x["outputs"].clone()))
+    .collect();
+  assert_eq!(
+    counter,
+    [
+      (1, serde_json::json!([1])),
+      (2, serde_json::json!([2])),
+      (3, serde_json::json!([3]))
+    ],
+    "{trace:#}"
+  );
+  let compare = entries
+    .iter()
+    .find(|x| x["node_type"] == "LogicalOp")
+    .unwrap();
+  assert_eq!(compare["inputs"], serde_json::json!([1, 2]), "{trace:#}");
+  assert_eq!(compare["finished"], true);
+  assert!(compare["error"].is_null());
+  assert!(compare["duration_us"].is_u64());
+}
+
+#[test]
+fn chrome_trace_has_a_row_per_instance()
+{
+  let trace = trace(
+    &example("subgraph.json"),
+    "chrome.json",
+    &["--trace-format", "chrome"],
+  );
+  let events = trace["traceEvents"].as_array().unwrap();
+  let threads = events.iter().filter(|x| x["ph"] == "M").count();
+  // the graph and the Complex node's subgraph
+  assert_eq!(threads, 2, "{trace:#}");
+  // the subgraph doubles twice, the second time giving 84
+  let mul = events
+    .iter()
+    .find(|x| x["name"] == "BinOp" && x["args"]["outputs"] == serde_json::json!([84]))
+    .unwrap_or_else(|| panic!("{trace:#}"));
+  assert_eq!(mul["ph"], "X");
+  assert_eq!(mul["args"]["inputs"], serde_json::json!([42, 2]));
+  assert_eq!(mul["args"]["iteration"], 2);
+  assert!(mul["ts"].is_u64() && mul["dur"].is_u64(), "{mul}");
+}
@@ -0,0 +1,33 @@
+mod common;
+
+use common::{graph, run_graph};
+use std::path::Path;
+
+#[test]
+fn transcripts_render_and_write()
+{
+  let written = Path::new(env!("CARGO_TARGET_TMPDIR")).join("transcript_out.json");
+  let mut graph: serde_json::Value =
+    serde_json::from_str(&std::fs::read_to_string(graph("transcript.json")).unwrap()).unwrap();
+  graph["instances"]["00000000-0000-0000-0000-000000000007"]["node_type"]["Atomic"]["Value"] =
+    written.to_str().unwrap().into();
+  let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("transcript.json");
+  std::fs::write(&path, graph.to_string()).unwrap();
+
+  let out = run_graph(&path, &[]);
+  assert!(
+    out.contains(r##"Ok([String("# Transcript\n\n2 messages, ~8 tokens\n\n## user at "##),
+    "{out}"
+  );
+  assert!(
+    out.contains(r#" (~5 tokens)\n\nmock: hello there\n")])"#),
+    "{out}"
+  );
+
+  let json: serde_json::Value =
+    serde_json::from_str(&std::fs::read_to_string(written).unwrap()).unwrap();
+  assert_eq!(json["total_tokens"], 8);
+  assert_eq!(json["messages"][1]["role"], "assistant");
+  assert_eq!(json["messages"][1]["content"], "mock: hello there");
+  assert!(json["messages"][0]["at_ms"].is_i64());
+}
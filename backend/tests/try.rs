@@ -0,0 +1,18 @@
+mod common;
+
+use common::{graph, run_graph};
+
+#[test]
+fn try_catches_failures()
+{
+  let out = run_graph(&graph("try.json"), &[]);
+  assert!(
+    out.contains(concat!(
+      // a failing atomic node, then a succeeding one, then a failing subgraph
+      r#"Ok([None, String("InvalidUuid"), "#,
+      r#"String("6ba7b810-9dad-11d1-80b4-00c04fd430c8"), None, "#,
+      r#"None, String("SubgraphFailed")])"#
+    )),
+    "{out}"
+  );
+}
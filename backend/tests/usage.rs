@@ -0,0 +1,59 @@
+mod common;
+
+use common::{graph, run_graph_output};
+use std::{fs, path::Path};
+
+/// Copies the transcript graph, one mock agent sent one message, to a directory of its
+/// own with an `agentnodes.toml` holding `config`, returning the graph's path
+fn write(name: &str, config: &str) -> std::path::PathBuf
+{
+  let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+  let _ = fs::remove_dir_all(&dir);
+  fs::create_dir_all(&dir).unwrap();
+  fs::write(dir.join("agentnodes.toml"), config).unwrap();
+  let mut graph: serde_json::Value =
+    serde_json::from_str(&fs::read_to_string(graph("transcript.json")).unwrap()).unwrap();
+  graph["instances"]["00000000-0000-0000-0000-000000000007"]["node_type"]["Atomic"]["Value"] =
+    dir.join("transcript.json").to_str().unwrap().into();
+  let path = dir.join("graph.json");
+  fs::write(&path, graph.to_string()).unwrap();
+  path
+}
+
+#[test]
+fn usage_file_counts_and_prices_agent_calls()
+{
+  let config = "[pricing.mock]\nprompt_per_million = 1000000\ncompletion_per_million = 2000000\n";
+  let path = write("usage_priced", config);
+  let report = path.with_file_name("usage.json");
+  let output = run_graph_output(&path, &["--usage-file", report.to_str().unwrap()]);
+  assert_eq!(output.status.code(), Some(0));
+
+  let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(report).unwrap()).unwrap();
+  let agent = &json["agents"][0];
+  assert_eq!(agent["node"], "00000000-0000-0000-0000-000000000003");
+  assert_eq!(agent["agent_type"], "Mock");
+  assert_eq!(agent["model"], "mock");
+  assert_eq!(agent["calls"], 1);
+  // "hello there" and "mock: hello there", at one token per four characters
+  assert_eq!(agent["prompt_tokens"], 3);
+  assert_eq!(agent["completion_tokens"], 5);
+  assert_eq!(agent["cost"], 13.0);
+  assert_eq!(json["calls"], 1);
+  assert_eq!(json["cost"], 13.0);
+}
+
+#[test]
+fn usage_table_leaves_unpriced_models_without_a_cost()
+{
+  let path = write("usage_unpriced", "");
+  let output = run_graph_output(&path, &["--usage"]);
+  assert_eq!(output.status.code(), Some(0));
+  let stdout = String::from_utf8(output.stdout).unwrap();
+  let total = stdout
+    .lines()
+    .find(|x| x.starts_with("total"))
+    .unwrap_or_else(|| panic!("{stdout}"));
+  let columns: Vec<&str> = total.split_whitespace().collect();
+  assert_eq!(columns, ["total", "1", "3", "5", "-"], "{stdout}");
+}
@@ -0,0 +1,45 @@
+mod common;
+
+use common::{example, interrupt, wait_for_exit};
+use std::{
+  fs,
+  io::{BufRead, BufReader},
+  path::Path,
+  process::{Command, Stdio},
+};
+
+#[test]
+fn watch_reruns_graphs_when_a_complex_graph_changes()
+{
+  let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("watch");
+  fs::create_dir_all(dir.join("subgraphs")).unwrap();
+  let path = dir.join("subgraph.json");
+  fs::copy(example("subgraph.json"), &path).unwrap();
+  let double = dir.join("subgraphs/double.json");
+  fs::copy(example("subgraphs/double.json"), &double).unwrap();
+
+  let mut child = Command::new(env!("CARGO_BIN_EXE_backend"))
+    .args(["watch", path.to_str().unwrap(), "--interval", "0.05"])
+    .current_dir(&dir)
+    .stdin(Stdio::null())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::null())
+    .spawn()
+    .unwrap();
+  // the graph prints its result too, only the outputs of each run matter here
+  let mut outputs = BufReader::new(child.stdout.take().unwrap())
+    .lines()
+    .map(|x| x.unwrap())
+    .filter(|x| x.starts_with("Ok("));
+  assert_eq!(outputs.next().unwrap(), "Ok([Integer(84)])");
+
+  // the doubling graph now triples, twice over
+  let text = fs::read_to_string(&double)
+    .unwrap()
+    .replace("\"Value\": 2", "\"Value\": 3");
+  fs::write(&double, text).unwrap();
+  assert_eq!(outputs.next().unwrap(), "Ok([Integer(189)])");
+
+  interrupt(&child);
+  wait_for_exit(child, &path);
+}
@@ -0,0 +1,36 @@
+mod common;
+
+use common::{graph, run_graph};
+use std::{fs, path::Path};
+
+#[test]
+fn crlf_lines_and_backslash_paths()
+{
+  let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("windows");
+  fs::create_dir_all(dir.join("subgraphs")).unwrap();
+  fs::write(dir.join("crlf.txt"), "crlf line\r\nlf line\nlast").unwrap();
+  fs::copy(
+    graph("subgraphs/runtime_info.json"),
+    dir.join("subgraphs/runtime_info.json"),
+  )
+  .unwrap();
+  let text = fs::read_to_string(graph("windows.json"))
+    .unwrap()
+    .replace("BASE", dir.to_str().unwrap());
+  fs::write(dir.join("windows.json"), text).unwrap();
+
+  // run it through a relative path, the binary starts in the manifest directory
+  let relative = dir
+    .strip_prefix(env!("CARGO_MANIFEST_DIR"))
+    .unwrap()
+    .join("windows.json");
+  let out = run_graph(&relative, &[]);
+  let subgraph = relative
+    .with_file_name("subgraphs")
+    .join("runtime_info.json");
+  let expected = format!(
+    r#"Ok([String("crlf line\n"), String("lf line\n"), String("last"), String({:?})])"#,
+    subgraph.to_str().unwrap()
+  );
+  assert!(out.contains(&expected), "{out}");
+}
@@ -0,0 +1,34 @@
+mod common;
+
+use common::{graph, run_graph};
+use std::path::PathBuf;
+
+/// The workspace path and exit code of the subprocess from `workspace.json`'s output.
+fn run_workspace(args: &[&str]) -> (PathBuf, String)
+{
+  let out = run_graph(&graph("workspace.json"), args);
+  let path = out
+    .strip_prefix(r#"Ok([String(""#)
+    .and_then(|x| x.split_once(r#""), "#))
+    .unwrap_or_else(|| panic!("{out}"));
+  (PathBuf::from(path.0), path.1.trim_end().to_string())
+}
+
+#[test]
+fn workspace_is_removed_after_the_run()
+{
+  let (path, rest) = run_workspace(&[]);
+  assert_eq!(rest, "Integer(0)])");
+  assert!(path.starts_with(std::env::temp_dir()), "{}", path.display());
+  assert!(!path.exists(), "{} was left behind", path.display());
+}
+
+#[test]
+fn keep_workspace_leaves_it_in_place()
+{
+  let (path, _) = run_workspace(&["--keep-workspace"]);
+  // the process was told where the workspace is through the environment
+  let written = std::fs::read_to_string(path.join("out.txt"));
+  std::fs::remove_dir_all(&path).unwrap();
+  assert_eq!(written.unwrap(), "from a process\n");
+}
@@ -0,0 +1,181 @@
+mod common;
+
+use agent_nodes_core::{
+  language::{
+    nodes::{AtomicType, ControlFlow},
+    wrapped::WrapOp,
+  },
+  DataType, DataValue, EvalError, Evaluator, GraphBuilder, NodeStateLogger,
+};
+use common::atomic;
+
+fn wrap(op: WrapOp) -> AtomicType
+{
+  AtomicType::Wrap(op)
+}
+
+/// The node wrapped in a `Try`, so its failure is given as an output
+fn caught(op: WrapOp) -> AtomicType
+{
+  AtomicType::Control(ControlFlow::Try(Box::new(atomic(wrap(op)))))
+}
+
+fn kind(error: &DataValue) -> Option<&DataValue>
+{
+  error.get_path("kind")
+}
+
+/// Runs `values` through the nodes `ops` in turn, the first node reading every
+/// value and each after it the one before, with the graph giving the last node's
+/// `outputs` outputs
+async fn run(
+  values: Vec<DataValue>,
+  ops: &[AtomicType],
+  outputs: usize,
+) -> Result<Vec<DataValue>, EvalError>
+{
+  let mut graph = GraphBuilder::new(vec![], vec![DataType::Any; outputs]);
+  let start = graph.add_node(atomic(AtomicType::Control(ControlFlow::Start)));
+  let mut last = start;
+  let mut sources = vec![];
+  for value in values
+  {
+    let node = graph.add_node(atomic(AtomicType::Value(value)));
+    graph.trigger(last, 0, node);
+    sources.push((node, 0));
+    last = node;
+  }
+  for op in ops
+  {
+    let node = graph.add_node(atomic(op.clone()));
+    graph.trigger(last, 0, node);
+    for (source, port) in sources.drain(..)
+    {
+      graph.connect(source, port, node, DataType::Any);
+    }
+    sources.push((node, 0));
+    last = node;
+  }
+  let end = graph.add_node(atomic(AtomicType::Control(ControlFlow::End)));
+  graph.trigger(last, 0, end);
+  for port in 0..outputs
+  {
+    graph.connect(last, port, end, DataType::Any);
+  }
+  graph.set_end(end);
+  let eval = Evaluator::<NodeStateLogger, NodeStateLogger>::from_complex(
+    graph.build().unwrap(),
+    "wrapped.json".to_string(),
+    None,
+    None,
+    None,
+  )
+  .unwrap();
+  let instance = eval.instantiate(vec![]).await;
+  let outputs = instance.get_outputs().await;
+  instance.clone().shutdown().await;
+  outputs
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn values_wrap_and_unwrap()
+{
+  let five = DataValue::Integer(5);
+  let some = DataValue::Optional(Some(Box::new(five.clone())));
+  assert_eq!(
+    run(vec![five.clone()], &[wrap(WrapOp::Some)], 1)
+      .await
+      .unwrap(),
+    vec![some]
+  );
+  assert_eq!(
+    run(
+      vec![five.clone()],
+      &[wrap(WrapOp::Ok), wrap(WrapOp::Unwrap)],
+      1
+    )
+    .await
+    .unwrap(),
+    vec![five.clone()]
+  );
+  assert_eq!(
+    run(
+      vec![five.clone()],
+      &[wrap(WrapOp::Err), wrap(WrapOp::Match)],
+      3
+    )
+    .await
+    .unwrap(),
+    vec![DataValue::Boolean(false), DataValue::None, five.clone()]
+  );
+  assert_eq!(
+    run(
+      vec![five.clone()],
+      &[wrap(WrapOp::Some), wrap(WrapOp::Match)],
+      3
+    )
+    .await
+    .unwrap(),
+    vec![DataValue::Boolean(true), five, DataValue::None]
+  );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unwrapping_nothing_fails_or_falls_back()
+{
+  let nothing = DataValue::Optional(None);
+  let err = DataValue::Result(Err(Box::new(DataValue::String("late".to_string()))));
+  let failed = DataValue::String("UnwrapFailed".to_string());
+  let outputs = run(vec![nothing], &[caught(WrapOp::Unwrap)], 2)
+    .await
+    .unwrap();
+  assert_eq!(kind(&outputs[1]), Some(&failed), "{outputs:?}");
+  let outputs = run(vec![err.clone()], &[caught(WrapOp::Unwrap)], 2)
+    .await
+    .unwrap();
+  assert_eq!(kind(&outputs[1]), Some(&failed), "{outputs:?}");
+  assert_eq!(
+    run(
+      vec![err, DataValue::Integer(1)],
+      &[wrap(WrapOp::UnwrapOr)],
+      1
+    )
+    .await
+    .unwrap(),
+    vec![DataValue::Integer(1)]
+  );
+  // None is a value like any other inside an Optional
+  let some_none = DataValue::Optional(Some(Box::new(DataValue::None)));
+  assert_eq!(
+    run(
+      vec![some_none, DataValue::Integer(1)],
+      &[wrap(WrapOp::UnwrapOr)],
+      1
+    )
+    .await
+    .unwrap(),
+    vec![DataValue::None]
+  );
+}
+
+#[test]
+fn wrapped_values_cast()
+{
+  let optional = |x: DataType| DataType::Optional(Box::new(x));
+  assert_eq!(
+    DataValue::Integer(2).try_cast(optional(DataType::String)),
+    Ok(DataValue::Optional(Some(Box::new(DataValue::String(
+      "2".to_string()
+    )))))
+  );
+  assert_eq!(
+    DataValue::None.try_cast(optional(DataType::Integer)),
+    Ok(DataValue::Optional(None))
+  );
+  assert!(!optional(DataType::Integer).casts_to(&DataType::Integer));
+  assert!(
+    DataType::Result(Box::new(DataType::Integer), Box::new(DataType::String)).casts_to(
+      &DataType::Result(Box::new(DataType::Float), Box::new(DataType::String))
+    )
+  );
+}